@@ -35,12 +35,15 @@ use quickwit_proto::search::{CountHits, SortField, SortOrder};
 use quickwit_proto::types::IndexId;
 use quickwit_rest_client::models::{IngestSource, SearchResponseRestClient};
 use quickwit_rest_client::rest_client::{CommitType, IngestEvent};
-use quickwit_serve::{ListSplitsQueryParams, SearchRequestQueryString, SortBy};
+use quickwit_serve::{
+    ExportFormat, ExportRequestQueryString, ListSplitsQueryParams, SearchRequestQueryString, SortBy,
+};
 use quickwit_storage::{StorageResolver, load_file};
 use tabled::settings::object::{FirstRow, Rows, Segment};
 use tabled::settings::panel::Footer;
 use tabled::settings::{Alignment, Format, Modify, Panel, Remove, Rotate, Style};
 use tabled::{Table, Tabled};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
 use tracing::{Level, debug};
 
 use crate::checklist::{GREEN_COLOR, RED_COLOR};
@@ -192,6 +195,37 @@ pub fn build_index_command() -> Command {
                         .required(false),
                 ])
             )
+        .subcommand(
+            Command::new("export")
+                .display_order(9)
+                .about("Exports all the documents of an index matching a query as NDJSON or CSV.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1)
+                        .required(true),
+                    arg!(--query <QUERY> "Query expressed in natural query language ((barack AND obama) OR \"president of united states\"). Learn more on https://quickwit.io/docs/reference/search-language.")
+                        .display_order(2)
+                        .required(true),
+                    arg!(--format <FORMAT> "Output format: `ndjson` or `csv`.")
+                        .value_parser(["ndjson", "csv"])
+                        .default_value("ndjson")
+                        .required(false),
+                    arg!(--fields <FIELD_NAME> "List of fields to export. Required by the CSV format. Space-separated list, e.g. \"field1 field2\". ")
+                        .num_args(1..)
+                        .required(false),
+                    arg!(--"search-fields" <FIELD_NAME> "List of fields that Quickwit will search into if the user query does not explicitly target a field in the query. It overrides the default search fields defined in the index config. Space-separated list, e.g. \"field1 field2\". ")
+                        .num_args(1..)
+                        .required(false),
+                    arg!(--"sort-by" <SORT_BY> "Comma-separated list of fields to sort documents on, e.g. \"-timestamp\". Fields are sorted in descending order unless prefixed with `-`. Unsorted exports are faster.")
+                        .required(false),
+                    arg!(--"start-timestamp" <TIMESTAMP> "Filters out documents before that timestamp (time-series indexes only).")
+                        .required(false),
+                    arg!(--"end-timestamp" <TIMESTAMP> "Filters out documents after that timestamp (time-series indexes only).")
+                        .required(false),
+                    arg!(--"output-path" <OUTPUT_PATH> "Location of the output file. Documents are written to stdout by default.")
+                        .required(false),
+                ])
+            )
         .arg_required_else_help(true)
 }
 
@@ -250,6 +284,20 @@ pub struct SearchIndexArgs {
     pub sort_by_score: bool,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct ExportIndexArgs {
+    pub client_args: ClientArgs,
+    pub index_id: IndexId,
+    pub query: String,
+    pub format: ExportFormat,
+    pub fields: Option<Vec<String>>,
+    pub search_fields: Option<Vec<String>>,
+    pub sort_by: Option<String>,
+    pub start_timestamp: Option<i64>,
+    pub end_timestamp: Option<i64>,
+    pub output_path_opt: Option<PathBuf>,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct DeleteIndexArgs {
     pub client_args: ClientArgs,
@@ -270,6 +318,7 @@ pub enum IndexCliCommand {
     Update(UpdateIndexArgs),
     Delete(DeleteIndexArgs),
     Describe(DescribeIndexArgs),
+    Export(ExportIndexArgs),
     Ingest(IngestDocsArgs),
     List(ListIndexesArgs),
     Search(SearchIndexArgs),
@@ -278,7 +327,7 @@ pub enum IndexCliCommand {
 impl IndexCliCommand {
     pub fn default_log_level(&self) -> Level {
         match self {
-            Self::Search(_) => Level::ERROR,
+            Self::Search(_) | Self::Export(_) => Level::ERROR,
             _ => Level::INFO,
         }
     }
@@ -292,6 +341,7 @@ impl IndexCliCommand {
             "create" => Self::parse_create_args(submatches),
             "delete" => Self::parse_delete_args(submatches),
             "describe" => Self::parse_describe_args(submatches),
+            "export" => Self::parse_export_args(submatches),
             "ingest" => Self::parse_ingest_args(submatches),
             "list" => Self::parse_list_args(submatches),
             "search" => Self::parse_search_args(submatches),
@@ -455,6 +505,57 @@ impl IndexCliCommand {
         }))
     }
 
+    fn parse_export_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
+        let index_id = matches
+            .remove_one::<String>("index")
+            .expect("`index` should be a required arg");
+        let query = matches
+            .remove_one::<String>("query")
+            .context("`query` should be a required arg")?;
+        let format = match matches
+            .remove_one::<String>("format")
+            .expect("`format` should have a default value.")
+            .as_str()
+        {
+            "csv" => ExportFormat::Csv,
+            _ => ExportFormat::Ndjson,
+        };
+        let fields = matches
+            .remove_many::<String>("fields")
+            .map(|values| values.collect());
+        if format == ExportFormat::Csv && fields.is_none() {
+            bail!("`--fields` is required by the CSV format");
+        }
+        let search_fields = matches
+            .remove_many::<String>("search-fields")
+            .map(|values| values.collect());
+        let sort_by = matches.remove_one::<String>("sort-by");
+        let start_timestamp = matches
+            .remove_one::<String>("start-timestamp")
+            .map(|ts| ts.parse())
+            .transpose()?;
+        let end_timestamp = matches
+            .remove_one::<String>("end-timestamp")
+            .map(|ts| ts.parse())
+            .transpose()?;
+        let output_path_opt = matches
+            .remove_one::<String>("output-path")
+            .map(PathBuf::from);
+        let client_args = ClientArgs::parse(&mut matches)?;
+        Ok(Self::Export(ExportIndexArgs {
+            client_args,
+            index_id,
+            query,
+            format,
+            fields,
+            search_fields,
+            sort_by,
+            start_timestamp,
+            end_timestamp,
+            output_path_opt,
+        }))
+    }
+
     fn parse_delete_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
         let client_args = ClientArgs::parse(&mut matches)?;
         let index_id = matches
@@ -476,6 +577,7 @@ impl IndexCliCommand {
             Self::Create(args) => create_index_cli(args).await,
             Self::Delete(args) => delete_index_cli(args).await,
             Self::Describe(args) => describe_index_cli(args).await,
+            Self::Export(args) => export_index_cli(args).await,
             Self::Ingest(args) => ingest_docs_cli(args).await,
             Self::List(args) => list_index_cli(args).await,
             Self::Search(args) => search_index_cli(args).await,
@@ -1137,6 +1239,36 @@ pub async fn search_index_cli(args: SearchIndexArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub async fn export_index_cli(args: ExportIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "export-index");
+    let export_request = ExportRequestQueryString {
+        query: args.query,
+        search_fields: args.search_fields,
+        start_timestamp: args.start_timestamp,
+        end_timestamp: args.end_timestamp,
+        sort_by: args.sort_by.map(SortBy::from).unwrap_or_default(),
+        format: args.format,
+        fields: args.fields,
+    };
+    let qw_client = args.client_args.client();
+    let mut response = qw_client.export(&args.index_id, &export_request).await?;
+
+    let mut output: Box<dyn AsyncWrite + Send + Unpin> =
+        if let Some(output_path) = &args.output_path_opt {
+            let file = tokio::fs::File::create(output_path)
+                .await
+                .with_context(|| format!("failed to create file `{}`", output_path.display()))?;
+            Box::new(BufWriter::new(file))
+        } else {
+            Box::new(BufWriter::new(tokio::io::stdout()))
+        };
+    while let Some(chunk) = response.chunk().await? {
+        output.write_all(&chunk).await?;
+    }
+    output.flush().await?;
+    Ok(())
+}
+
 pub async fn delete_index_cli(args: DeleteIndexArgs) -> anyhow::Result<()> {
     debug!(args=?args, "delete-index");
     if !args.dry_run && !args.assume_yes {
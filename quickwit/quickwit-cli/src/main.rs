@@ -135,6 +135,7 @@ fn about_text() -> String {
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
     use std::str::FromStr;
     use std::time::Duration;
 
@@ -142,8 +143,8 @@ mod tests {
     use quickwit_cli::ClientArgs;
     use quickwit_cli::cli::{CliCommand, build_cli};
     use quickwit_cli::index::{
-        ClearIndexArgs, CreateIndexArgs, DeleteIndexArgs, DescribeIndexArgs, IndexCliCommand,
-        IngestDocsArgs, SearchIndexArgs,
+        ClearIndexArgs, CreateIndexArgs, DeleteIndexArgs, DescribeIndexArgs, ExportIndexArgs,
+        IndexCliCommand, IngestDocsArgs, SearchIndexArgs,
     };
     use quickwit_cli::split::{DescribeSplitArgs, SplitCliCommand};
     use quickwit_cli::tool::{
@@ -154,6 +155,7 @@ mod tests {
     use quickwit_config::SourceInputFormat;
     use quickwit_rest_client::models::Timeout;
     use quickwit_rest_client::rest_client::CommitType;
+    use quickwit_serve::ExportFormat;
     use reqwest::Url;
 
     #[test]
@@ -512,6 +514,72 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_export_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches =
+            app.try_get_matches_from(["index", "export", "--index", "hdfs-logs", "--query", "*"])?;
+        let command = CliCommand::parse_cli_args(matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Export(ExportIndexArgs {
+                index_id,
+                query,
+                format: ExportFormat::Ndjson,
+                fields: None,
+                sort_by: None,
+                output_path_opt: None,
+                ..
+            })) if &index_id == "hdfs-logs" && &query == "*"
+        ));
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "export",
+            "--index",
+            "hdfs-logs",
+            "--query",
+            "severity_text:ERROR",
+            "--format",
+            "csv",
+            "--fields",
+            "timestamp",
+            "body",
+            "--sort-by",
+            "-timestamp",
+            "--output-path",
+            "/tmp/export.csv",
+        ])?;
+        let command = CliCommand::parse_cli_args(matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Export(ExportIndexArgs {
+                format: ExportFormat::Csv,
+                fields: Some(fields),
+                sort_by: Some(sort_by),
+                output_path_opt: Some(output_path),
+                ..
+            })) if fields == vec!["timestamp".to_string(), "body".to_string()]
+                  && sort_by == "-timestamp"
+                  && output_path == Path::new("/tmp/export.csv")
+        ));
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "export",
+            "--index",
+            "hdfs-logs",
+            "--query",
+            "*",
+            "--format",
+            "csv",
+        ])?;
+        CliCommand::parse_cli_args(matches).unwrap_err();
+        Ok(())
+    }
+
     #[test]
     fn test_parse_local_search_args() {
         let app = build_cli().no_binary_name(true);
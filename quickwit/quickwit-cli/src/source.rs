@@ -840,6 +840,10 @@ mod tests {
             source_params: SourceParams::file_from_str("path/to/file").unwrap(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         }];
         let expected_source = vec![SourceRow {
             source_id: "foo-source".to_string(),
@@ -901,6 +905,10 @@ mod tests {
                 source_params: SourceParams::stdin(),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_config: None,
+                schema_config: None,
+                line_format_config: None,
+                index_routing_config: None,
             },
             SourceConfig {
                 source_id: "bar-source".to_string(),
@@ -909,6 +917,10 @@ mod tests {
                 source_params: SourceParams::stdin(),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_config: None,
+                schema_config: None,
+                line_format_config: None,
+                index_routing_config: None,
             },
         ];
         let expected_sources = [
@@ -33,8 +33,8 @@ use quickwit_common::runtimes::RuntimesConfig;
 use quickwit_common::uri::Uri;
 use quickwit_config::service::QuickwitService;
 use quickwit_config::{
-    CLI_SOURCE_ID, IndexerConfig, NodeConfig, SourceConfig, SourceInputFormat, SourceParams,
-    TransformConfig, VecSourceParams,
+    CLI_SOURCE_ID, CsvConfig, IndexerConfig, LineFormatConfig, NodeConfig, SourceConfig,
+    SourceInputFormat, SourceParams, TransformConfig, VecSourceParams,
 };
 use quickwit_index_management::{IndexService, clear_cache_directory};
 use quickwit_indexing::IndexingPipeline;
@@ -77,7 +77,7 @@ pub fn build_tool_command() -> Command {
                         .required(true),
                     arg!(--"input-path" <INPUT_PATH> "Location of the input file.")
                         .required(false),
-                    arg!(--"input-format" <INPUT_FORMAT> "Format of the input data.")
+                    arg!(--"input-format" <INPUT_FORMAT> "Format of the input data: `json`, `plain`, `csv` (with a header row), or `logfmt`.")
                         .default_value("json")
                         .required(false),
                     arg!(--overwrite "Overwrites pre-existing index.")
@@ -413,6 +413,18 @@ pub async fn local_ingest_docs_cli(args: LocalIngestDocsArgs) -> anyhow::Result<
     let transform_config = args
         .vrl_script
         .map(|vrl_script| TransformConfig::new(vrl_script, None));
+    // CSV files ingested through the CLI are expected to start with a header row.
+    let line_format_config = if args.input_format == SourceInputFormat::Csv {
+        let csv_config = CsvConfig {
+            columns: None,
+            has_header: true,
+            delimiter: ',',
+            quote: '"',
+        };
+        Some(LineFormatConfig::Csv(csv_config))
+    } else {
+        None
+    };
     let source_config = SourceConfig {
         source_id: CLI_SOURCE_ID.to_string(),
         num_pipelines: NonZeroUsize::MIN,
@@ -420,6 +432,10 @@ pub async fn local_ingest_docs_cli(args: LocalIngestDocsArgs) -> anyhow::Result<
         source_params,
         transform_config,
         input_format: args.input_format,
+        dead_letter_config: None,
+        schema_config: None,
+        line_format_config,
+        index_routing_config: None,
     };
     run_index_checklist(
         &mut metastore,
@@ -457,6 +473,7 @@ pub async fn local_ingest_docs_cli(args: LocalIngestDocsArgs) -> anyhow::Result<
         None,
         merge_scheduler_service_mailbox,
         IngesterPool::default(),
+        None,
         storage_resolver,
         EventBroker::default(),
     )
@@ -545,6 +562,9 @@ pub async fn local_search_cli(args: LocalSearchArgs) -> anyhow::Result<()> {
         max_hits: args.max_hits as u64,
         search_fields: args.search_fields,
         snippet_fields: args.snippet_fields,
+        runtime_fields: None,
+        docvalue_fields: None,
+        knn: None,
         start_timestamp: args.start_timestamp,
         end_timestamp: args.end_timestamp,
         aggs,
@@ -552,6 +572,7 @@ pub async fn local_search_cli(args: LocalSearchArgs) -> anyhow::Result<()> {
         sort_by,
         count_all: CountHits::CountAll,
         allow_failed_splits: false,
+        profile: false,
     };
     let search_request =
         search_request_from_api_request(vec![args.index_id], search_request_query_string)?;
@@ -594,6 +615,7 @@ pub async fn merge_cli(args: MergeArgs) -> anyhow::Result<()> {
         None,
         merge_scheduler_service,
         IngesterPool::default(),
+        None,
         storage_resolver,
         EventBroker::default(),
     )
@@ -610,6 +632,10 @@ pub async fn merge_cli(args: MergeArgs) -> anyhow::Result<()> {
                 source_params: SourceParams::Vec(VecSourceParams::default()),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_config: None,
+                schema_config: None,
+                line_format_config: None,
+                index_routing_config: None,
             },
             pipeline_uid: PipelineUid::random(),
         })
@@ -0,0 +1,267 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decompression of files and request bodies.
+//!
+//! The codec of a request body is given by its content encoding. The codec of a file is detected
+//! from its extension, or from its magic bytes for files without a known extension. Concatenated
+//! streams, as produced by parallel compressors or by appending to compressed files, are
+//! decompressed entirely.
+
+use std::fmt;
+use std::io::{self, Read};
+
+use async_compression::tokio::bufread::{
+    BzDecoder, GzipDecoder, Lz4Decoder, XzDecoder, ZstdDecoder,
+};
+use tokio::io::{AsyncBufRead, AsyncRead};
+
+/// Number of bytes that [`CompressionCodec::from_magic_bytes`] needs to detect every codec.
+pub const MAGIC_BYTES_LEN: usize = 10;
+
+const GZIP_MAGIC_BYTES: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC_BYTES: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC_BYTES: &[u8] = &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00];
+const LZ4_FRAME_MAGIC_BYTES: &[u8] = &[0x04, 0x22, 0x4d, 0x18];
+
+// A bzip2 stream starts with `BZh` and the block size, followed by the magic bytes of a block
+// (the BCD digits of pi) or of the end of the stream (the BCD digits of the square root of pi).
+// Checking both avoids mistaking text starting with `BZh` for bzip2 data.
+const BZIP2_MAGIC_BYTES: &[u8] = b"BZh";
+const BZIP2_BLOCK_MAGIC_BYTES: &[u8] = &[0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+const BZIP2_END_OF_STREAM_MAGIC_BYTES: &[u8] = &[0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CompressionCodec {
+    Bzip2,
+    Gzip,
+    Lz4,
+    Xz,
+    Zstd,
+}
+
+impl CompressionCodec {
+    /// Returns the codec associated with a file extension.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let codec = match extension {
+            "bz2" => Self::Bzip2,
+            "gz" | "gzip" => Self::Gzip,
+            "lz4" => Self::Lz4,
+            "xz" => Self::Xz,
+            "zst" | "zstd" => Self::Zstd,
+            _ => return None,
+        };
+        Some(codec)
+    }
+
+    /// Returns the codec associated with a value of the `Content-Encoding` HTTP header.
+    pub fn from_content_encoding(content_encoding: &str) -> Option<Self> {
+        let codec = match content_encoding {
+            "bzip2" | "x-bzip2" => Self::Bzip2,
+            "gzip" | "x-gzip" => Self::Gzip,
+            "lz4" => Self::Lz4,
+            "xz" | "x-xz" => Self::Xz,
+            "zstd" => Self::Zstd,
+            _ => return None,
+        };
+        Some(codec)
+    }
+
+    /// Detects the codec of compressed data from its first bytes. Returns `None` if the data
+    /// does not look compressed. At least [`MAGIC_BYTES_LEN`] bytes should be provided when
+    /// available.
+    pub fn from_magic_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(GZIP_MAGIC_BYTES) {
+            return Some(Self::Gzip);
+        }
+        if bytes.starts_with(ZSTD_MAGIC_BYTES) {
+            return Some(Self::Zstd);
+        }
+        if bytes.starts_with(XZ_MAGIC_BYTES) {
+            return Some(Self::Xz);
+        }
+        if bytes.starts_with(LZ4_FRAME_MAGIC_BYTES) {
+            return Some(Self::Lz4);
+        }
+        if bytes.len() >= MAGIC_BYTES_LEN
+            && bytes.starts_with(BZIP2_MAGIC_BYTES)
+            && (b'1'..=b'9').contains(&bytes[3])
+            && (bytes[4..].starts_with(BZIP2_BLOCK_MAGIC_BYTES)
+                || bytes[4..].starts_with(BZIP2_END_OF_STREAM_MAGIC_BYTES))
+        {
+            return Some(Self::Bzip2);
+        }
+        None
+    }
+
+    /// Wraps a reader of compressed data into a reader of decompressed data.
+    pub fn async_decoder<R>(self, reader: R) -> Box<dyn AsyncRead + Send + Unpin>
+    where R: AsyncBufRead + Send + Unpin + 'static {
+        match self {
+            Self::Bzip2 => {
+                let mut decoder = BzDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::new(decoder)
+            }
+            Self::Gzip => {
+                let mut decoder = GzipDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::new(decoder)
+            }
+            Self::Lz4 => {
+                let mut decoder = Lz4Decoder::new(reader);
+                decoder.multiple_members(true);
+                Box::new(decoder)
+            }
+            Self::Xz => {
+                let mut decoder = XzDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::new(decoder)
+            }
+            Self::Zstd => {
+                let mut decoder = ZstdDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::new(decoder)
+            }
+        }
+    }
+
+    /// Decompresses a buffer. This is CPU intensive and should not run on an async runtime.
+    pub fn decompress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decompressed = Vec::new();
+        match self {
+            Self::Bzip2 => {
+                bzip2::read::MultiBzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+            }
+            Self::Gzip => {
+                flate2::read::MultiGzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+            }
+            Self::Lz4 => {
+                lz4::Decoder::new(bytes)?.read_to_end(&mut decompressed)?;
+            }
+            Self::Xz => {
+                liblzma::read::XzDecoder::new_multi_decoder(bytes)
+                    .read_to_end(&mut decompressed)?;
+            }
+            Self::Zstd => {
+                decompressed = zstd::decode_all(bytes)?;
+            }
+        }
+        Ok(decompressed)
+    }
+}
+
+impl fmt::Display for CompressionCodec {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Bzip2 => "bzip2",
+            Self::Gzip => "gzip",
+            Self::Lz4 => "lz4",
+            Self::Xz => "xz",
+            Self::Zstd => "zstd",
+        };
+        formatter.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    const ALL_CODECS: [CompressionCodec; 5] = [
+        CompressionCodec::Bzip2,
+        CompressionCodec::Gzip,
+        CompressionCodec::Lz4,
+        CompressionCodec::Xz,
+        CompressionCodec::Zstd,
+    ];
+
+    fn compress(codec: CompressionCodec, bytes: &[u8]) -> Vec<u8> {
+        match codec {
+            CompressionCodec::Bzip2 => {
+                let mut encoder =
+                    bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            }
+            CompressionCodec::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            }
+            CompressionCodec::Lz4 => {
+                let mut encoder = lz4::EncoderBuilder::new().build(Vec::new()).unwrap();
+                encoder.write_all(bytes).unwrap();
+                let (compressed, result) = encoder.finish();
+                result.unwrap();
+                compressed
+            }
+            CompressionCodec::Xz => {
+                let mut encoder = liblzma::write::XzEncoder::new(Vec::new(), 6);
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            }
+            CompressionCodec::Zstd => zstd::encode_all(bytes, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_compression_codec_from_magic_bytes() {
+        for codec in ALL_CODECS {
+            let compressed = compress(codec, b"hello");
+            assert_eq!(
+                CompressionCodec::from_magic_bytes(&compressed[..MAGIC_BYTES_LEN]),
+                Some(codec)
+            );
+        }
+        // Empty bzip2 streams only contain the end of stream marker.
+        let compressed = compress(CompressionCodec::Bzip2, b"");
+        assert_eq!(
+            CompressionCodec::from_magic_bytes(&compressed),
+            Some(CompressionCodec::Bzip2)
+        );
+        assert_eq!(CompressionCodec::from_magic_bytes(b""), None);
+        assert_eq!(CompressionCodec::from_magic_bytes(b"{\"body\": 1}"), None);
+        assert_eq!(CompressionCodec::from_magic_bytes(b"BZh9 is not bzip2"), None);
+    }
+
+    #[test]
+    fn test_compression_codec_decompress() {
+        for codec in ALL_CODECS {
+            let mut compressed = compress(codec, b"hello ");
+            compressed.extend(compress(codec, b"world"));
+            let decompressed = codec.decompress(&compressed).unwrap();
+            assert_eq!(decompressed, b"hello world", "{codec}");
+
+            codec.decompress(b"not compressed").unwrap_err();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compression_codec_async_decoder() {
+        for codec in ALL_CODECS {
+            let mut compressed = compress(codec, b"hello ");
+            compressed.extend(compress(codec, b"world"));
+            let mut decoder = codec.async_decoder(io::Cursor::new(compressed));
+            let mut decompressed = String::new();
+            decoder.read_to_string(&mut decompressed).await.unwrap();
+            assert_eq!(decompressed, "hello world", "{codec}");
+        }
+    }
+}
@@ -19,6 +19,8 @@ mod coolid;
 #[cfg(feature = "jemalloc-profiled")]
 pub(crate) mod alloc_tracker;
 pub mod binary_heap;
+#[cfg(feature = "compression")]
+pub mod compression;
 mod cpus;
 pub mod fs;
 pub mod io;
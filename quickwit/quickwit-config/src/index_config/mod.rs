@@ -14,7 +14,7 @@
 
 pub(crate) mod serialize;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::num::NonZeroUsize;
 use std::str::FromStr;
@@ -28,11 +28,13 @@ use cron::Schedule;
 use humantime::parse_duration;
 use quickwit_common::uri::Uri;
 use quickwit_common::{is_true, true_fn};
-use quickwit_doc_mapper::{DocMapper, DocMapperBuilder, DocMapping};
+use quickwit_doc_mapper::{DocMapper, DocMapperBuilder, DocMapping, JsonObject};
 use quickwit_proto::types::IndexId;
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 pub use serialize::{load_index_config_from_user_config, load_index_config_update};
 use siphasher::sip::SipHasher;
+use siphasher::sip128::{Hasher128, SipHasher13};
 use tracing::warn;
 
 use crate::index_config::serialize::VersionedIndexConfig;
@@ -119,6 +121,11 @@ pub struct IndexingSettings {
     pub merge_policy: MergePolicyConfig,
     #[serde(default)]
     pub resources: IndexingResources,
+    /// Drops the documents whose deduplication key was already seen recently by the indexing
+    /// pipeline.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<DedupSettings>,
 }
 
 impl IndexingSettings {
@@ -160,6 +167,145 @@ impl Default for IndexingSettings {
             split_num_docs_target: Self::default_split_num_docs_target(),
             merge_policy: MergePolicyConfig::default(),
             resources: IndexingResources::default(),
+            dedup: None,
+        }
+    }
+}
+
+/// Settings for deduplicating documents at indexing time.
+///
+/// Each indexing pipeline remembers the keys of the documents it indexed during the last
+/// `window_secs` seconds, up to `max_keys` keys, and drops the documents whose key it already saw.
+/// Deduplication is therefore best-effort: duplicates indexed by different pipelines or too far
+/// apart in time are kept.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DedupSettings {
+    /// Path of the field holding the deduplication key, with nested fields separated by dots. When
+    /// unset, the key is the whole document, and only indexing deduplicates documents: search
+    /// hits are only deduplicated by field. Documents without this field are never dropped.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[schema(default = 3600)]
+    #[serde(default = "DedupSettings::default_window_secs")]
+    pub window_secs: u64,
+    #[schema(default = 1_000_000)]
+    #[serde(default = "DedupSettings::default_max_keys")]
+    pub max_keys: usize,
+}
+
+impl DedupSettings {
+    fn default_window_secs() -> u64 {
+        3600
+    }
+
+    fn default_max_keys() -> usize {
+        1_000_000
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.window_secs)
+    }
+
+    /// Returns the deduplication key of a document, or `None` if the document does not have the
+    /// deduplication field.
+    pub fn dedup_key(&self, json_obj: &JsonObject) -> Option<DedupKey> {
+        let Some(field) = &self.field else {
+            let mut hasher = SipHasher13::new();
+            hash_json_obj(json_obj, &mut hasher);
+            return Some(DedupKey(hasher.finish128().as_u128()));
+        };
+        let mut path = field.split('.');
+        let mut value = json_obj.get(path.next()?)?;
+        for key in path {
+            value = value.as_object()?.get(key)?;
+        }
+        if value.is_null() {
+            return None;
+        }
+        Some(DedupKey::from_json_value(value))
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(field) = &self.field {
+            ensure!(
+                !field.is_empty() && field.split('.').all(|key| !key.is_empty()),
+                "invalid dedup field `{field}`"
+            );
+        }
+        ensure!(self.window_secs > 0, "dedup window must be positive");
+        ensure!(self.max_keys > 0, "dedup max keys must be positive");
+        Ok(())
+    }
+}
+
+/// A 128-bit hash of the deduplication key of a document. Documents sharing the same key have the
+/// same hash, which lets the indexing pipeline and the root searcher detect duplicates.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct DedupKey(u128);
+
+impl DedupKey {
+    pub fn from_json_value(json_value: &JsonValue) -> Self {
+        let mut hasher = SipHasher13::new();
+        hash_json_value(json_value, &mut hasher);
+        Self(hasher.finish128().as_u128())
+    }
+}
+
+// Hashes a canonical form of a JSON value: object keys are hashed in sorted order, so that the
+// hash does not depend on the order of the keys in the document.
+fn hash_json_value(json_value: &JsonValue, hasher: &mut SipHasher13) {
+    match json_value {
+        JsonValue::Null => hasher.write_u8(0),
+        JsonValue::Bool(value) => {
+            hasher.write_u8(1);
+            hasher.write_u8(*value as u8);
+        }
+        JsonValue::Number(value) => {
+            hasher.write_u8(2);
+            hash_str(&value.to_string(), hasher);
+        }
+        JsonValue::String(value) => {
+            hasher.write_u8(3);
+            hash_str(value, hasher);
+        }
+        JsonValue::Array(values) => {
+            hasher.write_u8(4);
+            hasher.write_u64(values.len() as u64);
+            for value in values {
+                hash_json_value(value, hasher);
+            }
+        }
+        JsonValue::Object(json_obj) => {
+            hasher.write_u8(5);
+            hash_json_obj(json_obj, hasher);
+        }
+    }
+}
+
+fn hash_json_obj(json_obj: &JsonObject, hasher: &mut SipHasher13) {
+    let mut entries: Vec<(&String, &JsonValue)> = json_obj.iter().collect();
+    entries.sort_unstable_by(|(left_key, _), (right_key, _)| left_key.cmp(right_key));
+
+    hasher.write_u64(entries.len() as u64);
+    for (key, value) in entries {
+        hash_str(key, hasher);
+        hash_json_value(value, hasher);
+    }
+}
+
+fn hash_str(value: &str, hasher: &mut SipHasher13) {
+    hasher.write_u64(value.len() as u64);
+    hasher.write(value.as_bytes());
+}
+
+impl Default for DedupSettings {
+    fn default() -> Self {
+        Self {
+            field: None,
+            window_secs: Self::default_window_secs(),
+            max_keys: Self::default_max_keys(),
         }
     }
 }
@@ -204,6 +350,68 @@ impl Default for IngestSettings {
 pub struct SearchSettings {
     #[serde(default)]
     pub default_search_fields: Vec<String>,
+    /// Fields computed at query time, available to every search request targeting the index.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub runtime_fields: BTreeMap<String, RuntimeFieldConfig>,
+}
+
+impl SearchSettings {
+    fn validate(&self, doc_mapper: &DocMapper) -> anyhow::Result<()> {
+        let schema = doc_mapper.schema();
+
+        for (field_name, runtime_field) in &self.runtime_fields {
+            ensure!(
+                !field_name.is_empty(),
+                "runtime field name must not be empty"
+            );
+            ensure!(
+                schema.get_field(field_name).is_err(),
+                "runtime field `{field_name}` conflicts with a field of the doc mapping"
+            );
+            runtime_field
+                .validate_vrl_script()
+                .with_context(|| format!("invalid runtime field `{field_name}`"))?;
+        }
+        Ok(())
+    }
+}
+
+/// A field whose value is computed at query time, by evaluating a VRL expression on each
+/// document matching the query, instead of being read from the index.
+///
+/// Runtime fields are meant for the occasional investigation of a field that was not extracted at
+/// indexing time: filtering, sorting or aggregating on them requires to fetch and evaluate every
+/// matching document, which is orders of magnitude slower than using an indexed field.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeFieldConfig {
+    /// VRL expression evaluated on the document, the result of which is the value of the field.
+    pub script: String,
+}
+
+impl RuntimeFieldConfig {
+    #[cfg(feature = "vrl")]
+    fn validate_vrl_script(&self) -> anyhow::Result<()> {
+        self.compile_vrl_script()?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "vrl"))]
+    fn validate_vrl_script(&self) -> anyhow::Result<()> {
+        // Just like transforms, runtime fields are only rejected by nodes that have to evaluate
+        // them.
+        Ok(())
+    }
+
+    #[cfg(feature = "vrl")]
+    /// Compiles the VRL expression to a VRL [`Program`](vrl::compiler::Program) and returns it
+    /// along with the timezone.
+    pub fn compile_vrl_script(
+        &self,
+    ) -> anyhow::Result<(vrl::compiler::Program, vrl::compiler::TimeZone)> {
+        crate::source_config::compile_vrl_program(&self.script, "UTC")
+    }
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
@@ -392,6 +600,7 @@ impl IndexConfig {
                 r#"attributes.server"#.to_string(),
                 r"attributes.server\.status".to_string(),
             ],
+            ..Default::default()
         };
         IndexConfig {
             index_id: index_id.to_string(),
@@ -498,6 +707,7 @@ impl crate::TestableForRegression for IndexConfig {
         };
         let search_settings = SearchSettings {
             default_search_fields: vec!["message".to_string()],
+            ..Default::default()
         };
         let retention_policy_opt = Some(RetentionPolicy {
             retention_period: "90 days".to_string(),
@@ -550,10 +760,14 @@ pub(super) fn validate_index_config(
     // Note: this needs a deep refactoring to separate the doc mapping configuration,
     // and doc mapper implementations.
     // TODO see if we should store the byproducton the IndexConfig.
-    build_doc_mapper(doc_mapping, search_settings)?;
+    let doc_mapper = build_doc_mapper(doc_mapping, search_settings)?;
+    search_settings.validate(&doc_mapper)?;
 
     indexing_settings.merge_policy.validate()?;
     indexing_settings.resources.validate()?;
+    if let Some(dedup_settings) = &indexing_settings.dedup {
+        dedup_settings.validate()?;
+    }
 
     if let Some(retention_policy) = retention_policy_opt {
         retention_policy.validate()?;
@@ -707,6 +921,7 @@ mod tests {
             index_config.search_settings,
             SearchSettings {
                 default_search_fields: vec!["severity_text".to_string(), "body".to_string()],
+                ..Default::default()
             }
         );
     }
@@ -749,6 +964,7 @@ mod tests {
 
             let expected_search_settings = SearchSettings {
                 default_search_fields: vec!["body".to_string()],
+                ..Default::default()
             };
             assert_eq!(index_config.search_settings, expected_search_settings);
             assert!(index_config.retention_policy_opt.is_none());
@@ -784,6 +1000,7 @@ mod tests {
                 index_config.search_settings,
                 SearchSettings {
                     default_search_fields: vec!["body".to_string()],
+                    ..Default::default()
                 }
             );
         }
@@ -848,6 +1065,190 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_index_config_with_runtime_fields() {
+        let config_yaml = r#"
+            version: 0.8
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping:
+              field_mappings:
+                - name: body
+                  type: text
+            search_settings:
+              runtime_fields:
+                status_code:
+                  script: parse_int!(parse_regex!(.body, r'status=(?P<code>\d+)').code)
+        "#;
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap();
+        let runtime_field = &index_config.search_settings.runtime_fields["status_code"];
+        assert!(runtime_field.script.starts_with("parse_int!"));
+
+        let config_yaml = r#"
+            version: 0.8
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping:
+              field_mappings:
+                - name: body
+                  type: text
+            search_settings:
+              runtime_fields:
+                body:
+                  script: upcase!(.body)
+        "#;
+        let error = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .root_cause()
+                .to_string()
+                .contains("runtime field `body` conflicts with a field of the doc mapping")
+        );
+    }
+
+    #[test]
+    fn test_index_config_with_dedup() {
+        let config_yaml = r#"
+            version: 0.8
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping:
+              field_mappings:
+                - name: body
+                  type: text
+            indexing_settings:
+              dedup:
+                field: event.id
+                window_secs: 600
+        "#;
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap();
+        let dedup_settings = index_config.indexing_settings.dedup.unwrap();
+        assert_eq!(dedup_settings.field.as_deref(), Some("event.id"));
+        assert_eq!(dedup_settings.window(), Duration::from_secs(600));
+        assert_eq!(dedup_settings.max_keys, 1_000_000);
+
+        let config_yaml = r#"
+            version: 0.8
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping:
+              field_mappings:
+                - name: body
+                  type: text
+            indexing_settings:
+              dedup:
+                window_secs: 0
+        "#;
+        let error = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::for_test("s3://my-index"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.root_cause().to_string(),
+            "dedup window must be positive"
+        );
+    }
+
+    #[test]
+    fn test_dedup_settings_dedup_key() {
+        let dedup_settings = DedupSettings {
+            field: Some("event.id".to_string()),
+            ..Default::default()
+        };
+        let json_obj = |json: JsonValue| -> JsonObject {
+            let JsonValue::Object(json_obj) = json else {
+                panic!("expected a JSON object");
+            };
+            json_obj
+        };
+        let dedup_key = dedup_settings
+            .dedup_key(&json_obj(
+                serde_json::json!({"event": {"id": "abc"}, "body": "foo"}),
+            ))
+            .unwrap();
+        assert_eq!(
+            dedup_key,
+            DedupKey::from_json_value(&JsonValue::from("abc"))
+        );
+
+        let dedup_key = dedup_settings
+            .dedup_key(&json_obj(serde_json::json!({"event": {"id": 42}})))
+            .unwrap();
+        assert_eq!(dedup_key, DedupKey::from_json_value(&JsonValue::from(42)));
+        assert_ne!(dedup_key, DedupKey::from_json_value(&JsonValue::from("42")));
+
+        assert!(
+            dedup_settings
+                .dedup_key(&json_obj(serde_json::json!({"event": {"id": null}})))
+                .is_none()
+        );
+        assert!(
+            dedup_settings
+                .dedup_key(&json_obj(serde_json::json!({"event": "abc"})))
+                .is_none()
+        );
+        let dedup_settings = DedupSettings::default();
+        let doc = json_obj(serde_json::json!({"body": "foo"}));
+        assert_eq!(
+            dedup_settings.dedup_key(&doc),
+            dedup_settings.dedup_key(&json_obj(serde_json::json!({"body": "foo"})))
+        );
+        assert_ne!(
+            dedup_settings.dedup_key(&doc),
+            dedup_settings.dedup_key(&json_obj(serde_json::json!({"body": "bar"})))
+        );
+    }
+
+    #[test]
+    fn test_dedup_key_ignores_key_order() {
+        let mut nested_obj = JsonObject::new();
+        nested_obj.insert("x".to_string(), JsonValue::from(1));
+        nested_obj.insert("y".to_string(), JsonValue::from(2));
+
+        let mut doc = JsonObject::new();
+        doc.insert("body".to_string(), JsonValue::from("foo"));
+        doc.insert("nested".to_string(), JsonValue::Object(nested_obj));
+
+        let mut reversed_nested_obj = JsonObject::new();
+        reversed_nested_obj.insert("y".to_string(), JsonValue::from(2));
+        reversed_nested_obj.insert("x".to_string(), JsonValue::from(1));
+
+        let mut reversed_doc = JsonObject::new();
+        reversed_doc.insert("nested".to_string(), JsonValue::Object(reversed_nested_obj));
+        reversed_doc.insert("body".to_string(), JsonValue::from("foo"));
+
+        let dedup_settings = DedupSettings::default();
+        assert_eq!(
+            dedup_settings.dedup_key(&doc),
+            dedup_settings.dedup_key(&reversed_doc)
+        );
+        let dedup_settings = DedupSettings {
+            field: Some("nested".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            dedup_settings.dedup_key(&doc),
+            dedup_settings.dedup_key(&reversed_doc)
+        );
+    }
+
     #[test]
     fn test_retention_policy_serialization() {
         let retention_policy = RetentionPolicy {
@@ -34,6 +34,7 @@ pub mod merge_policy_config;
 mod metastore_config;
 mod node_config;
 mod qw_env_vars;
+mod search_template;
 pub(crate) mod serde_utils;
 pub mod service;
 mod source_config;
@@ -45,23 +46,26 @@ pub use cluster_config::ClusterConfig;
 // See #2048
 use index_config::serialize::{IndexConfigV0_8, VersionedIndexConfig};
 pub use index_config::{
-    IndexConfig, IndexingResources, IndexingSettings, IngestSettings, RetentionPolicy,
-    SearchSettings, build_doc_mapper, load_index_config_from_user_config, load_index_config_update,
-    prepare_doc_mapping_update,
+    DedupKey, DedupSettings, IndexConfig, IndexingResources, IndexingSettings, IngestSettings,
+    RetentionPolicy, RuntimeFieldConfig, SearchSettings, build_doc_mapper,
+    load_index_config_from_user_config, load_index_config_update, prepare_doc_mapping_update,
 };
 pub use quickwit_doc_mapper::DocMapping;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 use serde_json::Value as JsonValue;
 use siphasher::sip::SipHasher;
-use source_config::FileSourceParamsForSerde;
 pub use source_config::{
-    CLI_SOURCE_ID, FileSourceMessageType, FileSourceNotification, FileSourceParams, FileSourceSqs,
-    INGEST_API_SOURCE_ID, INGEST_V2_SOURCE_ID, KafkaSourceParams, KinesisSourceParams,
-    PubSubSourceParams, PulsarSourceAuth, PulsarSourceParams, RegionOrEndpoint, SourceConfig,
-    SourceInputFormat, SourceParams, TransformConfig, VecSourceParams, VoidSourceParams,
-    load_source_config_from_user_config, load_source_config_update,
+    CLI_SOURCE_ID, CsvConfig, DeadLetterConfig, DirectorySourceParams, FileSourceMessageType,
+    FileSourceNotification, FileSourceParams, FileSourceSqs, GrokConfig, INGEST_API_SOURCE_ID,
+    INGEST_V2_SOURCE_ID, IndexIdTemplate, IndexRoutingConfig, KafkaSourceParams, KafkaStartFrom,
+    KinesisSourceParams, LineFormatConfig, LinePattern, PatternField, PatternFieldType,
+    PubSubSourceParams, PulsarSourceAuth, PulsarSourceParams, RegexConfig, RegionOrEndpoint,
+    SchemaConfig, SourceConfig, SourceInputFormat, SourceParams, TransformConfig, VecSourceParams,
+    VoidSourceParams, WebhookHmacAlgorithm, WebhookHmacParams, WebhookSignatureEncoding,
+    WebhookSourceParams, load_source_config_from_user_config, load_source_config_update,
 };
+use source_config::{DeadLetterConfigForSerde, FileSourceParamsForSerde};
 use tracing::warn;
 
 use crate::index_template::IndexTemplateV0_8;
@@ -74,8 +78,12 @@ pub use crate::metastore_config::{
 };
 pub use crate::node_config::{
     CacheConfig, CachePolicy, DEFAULT_QW_CONFIG_PATH, GrpcConfig, IndexerConfig, IngestApiConfig,
-    JaegerConfig, KeepAliveConfig, NodeConfig, RestConfig, SearcherConfig, SplitCacheLimits,
-    StorageTimeoutPolicy, TlsConfig,
+    JaegerConfig, KeepAliveConfig, NodeConfig, RestConfig, SearcherConfig, SlowQueryLogConfig,
+    SplitCacheLimits, StorageTimeoutPolicy, TlsConfig,
+};
+use crate::search_template::SearchTemplateV0_9;
+pub use crate::search_template::{
+    MustacheTemplate, SearchTemplate, SearchTemplateId, VersionedSearchTemplate,
 };
 use crate::source_config::serialize::{SourceConfigV0_7, SourceConfigV0_8, VersionedSourceConfig};
 pub use crate::storage_config::{
@@ -100,25 +108,36 @@ pub fn disable_ingest_v1() -> bool {
 #[derive(utoipa::OpenApi)]
 #[openapi(components(schemas(
     ConstWriteAmplificationMergePolicyConfig,
+    CsvConfig,
+    DeadLetterConfigForSerde,
+    DedupSettings,
+    DirectorySourceParams,
     DocMapping,
     FileSourceMessageType,
     FileSourceNotification,
     FileSourceParamsForSerde,
     FileSourceSqs,
+    GrokConfig,
     IndexConfigV0_8,
     IndexingResources,
     IndexingSettings,
+    IndexRoutingConfig,
     IndexTemplateV0_8,
     IngestSettings,
     KafkaSourceParams,
+    KafkaStartFrom,
     KinesisSourceParams,
     MergePolicyConfig,
     PubSubSourceParams,
     PulsarSourceAuth,
     PulsarSourceParams,
+    RegexConfig,
     RegionOrEndpoint,
     RetentionPolicy,
+    RuntimeFieldConfig,
+    SchemaConfig,
     SearchSettings,
+    SearchTemplateV0_9,
     SourceConfigV0_7,
     SourceConfigV0_8,
     SourceInputFormat,
@@ -128,8 +147,13 @@ pub fn disable_ingest_v1() -> bool {
     VecSourceParams,
     VersionedIndexConfig,
     VersionedIndexTemplate,
+    VersionedSearchTemplate,
     VersionedSourceConfig,
     VoidSourceParams,
+    WebhookHmacAlgorithm,
+    WebhookHmacParams,
+    WebhookSignatureEncoding,
+    WebhookSourceParams,
 )))]
 /// Schema used for the OpenAPI generation which are apart of this crate.
 pub struct ConfigApiSchemas;
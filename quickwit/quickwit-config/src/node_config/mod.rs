@@ -300,6 +300,9 @@ pub struct SearcherConfig {
     pub storage_timeout_policy: Option<StorageTimeoutPolicy>,
     pub warmup_memory_budget: ByteSize,
     pub warmup_single_split_initial_allocation: ByteSize,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_query_log: Option<SlowQueryLogConfig>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -418,6 +421,60 @@ impl StorageTimeoutPolicy {
     }
 }
 
+/// Configuration of the slow query log.
+///
+/// Root searches taking longer than `threshold_millis` are logged, with a breakdown of the
+/// time spent in the slowest splits, under the `quickwit_search::slow_query_log` tracing target.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlowQueryLogConfig {
+    pub threshold_millis: u64,
+    /// Fraction of the slow queries that are actually logged, between 0 and 1.
+    #[serde(default = "SlowQueryLogConfig::default_sampling_rate")]
+    pub sampling_rate: f64,
+    /// Number of splits reported in the breakdown of a slow query, between 1 and
+    /// [`SlowQueryLogConfig::MAX_NUM_SLOWEST_SPLITS`].
+    #[serde(default = "SlowQueryLogConfig::default_num_slowest_splits")]
+    pub num_slowest_splits: usize,
+    /// If true, slow query records are also ingested into the `quickwit-slowlog` index.
+    #[serde(default)]
+    pub enable_index: bool,
+}
+
+impl SlowQueryLogConfig {
+    /// Maximum number of slowest splits tracked by searches.
+    pub const MAX_NUM_SLOWEST_SPLITS: usize = 10;
+
+    fn default_sampling_rate() -> f64 {
+        1.0
+    }
+
+    fn default_num_slowest_splits() -> usize {
+        5
+    }
+
+    pub fn threshold(&self) -> Duration {
+        Duration::from_millis(self.threshold_millis)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&self.sampling_rate) {
+            anyhow::bail!(
+                "slow_query_log.sampling_rate ({}) must be between 0 and 1",
+                self.sampling_rate
+            );
+        }
+        if !(1..=Self::MAX_NUM_SLOWEST_SPLITS).contains(&self.num_slowest_splits) {
+            anyhow::bail!(
+                "slow_query_log.num_slowest_splits ({}) must be between 1 and {}",
+                self.num_slowest_splits,
+                Self::MAX_NUM_SLOWEST_SPLITS
+            );
+        }
+        Ok(())
+    }
+}
+
 impl Default for SearcherConfig {
     fn default() -> Self {
         SearcherConfig {
@@ -435,6 +492,7 @@ impl Default for SearcherConfig {
             storage_timeout_policy: None,
             warmup_memory_budget: ByteSize::gb(100),
             warmup_single_split_initial_allocation: ByteSize::gb(1),
+            slow_query_log: None,
         }
     }
 }
@@ -468,6 +526,9 @@ impl SearcherConfig {
                 );
             }
         }
+        if let Some(slow_query_log_config) = &self.slow_query_log {
+            slow_query_log_config.validate()?;
+        }
         Ok(())
     }
 }
@@ -943,4 +1004,47 @@ mod tests {
         };
         assert!(grpc_config.validate().is_err());
     }
+
+    #[test]
+    fn test_slow_query_log_config_validate() {
+        let slow_query_log_config: SlowQueryLogConfig = serde_yaml::from_str(
+            r#"
+                threshold_millis: 2000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(slow_query_log_config.threshold(), Duration::from_secs(2));
+        assert_eq!(slow_query_log_config.sampling_rate, 1.0);
+        assert_eq!(slow_query_log_config.num_slowest_splits, 5);
+        assert!(!slow_query_log_config.enable_index);
+        assert!(slow_query_log_config.validate().is_ok());
+
+        let invalid_slow_query_log_config = SlowQueryLogConfig {
+            sampling_rate: 1.5,
+            ..slow_query_log_config.clone()
+        };
+        assert!(invalid_slow_query_log_config.validate().is_err());
+
+        for num_slowest_splits in [1, SlowQueryLogConfig::MAX_NUM_SLOWEST_SPLITS] {
+            let slow_query_log_config = SlowQueryLogConfig {
+                num_slowest_splits,
+                ..slow_query_log_config.clone()
+            };
+            assert!(slow_query_log_config.validate().is_ok());
+        }
+        for num_slowest_splits in [0, SlowQueryLogConfig::MAX_NUM_SLOWEST_SPLITS + 1] {
+            let slow_query_log_config = SlowQueryLogConfig {
+                num_slowest_splits,
+                ..slow_query_log_config.clone()
+            };
+            let error = slow_query_log_config.validate().unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "slow_query_log.num_slowest_splits ({num_slowest_splits}) must be between 1 \
+                     and 10"
+                )
+            );
+        }
+    }
 }
@@ -687,6 +687,12 @@ mod tests {
                 }),
                 warmup_memory_budget: ByteSize::gb(100),
                 warmup_single_split_initial_allocation: ByteSize::gb(1),
+                slow_query_log: Some(crate::SlowQueryLogConfig {
+                    threshold_millis: 5_000,
+                    sampling_rate: 0.5,
+                    num_slowest_splits: 5,
+                    enable_index: false,
+                }),
             }
         );
         assert_eq!(
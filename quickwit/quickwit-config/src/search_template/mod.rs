@@ -0,0 +1,145 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod mustache;
+mod serialize;
+
+use anyhow::Context;
+pub use mustache::MustacheTemplate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+pub use serialize::{SearchTemplateV0_9, VersionedSearchTemplate};
+
+use crate::validate_identifier;
+
+pub type SearchTemplateId = String;
+
+/// A stored Elasticsearch search request body with mustache-style parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "VersionedSearchTemplate")]
+#[serde(from = "VersionedSearchTemplate")]
+pub struct SearchTemplate {
+    pub template_id: SearchTemplateId,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub source: String,
+}
+
+impl SearchTemplate {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        validate_identifier("search template", &self.template_id)?;
+        MustacheTemplate::parse(&self.source).context("failed to parse search template source")?;
+        Ok(())
+    }
+
+    /// Substitutes `params` into the template source.
+    pub fn render(&self, params: &JsonValue) -> anyhow::Result<String> {
+        let mustache_template = MustacheTemplate::parse(&self.source)
+            .context("failed to parse search template source")?;
+        Ok(mustache_template.render(params))
+    }
+
+    #[cfg(any(test, feature = "testsuite"))]
+    pub fn for_test(template_id: &str) -> Self {
+        SearchTemplate {
+            template_id: template_id.to_string(),
+            description: Some("Test description.".to_string()),
+            source: r#"{"query": {"match": {"{{field}}": "{{value}}"}}}"#.to_string(),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "testsuite"))]
+impl crate::TestableForRegression for SearchTemplate {
+    fn sample_for_regression() -> Self {
+        SearchTemplate {
+            template_id: "test-search-template".to_string(),
+            description: Some("Test description.".to_string()),
+            source: r#"{"query": {"term": {"{{field}}": "{{value}}"}}, "size": {{size}}}"#
+                .to_string(),
+        }
+    }
+
+    fn assert_equality(&self, other: &Self) {
+        assert_eq!(self, other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_search_template_serde() {
+        let search_template_yaml = r#"
+            version: 0.9
+
+            template_id: test-template
+            description: Test description.
+            source:
+              query:
+                match:
+                  "{{field}}": "{{value}}"
+        "#;
+        let search_template: SearchTemplate = serde_yaml::from_str(search_template_yaml).unwrap();
+        assert_eq!(search_template.template_id, "test-template");
+        assert_eq!(search_template.description.unwrap(), "Test description.");
+        assert_eq!(
+            search_template.source,
+            r#"{"query":{"match":{"{{field}}":"{{value}}"}}}"#
+        );
+
+        let search_template_json = r#"{
+            "version": "0.9",
+            "template_id": "test-template",
+            "source": "{\"size\": {{size}}}"
+        }"#;
+        let search_template: SearchTemplate = serde_json::from_str(search_template_json).unwrap();
+        assert!(search_template.description.is_none());
+        assert_eq!(search_template.source, r#"{"size": {{size}}}"#);
+
+        let search_template_json = r#"{
+            "version": "0.9",
+            "template_id": "test-template",
+            "source": 42
+        }"#;
+        serde_json::from_str::<SearchTemplate>(search_template_json).unwrap_err();
+    }
+
+    #[test]
+    fn test_search_template_validate() {
+        SearchTemplate::for_test("test-template")
+            .validate()
+            .unwrap();
+
+        let mut search_template = SearchTemplate::for_test("-invalid");
+        search_template.validate().unwrap_err();
+
+        search_template.template_id = "test-template".to_string();
+        search_template.source = r#"{"query": {{#query}}}"#.to_string();
+        let error = search_template.validate().unwrap_err();
+        assert_eq!(error.to_string(), "failed to parse search template source");
+    }
+
+    #[test]
+    fn test_search_template_render() {
+        let search_template = SearchTemplate::for_test("test-template");
+        let rendered = search_template
+            .render(&json!({"field": "title", "value": "quick fox"}))
+            .unwrap();
+        assert_eq!(rendered, r#"{"query": {"match": {"title": "quick fox"}}}"#);
+    }
+}
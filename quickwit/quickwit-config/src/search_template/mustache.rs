@@ -0,0 +1,394 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A small mustache engine covering the subset of the syntax supported by Elasticsearch search
+//! templates:
+//! - `{{param}}` and `{{a.b.c}}` substitute a (JSON-escaped) parameter value;
+//! - `{{{param}}}` and `{{&param}}` substitute a parameter value without escaping;
+//! - `{{#param}}...{{/param}}` and `{{^param}}...{{/param}}` render a block when a parameter is
+//!   truthy (resp. falsy), iterating over arrays;
+//! - `{{#toJson}}param{{/toJson}}` substitutes the JSON serialization of a parameter;
+//! - `{{#join}}param{{/join}}` substitutes the comma-separated items of an array parameter;
+//! - `{{! comment }}` is ignored.
+
+use anyhow::{bail, ensure};
+use serde_json::Value as JsonValue;
+
+const TO_JSON_FUNCTION: &str = "toJson";
+const JOIN_FUNCTION: &str = "join";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Variable {
+        path: String,
+        escape: bool,
+    },
+    Section {
+        path: String,
+        inverted: bool,
+        children: Vec<Node>,
+    },
+    ToJson(String),
+    Join(String),
+}
+
+/// A parsed mustache template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MustacheTemplate {
+    nodes: Vec<Node>,
+}
+
+struct OpenSection {
+    path: String,
+    inverted: bool,
+    nodes: Vec<Node>,
+}
+
+impl MustacheTemplate {
+    /// Parses a mustache template, returning an error if the template is malformed (unclosed
+    /// tags, mismatched sections, ...).
+    pub fn parse(template: &str) -> anyhow::Result<Self> {
+        let mut open_sections: Vec<OpenSection> = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut remaining = template;
+
+        while let Some(tag_start) = remaining.find("{{") {
+            let current_nodes = open_sections
+                .last_mut()
+                .map(|open_section| &mut open_section.nodes)
+                .unwrap_or(&mut nodes);
+            if tag_start > 0 {
+                current_nodes.push(Node::Text(remaining[..tag_start].to_string()));
+            }
+            remaining = &remaining[tag_start + 2..];
+
+            if let Some(after_brace) = remaining.strip_prefix('{') {
+                let Some(tag_end) = after_brace.find("}}}") else {
+                    bail!("unclosed tag `{{{{{{` in mustache template");
+                };
+                let path = parse_path(&after_brace[..tag_end])?;
+                current_nodes.push(Node::Variable {
+                    path,
+                    escape: false,
+                });
+                remaining = &after_brace[tag_end + 3..];
+                continue;
+            }
+            let Some(tag_end) = remaining.find("}}") else {
+                bail!("unclosed tag `{{{{` in mustache template");
+            };
+            let tag = remaining[..tag_end].trim();
+            remaining = &remaining[tag_end + 2..];
+
+            if tag.starts_with('!') {
+                continue;
+            }
+            if let Some(path) = tag.strip_prefix('&') {
+                let path = parse_path(path)?;
+                current_nodes.push(Node::Variable {
+                    path,
+                    escape: false,
+                });
+            } else if let Some(path) = tag.strip_prefix('#') {
+                let path = parse_path(path)?;
+                open_sections.push(OpenSection {
+                    path,
+                    inverted: false,
+                    nodes: Vec::new(),
+                });
+            } else if let Some(path) = tag.strip_prefix('^') {
+                let path = parse_path(path)?;
+                open_sections.push(OpenSection {
+                    path,
+                    inverted: true,
+                    nodes: Vec::new(),
+                });
+            } else if let Some(path) = tag.strip_prefix('/') {
+                let path = parse_path(path)?;
+                let Some(open_section) = open_sections.pop() else {
+                    bail!("unexpected closing tag `{{{{/{path}}}}}` in mustache template");
+                };
+                ensure!(
+                    open_section.path == path,
+                    "closing tag `{{{{/{path}}}}}` does not match opening tag `{{{{#{}}}}}` in \
+                     mustache template",
+                    open_section.path
+                );
+                let node = build_section_node(open_section)?;
+                open_sections
+                    .last_mut()
+                    .map(|open_section| &mut open_section.nodes)
+                    .unwrap_or(&mut nodes)
+                    .push(node);
+            } else {
+                let path = parse_path(tag)?;
+                current_nodes.push(Node::Variable { path, escape: true });
+            }
+        }
+        if let Some(open_section) = open_sections.last() {
+            bail!(
+                "unclosed section `{{{{#{}}}}}` in mustache template",
+                open_section.path
+            );
+        }
+        if !remaining.is_empty() {
+            nodes.push(Node::Text(remaining.to_string()));
+        }
+        Ok(Self { nodes })
+    }
+
+    /// Renders the template, resolving the variables against `params`. Missing variables are
+    /// rendered as empty strings.
+    pub fn render(&self, params: &JsonValue) -> String {
+        let mut output = String::new();
+        let mut context_stack = vec![params];
+        render_nodes(&self.nodes, &mut context_stack, &mut output);
+        output
+    }
+}
+
+fn parse_path(path: &str) -> anyhow::Result<String> {
+    let path = path.trim();
+    ensure!(!path.is_empty(), "empty tag in mustache template");
+    ensure!(
+        !path.contains(char::is_whitespace),
+        "invalid tag `{{{{{path}}}}}` in mustache template"
+    );
+    Ok(path.to_string())
+}
+
+fn build_section_node(open_section: OpenSection) -> anyhow::Result<Node> {
+    let OpenSection {
+        path,
+        inverted,
+        nodes,
+    } = open_section;
+
+    if inverted || (path != TO_JSON_FUNCTION && path != JOIN_FUNCTION) {
+        let section = Node::Section {
+            path,
+            inverted,
+            children: nodes,
+        };
+        return Ok(section);
+    }
+    let param_path = match nodes.as_slice() {
+        [Node::Text(param_path)] => parse_path(param_path)?,
+        _ => bail!("`{{{{#{path}}}}}` expects a parameter name in mustache template"),
+    };
+    if path == TO_JSON_FUNCTION {
+        Ok(Node::ToJson(param_path))
+    } else {
+        Ok(Node::Join(param_path))
+    }
+}
+
+fn lookup<'a>(path: &str, context_stack: &[&'a JsonValue]) -> Option<&'a JsonValue> {
+    if path == "." {
+        return context_stack.last().copied();
+    }
+    let mut segments = path.split('.');
+    let first_segment = segments.next()?;
+    let mut value = context_stack
+        .iter()
+        .rev()
+        .find_map(|context| context.as_object()?.get(first_segment))?;
+
+    for segment in segments {
+        value = match value {
+            JsonValue::Object(object) => object.get(segment)?,
+            JsonValue::Array(array) => array.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn is_truthy(value_opt: Option<&JsonValue>) -> bool {
+    match value_opt {
+        None | Some(JsonValue::Null) | Some(JsonValue::Bool(false)) => false,
+        Some(JsonValue::String(text)) => !text.is_empty(),
+        Some(JsonValue::Array(array)) => !array.is_empty(),
+        Some(_) => true,
+    }
+}
+
+fn render_value(value: &JsonValue, escape: bool, output: &mut String) {
+    match value {
+        JsonValue::Null => {}
+        JsonValue::String(text) if escape => {
+            let quoted = serde_json::to_string(text).expect("string should serialize to JSON");
+            output.push_str(&quoted[1..quoted.len() - 1]);
+        }
+        JsonValue::String(text) => output.push_str(text),
+        _ => output.push_str(&value.to_string()),
+    }
+}
+
+fn render_nodes<'a>(nodes: &[Node], context_stack: &mut Vec<&'a JsonValue>, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable { path, escape } => {
+                if let Some(value) = lookup(path, context_stack) {
+                    render_value(value, *escape, output);
+                }
+            }
+            Node::ToJson(path) => match lookup(path, context_stack) {
+                Some(value) => output.push_str(&value.to_string()),
+                None => output.push_str("null"),
+            },
+            Node::Join(path) => match lookup(path, context_stack) {
+                Some(JsonValue::Array(items)) => {
+                    for (item_idx, item) in items.iter().enumerate() {
+                        if item_idx > 0 {
+                            output.push(',');
+                        }
+                        render_value(item, false, output);
+                    }
+                }
+                Some(value) => render_value(value, false, output),
+                None => {}
+            },
+            Node::Section {
+                path,
+                inverted,
+                children,
+            } => {
+                let value_opt = lookup(path, context_stack);
+
+                if *inverted {
+                    if !is_truthy(value_opt) {
+                        render_nodes(children, context_stack, output);
+                    }
+                    continue;
+                }
+                if !is_truthy(value_opt) {
+                    continue;
+                }
+                let value = value_opt.expect("truthy value should be present");
+
+                if let JsonValue::Array(items) = value {
+                    for item in items {
+                        context_stack.push(item);
+                        render_nodes(children, context_stack, output);
+                        context_stack.pop();
+                    }
+                } else {
+                    context_stack.push(value);
+                    render_nodes(children, context_stack, output);
+                    context_stack.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn render(template: &str, params: JsonValue) -> String {
+        MustacheTemplate::parse(template).unwrap().render(&params)
+    }
+
+    #[test]
+    fn test_mustache_variables() {
+        assert_eq!(render("hello world", json!({})), "hello world");
+        assert_eq!(
+            render(
+                r#"{"query": {"match": {"{{field}}": "{{value}}"}}, "size": {{size}}}"#,
+                json!({"field": "title", "value": "the \"quick\" fox", "size": 10})
+            ),
+            r#"{"query": {"match": {"title": "the \"quick\" fox"}}, "size": 10}"#
+        );
+        assert_eq!(
+            render(
+                "{{ user.name }}-{{user.tags.1}}",
+                json!({"user": {"name": "jo", "tags": ["a", "b"]}})
+            ),
+            "jo-b"
+        );
+        assert_eq!(render("[{{missing}}]", json!({})), "[]");
+        assert_eq!(
+            render(r#"{{{raw}}}|{{&raw}}"#, json!({"raw": "\"a\""})),
+            "\"a\"|\"a\""
+        );
+        assert_eq!(render("a{{! a comment }}b", json!({})), "ab");
+    }
+
+    #[test]
+    fn test_mustache_sections() {
+        let template =
+            r#"{{#filter}}"filter": "{{value}}",{{/filter}}"size": {{size}}{{^size}}10{{/size}}"#;
+        assert_eq!(
+            render(template, json!({"filter": {"value": "x"}, "size": 5})),
+            r#""filter": "x","size": 5"#
+        );
+        assert_eq!(render(template, json!({})), r#""size": 10"#);
+
+        assert_eq!(
+            render("{{#items}}<{{.}}>{{/items}}", json!({"items": [1, 2, 3]})),
+            "<1><2><3>"
+        );
+        assert_eq!(
+            render(
+                "{{#users}}{{name}}@{{domain}};{{/users}}",
+                json!({"domain": "acme", "users": [{"name": "a"}, {"name": "b"}]})
+            ),
+            "a@acme;b@acme;"
+        );
+        assert_eq!(render("{{#flag}}yes{{/flag}}", json!({"flag": false})), "");
+        assert_eq!(
+            render("{{^items}}none{{/items}}", json!({"items": []})),
+            "none"
+        );
+    }
+
+    #[test]
+    fn test_mustache_functions() {
+        assert_eq!(
+            render(
+                r#"{"terms": {"tags": {{#toJson}}tags{{/toJson}}}}"#,
+                json!({"tags": ["a", "b"]})
+            ),
+            r#"{"terms": {"tags": ["a","b"]}}"#
+        );
+        assert_eq!(render("{{#toJson}}missing{{/toJson}}", json!({})), "null");
+        assert_eq!(
+            render("{{#join}}tags{{/join}}", json!({"tags": ["a", "b", 3]})),
+            "a,b,3"
+        );
+    }
+
+    #[test]
+    fn test_mustache_parse_errors() {
+        for template in [
+            "{{",
+            "{{name",
+            "{{{name}}",
+            "{{}}",
+            "{{#section}}",
+            "{{/section}}",
+            "{{#a}}{{/b}}",
+            "{{#toJson}}{{a}}{{/toJson}}",
+            "{{a b}}",
+        ] {
+            MustacheTemplate::parse(template).unwrap_err();
+        }
+    }
+}
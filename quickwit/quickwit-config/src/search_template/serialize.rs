@@ -0,0 +1,86 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
+
+use super::{SearchTemplate, SearchTemplateId};
+
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(tag = "version")]
+pub enum VersionedSearchTemplate {
+    #[serde(rename = "0.9")]
+    V0_9(SearchTemplateV0_9),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SearchTemplateV0_9 {
+    #[schema(value_type = String)]
+    pub template_id: SearchTemplateId,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The mustache template of the Elasticsearch search request body, provided either as a
+    /// string or as a JSON object.
+    #[schema(value_type = Object)]
+    #[serde(deserialize_with = "deserialize_template_source")]
+    pub source: String,
+}
+
+/// Accepts the template source as a string or as a JSON object, which is then stored in its
+/// serialized form.
+fn deserialize_template_source<'de, D>(deserializer: D) -> Result<String, D::Error>
+where D: Deserializer<'de> {
+    match JsonValue::deserialize(deserializer)? {
+        JsonValue::String(source) => Ok(source),
+        source @ JsonValue::Object(_) => Ok(source.to_string()),
+        _ => Err(serde::de::Error::custom(
+            "search template source must be a string or a JSON object",
+        )),
+    }
+}
+
+impl From<VersionedSearchTemplate> for SearchTemplate {
+    fn from(versioned_search_template: VersionedSearchTemplate) -> Self {
+        match versioned_search_template {
+            VersionedSearchTemplate::V0_9(v0_9) => v0_9.into(),
+        }
+    }
+}
+
+impl From<SearchTemplate> for VersionedSearchTemplate {
+    fn from(search_template: SearchTemplate) -> Self {
+        VersionedSearchTemplate::V0_9(search_template.into())
+    }
+}
+
+impl From<SearchTemplateV0_9> for SearchTemplate {
+    fn from(search_template_v0_9: SearchTemplateV0_9) -> Self {
+        SearchTemplate {
+            template_id: search_template_v0_9.template_id,
+            description: search_template_v0_9.description,
+            source: search_template_v0_9.source,
+        }
+    }
+}
+
+impl From<SearchTemplate> for SearchTemplateV0_9 {
+    fn from(search_template: SearchTemplate) -> Self {
+        SearchTemplateV0_9 {
+            template_id: search_template.template_id,
+            description: search_template.description,
+            source: search_template.source,
+        }
+    }
+}
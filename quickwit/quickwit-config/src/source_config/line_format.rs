@@ -0,0 +1,473 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{Context, bail, ensure};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Maximum depth of nested grok pattern references, which guards against reference cycles.
+const MAX_GROK_PATTERN_DEPTH: usize = 32;
+
+/// Library of grok patterns available to the `grok` input format. The patterns are adapted from
+/// the Logstash library to the syntax of the `regex` crate, which does not support look-arounds.
+const GROK_PATTERNS: &[(&str, &str)] = &[
+    // Base patterns.
+    ("USERNAME", r"[a-zA-Z0-9._-]+"),
+    ("USER", r"%{USERNAME}"),
+    ("INT", r"[+-]?[0-9]+"),
+    ("BASE10NUM", r"[+-]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)"),
+    ("NUMBER", r"%{BASE10NUM}"),
+    ("BASE16NUM", r"[+-]?(?:0x)?[0-9A-Fa-f]+"),
+    ("POSINT", r"\b[1-9][0-9]*\b"),
+    ("NONNEGINT", r"\b[0-9]+\b"),
+    ("WORD", r"\b\w+\b"),
+    ("NOTSPACE", r"\S+"),
+    ("SPACE", r"\s*"),
+    ("DATA", r".*?"),
+    ("GREEDYDATA", r".*"),
+    ("QUOTEDSTRING", r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'"#),
+    ("QS", r"%{QUOTEDSTRING}"),
+    (
+        "UUID",
+        r"[A-Fa-f0-9]{8}-[A-Fa-f0-9]{4}-[A-Fa-f0-9]{4}-[A-Fa-f0-9]{4}-[A-Fa-f0-9]{12}",
+    ),
+    (
+        "LOGLEVEL",
+        r"(?:[Aa]lert|ALERT|[Tt]race|TRACE|[Dd]ebug|DEBUG|[Nn]otice|NOTICE|[Ii]nfo|INFO|[Ww]arn(?:ing)?|WARN(?:ING)?|[Ee]rr(?:or)?|ERR(?:OR)?|[Cc]rit(?:ical)?|CRIT(?:ICAL)?|[Ff]atal|FATAL|[Ss]evere|SEVERE|[Ee]merg(?:ency)?|EMERG(?:ENCY)?)",
+    ),
+    // Networking.
+    (
+        "IPV4",
+        r"(?:(?:25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9]?[0-9])",
+    ),
+    (
+        "IPV6",
+        r"(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}|(?:[0-9A-Fa-f]{1,4}:){1,7}:|(?:[0-9A-Fa-f]{1,4}:){1,6}(?::[0-9A-Fa-f]{1,4}){1,6}|::(?:[0-9A-Fa-f]{1,4}(?::[0-9A-Fa-f]{1,4}){0,6})?",
+    ),
+    ("IP", r"%{IPV6}|%{IPV4}"),
+    (
+        "HOSTNAME",
+        r"\b[0-9A-Za-z][0-9A-Za-z-]{0,62}(?:\.[0-9A-Za-z][0-9A-Za-z-]{0,62})*\.?\b",
+    ),
+    ("IPORHOST", r"%{IP}|%{HOSTNAME}"),
+    ("HOSTPORT", r"%{IPORHOST}:%{POSINT}"),
+    // Paths and URIs.
+    ("UNIXPATH", r"(?:/[^/\s?#]*)+"),
+    ("PATH", r"%{UNIXPATH}"),
+    ("URIPROTO", r"[A-Za-z][A-Za-z0-9+.-]+"),
+    ("URIHOST", r"%{IPORHOST}(?::%{POSINT})?"),
+    ("URIPATH", r"(?:/[A-Za-z0-9$.+!*'(){},~:;=@#%&_\-]*)+"),
+    ("URIPARAM", r"\?[A-Za-z0-9$.+!*'|(){},~@#%&/=:;_?\-\[\]<>]*"),
+    ("URIPATHPARAM", r"%{URIPATH}(?:%{URIPARAM})?"),
+    (
+        "URI",
+        r"%{URIPROTO}://(?:%{USER}(?::[^@]*)?@)?(?:%{URIHOST})?(?:%{URIPATHPARAM})?",
+    ),
+    // Dates and times.
+    (
+        "MONTH",
+        r"\b(?:[Jj]an(?:uary)?|[Ff]eb(?:ruary)?|[Mm]ar(?:ch)?|[Aa]pr(?:il)?|[Mm]ay|[Jj]un(?:e)?|[Jj]ul(?:y)?|[Aa]ug(?:ust)?|[Ss]ep(?:tember)?|[Oo]ct(?:ober)?|[Nn]ov(?:ember)?|[Dd]ec(?:ember)?)\b",
+    ),
+    ("MONTHNUM", r"(?:0?[1-9]|1[0-2])"),
+    ("MONTHDAY", r"(?:0[1-9]|[12][0-9]|3[01]|[1-9])"),
+    (
+        "DAY",
+        r"(?:Mon(?:day)?|Tue(?:sday)?|Wed(?:nesday)?|Thu(?:rsday)?|Fri(?:day)?|Sat(?:urday)?|Sun(?:day)?)",
+    ),
+    ("YEAR", r"[0-9]{4}"),
+    ("HOUR", r"(?:2[0123]|[01]?[0-9])"),
+    ("MINUTE", r"(?:[0-5][0-9])"),
+    ("SECOND", r"(?:(?:[0-5]?[0-9]|60)(?:[:.,][0-9]+)?)"),
+    ("TIME", r"%{HOUR}:%{MINUTE}(?::%{SECOND})?"),
+    ("ISO8601_TIMEZONE", r"(?:Z|[+-]%{HOUR}(?::?%{MINUTE}))"),
+    (
+        "TIMESTAMP_ISO8601",
+        r"%{YEAR}-%{MONTHNUM}-%{MONTHDAY}[T ]%{HOUR}:?%{MINUTE}(?::?%{SECOND})?%{ISO8601_TIMEZONE}?",
+    ),
+    (
+        "HTTPDATE",
+        r"%{MONTHDAY}/%{MONTH}/%{YEAR}:%{TIME} [+-][0-9]{4}",
+    ),
+    ("SYSLOGTIMESTAMP", r"%{MONTH} +%{MONTHDAY} %{TIME}"),
+    // Syslog.
+    ("PROG", r"[\x21-\x5a\x5c\x5e-\x7e]+"),
+    ("SYSLOGPROG", r"%{PROG:program}(?:\[%{POSINT:pid}\])?"),
+    ("SYSLOGHOST", r"%{IPORHOST}"),
+    (
+        "SYSLOGFACILITY",
+        r"<%{NONNEGINT:facility}\.%{NONNEGINT:priority}>",
+    ),
+    (
+        "SYSLOGLINE",
+        r"%{SYSLOGTIMESTAMP:timestamp} (?:%{SYSLOGFACILITY} )?%{SYSLOGHOST:logsource} %{SYSLOGPROG}: %{GREEDYDATA:message}",
+    ),
+    // Apache and nginx.
+    ("HTTPDUSER", r"%{NOTSPACE}"),
+    (
+        "COMMONAPACHELOG",
+        r#"%{IPORHOST:clientip} %{HTTPDUSER:ident} %{HTTPDUSER:auth} \[%{HTTPDATE:timestamp}\] "(?:%{WORD:verb} %{NOTSPACE:request}(?: HTTP/%{NUMBER:httpversion})?|%{DATA:rawrequest})" %{NUMBER:response} (?:%{NUMBER:bytes}|-)"#,
+    ),
+    (
+        "COMBINEDAPACHELOG",
+        r"%{COMMONAPACHELOG} %{QS:referrer} %{QS:agent}",
+    ),
+    ("HTTPD_COMMONLOG", r"%{COMMONAPACHELOG}"),
+    ("HTTPD_COMBINEDLOG", r"%{COMBINEDAPACHELOG}"),
+    ("NGINXACCESS", r"%{COMBINEDAPACHELOG}"),
+    (
+        "NGINXERROR",
+        r"%{YEAR}/%{MONTHNUM}/%{MONTHDAY} %{TIME} \[%{LOGLEVEL:level}\] %{POSINT:pid}#%{NONNEGINT:tid}: (?:\*%{NONNEGINT:connection_id} )?%{GREEDYDATA:message}",
+    ),
+];
+
+static GROK_REFERENCE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"%\{(?P<pattern>\w+)(?::(?P<field>[^:}]+))?(?::(?P<type>[^:}]+))?\}")
+        .expect("grok reference regex should compile")
+});
+
+/// Parameters of the `csv`, `grok`, and `regex` input formats, which parse each line of the
+/// source into a JSON object.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LineFormatConfig {
+    Csv(CsvConfig),
+    Grok(GrokConfig),
+    Regex(RegexConfig),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CsvConfig {
+    /// Names of the columns. Defaults to the fields of the header row.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Vec<String>>,
+    /// Whether the first line of each file is a header row.
+    #[serde(default)]
+    pub has_header: bool,
+    #[serde(default = "default_csv_delimiter")]
+    pub delimiter: char,
+    #[serde(default = "default_csv_quote")]
+    pub quote: char,
+}
+
+fn default_csv_delimiter() -> char {
+    ','
+}
+
+fn default_csv_quote() -> char {
+    '"'
+}
+
+impl CsvConfig {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        if let Some(columns) = &self.columns {
+            ensure!(!columns.is_empty(), "CSV `columns` cannot be empty");
+
+            let mut unique_columns = HashSet::with_capacity(columns.len());
+            for column in columns {
+                ensure!(!column.is_empty(), "CSV column names cannot be empty");
+                ensure!(
+                    unique_columns.insert(column),
+                    "CSV column `{column}` is defined more than once"
+                );
+            }
+        } else {
+            ensure!(
+                self.has_header,
+                "either `columns` or `has_header` must be specified as CSV parameters"
+            );
+        }
+        ensure!(
+            self.delimiter != self.quote,
+            "CSV delimiter and quote characters must be different"
+        );
+        ensure!(
+            !matches!(self.delimiter, '\n' | '\r') && !matches!(self.quote, '\n' | '\r'),
+            "CSV delimiter and quote characters cannot be line breaks"
+        );
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GrokConfig {
+    /// Grok pattern matched against each line, for instance `%{COMBINEDAPACHELOG}`.
+    pub pattern: String,
+    /// Additional patterns, referenced by name in `pattern`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom_patterns: BTreeMap<String, String>,
+}
+
+impl GrokConfig {
+    /// Compiles the grok pattern into a regular expression, expanding the pattern references.
+    pub fn compile(&self) -> anyhow::Result<LinePattern> {
+        let mut fields = Vec::new();
+        let expanded_pattern = self.expand_pattern(&self.pattern, 0, &mut fields)?;
+        ensure!(
+            !fields.is_empty(),
+            "grok pattern must capture at least one field, for instance `%{{WORD:name}}`"
+        );
+        let regex = Regex::new(&expanded_pattern)
+            .with_context(|| format!("failed to compile grok pattern `{}`", self.pattern))?;
+        Ok(LinePattern { regex, fields })
+    }
+
+    fn expand_pattern(
+        &self,
+        pattern: &str,
+        depth: usize,
+        fields: &mut Vec<PatternField>,
+    ) -> anyhow::Result<String> {
+        if depth > MAX_GROK_PATTERN_DEPTH {
+            bail!("grok pattern references are nested too deeply, check for reference cycles");
+        }
+        let mut expanded_pattern = String::with_capacity(pattern.len());
+        let mut last_match_end = 0;
+
+        for captures in GROK_REFERENCE_REGEX.captures_iter(pattern) {
+            let reference = captures.get(0).expect("group 0 should always be set");
+            expanded_pattern.push_str(&pattern[last_match_end..reference.start()]);
+            last_match_end = reference.end();
+
+            let pattern_name = &captures["pattern"];
+            let referenced_pattern = self
+                .custom_patterns
+                .get(pattern_name)
+                .map(String::as_str)
+                .or_else(|| {
+                    GROK_PATTERNS
+                        .iter()
+                        .find(|(name, _)| *name == pattern_name)
+                        .map(|(_, pattern)| *pattern)
+                })
+                .with_context(|| format!("unknown grok pattern `{pattern_name}`"))?;
+            let expanded_reference = self.expand_pattern(referenced_pattern, depth + 1, fields)?;
+
+            let Some(field_name) = captures.name("field") else {
+                expanded_pattern.push_str("(?:");
+                expanded_pattern.push_str(&expanded_reference);
+                expanded_pattern.push(')');
+                continue;
+            };
+            let field_type = match captures.name("type").map(|field_type| field_type.as_str()) {
+                None | Some("string") => PatternFieldType::String,
+                Some("int") => PatternFieldType::Int,
+                Some("float") => PatternFieldType::Float,
+                Some(unknown) => bail!(
+                    "unknown grok field type `{unknown}`, expected `int`, `float`, or `string`"
+                ),
+            };
+            let group_name = format!("__field{}", fields.len());
+            expanded_pattern.push_str(&format!("(?P<{group_name}>{expanded_reference})"));
+
+            fields.push(PatternField {
+                group_name,
+                field_name: field_name.as_str().to_string(),
+                field_type,
+            });
+        }
+        expanded_pattern.push_str(&pattern[last_match_end..]);
+        Ok(expanded_pattern)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RegexConfig {
+    /// Regular expression matched against each line. Its named capture groups become the fields
+    /// of the document.
+    pub pattern: String,
+}
+
+impl RegexConfig {
+    pub fn compile(&self) -> anyhow::Result<LinePattern> {
+        let regex = Regex::new(&self.pattern)
+            .with_context(|| format!("failed to compile regex pattern `{}`", self.pattern))?;
+        let fields: Vec<PatternField> = regex
+            .capture_names()
+            .flatten()
+            .map(|group_name| PatternField {
+                group_name: group_name.to_string(),
+                field_name: group_name.to_string(),
+                field_type: PatternFieldType::String,
+            })
+            .collect();
+        ensure!(
+            !fields.is_empty(),
+            "regex pattern must contain at least one named capture group, for instance \
+             `(?P<name>\\w+)`"
+        );
+        Ok(LinePattern { regex, fields })
+    }
+}
+
+/// A regular expression whose named capture groups map to document fields.
+#[derive(Clone, Debug)]
+pub struct LinePattern {
+    pub regex: Regex,
+    pub fields: Vec<PatternField>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PatternField {
+    pub group_name: String,
+    pub field_name: String,
+    pub field_type: PatternFieldType,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PatternFieldType {
+    String,
+    Int,
+    Float,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grok_config(pattern: &str) -> GrokConfig {
+        GrokConfig {
+            pattern: pattern.to_string(),
+            custom_patterns: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_grok_patterns_compile() {
+        for (pattern_name, _) in GROK_PATTERNS {
+            let grok_config = grok_config(&format!("%{{{pattern_name}:field}}"));
+            grok_config
+                .compile()
+                .unwrap_or_else(|error| panic!("failed to compile `{pattern_name}`: {error:?}"));
+        }
+    }
+
+    #[test]
+    fn test_grok_config_compile() {
+        let line_pattern = grok_config("%{COMBINEDAPACHELOG}").compile().unwrap();
+        let line = r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326 "http://www.example.com/start.html" "Mozilla/4.08""#;
+        let captures = line_pattern.regex.captures(line).unwrap();
+        let field_values: BTreeMap<&str, &str> = line_pattern
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let value = captures.name(&field.group_name)?.as_str();
+                Some((field.field_name.as_str(), value))
+            })
+            .collect();
+        assert_eq!(field_values["clientip"], "127.0.0.1");
+        assert_eq!(field_values["ident"], "-");
+        assert_eq!(field_values["auth"], "frank");
+        assert_eq!(field_values["timestamp"], "10/Oct/2000:13:55:36 -0700");
+        assert_eq!(field_values["verb"], "GET");
+        assert_eq!(field_values["request"], "/apache_pb.gif");
+        assert_eq!(field_values["httpversion"], "1.0");
+        assert_eq!(field_values["response"], "200");
+        assert_eq!(field_values["bytes"], "2326");
+        assert_eq!(
+            field_values["referrer"],
+            r#""http://www.example.com/start.html""#
+        );
+        assert!(!field_values.contains_key("rawrequest"));
+
+        let mut grok_config = grok_config("%{DURATION:duration:float}s %{WORD:unit}");
+        grok_config
+            .custom_patterns
+            .insert("DURATION".to_string(), "%{NUMBER}".to_string());
+        let line_pattern = grok_config.compile().unwrap();
+        assert_eq!(line_pattern.fields.len(), 2);
+        assert_eq!(line_pattern.fields[0].field_name, "duration");
+        assert_eq!(line_pattern.fields[0].field_type, PatternFieldType::Float);
+        assert!(line_pattern.regex.is_match("1.5s ms"));
+    }
+
+    #[test]
+    fn test_grok_config_compile_errors() {
+        let error = grok_config("%{UNKNOWN:field}").compile().unwrap_err();
+        assert_eq!(error.to_string(), "unknown grok pattern `UNKNOWN`");
+
+        let error = grok_config("%{WORD:field:bool}").compile().unwrap_err();
+        assert!(error.to_string().contains("unknown grok field type `bool`"));
+
+        let error = grok_config("%{WORD}").compile().unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("must capture at least one field")
+        );
+
+        let mut grok_config = grok_config("%{LOOP:field}");
+        grok_config
+            .custom_patterns
+            .insert("LOOP".to_string(), "a%{LOOP}".to_string());
+        let error = grok_config.compile().unwrap_err();
+        assert!(error.to_string().contains("nested too deeply"));
+    }
+
+    #[test]
+    fn test_regex_config_compile() {
+        let regex_config = RegexConfig {
+            pattern: r"^(?P<level>\w+): (?P<message>.*)$".to_string(),
+        };
+        let line_pattern = regex_config.compile().unwrap();
+        let field_names: Vec<&str> = line_pattern
+            .fields
+            .iter()
+            .map(|field| field.field_name.as_str())
+            .collect();
+        assert_eq!(field_names, ["level", "message"]);
+
+        let regex_config = RegexConfig {
+            pattern: r"^(\w+)$".to_string(),
+        };
+        let error = regex_config.compile().unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("at least one named capture group")
+        );
+    }
+
+    #[test]
+    fn test_csv_config_validate() {
+        let csv_config: CsvConfig = serde_json::from_str(r#"{"has_header": true}"#).unwrap();
+        assert_eq!(csv_config.delimiter, ',');
+        assert_eq!(csv_config.quote, '"');
+        csv_config.validate().unwrap();
+
+        let csv_config: CsvConfig = serde_json::from_str(r#"{"delimiter": ";"}"#).unwrap();
+        let error = csv_config.validate().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "either `columns` or `has_header` must be specified as CSV parameters"
+        );
+        let csv_config: CsvConfig =
+            serde_json::from_str(r#"{"columns": ["a", "b", "a"]}"#).unwrap();
+        let error = csv_config.validate().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "CSV column `a` is defined more than once"
+        );
+
+        let csv_config: CsvConfig =
+            serde_json::from_str(r#"{"columns": ["a"], "delimiter": "\""}"#).unwrap();
+        csv_config.validate().unwrap_err();
+
+        serde_json::from_str::<CsvConfig>(r#"{"columns": ["a"], "delimiter": ";;"}"#).unwrap_err();
+    }
+}
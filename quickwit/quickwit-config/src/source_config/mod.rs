@@ -12,19 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod line_format;
 pub(crate) mod serialize;
 
 use std::borrow::Cow;
 use std::hash::{Hash, Hasher};
-use std::num::NonZeroUsize;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::Path;
 use std::str::FromStr;
 
-use anyhow::ensure;
+use anyhow::{Context, bail, ensure};
 use bytes::Bytes;
+pub use line_format::{
+    CsvConfig, GrokConfig, LineFormatConfig, LinePattern, PatternField, PatternFieldType,
+    RegexConfig,
+};
 use quickwit_common::is_false;
 use quickwit_common::uri::Uri;
+use quickwit_doc_mapper::JsonObject;
 use quickwit_proto::metastore::SourceType;
-use quickwit_proto::types::SourceId;
+use quickwit_proto::types::{IndexId, SourceId};
 use regex::Regex;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize};
@@ -34,7 +41,7 @@ use serialize::VersionedSourceConfig;
 pub use serialize::{load_source_config_from_user_config, load_source_config_update};
 use siphasher::sip::SipHasher;
 
-use crate::{disable_ingest_v1, enable_ingest_v2};
+use crate::{disable_ingest_v1, enable_ingest_v2, validate_identifier};
 
 /// Reserved source ID for the `quickwit index ingest` CLI command.
 pub const CLI_SOURCE_ID: &str = "_ingest-cli-source";
@@ -68,6 +75,18 @@ pub struct SourceConfig {
     // Denotes the input data format.
     #[serde(default)]
     pub input_format: SourceInputFormat,
+
+    // Destination of the documents rejected by the doc processor.
+    pub dead_letter_config: Option<DeadLetterConfig>,
+
+    // Schema parameters of the `avro` and `protobuf` input formats.
+    pub schema_config: Option<SchemaConfig>,
+
+    // Parameters of the `csv`, `grok`, and `regex` input formats.
+    pub line_format_config: Option<LineFormatConfig>,
+
+    // Routes the documents of the source to other indexes.
+    pub index_routing_config: Option<IndexRoutingConfig>,
 }
 
 impl SourceConfig {
@@ -75,9 +94,19 @@ impl SourceConfig {
         self.source_params.source_type()
     }
 
+    /// Returns whether the first line of each file read by the source is a CSV header row.
+    pub fn has_csv_header(&self) -> bool {
+        self.input_format == SourceInputFormat::Csv
+            && matches!(
+                &self.line_format_config,
+                Some(LineFormatConfig::Csv(csv_config)) if csv_config.has_header
+            )
+    }
+
     // TODO: Remove after source factory refactor.
     pub fn params(&self) -> JsonValue {
         match &self.source_params {
+            SourceParams::Directory(params) => serde_json::to_value(params),
             SourceParams::File(params) => serde_json::to_value(params),
             SourceParams::PubSub(params) => serde_json::to_value(params),
             SourceParams::Ingest => serde_json::to_value(()),
@@ -89,6 +118,7 @@ impl SourceConfig {
             SourceParams::Stdin => serde_json::to_value(()),
             SourceParams::Vec(params) => serde_json::to_value(params),
             SourceParams::Void(params) => serde_json::to_value(params),
+            SourceParams::Webhook(params) => serde_json::to_value(params),
         }
         .expect("`SourceParams` should be JSON serializable")
     }
@@ -102,6 +132,10 @@ impl SourceConfig {
             source_params: SourceParams::IngestCli,
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         }
     }
 
@@ -114,6 +148,10 @@ impl SourceConfig {
             source_params: SourceParams::Ingest,
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         }
     }
 
@@ -126,6 +164,10 @@ impl SourceConfig {
             source_params: SourceParams::IngestApi,
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         }
     }
 
@@ -140,6 +182,10 @@ impl SourceConfig {
         self.num_pipelines.hash(&mut hasher);
         self.source_params.hash(&mut hasher);
         self.transform_config.hash(&mut hasher);
+        self.dead_letter_config.hash(&mut hasher);
+        self.schema_config.hash(&mut hasher);
+        self.line_format_config.hash(&mut hasher);
+        self.index_routing_config.hash(&mut hasher);
         hasher.finish()
     }
 
@@ -152,6 +198,10 @@ impl SourceConfig {
             source_params,
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         }
     }
 }
@@ -168,12 +218,19 @@ impl crate::TestableForRegression for SourceConfig {
                 client_log_level: None,
                 client_params: serde_json::json!({}),
                 enable_backfill_mode: false,
+                include_metadata: false,
+                metadata_field_prefix: "kafka_".to_string(),
+                start_from: None,
             }),
             transform_config: Some(TransformConfig {
                 vrl_script: ".message = downcase(string!(.message))".to_string(),
                 timezone: default_timezone(),
             }),
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         }
     }
 
@@ -202,6 +259,31 @@ pub enum SourceInputFormat {
     OtlpTracesProtobuf,
     #[serde(alias = "plain")]
     PlainText,
+    Avro,
+    #[serde(alias = "proto")]
+    Protobuf,
+    Csv,
+    Logfmt,
+    Grok,
+    Regex,
+}
+
+impl SourceInputFormat {
+    /// Returns whether the input format requires schema parameters to decode payloads.
+    pub fn requires_schema(&self) -> bool {
+        matches!(self, SourceInputFormat::Avro | SourceInputFormat::Protobuf)
+    }
+
+    /// Returns whether the input format parses each line of the source into a document.
+    pub fn is_line_format(&self) -> bool {
+        matches!(
+            self,
+            SourceInputFormat::Csv
+                | SourceInputFormat::Logfmt
+                | SourceInputFormat::Grok
+                | SourceInputFormat::Regex
+        )
+    }
 }
 
 impl FromStr for SourceInputFormat {
@@ -211,6 +293,8 @@ impl FromStr for SourceInputFormat {
         match format_str {
             "json" => Ok(Self::Json),
             "plain" => Ok(Self::PlainText),
+            "csv" => Ok(Self::Csv),
+            "logfmt" => Ok(Self::Logfmt),
             unknown => Err(format!("unknown source input format: `{unknown}`")),
         }
     }
@@ -219,6 +303,7 @@ impl FromStr for SourceInputFormat {
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Hash, utoipa::ToSchema)]
 #[serde(tag = "source_type", content = "params", rename_all = "snake_case")]
 pub enum SourceParams {
+    Directory(DirectorySourceParams),
     #[schema(value_type = FileSourceParamsForSerde)]
     File(FileSourceParams),
     Ingest,
@@ -234,6 +319,7 @@ pub enum SourceParams {
     Stdin,
     Vec(VecSourceParams),
     Void(VoidSourceParams),
+    Webhook(WebhookSourceParams),
 }
 
 impl SourceParams {
@@ -255,6 +341,7 @@ impl SourceParams {
 
     fn source_type(&self) -> SourceType {
         match self {
+            SourceParams::Directory(_) => SourceType::Directory,
             SourceParams::File(_) => SourceType::File,
             SourceParams::Ingest => SourceType::IngestV2,
             SourceParams::IngestApi => SourceType::IngestV1,
@@ -266,11 +353,15 @@ impl SourceParams {
             SourceParams::Stdin => SourceType::Stdin,
             SourceParams::Vec(_) => SourceType::Vec,
             SourceParams::Void(_) => SourceType::Void,
+            SourceParams::Webhook(_) => SourceType::Webhook,
         }
     }
 
     fn validate_update(&self, new_source_params: &SourceParams) -> anyhow::Result<()> {
         match (self, new_source_params) {
+            (SourceParams::Directory(current), SourceParams::Directory(new)) => {
+                current.validate_update(new)
+            }
             (
                 SourceParams::File(FileSourceParams::Notifications(current)),
                 SourceParams::File(FileSourceParams::Notifications(new)),
@@ -287,6 +378,9 @@ impl SourceParams {
             (SourceParams::Pulsar(current), SourceParams::Pulsar(new)) => {
                 current.validate_update(new)
             }
+            (SourceParams::Webhook(current), SourceParams::Webhook(new)) => {
+                current.validate_update(new)
+            }
             (current, new) if current.source_type() != new.source_type() => Err(anyhow::anyhow!(
                 "source type cannot be changed, current type {}",
                 current.source_type(),
@@ -417,6 +511,33 @@ impl FileSourceParams {
     }
 }
 
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DirectorySourceParams {
+    /// Glob pattern matching the absolute paths of the files to tail (e.g.
+    /// `/var/log/app/*.log`).
+    pub glob: String,
+}
+
+impl DirectorySourceParams {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            Path::new(&self.glob).is_absolute(),
+            "directory source glob `{}` must be an absolute path",
+            self.glob
+        );
+        glob::Pattern::new(&self.glob)
+            .with_context(|| format!("invalid directory source glob `{}`", self.glob))?;
+        Ok(())
+    }
+
+    fn validate_update(&self, _other: &Self) -> anyhow::Result<()> {
+        // The checkpoint partitions are the inodes of the files, so they remain valid when the
+        // glob changes.
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct KafkaSourceParams {
@@ -435,9 +556,71 @@ pub struct KafkaSourceParams {
     #[serde(default)]
     #[serde(skip_serializing_if = "is_false")]
     pub enable_backfill_mode: bool,
+    /// When enabled, the key, headers, timestamp, partition, and offset of each message are added
+    /// to the document as fields whose names start with `metadata_field_prefix`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub include_metadata: bool,
+    /// Prefix of the names of the metadata fields.
+    #[schema(default = "kafka_")]
+    #[serde(default = "default_kafka_metadata_field_prefix")]
+    #[serde(skip_serializing_if = "is_default_kafka_metadata_field_prefix")]
+    pub metadata_field_prefix: String,
+    /// Position from which the partitions without checkpoint are consumed. Takes precedence over
+    /// the offsets committed by the consumer group and `auto.offset.reset`.
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_from: Option<KafkaStartFrom>,
+}
+
+fn default_kafka_metadata_field_prefix() -> String {
+    "kafka_".to_string()
+}
+
+fn is_default_kafka_metadata_field_prefix(prefix: &str) -> bool {
+    prefix == "kafka_"
+}
+
+/// Position from which a Kafka source consumes the partitions without checkpoint.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaStartFrom {
+    /// Oldest message of the partition.
+    Earliest,
+    /// Next message produced to the partition.
+    Latest,
+    /// First message whose timestamp, in milliseconds since the Unix epoch, is greater than or
+    /// equal to the given timestamp.
+    Timestamp(i64),
 }
 
 impl KafkaSourceParams {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        if self.include_metadata {
+            ensure!(
+                !self.metadata_field_prefix.is_empty(),
+                "Kafka metadata field prefix must not be empty"
+            );
+        }
+        if let Some(KafkaStartFrom::Timestamp(timestamp_millis)) = self.start_from {
+            ensure!(
+                timestamp_millis >= 0,
+                "Kafka `start_from` timestamp must not be negative, got `{timestamp_millis}`"
+            );
+        }
+        match self.client_params.get("isolation.level") {
+            None | Some(JsonValue::Null) => {}
+            Some(JsonValue::String(isolation_level))
+                if isolation_level == "read_committed" || isolation_level == "read_uncommitted" => {
+            }
+            Some(isolation_level) => bail!(
+                "invalid Kafka `isolation.level` {isolation_level}: expected `read_committed` or \
+                 `read_uncommitted`"
+            ),
+        }
+        Ok(())
+    }
+
     fn validate_update(&self, other: &Self) -> anyhow::Result<()> {
         // Updating the topic would likely mess up the checkpoints because the
         // Kafka partition IDs are used as metastore checkpoint PartitionId
@@ -554,6 +737,87 @@ pub struct VecSourceParams {
 #[serde(deny_unknown_fields)]
 pub struct VoidSourceParams;
 
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookSourceParams {
+    /// When set, requests without a valid HMAC signature are rejected.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hmac: Option<WebhookHmacParams>,
+    /// Maximum number of requests per second accepted by each node for this source.
+    #[schema(value_type = Option<u32>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_requests_per_sec: Option<NonZeroU32>,
+}
+
+impl WebhookSourceParams {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        if let Some(hmac_params) = &self.hmac {
+            hmac_params.validate()?;
+        }
+        Ok(())
+    }
+
+    fn validate_update(&self, _other: &Self) -> anyhow::Result<()> {
+        // Webhook sources have no checkpoint, so any update is fine.
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookHmacParams {
+    /// Name of the HTTP header carrying the signature (e.g. `X-Hub-Signature-256`).
+    pub header: String,
+    /// Secret shared with the sender of the webhook.
+    pub secret: String,
+    /// Hash function of the HMAC.
+    #[serde(default)]
+    pub algorithm: WebhookHmacAlgorithm,
+    /// Prefix preceding the signature in the header value (e.g. `sha256=`).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub prefix: String,
+    /// Encoding of the signature in the header value.
+    #[serde(default)]
+    pub encoding: WebhookSignatureEncoding,
+}
+
+impl WebhookHmacParams {
+    fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            !self.header.is_empty(),
+            "webhook signature header name must not be empty"
+        );
+        ensure!(
+            !self.secret.is_empty(),
+            "webhook HMAC secret must not be empty"
+        );
+        Ok(())
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookHmacAlgorithm {
+    Sha1,
+    #[default]
+    Sha256,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookSignatureEncoding {
+    Base64,
+    #[default]
+    Hex,
+}
+
 #[derive(
     Clone, Debug, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize, utoipa::ToSchema,
 )]
@@ -620,6 +884,258 @@ fn default_consumer_name() -> String {
     "quickwit".to_string()
 }
 
+/// Destination of the documents rejected by the doc processor.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(
+    try_from = "DeadLetterConfigForSerde",
+    into = "DeadLetterConfigForSerde"
+)]
+pub enum DeadLetterConfig {
+    /// Rejected documents are ingested into another index via the ingest API.
+    Index(IndexId),
+    /// Rejected documents are written as newline-delimited JSON files under a storage URI.
+    Storage(Uri),
+}
+
+impl TryFrom<DeadLetterConfigForSerde> for DeadLetterConfig {
+    type Error = Cow<'static, str>;
+
+    fn try_from(value: DeadLetterConfigForSerde) -> Result<Self, Self::Error> {
+        match (value.index_id, value.storage_uri) {
+            (Some(index_id), None) => Ok(DeadLetterConfig::Index(index_id)),
+            (None, Some(storage_uri)) => {
+                let uri = Uri::from_str(&storage_uri).map_err(|err| err.to_string())?;
+                Ok(DeadLetterConfig::Storage(uri))
+            }
+            (Some(_), Some(_)) => Err("dead letter parameters `index_id` and `storage_uri` are \
+                                       mutually exclusive"
+                .into()),
+            (None, None) => Err(
+                "either `index_id` or `storage_uri` must be specified as dead letter parameters"
+                    .into(),
+            ),
+        }
+    }
+}
+
+impl From<DeadLetterConfig> for DeadLetterConfigForSerde {
+    fn from(value: DeadLetterConfig) -> Self {
+        match value {
+            DeadLetterConfig::Index(index_id) => Self {
+                index_id: Some(index_id),
+                storage_uri: None,
+            },
+            DeadLetterConfig::Storage(uri) => Self {
+                index_id: None,
+                storage_uri: Some(uri.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub(super) struct DeadLetterConfigForSerde {
+    /// ID of the index receiving the rejected documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_id: Option<String>,
+    /// URI of the directory receiving the rejected documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    storage_uri: Option<String>,
+}
+
+/// Routes the documents of a source to several indexes, based on the values of their fields.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IndexRoutingConfig {
+    /// Template of the ID of the index receiving each document, e.g.
+    /// `logs-{kubernetes.namespace}`. Placeholders are replaced by the value of the document
+    /// field they name, with nested fields separated by dots.
+    pub index_id: String,
+    /// Whether the documents routed to an index that does not exist and cannot be created are
+    /// indexed by the index of the source. Otherwise, the pipeline fails.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub fallback_to_source_index: bool,
+}
+
+impl IndexRoutingConfig {
+    pub fn index_id_template(&self) -> anyhow::Result<IndexIdTemplate> {
+        IndexIdTemplate::parse(&self.index_id)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum IndexIdTemplatePart {
+    Literal(String),
+    // Path of a document field, split on dots.
+    Field(Vec<String>),
+}
+
+/// A parsed [`IndexRoutingConfig::index_id`] template.
+#[derive(Debug)]
+pub struct IndexIdTemplate {
+    parts: Vec<IndexIdTemplatePart>,
+}
+
+impl IndexIdTemplate {
+    fn parse(template: &str) -> anyhow::Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while !rest.is_empty() {
+            let Some(start) = rest.find('{') else {
+                parts.push(IndexIdTemplatePart::Literal(rest.to_string()));
+                break;
+            };
+            if start > 0 {
+                parts.push(IndexIdTemplatePart::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .with_context(|| {
+                    format!("unclosed placeholder in index ID template `{template}`")
+                })?;
+            let field = &rest[start + 1..end];
+            ensure!(
+                !field.contains('{') && field.split('.').all(|key| !key.is_empty()),
+                "invalid placeholder `{{{field}}}` in index ID template `{template}`"
+            );
+            let path = field.split('.').map(str::to_string).collect();
+            parts.push(IndexIdTemplatePart::Field(path));
+            rest = &rest[end + 1..];
+        }
+        for part in &parts {
+            if let IndexIdTemplatePart::Literal(literal) = part {
+                ensure!(
+                    literal.chars().all(is_index_id_char),
+                    "index ID template `{template}` contains invalid characters"
+                );
+            }
+        }
+        ensure!(
+            parts
+                .iter()
+                .any(|part| matches!(part, IndexIdTemplatePart::Field(_))),
+            "index ID template `{template}` must contain at least one placeholder"
+        );
+        Ok(Self { parts })
+    }
+
+    /// Returns the ID of the index receiving a document, or `None` if the document lacks a field
+    /// of the template or the resulting index ID is invalid. Characters of field values that are
+    /// not allowed in index IDs are replaced by dashes.
+    pub fn render(&self, json_obj: &JsonObject) -> Option<IndexId> {
+        let mut index_id = String::new();
+
+        for part in &self.parts {
+            match part {
+                IndexIdTemplatePart::Literal(literal) => index_id.push_str(literal),
+                IndexIdTemplatePart::Field(path) => {
+                    let mut value = json_obj.get(&path[0])?;
+                    for key in &path[1..] {
+                        value = value.as_object()?.get(key)?;
+                    }
+                    let value = match value {
+                        JsonValue::String(value) => Cow::Borrowed(value.as_str()),
+                        JsonValue::Number(value) => Cow::Owned(value.to_string()),
+                        JsonValue::Bool(value) => Cow::Owned(value.to_string()),
+                        _ => return None,
+                    };
+                    if value.is_empty() {
+                        return None;
+                    }
+                    index_id.extend(
+                        value
+                            .chars()
+                            .map(|c| if is_index_id_char(c) { c } else { '-' }),
+                    );
+                }
+            }
+        }
+        validate_identifier("index", &index_id).ok()?;
+        Some(index_id)
+    }
+}
+
+fn is_index_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')
+}
+
+/// Describes where the schemas of the `avro` and `protobuf` input formats come from.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SchemaConfig {
+    /// URL of a Confluent-compatible schema registry. Payloads are expected to be framed in the
+    /// Confluent wire format and their schema is resolved by ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry_url: Option<String>,
+    /// Inline Avro schema.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// URI of a schema file: an Avro schema or a serialized protobuf `FileDescriptorSet`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_uri: Option<String>,
+    /// Fully qualified name of the protobuf message type, required with `schema_uri`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<String>,
+}
+
+impl SchemaConfig {
+    fn validate(&self, input_format: SourceInputFormat) -> anyhow::Result<()> {
+        let num_schema_sources = [&self.registry_url, &self.schema, &self.schema_uri]
+            .into_iter()
+            .filter(|schema_source| schema_source.is_some())
+            .count();
+        ensure!(
+            num_schema_sources == 1,
+            "exactly one of `registry_url`, `schema`, or `schema_uri` must be specified as schema \
+             parameters"
+        );
+        if let Some(registry_url) = &self.registry_url {
+            ensure!(
+                registry_url.starts_with("http://") || registry_url.starts_with("https://"),
+                "schema registry URL `{registry_url}` must start with `http://` or `https://`"
+            );
+            ensure!(
+                self.message_type.is_none(),
+                "`message_type` cannot be used with `registry_url`: the message type is read from \
+                 the payload"
+            );
+        }
+        if let Some(schema_uri) = &self.schema_uri {
+            Uri::from_str(schema_uri)?;
+        }
+        match input_format {
+            SourceInputFormat::Avro => {
+                if let Some(schema) = &self.schema {
+                    serde_json::from_str::<JsonValue>(schema)
+                        .map_err(|error| anyhow::anyhow!("invalid Avro schema: {error}"))?;
+                }
+                ensure!(
+                    self.message_type.is_none(),
+                    "`message_type` is only supported with the `protobuf` input format"
+                );
+            }
+            SourceInputFormat::Protobuf => {
+                ensure!(
+                    self.schema.is_none(),
+                    "inline schemas are not supported with the `protobuf` input format: use \
+                     `schema_uri` with a serialized `FileDescriptorSet` instead"
+                );
+                ensure!(
+                    self.schema_uri.is_none() || self.message_type.is_some(),
+                    "`message_type` is required when `schema_uri` is used with the `protobuf` \
+                     input format"
+                );
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct TransformConfig {
@@ -670,38 +1186,10 @@ impl TransformConfig {
     pub fn compile_vrl_script(
         &self,
     ) -> anyhow::Result<(vrl::compiler::Program, vrl::compiler::TimeZone)> {
-        use anyhow::Context;
-        let timezone = vrl::compiler::TimeZone::parse(&self.timezone).with_context(|| {
-            format!(
-                "failed to parse timezone: `{}`. timezone must be a valid name \
-            in the TZ database: https://en.wikipedia.org/wiki/List_of_tz_database_time_zones",
-                self.timezone,
-            )
-        })?;
         // Append "\n." to the script to return the entire document and not only the modified
         // fields.
         let vrl_script = self.vrl_script.clone() + "\n.";
-        let functions = vrl::stdlib::all();
-
-        let compilation_res = match vrl::compiler::compile(&vrl_script, &functions) {
-            Ok(compilation_res) => compilation_res,
-            Err(diagnostics) => {
-                let mut formatter = vrl::diagnostic::Formatter::new(&vrl_script, diagnostics);
-                formatter.enable_colors(!quickwit_common::no_color());
-                anyhow::bail!("failed to compile VRL script:\n {formatter}")
-            }
-        };
-
-        let vrl::compiler::CompilationResult {
-            program, warnings, ..
-        } = compilation_res;
-
-        if !warnings.is_empty() {
-            let mut formatter = vrl::diagnostic::Formatter::new(&vrl_script, warnings);
-            formatter.enable_colors(!quickwit_common::no_color());
-            tracing::warn!("VRL program compiled with some warnings: {formatter}");
-        }
-        Ok((program, timezone))
+        compile_vrl_program(&vrl_script, &self.timezone)
     }
 
     #[cfg(any(test, feature = "testsuite"))]
@@ -713,6 +1201,42 @@ impl TransformConfig {
     }
 }
 
+/// Compiles a VRL script to a VRL [`Program`](vrl::compiler::Program) and returns it along with
+/// the parsed timezone.
+#[cfg(feature = "vrl")]
+pub(crate) fn compile_vrl_program(
+    vrl_script: &str,
+    timezone: &str,
+) -> anyhow::Result<(vrl::compiler::Program, vrl::compiler::TimeZone)> {
+    let timezone = vrl::compiler::TimeZone::parse(timezone).with_context(|| {
+        format!(
+            "failed to parse timezone: `{timezone}`. timezone must be a valid name in the TZ \
+             database: https://en.wikipedia.org/wiki/List_of_tz_database_time_zones",
+        )
+    })?;
+    let functions = vrl::stdlib::all();
+
+    let compilation_res = match vrl::compiler::compile(vrl_script, &functions) {
+        Ok(compilation_res) => compilation_res,
+        Err(diagnostics) => {
+            let mut formatter = vrl::diagnostic::Formatter::new(vrl_script, diagnostics);
+            formatter.enable_colors(!quickwit_common::no_color());
+            anyhow::bail!("failed to compile VRL script:\n {formatter}")
+        }
+    };
+
+    let vrl::compiler::CompilationResult {
+        program, warnings, ..
+    } = compilation_res;
+
+    if !warnings.is_empty() {
+        let mut formatter = vrl::diagnostic::Formatter::new(vrl_script, warnings);
+        formatter.enable_colors(!quickwit_common::no_color());
+        tracing::warn!("VRL program compiled with some warnings: {formatter}");
+    }
+    Ok((program, timezone))
+}
+
 #[cfg(test)]
 mod tests {
     use std::num::NonZero;
@@ -750,12 +1274,19 @@ mod tests {
                 client_log_level: None,
                 client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                 enable_backfill_mode: false,
+                include_metadata: false,
+                metadata_field_prefix: "kafka_".to_string(),
+                start_from: None,
             }),
             transform_config: Some(TransformConfig {
                 vrl_script: ".message = downcase(string!(.message))".to_string(),
                 timezone: "local".to_string(),
             }),
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         assert_eq!(source_config, expected_source_config);
         assert_eq!(source_config.num_pipelines.get(), 2);
@@ -769,6 +1300,9 @@ mod tests {
                 client_log_level: None,
                 client_params: json!(null),
                 enable_backfill_mode: false,
+                include_metadata: false,
+                metadata_field_prefix: "kafka_".to_string(),
+                start_from: None,
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -783,6 +1317,9 @@ mod tests {
                 client_log_level: Some("info".to_string()),
                 client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                 enable_backfill_mode: false,
+                include_metadata: false,
+                metadata_field_prefix: "kafka_".to_string(),
+                start_from: None,
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -806,6 +1343,9 @@ mod tests {
                     client_log_level: None,
                     client_params: json!(null),
                     enable_backfill_mode: false,
+                    include_metadata: false,
+                    metadata_field_prefix: "kafka_".to_string(),
+                    start_from: None,
                 }
             );
         }
@@ -824,11 +1364,102 @@ mod tests {
                     client_log_level: Some("info".to_string()),
                     client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                     enable_backfill_mode: true,
+                    include_metadata: false,
+                    metadata_field_prefix: "kafka_".to_string(),
+                    start_from: None,
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                    topic: my-topic
+                    include_metadata: true
+                    metadata_field_prefix: msg_
+                    start_from:
+                        timestamp: 1700000000000
+                "#;
+            let params = serde_yaml::from_str::<KafkaSourceParams>(yaml).unwrap();
+            assert_eq!(
+                params,
+                KafkaSourceParams {
+                    topic: "my-topic".to_string(),
+                    client_log_level: None,
+                    client_params: json!(null),
+                    enable_backfill_mode: false,
+                    include_metadata: true,
+                    metadata_field_prefix: "msg_".to_string(),
+                    start_from: Some(KafkaStartFrom::Timestamp(1_700_000_000_000)),
                 }
             );
+            let params_yaml = serde_yaml::to_string(&params).unwrap();
+            assert_eq!(
+                serde_yaml::from_str::<KafkaSourceParams>(&params_yaml).unwrap(),
+                params,
+            );
+        }
+        {
+            let yaml = r#"
+                    topic: my-topic
+                    start_from: latest
+                "#;
+            let params = serde_yaml::from_str::<KafkaSourceParams>(yaml).unwrap();
+            assert_eq!(params.start_from, Some(KafkaStartFrom::Latest));
         }
     }
 
+    #[test]
+    fn test_kafka_source_params_validation() {
+        let load_source_config = |source_config_json: JsonValue| {
+            let file_content = serde_json::to_vec(&source_config_json).unwrap();
+            load_source_config_from_user_config(ConfigFormat::Json, &file_content)
+        };
+        load_source_config(json!({
+            "version": "0.9",
+            "source_id": "kafka-source",
+            "source_type": "kafka",
+            "params": {
+                "topic": "my-topic",
+                "client_params": {"isolation.level": "read_committed"},
+                "include_metadata": true,
+                "start_from": "earliest",
+            },
+        }))
+        .unwrap();
+
+        let error = load_source_config(json!({
+            "version": "0.9",
+            "source_id": "kafka-source",
+            "source_type": "kafka",
+            "params": {
+                "topic": "my-topic",
+                "client_params": {"isolation.level": "read_everything"},
+            },
+        }))
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("invalid Kafka `isolation.level`")
+        );
+
+        load_source_config(json!({
+            "version": "0.9",
+            "source_id": "kafka-source",
+            "source_type": "kafka",
+            "params": {"topic": "my-topic", "include_metadata": true},
+            "input_format": "plain_text",
+        }))
+        .unwrap_err();
+
+        load_source_config(json!({
+            "version": "0.9",
+            "source_id": "kafka-source",
+            "source_type": "kafka",
+            "params": {"topic": "my-topic", "start_from": {"timestamp": -1}},
+        }))
+        .unwrap_err();
+    }
+
     #[tokio::test]
     async fn test_load_kinesis_source_config() {
         let source_config_filepath = get_source_config_filepath("kinesis-source.yaml");
@@ -851,6 +1482,10 @@ mod tests {
                 timezone: "local".to_string(),
             }),
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         assert_eq!(source_config, expected_source_config);
         assert_eq!(source_config.num_pipelines.get(), 1);
@@ -1357,6 +1992,10 @@ mod tests {
                 timezone: default_timezone(),
             }),
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         assert_eq!(source_config, expected_source_config);
         assert_eq!(source_config.num_pipelines.get(), 1);
@@ -1479,6 +2118,532 @@ mod tests {
         assert_eq!(source_config.input_format, SourceInputFormat::PlainText);
     }
 
+    #[test]
+    fn test_dead_letter_config_serde() {
+        {
+            let yaml = r#"
+                index_id: my-index-dlq
+            "#;
+            let dead_letter_config = serde_yaml::from_str::<DeadLetterConfig>(yaml).unwrap();
+            assert_eq!(
+                dead_letter_config,
+                DeadLetterConfig::Index("my-index-dlq".to_string())
+            );
+            let dead_letter_config_json = serde_json::to_value(dead_letter_config).unwrap();
+            assert_eq!(dead_letter_config_json, json!({"index_id": "my-index-dlq"}));
+        }
+        {
+            let yaml = r#"
+                storage_uri: s3://my-bucket/dead-letters
+            "#;
+            let dead_letter_config = serde_yaml::from_str::<DeadLetterConfig>(yaml).unwrap();
+            let uri = Uri::for_test("s3://my-bucket/dead-letters");
+            assert_eq!(dead_letter_config, DeadLetterConfig::Storage(uri));
+        }
+        {
+            let yaml = r#"
+                index_id: my-index-dlq
+                storage_uri: s3://my-bucket/dead-letters
+            "#;
+            let error = serde_yaml::from_str::<DeadLetterConfig>(yaml).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "dead letter parameters `index_id` and `storage_uri` are mutually exclusive"
+            );
+        }
+        {
+            let error = serde_json::from_str::<DeadLetterConfig>("{}").unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "either `index_id` or `storage_uri` must be specified as dead letter parameters"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_source_config_with_dead_letter() {
+        let file_content = r#"{
+            "version": "0.9",
+            "source_id": "logs-file-source",
+            "source_type": "file",
+            "params": {
+              "filepath": "s3://mybucket/logs.json"
+            },
+            "dead_letter": {
+              "index_id": "logs-dlq"
+            }
+        }"#;
+        let source_config =
+            load_source_config_from_user_config(ConfigFormat::Json, file_content.as_bytes())
+                .unwrap();
+        assert_eq!(
+            source_config.dead_letter_config,
+            Some(DeadLetterConfig::Index("logs-dlq".to_string()))
+        );
+
+        let invalid_file_content = file_content.replace("logs-dlq", "_logs-dlq");
+        let error = load_source_config_from_user_config(
+            ConfigFormat::Json,
+            invalid_file_content.as_bytes(),
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("dead letter index ID `_logs-dlq` is invalid")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_source_config_with_index_routing() {
+        let file_content = r#"{
+            "version": "0.9",
+            "source_id": "kafka-source",
+            "source_type": "kafka",
+            "params": {
+              "topic": "logs"
+            },
+            "index_routing": {
+              "index_id": "logs-{kubernetes.namespace}"
+            }
+        }"#;
+        let source_config =
+            load_source_config_from_user_config(ConfigFormat::Json, file_content.as_bytes())
+                .unwrap();
+        assert_eq!(
+            source_config.index_routing_config,
+            Some(IndexRoutingConfig {
+                index_id: "logs-{kubernetes.namespace}".to_string(),
+                fallback_to_source_index: false,
+            })
+        );
+        let invalid_file_content = file_content.replace("{kubernetes.namespace}", "namespace");
+        let error = load_source_config_from_user_config(
+            ConfigFormat::Json,
+            invalid_file_content.as_bytes(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "index ID template `logs-namespace` must contain at least one placeholder"
+        );
+        let file_content = r#"{
+            "version": "0.9",
+            "source_id": "webhook-source",
+            "source_type": "webhook",
+            "params": {},
+            "index_routing": {
+              "index_id": "logs-{namespace}"
+            }
+        }"#;
+        let error =
+            load_source_config_from_user_config(ConfigFormat::Json, file_content.as_bytes())
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "index routing is not supported by webhook sources"
+        );
+    }
+
+    #[test]
+    fn test_index_id_template() {
+        for invalid_template in [
+            "logs",
+            "logs-{namespace",
+            "logs-{}",
+            "logs-{a..b}",
+            "logs/{ns}",
+        ] {
+            IndexIdTemplate::parse(invalid_template).unwrap_err();
+        }
+        let template = IndexIdTemplate::parse("logs-{kubernetes.namespace}-{level}").unwrap();
+        let json_obj = |json: JsonValue| -> JsonObject {
+            let JsonValue::Object(json_obj) = json else {
+                panic!("expected a JSON object");
+            };
+            json_obj
+        };
+        let doc = json_obj(json!({"kubernetes": {"namespace": "team-a"}, "level": "info"}));
+        assert_eq!(template.render(&doc).unwrap(), "logs-team-a-info");
+
+        let doc = json_obj(json!({"kubernetes": {"namespace": "Team A/B"}, "level": 3}));
+        assert_eq!(template.render(&doc).unwrap(), "logs-Team-A-B-3");
+
+        let doc = json_obj(json!({"kubernetes": {"namespace": "team-a"}}));
+        assert!(template.render(&doc).is_none());
+
+        let doc = json_obj(json!({"kubernetes": {"namespace": ""}, "level": "info"}));
+        assert!(template.render(&doc).is_none());
+
+        let doc = json_obj(json!({"kubernetes": {"namespace": ["team-a"]}, "level": "info"}));
+        assert!(template.render(&doc).is_none());
+
+        let template = IndexIdTemplate::parse("{namespace}").unwrap();
+        let doc = json_obj(json!({"namespace": "1-team"}));
+        assert!(template.render(&doc).is_none());
+    }
+
+    #[test]
+    fn test_source_config_schema_input_formats() {
+        let load_source_config = |input_format: &str, schema: JsonValue| {
+            let mut source_config_json = json!({
+                "version": "0.9",
+                "source_id": "kafka-source",
+                "source_type": "kafka",
+                "params": {
+                    "topic": "my-topic"
+                },
+                "input_format": input_format,
+            });
+            if !schema.is_null() {
+                source_config_json["schema"] = schema;
+            }
+            let file_content = serde_json::to_vec(&source_config_json).unwrap();
+            load_source_config_from_user_config(ConfigFormat::Json, &file_content)
+        };
+        {
+            let source_config =
+                load_source_config("avro", json!({"registry_url": "http://localhost:8081"}))
+                    .unwrap();
+            assert_eq!(source_config.input_format, SourceInputFormat::Avro);
+            assert_eq!(
+                source_config.schema_config.unwrap().registry_url.unwrap(),
+                "http://localhost:8081"
+            );
+        }
+        {
+            let avro_schema = r#"{"type": "record", "name": "Event", "fields": []}"#;
+            let source_config = load_source_config("avro", json!({"schema": avro_schema})).unwrap();
+            assert_eq!(
+                source_config.schema_config.unwrap().schema.unwrap(),
+                avro_schema
+            );
+        }
+        {
+            let source_config = load_source_config(
+                "protobuf",
+                json!({
+                    "schema_uri": "s3://my-bucket/descriptors.binpb",
+                    "message_type": "my.package.Event",
+                }),
+            )
+            .unwrap();
+            assert_eq!(source_config.input_format, SourceInputFormat::Protobuf);
+        }
+        {
+            let error = load_source_config("avro", JsonValue::Null).unwrap_err();
+            assert!(error.to_string().contains("require schema parameters"));
+        }
+        {
+            let error =
+                load_source_config("json", json!({"registry_url": "http://localhost:8081"}))
+                    .unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains("schema parameters are only supported")
+            );
+        }
+        {
+            let error = load_source_config(
+                "avro",
+                json!({
+                    "registry_url": "http://localhost:8081",
+                    "schema_uri": "s3://my-bucket/schema.avsc",
+                }),
+            )
+            .unwrap_err();
+            assert!(error.to_string().contains("exactly one of"));
+        }
+        {
+            let error = load_source_config(
+                "protobuf",
+                json!({"schema_uri": "s3://my-bucket/descriptors.binpb"}),
+            )
+            .unwrap_err();
+            assert!(error.to_string().contains("`message_type` is required"));
+        }
+        {
+            let error =
+                load_source_config("protobuf", json!({"schema": "message Event {}"})).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains("inline schemas are not supported")
+            );
+        }
+        {
+            let error =
+                load_source_config("avro", json!({"registry_url": "localhost:8081"})).unwrap_err();
+            assert!(error.to_string().contains("must start with `http://`"));
+        }
+    }
+
+    #[test]
+    fn test_source_config_line_input_formats() {
+        let load_source_config = |source_config_json: JsonValue| {
+            let file_content = serde_json::to_vec(&source_config_json).unwrap();
+            load_source_config_from_user_config(ConfigFormat::Json, &file_content)
+        };
+        {
+            let source_config = load_source_config(json!({
+                "version": "0.9",
+                "source_id": "file-source",
+                "source_type": "file",
+                "params": {"filepath": "s3://my-bucket/logs.csv"},
+                "input_format": "csv",
+                "csv": {"has_header": true, "delimiter": ";"},
+            }))
+            .unwrap();
+            assert!(source_config.has_csv_header());
+            let Some(LineFormatConfig::Csv(csv_config)) = &source_config.line_format_config else {
+                panic!("expected CSV parameters");
+            };
+            assert_eq!(csv_config.delimiter, ';');
+
+            let source_config_json = serde_json::to_value(&source_config).unwrap();
+            assert_eq!(source_config_json["csv"]["has_header"], true);
+            assert!(source_config_json.get("grok").is_none());
+        }
+        {
+            let error = load_source_config(json!({
+                "version": "0.9",
+                "source_id": "kafka-source",
+                "source_type": "kafka",
+                "params": {"topic": "my-topic"},
+                "input_format": "csv",
+                "csv": {"has_header": true},
+            }))
+            .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "CSV header rows are only supported by the file and stdin sources"
+            );
+        }
+        {
+            let source_config = load_source_config(json!({
+                "version": "0.9",
+                "source_id": "kafka-source",
+                "source_type": "kafka",
+                "params": {"topic": "my-topic"},
+                "input_format": "grok",
+                "grok": {"pattern": "%{SYSLOGLINE}"},
+            }))
+            .unwrap();
+            assert!(!source_config.has_csv_header());
+            assert!(matches!(
+                source_config.line_format_config,
+                Some(LineFormatConfig::Grok(_))
+            ));
+        }
+        {
+            let source_config = load_source_config(json!({
+                "version": "0.9",
+                "source_id": "kafka-source",
+                "source_type": "kafka",
+                "params": {"topic": "my-topic"},
+                "input_format": "logfmt",
+            }))
+            .unwrap();
+            assert_eq!(source_config.input_format, SourceInputFormat::Logfmt);
+            assert!(source_config.line_format_config.is_none());
+        }
+        {
+            let error = load_source_config(json!({
+                "version": "0.9",
+                "source_id": "kafka-source",
+                "source_type": "kafka",
+                "params": {"topic": "my-topic"},
+                "input_format": "regex",
+            }))
+            .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "the `regex` input format requires `regex` parameters"
+            );
+        }
+        {
+            let error = load_source_config(json!({
+                "version": "0.9",
+                "source_id": "kafka-source",
+                "source_type": "kafka",
+                "params": {"topic": "my-topic"},
+                "input_format": "json",
+                "regex": {"pattern": "(?P<message>.*)"},
+            }))
+            .unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains("only supported with the input format")
+            );
+        }
+        {
+            let error = load_source_config(json!({
+                "version": "0.9",
+                "source_id": "kafka-source",
+                "source_type": "kafka",
+                "params": {"topic": "my-topic"},
+                "input_format": "grok",
+                "grok": {"pattern": "%{NOPE:message}"},
+            }))
+            .unwrap_err();
+            assert_eq!(error.to_string(), "unknown grok pattern `NOPE`");
+        }
+    }
+
+    #[test]
+    fn test_source_config_columnar_files() {
+        let load_source_config = |source_config_json: JsonValue| {
+            let file_content = serde_json::to_vec(&source_config_json).unwrap();
+            load_source_config_from_user_config(ConfigFormat::Json, &file_content)
+        };
+        load_source_config(json!({
+            "version": "0.9",
+            "source_id": "file-source",
+            "source_type": "file",
+            "params": {"filepath": "s3://my-bucket/events.parquet"},
+        }))
+        .unwrap();
+
+        let error = load_source_config(json!({
+            "version": "0.9",
+            "source_id": "file-source",
+            "source_type": "file",
+            "params": {"filepath": "s3://my-bucket/events.arrow"},
+            "input_format": "plain_text",
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the rows of Arrow IPC and Parquet files are read as JSON objects: `input_format` \
+             must be `json`"
+        );
+    }
+
+    #[test]
+    fn test_source_config_directory() {
+        let load_source_config = |source_config_json: JsonValue| {
+            let file_content = serde_json::to_vec(&source_config_json).unwrap();
+            load_source_config_from_user_config(ConfigFormat::Json, &file_content)
+        };
+        let source_config = load_source_config(json!({
+            "version": "0.9",
+            "source_id": "directory-source",
+            "source_type": "directory",
+            "params": {"glob": "/var/log/app/*.log"},
+            "input_format": "plain_text",
+        }))
+        .unwrap();
+        assert_eq!(source_config.source_type(), SourceType::Directory);
+        assert_eq!(
+            source_config.source_params,
+            SourceParams::Directory(DirectorySourceParams {
+                glob: "/var/log/app/*.log".to_string(),
+            })
+        );
+        let error = load_source_config(json!({
+            "version": "0.9",
+            "source_id": "directory-source",
+            "source_type": "directory",
+            "params": {"glob": "var/log/app/*.log"},
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "directory source glob `var/log/app/*.log` must be an absolute path"
+        );
+        load_source_config(json!({
+            "version": "0.9",
+            "source_id": "directory-source",
+            "source_type": "directory",
+            "params": {"glob": "/var/log/[app/*.log"},
+        }))
+        .unwrap_err();
+
+        load_source_config(json!({
+            "version": "0.9",
+            "source_id": "directory-source",
+            "source_type": "directory",
+            "num_pipelines": 2,
+            "params": {"glob": "/var/log/app/*.log"},
+        }))
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_source_config_webhook() {
+        let load_source_config = |source_config_json: JsonValue| {
+            let file_content = serde_json::to_vec(&source_config_json).unwrap();
+            load_source_config_from_user_config(ConfigFormat::Json, &file_content)
+        };
+        let source_config = load_source_config(json!({
+            "version": "0.9",
+            "source_id": "github-webhook",
+            "source_type": "webhook",
+            "params": {
+                "hmac": {
+                    "header": "X-Hub-Signature-256",
+                    "secret": "my-secret",
+                    "prefix": "sha256=",
+                },
+                "max_requests_per_sec": 100,
+            },
+        }))
+        .unwrap();
+        assert_eq!(source_config.source_type(), SourceType::Webhook);
+        assert_eq!(
+            source_config.source_params,
+            SourceParams::Webhook(WebhookSourceParams {
+                hmac: Some(WebhookHmacParams {
+                    header: "X-Hub-Signature-256".to_string(),
+                    secret: "my-secret".to_string(),
+                    algorithm: WebhookHmacAlgorithm::Sha256,
+                    prefix: "sha256=".to_string(),
+                    encoding: WebhookSignatureEncoding::Hex,
+                }),
+                max_requests_per_sec: NonZeroU32::new(100),
+            })
+        );
+        let source_config = load_source_config(json!({
+            "version": "0.9",
+            "source_id": "webhook",
+            "source_type": "webhook",
+            "params": {},
+        }))
+        .unwrap();
+        assert_eq!(
+            source_config.source_params,
+            SourceParams::Webhook(WebhookSourceParams::default())
+        );
+        let error = load_source_config(json!({
+            "version": "0.9",
+            "source_id": "webhook",
+            "source_type": "webhook",
+            "params": {"hmac": {"header": "X-Signature", "secret": ""}},
+        }))
+        .unwrap_err();
+        assert_eq!(error.to_string(), "webhook HMAC secret must not be empty");
+
+        load_source_config(json!({
+            "version": "0.9",
+            "source_id": "webhook",
+            "source_type": "webhook",
+            "params": {},
+            "input_format": "plain_text",
+        }))
+        .unwrap_err();
+
+        load_source_config(json!({
+            "version": "0.9",
+            "source_id": "webhook",
+            "source_type": "webhook",
+            "params": {},
+            "transform": {"script": ".message = downcase(string!(.message))"},
+        }))
+        .unwrap_err();
+    }
+
     #[tokio::test]
     async fn test_update_kafka_source_config() {
         let source_config_filepath = get_source_config_filepath("kafka-source.json");
@@ -1502,12 +2667,19 @@ mod tests {
                     client_log_level: None,
                     client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                     enable_backfill_mode: false,
+                    include_metadata: false,
+                    metadata_field_prefix: "kafka_".to_string(),
+                    start_from: None,
                 }),
                 transform_config: Some(TransformConfig {
                     vrl_script: ".message = downcase(string!(.message))".to_string(),
                     timezone: "local".to_string(),
                 }),
                 input_format: SourceInputFormat::Json,
+                dead_letter_config: None,
+                schema_config: None,
+                line_format_config: None,
+                index_routing_config: None,
             };
             assert_eq!(new_source_config, expected_source_config);
             assert_eq!(new_source_config.num_pipelines.get(), 2);
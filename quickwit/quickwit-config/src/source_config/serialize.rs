@@ -18,7 +18,10 @@ use anyhow::{bail, ensure};
 use quickwit_proto::types::SourceId;
 use serde::{Deserialize, Serialize};
 
-use super::{RESERVED_SOURCE_IDS, TransformConfig};
+use super::{
+    CsvConfig, DeadLetterConfig, DeadLetterConfigForSerde, GrokConfig, IndexRoutingConfig,
+    LineFormatConfig, RESERVED_SOURCE_IDS, RegexConfig, SchemaConfig, TransformConfig,
+};
 use crate::{
     ConfigFormat, FileSourceParams, SourceConfig, SourceInputFormat, SourceParams,
     validate_identifier,
@@ -110,10 +113,41 @@ impl SourceConfigForSerialization {
                      local-ingest`"
                 );
             }
-            SourceParams::File(_)
-            | SourceParams::Kafka(_)
-            | SourceParams::Kinesis(_)
-            | SourceParams::Pulsar(_) => {
+            SourceParams::File(FileSourceParams::Filepath(file_uri))
+                if matches!(file_uri.extension(), Some("arrow" | "parquet")) =>
+            {
+                if self.input_format != SourceInputFormat::Json {
+                    bail!(
+                        "the rows of Arrow IPC and Parquet files are read as JSON objects: \
+                         `input_format` must be `json`"
+                    );
+                }
+            }
+            SourceParams::Directory(params) => {
+                params.validate()?;
+            }
+            SourceParams::Webhook(params) => {
+                if self.input_format != SourceInputFormat::Json {
+                    bail!("webhook sources receive JSON documents: `input_format` must be `json`");
+                }
+                if self.transform.is_some() {
+                    bail!(
+                        "webhook sources forward their documents to the ingest API source of the \
+                         index: configure the VRL transform on the ingest API source instead"
+                    );
+                }
+                params.validate()?;
+            }
+            SourceParams::Kafka(params) => {
+                if params.include_metadata && self.input_format != SourceInputFormat::Json {
+                    bail!(
+                        "Kafka message metadata can only be added to JSON documents: \
+                         `input_format` must be `json`"
+                    );
+                }
+                params.validate()?;
+            }
+            SourceParams::File(_) | SourceParams::Kinesis(_) | SourceParams::Pulsar(_) => {
                 // TODO consider any validation opportunity
             }
             SourceParams::PubSub(_)
@@ -146,6 +180,79 @@ impl SourceConfigForSerialization {
             }
             transform_config.validate_vrl_script()?;
         }
+        match &self.schema {
+            Some(schema_config) if self.input_format.requires_schema() => {
+                schema_config.validate(self.input_format)?;
+            }
+            Some(_) => {
+                bail!(
+                    "schema parameters are only supported with the `avro` and `protobuf` input \
+                     formats"
+                );
+            }
+            None if self.input_format.requires_schema() => {
+                bail!("the `avro` and `protobuf` input formats require schema parameters");
+            }
+            None => {}
+        }
+        let line_format_config = match (self.input_format, self.csv, self.grok, self.regex) {
+            (SourceInputFormat::Csv, Some(csv_config), None, None) => {
+                csv_config.validate()?;
+
+                if csv_config.has_header
+                    && !matches!(
+                        self.source_params,
+                        SourceParams::File(_) | SourceParams::Stdin
+                    )
+                {
+                    bail!("CSV header rows are only supported by the file and stdin sources");
+                }
+                Some(LineFormatConfig::Csv(csv_config))
+            }
+            (SourceInputFormat::Grok, None, Some(grok_config), None) => {
+                grok_config.compile()?;
+                Some(LineFormatConfig::Grok(grok_config))
+            }
+            (SourceInputFormat::Regex, None, None, Some(regex_config)) => {
+                regex_config.compile()?;
+                Some(LineFormatConfig::Regex(regex_config))
+            }
+            (SourceInputFormat::Csv, None, _, _) => {
+                bail!("the `csv` input format requires `csv` parameters");
+            }
+            (SourceInputFormat::Grok, _, None, _) => {
+                bail!("the `grok` input format requires `grok` parameters");
+            }
+            (SourceInputFormat::Regex, _, _, None) => {
+                bail!("the `regex` input format requires `regex` parameters");
+            }
+            (_, None, None, None) => None,
+            _ => {
+                bail!(
+                    "`csv`, `grok`, and `regex` parameters are only supported with the input \
+                     format of the same name"
+                );
+            }
+        };
+        if let Some(DeadLetterConfig::Index(dead_letter_index_id)) = &self.dead_letter {
+            validate_identifier("dead letter index", dead_letter_index_id)?;
+        }
+        if let Some(index_routing_config) = &self.index_routing {
+            if matches!(
+                self.source_params,
+                SourceParams::Ingest
+                    | SourceParams::IngestApi
+                    | SourceParams::IngestCli
+                    | SourceParams::Stdin
+                    | SourceParams::Webhook(_)
+            ) {
+                bail!(
+                    "index routing is not supported by {} sources",
+                    self.source_params.source_type()
+                );
+            }
+            index_routing_config.index_id_template()?;
+        }
 
         Ok(SourceConfig {
             source_id: self.source_id,
@@ -154,20 +261,41 @@ impl SourceConfigForSerialization {
             source_params: self.source_params,
             transform_config: self.transform,
             input_format: self.input_format,
+            dead_letter_config: self.dead_letter,
+            schema_config: self.schema,
+            line_format_config,
+            index_routing_config: self.index_routing,
         })
     }
 }
 
 impl From<SourceConfig> for SourceConfigV0_8 {
     fn from(source_config: SourceConfig) -> Self {
-        SourceConfigV0_8 {
+        let mut source_config_v0_8 = SourceConfigV0_8 {
             source_id: source_config.source_id,
             num_pipelines: source_config.num_pipelines.get(),
             enabled: source_config.enabled,
             source_params: source_config.source_params,
             transform: source_config.transform_config,
             input_format: source_config.input_format,
+            dead_letter: source_config.dead_letter_config,
+            schema: source_config.schema_config,
+            csv: None,
+            grok: None,
+            regex: None,
+            index_routing: source_config.index_routing_config,
+        };
+        match source_config.line_format_config {
+            Some(LineFormatConfig::Csv(csv_config)) => source_config_v0_8.csv = Some(csv_config),
+            Some(LineFormatConfig::Grok(grok_config)) => {
+                source_config_v0_8.grok = Some(grok_config)
+            }
+            Some(LineFormatConfig::Regex(regex_config)) => {
+                source_config_v0_8.regex = Some(regex_config)
+            }
+            None => {}
         }
+        source_config_v0_8
     }
 }
 
@@ -250,6 +378,31 @@ pub struct SourceConfigV0_8 {
     // Denotes the input data format.
     #[serde(default)]
     pub input_format: SourceInputFormat,
+
+    /// Destination of the documents rejected by the doc processor.
+    #[schema(value_type = Option<DeadLetterConfigForSerde>)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dead_letter: Option<DeadLetterConfig>,
+
+    /// Schema parameters of the `avro` and `protobuf` input formats.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<SchemaConfig>,
+
+    /// Parameters of the `csv` input format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub csv: Option<CsvConfig>,
+
+    /// Parameters of the `grok` input format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grok: Option<GrokConfig>,
+
+    /// Parameters of the `regex` input format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<RegexConfig>,
+
+    /// Routes the documents of the source to other indexes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_routing: Option<IndexRoutingConfig>,
 }
 
 impl From<SourceConfigV0_7> for SourceConfigV0_8 {
@@ -270,6 +423,12 @@ impl From<SourceConfigV0_7> for SourceConfigV0_8 {
             source_params,
             transform,
             input_format,
+            dead_letter: None,
+            schema: None,
+            csv: None,
+            grok: None,
+            regex: None,
+            index_routing: None,
         }
     }
 }
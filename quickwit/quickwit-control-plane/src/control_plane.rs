@@ -1403,6 +1403,9 @@ mod tests {
                 topic: "test-topic".to_string(),
                 client_log_level: None,
                 enable_backfill_mode: false,
+                include_metadata: false,
+                metadata_field_prefix: "kafka_".to_string(),
+                start_from: None,
                 client_params: json!({}),
             }),
         );
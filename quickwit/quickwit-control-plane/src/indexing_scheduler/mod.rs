@@ -211,7 +211,8 @@ fn get_sources_to_schedule(model: &ControlPlaneModel) -> Vec<SourceToSchedule> {
             | SourceParams::IngestCli
             | SourceParams::Stdin
             | SourceParams::Void(_)
-            | SourceParams::Vec(_) => { // We don't need to schedule those.
+            | SourceParams::Vec(_)
+            | SourceParams::Webhook(_) => { // We don't need to schedule those.
             }
 
             SourceParams::IngestApi => {
@@ -248,6 +249,14 @@ fn get_sources_to_schedule(model: &ControlPlaneModel) -> Vec<SourceToSchedule> {
                     params_fingerprint,
                 });
             }
+            SourceParams::Directory(_) => {
+                // The directory source reads the files of the node it runs on.
+                sources.push(SourceToSchedule {
+                    source_uid,
+                    source_type: SourceToScheduleType::PerIndexer,
+                    params_fingerprint,
+                });
+            }
             SourceParams::Kafka(_)
             | SourceParams::Kinesis(_)
             | SourceParams::PubSub(_)
@@ -696,7 +705,9 @@ mod tests {
     use std::str::FromStr;
 
     use proptest::{prop_compose, proptest};
-    use quickwit_config::{IndexConfig, KafkaSourceParams, SourceConfig, SourceParams};
+    use quickwit_config::{
+        DirectorySourceParams, IndexConfig, KafkaSourceParams, SourceConfig, SourceParams,
+    };
     use quickwit_metastore::IndexMetadata;
     use quickwit_proto::types::{IndexUid, PipelineUid, ShardId, SourceUid};
 
@@ -872,6 +883,9 @@ mod tests {
             client_log_level: None,
             client_params: serde_json::json!({}),
             enable_backfill_mode: false,
+            include_metadata: false,
+            metadata_field_prefix: "kafka_".to_string(),
+            start_from: None,
         };
         let index_metadata = IndexMetadata::for_test("test-index", "ram:///test-index");
         let index_uid = index_metadata.index_uid.clone();
@@ -886,6 +900,10 @@ mod tests {
                     source_params: SourceParams::Kafka(kafka_source_params.clone()),
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_config: None,
+                    schema_config: None,
+                    line_format_config: None,
+                    index_routing_config: None,
                 },
             )
             .unwrap();
@@ -899,6 +917,10 @@ mod tests {
                     source_params: SourceParams::Kafka(kafka_source_params.clone()),
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_config: None,
+                    schema_config: None,
+                    line_format_config: None,
+                    index_routing_config: None,
                 },
             )
             .unwrap();
@@ -913,6 +935,10 @@ mod tests {
                     source_params: SourceParams::IngestApi,
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_config: None,
+                    schema_config: None,
+                    line_format_config: None,
+                    index_routing_config: None,
                 },
             )
             .unwrap();
@@ -927,6 +953,10 @@ mod tests {
                     source_params: SourceParams::Ingest,
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_config: None,
+                    schema_config: None,
+                    line_format_config: None,
+                    index_routing_config: None,
                 },
             )
             .unwrap();
@@ -942,6 +972,10 @@ mod tests {
                     source_params: SourceParams::Ingest,
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_config: None,
+                    schema_config: None,
+                    line_format_config: None,
+                    index_routing_config: None,
                 },
             )
             .unwrap();
@@ -956,6 +990,29 @@ mod tests {
                     source_params: SourceParams::IngestCli,
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_config: None,
+                    schema_config: None,
+                    line_format_config: None,
+                    index_routing_config: None,
+                },
+            )
+            .unwrap();
+        model
+            .add_source(
+                &index_uid,
+                SourceConfig {
+                    source_id: "directory".to_string(),
+                    num_pipelines: NonZeroUsize::MIN,
+                    enabled: true,
+                    source_params: SourceParams::Directory(DirectorySourceParams {
+                        glob: "/var/log/*.log".to_string(),
+                    }),
+                    transform_config: None,
+                    input_format: Default::default(),
+                    dead_letter_config: None,
+                    schema_config: None,
+                    line_format_config: None,
+                    index_routing_config: None,
                 },
             )
             .unwrap();
@@ -968,7 +1025,41 @@ mod tests {
         };
         model.insert_shards(&index_uid, &"ingest_v2".to_string(), vec![shard]);
         let shards: Vec<SourceToSchedule> = get_sources_to_schedule(&model);
-        assert_eq!(shards.len(), 3);
+        assert_eq!(shards.len(), 4);
+
+        let directory_source = shards
+            .iter()
+            .find(|source| source.source_uid.source_id == "directory")
+            .unwrap();
+        assert!(matches!(
+            directory_source.source_type,
+            SourceToScheduleType::PerIndexer
+        ));
+    }
+
+    #[test]
+    fn test_build_physical_indexing_plan_per_indexer_source() {
+        let source_uid = SourceUid {
+            index_uid: IndexUid::for_test("index-1", 0),
+            source_id: "directory".to_string(),
+        };
+        let sources = [SourceToSchedule {
+            source_uid: source_uid.clone(),
+            source_type: SourceToScheduleType::PerIndexer,
+            params_fingerprint: 0,
+        }];
+        let mut indexer_max_loads = FnvHashMap::default();
+        indexer_max_loads.insert("indexer1".to_string(), mcpu(3_000));
+        indexer_max_loads.insert("indexer2".to_string(), mcpu(3_000));
+        let shard_locations = ShardLocations::default();
+        let physical_plan =
+            build_physical_indexing_plan(&sources[..], &indexer_max_loads, None, &shard_locations);
+
+        for indexer_id in ["indexer1", "indexer2"] {
+            let indexing_tasks = physical_plan.indexer(indexer_id).unwrap();
+            assert_eq!(indexing_tasks.len(), 1);
+            assert_eq!(indexing_tasks[0].source_id, source_uid.source_id);
+        }
     }
 
     #[test]
@@ -1096,6 +1187,9 @@ mod tests {
                 "bootstrap.servers": "localhost:9092",
             }),
             enable_backfill_mode: true,
+            include_metadata: false,
+            metadata_field_prefix: "kafka_".to_string(),
+            start_from: None,
         })
     }
 
@@ -1111,6 +1205,10 @@ mod tests {
               source_params: kafka_source_params_for_test(),
               transform_config: None,
               input_format: SourceInputFormat::Json,
+              dead_letter_config: None,
+              schema_config: None,
+              line_format_config: None,
+              index_routing_config: None,
           })
       }
     }
@@ -52,7 +52,7 @@ fn populate_problem(
     problem: &mut SchedulingProblem,
 ) -> Option<SourceOrd> {
     match &source.source_type {
-        SourceToScheduleType::IngestV1 => {
+        SourceToScheduleType::IngestV1 | SourceToScheduleType::PerIndexer => {
             // TODO ingest v1 is scheduled differently
             None
         }
@@ -140,8 +140,9 @@ fn convert_physical_plan_to_solution(
                             // solutions
                             indexer_assignment.add_shards(source_ord, 1);
                         }
-                        SourceToScheduleType::IngestV1 => {
-                            // Ingest V1 is not part of the logical placement algorithm.
+                        SourceToScheduleType::IngestV1 | SourceToScheduleType::PerIndexer => {
+                            // Ingest V1 and per-indexer sources are not part of the logical
+                            // placement algorithm.
                         }
                     }
                 }
@@ -169,6 +170,9 @@ pub enum SourceToScheduleType {
     },
     // deprecated
     IngestV1,
+    // One pipeline runs on every indexer, like ingest V1. Used by sources reading node-local
+    // data, such as the directory source.
+    PerIndexer,
 }
 
 fn compute_max_num_shards_per_pipeline(source_type: &SourceToScheduleType) -> NonZeroU32 {
@@ -190,9 +194,9 @@ fn compute_max_num_shards_per_pipeline(source_type: &SourceToScheduleType) -> No
                     NonZeroU32::MIN // also colloquially known as `1`
                 })
         }
-        SourceToScheduleType::IngestV1 | SourceToScheduleType::NonSharded { .. } => {
-            NonZeroU32::new(1u32).unwrap()
-        }
+        SourceToScheduleType::IngestV1
+        | SourceToScheduleType::PerIndexer
+        | SourceToScheduleType::NonSharded { .. } => NonZeroU32::new(1u32).unwrap(),
     }
 }
 
@@ -283,7 +287,7 @@ fn convert_scheduling_solution_to_physical_plan_single_node_single_source(
             });
             indexing_tasks
         }
-        SourceToScheduleType::IngestV1 => {
+        SourceToScheduleType::IngestV1 | SourceToScheduleType::PerIndexer => {
             // Ingest V1 is simple. One pipeline per indexer node.
             if let Some(indexing_task) = previous_tasks.first() {
                 // The pipeline already exists, let's reuse it.
@@ -319,7 +323,7 @@ fn convert_scheduling_solution_to_physical_plan_single_node(
             if let Some(source_ord) = id_to_ord_map.source_ord(&source.source_uid) {
                 indexer_assignment.num_shards(source_ord)
             } else {
-                // This can happen for IngestV1
+                // This can happen for IngestV1 and per-indexer sources
                 1u32
             };
         let source_pipelines: Vec<&IndexingTask> = previous_tasks
@@ -54,9 +54,16 @@ fn index_metadata_for_test(index_id: &str, source_id: &str, num_pipelines: usize
             "bootstrap.servers": "localhost:9092",
             }),
             enable_backfill_mode: true,
+            include_metadata: false,
+            metadata_field_prefix: "kafka_".to_string(),
+            start_from: None,
         }),
         transform_config: None,
         input_format: SourceInputFormat::Json,
+        dead_letter_config: None,
+        schema_config: None,
+        line_format_config: None,
+        index_routing_config: None,
     };
     index_metadata.add_source(kafka_source_config).unwrap();
     index_metadata
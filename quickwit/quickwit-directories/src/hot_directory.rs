@@ -476,6 +476,19 @@ fn list_index_files(index: &Index) -> tantivy::Result<HashSet<PathBuf>> {
 pub fn write_hotcache<D: Directory>(
     directory: D,
     output: &mut dyn io::Write,
+) -> tantivy::Result<()> {
+    write_hotcache_with_extra_files(directory, &[], output)
+}
+
+/// Same as [`write_hotcache`], but also registers files that are not part of the tantivy index
+/// and caches their first bytes.
+///
+/// `extra_files` lists the path of each of these files along with the number of bytes to cache
+/// from the beginning of the file.
+pub fn write_hotcache_with_extra_files<D: Directory>(
+    directory: D,
+    extra_files: &[(PathBuf, usize)],
+    output: &mut dyn io::Write,
 ) -> tantivy::Result<()> {
     // We use the caching directory here in order to defensively ensure that
     // the content of the directory that will be written in the hotcache is precisely
@@ -534,6 +547,16 @@ pub fn write_hotcache<D: Directory>(
             }
         }
     }
+    for (file_path, hot_num_bytes) in extra_files {
+        let file_slice = debug_proxy_directory.open_read(file_path)?;
+        let file_cache_builder = cache_builder.add_file(file_path, file_slice.len() as u64);
+        let hot_num_bytes = (*hot_num_bytes).min(file_slice.len());
+
+        if hot_num_bytes > 0 {
+            let bytes = file_slice.read_bytes_slice(0..hot_num_bytes)?;
+            file_cache_builder.add_bytes(bytes.as_slice(), 0);
+        }
+    }
     cache_builder.write(output)?;
     output.flush()?;
     Ok(())
@@ -42,8 +42,8 @@ use crate::query_builder::build_query;
 use crate::routing_expression::RoutingExpr;
 use crate::{
     Cardinality, DOCUMENT_SIZE_FIELD_NAME, DYNAMIC_FIELD_NAME, DocMapping, DocParsingError,
-    FIELD_PRESENCE_FIELD_NAME, Mode, ModeType, NamedField, QueryParserError, SOURCE_FIELD_NAME,
-    TokenizerEntry, WarmupInfo,
+    FIELD_PRESENCE_FIELD_NAME, Mode, ModeType, NamedField, QueryParserError,
+    QuickwitDenseVectorOptions, SOURCE_FIELD_NAME, TokenizerEntry, WarmupInfo,
 };
 
 const FIELD_PRESENCE_FIELD: Field = Field::from_field_id(0u32);
@@ -169,6 +169,9 @@ impl TryFrom<DocMapperBuilder> for DocMapper {
         let doc_mapping = builder.doc_mapping;
 
         let dynamic_field = if let Mode::Dynamic(json_options) = &doc_mapping.mode {
+            if !json_options.fields.is_empty() {
+                bail!("dynamic mapping does not support `fields` sub-mappings");
+            }
             Some(schema_builder.add_json_field(DYNAMIC_FIELD_NAME, json_options.clone()))
         } else {
             None
@@ -237,6 +240,20 @@ impl TryFrom<DocMapperBuilder> for DocMapper {
                 .iter()
                 .any(|filter| matches!(filter, crate::TokenFilterType::LowerCaser));
             tokenizer_manager.register(&tokenizer_config_entry.name, tokenizer, does_lowercasing);
+            let search_tokenizer_opt = tokenizer_config_entry
+                .config
+                .search_text_analyzer()
+                .map_err(|error| {
+                    anyhow::anyhow!(
+                        "failed to build tokenizer `{}`: {:?}",
+                        tokenizer_config_entry.name,
+                        error
+                    )
+                })?;
+            if let Some(search_tokenizer) = search_tokenizer_opt {
+                tokenizer_manager
+                    .register_search_analyzer(&tokenizer_config_entry.name, search_tokenizer);
+            }
             custom_tokenizer_names.insert(&tokenizer_config_entry.name);
         }
         validate_fields_tokenizers(&schema, &tokenizer_manager)?;
@@ -697,6 +714,19 @@ impl DocMapper {
         self.tag_field_names.clone()
     }
 
+    /// Returns the names and options of the `dense_vector` fields.
+    pub fn dense_vector_fields(&self) -> Vec<(String, QuickwitDenseVectorOptions)> {
+        self.field_mappings.dense_vector_fields()
+    }
+
+    /// Returns the options of the `dense_vector` field with the given name, if any.
+    pub fn dense_vector_field(&self, field_name: &str) -> Option<QuickwitDenseVectorOptions> {
+        match self.field_mappings.find_field_mapping_type(field_name)? {
+            FieldMappingType::DenseVector(options) => Some(options),
+            _ => None,
+        }
+    }
+
     /// Returns the maximum number of partitions.
     pub fn max_num_partitions(&self) -> NonZeroU32 {
         self.max_num_partitions
@@ -1236,6 +1266,55 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_document_with_dense_vector() {
+        let doc_mapper = DocMapperBuilder::deserialize(json!({
+            "field_mappings": [
+                {"name": "title", "type": "text"},
+                {
+                    "name": "meta",
+                    "type": "object",
+                    "field_mappings": [
+                        {"name": "embedding", "type": "dense_vector", "dims": 3}
+                    ]
+                }
+            ]
+        }))
+        .unwrap()
+        .try_build()
+        .unwrap();
+
+        let dense_vector_fields = doc_mapper.dense_vector_fields();
+        assert_eq!(dense_vector_fields.len(), 1);
+        assert_eq!(dense_vector_fields[0].0, "meta.embedding");
+        assert_eq!(dense_vector_fields[0].1.dims, 3);
+        assert!(doc_mapper.dense_vector_field("meta.embedding").is_some());
+        assert!(doc_mapper.dense_vector_field("title").is_none());
+
+        let JsonValue::Object(doc) = json!({
+            "title": "hello",
+            "meta": {"embedding": [0.5, -1.0, 2.0]}
+        }) else {
+            panic!();
+        };
+        let tantivy_doc = doc_mapper.doc_from_json_obj(doc.clone(), 0).unwrap().1;
+        let named_doc = tantivy_doc.to_named_doc(&doc_mapper.schema());
+        assert_eq!(doc_mapper.doc_to_json(named_doc.0).unwrap(), doc);
+
+        let error = doc_mapper
+            .doc_from_json_str(r#"{"meta": {"embedding": [0.5, -1.0]}}"#)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the field `meta.embedding` could not be parsed: expected vector with 3 dimensions, \
+             got 2 dimensions"
+        );
+        let error = doc_mapper
+            .validate_json_obj(&serde_json::from_str(r#"{"meta": {"embedding": 1.0}}"#).unwrap())
+            .unwrap_err();
+        assert!(error.to_string().contains("expected array of numbers"));
+    }
+
     #[test]
     fn test_parse_document_with_tag_fields() {
         let doc_mapper = r#"{
@@ -1687,6 +1766,139 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_text_field_with_sub_fields() {
+        let doc_mapper_json = r#"{
+            "field_mappings": [
+                {
+                    "name": "message",
+                    "type": "array<text>",
+                    "fields": [
+                        {
+                            "name": "keyword",
+                            "type": "text",
+                            "tokenizer": "raw",
+                            "fast": true
+                        }
+                    ]
+                }
+            ],
+            "mode": "strict"
+        }"#;
+        test_doc_from_json_test_aux(
+            doc_mapper_json,
+            "message.keyword",
+            r#"{"message": ["Disk is full", "Out of memory"]}"#,
+            vec!["Disk is full".into(), "Out of memory".into()],
+        );
+        let doc_mapper: DocMapper = serde_json::from_str(doc_mapper_json).unwrap();
+        let schema = doc_mapper.schema();
+        let sub_field_entry = schema.get_field_entry(schema.get_field("message.keyword").unwrap());
+        assert!(sub_field_entry.is_fast());
+        assert!(!sub_field_entry.is_stored());
+
+        let serialized_doc_mapper = serde_json::to_value(&doc_mapper).unwrap();
+        let deserialized_doc_mapper: DocMapper =
+            serde_json::from_value(serialized_doc_mapper).unwrap();
+        assert!(
+            deserialized_doc_mapper
+                .schema()
+                .get_field("message.keyword")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_json_field_with_sub_fields() {
+        test_doc_from_json_test_aux(
+            r#"{
+                "field_mappings": [
+                    {
+                        "name": "attributes",
+                        "type": "json",
+                        "tokenizer": "default",
+                        "fields": [
+                            {
+                                "name": "raw",
+                                "type": "json",
+                                "tokenizer": "raw"
+                            }
+                        ]
+                    }
+                ],
+                "mode": "strict"
+            }"#,
+            "attributes.raw",
+            r#"{"attributes": {"service": "Order API"}}"#,
+            vec![json!({"service": "Order API"}).into()],
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_sub_fields() {
+        let error = serde_json::from_str::<DocMapper>(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "message",
+                    "type": "text",
+                    "fields": [{"name": "length", "type": "u64"}]
+                }
+            ]
+        }"#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("sub-field `length` must be of type `text`")
+        );
+        let error = serde_json::from_str::<DocMapper>(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "message",
+                    "type": "text",
+                    "fields": [
+                        {
+                            "name": "raw",
+                            "type": "text",
+                            "fields": [{"name": "raw", "type": "text"}]
+                        }
+                    ]
+                }
+            ]
+        }"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("cannot have sub-fields"));
+        let error = serde_json::from_str::<DocMapper>(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "message",
+                    "type": "text",
+                    "fields": [{"name": "raw", "type": "text"}, {"name": "raw", "type": "text"}]
+                }
+            ]
+        }"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("duplicated sub-field `raw`"));
+        let error = serde_json::from_str::<DocMapper>(
+            r#"{
+            "mode": "dynamic",
+            "dynamic_mapping": {"fields": [{"name": "raw", "type": "json"}]}
+        }"#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("dynamic mapping does not support `fields`")
+        );
+    }
+
     #[test]
     fn test_reject_invalid_concatenate_field() {
         assert!(
@@ -2421,6 +2633,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_build_doc_mapper_with_synonyms_tokenizer() {
+        let mapper = serde_json::from_str::<DocMapper>(
+            r#"{
+            "tokenizers": [
+                {
+                    "name": "my_tokenizer",
+                    "filters": [
+                        "lower_caser",
+                        {"synonyms": {"synonyms": [["laptop", "notebook"]]}}
+                    ],
+                    "type": "simple"
+                }
+            ],
+            "field_mappings": [
+                {
+                    "name": "my_text",
+                    "type": "text",
+                    "tokenizer": "my_tokenizer"
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        let tokenizer_manager = mapper.tokenizer_manager();
+        let mut tokenizer = tokenizer_manager.get_tokenizer("my_tokenizer").unwrap();
+        let mut token_stream = tokenizer.token_stream("Laptop");
+        assert_eq!(token_stream.next().unwrap().text, "laptop");
+        assert!(token_stream.next().is_none());
+
+        let mut search_tokenizer = tokenizer_manager
+            .get_search_tokenizer("my_tokenizer")
+            .unwrap();
+        let mut token_stream = search_tokenizer.token_stream("Laptop");
+        assert_eq!(token_stream.next().unwrap().text, "laptop");
+        assert_eq!(token_stream.next().unwrap().text, "notebook");
+        assert!(token_stream.next().is_none());
+    }
+
     #[test]
     fn test_deserialize_doc_after_mapping_change_json_to_obj() {
         use serde::Deserialize;
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::convert::TryFrom;
 
 use anyhow::bail;
@@ -30,6 +31,7 @@ use tantivy::schema::{
 use super::FieldMappingType;
 use super::date_time_type::QuickwitDateTimeOptions;
 use crate::doc_mapper::field_mapping_type::QuickwitFieldType;
+use crate::vector_index::vector_to_bytes;
 use crate::{Cardinality, QW_RESERVED_FIELD_NAMES};
 
 #[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
@@ -265,6 +267,99 @@ impl Default for QuickwitIpAddrOptions {
     }
 }
 
+/// Maximum number of dimensions of a dense vector field.
+pub const MAX_DENSE_VECTOR_DIMS: u32 = 4096;
+
+/// Options associated to a dense vector field.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QuickwitDenseVectorOptions {
+    /// Optional description of the dense vector field.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Number of dimensions of the vectors.
+    pub dims: u32,
+    /// Similarity used to compare vectors.
+    #[serde(default)]
+    pub similarity: VectorSimilarity,
+    /// Encoding of the vectors in the vector index of each split.
+    #[serde(default)]
+    pub quantization: VectorQuantization,
+    /// If true, the vectors will be stored in the doc store.
+    #[serde(default = "true_fn")]
+    pub stored: bool,
+}
+
+impl QuickwitDenseVectorOptions {
+    /// Parses a vector from its components, checking its number of dimensions and, for the
+    /// cosine similarity, that it is not a zero vector.
+    pub fn parse_vector(
+        &self,
+        components: impl ExactSizeIterator<Item = Option<f64>>,
+    ) -> Result<Vec<f32>, String> {
+        if components.len() != self.dims as usize {
+            return Err(format!(
+                "expected vector with {} dimensions, got {} dimensions",
+                self.dims,
+                components.len()
+            ));
+        }
+        let mut vector = Vec::with_capacity(components.len());
+
+        for component_opt in components {
+            let Some(component) = component_opt.map(|component| component as f32) else {
+                return Err("expected vector components to be numbers".to_string());
+            };
+            if !component.is_finite() {
+                return Err(format!("expected finite vector components, got `{component}`"));
+            }
+            vector.push(component);
+        }
+        if self.similarity == VectorSimilarity::Cosine && vector.iter().all(|value| *value == 0.0)
+        {
+            return Err("zero vectors are not supported with the cosine similarity".to_string());
+        }
+        Ok(vector)
+    }
+
+    /// Parses the `serde_json::Value` into `tantivy::schema::Value`.
+    pub(crate) fn parse_json(&self, json_val: &JsonValue) -> Result<TantivyValue, String> {
+        let JsonValue::Array(components) = json_val else {
+            return Err(format!("expected array of numbers, got `{json_val}`"));
+        };
+        let vector = self.parse_vector(components.iter().map(JsonValue::as_f64))?;
+        Ok(TantivyValue::Bytes(vector_to_bytes(&vector)))
+    }
+}
+
+/// Similarity used to compare dense vectors. Scores are always positive, and higher scores mean
+/// more similar vectors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorSimilarity {
+    /// Cosine of the angle between the vectors, scored as `(1 + cosine) / 2`.
+    #[default]
+    Cosine,
+    /// Dot product of the vectors, scored as `(1 + dot_product) / 2`. Vectors are expected to be
+    /// normalized.
+    DotProduct,
+    /// Euclidean distance between the vectors, scored as `1 / (1 + l2_norm^2)`.
+    L2Norm,
+}
+
+/// Encoding of the vectors in the vector index of a split.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorQuantization {
+    /// 32-bit floats.
+    #[default]
+    F32,
+    /// 8-bit integers, with a scale factor per vector. Vector indexes are about four times
+    /// smaller, at the cost of a lower precision.
+    I8,
+}
+
 #[derive(Clone, PartialEq, Debug, Eq, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct QuickwitTextTokenizer(Cow<'static, str>);
 
@@ -452,6 +547,12 @@ pub struct QuickwitTextOptions {
     pub stored: bool,
     #[serde(default)]
     pub fast: FastFieldOptions,
+    /// Additional text fields indexing the same value with their own options. A sub-field
+    /// `keyword` of the field `message` is addressable as `message.keyword`.
+    #[schema(value_type = Vec<FieldMappingEntryForSerialization>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldMappingEntry>,
 }
 
 #[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
@@ -517,6 +618,7 @@ impl Default for QuickwitTextOptions {
             indexing_options: Some(TextIndexingOptions::default()),
             stored: true,
             fast: FastFieldOptions::default(),
+            fields: Vec::new(),
         }
     }
 }
@@ -606,6 +708,12 @@ pub struct QuickwitJsonOptions {
     /// If true, the json object will be stored in columnar format.
     #[serde(default)]
     pub fast: FastFieldOptions,
+    /// Additional json fields indexing the same object with their own options. A sub-field
+    /// `raw` of the field `attributes` is addressable as `attributes.raw`.
+    #[schema(value_type = Vec<FieldMappingEntryForSerialization>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldMappingEntry>,
 }
 
 impl QuickwitJsonOptions {
@@ -626,6 +734,7 @@ impl Default for QuickwitJsonOptions {
             stored: true,
             expand_dots: true,
             fast: FastFieldOptions::default(),
+            fields: Vec::new(),
         }
     }
 }
@@ -732,6 +841,13 @@ fn deserialize_mapping_type(
             }
             return Ok(FieldMappingType::Object(object_options));
         }
+        QuickwitFieldType::DenseVector => {
+            let dense_vector_options: QuickwitDenseVectorOptions = serde_json::from_value(json)?;
+            if !(1..=MAX_DENSE_VECTOR_DIMS).contains(&dense_vector_options.dims) {
+                bail!("dense vector dimensions must be between 1 and {MAX_DENSE_VECTOR_DIMS}");
+            }
+            return Ok(FieldMappingType::DenseVector(dense_vector_options));
+        }
         QuickwitFieldType::Concatenate => {
             let concatenate_options: QuickwitConcatenateOptions = serde_json::from_value(json)?;
             if concatenate_options.concatenate_fields.is_empty()
@@ -745,6 +861,7 @@ fn deserialize_mapping_type(
     match typ {
         Type::Str => {
             let text_options: QuickwitTextOptions = serde_json::from_value(json)?;
+            validate_sub_fields(&text_options.fields, typ)?;
             Ok(FieldMappingType::Text(text_options, cardinality))
         }
         Type::U64 => {
@@ -781,11 +898,40 @@ fn deserialize_mapping_type(
         }
         Type::Json => {
             let json_options: QuickwitJsonOptions = serde_json::from_value(json)?;
+            validate_sub_fields(&json_options.fields, typ)?;
             Ok(FieldMappingType::Json(json_options, cardinality))
         }
     }
 }
 
+/// Checks that the `fields` sub-mappings of a text or json field are single-valued fields of
+/// the same type, without sub-mappings of their own.
+fn validate_sub_fields(sub_fields: &[FieldMappingEntry], parent_type: Type) -> anyhow::Result<()> {
+    let mut sub_field_names = HashSet::new();
+    for sub_field in sub_fields {
+        if !sub_field_names.insert(&sub_field.name) {
+            bail!("duplicated sub-field `{}`", sub_field.name);
+        }
+        let is_valid_sub_field = match (&sub_field.mapping_type, parent_type) {
+            (FieldMappingType::Text(options, Cardinality::SingleValued), Type::Str) => {
+                options.fields.is_empty()
+            }
+            (FieldMappingType::Json(options, Cardinality::SingleValued), Type::Json) => {
+                options.fields.is_empty()
+            }
+            _ => false,
+        };
+        if !is_valid_sub_field {
+            bail!(
+                "sub-field `{}` must be of type `{}` and cannot have sub-fields",
+                sub_field.name,
+                QuickwitFieldType::Simple(parent_type).to_type_id()
+            );
+        }
+    }
+    Ok(())
+}
+
 impl TryFrom<FieldMappingEntryForSerialization> for FieldMappingEntry {
     type Error = String;
 
@@ -833,6 +979,9 @@ fn typed_mapping_to_json_params(
         FieldMappingType::IpAddr(options, _) => serialize_to_map(&options),
         FieldMappingType::DateTime(date_time_options, _) => serialize_to_map(&date_time_options),
         FieldMappingType::Json(json_options, _) => serialize_to_map(&json_options),
+        FieldMappingType::DenseVector(dense_vector_options) => {
+            serialize_to_map(&dense_vector_options)
+        }
         FieldMappingType::Object(object_options) => serialize_to_map(&object_options),
         FieldMappingType::Concatenate(concatenate_options) => {
             serialize_to_map(&concatenate_options)
@@ -1784,6 +1933,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_dense_vector_mapping() {
+        let entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "embedding",
+                "type": "dense_vector",
+                "dims": 3,
+                "quantization": "i8"
+            }
+            "#,
+        )
+        .unwrap();
+        let FieldMappingType::DenseVector(options) = &entry.mapping_type else {
+            panic!("expected dense vector field mapping");
+        };
+        assert_eq!(options.dims, 3);
+        assert_eq!(options.quantization, VectorQuantization::I8);
+        assert_eq!(
+            serde_json::to_value(&entry).unwrap(),
+            json!({
+                "name": "embedding",
+                "type": "dense_vector",
+                "dims": 3,
+                "similarity": "cosine",
+                "quantization": "i8",
+                "stored": true
+            })
+        );
+        assert_eq!(
+            options.parse_json(&json!([1.0, 0.5, 0])).unwrap(),
+            TantivyValue::Bytes(vector_to_bytes(&[1.0, 0.5, 0.0]))
+        );
+        assert_eq!(
+            options.parse_json(&json!([1.0, 0.5])).unwrap_err(),
+            "expected vector with 3 dimensions, got 2 dimensions"
+        );
+        assert_eq!(
+            options.parse_json(&json!([0, 0, 0])).unwrap_err(),
+            "zero vectors are not supported with the cosine similarity"
+        );
+        options.parse_json(&json!([1.0, "a", 0])).unwrap_err();
+
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"{"name": "embedding", "type": "dense_vector", "dims": 0}"#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("dense vector dimensions must be between 1 and 4096")
+        );
+        serde_json::from_str::<FieldMappingEntry>(
+            r#"{"name": "embedding", "type": "array<dense_vector>", "dims": 3}"#,
+        )
+        .unwrap_err();
+    }
+
     #[test]
     fn test_parse_json_mapping_singlevalue() {
         let field_mapping_entry = serde_json::from_str::<FieldMappingEntry>(
@@ -1802,6 +2009,7 @@ mod tests {
             stored: true,
             fast: FastFieldOptions::Disabled,
             expand_dots: true,
+            fields: Vec::new(),
         };
         assert_eq!(&field_mapping_entry.name, "my_json_field");
         assert!(
@@ -1855,6 +2063,7 @@ mod tests {
             stored: false,
             expand_dots: true,
             fast: FastFieldOptions::Disabled,
+            fields: Vec::new(),
         };
         assert_eq!(&field_mapping_entry.name, "my_json_field_multi");
         assert!(
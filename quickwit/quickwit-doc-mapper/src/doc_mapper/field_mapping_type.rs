@@ -18,8 +18,9 @@ use super::date_time_type::QuickwitDateTimeOptions;
 use super::field_mapping_entry::QuickwitBoolOptions;
 use crate::Cardinality;
 use crate::doc_mapper::field_mapping_entry::{
-    QuickwitBytesOptions, QuickwitConcatenateOptions, QuickwitIpAddrOptions, QuickwitJsonOptions,
-    QuickwitNumericOptions, QuickwitObjectOptions, QuickwitTextOptions,
+    QuickwitBytesOptions, QuickwitConcatenateOptions, QuickwitDenseVectorOptions,
+    QuickwitIpAddrOptions, QuickwitJsonOptions, QuickwitNumericOptions, QuickwitObjectOptions,
+    QuickwitTextOptions,
 };
 
 /// A `FieldMappingType` defines the type and indexing options
@@ -44,6 +45,8 @@ pub enum FieldMappingType {
     Bytes(QuickwitBytesOptions, Cardinality),
     /// Json mapping type configuration.
     Json(QuickwitJsonOptions, Cardinality),
+    /// Dense vector mapping type configuration.
+    DenseVector(QuickwitDenseVectorOptions),
     /// Object mapping type configuration.
     Object(QuickwitObjectOptions),
     /// Concatenate field mapping type configuration.
@@ -63,6 +66,7 @@ impl FieldMappingType {
             FieldMappingType::DateTime(_, cardinality) => (Type::Date, *cardinality),
             FieldMappingType::Bytes(_, cardinality) => (Type::Bytes, *cardinality),
             FieldMappingType::Json(_, cardinality) => (Type::Json, *cardinality),
+            FieldMappingType::DenseVector(_) => return QuickwitFieldType::DenseVector,
             FieldMappingType::Object(_) => {
                 return QuickwitFieldType::Object;
             }
@@ -80,6 +84,7 @@ pub enum QuickwitFieldType {
     Simple(Type),
     Object,
     Concatenate,
+    DenseVector,
     Array(Type),
 }
 
@@ -90,6 +95,7 @@ impl QuickwitFieldType {
             QuickwitFieldType::Object => "object".to_string(),
             QuickwitFieldType::Array(typ) => format!("array<{}>", primitive_type_to_str(typ)),
             QuickwitFieldType::Concatenate => "concatenate".to_string(),
+            QuickwitFieldType::DenseVector => "dense_vector".to_string(),
         }
     }
 
@@ -100,6 +106,9 @@ impl QuickwitFieldType {
         if type_str == "concatenate" {
             return Some(QuickwitFieldType::Concatenate);
         }
+        if type_str == "dense_vector" {
+            return Some(QuickwitFieldType::DenseVector);
+        }
         if type_str.starts_with("array<") && type_str.ends_with('>') {
             let parsed_type_str = parse_primitive_type(&type_str[6..type_str.len() - 1])?;
             return Some(QuickwitFieldType::Array(parsed_type_str));
@@ -163,5 +172,7 @@ mod tests {
         test_parse_type_aux("object2", None);
         test_parse_type_aux("bool", Some(QuickwitFieldType::Simple(Type::Bool)));
         test_parse_type_aux("ip", Some(QuickwitFieldType::Simple(Type::IpAddr)));
+        test_parse_type_aux("dense_vector", Some(QuickwitFieldType::DenseVector));
+        test_parse_type_aux("array<dense_vector>", None);
     }
 }
@@ -31,8 +31,8 @@ use super::date_time_type::QuickwitDateTimeOptions;
 use super::field_mapping_entry::QuickwitBoolOptions;
 use super::tantivy_val_to_json::formatted_tantivy_value_to_json;
 use crate::doc_mapper::field_mapping_entry::{
-    QuickwitBytesOptions, QuickwitIpAddrOptions, QuickwitNumericOptions, QuickwitObjectOptions,
-    QuickwitTextOptions,
+    QuickwitBytesOptions, QuickwitDenseVectorOptions, QuickwitIpAddrOptions,
+    QuickwitNumericOptions, QuickwitObjectOptions, QuickwitTextOptions,
 };
 use crate::doc_mapper::{FieldMappingType, QuickwitJsonOptions};
 use crate::{Cardinality, DocParsingError, FieldMappingEntry, ModeType};
@@ -42,6 +42,7 @@ pub enum LeafType {
     Bool(QuickwitBoolOptions),
     Bytes(QuickwitBytesOptions),
     DateTime(QuickwitDateTimeOptions),
+    DenseVector(QuickwitDenseVectorOptions),
     F64(QuickwitNumericOptions),
     I64(QuickwitNumericOptions),
     U64(QuickwitNumericOptions),
@@ -208,6 +209,17 @@ impl LeafType {
                     ))
                 }
             }
+            LeafType::DenseVector(dense_vector_options) => {
+                let BorrowedJsonValue::Array(components) = json_val else {
+                    return Err(format!("expected array of numbers, got `{json_val}`"));
+                };
+                let components = components.iter().map(|component| match component {
+                    BorrowedJsonValue::Number(number) => serde_json::Number::from(*number).as_f64(),
+                    _ => None,
+                });
+                dense_vector_options.parse_vector(components)?;
+                Ok(())
+            }
             LeafType::Json(_) => {
                 if json_val.is_object() {
                     Ok(())
@@ -249,6 +261,9 @@ impl LeafType {
             }
             LeafType::DateTime(date_time_options) => date_time_options.parse_json(&json_val),
             LeafType::Bytes(binary_options) => binary_options.input_format.parse_json(&json_val),
+            LeafType::DenseVector(dense_vector_options) => {
+                dense_vector_options.parse_json(&json_val)
+            }
             LeafType::Json(_) => {
                 if let JsonValue::Object(json_obj) = json_val {
                     Ok(TantivyValue::Object(
@@ -300,6 +315,7 @@ impl LeafType {
                 Err("unsupported concat type: DateTime".to_string())
             }
             LeafType::Bytes(_binary_options) => Err("unsupported concat type: Bytes".to_string()),
+            LeafType::DenseVector(_) => Err("unsupported concat type: DenseVector".to_string()),
             LeafType::Json(_) => {
                 if let JsonValue::Object(json_obj) = json_val {
                     Ok(OneOrIter::Iter(
@@ -324,6 +340,7 @@ impl LeafType {
             IpAddr(_),
             // won't be supported
             Bytes(_),
+            DenseVector(_),
         */
     }
 }
@@ -335,6 +352,8 @@ pub(crate) struct MappingLeaf {
     cardinality: Cardinality,
     // concatenate fields this field is part of
     concatenate: Vec<Field>,
+    // fields indexing the same value with other options, as defined by the `fields` sub-mappings
+    sub_fields: Vec<Field>,
 }
 
 impl MappingLeaf {
@@ -347,6 +366,13 @@ impl MappingLeaf {
             // We just ignore `null`.
             return Ok(());
         }
+        // Dense vectors are arrays of numbers but hold a single value.
+        if let LeafType::DenseVector(_) = self.typ {
+            return self
+                .typ
+                .validate_from_json(json_value)
+                .map_err(|err_msg| DocParsingError::ValueError(path.join("."), err_msg));
+        }
         if let BorrowedJsonValue::Array(els) = json_value {
             if self.cardinality == Cardinality::SingleValued {
                 return Err(DocParsingError::MultiValuesNotSupported(path.join(".")));
@@ -380,6 +406,14 @@ impl MappingLeaf {
             // We just ignore `null`.
             return Ok(());
         }
+        if let LeafType::DenseVector(_) = self.typ {
+            let value = self
+                .typ
+                .value_from_json(json_val)
+                .map_err(|err_msg| DocParsingError::ValueError(path.join("."), err_msg))?;
+            self.add_value(&value, document);
+            return Ok(());
+        }
         if let JsonValue::Array(els) = json_val {
             if self.cardinality == Cardinality::SingleValued {
                 return Err(DocParsingError::MultiValuesNotSupported(path.join(".")));
@@ -404,7 +438,7 @@ impl MappingLeaf {
                     .typ
                     .value_from_json(el_json_val)
                     .map_err(|err_msg| DocParsingError::ValueError(path.join("."), err_msg))?;
-                document.add_field_value(self.field, &value);
+                self.add_value(&value, document);
             }
             return Ok(());
         }
@@ -424,10 +458,17 @@ impl MappingLeaf {
             .typ
             .value_from_json(json_val)
             .map_err(|err_msg| DocParsingError::ValueError(path.join("."), err_msg))?;
-        document.add_field_value(self.field, &value);
+        self.add_value(&value, document);
         Ok(())
     }
 
+    fn add_value(&self, value: &TantivyValue, document: &mut Document) {
+        for sub_field in &self.sub_fields {
+            document.add_field_value(*sub_field, value);
+        }
+        document.add_field_value(self.field, value);
+    }
+
     fn populate_json<'a>(
         &'a self,
         named_doc: &mut BTreeMap<String, Vec<TantivyValue>>,
@@ -812,6 +853,42 @@ impl MappingNode {
         self.branches.insert(path.to_string(), node);
     }
 
+    /// Returns the names and options of the dense vector fields, in the schema naming
+    /// convention.
+    pub fn dense_vector_fields(&self) -> Vec<(String, QuickwitDenseVectorOptions)> {
+        let mut dense_vector_fields = Vec::new();
+        self.collect_dense_vector_fields(&mut Vec::new(), &mut dense_vector_fields);
+        dense_vector_fields
+    }
+
+    fn collect_dense_vector_fields<'a>(
+        &'a self,
+        field_path: &mut Vec<&'a str>,
+        dense_vector_fields: &mut Vec<(String, QuickwitDenseVectorOptions)>,
+    ) {
+        for field_name in &self.branches_order {
+            let Some(child_tree) = self.branches.get(field_name) else {
+                continue;
+            };
+            field_path.push(field_name);
+
+            match child_tree {
+                MappingTree::Leaf(MappingLeaf {
+                    typ: LeafType::DenseVector(options),
+                    ..
+                }) => {
+                    let field_name = field_name_for_field_path(field_path);
+                    dense_vector_fields.push((field_name, options.clone()));
+                }
+                MappingTree::Leaf(_) => {}
+                MappingTree::Node(child_node) => {
+                    child_node.collect_dense_vector_fields(field_path, dense_vector_fields);
+                }
+            }
+            field_path.pop();
+        }
+    }
+
     pub fn ordered_field_mapping_entries(&self) -> Vec<FieldMappingEntry> {
         assert_eq!(self.branches.len(), self.branches_order.len());
         let mut field_mapping_entries = Vec::new();
@@ -916,6 +993,7 @@ impl From<MappingLeaf> for FieldMappingType {
             LeafType::IpAddr(opt) => FieldMappingType::IpAddr(opt, leaf.cardinality),
             LeafType::DateTime(opt) => FieldMappingType::DateTime(opt, leaf.cardinality),
             LeafType::Bytes(opt) => FieldMappingType::Bytes(opt, leaf.cardinality),
+            LeafType::DenseVector(opt) => FieldMappingType::DenseVector(opt),
             LeafType::Json(opt) => FieldMappingType::Json(opt, leaf.cardinality),
         }
     }
@@ -1135,6 +1213,17 @@ fn get_bytes_options(quickwit_numeric_options: &QuickwitBytesOptions) -> BytesOp
     bytes_options
 }
 
+fn get_dense_vector_options(dense_vector_options: &QuickwitDenseVectorOptions) -> BytesOptions {
+    // Vectors are read from the columnar storage when building the vector index of a split.
+    let bytes_options = BytesOptions::default().set_fast();
+
+    if dense_vector_options.stored {
+        bytes_options.set_stored()
+    } else {
+        bytes_options
+    }
+}
+
 fn get_ip_address_options(quickwit_ip_address_options: &QuickwitIpAddrOptions) -> IpAddrOptions {
     let mut ip_address_options = IpAddrOptions::default();
     if quickwit_ip_address_options.stored {
@@ -1200,6 +1289,39 @@ fn escape_dots(field_name: &str) -> String {
     escaped_field_name
 }
 
+/// Adds to the schema the fields defined by the `fields` sub-mappings of the field
+/// `field_name`. Sub-fields are never stored: they only index the value of their parent field.
+fn add_sub_fields(
+    sub_field_entries: &[FieldMappingEntry],
+    field_name: &str,
+    schema_builder: &mut SchemaBuilder,
+) -> Vec<Field> {
+    let mut sub_fields = Vec::with_capacity(sub_field_entries.len());
+    for sub_field_entry in sub_field_entries {
+        let sub_field_name = format!("{field_name}.{}", escape_dots(&sub_field_entry.name));
+        let sub_field = match &sub_field_entry.mapping_type {
+            FieldMappingType::Text(options, _) => {
+                let text_options = TextOptions::from(QuickwitTextOptions {
+                    stored: false,
+                    ..options.clone()
+                });
+                schema_builder.add_text_field(&sub_field_name, text_options)
+            }
+            FieldMappingType::Json(options, _) => {
+                let json_options = JsonObjectOptions::from(QuickwitJsonOptions {
+                    stored: false,
+                    ..options.clone()
+                });
+                schema_builder.add_json_field(&sub_field_name, json_options)
+            }
+            // sub-field types are checked when the field mapping is deserialized
+            _ => unreachable!("sub-fields are either text or json fields"),
+        };
+        sub_fields.push(sub_field);
+    }
+    sub_fields
+}
+
 /// build a sub-mapping tree from the fields it contains.
 ///
 /// also returns the list of concatenate fields which consume the dynamic field
@@ -1213,11 +1335,13 @@ fn build_mapping_from_field_type<'a>(
         FieldMappingType::Text(options, cardinality) => {
             let text_options: TextOptions = options.clone().into();
             let field = schema_builder.add_text_field(&field_name, text_options);
+            let sub_fields = add_sub_fields(&options.fields, &field_name, schema_builder);
             let mapping_leaf = MappingLeaf {
                 field,
                 typ: LeafType::Text(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                sub_fields,
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1229,6 +1353,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::I64(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                sub_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1240,6 +1365,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::U64(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                sub_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1251,6 +1377,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::F64(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                sub_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1262,6 +1389,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::Bool(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                sub_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1273,6 +1401,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::IpAddr(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                sub_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1284,6 +1413,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::DateTime(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                sub_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1295,17 +1425,32 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::Bytes(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                sub_fields: Vec::new(),
+            };
+            Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
+        }
+        FieldMappingType::DenseVector(options) => {
+            let bytes_options = get_dense_vector_options(options);
+            let field = schema_builder.add_bytes_field(&field_name, bytes_options);
+            let mapping_leaf = MappingLeaf {
+                field,
+                typ: LeafType::DenseVector(options.clone()),
+                cardinality: Cardinality::SingleValued,
+                concatenate: Vec::new(),
+                sub_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
         FieldMappingType::Json(options, cardinality) => {
             let json_options = JsonObjectOptions::from(options.clone());
             let field = schema_builder.add_json_field(&field_name, json_options);
+            let sub_fields = add_sub_fields(&options.fields, &field_name, schema_builder);
             let mapping_leaf = MappingLeaf {
                 field,
                 typ: LeafType::Json(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                sub_fields,
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1525,6 +1670,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            sub_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = Vec::new();
@@ -1577,6 +1723,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            sub_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = Vec::new();
@@ -1600,6 +1747,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            sub_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = Vec::new();
@@ -1618,6 +1766,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            sub_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = Vec::new();
@@ -1637,6 +1786,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            sub_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = vec!["root".to_string(), "my_field".to_string()];
@@ -1783,6 +1933,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            sub_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = vec!["root".to_string(), "my_field".to_string()];
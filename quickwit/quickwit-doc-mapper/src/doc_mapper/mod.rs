@@ -31,8 +31,9 @@ pub use doc_mapper_impl::DocMapper;
 #[cfg(all(test, feature = "multilang"))]
 pub(crate) use field_mapping_entry::TextIndexingOptions;
 pub use field_mapping_entry::{
-    BinaryFormat, FastFieldOptions, FieldMappingEntry, QuickwitBytesOptions, QuickwitJsonOptions,
-    QuickwitTextNormalizer,
+    BinaryFormat, FastFieldOptions, FieldMappingEntry, MAX_DENSE_VECTOR_DIMS, QuickwitBytesOptions,
+    QuickwitDenseVectorOptions, QuickwitJsonOptions, QuickwitTextNormalizer, VectorQuantization,
+    VectorSimilarity,
 };
 pub(crate) use field_mapping_entry::{
     FieldMappingEntryForSerialization, IndexRecordOptionSchema, QuickwitTextTokenizer,
@@ -40,11 +41,14 @@ pub(crate) use field_mapping_entry::{
 #[cfg(test)]
 pub(crate) use field_mapping_entry::{QuickwitNumericOptions, QuickwitTextOptions};
 pub use field_mapping_type::FieldMappingType;
+use quickwit_query::query_ast::FuzzyTermAutomaton;
 use serde_json::Value as JsonValue;
 use tantivy::Term;
 use tantivy::schema::{Field, FieldType};
 pub(crate) use tokenizer_entry::{
-    NgramTokenizerOption, RegexTokenizerOption, TokenFilterType, TokenizerType,
+    NgramTokenizerOption, RegexTokenizerOption, SplitCompoundFilterOption, StemmerFilterOption,
+    StopWordsFilterOption, SynonymsFilterOption, TokenFilterLanguage, TokenFilterType,
+    TokenizerType,
 };
 pub use tokenizer_entry::{TokenizerConfig, TokenizerEntry, analyze_text};
 
@@ -81,6 +85,8 @@ pub enum Automaton {
     /// A regex in it's str representation as tantivy_fst::Regex isn't PartialEq, and the path if
     /// inside a json field
     Regex(Option<Vec<u8>>, String),
+    /// A Levenshtein automaton, along with the json path and exact prefix of the terms it matches
+    Fuzzy(FuzzyTermAutomaton),
     // we could add termset query here, instead of downloading the whole dictionary
 }
 
@@ -18,6 +18,7 @@ use tantivy::schema::OwnedValue as TantivyValue;
 use super::BinaryFormat;
 use super::field_mapping_entry::{NumericOutputFormat, QuickwitNumericOptions};
 use super::mapping_tree::LeafType;
+use crate::vector_index::vector_from_bytes;
 
 pub(crate) trait NumToJson {
     fn to_json(&self, output_format: NumericOutputFormat) -> Option<JsonValue>;
@@ -255,6 +256,20 @@ pub fn formatted_tantivy_value_to_json(
                 Err(value)
             }
         }
+        LeafType::DenseVector(_) => {
+            if let TantivyValue::Bytes(ref bytes) = value
+                && let Some(vector) = vector_from_bytes(bytes)
+            {
+                let components = vector
+                    .into_iter()
+                    .flat_map(|component| serde_json::Number::from_f64(component as f64))
+                    .map(JsonValue::Number)
+                    .collect();
+                Ok(JsonValue::Array(components))
+            } else {
+                Err(value)
+            }
+        }
         LeafType::DateTime(date_time_options) => date_time_options
             .reparse_tantivy_value(&value)
             .map(|date_time| {
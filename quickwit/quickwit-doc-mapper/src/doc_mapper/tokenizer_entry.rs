@@ -12,12 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Context;
-use quickwit_query::{CodeTokenizer, DEFAULT_REMOVE_TOKEN_LENGTH};
+use anyhow::{Context, bail};
+use quickwit_query::{CodeTokenizer, DEFAULT_REMOVE_TOKEN_LENGTH, SynonymFilter};
 use serde::{Deserialize, Serialize};
 use tantivy::tokenizer::{
-    AsciiFoldingFilter, LowerCaser, NgramTokenizer, RegexTokenizer, RemoveLongFilter,
-    SimpleTokenizer, TextAnalyzer, Token,
+    AsciiFoldingFilter, Language, LowerCaser, NgramTokenizer, RegexTokenizer, RemoveLongFilter,
+    SimpleTokenizer, SplitCompoundWords, Stemmer, StopWordFilter, TextAnalyzer, Token,
 };
 
 /// A `TokenizerEntry` defines a custom tokenizer with its name and configuration.
@@ -42,6 +42,23 @@ pub struct TokenizerConfig {
 impl TokenizerConfig {
     /// Build a `TextAnalyzer` from a `TokenizerConfig`.
     pub fn text_analyzer(&self) -> anyhow::Result<TextAnalyzer> {
+        self.build_text_analyzer(false)
+    }
+
+    /// Build the `TextAnalyzer` used to tokenize queries if it differs from the one used at
+    /// indexing time, that is if the tokenizer has a `synonyms` filter.
+    pub fn search_text_analyzer(&self) -> anyhow::Result<Option<TextAnalyzer>> {
+        let has_synonyms = self
+            .filters
+            .iter()
+            .any(|filter| matches!(filter, TokenFilterType::Synonyms(_)));
+        if !has_synonyms {
+            return Ok(None);
+        }
+        self.build_text_analyzer(true).map(Some)
+    }
+
+    fn build_text_analyzer(&self, with_synonyms: bool) -> anyhow::Result<TextAnalyzer> {
         let mut text_analyzer_builder = match &self.tokenizer_type {
             TokenizerType::Simple => TextAnalyzer::builder(SimpleTokenizer::default()).dynamic(),
             #[cfg(any(test, feature = "multilang"))]
@@ -62,7 +79,7 @@ impl TokenizerConfig {
             }
         };
         for filter in &self.filters {
-            match filter.tantivy_token_filter_enum() {
+            match filter.tantivy_token_filter_enum()? {
                 TantivyTokenFilterEnum::RemoveLong(token_filter) => {
                     text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
                 }
@@ -72,6 +89,23 @@ impl TokenizerConfig {
                 TantivyTokenFilterEnum::AsciiFolding(token_filter) => {
                     text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
                 }
+                TantivyTokenFilterEnum::Stemmer(token_filter) => {
+                    text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                }
+                TantivyTokenFilterEnum::StopWords(token_filters) => {
+                    for token_filter in token_filters {
+                        text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                    }
+                }
+                TantivyTokenFilterEnum::SplitCompound(token_filter) => {
+                    text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                }
+                // Synonyms are only expanded at query time.
+                TantivyTokenFilterEnum::Synonyms(token_filter) => {
+                    if with_synonyms {
+                        text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                    }
+                }
             }
         }
         Ok(text_analyzer_builder.build())
@@ -95,6 +129,10 @@ pub enum TokenFilterType {
     RemoveLong,
     LowerCaser,
     AsciiFolding,
+    Stemmer(StemmerFilterOption),
+    StopWords(StopWordsFilterOption),
+    SplitCompound(SplitCompoundFilterOption),
+    Synonyms(SynonymsFilterOption),
 }
 
 /// Tantivy token filter enum to build
@@ -103,20 +141,142 @@ enum TantivyTokenFilterEnum {
     RemoveLong(RemoveLongFilter),
     LowerCaser(LowerCaser),
     AsciiFolding(AsciiFoldingFilter),
+    Stemmer(Stemmer),
+    StopWords(Vec<StopWordFilter>),
+    SplitCompound(SplitCompoundWords),
+    Synonyms(SynonymFilter),
 }
 
 impl TokenFilterType {
-    fn tantivy_token_filter_enum(&self) -> TantivyTokenFilterEnum {
-        match &self {
+    fn tantivy_token_filter_enum(&self) -> anyhow::Result<TantivyTokenFilterEnum> {
+        let token_filter = match &self {
             Self::RemoveLong => TantivyTokenFilterEnum::RemoveLong(RemoveLongFilter::limit(
                 DEFAULT_REMOVE_TOKEN_LENGTH,
             )),
             Self::LowerCaser => TantivyTokenFilterEnum::LowerCaser(LowerCaser),
             Self::AsciiFolding => TantivyTokenFilterEnum::AsciiFolding(AsciiFoldingFilter),
+            Self::Stemmer(options) => {
+                TantivyTokenFilterEnum::Stemmer(Stemmer::new(options.language.tantivy_language()))
+            }
+            Self::StopWords(options) => {
+                if options.language.is_none() && options.words.is_empty() {
+                    bail!("stop_words filter requires a `language` or a list of `words`");
+                }
+                let mut token_filters = Vec::new();
+                if let Some(language) = options.language {
+                    let token_filter = StopWordFilter::new(language.tantivy_language())
+                        .with_context(|| {
+                            format!("no built-in stop word list for language `{language:?}`")
+                        })?;
+                    token_filters.push(token_filter);
+                }
+                if !options.words.is_empty() {
+                    token_filters.push(StopWordFilter::remove(options.words.clone()));
+                }
+                TantivyTokenFilterEnum::StopWords(token_filters)
+            }
+            Self::SplitCompound(options) => {
+                if options.words.is_empty() {
+                    bail!("split_compound filter requires a non-empty list of `words`");
+                }
+                let token_filter = SplitCompoundWords::from_dictionary(&options.words)
+                    .context("invalid split_compound dictionary")?;
+                TantivyTokenFilterEnum::SplitCompound(token_filter)
+            }
+            Self::Synonyms(options) => {
+                for word in options.synonyms.iter().flatten() {
+                    if word.is_empty() || word.contains(char::is_whitespace) {
+                        bail!("synonym `{word}` must be a single non-empty token");
+                    }
+                }
+                TantivyTokenFilterEnum::Synonyms(SynonymFilter::from_groups(&options.synonyms))
+            }
+        };
+        Ok(token_filter)
+    }
+}
+
+/// Languages supported by the `stemmer` and `stop_words` filters. Built-in stop word lists are
+/// not available for Arabic, Greek, Romanian, Tamil and Turkish.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenFilterLanguage {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+impl TokenFilterLanguage {
+    fn tantivy_language(self) -> Language {
+        match self {
+            Self::Arabic => Language::Arabic,
+            Self::Danish => Language::Danish,
+            Self::Dutch => Language::Dutch,
+            Self::English => Language::English,
+            Self::Finnish => Language::Finnish,
+            Self::French => Language::French,
+            Self::German => Language::German,
+            Self::Greek => Language::Greek,
+            Self::Hungarian => Language::Hungarian,
+            Self::Italian => Language::Italian,
+            Self::Norwegian => Language::Norwegian,
+            Self::Portuguese => Language::Portuguese,
+            Self::Romanian => Language::Romanian,
+            Self::Russian => Language::Russian,
+            Self::Spanish => Language::Spanish,
+            Self::Swedish => Language::Swedish,
+            Self::Tamil => Language::Tamil,
+            Self::Turkish => Language::Turkish,
         }
     }
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StemmerFilterOption {
+    pub language: TokenFilterLanguage,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StopWordsFilterOption {
+    /// Built-in stop word list to remove.
+    #[serde(default)]
+    pub language: Option<TokenFilterLanguage>,
+    /// Additional stop words to remove.
+    #[serde(default)]
+    pub words: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SplitCompoundFilterOption {
+    /// Dictionary of the words compounds are split into.
+    pub words: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SynonymsFilterOption {
+    /// Groups of equivalent words.
+    pub synonyms: Vec<Vec<String>>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum TokenizerType {
@@ -145,9 +305,14 @@ pub struct RegexTokenizerOption {
 
 #[cfg(test)]
 mod tests {
-    use super::{NgramTokenizerOption, TokenizerType};
-    use crate::TokenizerEntry;
+    use tantivy::tokenizer::TokenStream;
+
+    use super::{
+        NgramTokenizerOption, StemmerFilterOption, TokenFilterLanguage, TokenFilterType,
+        TokenizerType,
+    };
     use crate::doc_mapper::RegexTokenizerOption;
+    use crate::{TokenizerEntry, analyze_text};
 
     #[test]
     fn test_deserialize_tokenizer_entry() {
@@ -240,4 +405,118 @@ mod tests {
             _ => panic!("Unexpected tokenizer type"),
         }
     }
+
+    fn analyze_with_filters(filters_json: &str, text: &str) -> anyhow::Result<Vec<String>> {
+        let tokenizer_entry_json =
+            format!(r#"{{"name": "my_tokenizer", "type": "simple", "filters": {filters_json}}}"#);
+        let tokenizer_entry: TokenizerEntry = serde_json::from_str(&tokenizer_entry_json)?;
+        let tokens = analyze_text(text, &tokenizer_entry.config)?
+            .into_iter()
+            .map(|token| token.text)
+            .collect();
+        Ok(tokens)
+    }
+
+    #[test]
+    fn test_deserialize_tokenizer_entry_with_stemmer() {
+        let tokenizer_entry: TokenizerEntry = serde_json::from_str(
+            r#"
+            {
+                "name": "my_tokenizer",
+                "type": "simple",
+                "filters": ["lower_caser", {"stemmer": {"language": "french"}}]
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            tokenizer_entry.config.filters,
+            [
+                TokenFilterType::LowerCaser,
+                TokenFilterType::Stemmer(StemmerFilterOption {
+                    language: TokenFilterLanguage::French,
+                }),
+            ]
+        );
+        let error = serde_json::from_str::<TokenizerEntry>(
+            r#"
+            {
+                "name": "my_tokenizer",
+                "type": "simple",
+                "filters": [{"stemmer": {"language": "klingon"}}]
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unknown variant `klingon`"));
+    }
+
+    #[test]
+    fn test_stemmer_filter() {
+        let tokens = analyze_with_filters(
+            r#"["lower_caser", {"stemmer": {"language": "english"}}]"#,
+            "Running servers",
+        )
+        .unwrap();
+        assert_eq!(tokens, ["run", "server"]);
+    }
+
+    #[test]
+    fn test_stop_words_filter() {
+        let tokens = analyze_with_filters(
+            r#"["lower_caser", {"stop_words": {"language": "english", "words": ["please"]}}]"#,
+            "Please restart the server",
+        )
+        .unwrap();
+        assert_eq!(tokens, ["restart", "server"]);
+
+        let error = analyze_with_filters(r#"[{"stop_words": {}}]"#, "").unwrap_err();
+        assert!(error.to_string().contains("requires a `language`"));
+
+        let error =
+            analyze_with_filters(r#"[{"stop_words": {"language": "tamil"}}]"#, "").unwrap_err();
+        assert!(error.to_string().contains("no built-in stop word list"));
+    }
+
+    #[test]
+    fn test_split_compound_filter() {
+        let tokens = analyze_with_filters(
+            r#"["lower_caser", {"split_compound": {"words": ["dampf", "schiff", "fahrt"]}}]"#,
+            "Dampfschifffahrt brennt",
+        )
+        .unwrap();
+        assert_eq!(tokens, ["dampf", "schiff", "fahrt", "brennt"]);
+    }
+
+    #[test]
+    fn test_synonyms_filter_is_not_applied_at_indexing() {
+        let filters_json = r#"["lower_caser", {"synonyms": {"synonyms": [["tv", "television"]]}}]"#;
+        let tokens = analyze_with_filters(filters_json, "TV").unwrap();
+        assert_eq!(tokens, ["tv"]);
+
+        let tokenizer_entry: TokenizerEntry = serde_json::from_str(&format!(
+            r#"{{"name": "my_tokenizer", "type": "simple", "filters": {filters_json}}}"#
+        ))
+        .unwrap();
+        let mut search_text_analyzer = tokenizer_entry
+            .config
+            .search_text_analyzer()
+            .unwrap()
+            .unwrap();
+        let mut token_stream = search_text_analyzer.token_stream("TV");
+        assert_eq!(token_stream.next().unwrap().text, "tv");
+        assert_eq!(token_stream.next().unwrap().text, "television");
+        assert!(token_stream.next().is_none());
+
+        let error = analyze_with_filters(
+            r#"[{"synonyms": {"synonyms": [["smart tv", "television"]]}}]"#,
+            "",
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("must be a single non-empty token")
+        );
+    }
 }
@@ -28,16 +28,19 @@ mod routing_expression;
 
 /// Pruning tags manipulation.
 pub mod tag_pruning;
+pub mod vector_index;
 
 pub use doc_mapper::{
     Automaton, BinaryFormat, DocMapper, DocMapperBuilder, FastFieldWarmupInfo, FieldMappingEntry,
-    FieldMappingType, JsonObject, NamedField, QuickwitBytesOptions, QuickwitJsonOptions, TermRange,
-    TokenizerConfig, TokenizerEntry, WarmupInfo, analyze_text,
+    FieldMappingType, JsonObject, MAX_DENSE_VECTOR_DIMS, NamedField, QuickwitBytesOptions,
+    QuickwitDenseVectorOptions, QuickwitJsonOptions, TermRange, TokenizerConfig, TokenizerEntry,
+    VectorQuantization, VectorSimilarity, WarmupInfo, analyze_text,
 };
 use doc_mapper::{
     FastFieldOptions, FieldMappingEntryForSerialization, IndexRecordOptionSchema,
     NgramTokenizerOption, QuickwitTextNormalizer, QuickwitTextTokenizer, RegexTokenizerOption,
-    TokenFilterType, TokenizerType,
+    SplitCompoundFilterOption, StemmerFilterOption, StopWordsFilterOption, SynonymsFilterOption,
+    TokenFilterLanguage, TokenFilterType, TokenizerType,
 };
 pub use doc_mapping::{DocMapping, Mode, ModeType};
 pub use error::{DocParsingError, QueryParserError};
@@ -83,6 +86,11 @@ pub enum Cardinality {
     QuickwitTextNormalizer,
     QuickwitTextTokenizer,
     RegexTokenizerOption,
+    SplitCompoundFilterOption,
+    StemmerFilterOption,
+    StopWordsFilterOption,
+    SynonymsFilterOption,
+    TokenFilterLanguage,
     TokenFilterType,
     TokenizerConfig,
     TokenizerEntry,
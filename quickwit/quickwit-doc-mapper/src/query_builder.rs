@@ -19,8 +19,9 @@ use std::sync::Arc;
 
 use quickwit_proto::types::SplitId;
 use quickwit_query::query_ast::{
-    BuildTantivyAstContext, FieldPresenceQuery, FullTextQuery, PhrasePrefixQuery, QueryAst,
-    QueryAstTransformer, QueryAstVisitor, RangeQuery, RegexQuery, TermSetQuery, WildcardQuery,
+    BuildTantivyAstContext, FieldPresenceQuery, FullTextQuery, FuzzyQuery, PhrasePrefixQuery,
+    QueryAst, QueryAstTransformer, QueryAstVisitor, RangeQuery, RegexQuery, TermSetQuery,
+    WildcardQuery,
 };
 use quickwit_query::tokenizers::TokenizerManager;
 use quickwit_query::{InvalidQuery, find_field_or_hit_dynamic};
@@ -360,6 +361,11 @@ impl<'a, 'b: 'a> QueryAstVisitor<'a> for ExtractPrefixTermRanges<'b> {
             // see https://github.com/elastic/elasticsearch/blob/6ad48306d029e6e527c0481e2e9880bd2f06b239/docs/reference/query-dsl/match-bool-prefix-query.asciidoc#parameters
             self.add_prefix_term(prefix_term, u32::MAX, false);
         }
+        for (field, fuzzy_term_automaton) in
+            full_text_query.get_fuzzy_term_automatons(self.schema, self.tokenizer_manager)
+        {
+            self.add_automaton(field, Automaton::Fuzzy(fuzzy_term_automaton));
+        }
         Ok(())
     }
 
@@ -403,6 +409,18 @@ impl<'a, 'b: 'a> QueryAstVisitor<'a> for ExtractPrefixTermRanges<'b> {
         self.add_automaton(field, Automaton::Regex(path, regex));
         Ok(())
     }
+
+    fn visit_fuzzy(&mut self, fuzzy_query: &'a FuzzyQuery) -> Result<(), Self::Err> {
+        let (field, fuzzy_term_automaton) =
+            match fuzzy_query.to_fuzzy_term_automaton(self.schema, self.tokenizer_manager) {
+                Ok(res) => res,
+                /* the query will be nullified when casting to a tantivy ast */
+                Err(InvalidQuery::FieldDoesNotExist { .. }) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+        self.add_automaton(field, Automaton::Fuzzy(fuzzy_term_automaton));
+        Ok(())
+    }
 }
 
 type TermRangeWarmupInfo = HashMap<Field, HashMap<TermRange, PositionNeeded>>;
@@ -437,7 +455,7 @@ mod test {
     use tantivy::schema::{DateOptions, DateTimePrecision, FAST, INDEXED, STORED, Schema, TEXT};
 
     use super::{ExtractPrefixTermRanges, build_query};
-    use crate::{DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME, TermRange};
+    use crate::{Automaton, DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME, TermRange};
 
     enum TestExpectation<'a> {
         Err(&'a str),
@@ -700,6 +718,30 @@ mod test {
         check_build_query_static_mode("title:hello*yo", Vec::new(), TestExpectation::Ok("Regex"));
     }
 
+    #[test]
+    fn test_fuzzy_query() {
+        check_build_query_static_mode(
+            "title:helo~1",
+            Vec::new(),
+            TestExpectation::Ok("LevenshteinAutomaton"),
+        );
+        check_build_query_static_mode(
+            "json_text.product:laptpo~2",
+            Vec::new(),
+            TestExpectation::Ok("LevenshteinAutomaton"),
+        );
+        check_build_query_static_mode(
+            "foo:bar~1",
+            Vec::new(),
+            TestExpectation::Err("invalid query: field does not exist: `foo`"),
+        );
+        check_build_query_static_mode(
+            "u64_fast:10~1",
+            Vec::new(),
+            TestExpectation::Err("trying to run a Fuzzy query on a non-text field"),
+        );
+    }
+
     #[test]
     fn test_existence_query() {
         check_build_query_static_mode(
@@ -903,6 +945,27 @@ mod test {
         assert!(warmup_info.term_dict_fields.is_empty());
     }
 
+    #[test]
+    fn test_build_query_warmup_info_fuzzy() {
+        let fuzzy_query = query_ast_from_user_text("desc:helo~1", None)
+            .parse_user_query(&[])
+            .unwrap();
+
+        let schema = make_schema(true);
+        let context = BuildTantivyAstContext::for_test(&schema);
+
+        let (_, warmup_info) = build_query(fuzzy_query, &context, None).unwrap();
+        let desc_field = tantivy::schema::Field::from_field_id(2);
+        let automatons = &warmup_info.automatons_grouped_by_field[&desc_field];
+        assert_eq!(automatons.len(), 1);
+        let Some(Automaton::Fuzzy(fuzzy_term_automaton)) = automatons.iter().next() else {
+            panic!("expected a fuzzy automaton");
+        };
+        assert!(fuzzy_term_automaton.prefix.is_empty());
+        assert_eq!(fuzzy_term_automaton.text, "helo");
+        assert_eq!(fuzzy_term_automaton.distance, 1);
+    }
+
     #[test]
     fn test_extract_phrase_prefix_position_required() {
         let schema = make_schema(false);
@@ -912,6 +975,7 @@ mod test {
             tokenizer: None,
             mode: FullTextMode::Phrase { slop: 0 },
             zero_terms_query: MatchAllOrNone::MatchNone,
+            fuzzy: None,
         };
         let short = PhrasePrefixQuery {
             field: "title".to_string(),
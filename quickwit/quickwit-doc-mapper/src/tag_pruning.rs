@@ -113,7 +113,7 @@ fn extract_unsimplified_tags_filter_ast(query_ast: QueryAst) -> UnsimplifiedTagF
             panic!("Extract unsimplified should only be called on AST without UserInputQuery.");
         }
         QueryAst::FieldPresence(_) => UnsimplifiedTagFilterAst::Uninformative,
-        QueryAst::Regex(_) => UnsimplifiedTagFilterAst::Uninformative,
+        QueryAst::Regex(_) | QueryAst::Fuzzy(_) => UnsimplifiedTagFilterAst::Uninformative,
         QueryAst::Cache(cache_node) => extract_unsimplified_tags_filter_ast(*cache_node.inner),
     }
 }
@@ -0,0 +1,687 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Approximate nearest neighbor index for `dense_vector` fields.
+//!
+//! Each split holds one vector index per dense vector field, stored in the split file next to the
+//! tantivy index. The index is an inverted file (IVF): vectors are clustered with k-means and
+//! stored list by list, grouped by their closest centroid. At search time, only the lists whose
+//! centroids are the closest to the query vector are fetched and scored.
+//!
+//! The file layout is:
+//! - the length of the header, as a little-endian u64;
+//! - the header: format version, dimensions, similarity, quantization, number of lists, number of
+//!   vectors, centroids, and the byte offsets of the lists relative to the end of the header;
+//! - the lists, each entry being a doc id followed by the (possibly quantized) vector.
+//!
+//! The length prefix and the header are meant to be stored in the hotcache, so that opening the
+//! index and probing the lists requires no extra read.
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+use anyhow::{Context, bail, ensure};
+use tantivy::DocId;
+use tantivy::schema::Field;
+
+use crate::{QuickwitDenseVectorOptions, VectorQuantization, VectorSimilarity};
+
+const VECTOR_INDEX_FORMAT_VERSION: u32 = 1;
+
+const MAX_NUM_LISTS: usize = 256;
+
+/// Number of vectors sampled per list to train the centroids.
+const NUM_TRAINING_VECTORS_PER_LIST: usize = 64;
+
+const NUM_KMEANS_ITERATIONS: usize = 10;
+
+/// Minimum number of lists probed at search time, regardless of the number of candidates.
+const MIN_NUM_PROBED_LISTS: usize = 4;
+
+const LEN_PREFIX_NUM_BYTES: usize = std::mem::size_of::<u64>();
+
+/// Returns the path of the vector index of a dense vector field within a split.
+pub fn vector_index_file_path(field: Field) -> PathBuf {
+    PathBuf::from(format!("dense_vector.{}.idx", field.field_id()))
+}
+
+/// Encodes a vector as the little-endian bytes of its components. This is how dense vectors are
+/// stored in tantivy bytes fields.
+pub fn vector_to_bytes(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|component| component.to_le_bytes())
+        .collect()
+}
+
+/// Decodes a vector encoded with [`vector_to_bytes`].
+pub fn vector_from_bytes(bytes: &[u8]) -> Option<Vec<f32>> {
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    let vector = bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    Some(vector)
+}
+
+/// Serialized vector index.
+pub struct VectorIndexBytes {
+    /// Bytes of the vector index file.
+    pub bytes: Vec<u8>,
+    /// Number of bytes at the beginning of the file that are accessed every time the index is
+    /// opened and should be stored in the hotcache.
+    pub hot_num_bytes: usize,
+}
+
+/// Builds the vector index of a dense vector field for a split.
+pub struct VectorIndexBuilder {
+    dims: usize,
+    similarity: VectorSimilarity,
+    quantization: VectorQuantization,
+    doc_ids: Vec<DocId>,
+    vectors: Vec<f32>,
+}
+
+impl VectorIndexBuilder {
+    /// Creates a builder for a field with the given options.
+    pub fn new(options: &QuickwitDenseVectorOptions) -> Self {
+        VectorIndexBuilder {
+            dims: options.dims as usize,
+            similarity: options.similarity,
+            quantization: options.quantization,
+            doc_ids: Vec::new(),
+            vectors: Vec::new(),
+        }
+    }
+
+    /// Adds the vector of a document. Vectors with an unexpected number of dimensions are
+    /// ignored.
+    pub fn add(&mut self, doc_id: DocId, vector: &[f32]) {
+        if vector.len() != self.dims {
+            return;
+        }
+        let start = self.vectors.len();
+        self.vectors.extend_from_slice(vector);
+
+        if self.similarity == VectorSimilarity::Cosine {
+            normalize(&mut self.vectors[start..]);
+        }
+        self.doc_ids.push(doc_id);
+    }
+
+    /// Returns the number of vectors added so far.
+    pub fn num_vectors(&self) -> usize {
+        self.doc_ids.len()
+    }
+
+    fn vector(&self, ord: usize) -> &[f32] {
+        &self.vectors[ord * self.dims..(ord + 1) * self.dims]
+    }
+
+    /// Trains the centroids, assigns the vectors to their lists, and serializes the index.
+    pub fn build(self) -> VectorIndexBytes {
+        let num_vectors = self.num_vectors();
+        let centroids = self.train_centroids();
+        let num_lists = centroids.len() / self.dims.max(1);
+
+        let mut lists: Vec<Vec<usize>> = vec![Vec::new(); num_lists];
+        for ord in 0..num_vectors {
+            let list = closest_centroid(&centroids, self.dims, self.vector(ord));
+            lists[list].push(ord);
+        }
+        let entry_num_bytes = entry_num_bytes(self.dims, self.quantization);
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&VECTOR_INDEX_FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&(self.dims as u32).to_le_bytes());
+        header.push(similarity_to_code(self.similarity));
+        header.push(quantization_to_code(self.quantization));
+        header.extend_from_slice(&(num_lists as u32).to_le_bytes());
+        header.extend_from_slice(&(num_vectors as u64).to_le_bytes());
+        header.extend(vector_to_bytes(&centroids));
+
+        let mut list_offset = 0u64;
+        header.extend_from_slice(&list_offset.to_le_bytes());
+        for list in &lists {
+            list_offset += (list.len() * entry_num_bytes) as u64;
+            header.extend_from_slice(&list_offset.to_le_bytes());
+        }
+        let hot_num_bytes = LEN_PREFIX_NUM_BYTES + header.len();
+
+        let mut bytes = Vec::with_capacity(hot_num_bytes + list_offset as usize);
+        bytes.extend_from_slice(&(header.len() as u64).to_le_bytes());
+        bytes.extend(header);
+
+        for ord in lists.into_iter().flatten() {
+            bytes.extend_from_slice(&self.doc_ids[ord].to_le_bytes());
+            let vector = self.vector(ord);
+
+            match self.quantization {
+                VectorQuantization::F32 => bytes.extend(vector_to_bytes(vector)),
+                VectorQuantization::I8 => {
+                    let (scale, quantized) = quantize(vector);
+                    bytes.extend_from_slice(&scale.to_le_bytes());
+                    bytes.extend(quantized.into_iter().map(|component| component as u8));
+                }
+            }
+        }
+        VectorIndexBytes {
+            bytes,
+            hot_num_bytes,
+        }
+    }
+
+    /// Runs a few k-means iterations over a sample of the vectors and returns the flattened
+    /// centroids.
+    fn train_centroids(&self) -> Vec<f32> {
+        let num_vectors = self.num_vectors();
+
+        if num_vectors == 0 {
+            return Vec::new();
+        }
+        let num_lists = ((num_vectors as f64).sqrt().ceil() as usize).clamp(1, MAX_NUM_LISTS);
+        let num_samples = num_vectors.min(num_lists * NUM_TRAINING_VECTORS_PER_LIST);
+        let samples: Vec<usize> = (0..num_samples)
+            .map(|sample_ord| sample_ord * num_vectors / num_samples)
+            .collect();
+
+        let mut centroids: Vec<f32> = (0..num_lists)
+            .flat_map(|list| self.vector(samples[list * num_samples / num_lists]))
+            .copied()
+            .collect();
+
+        for _ in 0..NUM_KMEANS_ITERATIONS {
+            let mut sums = vec![0f32; centroids.len()];
+            let mut counts = vec![0usize; num_lists];
+
+            for &ord in &samples {
+                let vector = self.vector(ord);
+                let list = closest_centroid(&centroids, self.dims, vector);
+                counts[list] += 1;
+
+                for (sum, component) in sums[list * self.dims..].iter_mut().zip(vector) {
+                    *sum += component;
+                }
+            }
+            for (list, count) in counts.into_iter().enumerate() {
+                // Empty lists keep their previous centroid.
+                if count == 0 {
+                    continue;
+                }
+                let range = list * self.dims..(list + 1) * self.dims;
+
+                for (centroid, sum) in centroids[range.clone()].iter_mut().zip(&sums[range]) {
+                    *centroid = sum / count as f32;
+                }
+            }
+        }
+        centroids
+    }
+}
+
+/// A vector and its similarity score with the query vector.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoredVector {
+    /// Doc id of the document within the split.
+    pub doc_id: DocId,
+    /// Similarity score, the higher the more similar.
+    pub score: f32,
+}
+
+/// An opened vector index. Only the header is held in memory, the lists must be fetched
+/// separately with the byte ranges returned by [`VectorIndex::list_byte_range`].
+#[derive(Debug)]
+pub struct VectorIndex {
+    dims: usize,
+    similarity: VectorSimilarity,
+    quantization: VectorQuantization,
+    num_vectors: u64,
+    centroids: Vec<f32>,
+    list_offsets: Vec<u64>,
+    lists_start: u64,
+}
+
+impl VectorIndex {
+    /// Returns the number of bytes to read from the beginning of the file to open the index,
+    /// given its first 8 bytes.
+    pub fn hot_num_bytes(len_prefix: &[u8]) -> anyhow::Result<usize> {
+        let len_prefix_bytes: [u8; LEN_PREFIX_NUM_BYTES] = len_prefix
+            .get(..LEN_PREFIX_NUM_BYTES)
+            .and_then(|bytes| bytes.try_into().ok())
+            .context("vector index is truncated")?;
+        let header_num_bytes = u64::from_le_bytes(len_prefix_bytes) as usize;
+        Ok(LEN_PREFIX_NUM_BYTES + header_num_bytes)
+    }
+
+    /// Opens a vector index from the first [`VectorIndex::hot_num_bytes`] bytes of its file.
+    pub fn open(hot_bytes: &[u8]) -> anyhow::Result<VectorIndex> {
+        let hot_num_bytes = Self::hot_num_bytes(hot_bytes)?;
+        ensure!(
+            hot_bytes.len() >= hot_num_bytes,
+            "vector index is truncated"
+        );
+
+        let mut reader = ByteReader {
+            bytes: &hot_bytes[LEN_PREFIX_NUM_BYTES..hot_num_bytes],
+        };
+        let version = u32::from_le_bytes(reader.read_array()?);
+
+        if version != VECTOR_INDEX_FORMAT_VERSION {
+            bail!("unsupported vector index format version `{version}`");
+        }
+        let dims = u32::from_le_bytes(reader.read_array()?) as usize;
+        let similarity = similarity_from_code(reader.read_array::<1>()?[0])?;
+        let quantization = quantization_from_code(reader.read_array::<1>()?[0])?;
+        let num_lists = u32::from_le_bytes(reader.read_array()?) as usize;
+        let num_vectors = u64::from_le_bytes(reader.read_array()?);
+        let centroids = vector_from_bytes(reader.read_slice(num_lists * dims * 4)?)
+            .context("vector index is corrupted")?;
+        let list_offsets = (0..=num_lists)
+            .map(|_| reader.read_array().map(u64::from_le_bytes))
+            .collect::<anyhow::Result<Vec<u64>>>()?;
+
+        Ok(VectorIndex {
+            dims,
+            similarity,
+            quantization,
+            num_vectors,
+            centroids,
+            list_offsets,
+            lists_start: hot_num_bytes as u64,
+        })
+    }
+
+    /// Returns the number of dimensions of the indexed vectors.
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    /// Returns the number of indexed vectors.
+    pub fn num_vectors(&self) -> u64 {
+        self.num_vectors
+    }
+
+    fn num_lists(&self) -> usize {
+        self.list_offsets.len() - 1
+    }
+
+    fn num_vectors_in_list(&self, list: usize) -> usize {
+        let list_num_bytes = self.list_offsets[list + 1] - self.list_offsets[list];
+        list_num_bytes as usize / entry_num_bytes(self.dims, self.quantization)
+    }
+
+    /// Returns the lists to search, closest first, so that they hold at least `num_candidates`
+    /// vectors.
+    pub fn probe(&self, query_vector: &[f32], num_candidates: usize) -> Vec<usize> {
+        let query_vector = self.prepare_query_vector(query_vector);
+        let mut lists: Vec<(usize, f32)> = (0..self.num_lists())
+            .map(|list| {
+                let centroid = &self.centroids[list * self.dims..(list + 1) * self.dims];
+                (list, squared_l2_distance(centroid, &query_vector))
+            })
+            .collect();
+        lists.sort_by(|(_, left), (_, right)| left.total_cmp(right));
+
+        let mut num_probed_vectors = 0;
+        let mut num_probed_lists = 0;
+
+        for &(list, _) in &lists {
+            if num_probed_lists >= MIN_NUM_PROBED_LISTS && num_probed_vectors >= num_candidates {
+                break;
+            }
+            num_probed_vectors += self.num_vectors_in_list(list);
+            num_probed_lists += 1;
+        }
+        lists
+            .into_iter()
+            .take(num_probed_lists)
+            .map(|(list, _)| list)
+            .collect()
+    }
+
+    /// Returns the byte range of a list within the vector index file.
+    pub fn list_byte_range(&self, list: usize) -> Range<usize> {
+        let start = self.lists_start + self.list_offsets[list];
+        let end = self.lists_start + self.list_offsets[list + 1];
+        start as usize..end as usize
+    }
+
+    /// Scores the vectors of the given lists against the query vector and returns the `k` most
+    /// similar ones accepted by `filter`, most similar first.
+    pub fn search<'a>(
+        &self,
+        query_vector: &[f32],
+        lists: impl IntoIterator<Item = &'a [u8]>,
+        filter: impl Fn(DocId) -> bool,
+        k: usize,
+    ) -> Vec<ScoredVector> {
+        let query_vector = self.prepare_query_vector(query_vector);
+        let entry_num_bytes = entry_num_bytes(self.dims, self.quantization);
+        let mut vector_buffer = vec![0f32; self.dims];
+        let mut scored_vectors = Vec::new();
+
+        for list_bytes in lists {
+            for entry in list_bytes.chunks_exact(entry_num_bytes) {
+                let doc_id = DocId::from_le_bytes(entry[..4].try_into().unwrap());
+
+                if !filter(doc_id) {
+                    continue;
+                }
+                self.decode_vector(&entry[4..], &mut vector_buffer);
+                let score = self.score(&query_vector, &vector_buffer);
+                scored_vectors.push(ScoredVector { doc_id, score });
+            }
+        }
+        let compare = |left: &ScoredVector, right: &ScoredVector| {
+            right
+                .score
+                .total_cmp(&left.score)
+                .then_with(|| left.doc_id.cmp(&right.doc_id))
+        };
+        if scored_vectors.len() > k && k > 0 {
+            scored_vectors.select_nth_unstable_by(k - 1, compare);
+        }
+        scored_vectors.truncate(k);
+        scored_vectors.sort_unstable_by(compare);
+        scored_vectors
+    }
+
+    fn prepare_query_vector(&self, query_vector: &[f32]) -> Vec<f32> {
+        let mut query_vector = query_vector.to_vec();
+
+        if self.similarity == VectorSimilarity::Cosine {
+            normalize(&mut query_vector);
+        }
+        query_vector
+    }
+
+    fn decode_vector(&self, bytes: &[u8], vector: &mut [f32]) {
+        match self.quantization {
+            VectorQuantization::F32 => {
+                for (component, chunk) in vector.iter_mut().zip(bytes.chunks_exact(4)) {
+                    *component = f32::from_le_bytes(chunk.try_into().unwrap());
+                }
+            }
+            VectorQuantization::I8 => {
+                let scale = f32::from_le_bytes(bytes[..4].try_into().unwrap());
+
+                for (component, quantized) in vector.iter_mut().zip(&bytes[4..]) {
+                    *component = *quantized as i8 as f32 * scale;
+                }
+            }
+        }
+    }
+
+    fn score(&self, query_vector: &[f32], vector: &[f32]) -> f32 {
+        match self.similarity {
+            VectorSimilarity::Cosine | VectorSimilarity::DotProduct => {
+                (1.0 + dot_product(query_vector, vector)) / 2.0
+            }
+            VectorSimilarity::L2Norm => 1.0 / (1.0 + squared_l2_distance(query_vector, vector)),
+        }
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn read_slice(&mut self, num_bytes: usize) -> anyhow::Result<&'a [u8]> {
+        ensure!(self.bytes.len() >= num_bytes, "vector index is truncated");
+        let (slice, rest) = self.bytes.split_at(num_bytes);
+        self.bytes = rest;
+        Ok(slice)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        Ok(self.read_slice(N)?.try_into().unwrap())
+    }
+}
+
+fn entry_num_bytes(dims: usize, quantization: VectorQuantization) -> usize {
+    let vector_num_bytes = match quantization {
+        VectorQuantization::F32 => dims * 4,
+        VectorQuantization::I8 => 4 + dims,
+    };
+    std::mem::size_of::<DocId>() + vector_num_bytes
+}
+
+fn similarity_to_code(similarity: VectorSimilarity) -> u8 {
+    match similarity {
+        VectorSimilarity::Cosine => 0,
+        VectorSimilarity::DotProduct => 1,
+        VectorSimilarity::L2Norm => 2,
+    }
+}
+
+fn similarity_from_code(code: u8) -> anyhow::Result<VectorSimilarity> {
+    match code {
+        0 => Ok(VectorSimilarity::Cosine),
+        1 => Ok(VectorSimilarity::DotProduct),
+        2 => Ok(VectorSimilarity::L2Norm),
+        _ => bail!("unknown vector similarity code `{code}`"),
+    }
+}
+
+fn quantization_to_code(quantization: VectorQuantization) -> u8 {
+    match quantization {
+        VectorQuantization::F32 => 0,
+        VectorQuantization::I8 => 1,
+    }
+}
+
+fn quantization_from_code(code: u8) -> anyhow::Result<VectorQuantization> {
+    match code {
+        0 => Ok(VectorQuantization::F32),
+        1 => Ok(VectorQuantization::I8),
+        _ => bail!("unknown vector quantization code `{code}`"),
+    }
+}
+
+/// Quantizes a vector to i8 components, scaled so that the largest absolute component maps to
+/// 127.
+fn quantize(vector: &[f32]) -> (f32, Vec<i8>) {
+    let max_abs = vector
+        .iter()
+        .map(|component| component.abs())
+        .fold(0f32, f32::max);
+    let scale = if max_abs > 0.0 { max_abs / 127.0 } else { 1.0 };
+    let quantized = vector
+        .iter()
+        .map(|component| (component / scale).round().clamp(-127.0, 127.0) as i8)
+        .collect();
+    (scale, quantized)
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = dot_product(vector, vector).sqrt();
+
+    if norm > 0.0 {
+        for component in vector {
+            *component /= norm;
+        }
+    }
+}
+
+fn dot_product(left: &[f32], right: &[f32]) -> f32 {
+    left.iter()
+        .zip(right)
+        .map(|(left, right)| left * right)
+        .sum()
+}
+
+fn squared_l2_distance(left: &[f32], right: &[f32]) -> f32 {
+    left.iter()
+        .zip(right)
+        .map(|(left, right)| (left - right) * (left - right))
+        .sum()
+}
+
+fn closest_centroid(centroids: &[f32], dims: usize, vector: &[f32]) -> usize {
+    centroids
+        .chunks_exact(dims)
+        .map(|centroid| squared_l2_distance(centroid, vector))
+        .enumerate()
+        .min_by(|(_, left), (_, right)| left.total_cmp(right))
+        .map(|(list, _)| list)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dense_vector_options(
+        dims: u32,
+        similarity: VectorSimilarity,
+        quantization: VectorQuantization,
+    ) -> QuickwitDenseVectorOptions {
+        QuickwitDenseVectorOptions {
+            description: None,
+            dims,
+            similarity,
+            quantization,
+            stored: true,
+        }
+    }
+
+    /// Builds an index of 1000 2D vectors on a circle, where the doc id is the angle in
+    /// thousandths of a turn.
+    fn build_circle_index(
+        similarity: VectorSimilarity,
+        quantization: VectorQuantization,
+    ) -> (VectorIndex, Vec<u8>) {
+        let options = dense_vector_options(2, similarity, quantization);
+        let mut builder = VectorIndexBuilder::new(&options);
+
+        for doc_id in 0..1_000 {
+            let angle = doc_id as f32 * std::f32::consts::TAU / 1_000.0;
+            builder.add(doc_id, &[angle.cos(), angle.sin()]);
+        }
+        // Ignored because of the dimensions mismatch.
+        builder.add(1_000, &[1.0, 0.0, 0.0]);
+
+        let index_bytes = builder.build();
+        let vector_index =
+            VectorIndex::open(&index_bytes.bytes[..index_bytes.hot_num_bytes]).unwrap();
+        assert_eq!(vector_index.num_vectors(), 1_000);
+        assert_eq!(vector_index.dims(), 2);
+        (vector_index, index_bytes.bytes)
+    }
+
+    fn search_circle_index(
+        vector_index: &VectorIndex,
+        bytes: &[u8],
+        query_vector: &[f32],
+        filter: impl Fn(DocId) -> bool,
+    ) -> Vec<ScoredVector> {
+        let lists = vector_index.probe(query_vector, 10);
+        assert!(lists.len() >= MIN_NUM_PROBED_LISTS);
+        assert!(lists.len() < vector_index.num_lists());
+
+        let list_bytes = lists
+            .into_iter()
+            .map(|list| &bytes[vector_index.list_byte_range(list)]);
+        vector_index.search(query_vector, list_bytes, filter, 3)
+    }
+
+    #[test]
+    fn test_vector_bytes_round_trip() {
+        let vector = [1.5, -0.25, 0.0];
+        let bytes = vector_to_bytes(&vector);
+        assert_eq!(bytes.len(), 12);
+        assert_eq!(vector_from_bytes(&bytes).unwrap(), vector);
+        assert!(vector_from_bytes(&bytes[1..]).is_none());
+    }
+
+    #[test]
+    fn test_vector_index_cosine_f32() {
+        let (vector_index, bytes) =
+            build_circle_index(VectorSimilarity::Cosine, VectorQuantization::F32);
+        // The norm of the query vector does not matter with the cosine similarity.
+        let scored_vectors = search_circle_index(&vector_index, &bytes, &[0.0, 10.0], |_| true);
+        assert_eq!(scored_vectors[0].doc_id, 250);
+        assert!((scored_vectors[0].score - 1.0).abs() < 1e-5);
+        assert!(scored_vectors[0].score > scored_vectors[1].score);
+        assert!(scored_vectors[1].score >= scored_vectors[2].score);
+
+        let mut doc_ids: Vec<DocId> = scored_vectors
+            .iter()
+            .map(|scored_vector| scored_vector.doc_id)
+            .collect();
+        doc_ids.sort();
+        assert_eq!(doc_ids, [249, 250, 251]);
+
+        let scored_vectors =
+            search_circle_index(&vector_index, &bytes, &[0.0, 1.0], |doc_id| doc_id < 250);
+        let doc_ids: Vec<DocId> = scored_vectors
+            .iter()
+            .map(|scored_vector| scored_vector.doc_id)
+            .collect();
+        assert_eq!(doc_ids, [249, 248, 247]);
+    }
+
+    #[test]
+    fn test_vector_index_cosine_i8() {
+        let (vector_index, bytes) =
+            build_circle_index(VectorSimilarity::Cosine, VectorQuantization::I8);
+        let scored_vectors = search_circle_index(&vector_index, &bytes, &[-1.0, 0.0], |_| true);
+        assert_eq!(scored_vectors.len(), 3);
+
+        for scored_vector in &scored_vectors {
+            assert!(scored_vector.doc_id.abs_diff(500) <= 2);
+            assert!(scored_vector.score > 0.99);
+        }
+    }
+
+    #[test]
+    fn test_vector_index_l2_norm() {
+        let (vector_index, bytes) =
+            build_circle_index(VectorSimilarity::L2Norm, VectorQuantization::F32);
+        let scored_vectors = search_circle_index(&vector_index, &bytes, &[0.0, -2.0], |_| true);
+        assert_eq!(scored_vectors[0].doc_id, 750);
+        assert!((scored_vectors[0].score - 0.5).abs() < 1e-5);
+
+        let mut doc_ids: Vec<DocId> = scored_vectors
+            .iter()
+            .map(|scored_vector| scored_vector.doc_id)
+            .collect();
+        doc_ids.sort();
+        assert_eq!(doc_ids, [749, 750, 751]);
+    }
+
+    #[test]
+    fn test_vector_index_empty() {
+        let options =
+            dense_vector_options(4, VectorSimilarity::DotProduct, VectorQuantization::F32);
+        let index_bytes = VectorIndexBuilder::new(&options).build();
+        assert_eq!(index_bytes.bytes.len(), index_bytes.hot_num_bytes);
+
+        let vector_index = VectorIndex::open(&index_bytes.bytes).unwrap();
+        assert_eq!(vector_index.num_vectors(), 0);
+        assert!(vector_index.probe(&[1.0, 0.0, 0.0, 0.0], 10).is_empty());
+    }
+
+    #[test]
+    fn test_vector_index_truncated() {
+        let options =
+            dense_vector_options(4, VectorSimilarity::DotProduct, VectorQuantization::F32);
+        let index_bytes = VectorIndexBuilder::new(&options).build();
+        VectorIndex::open(&index_bytes.bytes[..4]).unwrap_err();
+        VectorIndex::open(&index_bytes.bytes[..12]).unwrap_err();
+    }
+}
@@ -129,6 +129,11 @@ fn create_doc_processor(
         indexer_mailbox,
         transform_config_opt,
         SourceInputFormat::Json,
+        None,
+        None,
+        None,
+        None,
+        None,
     )
     .unwrap();
     let (mailbox, handle) = universe.spawn_builder().spawn(doc_processor);
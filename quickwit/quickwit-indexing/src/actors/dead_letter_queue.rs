@@ -0,0 +1,430 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, bail};
+use base64::prelude::{BASE64_STANDARD, Engine};
+use quickwit_actors::ActorContext;
+use quickwit_common::retry::RetryParams;
+use quickwit_config::{DeadLetterConfig, INGEST_V2_SOURCE_ID};
+use quickwit_ingest::DocBatchV2Builder;
+use quickwit_metastore::checkpoint::SourceCheckpointDelta;
+use quickwit_proto::ingest::CommitTypeV2;
+use quickwit_proto::ingest::router::{
+    IngestRequestV2, IngestRouterService, IngestRouterServiceClient, IngestSubrequest,
+};
+use quickwit_proto::types::{DocUidGenerator, IndexId, PipelineUid, SourceId};
+use quickwit_storage::{Storage, StorageResolver};
+use serde::Serialize;
+use time::OffsetDateTime;
+use tracing::warn;
+use ulid::Ulid;
+
+use super::DocProcessor;
+use super::doc_processor::DocProcessorError;
+use super::index_router::is_transient;
+
+/// Destination of the dead letter records.
+pub enum DeadLetterSink {
+    /// Records are ingested into another index via the ingest router.
+    Index {
+        index_id: IndexId,
+        ingest_router: IngestRouterServiceClient,
+    },
+    /// Records are written as newline-delimited JSON files.
+    Storage(Arc<dyn Storage>),
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SourcePosition {
+    partition_id: String,
+    from: String,
+    to: String,
+}
+
+/// A document rejected by the doc processor, along with the reason why it was rejected and where
+/// it came from.
+#[derive(Debug, Serialize)]
+struct DeadLetterRecord {
+    timestamp: i64,
+    index_id: IndexId,
+    source_id: SourceId,
+    pipeline_uid: String,
+    error_kind: &'static str,
+    error_message: String,
+    source_position: Vec<SourcePosition>,
+    /// The raw document, if it is valid UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doc: Option<String>,
+    /// The raw document encoded in base64 otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doc_base64: Option<String>,
+}
+
+/// Collects the documents rejected by the doc processor and writes them to the sink configured in
+/// the source's `dead_letter` parameters.
+///
+/// Records are buffered while a batch is processed and flushed before the batch is forwarded to
+/// the indexer, so that rejected documents are persisted before the source checkpoint moves past
+/// them. Ingest subrequests failing for a transient reason are retried with backoff. Failing to
+/// flush records fails the pipeline, so that the batch is replayed once the pipeline restarts.
+pub struct DeadLetterQueue {
+    index_id: IndexId,
+    source_id: SourceId,
+    pipeline_uid: PipelineUid,
+    sink: DeadLetterSink,
+    source_position: Vec<SourcePosition>,
+    records: Vec<DeadLetterRecord>,
+    retry_params: RetryParams,
+}
+
+impl DeadLetterQueue {
+    pub fn new(
+        index_id: IndexId,
+        source_id: SourceId,
+        pipeline_uid: PipelineUid,
+        sink: DeadLetterSink,
+    ) -> Self {
+        Self {
+            index_id,
+            source_id,
+            pipeline_uid,
+            sink,
+            source_position: Vec::new(),
+            records: Vec::new(),
+            retry_params: RetryParams::standard(),
+        }
+    }
+
+    pub async fn try_from_config(
+        dead_letter_config: &DeadLetterConfig,
+        index_id: IndexId,
+        source_id: SourceId,
+        pipeline_uid: PipelineUid,
+        ingest_router_opt: Option<IngestRouterServiceClient>,
+        storage_resolver: &StorageResolver,
+    ) -> anyhow::Result<Self> {
+        let sink = match dead_letter_config {
+            DeadLetterConfig::Index(dead_letter_index_id) => {
+                let ingest_router =
+                    ingest_router_opt.context("dead letter index requires the ingest API v2")?;
+                DeadLetterSink::Index {
+                    index_id: dead_letter_index_id.clone(),
+                    ingest_router,
+                }
+            }
+            DeadLetterConfig::Storage(storage_uri) => {
+                let storage = storage_resolver.resolve(storage_uri).await?;
+                DeadLetterSink::Storage(storage)
+            }
+        };
+        Ok(Self::new(index_id, source_id, pipeline_uid, sink))
+    }
+
+    /// Sets the source position attached to the records of the batch being processed.
+    pub fn set_source_position(&mut self, checkpoint_delta: &SourceCheckpointDelta) {
+        self.source_position = checkpoint_delta
+            .iter()
+            .map(|(partition_id, partition_delta)| SourcePosition {
+                partition_id: partition_id.to_string(),
+                from: partition_delta.from.to_string(),
+                to: partition_delta.to.to_string(),
+            })
+            .collect();
+    }
+
+    pub fn push(&mut self, raw_doc: &[u8], error: &DocProcessorError) {
+        let (doc, doc_base64) = match String::from_utf8(raw_doc.to_vec()) {
+            Ok(doc) => (Some(doc), None),
+            Err(_) => (None, Some(BASE64_STANDARD.encode(raw_doc))),
+        };
+        let record = DeadLetterRecord {
+            timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            index_id: self.index_id.clone(),
+            source_id: self.source_id.clone(),
+            pipeline_uid: self.pipeline_uid.to_string(),
+            error_kind: error.kind(),
+            error_message: error.to_string(),
+            source_position: self.source_position.clone(),
+            doc,
+            doc_base64,
+        };
+        self.records.push(record);
+    }
+
+    pub fn num_buffered_records(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Writes the buffered records to the sink.
+    pub async fn flush(&mut self, ctx: &ActorContext<DocProcessor>) -> anyhow::Result<()> {
+        if self.records.is_empty() {
+            return Ok(());
+        }
+        let records = std::mem::take(&mut self.records);
+        let num_records = records.len();
+
+        self.write_records(records, ctx)
+            .await
+            .with_context(|| format!("failed to write {num_records} dead letter record(s)"))
+    }
+
+    async fn write_records(
+        &self,
+        records: Vec<DeadLetterRecord>,
+        ctx: &ActorContext<DocProcessor>,
+    ) -> anyhow::Result<()> {
+        match &self.sink {
+            DeadLetterSink::Index {
+                index_id,
+                ingest_router,
+            } => {
+                let mut doc_uid_generator = DocUidGenerator::default();
+                let mut doc_batch_builder = DocBatchV2Builder::default();
+
+                for record in &records {
+                    let doc = serde_json::to_vec(record)?;
+                    doc_batch_builder.add_doc(doc_uid_generator.next_doc_uid(), &doc);
+                }
+                let doc_batch = doc_batch_builder.build();
+                let mut num_attempts = 0;
+
+                loop {
+                    num_attempts += 1;
+                    let ingest_request = IngestRequestV2 {
+                        subrequests: vec![IngestSubrequest {
+                            subrequest_id: 0,
+                            index_id: index_id.clone(),
+                            source_id: INGEST_V2_SOURCE_ID.to_string(),
+                            doc_batch: doc_batch.clone(),
+                        }],
+                        commit_type: CommitTypeV2::Auto as i32,
+                    };
+                    let ingest_result = {
+                        let _protect_guard = ctx.protect_zone();
+                        ingest_router.ingest(ingest_request).await
+                    };
+                    let error_msg = match ingest_result {
+                        Ok(ingest_response) => match ingest_response.failures.first() {
+                            None => break,
+                            Some(failure) if is_transient(failure.reason()) => {
+                                failure.reason().as_str_name().to_string()
+                            }
+                            Some(failure) => {
+                                bail!(
+                                    "failed to ingest into index `{index_id}`: {}",
+                                    failure.reason().as_str_name()
+                                );
+                            }
+                        },
+                        Err(error) => error.to_string(),
+                    };
+                    if num_attempts >= self.retry_params.max_attempts {
+                        bail!(
+                            "failed to ingest into index `{index_id}` after {num_attempts} \
+                             attempt(s): {error_msg}"
+                        );
+                    }
+                    warn!(
+                        index_id = self.index_id,
+                        source_id = self.source_id,
+                        num_attempts,
+                        "failed to ingest dead letter records, retrying: {error_msg}"
+                    );
+                    let _protect_guard = ctx.protect_zone();
+                    tokio::time::sleep(self.retry_params.compute_delay(num_attempts)).await;
+                }
+            }
+            DeadLetterSink::Storage(storage) => {
+                let mut payload: Vec<u8> = Vec::new();
+                for record in &records {
+                    serde_json::to_writer(&mut payload, record)?;
+                    payload.push(b'\n');
+                }
+                let path = self.dead_letter_file_path();
+                let _protect_guard = ctx.protect_zone();
+                storage.put(&path, Box::new(payload)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn dead_letter_file_path(&self) -> PathBuf {
+        PathBuf::from(&self.index_id)
+            .join(&self.source_id)
+            .join(format!("{}.ndjson", Ulid::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_metastore::checkpoint::PartitionId;
+    use quickwit_proto::ingest::router::{
+        IngestFailure, IngestFailureReason, IngestResponseV2, IngestSuccess,
+        MockIngestRouterService,
+    };
+    use quickwit_proto::types::Position;
+    use quickwit_storage::RamStorage;
+    use tokio::sync::watch;
+
+    use super::*;
+    use crate::actors::DocProcessorCounters;
+
+    fn ingest_failure(reason: IngestFailureReason) -> IngestResponseV2 {
+        IngestResponseV2 {
+            successes: Vec::new(),
+            failures: vec![IngestFailure {
+                subrequest_id: 0,
+                index_id: "my-dlq".to_string(),
+                source_id: INGEST_V2_SOURCE_ID.to_string(),
+                reason: reason as i32,
+            }],
+        }
+    }
+
+    async fn flush_to_index(mock_ingest_router: MockIngestRouterService) -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (doc_processor_mailbox, _doc_processor_inbox) =
+            universe.create_test_mailbox::<DocProcessor>();
+        let counters =
+            DocProcessorCounters::new("test-index".to_string(), "test-source".to_string());
+        let (observable_state_tx, _observable_state_rx) = watch::channel(Arc::new(counters));
+        let ctx = ActorContext::for_test(&universe, doc_processor_mailbox, observable_state_tx);
+
+        let mut dead_letter_queue = DeadLetterQueue::new(
+            "test-index".to_string(),
+            "test-source".to_string(),
+            PipelineUid::for_test(0),
+            DeadLetterSink::Index {
+                index_id: "my-dlq".to_string(),
+                ingest_router: IngestRouterServiceClient::from_mock(mock_ingest_router),
+            },
+        );
+        dead_letter_queue.retry_params = RetryParams::for_test();
+
+        let error = DocProcessorError::JsonParsing("expected value".to_string());
+        dead_letter_queue.push(b"{not json", &error);
+        let flush_result = dead_letter_queue.flush(&ctx).await;
+        assert_eq!(dead_letter_queue.num_buffered_records(), 0);
+        flush_result
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_queue_flush_to_index() {
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        let mut seq = mockall::Sequence::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(ingest_failure(IngestFailureReason::ShardRateLimited)));
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|ingest_request| {
+                assert_eq!(ingest_request.subrequests.len(), 1);
+
+                let subrequest = &ingest_request.subrequests[0];
+                assert_eq!(subrequest.index_id, "my-dlq");
+                assert_eq!(subrequest.source_id, INGEST_V2_SOURCE_ID);
+
+                let doc_batch = subrequest.doc_batch.as_ref().unwrap();
+                assert_eq!(doc_batch.num_docs(), 1);
+
+                let (_doc_uid, doc) = doc_batch.docs().next().unwrap();
+                let record_json: serde_json::Value = serde_json::from_slice(&doc).unwrap();
+                assert_eq!(record_json["error_kind"], "json_parsing");
+                assert_eq!(record_json["doc"], "{not json");
+
+                Ok(IngestResponseV2 {
+                    successes: vec![IngestSuccess {
+                        subrequest_id: 0,
+                        num_ingested_docs: 1,
+                        ..Default::default()
+                    }],
+                    failures: Vec::new(),
+                })
+            });
+        flush_to_index(mock_ingest_router).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_queue_flush_to_index_failure() {
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .returning(|_| Ok(ingest_failure(IngestFailureReason::IndexNotFound)));
+        let error = flush_to_index(mock_ingest_router).await.unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "failed to write 1 dead letter record(s): failed to ingest into index `my-dlq`: \
+             INGEST_FAILURE_REASON_INDEX_NOT_FOUND"
+        );
+
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .times(3)
+            .returning(|_| Ok(ingest_failure(IngestFailureReason::NoShardsAvailable)));
+        let error = flush_to_index(mock_ingest_router).await.unwrap_err();
+        assert!(format!("{error:#}").contains("after 3 attempt(s)"));
+    }
+
+    #[test]
+    fn test_dead_letter_queue_push() {
+        let storage = Arc::new(RamStorage::default());
+        let mut dead_letter_queue = DeadLetterQueue::new(
+            "test-index".to_string(),
+            "test-source".to_string(),
+            PipelineUid::for_test(0),
+            DeadLetterSink::Storage(storage),
+        );
+        let checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from("partition-1"),
+            Position::offset(10u64),
+            Position::offset(20u64),
+        )
+        .unwrap();
+        dead_letter_queue.set_source_position(&checkpoint_delta);
+
+        let error = DocProcessorError::JsonParsing("expected value".to_string());
+        dead_letter_queue.push(b"{not json", &error);
+        dead_letter_queue.push(&[0xff, 0xfe], &error);
+        assert_eq!(dead_letter_queue.num_buffered_records(), 2);
+
+        let record_json = serde_json::to_value(&dead_letter_queue.records[0]).unwrap();
+        assert_eq!(record_json["index_id"], "test-index");
+        assert_eq!(record_json["source_id"], "test-source");
+        assert_eq!(record_json["error_kind"], "json_parsing");
+        assert_eq!(
+            record_json["error_message"],
+            "JSON parse error: expected value"
+        );
+        assert_eq!(record_json["doc"], "{not json");
+        assert_eq!(
+            record_json["source_position"][0]["partition_id"],
+            "partition-1"
+        );
+        assert!(record_json.get("doc_base64").is_none());
+
+        let record_json = serde_json::to_value(&dead_letter_queue.records[1]).unwrap();
+        assert!(record_json.get("doc").is_none());
+        assert_eq!(record_json["doc_base64"], "//4=");
+    }
+}
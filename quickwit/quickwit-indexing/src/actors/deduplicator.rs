@@ -0,0 +1,122 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use quickwit_config::{DedupKey, DedupSettings};
+use quickwit_doc_mapper::JsonObject;
+
+/// Remembers the deduplication keys of the documents indexed by a pipeline during the dedup
+/// window, so that the doc processor can drop retried documents.
+///
+/// The keys are kept in memory and bounded by `max_keys`: when full, the oldest key is forgotten
+/// before it expires. Keys are lost when the pipeline restarts.
+pub(super) struct Deduplicator {
+    dedup_settings: DedupSettings,
+    window: Duration,
+    dedup_keys: HashSet<DedupKey>,
+    // Keys in insertion order, along with the instant they were first seen.
+    dedup_keys_by_age: VecDeque<(Instant, DedupKey)>,
+}
+
+impl Deduplicator {
+    pub fn new(dedup_settings: DedupSettings) -> Self {
+        let window = dedup_settings.window();
+        Self {
+            dedup_settings,
+            window,
+            dedup_keys: HashSet::new(),
+            dedup_keys_by_age: VecDeque::new(),
+        }
+    }
+
+    /// Returns the deduplication key of the document, if any.
+    pub fn dedup_key(&self, json_obj: &JsonObject) -> Option<DedupKey> {
+        self.dedup_settings.dedup_key(json_obj)
+    }
+
+    /// Records a deduplication key. Returns `false` if the key was already seen within the dedup
+    /// window, in which case the document is a duplicate.
+    pub fn insert(&mut self, dedup_key: DedupKey) -> bool {
+        self.insert_at(dedup_key, Instant::now())
+    }
+
+    fn insert_at(&mut self, dedup_key: DedupKey, now: Instant) -> bool {
+        while let Some((seen_at, oldest_dedup_key)) = self.dedup_keys_by_age.front()
+            && now.saturating_duration_since(*seen_at) >= self.window
+        {
+            self.dedup_keys.remove(oldest_dedup_key);
+            self.dedup_keys_by_age.pop_front();
+        }
+        if self.dedup_keys.contains(&dedup_key) {
+            return false;
+        }
+        if self.dedup_keys_by_age.len() >= self.dedup_settings.max_keys
+            && let Some((_, oldest_dedup_key)) = self.dedup_keys_by_age.pop_front()
+        {
+            self.dedup_keys.remove(&oldest_dedup_key);
+        }
+        self.dedup_keys.insert(dedup_key);
+        self.dedup_keys_by_age.push_back((now, dedup_key));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+
+    #[test]
+    fn test_deduplicator_window() {
+        let dedup_settings = DedupSettings {
+            window_secs: 60,
+            ..Default::default()
+        };
+        let mut deduplicator = Deduplicator::new(dedup_settings);
+        let start = Instant::now();
+        let dedup_key_a = DedupKey::from_json_value(&JsonValue::from("a"));
+        let dedup_key_b = DedupKey::from_json_value(&JsonValue::from("b"));
+
+        assert!(deduplicator.insert_at(dedup_key_a, start));
+        assert!(!deduplicator.insert_at(dedup_key_a, start + Duration::from_secs(30)));
+        assert!(deduplicator.insert_at(dedup_key_b, start + Duration::from_secs(30)));
+        assert!(deduplicator.insert_at(dedup_key_a, start + Duration::from_secs(60)));
+        assert!(!deduplicator.insert_at(dedup_key_b, start + Duration::from_secs(60)));
+        assert!(deduplicator.insert_at(dedup_key_b, start + Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn test_deduplicator_max_keys() {
+        let dedup_settings = DedupSettings {
+            max_keys: 2,
+            ..Default::default()
+        };
+        let mut deduplicator = Deduplicator::new(dedup_settings);
+        let now = Instant::now();
+        let dedup_key_a = DedupKey::from_json_value(&JsonValue::from("a"));
+        let dedup_key_b = DedupKey::from_json_value(&JsonValue::from("b"));
+        let dedup_key_c = DedupKey::from_json_value(&JsonValue::from("c"));
+
+        assert!(deduplicator.insert_at(dedup_key_a, now));
+        assert!(deduplicator.insert_at(dedup_key_b, now));
+        assert!(deduplicator.insert_at(dedup_key_c, now));
+        assert!(!deduplicator.insert_at(dedup_key_b, now));
+        assert!(!deduplicator.insert_at(dedup_key_c, now));
+        assert!(deduplicator.insert_at(dedup_key_a, now));
+        assert_eq!(deduplicator.dedup_keys.len(), 2);
+    }
+}
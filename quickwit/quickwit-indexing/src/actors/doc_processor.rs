@@ -23,8 +23,9 @@ use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, Qu
 use quickwit_common::metrics::IntCounter;
 use quickwit_common::rate_limited_tracing::rate_limited_warn;
 use quickwit_common::runtimes::RuntimeType;
-use quickwit_config::{SourceInputFormat, TransformConfig};
+use quickwit_config::{DedupSettings, LineFormatConfig, SourceInputFormat, TransformConfig};
 use quickwit_doc_mapper::{DocMapper, DocParsingError, JsonObject};
+use quickwit_metastore::checkpoint::PartitionId;
 use quickwit_opentelemetry::otlp::{
     JsonLogIterator, JsonSpanIterator, OtlpLogsError, OtlpTracesError, parse_otlp_logs_json,
     parse_otlp_logs_protobuf, parse_otlp_spans_json, parse_otlp_spans_protobuf,
@@ -40,6 +41,11 @@ use tokio::runtime::Handle;
 #[cfg(feature = "vrl")]
 use super::vrl_processing::*;
 use crate::actors::Indexer;
+use crate::actors::dead_letter_queue::DeadLetterQueue;
+use crate::actors::deduplicator::Deduplicator;
+use crate::actors::index_router::IndexRouter;
+use crate::actors::line_parser::LineParser;
+use crate::actors::schema_decoder::SchemaDecoder;
 use crate::models::{
     NewPublishLock, NewPublishToken, ProcessedDoc, ProcessedDocBatch, PublishLock, RawDocBatch,
 };
@@ -47,8 +53,8 @@ use crate::models::{
 const PLAIN_TEXT: &str = "plain_text";
 
 pub(super) struct JsonDoc {
-    json_obj: JsonObject,
-    num_bytes: usize,
+    pub(super) json_obj: JsonObject,
+    pub(super) num_bytes: usize,
 }
 
 impl JsonDoc {
@@ -89,11 +95,31 @@ pub enum DocProcessorError {
     OltpLogsParsing(OtlpLogsError),
     #[error("OLTP traces parse error: {0}")]
     OltpTracesParsing(OtlpTracesError),
+    #[error("schema decoding error: {0}")]
+    SchemaDecoding(String),
+    #[error("line parse error: {0}")]
+    LineParsing(String),
     #[cfg(feature = "vrl")]
     #[error("VRL transform error: {0}")]
     Transform(VrlTerminate),
 }
 
+impl DocProcessorError {
+    /// Returns a short identifier of the error kind.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DocProcessorError::DocMapperParsing(_) => "doc_mapper_parsing",
+            DocProcessorError::JsonParsing(_) => "json_parsing",
+            DocProcessorError::OltpLogsParsing(_) => "otlp_logs_parsing",
+            DocProcessorError::OltpTracesParsing(_) => "otlp_traces_parsing",
+            DocProcessorError::SchemaDecoding(_) => "schema_decoding",
+            DocProcessorError::LineParsing(_) => "line_parsing",
+            #[cfg(feature = "vrl")]
+            DocProcessorError::Transform(_) => "transform",
+        }
+    }
+}
+
 impl From<OtlpLogsError> for DocProcessorError {
     fn from(error: OtlpLogsError) -> Self {
         Self::OltpLogsParsing(error)
@@ -129,9 +155,14 @@ fn try_into_vrl_doc(
     input_format: SourceInputFormat,
     raw_doc: Bytes,
     num_bytes: usize,
+    schema_decoder_opt: Option<&SchemaDecoder>,
 ) -> Result<VrlDoc, DocProcessorError> {
     let vrl_value = match input_format {
         SourceInputFormat::Json => serde_json::from_slice::<VrlValue>(&raw_doc)?,
+        SourceInputFormat::Avro | SourceInputFormat::Protobuf => {
+            let json_obj = decode_with_schema(schema_decoder_opt, &raw_doc)?;
+            serde_json::from_value::<VrlValue>(JsonValue::Object(json_obj))?
+        }
         SourceInputFormat::PlainText => {
             let mut map = std::collections::BTreeMap::new();
             let key = vrl::value::KeyString::from(PLAIN_TEXT);
@@ -145,15 +176,31 @@ fn try_into_vrl_doc(
         | SourceInputFormat::OtlpTracesProtobuf => {
             panic!("OTP logs or traces do not support VRL transforms")
         }
+        SourceInputFormat::Csv
+        | SourceInputFormat::Logfmt
+        | SourceInputFormat::Grok
+        | SourceInputFormat::Regex => {
+            unreachable!("line formats should be parsed by the line parser")
+        }
     };
     let vrl_doc = VrlDoc::new(vrl_value, num_bytes);
     Ok(vrl_doc)
 }
 
+fn decode_with_schema(
+    schema_decoder_opt: Option<&SchemaDecoder>,
+    raw_doc: &[u8],
+) -> Result<JsonObject, DocProcessorError> {
+    schema_decoder_opt
+        .expect("`avro` and `protobuf` input formats should have a schema decoder")
+        .decode(raw_doc)
+}
+
 fn try_into_json_docs(
     input_format: SourceInputFormat,
     raw_doc: Bytes,
     num_bytes: usize,
+    schema_decoder_opt: Option<&SchemaDecoder>,
 ) -> JsonDocIterator {
     match input_format {
         SourceInputFormat::Json => {
@@ -161,6 +208,11 @@ fn try_into_json_docs(
                 .map(|json_obj| JsonDoc::new(json_obj, num_bytes));
             JsonDocIterator::from(json_doc_result)
         }
+        SourceInputFormat::Avro | SourceInputFormat::Protobuf => {
+            let json_doc_result = decode_with_schema(schema_decoder_opt, &raw_doc)
+                .map(|json_obj| JsonDoc::new(json_obj, num_bytes));
+            JsonDocIterator::from(json_doc_result)
+        }
         SourceInputFormat::OtlpLogsJson => {
             let logs = parse_otlp_logs_json(&raw_doc);
             JsonDocIterator::from(logs)
@@ -186,6 +238,12 @@ fn try_into_json_docs(
             });
             JsonDocIterator::from(json_doc_result)
         }
+        SourceInputFormat::Csv
+        | SourceInputFormat::Logfmt
+        | SourceInputFormat::Grok
+        | SourceInputFormat::Regex => {
+            unreachable!("line formats should be parsed by the line parser")
+        }
     }
 }
 
@@ -194,12 +252,13 @@ fn parse_raw_doc(
     input_format: SourceInputFormat,
     raw_doc: Bytes,
     num_bytes: usize,
+    schema_decoder_opt: Option<&SchemaDecoder>,
     vrl_program_opt: Option<&mut VrlProgram>,
 ) -> JsonDocIterator {
     let Some(vrl_program) = vrl_program_opt else {
-        return try_into_json_docs(input_format, raw_doc, num_bytes);
+        return try_into_json_docs(input_format, raw_doc, num_bytes, schema_decoder_opt);
     };
-    let json_doc_result = try_into_vrl_doc(input_format, raw_doc, num_bytes)
+    let json_doc_result = try_into_vrl_doc(input_format, raw_doc, num_bytes, schema_decoder_opt)
         .and_then(|vrl_doc| vrl_program.transform_doc(vrl_doc))
         .and_then(JsonDoc::try_from_vrl_doc);
 
@@ -211,9 +270,51 @@ fn parse_raw_doc(
     input_format: SourceInputFormat,
     raw_doc: Bytes,
     num_bytes: usize,
+    schema_decoder_opt: Option<&SchemaDecoder>,
+    _vrl_program_opt: Option<&mut VrlProgram>,
+) -> JsonDocIterator {
+    try_into_json_docs(input_format, raw_doc, num_bytes, schema_decoder_opt)
+}
+
+#[cfg(feature = "vrl")]
+fn parse_line(
+    line_parser: &mut LineParser,
+    partition_id: &PartitionId,
+    raw_doc: &[u8],
+    num_bytes: usize,
+    vrl_program_opt: Option<&mut VrlProgram>,
+) -> JsonDocIterator {
+    let json_obj = match line_parser.parse(partition_id, raw_doc) {
+        Ok(Some(json_obj)) => json_obj,
+        // CSV header rows are not documents.
+        Ok(None) => return JsonDocIterator::One(None),
+        Err(error) => return JsonDocIterator::One(Some(Err(error))),
+    };
+    let Some(vrl_program) = vrl_program_opt else {
+        return JsonDocIterator::One(Some(Ok(JsonDoc::new(json_obj, num_bytes))));
+    };
+    let json_doc_result = serde_json::from_value::<VrlValue>(JsonValue::Object(json_obj))
+        .map_err(DocProcessorError::from)
+        .and_then(|vrl_value| vrl_program.transform_doc(VrlDoc::new(vrl_value, num_bytes)))
+        .and_then(JsonDoc::try_from_vrl_doc);
+
+    JsonDocIterator::from(json_doc_result)
+}
+
+#[cfg(not(feature = "vrl"))]
+fn parse_line(
+    line_parser: &mut LineParser,
+    partition_id: &PartitionId,
+    raw_doc: &[u8],
+    num_bytes: usize,
     _vrl_program_opt: Option<&mut VrlProgram>,
 ) -> JsonDocIterator {
-    try_into_json_docs(input_format, raw_doc, num_bytes)
+    match line_parser.parse(partition_id, raw_doc) {
+        Ok(json_obj_opt) => {
+            JsonDocIterator::One(json_obj_opt.map(|json_obj| Ok(JsonDoc::new(json_obj, num_bytes))))
+        }
+        Err(error) => JsonDocIterator::One(Some(Err(error))),
+    }
 }
 
 enum JsonDocIterator {
@@ -314,18 +415,28 @@ pub struct DocProcessorCounters {
     source_id: SourceId,
 
     /// Overall number of documents received, partitioned
-    /// into 5 categories:
+    /// into 8 categories:
     /// - valid documents
     /// - number of docs that could not be parsed.
     /// - number of docs that were not valid json.
+    /// - number of docs that could not be decoded with their schema.
+    /// - number of lines that could not be parsed with their input format.
     /// - number of docs that could not be transformed.
     /// - number of docs for which the doc mapper returned an error.
     /// - number of valid docs.
+    /// - number of duplicate docs.
+    /// - number of docs routed to other indexes.
     pub valid: DocProcessorCounter,
     pub doc_mapper_errors: DocProcessorCounter,
     pub transform_errors: DocProcessorCounter,
     pub json_parse_errors: DocProcessorCounter,
     pub otlp_parse_errors: DocProcessorCounter,
+    pub schema_decoding_errors: DocProcessorCounter,
+    pub line_parse_errors: DocProcessorCounter,
+    /// Number of valid docs dropped because they were duplicates.
+    pub duplicates: DocProcessorCounter,
+    /// Number of valid docs routed to other indexes.
+    pub routed: DocProcessorCounter,
 
     /// Number of bytes that went through the indexer
     /// during its entire lifetime.
@@ -346,6 +457,15 @@ impl DocProcessorCounters {
             DocProcessorCounter::for_index_and_doc_processor_outcome(&index_id, "json_parse_error");
         let otlp_parse_errors =
             DocProcessorCounter::for_index_and_doc_processor_outcome(&index_id, "otlp_parse_error");
+        let schema_decoding_errors = DocProcessorCounter::for_index_and_doc_processor_outcome(
+            &index_id,
+            "schema_decoding_error",
+        );
+        let line_parse_errors =
+            DocProcessorCounter::for_index_and_doc_processor_outcome(&index_id, "line_parse_error");
+        let duplicates =
+            DocProcessorCounter::for_index_and_doc_processor_outcome(&index_id, "duplicate");
+        let routed = DocProcessorCounter::for_index_and_doc_processor_outcome(&index_id, "routed");
         DocProcessorCounters {
             index_id,
             source_id,
@@ -355,6 +475,10 @@ impl DocProcessorCounters {
             transform_errors,
             json_parse_errors,
             otlp_parse_errors,
+            schema_decoding_errors,
+            line_parse_errors,
+            duplicates,
+            routed,
             num_bytes_total: Default::default(),
         }
     }
@@ -365,7 +489,11 @@ impl DocProcessorCounters {
             + self.doc_mapper_errors.get_num_docs()
             + self.json_parse_errors.get_num_docs()
             + self.otlp_parse_errors.get_num_docs()
+            + self.schema_decoding_errors.get_num_docs()
+            + self.line_parse_errors.get_num_docs()
             + self.transform_errors.get_num_docs()
+            + self.duplicates.get_num_docs()
+            + self.routed.get_num_docs()
     }
 
     /// Returns the overall number of docs that were sent to the indexer but were invalid.
@@ -375,6 +503,8 @@ impl DocProcessorCounters {
         self.doc_mapper_errors.get_num_docs()
             + self.json_parse_errors.get_num_docs()
             + self.otlp_parse_errors.get_num_docs()
+            + self.schema_decoding_errors.get_num_docs()
+            + self.line_parse_errors.get_num_docs()
             + self.transform_errors.get_num_docs()
     }
 
@@ -383,6 +513,16 @@ impl DocProcessorCounters {
         self.valid.record_doc(num_bytes);
     }
 
+    pub fn record_duplicate(&self, num_bytes: u64) {
+        self.num_bytes_total.fetch_add(num_bytes, Ordering::Relaxed);
+        self.duplicates.record_doc(num_bytes);
+    }
+
+    pub fn record_routed(&self, num_bytes: u64) {
+        self.num_bytes_total.fetch_add(num_bytes, Ordering::Relaxed);
+        self.routed.record_doc(num_bytes);
+    }
+
     pub fn record_error(&self, error: DocProcessorError, num_bytes: u64) {
         self.num_bytes_total.fetch_add(num_bytes, Ordering::Relaxed);
         match error {
@@ -395,6 +535,12 @@ impl DocProcessorCounters {
             DocProcessorError::OltpLogsParsing(_) | DocProcessorError::OltpTracesParsing(_) => {
                 self.otlp_parse_errors.record_doc(num_bytes);
             }
+            DocProcessorError::SchemaDecoding(_) => {
+                self.schema_decoding_errors.record_doc(num_bytes);
+            }
+            DocProcessorError::LineParsing(_) => {
+                self.line_parse_errors.record_doc(num_bytes);
+            }
             #[cfg(feature = "vrl")]
             DocProcessorError::Transform(_) => {
                 self.transform_errors.record_doc(num_bytes);
@@ -412,6 +558,11 @@ pub struct DocProcessor {
     #[cfg(feature = "vrl")]
     transform_opt: Option<VrlProgram>,
     input_format: SourceInputFormat,
+    dead_letter_queue_opt: Option<DeadLetterQueue>,
+    schema_decoder_opt: Option<SchemaDecoder>,
+    line_parser_opt: Option<LineParser>,
+    deduplicator_opt: Option<Deduplicator>,
+    index_router_opt: Option<IndexRouter>,
 }
 
 impl DocProcessor {
@@ -422,11 +573,20 @@ impl DocProcessor {
         indexer_mailbox: Mailbox<Indexer>,
         transform_config_opt: Option<TransformConfig>,
         input_format: SourceInputFormat,
+        dead_letter_queue_opt: Option<DeadLetterQueue>,
+        schema_decoder_opt: Option<SchemaDecoder>,
+        line_format_config_opt: Option<LineFormatConfig>,
+        dedup_settings_opt: Option<DedupSettings>,
+        index_router_opt: Option<IndexRouter>,
     ) -> anyhow::Result<Self> {
         let timestamp_field_opt = extract_timestamp_field(&doc_mapper)?;
         if cfg!(not(feature = "vrl")) && transform_config_opt.is_some() {
             bail!("VRL is not enabled: please recompile with the `vrl` feature")
         }
+        if input_format.requires_schema() && schema_decoder_opt.is_none() {
+            bail!("input format `{input_format:?}` requires a schema decoder")
+        }
+        let line_parser_opt = LineParser::try_new(input_format, line_format_config_opt.as_ref())?;
         Ok(DocProcessor {
             doc_mapper,
             indexer_mailbox,
@@ -438,6 +598,11 @@ impl DocProcessor {
                 .map(VrlProgram::try_from_transform_config)
                 .transpose()?,
             input_format,
+            dead_letter_queue_opt,
+            schema_decoder_opt,
+            line_parser_opt,
+            deduplicator_opt: dedup_settings_opt.map(Deduplicator::new),
+            index_router_opt,
         })
     }
 
@@ -461,7 +626,12 @@ impl DocProcessor {
         Ok(Some(timestamp))
     }
 
-    fn process_raw_doc(&mut self, raw_doc: Bytes, processed_docs: &mut Vec<ProcessedDoc>) {
+    fn process_raw_doc(
+        &mut self,
+        partition_id: &PartitionId,
+        raw_doc: Bytes,
+        processed_docs: &mut Vec<ProcessedDoc>,
+    ) {
         let num_bytes = raw_doc.len();
 
         #[cfg(feature = "vrl")]
@@ -469,24 +639,86 @@ impl DocProcessor {
         #[cfg(not(feature = "vrl"))]
         let transform_opt: Option<&mut VrlProgram> = None;
 
-        for json_doc_result in parse_raw_doc(self.input_format, raw_doc, num_bytes, transform_opt) {
-            let processed_doc_result =
-                json_doc_result.and_then(|json_doc| self.process_json_doc(json_doc));
+        // `Bytes` clones are cheap, but we only need the raw doc if we have to dead-letter it.
+        let raw_doc_opt = self.dead_letter_queue_opt.as_ref().map(|_| raw_doc.clone());
+
+        let json_doc_iter = if let Some(line_parser) = &mut self.line_parser_opt {
+            parse_line(
+                line_parser,
+                partition_id,
+                &raw_doc,
+                num_bytes,
+                transform_opt,
+            )
+        } else {
+            parse_raw_doc(
+                self.input_format,
+                raw_doc,
+                num_bytes,
+                self.schema_decoder_opt.as_ref(),
+                transform_opt,
+            )
+        };
+        for json_doc_result in json_doc_iter {
+            let json_doc_result = match (json_doc_result, &mut self.index_router_opt) {
+                (Ok(json_doc), Some(index_router)) => match index_router.route(json_doc) {
+                    Some(json_doc) => Ok(json_doc),
+                    // The document is ingested into another index when the router is flushed.
+                    None => continue,
+                },
+                (json_doc_result, _) => json_doc_result,
+            };
+            self.process_json_doc_result(
+                json_doc_result,
+                raw_doc_opt.as_ref(),
+                num_bytes,
+                processed_docs,
+            );
+        }
+    }
 
-            match processed_doc_result {
-                Ok(processed_doc) => {
-                    self.counters.record_valid(processed_doc.num_bytes as u64);
-                    processed_docs.push(processed_doc);
+    fn process_json_doc_result(
+        &mut self,
+        json_doc_result: Result<JsonDoc, DocProcessorError>,
+        raw_doc_opt: Option<&Bytes>,
+        num_bytes: usize,
+        processed_docs: &mut Vec<ProcessedDoc>,
+    ) {
+        // The dedup key is extracted before the doc mapper consumes the document.
+        let mut dedup_key_opt = None;
+        let processed_doc_result = json_doc_result.and_then(|json_doc| {
+            if let Some(deduplicator) = &self.deduplicator_opt {
+                dedup_key_opt = deduplicator.dedup_key(&json_doc.json_obj);
+            }
+            self.process_json_doc(json_doc)
+        });
+
+        match processed_doc_result {
+            Ok(processed_doc) => {
+                if let Some(deduplicator) = &mut self.deduplicator_opt
+                    && let Some(dedup_key) = dedup_key_opt
+                    && !deduplicator.insert(dedup_key)
+                {
+                    self.counters
+                        .record_duplicate(processed_doc.num_bytes as u64);
+                    return;
                 }
-                Err(error) => {
-                    rate_limited_warn!(
-                        limit_per_min = 10,
-                        index_id = self.counters.index_id,
-                        source_id = self.counters.source_id,
-                        "{error}",
-                    );
-                    self.counters.record_error(error, num_bytes as u64);
+                self.counters.record_valid(processed_doc.num_bytes as u64);
+                processed_docs.push(processed_doc);
+            }
+            Err(error) => {
+                rate_limited_warn!(
+                    limit_per_min = 10,
+                    index_id = self.counters.index_id,
+                    source_id = self.counters.source_id,
+                    "{error}",
+                );
+                if let Some(dead_letter_queue) = &mut self.dead_letter_queue_opt
+                    && let Some(raw_doc) = raw_doc_opt
+                {
+                    dead_letter_queue.push(raw_doc, &error);
                 }
+                self.counters.record_error(error, num_bytes as u64);
             }
         }
     }
@@ -574,11 +806,60 @@ impl Handler<RawDocBatch> for DocProcessor {
         }
         let mut processed_docs: Vec<ProcessedDoc> = Vec::with_capacity(raw_doc_batch.docs.len());
 
+        if let Some(dead_letter_queue) = &mut self.dead_letter_queue_opt {
+            dead_letter_queue.set_source_position(&raw_doc_batch.checkpoint_delta);
+        }
+        // Fails the pipeline if the schema registry is unavailable, so that the batch is retried
+        // once the pipeline restarts instead of rejecting its documents.
+        if let Some(schema_decoder) = &mut self.schema_decoder_opt {
+            let _protected_zone_guard = ctx.protect_zone();
+            schema_decoder
+                .resolve_schemas(&raw_doc_batch.docs)
+                .await
+                .context("failed to resolve schemas")?;
+        }
+        // Sources emit the lines of a file in batches that cover a single partition. The stdin
+        // source does not report positions, so its checkpoint delta is empty.
+        let partition_id = raw_doc_batch
+            .checkpoint_delta
+            .partitions()
+            .next()
+            .cloned()
+            .unwrap_or_default();
+
         for raw_doc in raw_doc_batch.docs {
             let _protected_zone_guard = ctx.protect_zone();
-            self.process_raw_doc(raw_doc, &mut processed_docs);
+            self.process_raw_doc(&partition_id, raw_doc, &mut processed_docs);
             ctx.record_progress();
         }
+        // Fails the pipeline if the routed documents cannot be ingested after retrying, so that the
+        // batch is replayed once the pipeline restarts. Documents routed to indexes that do not
+        // exist are indexed locally when the index routing config allows it.
+        if let Some(index_router) = &mut self.index_router_opt {
+            let unrouted_docs = {
+                let _protected_zone_guard = ctx.protect_zone();
+                index_router
+                    .flush(&self.counters)
+                    .await
+                    .context("failed to route documents")?
+            };
+            for json_doc in unrouted_docs {
+                let num_bytes = json_doc.num_bytes;
+                self.process_json_doc_result(Ok(json_doc), None, num_bytes, &mut processed_docs);
+            }
+        }
+        if let Some(line_parser) = &mut self.line_parser_opt {
+            for (partition_id, partition_delta) in raw_doc_batch.checkpoint_delta.iter() {
+                if partition_delta.to.is_eof() {
+                    line_parser.end_partition(&partition_id);
+                }
+            }
+        }
+        // Rejected docs must be persisted before the checkpoint delta reaches the indexer, so the
+        // pipeline fails if they cannot be written and the batch is replayed once it restarts.
+        if let Some(dead_letter_queue) = &mut self.dead_letter_queue_opt {
+            dead_letter_queue.flush(ctx).await?;
+        }
         let processed_doc_batch = ProcessedDocBatch::new(
             processed_docs,
             raw_doc_batch.checkpoint_delta,
@@ -627,7 +908,7 @@ mod tests {
     use prost::Message;
     use quickwit_actors::Universe;
     use quickwit_common::uri::Uri;
-    use quickwit_config::{SearchSettings, build_doc_mapper};
+    use quickwit_config::{CsvConfig, SearchSettings, build_doc_mapper};
     use quickwit_doc_mapper::{DocMapper, default_doc_mapper_for_test};
     use quickwit_metastore::checkpoint::SourceCheckpointDelta;
     use quickwit_opentelemetry::otlp::{OtlpGrpcLogsService, OtlpGrpcTracesService};
@@ -637,11 +918,14 @@ mod tests {
     use quickwit_proto::opentelemetry::proto::common::v1::any_value::Value as OtlpAnyValueValue;
     use quickwit_proto::opentelemetry::proto::logs::v1::{LogRecord, ResourceLogs, ScopeLogs};
     use quickwit_proto::opentelemetry::proto::trace::v1::{ResourceSpans, ScopeSpans, Span};
+    use quickwit_proto::types::PipelineUid;
+    use quickwit_storage::{RamStorage, Storage};
     use serde_json::Value as JsonValue;
     use tantivy::Document;
     use tantivy::schema::NamedFieldDocument;
 
     use super::*;
+    use crate::actors::DeadLetterSink;
     use crate::models::{PublishLock, RawDocBatch};
 
     #[tokio::test]
@@ -658,6 +942,11 @@ mod tests {
             indexer_mailbox,
             None,
             SourceInputFormat::Json,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
         let (doc_processor_mailbox, doc_processor_handle) =
@@ -722,6 +1011,145 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_doc_processor_dedup() {
+        let universe = Universe::with_accelerated_time();
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let (indexer_mailbox, indexer_inbox) = universe.create_test_mailbox();
+        let dedup_settings = DedupSettings {
+            field: Some("body".to_string()),
+            ..Default::default()
+        };
+        let doc_processor = DocProcessor::try_new(
+            "my-index".to_string(),
+            "my-source".to_string(),
+            doc_mapper,
+            indexer_mailbox,
+            None,
+            SourceInputFormat::Json,
+            None,
+            None,
+            None,
+            Some(dedup_settings),
+            None,
+        )
+        .unwrap();
+        let (doc_processor_mailbox, doc_processor_handle) =
+            universe.spawn_builder().spawn(doc_processor);
+        doc_processor_mailbox
+            .send_message(RawDocBatch::for_test(
+                &[
+                    br#"{"body": "happy", "response_date": "2021-12-19T16:39:57+00:00", "response_time": 12, "response_payload": "YWJj"}"#, // missing timestamp
+                    br#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#, // ok
+                    br#"{"body": "happy", "timestamp": 1628837063, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#, // duplicate
+                    br#"{"body": "happy2", "timestamp": 1628837062, "response_date": "2021-12-19T16:40:57+00:00", "response_time": 13, "response_payload": "YWJj"}"#, // ok
+                ],
+                0..4,
+            ))
+            .await
+            .unwrap();
+
+        let counters = doc_processor_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(counters.doc_mapper_errors.get_num_docs(), 1);
+        assert_eq!(counters.duplicates.get_num_docs(), 1);
+        assert_eq!(counters.valid.get_num_docs(), 2);
+        assert_eq!(counters.num_processed_docs(), 4);
+        assert_eq!(counters.num_invalid_docs(), 1);
+
+        let output_messages = indexer_inbox.drain_for_test();
+        assert_eq!(output_messages.len(), 1);
+        let batch = output_messages
+            .into_iter()
+            .next()
+            .unwrap()
+            .downcast::<ProcessedDocBatch>()
+            .unwrap();
+        assert_eq!(batch.docs.len(), 2);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_doc_processor_dead_letter_queue() {
+        let index_id = "my-index";
+        let source_id = "my-source";
+        let universe = Universe::with_accelerated_time();
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let (indexer_mailbox, indexer_inbox) = universe.create_test_mailbox();
+        let storage = Arc::new(RamStorage::default());
+        let dead_letter_queue = DeadLetterQueue::new(
+            index_id.to_string(),
+            source_id.to_string(),
+            PipelineUid::for_test(1),
+            DeadLetterSink::Storage(storage.clone()),
+        );
+        let doc_processor = DocProcessor::try_new(
+            index_id.to_string(),
+            source_id.to_string(),
+            doc_mapper,
+            indexer_mailbox,
+            None,
+            SourceInputFormat::Json,
+            Some(dead_letter_queue),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let (doc_processor_mailbox, doc_processor_handle) =
+            universe.spawn_builder().spawn(doc_processor);
+        doc_processor_mailbox
+            .send_message(RawDocBatch::for_test(
+                &[
+                    br#"{"body": "happy", "response_date": "2021-12-19T16:39:57+00:00", "response_time": 12, "response_payload": "YWJj"}"#, // missing timestamp
+                    br#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#, // ok
+                    b"{", // invalid json
+                ],
+                0..3,
+            ))
+            .await.unwrap();
+
+        let counters = doc_processor_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(counters.num_invalid_docs(), 2);
+        assert_eq!(counters.valid.get_num_docs(), 1);
+
+        let output_messages = indexer_inbox.drain_for_test();
+        assert_eq!(output_messages.len(), 1);
+
+        let dead_letter_files = storage.list_files().await;
+        assert_eq!(dead_letter_files.len(), 1);
+        assert!(dead_letter_files[0].starts_with("my-index/my-source"));
+
+        let dead_letter_file = storage.get_all(&dead_letter_files[0]).await.unwrap();
+        let records: Vec<JsonValue> = std::str::from_utf8(dead_letter_file.as_slice())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["error_kind"], "doc_mapper_parsing");
+        assert_eq!(
+            records[0]["pipeline_uid"],
+            PipelineUid::for_test(1).to_string()
+        );
+        assert!(
+            records[0]["doc"]
+                .as_str()
+                .unwrap()
+                .contains("2021-12-19T16:39:57")
+        );
+        assert_eq!(records[1]["error_kind"], "json_parsing");
+        assert_eq!(records[1]["doc"], "{");
+        assert_eq!(records[1]["source_position"].as_array().unwrap().len(), 1);
+        universe.assert_quit().await;
+    }
+
     const DOCMAPPER_WITH_PARTITION_JSON: &str = r#"
         {
             "tag_fields": ["tenant"],
@@ -745,6 +1173,11 @@ mod tests {
             indexer_mailbox,
             None,
             SourceInputFormat::Json,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
         let (doc_processor_mailbox, doc_processor_handle) =
@@ -781,6 +1214,77 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_doc_processor_csv_input() {
+        let index_id = "my-index";
+        let source_id = "my-source";
+        let universe = Universe::with_accelerated_time();
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let (indexer_mailbox, indexer_inbox) = universe.create_test_mailbox();
+        let csv_config = CsvConfig {
+            columns: None,
+            has_header: true,
+            delimiter: ',',
+            quote: '"',
+        };
+        let doc_processor = DocProcessor::try_new(
+            index_id.to_string(),
+            source_id.to_string(),
+            doc_mapper.clone(),
+            indexer_mailbox,
+            None,
+            SourceInputFormat::Csv,
+            None,
+            None,
+            Some(LineFormatConfig::Csv(csv_config)),
+            None,
+            None,
+        )
+        .unwrap();
+        let (doc_processor_mailbox, doc_processor_handle) =
+            universe.spawn_builder().spawn(doc_processor);
+        doc_processor_mailbox
+            .send_message(RawDocBatch::for_test(
+                &[
+                    b"body,timestamp,response_time\n",
+                    b"\"happy, indeed\",2021-12-19T16:39:59Z,2\n",
+                    b"\"unterminated,2021-12-19T16:39:59Z,2\n",
+                    b"happy2,2021-12-19T16:40:57Z,\n",
+                ],
+                0..4,
+            ))
+            .await
+            .unwrap();
+        let counters = doc_processor_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(counters.line_parse_errors.get_num_docs(), 1);
+        assert_eq!(counters.valid.get_num_docs(), 2);
+
+        let output_messages = indexer_inbox.drain_for_test();
+        assert_eq!(output_messages.len(), 1);
+        let batch = *(output_messages
+            .into_iter()
+            .next()
+            .unwrap()
+            .downcast::<ProcessedDocBatch>()
+            .unwrap());
+        assert_eq!(batch.docs.len(), 2);
+
+        let schema = doc_mapper.schema();
+        let NamedFieldDocument(named_field_doc_map) = batch.docs[1].doc.to_named_doc(&schema);
+        let doc_json = JsonValue::Object(doc_mapper.doc_to_json(named_field_doc_map).unwrap());
+        assert_eq!(
+            doc_json["_source"],
+            serde_json::json!({
+                "body": "happy2",
+                "timestamp": "2021-12-19T16:40:57Z",
+            })
+        );
+        universe.assert_quit().await;
+    }
+
     #[tokio::test]
     async fn test_doc_processor_forward_publish_lock() {
         let doc_mapper = Arc::new(default_doc_mapper_for_test());
@@ -793,6 +1297,11 @@ mod tests {
             indexer_mailbox,
             None,
             SourceInputFormat::Json,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
         let (doc_processor_mailbox, doc_processor_handle) =
@@ -825,6 +1334,11 @@ mod tests {
             indexer_mailbox,
             None,
             SourceInputFormat::Json,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
         let (doc_processor_mailbox, doc_processor_handle) =
@@ -871,6 +1385,11 @@ mod tests {
             indexer_mailbox,
             None,
             SourceInputFormat::OtlpLogsJson,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -948,6 +1467,11 @@ mod tests {
             indexer_mailbox,
             None,
             SourceInputFormat::OtlpLogsProtobuf,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -1027,6 +1551,11 @@ mod tests {
             indexer_mailbox,
             None,
             SourceInputFormat::OtlpTracesJson,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -1100,6 +1629,11 @@ mod tests {
             indexer_mailbox,
             None,
             SourceInputFormat::OtlpTracesProtobuf,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -1186,6 +1720,11 @@ mod tests_vrl {
             indexer_mailbox,
             Some(transform_config),
             SourceInputFormat::Json,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
         let (doc_processor_mailbox, doc_processor_handle) =
@@ -1277,6 +1816,11 @@ mod tests_vrl {
             indexer_mailbox,
             Some(transform_config),
             SourceInputFormat::PlainText,
+            None,
+            None,
+            None,
+            None,
+            None,
         )
         .unwrap();
         let (doc_processor_mailbox, doc_processor_handle) =
@@ -0,0 +1,458 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::bail;
+use quickwit_common::retry::RetryParams;
+use quickwit_config::{INGEST_V2_SOURCE_ID, IndexIdTemplate, IndexRoutingConfig};
+use quickwit_ingest::DocBatchV2Builder;
+use quickwit_proto::ingest::CommitTypeV2;
+use quickwit_proto::ingest::router::{
+    IngestFailureReason, IngestRequestV2, IngestRouterService, IngestRouterServiceClient,
+    IngestSubrequest,
+};
+use quickwit_proto::types::{DocUidGenerator, IndexId};
+use tracing::warn;
+
+use super::DocProcessorCounters;
+use super::doc_processor::JsonDoc;
+
+/// Routes the documents of a source to other indexes, according to the index ID template of the
+/// source.
+///
+/// Routed documents are buffered, then ingested into the ingest source of their index via the
+/// ingest router, which creates the missing indexes from the matching index templates. The
+/// documents routed to the index of the pipeline, or for which the template yields no valid index
+/// ID, are indexed by the pipeline itself, as are the documents routed to indexes that do not
+/// exist when `fallback_to_source_index` is set.
+///
+/// Routed documents are delivered at least once: they are ingested before the pipeline publishes
+/// its checkpoint, so they are ingested again if the pipeline fails and replays the batch.
+pub struct IndexRouter {
+    index_id: IndexId,
+    index_id_template: IndexIdTemplate,
+    ingest_router: IngestRouterServiceClient,
+    routed_docs: BTreeMap<IndexId, Vec<JsonDoc>>,
+    fallback_to_source_index: bool,
+    retry_params: RetryParams,
+}
+
+impl IndexRouter {
+    pub fn try_new(
+        index_routing_config: &IndexRoutingConfig,
+        index_id: IndexId,
+        ingest_router: IngestRouterServiceClient,
+    ) -> anyhow::Result<Self> {
+        let index_id_template = index_routing_config.index_id_template()?;
+        Ok(Self {
+            index_id,
+            index_id_template,
+            ingest_router,
+            routed_docs: BTreeMap::new(),
+            fallback_to_source_index: index_routing_config.fallback_to_source_index,
+            retry_params: RetryParams::standard(),
+        })
+    }
+
+    /// Buffers the document if it is routed to another index, otherwise returns it.
+    pub(super) fn route(&mut self, json_doc: JsonDoc) -> Option<JsonDoc> {
+        match self.index_id_template.render(&json_doc.json_obj) {
+            Some(index_id) if index_id != self.index_id => {
+                self.routed_docs.entry(index_id).or_default().push(json_doc);
+                None
+            }
+            _ => Some(json_doc),
+        }
+    }
+
+    /// Ingests the buffered documents into their index. Subrequests failing for a transient reason
+    /// are retried with backoff.
+    ///
+    /// With `fallback_to_source_index`, returns the documents routed to indexes that do not exist
+    /// and could not be created from an index template, which are indexed by the pipeline instead.
+    /// Otherwise, these documents fail the flush.
+    pub(super) async fn flush(
+        &mut self,
+        counters: &DocProcessorCounters,
+    ) -> anyhow::Result<Vec<JsonDoc>> {
+        let mut pending_docs = std::mem::take(&mut self.routed_docs);
+        let mut unrouted_docs = Vec::new();
+        let mut num_attempts = 0;
+
+        while !pending_docs.is_empty() {
+            num_attempts += 1;
+            let routed_docs: Vec<(IndexId, Vec<JsonDoc>)> = pending_docs.into_iter().collect();
+            let ingest_request = build_ingest_request(&routed_docs)?;
+            let failures = match self.ingest_router.ingest(ingest_request).await {
+                Ok(ingest_response) => ingest_response.failures,
+                Err(error) if num_attempts < self.retry_params.max_attempts => {
+                    warn!(
+                        index_id = self.index_id,
+                        num_attempts, "failed to route documents, retrying: {error}"
+                    );
+                    pending_docs = routed_docs.into_iter().collect();
+                    tokio::time::sleep(self.retry_params.compute_delay(num_attempts)).await;
+                    continue;
+                }
+                Err(error) => return Err(error.into()),
+            };
+            let mut failure_reasons = HashMap::with_capacity(failures.len());
+
+            for failure in &failures {
+                failure_reasons.insert(failure.subrequest_id as usize, failure.reason());
+            }
+            pending_docs = BTreeMap::new();
+
+            for (subrequest_id, (index_id, json_docs)) in routed_docs.into_iter().enumerate() {
+                match failure_reasons.get(&subrequest_id) {
+                    None => {
+                        for json_doc in &json_docs {
+                            counters.record_routed(json_doc.num_bytes as u64);
+                        }
+                    }
+                    Some(IngestFailureReason::IndexNotFound)
+                    | Some(IngestFailureReason::SourceNotFound)
+                        if self.fallback_to_source_index =>
+                    {
+                        warn!(
+                            index_id = self.index_id,
+                            target_index_id = index_id,
+                            "failed to route documents: index not found, indexing them locally"
+                        );
+                        unrouted_docs.extend(json_docs);
+                    }
+                    Some(reason)
+                        if is_transient(*reason)
+                            && num_attempts < self.retry_params.max_attempts =>
+                    {
+                        warn!(
+                            index_id = self.index_id,
+                            target_index_id = index_id,
+                            num_attempts,
+                            "failed to route documents, retrying: {}",
+                            reason.as_str_name()
+                        );
+                        pending_docs.insert(index_id, json_docs);
+                    }
+                    Some(reason) => {
+                        bail!(
+                            "failed to route documents to index `{index_id}` after {num_attempts} \
+                             attempt(s): {}",
+                            reason.as_str_name()
+                        );
+                    }
+                }
+            }
+            if !pending_docs.is_empty() {
+                tokio::time::sleep(self.retry_params.compute_delay(num_attempts)).await;
+            }
+        }
+        Ok(unrouted_docs)
+    }
+}
+
+fn build_ingest_request(
+    routed_docs: &[(IndexId, Vec<JsonDoc>)],
+) -> anyhow::Result<IngestRequestV2> {
+    let mut doc_uid_generator = DocUidGenerator::default();
+    let mut subrequests = Vec::with_capacity(routed_docs.len());
+
+    for (subrequest_id, (index_id, json_docs)) in routed_docs.iter().enumerate() {
+        let mut doc_batch_builder = DocBatchV2Builder::default();
+
+        for json_doc in json_docs {
+            let doc = serde_json::to_vec(&json_doc.json_obj)?;
+            doc_batch_builder.add_doc(doc_uid_generator.next_doc_uid(), &doc);
+        }
+        let subrequest = IngestSubrequest {
+            subrequest_id: subrequest_id as u32,
+            index_id: index_id.clone(),
+            source_id: INGEST_V2_SOURCE_ID.to_string(),
+            doc_batch: doc_batch_builder.build(),
+        };
+        subrequests.push(subrequest);
+    }
+    let ingest_request = IngestRequestV2 {
+        subrequests,
+        commit_type: CommitTypeV2::Auto as i32,
+    };
+    Ok(ingest_request)
+}
+
+/// Returns whether an ingest subrequest that failed for this reason may succeed if retried.
+pub(super) fn is_transient(reason: IngestFailureReason) -> bool {
+    matches!(
+        reason,
+        IngestFailureReason::NoShardsAvailable
+            | IngestFailureReason::ShardRateLimited
+            | IngestFailureReason::WalFull
+            | IngestFailureReason::Timeout
+            | IngestFailureReason::RouterLoadShedding
+            | IngestFailureReason::LoadShedding
+            | IngestFailureReason::CircuitBreaker
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_doc_mapper::JsonObject;
+    use quickwit_proto::ingest::router::{
+        IngestFailure, IngestResponseV2, IngestSuccess, MockIngestRouterService,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    fn json_doc(json: serde_json::Value) -> JsonDoc {
+        let json_obj: JsonObject = serde_json::from_value(json).unwrap();
+        JsonDoc::new(json_obj, 10)
+    }
+
+    #[tokio::test]
+    async fn test_index_router() {
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .returning(|ingest_request| {
+                assert_eq!(ingest_request.subrequests.len(), 2);
+
+                let subrequest_0 = &ingest_request.subrequests[0];
+                assert_eq!(subrequest_0.index_id, "logs-team-a");
+                assert_eq!(subrequest_0.source_id, INGEST_V2_SOURCE_ID);
+                assert_eq!(subrequest_0.doc_batch.as_ref().unwrap().num_docs(), 2);
+
+                let subrequest_1 = &ingest_request.subrequests[1];
+                assert_eq!(subrequest_1.index_id, "logs-team-b");
+                assert_eq!(subrequest_1.doc_batch.as_ref().unwrap().num_docs(), 1);
+
+                Ok(IngestResponseV2 {
+                    successes: vec![IngestSuccess {
+                        subrequest_id: 0,
+                        num_ingested_docs: 2,
+                        ..Default::default()
+                    }],
+                    failures: vec![IngestFailure {
+                        subrequest_id: 1,
+                        index_id: "logs-team-b".to_string(),
+                        source_id: INGEST_V2_SOURCE_ID.to_string(),
+                        reason: IngestFailureReason::IndexNotFound as i32,
+                    }],
+                })
+            });
+        let index_routing_config = IndexRoutingConfig {
+            index_id: "logs-{namespace}".to_string(),
+            fallback_to_source_index: true,
+        };
+        let mut index_router = IndexRouter::try_new(
+            &index_routing_config,
+            "logs-default".to_string(),
+            IngestRouterServiceClient::from_mock(mock_ingest_router),
+        )
+        .unwrap();
+        assert!(
+            index_router
+                .route(json_doc(json!({"namespace": "team-a", "body": "foo"})))
+                .is_none()
+        );
+        assert!(
+            index_router
+                .route(json_doc(json!({"namespace": "team-b"})))
+                .is_none()
+        );
+        assert!(
+            index_router
+                .route(json_doc(json!({"namespace": "team-a", "body": "bar"})))
+                .is_none()
+        );
+        assert!(
+            index_router
+                .route(json_doc(json!({"namespace": "default"})))
+                .is_some()
+        );
+        assert!(
+            index_router
+                .route(json_doc(json!({"body": "baz"})))
+                .is_some()
+        );
+
+        let counters =
+            DocProcessorCounters::new("logs-default".to_string(), "my-source".to_string());
+        let unrouted_docs = index_router.flush(&counters).await.unwrap();
+        assert_eq!(unrouted_docs.len(), 1);
+        assert_eq!(unrouted_docs[0].json_obj["namespace"], "team-b");
+        assert_eq!(counters.routed.get_num_docs(), 2);
+
+        let unrouted_docs = index_router.flush(&counters).await.unwrap();
+        assert!(unrouted_docs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_index_router_retries_transient_failures() {
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        let mut seq = mockall::Sequence::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|ingest_request| {
+                assert_eq!(ingest_request.subrequests.len(), 2);
+
+                Ok(IngestResponseV2 {
+                    successes: vec![IngestSuccess {
+                        subrequest_id: 0,
+                        num_ingested_docs: 1,
+                        ..Default::default()
+                    }],
+                    failures: vec![IngestFailure {
+                        subrequest_id: 1,
+                        index_id: "logs-team-b".to_string(),
+                        source_id: INGEST_V2_SOURCE_ID.to_string(),
+                        reason: IngestFailureReason::ShardRateLimited as i32,
+                    }],
+                })
+            });
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|ingest_request| {
+                // Only the failed subrequest is retried.
+                assert_eq!(ingest_request.subrequests.len(), 1);
+                assert_eq!(ingest_request.subrequests[0].index_id, "logs-team-b");
+
+                Ok(IngestResponseV2 {
+                    successes: vec![IngestSuccess {
+                        subrequest_id: 0,
+                        num_ingested_docs: 1,
+                        ..Default::default()
+                    }],
+                    failures: Vec::new(),
+                })
+            });
+        let index_routing_config = IndexRoutingConfig {
+            index_id: "logs-{namespace}".to_string(),
+            fallback_to_source_index: false,
+        };
+        let mut index_router = IndexRouter::try_new(
+            &index_routing_config,
+            "logs-default".to_string(),
+            IngestRouterServiceClient::from_mock(mock_ingest_router),
+        )
+        .unwrap();
+        index_router.retry_params = RetryParams::for_test();
+
+        assert!(
+            index_router
+                .route(json_doc(json!({"namespace": "team-a"})))
+                .is_none()
+        );
+        assert!(
+            index_router
+                .route(json_doc(json!({"namespace": "team-b"})))
+                .is_none()
+        );
+        let counters =
+            DocProcessorCounters::new("logs-default".to_string(), "my-source".to_string());
+        let unrouted_docs = index_router.flush(&counters).await.unwrap();
+        assert!(unrouted_docs.is_empty());
+        assert_eq!(counters.routed.get_num_docs(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_index_router_ingest_failure() {
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .times(5)
+            .returning(|ingest_request| {
+                let failures = ingest_request
+                    .subrequests
+                    .iter()
+                    .map(|subrequest| {
+                        let reason = match subrequest.index_id.as_str() {
+                            "logs-team-a" => IngestFailureReason::NoShardsAvailable,
+                            "logs-team-b" => IngestFailureReason::Internal,
+                            _ => IngestFailureReason::IndexNotFound,
+                        };
+                        IngestFailure {
+                            subrequest_id: subrequest.subrequest_id,
+                            index_id: subrequest.index_id.clone(),
+                            source_id: INGEST_V2_SOURCE_ID.to_string(),
+                            reason: reason as i32,
+                        }
+                    })
+                    .collect();
+                Ok(IngestResponseV2 {
+                    successes: Vec::new(),
+                    failures,
+                })
+            });
+        let index_routing_config = IndexRoutingConfig {
+            index_id: "logs-{namespace}".to_string(),
+            fallback_to_source_index: false,
+        };
+        let mut index_router = IndexRouter::try_new(
+            &index_routing_config,
+            "logs-default".to_string(),
+            IngestRouterServiceClient::from_mock(mock_ingest_router),
+        )
+        .unwrap();
+        index_router.retry_params = RetryParams::for_test();
+
+        // Transient failures are retried until the maximum number of attempts is reached.
+        assert!(
+            index_router
+                .route(json_doc(json!({"namespace": "team-a"})))
+                .is_none()
+        );
+        let counters =
+            DocProcessorCounters::new("logs-default".to_string(), "my-source".to_string());
+        let error = index_router.flush(&counters).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "failed to route documents to index `logs-team-a` after 3 attempt(s): \
+             INGEST_FAILURE_REASON_NO_SHARDS_AVAILABLE"
+        );
+
+        // Other failures are not retried.
+        assert!(
+            index_router
+                .route(json_doc(json!({"namespace": "team-b"})))
+                .is_none()
+        );
+        let error = index_router.flush(&counters).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "failed to route documents to index `logs-team-b` after 1 attempt(s): \
+             INGEST_FAILURE_REASON_INTERNAL"
+        );
+
+        // Documents routed to missing indexes are not indexed locally without
+        // `fallback_to_source_index`.
+        assert!(
+            index_router
+                .route(json_doc(json!({"namespace": "team-c"})))
+                .is_none()
+        );
+        let error = index_router.flush(&counters).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "failed to route documents to index `logs-team-c` after 1 attempt(s): \
+             INGEST_FAILURE_REASON_INDEX_NOT_FOUND"
+        );
+        assert_eq!(counters.routed.get_num_docs(), 0);
+    }
+}
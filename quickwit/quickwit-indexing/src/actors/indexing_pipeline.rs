@@ -17,6 +17,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use anyhow::Context;
 use async_trait::async_trait;
 use quickwit_actors::{
     Actor, ActorContext, ActorExitStatus, ActorHandle, HEARTBEAT, Handler, Health, Mailbox,
@@ -30,6 +31,7 @@ use quickwit_config::{IndexingSettings, RetentionPolicy, SourceConfig};
 use quickwit_doc_mapper::DocMapper;
 use quickwit_ingest::IngesterPool;
 use quickwit_proto::indexing::IndexingPipelineId;
+use quickwit_proto::ingest::router::IngestRouterServiceClient;
 use quickwit_proto::metastore::{MetastoreError, MetastoreServiceClient};
 use quickwit_proto::types::ShardId;
 use quickwit_storage::{Storage, StorageResolver};
@@ -38,9 +40,12 @@ use tracing::{debug, error, info, instrument};
 
 use super::MergePlanner;
 use crate::SplitsUpdateMailbox;
+use crate::actors::dead_letter_queue::DeadLetterQueue;
 use crate::actors::doc_processor::DocProcessor;
+use crate::actors::index_router::IndexRouter;
 use crate::actors::index_serializer::IndexSerializer;
 use crate::actors::publisher::PublisherType;
+use crate::actors::schema_decoder::SchemaDecoder;
 use crate::actors::sequencer::Sequencer;
 use crate::actors::uploader::UploaderType;
 use crate::actors::{Indexer, Packager, Publisher, Uploader};
@@ -387,7 +392,13 @@ impl IndexingPipeline {
 
         // Packager
         let tag_fields = self.params.doc_mapper.tag_named_fields()?;
-        let packager = Packager::new("Packager", tag_fields, uploader_mailbox);
+        let dense_vector_fields = self.params.doc_mapper.dense_vector_fields();
+        let packager = Packager::new(
+            "Packager",
+            tag_fields,
+            dense_vector_fields,
+            uploader_mailbox,
+        );
         let (packager_mailbox, packager_handle) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -420,13 +431,65 @@ impl IndexingPipeline {
             .set_kill_switch(self.kill_switch.clone())
             .spawn(indexer);
 
+        let dead_letter_queue_opt =
+            if let Some(dead_letter_config) = &self.params.source_config.dead_letter_config {
+                let dead_letter_queue = DeadLetterQueue::try_from_config(
+                    dead_letter_config,
+                    index_id.to_string(),
+                    source_id.to_string(),
+                    self.params.pipeline_id.pipeline_uid,
+                    self.params.ingest_router_opt.clone(),
+                    &self.params.source_storage_resolver,
+                )
+                .await?;
+                Some(dead_letter_queue)
+            } else {
+                None
+            };
+        let input_format = self.params.source_config.input_format;
+        let schema_decoder_opt = if input_format.requires_schema() {
+            let schema_config = self
+                .params
+                .source_config
+                .schema_config
+                .as_ref()
+                .context("missing schema parameters")?;
+            let schema_decoder = SchemaDecoder::try_from_config(
+                input_format,
+                schema_config,
+                &self.params.source_storage_resolver,
+            )
+            .await?;
+            Some(schema_decoder)
+        } else {
+            None
+        };
+        let index_router_opt = if let Some(index_routing_config) =
+            &self.params.source_config.index_routing_config
+        {
+            let ingest_router = self
+                .params
+                .ingest_router_opt
+                .clone()
+                .context("index routing requires the ingest API v2")?;
+            let index_router =
+                IndexRouter::try_new(index_routing_config, index_id.to_string(), ingest_router)?;
+            Some(index_router)
+        } else {
+            None
+        };
         let doc_processor = DocProcessor::try_new(
             index_id.to_string(),
             source_id.to_string(),
             self.params.doc_mapper.clone(),
             indexer_mailbox,
             self.params.source_config.transform_config.clone(),
-            self.params.source_config.input_format,
+            input_format,
+            dead_letter_queue_opt,
+            schema_decoder_opt,
+            self.params.source_config.line_format_config.clone(),
+            self.params.indexing_settings.dedup.clone(),
+            index_router_opt,
         )?;
         let (doc_processor_mailbox, doc_processor_handle) = ctx
             .spawn_actor()
@@ -596,6 +659,7 @@ pub struct IndexingPipelineParams {
     pub source_config: SourceConfig,
     pub source_storage_resolver: StorageResolver,
     pub ingester_pool: IngesterPool,
+    pub ingest_router_opt: Option<IngestRouterServiceClient>,
     pub queues_dir_path: PathBuf,
     pub params_fingerprint: u64,
 
@@ -654,6 +718,10 @@ mod tests {
             source_params: SourceParams::file_from_str(test_file).unwrap(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let source_config_clone = source_config.clone();
 
@@ -717,6 +785,7 @@ mod tests {
             indexing_directory: TempDirectory::for_test(),
             indexing_settings: IndexingSettings::for_test(),
             ingester_pool: IngesterPool::default(),
+            ingest_router_opt: None,
             metastore: MetastoreServiceClient::from_mock(mock_metastore),
             storage,
             split_store,
@@ -785,6 +854,10 @@ mod tests {
             source_params: SourceParams::file_from_str(test_file).unwrap(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let source_config_clone = source_config.clone();
 
@@ -841,6 +914,7 @@ mod tests {
             indexing_directory: TempDirectory::for_test(),
             indexing_settings: IndexingSettings::for_test(),
             ingester_pool: IngesterPool::default(),
+            ingest_router_opt: None,
             metastore: MetastoreServiceClient::from_mock(mock_metastore),
             queues_dir_path: PathBuf::from("./queues"),
             storage,
@@ -891,6 +965,10 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let source_config_clone = source_config.clone();
 
@@ -942,6 +1020,7 @@ mod tests {
             indexing_directory: TempDirectory::for_test(),
             indexing_settings: IndexingSettings::for_test(),
             ingester_pool: IngesterPool::default(),
+            ingest_router_opt: None,
             metastore,
             queues_dir_path: PathBuf::from("./queues"),
             storage,
@@ -997,6 +1076,10 @@ mod tests {
             source_params: SourceParams::file_from_str(test_file).unwrap(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let source_config_clone = source_config.clone();
 
@@ -1070,6 +1153,7 @@ mod tests {
             indexing_directory: TempDirectory::for_test(),
             indexing_settings: IndexingSettings::for_test(),
             ingester_pool: IngesterPool::default(),
+            ingest_router_opt: None,
             metastore: MetastoreServiceClient::from_mock(mock_metastore),
             queues_dir_path: PathBuf::from("./queues"),
             storage,
@@ -47,6 +47,7 @@ use quickwit_proto::indexing::{
     ApplyIndexingPlanRequest, ApplyIndexingPlanResponse, IndexingError, IndexingPipelineId,
     IndexingTask, MergePipelineId, PipelineMetrics,
 };
+use quickwit_proto::ingest::router::IngestRouterServiceClient;
 use quickwit_proto::metastore::{
     IndexMetadataRequest, IndexMetadataSubrequest, IndexesMetadataRequest,
     ListIndexesMetadataRequest, ListSplitsRequest, MetastoreResult, MetastoreService,
@@ -106,6 +107,7 @@ pub struct IndexingService {
     ingest_api_service_opt: Option<Mailbox<IngestApiService>>,
     merge_scheduler_service: Mailbox<MergeSchedulerService>,
     ingester_pool: IngesterPool,
+    ingest_router_opt: Option<IngestRouterServiceClient>,
     storage_resolver: StorageResolver,
     indexing_pipelines: HashMap<PipelineUid, PipelineHandle>,
     counters: IndexingServiceCounters,
@@ -140,6 +142,7 @@ impl IndexingService {
         ingest_api_service_opt: Option<Mailbox<IngestApiService>>,
         merge_scheduler_service: Mailbox<MergeSchedulerService>,
         ingester_pool: IngesterPool,
+        ingest_router_opt: Option<IngestRouterServiceClient>,
         storage_resolver: StorageResolver,
         event_broker: EventBroker,
     ) -> anyhow::Result<IndexingService> {
@@ -169,6 +172,7 @@ impl IndexingService {
             ingest_api_service_opt,
             merge_scheduler_service,
             ingester_pool,
+            ingest_router_opt,
             storage_resolver,
             local_split_store: Arc::new(local_split_store),
             indexing_pipelines: Default::default(),
@@ -359,6 +363,7 @@ impl IndexingService {
             // Source-related parameters
             source_config,
             ingester_pool: self.ingester_pool.clone(),
+            ingest_router_opt: self.ingest_router_opt.clone(),
             queues_dir_path: self.queue_dir_path.clone(),
             source_storage_resolver: self.storage_resolver.clone(),
             params_fingerprint,
@@ -1055,6 +1060,7 @@ mod tests {
             Some(ingest_api_service),
             merge_scheduler_mailbox,
             IngesterPool::default(),
+            None,
             storage_resolver.clone(),
             EventBroker::default(),
         )
@@ -1108,6 +1114,10 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let spawn_pipeline_msg = SpawnPipeline {
             index_id: index_id.clone(),
@@ -1189,6 +1199,10 @@ mod tests {
             }),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let create_index_request = CreateIndexRequest::try_from_index_and_source_configs(
             &index_config,
@@ -1276,6 +1290,10 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         {
             // Assign 2 indexing tasks
@@ -1317,6 +1335,9 @@ mod tests {
             client_log_level: None,
             client_params: serde_json::Value::Null,
             enable_backfill_mode: false,
+            include_metadata: false,
+            metadata_field_prefix: "kafka_".to_string(),
+            start_from: None,
         };
         let source_config_2 = SourceConfig {
             source_id: "test-indexing-service--source-2".to_string(),
@@ -1325,6 +1346,10 @@ mod tests {
             source_params: SourceParams::Kafka(kafka_params),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         {
             // Assign 2 more indexing tasks (1 new source + activate ingest API source)
@@ -1532,6 +1557,10 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let create_index_request =
             CreateIndexRequest::try_from_index_config(&index_config).unwrap();
@@ -1568,6 +1597,7 @@ mod tests {
             Some(ingest_api_service),
             merge_scheduler_service,
             IngesterPool::default(),
+            None,
             storage_resolver.clone(),
             EventBroker::default(),
         )
@@ -1661,6 +1691,10 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         index_metadata
             .sources
@@ -1770,6 +1804,7 @@ mod tests {
             Some(ingest_api_service.clone()),
             merge_scheduler_service,
             IngesterPool::default(),
+            None,
             storage_resolver.clone(),
             EventBroker::default(),
         )
@@ -0,0 +1,227 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, bail, ensure};
+use quickwit_config::CsvConfig;
+use quickwit_doc_mapper::JsonObject;
+use quickwit_metastore::checkpoint::PartitionId;
+use serde_json::Value as JsonValue;
+
+/// Parses CSV lines into JSON objects keyed by column name. Empty fields are omitted.
+///
+/// Since documents are lines, quoted fields cannot span multiple lines.
+pub(crate) struct CsvParser {
+    csv_config: CsvConfig,
+    // Header rows read so far, by partition. The first line read from a partition is its header
+    // row.
+    header_rows: HashMap<PartitionId, Vec<String>>,
+}
+
+impl CsvParser {
+    pub fn new(csv_config: CsvConfig) -> Self {
+        Self {
+            csv_config,
+            header_rows: HashMap::new(),
+        }
+    }
+
+    pub fn parse(
+        &mut self,
+        partition_id: &PartitionId,
+        line: &str,
+    ) -> anyhow::Result<Option<JsonObject>> {
+        let fields_result = split_csv_line(line, self.csv_config.delimiter, self.csv_config.quote);
+
+        if self.csv_config.has_header && !self.header_rows.contains_key(partition_id) {
+            // An invalid header row is still recorded, so that the next line is not mistaken for
+            // the header row.
+            let header_row = fields_result.as_ref().cloned().unwrap_or_default();
+            self.header_rows.insert(partition_id.clone(), header_row);
+            fields_result.context("failed to parse CSV header row")?;
+            return Ok(None);
+        }
+        let fields = fields_result?;
+        let columns = self
+            .csv_config
+            .columns
+            .as_ref()
+            .or_else(|| self.header_rows.get(partition_id))
+            .context("CSV columns are unknown")?;
+        ensure!(
+            fields.len() == columns.len(),
+            "expected {} CSV fields, found {}",
+            columns.len(),
+            fields.len()
+        );
+        let json_obj = columns
+            .iter()
+            .zip(fields)
+            .filter(|(_, field)| !field.is_empty())
+            .map(|(column, field)| (column.clone(), JsonValue::String(field)))
+            .collect();
+        Ok(Some(json_obj))
+    }
+
+    pub fn end_partition(&mut self, partition_id: &PartitionId) {
+        self.header_rows.remove(partition_id);
+    }
+}
+
+fn split_csv_line(line: &str, delimiter: char, quote: char) -> anyhow::Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        if chars.next_if_eq(&quote).is_some() {
+            loop {
+                match chars.next() {
+                    // A doubled quote is an escaped quote.
+                    Some(c) if c == quote => {
+                        if chars.next_if_eq(&quote).is_some() {
+                            field.push(quote);
+                        } else {
+                            break;
+                        }
+                    }
+                    Some(c) => field.push(c),
+                    None => bail!("unterminated quoted CSV field"),
+                }
+            }
+            match chars.next() {
+                Some(c) if c == delimiter => fields.push(std::mem::take(&mut field)),
+                Some(c) => bail!("unexpected character `{c}` after quoted CSV field"),
+                None => {
+                    fields.push(field);
+                    return Ok(fields);
+                }
+            }
+        } else {
+            loop {
+                match chars.next() {
+                    Some(c) if c == delimiter => {
+                        fields.push(std::mem::take(&mut field));
+                        break;
+                    }
+                    Some(c) => field.push(c),
+                    None => {
+                        fields.push(field);
+                        return Ok(fields);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn csv_config(columns: Option<&[&str]>, has_header: bool) -> CsvConfig {
+        CsvConfig {
+            columns: columns
+                .map(|columns| columns.iter().map(|column| column.to_string()).collect()),
+            has_header,
+            delimiter: ',',
+            quote: '"',
+        }
+    }
+
+    #[test]
+    fn test_split_csv_line() {
+        assert_eq!(split_csv_line("", ',', '"').unwrap(), [""]);
+        assert_eq!(split_csv_line("a,b,", ',', '"').unwrap(), ["a", "b", ""]);
+        assert_eq!(
+            split_csv_line(r#""a,b","say ""hi""",c"#, ',', '"').unwrap(),
+            ["a,b", r#"say "hi""#, "c"]
+        );
+        assert_eq!(split_csv_line("'a;b';c", ';', '\'').unwrap(), ["a;b", "c"]);
+        let error = split_csv_line(r#""a,b"#, ',', '"').unwrap_err();
+        assert_eq!(error.to_string(), "unterminated quoted CSV field");
+
+        let error = split_csv_line(r#""a"b,c"#, ',', '"').unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unexpected character `b` after quoted CSV field"
+        );
+    }
+
+    #[test]
+    fn test_csv_parser_with_columns() {
+        let mut csv_parser = CsvParser::new(csv_config(Some(&["name", "age"]), false));
+        let partition_id = PartitionId::default();
+
+        let json_obj = csv_parser.parse(&partition_id, "bob,42").unwrap().unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({"name": "bob", "age": "42"})
+        );
+        let json_obj = csv_parser.parse(&partition_id, "alice,").unwrap().unwrap();
+        assert_eq!(JsonValue::Object(json_obj), json!({"name": "alice"}));
+
+        let error = csv_parser.parse(&partition_id, "bob").unwrap_err();
+        assert_eq!(error.to_string(), "expected 2 CSV fields, found 1");
+    }
+
+    #[test]
+    fn test_csv_parser_with_header() {
+        let mut csv_parser = CsvParser::new(csv_config(None, true));
+        let partition_1 = PartitionId::from("file-1.csv");
+        let partition_2 = PartitionId::from("file-2.csv");
+
+        assert!(
+            csv_parser
+                .parse(&partition_1, "name,age")
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            csv_parser
+                .parse(&partition_2, "age,name")
+                .unwrap()
+                .is_none()
+        );
+
+        let json_obj = csv_parser.parse(&partition_1, "bob,42").unwrap().unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({"name": "bob", "age": "42"})
+        );
+        let json_obj = csv_parser.parse(&partition_2, "42,bob").unwrap().unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({"name": "bob", "age": "42"})
+        );
+        csv_parser.end_partition(&partition_1);
+        assert!(csv_parser.parse(&partition_1, "city").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_csv_parser_skips_header_with_columns() {
+        let mut csv_parser = CsvParser::new(csv_config(Some(&["first", "second"]), true));
+        let partition_id = PartitionId::default();
+
+        assert!(csv_parser.parse(&partition_id, "a,b").unwrap().is_none());
+        let json_obj = csv_parser.parse(&partition_id, "1,2").unwrap().unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({"first": "1", "second": "2"})
+        );
+    }
+}
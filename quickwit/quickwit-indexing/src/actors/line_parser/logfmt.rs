@@ -0,0 +1,156 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::ensure;
+use quickwit_doc_mapper::JsonObject;
+use serde_json::Value as JsonValue;
+
+/// Parses a logfmt line, such as `level=info msg="request served" took=12ms cached`, into a JSON
+/// object. Values are strings, and keys without a value are set to `true`. When a key is repeated,
+/// the last value wins.
+pub(super) fn parse_logfmt(line: &str) -> anyhow::Result<JsonObject> {
+    let bytes = line.as_bytes();
+    let mut json_obj = JsonObject::new();
+    let mut pos = 0;
+
+    // All the delimiters are ASCII characters, so the positions at which the line is sliced are
+    // always character boundaries.
+    while pos < bytes.len() {
+        if bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+        let key_start = pos;
+
+        while pos < bytes.len() && bytes[pos] != b'=' && !bytes[pos].is_ascii_whitespace() {
+            ensure!(
+                bytes[pos] != b'"',
+                "unexpected quote in logfmt key at position {pos}"
+            );
+            pos += 1;
+        }
+        let key = &line[key_start..pos];
+        ensure!(
+            !key.is_empty(),
+            "missing logfmt key at position {key_start}"
+        );
+
+        if pos == bytes.len() || bytes[pos] != b'=' {
+            json_obj.insert(key.to_string(), JsonValue::Bool(true));
+            continue;
+        }
+        // Skip the `=`.
+        pos += 1;
+
+        let value = if pos < bytes.len() && bytes[pos] == b'"' {
+            pos += 1;
+            let mut value = String::new();
+            let mut segment_start = pos;
+
+            loop {
+                ensure!(
+                    pos < bytes.len(),
+                    "unterminated quoted logfmt value for key `{key}`"
+                );
+                match bytes[pos] {
+                    b'"' => {
+                        value.push_str(&line[segment_start..pos]);
+                        pos += 1;
+                        break;
+                    }
+                    b'\\' if pos + 1 < bytes.len() => {
+                        let escaped_char_opt = match bytes[pos + 1] {
+                            b'"' => Some('"'),
+                            b'\\' => Some('\\'),
+                            b'n' => Some('\n'),
+                            b'r' => Some('\r'),
+                            b't' => Some('\t'),
+                            _ => None,
+                        };
+                        // Unknown escape sequences are kept as is.
+                        if let Some(escaped_char) = escaped_char_opt {
+                            value.push_str(&line[segment_start..pos]);
+                            value.push(escaped_char);
+                            pos += 2;
+                            segment_start = pos;
+                        } else {
+                            pos += 1;
+                        }
+                    }
+                    _ => pos += 1,
+                }
+            }
+            value
+        } else {
+            let value_start = pos;
+
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            line[value_start..pos].to_string()
+        };
+        json_obj.insert(key.to_string(), JsonValue::String(value));
+    }
+    ensure!(!json_obj.is_empty(), "line does not contain any logfmt key");
+    Ok(json_obj)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_logfmt() {
+        let json_obj = parse_logfmt(
+            r#"level=info msg="request \"served\"" took=12ms cached path=/é  empty= msg2="a\qb""#,
+        )
+        .unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({
+                "level": "info",
+                "msg": r#"request "served""#,
+                "took": "12ms",
+                "cached": true,
+                "path": "/é",
+                "empty": "",
+                "msg2": r"a\qb",
+            })
+        );
+        let json_obj = parse_logfmt("a=1 a=2").unwrap();
+        assert_eq!(JsonValue::Object(json_obj), json!({"a": "2"}));
+    }
+
+    #[test]
+    fn test_parse_logfmt_errors() {
+        let error = parse_logfmt("   ").unwrap_err();
+        assert_eq!(error.to_string(), "line does not contain any logfmt key");
+
+        let error = parse_logfmt(r#"msg="unterminated"#).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unterminated quoted logfmt value for key `msg`"
+        );
+        let error = parse_logfmt("=value").unwrap_err();
+        assert_eq!(error.to_string(), "missing logfmt key at position 0");
+
+        let error = parse_logfmt(r#"a"b=c"#).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unexpected quote in logfmt key at position 1"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod csv;
+mod logfmt;
+
+use anyhow::{Context, bail};
+use quickwit_config::{LineFormatConfig, LinePattern, PatternFieldType, SourceInputFormat};
+use quickwit_doc_mapper::JsonObject;
+use quickwit_metastore::checkpoint::PartitionId;
+use serde_json::{Number as JsonNumber, Value as JsonValue};
+
+use self::csv::CsvParser;
+use self::logfmt::parse_logfmt;
+use super::doc_processor::DocProcessorError;
+
+/// Parses the lines of the `csv`, `logfmt`, `grok`, and `regex` input formats into JSON objects.
+pub(crate) enum LineParser {
+    Csv(CsvParser),
+    Logfmt,
+    Pattern(LinePattern),
+}
+
+impl LineParser {
+    /// Returns `None` if the input format is not a line format.
+    pub fn try_new(
+        input_format: SourceInputFormat,
+        line_format_config_opt: Option<&LineFormatConfig>,
+    ) -> anyhow::Result<Option<Self>> {
+        let line_parser = match (input_format, line_format_config_opt) {
+            (SourceInputFormat::Csv, Some(LineFormatConfig::Csv(csv_config))) => {
+                LineParser::Csv(CsvParser::new(csv_config.clone()))
+            }
+            (SourceInputFormat::Logfmt, _) => LineParser::Logfmt,
+            (SourceInputFormat::Grok, Some(LineFormatConfig::Grok(grok_config))) => {
+                LineParser::Pattern(grok_config.compile()?)
+            }
+            (SourceInputFormat::Regex, Some(LineFormatConfig::Regex(regex_config))) => {
+                LineParser::Pattern(regex_config.compile()?)
+            }
+            (input_format, _) if input_format.is_line_format() => {
+                bail!("input format `{input_format:?}` is missing its parameters")
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(line_parser))
+    }
+
+    /// Parses a line read from a partition. Returns `None` for CSV header rows.
+    pub fn parse(
+        &mut self,
+        partition_id: &PartitionId,
+        raw_doc: &[u8],
+    ) -> Result<Option<JsonObject>, DocProcessorError> {
+        let line = std::str::from_utf8(raw_doc)
+            .map_err(|error| DocProcessorError::LineParsing(error.to_string()))?
+            .trim_end_matches(['\n', '\r']);
+
+        let json_obj_result = match self {
+            LineParser::Csv(csv_parser) => csv_parser.parse(partition_id, line),
+            LineParser::Logfmt => parse_logfmt(line).map(Some),
+            LineParser::Pattern(line_pattern) => parse_pattern(line_pattern, line).map(Some),
+        };
+        json_obj_result.map_err(|error| DocProcessorError::LineParsing(format!("{error:#}")))
+    }
+
+    /// Releases the state kept for a partition once it has been read entirely.
+    pub fn end_partition(&mut self, partition_id: &PartitionId) {
+        if let LineParser::Csv(csv_parser) = self {
+            csv_parser.end_partition(partition_id);
+        }
+    }
+}
+
+fn parse_pattern(line_pattern: &LinePattern, line: &str) -> anyhow::Result<JsonObject> {
+    let captures = line_pattern
+        .regex
+        .captures(line)
+        .context("line does not match pattern")?;
+    let mut json_obj = JsonObject::with_capacity(line_pattern.fields.len());
+
+    for field in &line_pattern.fields {
+        // Fields in optional groups or in alternations may not be captured.
+        let Some(capture) = captures.name(&field.group_name) else {
+            continue;
+        };
+        let value = capture.as_str();
+        let json_value = match field.field_type {
+            PatternFieldType::String => JsonValue::String(value.to_string()),
+            PatternFieldType::Int => {
+                value.parse::<i64>().map(JsonValue::from).with_context(|| {
+                    format!(
+                        "failed to parse value `{value}` of field `{}` as an integer",
+                        field.field_name
+                    )
+                })?
+            }
+            PatternFieldType::Float => value
+                .parse::<f64>()
+                .ok()
+                .and_then(JsonNumber::from_f64)
+                .map(JsonValue::Number)
+                .with_context(|| {
+                    format!(
+                        "failed to parse value `{value}` of field `{}` as a float",
+                        field.field_name
+                    )
+                })?,
+        };
+        json_obj.insert(field.field_name.clone(), json_value);
+    }
+    Ok(json_obj)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use quickwit_config::{GrokConfig, RegexConfig};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_line_parser_try_new() {
+        let line_parser_opt = LineParser::try_new(SourceInputFormat::Json, None).unwrap();
+        assert!(line_parser_opt.is_none());
+
+        let line_parser_opt = LineParser::try_new(SourceInputFormat::Logfmt, None).unwrap();
+        assert!(matches!(line_parser_opt, Some(LineParser::Logfmt)));
+
+        LineParser::try_new(SourceInputFormat::Grok, None)
+            .err()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_line_parser_grok() {
+        let grok_config = GrokConfig {
+            pattern: "%{SYSLOGLINE}".to_string(),
+            custom_patterns: BTreeMap::new(),
+        };
+        let line_format_config = LineFormatConfig::Grok(grok_config);
+        let mut line_parser =
+            LineParser::try_new(SourceInputFormat::Grok, Some(&line_format_config))
+                .unwrap()
+                .unwrap();
+        let partition_id = PartitionId::default();
+
+        let json_obj = line_parser
+            .parse(
+                &partition_id,
+                b"Mar  7 09:12:44 web-1 sshd[4242]: Accepted publickey for bob\n",
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({
+                "timestamp": "Mar  7 09:12:44",
+                "logsource": "web-1",
+                "program": "sshd",
+                "pid": "4242",
+                "message": "Accepted publickey for bob",
+            })
+        );
+        let error = line_parser
+            .parse(&partition_id, b"not a syslog line")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line parse error: line does not match pattern"
+        );
+    }
+
+    #[test]
+    fn test_line_parser_typed_fields() {
+        let grok_config = GrokConfig {
+            pattern: r"^%{INT:status:int} %{NUMBER:duration:float} %{WORD:method}$".to_string(),
+            custom_patterns: BTreeMap::new(),
+        };
+        let line_format_config = LineFormatConfig::Grok(grok_config);
+        let mut line_parser =
+            LineParser::try_new(SourceInputFormat::Grok, Some(&line_format_config))
+                .unwrap()
+                .unwrap();
+        let partition_id = PartitionId::default();
+
+        let json_obj = line_parser
+            .parse(&partition_id, b"200 0.25 GET")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({"status": 200, "duration": 0.25, "method": "GET"})
+        );
+    }
+
+    #[test]
+    fn test_line_parser_regex() {
+        let regex_config = RegexConfig {
+            pattern: r"^(?P<level>[A-Z]+)(?: \[(?P<module>\w+)\])? (?P<message>.*)$".to_string(),
+        };
+        let line_format_config = LineFormatConfig::Regex(regex_config);
+        let mut line_parser =
+            LineParser::try_new(SourceInputFormat::Regex, Some(&line_format_config))
+                .unwrap()
+                .unwrap();
+        let partition_id = PartitionId::default();
+
+        let json_obj = line_parser
+            .parse(&partition_id, b"WARN disk is almost full\r\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({"level": "WARN", "message": "disk is almost full"})
+        );
+        let error = line_parser.parse(&partition_id, &[0xff]).unwrap_err();
+        assert_eq!(error.kind(), "line_parsing");
+    }
+}
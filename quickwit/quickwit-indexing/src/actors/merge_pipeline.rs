@@ -297,7 +297,13 @@ impl MergePipeline {
 
         // Merge Packager
         let tag_fields = self.params.doc_mapper.tag_named_fields()?;
-        let merge_packager = Packager::new("MergePackager", tag_fields, merge_uploader_mailbox);
+        let dense_vector_fields = self.params.doc_mapper.dense_vector_fields();
+        let merge_packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            dense_vector_fields,
+            merge_uploader_mailbox,
+        );
         let (merge_packager_mailbox, merge_packager_handle) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -13,11 +13,15 @@
 // limitations under the License.
 
 mod cooperative_indexing;
+mod dead_letter_queue;
+mod deduplicator;
 mod doc_processor;
+mod index_router;
 mod index_serializer;
 mod indexer;
 mod indexing_pipeline;
 mod indexing_service;
+mod line_parser;
 mod merge_executor;
 mod merge_pipeline;
 mod merge_planner;
@@ -25,12 +29,15 @@ mod merge_scheduler_service;
 mod merge_split_downloader;
 mod packager;
 mod publisher;
+mod schema_decoder;
 mod sequencer;
 mod uploader;
 #[cfg(feature = "vrl")]
 mod vrl_processing;
 
+pub use dead_letter_queue::{DeadLetterQueue, DeadLetterSink};
 pub use doc_processor::{DocProcessor, DocProcessorCounters};
+pub use index_router::IndexRouter;
 pub use index_serializer::IndexSerializer;
 pub use indexer::{Indexer, IndexerCounters};
 pub use indexing_pipeline::{IndexingPipeline, IndexingPipelineParams};
@@ -43,5 +50,6 @@ pub use merge_split_downloader::MergeSplitDownloader;
 pub use packager::Packager;
 pub use publisher::{Publisher, PublisherCounters, PublisherType};
 pub use quickwit_proto::indexing::IndexingError;
+pub use schema_decoder::SchemaDecoder;
 pub use sequencer::Sequencer;
 pub use uploader::{SplitsUpdateMailbox, Uploader, UploaderCounters, UploaderType};
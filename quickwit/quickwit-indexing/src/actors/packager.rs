@@ -24,15 +24,18 @@ use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_common::temp_dir::TempDirectory;
-use quickwit_directories::write_hotcache;
-use quickwit_doc_mapper::NamedField;
+use quickwit_directories::write_hotcache_with_extra_files;
 use quickwit_doc_mapper::tag_pruning::append_to_tag_set;
+use quickwit_doc_mapper::vector_index::{
+    VectorIndexBuilder, VectorIndexBytes, vector_from_bytes, vector_index_file_path,
+};
+use quickwit_doc_mapper::{NamedField, QuickwitDenseVectorOptions};
 use quickwit_proto::search::{
     ListFieldType, ListFields, ListFieldsEntryResponse, serialize_split_fields,
 };
 use tantivy::index::FieldMetadata;
 use tantivy::schema::{FieldType, Type};
-use tantivy::{InvertedIndexReader, ReloadPolicy, SegmentMeta};
+use tantivy::{InvertedIndexReader, ReloadPolicy, Searcher, SegmentMeta};
 use tokio::runtime::Handle;
 use tracing::{debug, info, instrument, warn};
 
@@ -54,6 +57,7 @@ use crate::models::{
 /// This includes the following steps:
 /// - commit: this step is CPU heavy
 /// - identifying the list of tags for the splits, and labelling it accordingly
+/// - building the vector indexes of the dense vector fields
 /// - creating a bundle file
 /// - computing the hotcache
 /// - appending it to the split file.
@@ -65,18 +69,22 @@ pub struct Packager {
     uploader_mailbox: Mailbox<Uploader>,
     /// List of tag fields ([`Vec<NamedField>`]) defined in the index config.
     tag_fields: Vec<NamedField>,
+    /// Names and options of the dense vector fields defined in the index config.
+    dense_vector_fields: Vec<(String, QuickwitDenseVectorOptions)>,
 }
 
 impl Packager {
     pub fn new(
         actor_name: &'static str,
         tag_fields: Vec<NamedField>,
+        dense_vector_fields: Vec<(String, QuickwitDenseVectorOptions)>,
         uploader_mailbox: Mailbox<Uploader>,
     ) -> Packager {
         Packager {
             actor_name,
             uploader_mailbox,
             tag_fields,
+            dense_vector_fields,
         }
     }
 
@@ -87,8 +95,13 @@ impl Packager {
     ) -> anyhow::Result<PackagedSplit> {
         let segment_metas = split.index.searchable_segment_metas()?;
         assert_eq!(segment_metas.len(), 1);
-        let packaged_split =
-            create_packaged_split(&segment_metas[..], split, &self.tag_fields, ctx)?;
+        let packaged_split = create_packaged_split(
+            &segment_metas[..],
+            split,
+            &self.tag_fields,
+            &self.dense_vector_fields,
+            ctx,
+        )?;
         Ok(packaged_split)
     }
 }
@@ -207,12 +220,47 @@ fn list_split_files(
     Ok(index_files)
 }
 
-fn build_hotcache<W: io::Write>(split_path: &Path, out: &mut W) -> anyhow::Result<()> {
+fn build_hotcache<W: io::Write>(
+    split_path: &Path,
+    extra_files: &[(PathBuf, usize)],
+    out: &mut W,
+) -> anyhow::Result<()> {
     let mmap_directory = tantivy::directory::MmapDirectory::open(split_path)?;
-    write_hotcache(mmap_directory, out)?;
+    write_hotcache_with_extra_files(mmap_directory, extra_files, out)?;
     Ok(())
 }
 
+/// Builds the vector index of a dense vector field from the vectors stored in its fast field.
+fn build_vector_index(
+    searcher: &Searcher,
+    field_name: &str,
+    dense_vector_options: &QuickwitDenseVectorOptions,
+) -> anyhow::Result<VectorIndexBytes> {
+    let mut vector_index_builder = VectorIndexBuilder::new(dense_vector_options);
+    let mut vector_bytes = Vec::new();
+
+    // Doc ids are segment local, which is fine since splits have a single segment.
+    for segment_reader in searcher.segment_readers() {
+        let Some(vector_column) = segment_reader.fast_fields().bytes(field_name)? else {
+            continue;
+        };
+        for doc_id in 0..segment_reader.max_doc() {
+            if segment_reader.is_deleted(doc_id) {
+                continue;
+            }
+            let Some(term_ord) = vector_column.term_ords(doc_id).next() else {
+                continue;
+            };
+            vector_column.ord_to_bytes(term_ord, &mut vector_bytes)?;
+
+            if let Some(vector) = vector_from_bytes(&vector_bytes) {
+                vector_index_builder.add(doc_id, &vector);
+            }
+        }
+    }
+    Ok(vector_index_builder.build())
+}
+
 /// Attempts to exhaustively extract the list of terms in a
 /// field term dictionary.
 ///
@@ -272,10 +320,11 @@ fn create_packaged_split(
     segment_metas: &[SegmentMeta],
     split: IndexedSplit,
     tag_fields: &[NamedField],
+    dense_vector_fields: &[(String, QuickwitDenseVectorOptions)],
     ctx: &ActorContext<Packager>,
 ) -> anyhow::Result<PackagedSplit> {
     debug!(split_id = split.split_id(), "create-packaged-split");
-    let split_files = list_split_files(segment_metas, &split.split_scratch_directory)?;
+    let mut split_files = list_split_files(segment_metas, &split.split_scratch_directory)?;
 
     // Extracts tag values from inverted indexes only when a field cardinality is less
     // than `MAX_VALUES_PER_TAG_FIELD`.
@@ -309,9 +358,37 @@ fn create_packaged_split(
 
     ctx.record_progress();
 
+    // The headers of the vector indexes are stored in the hotcache.
+    let mut hotcache_extra_files = Vec::with_capacity(dense_vector_fields.len());
+    let schema = split.index.schema();
+
+    for (field_name, dense_vector_options) in dense_vector_fields {
+        let Ok(field) = schema.get_field(field_name) else {
+            continue;
+        };
+        debug!(
+            split_id = split.split_id(),
+            field_name, "build-vector-index"
+        );
+        let vector_index_bytes =
+            build_vector_index(&index_reader.searcher(), field_name, dense_vector_options)?;
+        let file_path = vector_index_file_path(field);
+        let split_file_path = split.split_scratch_directory.path().join(&file_path);
+        std::fs::write(&split_file_path, &vector_index_bytes.bytes)?;
+
+        split_files.push(split_file_path);
+        hotcache_extra_files.push((file_path, vector_index_bytes.hot_num_bytes));
+        ctx.record_progress();
+    }
+    split_files.sort();
+
     debug!(split_id = split.split_id(), "build-hotcache");
     let mut hotcache_bytes = Vec::new();
-    build_hotcache(split.split_scratch_directory.path(), &mut hotcache_bytes)?;
+    build_hotcache(
+        split.split_scratch_directory.path(),
+        &hotcache_extra_files,
+        &mut hotcache_bytes,
+    )?;
     ctx.record_progress();
 
     let serialized_split_fields = serialize_field_metadata(&fields_metadata);
@@ -381,11 +458,13 @@ mod tests {
     use std::ops::RangeInclusive;
 
     use quickwit_actors::{ObservationType, Universe};
+    use quickwit_doc_mapper::vector_index::{VectorIndex, vector_to_bytes};
+    use quickwit_doc_mapper::{VectorQuantization, VectorSimilarity};
     use quickwit_metastore::checkpoint::IndexCheckpointDelta;
     use quickwit_proto::search::{ListFieldsEntryResponse, deserialize_split_fields};
     use quickwit_proto::types::{DocMappingUid, IndexUid, NodeId};
     use tantivy::directory::MmapDirectory;
-    use tantivy::schema::{FAST, NumericOptions, STRING, Schema, TEXT, Type};
+    use tantivy::schema::{BytesOptions, FAST, NumericOptions, STRING, Schema, TEXT, Type};
     use tantivy::{DateTime, IndexBuilder, IndexSettings, doc};
     use tracing::Span;
 
@@ -463,6 +542,8 @@ mod tests {
             schema_builder.add_f64_field("tag_f64", NumericOptions::default().set_indexed());
         let tag_bool =
             schema_builder.add_bool_field("tag_bool", NumericOptions::default().set_indexed());
+        let embedding =
+            schema_builder.add_bytes_field("embedding", BytesOptions::default().set_fast());
         let schema = schema_builder.build();
         let index_builder = IndexBuilder::new()
             .settings(IndexSettings::default())
@@ -493,6 +574,7 @@ mod tests {
                     tag_i64 => -42i64,
                     tag_f64 => -42.02f64,
                     tag_bool => true,
+                    embedding => vector_to_bytes(&[num as f32, 1.0]),
                 );
                 index_writer.add_document(doc)?;
                 num_docs += 1;
@@ -567,7 +649,7 @@ mod tests {
                 "tag_str", "tag_many", "tag_u64", "tag_i64", "tag_f64", "tag_bool",
             ],
         );
-        let packager = Packager::new("TestPackager", tag_fields, mailbox);
+        let packager = Packager::new("TestPackager", tag_fields, Vec::new(), mailbox);
         let (packager_mailbox, packager_handle) = universe.spawn_builder().spawn(packager);
         packager_mailbox
             .send_message(IndexedSplitBatch {
@@ -614,4 +696,62 @@ mod tests {
         universe.assert_quit().await;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_packager_builds_vector_indexes() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, inbox) = universe.create_test_mailbox();
+        let indexed_split = make_indexed_split_for_test(&[
+            DateTime::from_timestamp_secs(1628203589),
+            DateTime::from_timestamp_secs(1628203640),
+        ])?;
+        let embedding_field = indexed_split.index.schema().get_field("embedding")?;
+        let dense_vector_options = QuickwitDenseVectorOptions {
+            description: None,
+            dims: 2,
+            similarity: VectorSimilarity::L2Norm,
+            quantization: VectorQuantization::F32,
+            stored: false,
+        };
+        let dense_vector_fields = vec![
+            ("embedding".to_string(), dense_vector_options.clone()),
+            // Fields missing from the split schema are ignored.
+            ("missing".to_string(), dense_vector_options),
+        ];
+        let packager = Packager::new("TestPackager", Vec::new(), dense_vector_fields, mailbox);
+        let (packager_mailbox, packager_handle) = universe.spawn_builder().spawn(packager);
+        packager_mailbox
+            .send_message(IndexedSplitBatch {
+                splits: vec![indexed_split],
+                checkpoint_delta_opt: IndexCheckpointDelta::for_test("source_id", 10..20).into(),
+                publish_lock: PublishLock::default(),
+                publish_token_opt: None,
+                merge_task_opt: None,
+                batch_parent_span: Span::none(),
+            })
+            .await?;
+        assert_eq!(
+            packager_handle.process_pending_and_observe().await.obs_type,
+            ObservationType::Alive
+        );
+        let packaged_splits = inbox.drain_for_test();
+        let packaged_split = packaged_splits[0]
+            .downcast_ref::<PackagedSplitBatch>()
+            .unwrap();
+        let split = &packaged_split.splits[0];
+
+        let vector_index_path = split
+            .split_scratch_directory
+            .path()
+            .join(vector_index_file_path(embedding_field));
+        assert!(split.split_files.contains(&vector_index_path));
+
+        let vector_index_bytes = std::fs::read(&vector_index_path)?;
+        let vector_index = VectorIndex::open(&vector_index_bytes)?;
+        assert_eq!(vector_index.num_vectors(), 18);
+        assert_eq!(vector_index.dims(), 2);
+
+        universe.assert_quit().await;
+        Ok(())
+    }
 }
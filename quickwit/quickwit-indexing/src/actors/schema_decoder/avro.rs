@@ -0,0 +1,632 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decodes Avro binary payloads into JSON objects.
+//!
+//! Unions are decoded into the value of the selected branch, and `bytes` and `fixed` values are
+//! encoded in base64. Logical types are decoded as their underlying primitive type.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, bail};
+use base64::prelude::{BASE64_STANDARD, Engine};
+use quickwit_doc_mapper::JsonObject;
+use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
+
+use super::{MAX_NESTING_DEPTH, WireReader};
+
+/// Maximum number of items in an array whose items are encoded on zero bytes, such as `null`.
+/// The number of other items is bounded by the size of the payload.
+const MAX_ZERO_SIZED_ITEMS: usize = 10_000;
+
+#[derive(Debug)]
+enum AvroType {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Array(Box<AvroType>),
+    Map(Box<AvroType>),
+    Union(Vec<AvroType>),
+    Named(usize),
+}
+
+#[derive(Debug)]
+enum NamedAvroType {
+    // Placeholder for a record whose fields are being parsed.
+    Pending,
+    Record(Vec<(String, AvroType)>),
+    Enum(Vec<String>),
+    Fixed(usize),
+}
+
+/// A parsed Avro schema whose root is a record.
+#[derive(Debug)]
+pub(crate) struct AvroSchema {
+    root: AvroType,
+    named_types: Vec<NamedAvroType>,
+}
+
+impl AvroSchema {
+    /// Parses an Avro schema, after registering the named types defined in the referenced
+    /// schemas.
+    pub fn parse(schema_str: &str, referenced_schema_strs: &[String]) -> anyhow::Result<Self> {
+        let mut parser = AvroSchemaParser::default();
+
+        for referenced_schema_str in referenced_schema_strs {
+            let referenced_schema_json: JsonValue = serde_json::from_str(referenced_schema_str)
+                .context("failed to parse referenced Avro schema")?;
+            parser.parse_type(&referenced_schema_json, "")?;
+        }
+        let schema_json: JsonValue =
+            serde_json::from_str(schema_str).context("failed to parse Avro schema")?;
+        let root = parser.parse_type(&schema_json, "")?;
+
+        let AvroType::Named(root_idx) = root else {
+            bail!("the root of an Avro schema must be a record");
+        };
+        if !matches!(parser.named_types[root_idx], NamedAvroType::Record(_)) {
+            bail!("the root of an Avro schema must be a record");
+        }
+        Ok(Self {
+            root,
+            named_types: parser.named_types,
+        })
+    }
+
+    pub fn decode(&self, payload: &[u8]) -> anyhow::Result<JsonObject> {
+        let mut reader = WireReader::new(payload);
+        let json_value = self.decode_value(&self.root, &mut reader, 0)?;
+
+        if !reader.is_empty() {
+            bail!("trailing bytes after Avro record");
+        }
+        match json_value {
+            JsonValue::Object(json_obj) => Ok(json_obj),
+            _ => unreachable!("the root of an Avro schema should be a record"),
+        }
+    }
+
+    fn decode_value(
+        &self,
+        avro_type: &AvroType,
+        reader: &mut WireReader,
+        depth: usize,
+    ) -> anyhow::Result<JsonValue> {
+        if depth > MAX_NESTING_DEPTH {
+            bail!("Avro value exceeds the maximum nesting depth of {MAX_NESTING_DEPTH}");
+        }
+        let json_value = match avro_type {
+            AvroType::Null => JsonValue::Null,
+            AvroType::Boolean => JsonValue::Bool(reader.read_u8()? != 0),
+            AvroType::Int | AvroType::Long => JsonValue::from(reader.read_zigzag_varint()?),
+            AvroType::Float => float_to_json(reader.read_f32_le()? as f64),
+            AvroType::Double => float_to_json(reader.read_f64_le()?),
+            AvroType::Bytes => {
+                let len = read_len(reader)?;
+                JsonValue::String(BASE64_STANDARD.encode(reader.read_bytes(len)?))
+            }
+            AvroType::String => {
+                let len = read_len(reader)?;
+                let value = std::str::from_utf8(reader.read_bytes(len)?)
+                    .context("invalid UTF-8 in Avro string")?;
+                JsonValue::String(value.to_string())
+            }
+            AvroType::Array(item_type) => {
+                let mut items = Vec::new();
+                let zero_sized_items = self.is_zero_sized(item_type, depth + 1);
+
+                while let Some(block_len) = read_block_len(reader, zero_sized_items)? {
+                    if zero_sized_items
+                        && items.len().saturating_add(block_len) > MAX_ZERO_SIZED_ITEMS
+                    {
+                        bail!(
+                            "Avro array exceeds the maximum number of {MAX_ZERO_SIZED_ITEMS} \
+                             zero-sized items"
+                        );
+                    }
+                    for _ in 0..block_len {
+                        items.push(self.decode_value(item_type, reader, depth + 1)?);
+                    }
+                }
+                JsonValue::Array(items)
+            }
+            AvroType::Map(value_type) => {
+                let mut json_map = JsonMap::new();
+                // Map entries are never zero-sized: the length of the key takes at least one byte.
+                while let Some(block_len) = read_block_len(reader, false)? {
+                    for _ in 0..block_len {
+                        let key_len = read_len(reader)?;
+                        let key = std::str::from_utf8(reader.read_bytes(key_len)?)
+                            .context("invalid UTF-8 in Avro map key")?
+                            .to_string();
+                        let value = self.decode_value(value_type, reader, depth + 1)?;
+                        json_map.insert(key, value);
+                    }
+                }
+                JsonValue::Object(json_map)
+            }
+            AvroType::Union(branches) => {
+                let branch_idx = reader.read_zigzag_varint()?;
+                let branch = usize::try_from(branch_idx)
+                    .ok()
+                    .and_then(|branch_idx| branches.get(branch_idx))
+                    .with_context(|| format!("invalid Avro union branch index `{branch_idx}`"))?;
+                self.decode_value(branch, reader, depth + 1)?
+            }
+            AvroType::Named(named_idx) => match &self.named_types[*named_idx] {
+                NamedAvroType::Record(fields) => {
+                    let mut json_obj = JsonMap::with_capacity(fields.len());
+                    for (field_name, field_type) in fields {
+                        let field_value = self.decode_value(field_type, reader, depth + 1)?;
+                        json_obj.insert(field_name.clone(), field_value);
+                    }
+                    JsonValue::Object(json_obj)
+                }
+                NamedAvroType::Enum(symbols) => {
+                    let symbol_idx = reader.read_zigzag_varint()?;
+                    let symbol = usize::try_from(symbol_idx)
+                        .ok()
+                        .and_then(|symbol_idx| symbols.get(symbol_idx))
+                        .with_context(|| format!("invalid Avro enum index `{symbol_idx}`"))?;
+                    JsonValue::String(symbol.clone())
+                }
+                NamedAvroType::Fixed(size) => {
+                    JsonValue::String(BASE64_STANDARD.encode(reader.read_bytes(*size)?))
+                }
+                NamedAvroType::Pending => unreachable!("named types should be fully parsed"),
+            },
+        };
+        Ok(json_value)
+    }
+
+    /// Returns whether values of this type are encoded on zero bytes.
+    fn is_zero_sized(&self, avro_type: &AvroType, depth: usize) -> bool {
+        if depth > MAX_NESTING_DEPTH {
+            return false;
+        }
+        match avro_type {
+            AvroType::Null => true,
+            AvroType::Named(named_idx) => match &self.named_types[*named_idx] {
+                NamedAvroType::Record(fields) => fields
+                    .iter()
+                    .all(|(_, field_type)| self.is_zero_sized(field_type, depth + 1)),
+                NamedAvroType::Fixed(size) => *size == 0,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+fn float_to_json(value: f64) -> JsonValue {
+    JsonNumber::from_f64(value)
+        .map(JsonValue::Number)
+        .unwrap_or(JsonValue::Null)
+}
+
+fn read_len(reader: &mut WireReader) -> anyhow::Result<usize> {
+    let len = reader.read_zigzag_varint()?;
+    usize::try_from(len).with_context(|| format!("invalid Avro length `{len}`"))
+}
+
+/// Reads the header of an array or map block. Returns `None` for the terminating empty block.
+///
+/// Unless the items are zero-sized, each item takes at least one byte, so a block cannot hold
+/// more items than there are remaining bytes in the payload.
+fn read_block_len(
+    reader: &mut WireReader,
+    zero_sized_items: bool,
+) -> anyhow::Result<Option<usize>> {
+    let block_len = reader.read_zigzag_varint()?;
+
+    if block_len == 0 {
+        return Ok(None);
+    }
+    if block_len < 0 {
+        // Negative counts are followed by the size of the block in bytes, which we don't need.
+        reader.read_zigzag_varint()?;
+    }
+    let block_len = block_len.unsigned_abs();
+    let num_remaining_bytes = reader.remaining().len();
+
+    if !zero_sized_items && block_len > num_remaining_bytes as u64 {
+        bail!("invalid Avro block count `{block_len}`: only {num_remaining_bytes} bytes remaining");
+    }
+    Ok(Some(block_len.min(usize::MAX as u64) as usize))
+}
+
+#[derive(Default)]
+struct AvroSchemaParser {
+    named_types: Vec<NamedAvroType>,
+    named_type_ids: HashMap<String, usize>,
+}
+
+impl AvroSchemaParser {
+    fn parse_type(&mut self, schema_json: &JsonValue, namespace: &str) -> anyhow::Result<AvroType> {
+        match schema_json {
+            JsonValue::String(type_name) => self.parse_type_name(type_name, namespace),
+            JsonValue::Array(branches) => {
+                let branches = branches
+                    .iter()
+                    .map(|branch| self.parse_type(branch, namespace))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok(AvroType::Union(branches))
+            }
+            JsonValue::Object(schema_obj) => self.parse_complex_type(schema_obj, namespace),
+            _ => bail!("invalid Avro schema `{schema_json}`"),
+        }
+    }
+
+    fn parse_type_name(&self, type_name: &str, namespace: &str) -> anyhow::Result<AvroType> {
+        let avro_type = match type_name {
+            "null" => AvroType::Null,
+            "boolean" => AvroType::Boolean,
+            "int" => AvroType::Int,
+            "long" => AvroType::Long,
+            "float" => AvroType::Float,
+            "double" => AvroType::Double,
+            "bytes" => AvroType::Bytes,
+            "string" => AvroType::String,
+            _ => {
+                let named_idx = self
+                    .named_type_ids
+                    .get(&full_name(type_name, namespace))
+                    .or_else(|| self.named_type_ids.get(type_name))
+                    .with_context(|| format!("unknown Avro type `{type_name}`"))?;
+                AvroType::Named(*named_idx)
+            }
+        };
+        Ok(avro_type)
+    }
+
+    fn parse_complex_type(
+        &mut self,
+        schema_obj: &JsonMap<String, JsonValue>,
+        namespace: &str,
+    ) -> anyhow::Result<AvroType> {
+        let type_json = schema_obj
+            .get("type")
+            .context("Avro schema object is missing a `type`")?;
+        let Some(type_name) = type_json.as_str() else {
+            // For instance, `{"type": {"type": "array", "items": "int"}}`.
+            return self.parse_type(type_json, namespace);
+        };
+        match type_name {
+            "array" => {
+                let items_json = schema_obj
+                    .get("items")
+                    .context("Avro array is missing `items`")?;
+                let item_type = self.parse_type(items_json, namespace)?;
+                Ok(AvroType::Array(Box::new(item_type)))
+            }
+            "map" => {
+                let values_json = schema_obj
+                    .get("values")
+                    .context("Avro map is missing `values`")?;
+                let value_type = self.parse_type(values_json, namespace)?;
+                Ok(AvroType::Map(Box::new(value_type)))
+            }
+            "record" | "error" | "enum" | "fixed" => {
+                self.parse_named_type(type_name, schema_obj, namespace)
+            }
+            // Primitive types, possibly annotated with a logical type.
+            _ => self.parse_type_name(type_name, namespace),
+        }
+    }
+
+    fn parse_named_type(
+        &mut self,
+        type_name: &str,
+        schema_obj: &JsonMap<String, JsonValue>,
+        namespace: &str,
+    ) -> anyhow::Result<AvroType> {
+        let name = schema_obj
+            .get("name")
+            .and_then(JsonValue::as_str)
+            .with_context(|| format!("Avro {type_name} is missing a `name`"))?;
+        let namespace = schema_obj
+            .get("namespace")
+            .and_then(JsonValue::as_str)
+            .unwrap_or(namespace);
+        let full_name = full_name(name, namespace);
+
+        if self.named_type_ids.contains_key(&full_name) {
+            bail!("Avro type `{full_name}` is defined more than once");
+        }
+        let named_idx = self.named_types.len();
+        self.named_types.push(NamedAvroType::Pending);
+        self.named_type_ids.insert(full_name.clone(), named_idx);
+
+        // Names without a namespace are resolved in the namespace of the enclosing type.
+        let enclosing_namespace = full_name
+            .rsplit_once('.')
+            .map(|(namespace, _)| namespace)
+            .unwrap_or("");
+
+        let named_type = match type_name {
+            "enum" => {
+                let symbols = schema_obj
+                    .get("symbols")
+                    .and_then(JsonValue::as_array)
+                    .context("Avro enum is missing `symbols`")?
+                    .iter()
+                    .map(|symbol| {
+                        symbol
+                            .as_str()
+                            .map(str::to_string)
+                            .context("Avro enum symbols must be strings")
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                NamedAvroType::Enum(symbols)
+            }
+            "fixed" => {
+                let size = schema_obj
+                    .get("size")
+                    .and_then(JsonValue::as_u64)
+                    .context("Avro fixed is missing `size`")?;
+                NamedAvroType::Fixed(size as usize)
+            }
+            _ => {
+                let fields_json = schema_obj
+                    .get("fields")
+                    .and_then(JsonValue::as_array)
+                    .context("Avro record is missing `fields`")?;
+                let mut fields = Vec::with_capacity(fields_json.len());
+
+                for field_json in fields_json {
+                    let field_name = field_json
+                        .get("name")
+                        .and_then(JsonValue::as_str)
+                        .context("Avro record field is missing a `name`")?;
+                    let field_type_json = field_json.get("type").with_context(|| {
+                        format!("Avro record field `{field_name}` is missing a `type`")
+                    })?;
+                    let field_type = self.parse_type(field_type_json, enclosing_namespace)?;
+                    fields.push((field_name.to_string(), field_type));
+                }
+                NamedAvroType::Record(fields)
+            }
+        };
+        self.named_types[named_idx] = named_type;
+        Ok(AvroType::Named(named_idx))
+    }
+}
+
+fn full_name(name: &str, namespace: &str) -> String {
+    if name.contains('.') || namespace.is_empty() {
+        name.to_string()
+    } else {
+        format!("{namespace}.{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn encode_zigzag_varint(value: i64, buffer: &mut Vec<u8>) {
+        let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+        loop {
+            let byte = (zigzag & 0x7f) as u8;
+            zigzag >>= 7;
+            if zigzag == 0 {
+                buffer.push(byte);
+                break;
+            }
+            buffer.push(byte | 0x80);
+        }
+    }
+
+    fn encode_string(value: &str, buffer: &mut Vec<u8>) {
+        encode_zigzag_varint(value.len() as i64, buffer);
+        buffer.extend_from_slice(value.as_bytes());
+    }
+
+    #[test]
+    fn test_avro_schema_decode() {
+        let schema_str = r#"{
+            "type": "record",
+            "name": "Event",
+            "namespace": "com.example",
+            "fields": [
+                {"name": "id", "type": "long"},
+                {"name": "message", "type": "string"},
+                {
+                    "name": "level",
+                    "type": {"type": "enum", "name": "Level", "symbols": ["INFO", "WARN"]}
+                },
+                {"name": "tags", "type": {"type": "array", "items": "string"}},
+                {"name": "labels", "type": {"type": "map", "values": "int"}},
+                {"name": "user", "type": ["null", {
+                    "type": "record",
+                    "name": "User",
+                    "fields": [{"name": "name", "type": "string"}]
+                }]},
+                {"name": "previous_user", "type": ["null", "User"]},
+                {"name": "timestamp", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+                {"name": "score", "type": "double"},
+                {"name": "payload", "type": "bytes"},
+                {"name": "active", "type": "boolean"}
+            ]
+        }"#;
+        let avro_schema = AvroSchema::parse(schema_str, &[]).unwrap();
+
+        let mut payload = Vec::new();
+        encode_zigzag_varint(42, &mut payload);
+        encode_string("hello", &mut payload);
+        encode_zigzag_varint(1, &mut payload);
+        // tags: one block of 2 items.
+        encode_zigzag_varint(2, &mut payload);
+        encode_string("a", &mut payload);
+        encode_string("b", &mut payload);
+        encode_zigzag_varint(0, &mut payload);
+        // labels: one block of 1 item with its size in bytes.
+        encode_zigzag_varint(-1, &mut payload);
+        encode_zigzag_varint(4, &mut payload);
+        encode_string("k", &mut payload);
+        encode_zigzag_varint(-3, &mut payload);
+        encode_zigzag_varint(0, &mut payload);
+        // user: second branch of the union.
+        encode_zigzag_varint(1, &mut payload);
+        encode_string("bob", &mut payload);
+        // previous_user: null.
+        encode_zigzag_varint(0, &mut payload);
+        encode_zigzag_varint(1_700_000_000_000, &mut payload);
+        payload.extend_from_slice(&1.5f64.to_le_bytes());
+        encode_zigzag_varint(2, &mut payload);
+        payload.extend_from_slice(&[0xff, 0xfe]);
+        payload.push(1);
+
+        let json_obj = avro_schema.decode(&payload).unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({
+                "id": 42,
+                "message": "hello",
+                "level": "WARN",
+                "tags": ["a", "b"],
+                "labels": {"k": -3},
+                "user": {"name": "bob"},
+                "previous_user": null,
+                "timestamp": 1_700_000_000_000i64,
+                "score": 1.5,
+                "payload": "//4=",
+                "active": true,
+            })
+        );
+        let error = avro_schema.decode(&payload[..10]).unwrap_err();
+        assert!(error.to_string().contains("unexpected end of payload"));
+    }
+
+    #[test]
+    fn test_avro_schema_with_references() {
+        let referenced_schema_str = r#"{
+            "type": "record",
+            "name": "User",
+            "namespace": "com.example",
+            "fields": [{"name": "name", "type": "string"}]
+        }"#;
+        let schema_str = r#"{
+            "type": "record",
+            "name": "Event",
+            "namespace": "com.example",
+            "fields": [{"name": "user", "type": "com.example.User"}]
+        }"#;
+        let avro_schema =
+            AvroSchema::parse(schema_str, &[referenced_schema_str.to_string()]).unwrap();
+        let mut payload = Vec::new();
+        encode_string("alice", &mut payload);
+        let json_obj = avro_schema.decode(&payload).unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({"user": {"name": "alice"}})
+        );
+    }
+
+    #[test]
+    fn test_avro_schema_parse_errors() {
+        let error = AvroSchema::parse(r#""string""#, &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the root of an Avro schema must be a record"
+        );
+        let error = AvroSchema::parse(
+            r#"{"type": "record", "name": "Event", "fields": [{"name": "user", "type": "User"}]}"#,
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "unknown Avro type `User`");
+    }
+
+    #[test]
+    fn test_avro_schema_decode_invalid_block_count() {
+        let schema_str = r#"{
+            "type": "record",
+            "name": "Event",
+            "fields": [
+                {"name": "tags", "type": {"type": "array", "items": "string"}},
+                {"name": "labels", "type": {"type": "map", "values": "string"}}
+            ]
+        }"#;
+        let avro_schema = AvroSchema::parse(schema_str, &[]).unwrap();
+
+        let mut payload = Vec::new();
+        encode_zigzag_varint(i64::MAX, &mut payload);
+        encode_string("a", &mut payload);
+        let error = avro_schema.decode(&payload).unwrap_err();
+        assert!(error.to_string().starts_with("invalid Avro block count"));
+
+        let mut payload = Vec::new();
+        encode_zigzag_varint(0, &mut payload);
+        encode_zigzag_varint(-1_000_000, &mut payload);
+        encode_zigzag_varint(0, &mut payload);
+        let error = avro_schema.decode(&payload).unwrap_err();
+        assert!(error.to_string().starts_with("invalid Avro block count"));
+
+        let schema_str = r#"{
+            "type": "record",
+            "name": "Event",
+            "fields": [{"name": "nulls", "type": {"type": "array", "items": "null"}}]
+        }"#;
+        let avro_schema = AvroSchema::parse(schema_str, &[]).unwrap();
+
+        let mut payload = Vec::new();
+        encode_zigzag_varint(3, &mut payload);
+        encode_zigzag_varint(0, &mut payload);
+        let json_obj = avro_schema.decode(&payload).unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({"nulls": [null, null, null]})
+        );
+
+        let mut payload = Vec::new();
+        encode_zigzag_varint(i64::MAX, &mut payload);
+        encode_zigzag_varint(0, &mut payload);
+        let error = avro_schema.decode(&payload).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Avro array exceeds the maximum number of 10000 zero-sized items"
+        );
+    }
+
+    #[test]
+    fn test_avro_schema_recursive_record() {
+        let schema_str = r#"{
+            "type": "record",
+            "name": "Node",
+            "fields": [
+                {"name": "value", "type": "int"},
+                {"name": "next", "type": ["null", "Node"]}
+            ]
+        }"#;
+        let avro_schema = AvroSchema::parse(schema_str, &[]).unwrap();
+        let mut payload = Vec::new();
+        encode_zigzag_varint(1, &mut payload);
+        encode_zigzag_varint(1, &mut payload);
+        encode_zigzag_varint(2, &mut payload);
+        encode_zigzag_varint(0, &mut payload);
+        let json_obj = avro_schema.decode(&payload).unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({"value": 1, "next": {"value": 2, "next": null}})
+        );
+    }
+}
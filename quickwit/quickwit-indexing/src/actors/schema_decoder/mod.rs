@@ -0,0 +1,583 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod avro;
+mod protobuf;
+mod registry;
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{Context, bail};
+use base64::prelude::{BASE64_STANDARD, Engine};
+use bytes::Bytes;
+use prost::Message;
+use prost_types::FileDescriptorProto;
+use quickwit_common::uri::Uri;
+use quickwit_config::{SchemaConfig, SourceInputFormat};
+use quickwit_doc_mapper::JsonObject;
+use quickwit_storage::{StorageResolver, load_file};
+use tracing::warn;
+
+use self::avro::AvroSchema;
+use self::protobuf::ProtobufSchema;
+use self::registry::SchemaRegistryClient;
+use super::doc_processor::DocProcessorError;
+
+/// Maximum nesting depth of decoded values, which guards against stack overflows on recursive
+/// schemas.
+const MAX_NESTING_DEPTH: usize = 100;
+
+/// First byte of payloads framed in the Confluent wire format.
+const CONFLUENT_MAGIC_BYTE: u8 = 0;
+
+/// Name given to the file of a protobuf schema fetched from the registry. The message indexes of
+/// the Confluent wire format are relative to this file.
+const ROOT_PROTO_FILE_NAME: &str = "__root__.proto";
+
+enum Schema {
+    Avro(AvroSchema),
+    Protobuf(ProtobufSchema),
+}
+
+enum SchemaSource {
+    Static {
+        schema: Schema,
+        // Only set for protobuf schemas.
+        message_type_opt: Option<String>,
+    },
+    Registry {
+        client: SchemaRegistryClient,
+        // Schema IDs are immutable, so schemas are cached for the lifetime of the pipeline.
+        // Schemas that do not exist or fail to parse are cached as errors.
+        schemas: HashMap<u32, Result<Schema, String>>,
+    },
+}
+
+/// Decodes `avro` and `protobuf` payloads into JSON objects, resolving their schema either from a
+/// static schema or by ID from a Confluent-compatible schema registry.
+pub struct SchemaDecoder {
+    input_format: SourceInputFormat,
+    schema_source: SchemaSource,
+}
+
+impl SchemaDecoder {
+    pub async fn try_from_config(
+        input_format: SourceInputFormat,
+        schema_config: &SchemaConfig,
+        storage_resolver: &StorageResolver,
+    ) -> anyhow::Result<Self> {
+        if let Some(registry_url) = &schema_config.registry_url {
+            let client = SchemaRegistryClient::new(registry_url)?;
+            let schema_source = SchemaSource::Registry {
+                client,
+                schemas: HashMap::new(),
+            };
+            return Ok(Self {
+                input_format,
+                schema_source,
+            });
+        }
+        let schema_bytes = if let Some(schema_str) = &schema_config.schema {
+            schema_str.as_bytes().to_vec()
+        } else if let Some(schema_uri_str) = &schema_config.schema_uri {
+            let schema_uri = Uri::from_str(schema_uri_str)?;
+            load_file(storage_resolver, &schema_uri)
+                .await
+                .with_context(|| format!("failed to load schema file `{schema_uri}`"))?
+                .to_vec()
+        } else {
+            bail!("schema parameters are missing a schema");
+        };
+        Self::from_static_schema(
+            input_format,
+            &schema_bytes,
+            schema_config.message_type.clone(),
+        )
+    }
+
+    fn from_static_schema(
+        input_format: SourceInputFormat,
+        schema_bytes: &[u8],
+        message_type_opt: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let schema = match input_format {
+            SourceInputFormat::Avro => {
+                let schema_str =
+                    std::str::from_utf8(schema_bytes).context("Avro schema is not valid UTF-8")?;
+                Schema::Avro(AvroSchema::parse(schema_str, &[])?)
+            }
+            SourceInputFormat::Protobuf => {
+                let schema = ProtobufSchema::from_file_descriptor_set(schema_bytes)?;
+                let message_type = message_type_opt
+                    .as_deref()
+                    .context("`message_type` is required with a static protobuf schema")?;
+                if !schema.contains_message_type(message_type) {
+                    bail!("protobuf message type `{message_type}` not found in schema");
+                }
+                Schema::Protobuf(schema)
+            }
+            _ => bail!("input format `{input_format:?}` does not support schemas"),
+        };
+        let schema_source = SchemaSource::Static {
+            schema,
+            message_type_opt,
+        };
+        Ok(Self {
+            input_format,
+            schema_source,
+        })
+    }
+
+    /// Fetches the schemas of the raw docs that are not in cache yet. Fails if the schema registry
+    /// is unavailable, so that the batch is retried later instead of rejecting its documents.
+    pub async fn resolve_schemas(&mut self, raw_docs: &[Bytes]) -> anyhow::Result<()> {
+        let SchemaSource::Registry { client, schemas } = &mut self.schema_source else {
+            return Ok(());
+        };
+        for raw_doc in raw_docs {
+            let Ok((schema_id, _)) = split_confluent_header(raw_doc) else {
+                continue;
+            };
+            if schemas.contains_key(&schema_id) {
+                continue;
+            }
+            let schema_result = fetch_schema(client, self.input_format, schema_id).await?;
+
+            if let Err(error) = &schema_result {
+                warn!(schema_id, "failed to resolve schema: {error}");
+            }
+            schemas.insert(schema_id, schema_result);
+        }
+        Ok(())
+    }
+
+    pub fn decode(&self, raw_doc: &[u8]) -> Result<JsonObject, DocProcessorError> {
+        self.decode_inner(raw_doc)
+            .map_err(|error| DocProcessorError::SchemaDecoding(format!("{error:#}")))
+    }
+
+    fn decode_inner(&self, raw_doc: &[u8]) -> anyhow::Result<JsonObject> {
+        match &self.schema_source {
+            SchemaSource::Static {
+                schema: Schema::Avro(avro_schema),
+                ..
+            } => avro_schema.decode(raw_doc),
+            SchemaSource::Static {
+                schema: Schema::Protobuf(protobuf_schema),
+                message_type_opt,
+            } => {
+                let message_type = message_type_opt
+                    .as_deref()
+                    .expect("static protobuf schemas should have a message type");
+                protobuf_schema.decode(message_type, raw_doc)
+            }
+            SchemaSource::Registry { schemas, .. } => {
+                let (schema_id, payload) = split_confluent_header(raw_doc)?;
+                let schema = schemas
+                    .get(&schema_id)
+                    .with_context(|| format!("schema `{schema_id}` is not resolved"))?
+                    .as_ref()
+                    .map_err(|error| anyhow::anyhow!("schema `{schema_id}`: {error}"))?;
+                match schema {
+                    Schema::Avro(avro_schema) => avro_schema.decode(payload),
+                    Schema::Protobuf(protobuf_schema) => {
+                        let (message_indexes, payload) = split_message_indexes(payload)?;
+                        let message_type = protobuf_schema
+                            .message_type_from_indexes(ROOT_PROTO_FILE_NAME, &message_indexes)?;
+                        protobuf_schema.decode(message_type, payload)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Fetches and parses a schema from the registry. The outer error signals that the registry could
+/// not be reached, the inner one that the schema is missing or invalid.
+async fn fetch_schema(
+    client: &SchemaRegistryClient,
+    input_format: SourceInputFormat,
+    schema_id: u32,
+) -> anyhow::Result<Result<Schema, String>> {
+    let serialized = input_format == SourceInputFormat::Protobuf;
+
+    let Some(registered_schema) = client.get_schema(schema_id, serialized).await? else {
+        return Ok(Err("schema not found in registry".to_string()));
+    };
+    let schema_type = registered_schema.schema_type.as_deref().unwrap_or("AVRO");
+    let expected_schema_type = if serialized { "PROTOBUF" } else { "AVRO" };
+
+    if schema_type != expected_schema_type {
+        return Ok(Err(format!(
+            "expected a schema of type `{expected_schema_type}`, got `{schema_type}`"
+        )));
+    }
+    let Some(referenced_schemas) = client
+        .get_referenced_schemas(registered_schema.references, serialized)
+        .await?
+    else {
+        return Ok(Err("referenced schema not found in registry".to_string()));
+    };
+    let schema_result = if serialized {
+        parse_serialized_protobuf_schema(&registered_schema.schema, &referenced_schemas)
+            .map(Schema::Protobuf)
+    } else {
+        AvroSchema::parse(&registered_schema.schema, &referenced_schemas).map(Schema::Avro)
+    };
+    Ok(schema_result.map_err(|error| format!("{error:#}")))
+}
+
+fn parse_serialized_protobuf_schema(
+    schema_base64: &str,
+    referenced_schemas_base64: &[String],
+) -> anyhow::Result<ProtobufSchema> {
+    let decode_file_descriptor = |file_descriptor_base64: &str| {
+        let file_descriptor_bytes = BASE64_STANDARD
+            .decode(file_descriptor_base64)
+            .context("serialized protobuf schema is not valid base64")?;
+        FileDescriptorProto::decode(file_descriptor_bytes.as_slice())
+            .context("failed to decode serialized protobuf schema")
+    };
+    let mut file_descriptors = referenced_schemas_base64
+        .iter()
+        .map(|referenced_schema_base64| decode_file_descriptor(referenced_schema_base64))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut root_file_descriptor = decode_file_descriptor(schema_base64)?;
+    root_file_descriptor.name = Some(ROOT_PROTO_FILE_NAME.to_string());
+    file_descriptors.push(root_file_descriptor);
+
+    ProtobufSchema::from_file_descriptors(file_descriptors)
+}
+
+/// Splits a payload framed in the Confluent wire format into its schema ID and the rest of the
+/// payload.
+fn split_confluent_header(raw_doc: &[u8]) -> anyhow::Result<(u32, &[u8])> {
+    if raw_doc.len() < 5 || raw_doc[0] != CONFLUENT_MAGIC_BYTE {
+        bail!("payload is not framed in the Confluent wire format");
+    }
+    let schema_id = u32::from_be_bytes([raw_doc[1], raw_doc[2], raw_doc[3], raw_doc[4]]);
+    Ok((schema_id, &raw_doc[5..]))
+}
+
+/// Splits the message indexes prefix of a protobuf payload framed in the Confluent wire format.
+fn split_message_indexes(payload: &[u8]) -> anyhow::Result<(Vec<usize>, &[u8])> {
+    let mut reader = WireReader::new(payload);
+    let num_indexes = reader.read_zigzag_varint()?;
+
+    // A single `0` is the shorthand for the first message type.
+    if num_indexes == 0 {
+        return Ok((vec![0], reader.remaining()));
+    }
+    if num_indexes < 0 || num_indexes > MAX_NESTING_DEPTH as i64 {
+        bail!("invalid number of protobuf message indexes `{num_indexes}`");
+    }
+    let mut message_indexes = Vec::with_capacity(num_indexes as usize);
+
+    for _ in 0..num_indexes {
+        let message_index = reader.read_zigzag_varint()?;
+        let message_index = usize::try_from(message_index)
+            .with_context(|| format!("invalid protobuf message index `{message_index}`"))?;
+        message_indexes.push(message_index);
+    }
+    Ok((message_indexes, reader.remaining()))
+}
+
+/// Reads the primitive values shared by the Avro and protobuf binary encodings.
+struct WireReader<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> WireReader<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    fn remaining(&self) -> &'a [u8] {
+        self.buffer
+    }
+
+    fn read_bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.buffer.len() < len {
+            bail!("unexpected end of payload");
+        }
+        let (bytes, buffer) = self.buffer.split_at(len);
+        self.buffer = buffer;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let bytes = self.read_bytes(N)?;
+        Ok(bytes
+            .try_into()
+            .expect("slice should have the right length"))
+    }
+
+    fn read_u8(&mut self) -> anyhow::Result<u8> {
+        let [byte] = self.read_array::<1>()?;
+        Ok(byte)
+    }
+
+    fn read_u32_le(&mut self) -> anyhow::Result<u32> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    fn read_u64_le(&mut self) -> anyhow::Result<u64> {
+        self.read_array().map(u64::from_le_bytes)
+    }
+
+    fn read_f32_le(&mut self) -> anyhow::Result<f32> {
+        self.read_array().map(f32::from_le_bytes)
+    }
+
+    fn read_f64_le(&mut self) -> anyhow::Result<f64> {
+        self.read_array().map(f64::from_le_bytes)
+    }
+
+    fn read_varint(&mut self) -> anyhow::Result<u64> {
+        let mut value: u64 = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("varint is too long")
+    }
+
+    fn read_zigzag_varint(&mut self) -> anyhow::Result<i64> {
+        let value = self.read_varint()?;
+        Ok(((value >> 1) as i64) ^ -((value & 1) as i64))
+    }
+
+    fn read_len(&mut self) -> anyhow::Result<usize> {
+        let len = self.read_varint()?;
+        usize::try_from(len).with_context(|| format!("invalid length `{len}`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_types::field_descriptor_proto::{Label, Type};
+    use prost_types::{DescriptorProto, FieldDescriptorProto};
+    use serde_json::{Value as JsonValue, json};
+
+    use super::*;
+
+    const AVRO_SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "Event",
+        "fields": [{"name": "message", "type": "string"}]
+    }"#;
+
+    fn proto_field(name: &str, number: i32, field_type: Type) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type: Some(field_type as i32),
+            ..Default::default()
+        }
+    }
+
+    fn protobuf_file_descriptor() -> FileDescriptorProto {
+        let mut tags_field = proto_field("tags", 3, Type::String);
+        tags_field.label = Some(Label::Repeated as i32);
+        let mut codes_field = proto_field("codes", 4, Type::Int32);
+        codes_field.label = Some(Label::Repeated as i32);
+        let mut user_field = proto_field("user", 5, Type::Message);
+        user_field.type_name = Some(".test.Event.User".to_string());
+
+        let user_message = DescriptorProto {
+            name: Some("User".to_string()),
+            field: vec![proto_field("name", 1, Type::String)],
+            ..Default::default()
+        };
+        let event_message = DescriptorProto {
+            name: Some("Event".to_string()),
+            field: vec![
+                proto_field("message", 1, Type::String),
+                proto_field("count", 2, Type::Sint64),
+                tags_field,
+                codes_field,
+                user_field,
+            ],
+            nested_type: vec![user_message],
+            ..Default::default()
+        };
+        FileDescriptorProto {
+            name: Some("event.proto".to_string()),
+            package: Some("test".to_string()),
+            message_type: vec![event_message],
+            ..Default::default()
+        }
+    }
+
+    // Encodes `Event {message: "hello", count: -2, tags: ["a", "b"], codes: [1, 2], user: {name:
+    // "bob"}}`.
+    fn protobuf_event_payload() -> Vec<u8> {
+        let mut payload = vec![0x0a, 5];
+        payload.extend_from_slice(b"hello");
+        payload.extend_from_slice(&[0x10, 3]);
+        payload.extend_from_slice(&[0x1a, 1, b'a', 0x1a, 1, b'b']);
+        payload.extend_from_slice(&[0x22, 2, 1, 2]);
+        payload.extend_from_slice(&[0x2a, 5, 0x0a, 3]);
+        payload.extend_from_slice(b"bob");
+        payload
+    }
+
+    #[test]
+    fn test_wire_reader() {
+        let mut reader = WireReader::new(&[0x96, 0x01, 0x03, 0x01]);
+        assert_eq!(reader.read_varint().unwrap(), 150);
+        assert_eq!(reader.read_zigzag_varint().unwrap(), -2);
+        assert_eq!(reader.read_zigzag_varint().unwrap(), -1);
+        assert!(reader.is_empty());
+        let error = reader.read_u8().unwrap_err();
+        assert_eq!(error.to_string(), "unexpected end of payload");
+    }
+
+    #[test]
+    fn test_split_confluent_header() {
+        let (schema_id, payload) = split_confluent_header(&[0, 0, 0, 1, 2, 42]).unwrap();
+        assert_eq!(schema_id, 258);
+        assert_eq!(payload, &[42]);
+
+        split_confluent_header(&[1, 0, 0, 1, 2, 42]).unwrap_err();
+        split_confluent_header(&[0, 0, 0]).unwrap_err();
+
+        let (message_indexes, payload) = split_message_indexes(&[0, 42]).unwrap();
+        assert_eq!(message_indexes, vec![0]);
+        assert_eq!(payload, &[42]);
+
+        let (message_indexes, payload) = split_message_indexes(&[4, 2, 0, 42]).unwrap();
+        assert_eq!(message_indexes, vec![1, 0]);
+        assert_eq!(payload, &[42]);
+    }
+
+    #[test]
+    fn test_schema_decoder_static_avro() {
+        let schema_decoder = SchemaDecoder::from_static_schema(
+            SourceInputFormat::Avro,
+            AVRO_SCHEMA.as_bytes(),
+            None,
+        )
+        .unwrap();
+        let json_obj = schema_decoder
+            .decode(&[10, b'h', b'e', b'l', b'l', b'o'])
+            .unwrap();
+        assert_eq!(JsonValue::Object(json_obj), json!({"message": "hello"}));
+
+        let error = schema_decoder.decode(&[10, b'h']).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "schema decoding error: unexpected end of payload"
+        );
+    }
+
+    #[test]
+    fn test_schema_decoder_static_protobuf() {
+        let file_descriptor_set = prost_types::FileDescriptorSet {
+            file: vec![protobuf_file_descriptor()],
+        };
+        let schema_decoder = SchemaDecoder::from_static_schema(
+            SourceInputFormat::Protobuf,
+            &file_descriptor_set.encode_to_vec(),
+            Some("test.Event".to_string()),
+        )
+        .unwrap();
+        let json_obj = schema_decoder.decode(&protobuf_event_payload()).unwrap();
+        assert_eq!(
+            JsonValue::Object(json_obj),
+            json!({
+                "message": "hello",
+                "count": -2,
+                "tags": ["a", "b"],
+                "codes": [1, 2],
+                "user": {"name": "bob"},
+            })
+        );
+        let error = SchemaDecoder::from_static_schema(
+            SourceInputFormat::Protobuf,
+            &file_descriptor_set.encode_to_vec(),
+            Some("test.Unknown".to_string()),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "protobuf message type `test.Unknown` not found in schema"
+        );
+    }
+
+    #[test]
+    fn test_schema_decoder_registry() {
+        let avro_schema = AvroSchema::parse(AVRO_SCHEMA, &[]).unwrap();
+        let file_descriptor_base64 =
+            BASE64_STANDARD.encode(protobuf_file_descriptor().encode_to_vec());
+        let protobuf_schema =
+            parse_serialized_protobuf_schema(&file_descriptor_base64, &[]).unwrap();
+
+        let schemas = HashMap::from_iter([
+            (1, Ok(Schema::Avro(avro_schema))),
+            (2, Ok(Schema::Protobuf(protobuf_schema))),
+            (3, Err("schema not found in registry".to_string())),
+        ]);
+        let schema_decoder = SchemaDecoder {
+            input_format: SourceInputFormat::Avro,
+            schema_source: SchemaSource::Registry {
+                client: SchemaRegistryClient::new("http://localhost:8081").unwrap(),
+                schemas,
+            },
+        };
+        let json_obj = schema_decoder
+            .decode(&[0, 0, 0, 0, 1, 4, b'h', b'i'])
+            .unwrap();
+        assert_eq!(JsonValue::Object(json_obj), json!({"message": "hi"}));
+
+        let mut protobuf_payload = vec![0, 0, 0, 0, 2, 0];
+        protobuf_payload.extend_from_slice(&protobuf_event_payload());
+        let json_obj = schema_decoder.decode(&protobuf_payload).unwrap();
+        assert_eq!(json_obj["user"], json!({"name": "bob"}));
+
+        // Nested message `test.Event.User`.
+        let json_obj = schema_decoder
+            .decode(&[0, 0, 0, 0, 2, 4, 0, 0, 0x0a, 3, b'b', b'o', b'b'])
+            .unwrap();
+        assert_eq!(JsonValue::Object(json_obj), json!({"name": "bob"}));
+
+        let error = schema_decoder.decode(&[0, 0, 0, 0, 3, 0]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "schema decoding error: schema `3`: schema not found in registry"
+        );
+        let error = schema_decoder.decode(&[0, 0, 0, 0, 4, 0]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "schema decoding error: schema `4` is not resolved"
+        );
+        let error = schema_decoder.decode(b"{}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "schema decoding error: payload is not framed in the Confluent wire format"
+        );
+    }
+}
@@ -0,0 +1,454 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decodes protobuf payloads into JSON objects using message descriptors.
+//!
+//! Fields are keyed by their name in the `.proto` file and only fields present in the payload are
+//! emitted. 64-bit integers are decoded as JSON numbers, `bytes` values are encoded in base64, enum
+//! values are decoded as their name, and `google.protobuf.Timestamp` values are decoded as RFC 3339
+//! strings.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, bail};
+use base64::prelude::{BASE64_STANDARD, Engine};
+use prost::Message;
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorProto, FileDescriptorSet};
+use quickwit_doc_mapper::JsonObject;
+use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use super::{MAX_NESTING_DEPTH, WireReader};
+
+const TIMESTAMP_MESSAGE_TYPE: &str = "google.protobuf.Timestamp";
+
+const WIRE_TYPE_VARINT: u64 = 0;
+const WIRE_TYPE_FIXED64: u64 = 1;
+const WIRE_TYPE_LEN: u64 = 2;
+const WIRE_TYPE_FIXED32: u64 = 5;
+
+#[derive(Debug)]
+enum FieldKind {
+    Double,
+    Float,
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    Sint32,
+    Sint64,
+    Fixed32,
+    Fixed64,
+    Sfixed32,
+    Sfixed64,
+    Bool,
+    String,
+    Bytes,
+    Enum(String),
+    Message(String),
+}
+
+impl FieldKind {
+    fn is_packable(&self) -> bool {
+        !matches!(
+            self,
+            FieldKind::String | FieldKind::Bytes | FieldKind::Message(_)
+        )
+    }
+}
+
+#[derive(Debug)]
+struct FieldDescriptor {
+    name: String,
+    kind: FieldKind,
+    is_repeated: bool,
+}
+
+#[derive(Debug)]
+struct MessageDescriptor {
+    fields: HashMap<u32, FieldDescriptor>,
+    // Fully qualified names of the nested message types, in declaration order.
+    nested_message_types: Vec<String>,
+    is_map_entry: bool,
+}
+
+/// A pool of protobuf message and enum descriptors.
+#[derive(Debug, Default)]
+pub(crate) struct ProtobufSchema {
+    messages: HashMap<String, MessageDescriptor>,
+    enums: HashMap<String, HashMap<i32, String>>,
+    // Fully qualified names of the top-level message types of each file, in declaration order.
+    file_message_types: HashMap<String, Vec<String>>,
+}
+
+impl ProtobufSchema {
+    /// Builds a schema from a serialized `FileDescriptorSet`.
+    pub fn from_file_descriptor_set(file_descriptor_set_bytes: &[u8]) -> anyhow::Result<Self> {
+        let file_descriptor_set = FileDescriptorSet::decode(file_descriptor_set_bytes)
+            .context("failed to decode protobuf `FileDescriptorSet`")?;
+        Self::from_file_descriptors(file_descriptor_set.file)
+    }
+
+    pub fn from_file_descriptors(
+        file_descriptors: impl IntoIterator<Item = FileDescriptorProto>,
+    ) -> anyhow::Result<Self> {
+        let mut schema = ProtobufSchema::default();
+
+        for file_descriptor in file_descriptors {
+            let package = file_descriptor.package();
+            let mut message_types = Vec::with_capacity(file_descriptor.message_type.len());
+
+            for message_descriptor in &file_descriptor.message_type {
+                let full_name = schema.add_message(message_descriptor, package)?;
+                message_types.push(full_name);
+            }
+            for enum_descriptor in &file_descriptor.enum_type {
+                schema.add_enum(enum_descriptor, package);
+            }
+            schema
+                .file_message_types
+                .insert(file_descriptor.name().to_string(), message_types);
+        }
+        Ok(schema)
+    }
+
+    fn add_message(
+        &mut self,
+        message_descriptor: &DescriptorProto,
+        scope: &str,
+    ) -> anyhow::Result<String> {
+        let full_name = qualify(scope, message_descriptor.name());
+        let mut fields = HashMap::with_capacity(message_descriptor.field.len());
+
+        for field_descriptor in &message_descriptor.field {
+            let type_name = field_descriptor
+                .type_name()
+                .trim_start_matches('.')
+                .to_string();
+            let kind = match field_descriptor.r#type() {
+                Type::Double => FieldKind::Double,
+                Type::Float => FieldKind::Float,
+                Type::Int32 => FieldKind::Int32,
+                Type::Int64 => FieldKind::Int64,
+                Type::Uint32 => FieldKind::Uint32,
+                Type::Uint64 => FieldKind::Uint64,
+                Type::Sint32 => FieldKind::Sint32,
+                Type::Sint64 => FieldKind::Sint64,
+                Type::Fixed32 => FieldKind::Fixed32,
+                Type::Fixed64 => FieldKind::Fixed64,
+                Type::Sfixed32 => FieldKind::Sfixed32,
+                Type::Sfixed64 => FieldKind::Sfixed64,
+                Type::Bool => FieldKind::Bool,
+                Type::String => FieldKind::String,
+                Type::Bytes => FieldKind::Bytes,
+                Type::Enum => FieldKind::Enum(type_name),
+                Type::Message => FieldKind::Message(type_name),
+                Type::Group => bail!(
+                    "field `{}` of message `{full_name}` is a group: groups are not supported",
+                    field_descriptor.name()
+                ),
+            };
+            let field = FieldDescriptor {
+                name: field_descriptor.name().to_string(),
+                kind,
+                is_repeated: field_descriptor.label() == Label::Repeated,
+            };
+            fields.insert(field_descriptor.number() as u32, field);
+        }
+        let mut nested_message_types = Vec::with_capacity(message_descriptor.nested_type.len());
+
+        for nested_message_descriptor in &message_descriptor.nested_type {
+            let nested_full_name = self.add_message(nested_message_descriptor, &full_name)?;
+            nested_message_types.push(nested_full_name);
+        }
+        for nested_enum_descriptor in &message_descriptor.enum_type {
+            self.add_enum(nested_enum_descriptor, &full_name);
+        }
+        let is_map_entry = message_descriptor
+            .options
+            .as_ref()
+            .is_some_and(|options| options.map_entry());
+        let message = MessageDescriptor {
+            fields,
+            nested_message_types,
+            is_map_entry,
+        };
+        self.messages.insert(full_name.clone(), message);
+        Ok(full_name)
+    }
+
+    fn add_enum(&mut self, enum_descriptor: &EnumDescriptorProto, scope: &str) {
+        let full_name = qualify(scope, enum_descriptor.name());
+        let values = enum_descriptor
+            .value
+            .iter()
+            .map(|value| (value.number(), value.name().to_string()))
+            .collect();
+        self.enums.insert(full_name, values);
+    }
+
+    pub fn contains_message_type(&self, message_type: &str) -> bool {
+        self.messages.contains_key(message_type)
+    }
+
+    /// Resolves the message type designated by the message indexes of the Confluent wire format:
+    /// the first index selects a top-level message of the file, the following ones select nested
+    /// messages.
+    pub fn message_type_from_indexes(
+        &self,
+        file_name: &str,
+        message_indexes: &[usize],
+    ) -> anyhow::Result<&str> {
+        let file_message_types = self
+            .file_message_types
+            .get(file_name)
+            .with_context(|| format!("unknown protobuf file `{file_name}`"))?;
+        let (first_index, nested_indexes) = message_indexes
+            .split_first()
+            .context("protobuf message indexes are empty")?;
+        let mut message_type = file_message_types
+            .get(*first_index)
+            .with_context(|| format!("invalid protobuf message index `{first_index}`"))?;
+
+        for nested_index in nested_indexes {
+            message_type = self.messages[message_type]
+                .nested_message_types
+                .get(*nested_index)
+                .with_context(|| format!("invalid protobuf message index `{nested_index}`"))?;
+        }
+        Ok(message_type)
+    }
+
+    pub fn decode(&self, message_type: &str, payload: &[u8]) -> anyhow::Result<JsonObject> {
+        self.decode_message(message_type, payload, 0)
+    }
+
+    fn decode_message(
+        &self,
+        message_type: &str,
+        payload: &[u8],
+        depth: usize,
+    ) -> anyhow::Result<JsonObject> {
+        if depth > MAX_NESTING_DEPTH {
+            bail!("protobuf message exceeds the maximum nesting depth of {MAX_NESTING_DEPTH}");
+        }
+        let message = self
+            .messages
+            .get(message_type)
+            .with_context(|| format!("unknown protobuf message type `{message_type}`"))?;
+        let mut json_obj = JsonMap::new();
+        let mut reader = WireReader::new(payload);
+
+        while !reader.is_empty() {
+            let tag = reader.read_varint()?;
+            let field_number = (tag >> 3) as u32;
+            let wire_type = tag & 0x7;
+
+            let Some(field) = message.fields.get(&field_number) else {
+                skip_field(&mut reader, wire_type)?;
+                continue;
+            };
+            if field.is_repeated && field.kind.is_packable() && wire_type == WIRE_TYPE_LEN {
+                let len = reader.read_len()?;
+                let mut packed_reader = WireReader::new(reader.read_bytes(len)?);
+                let mut packed_values = Vec::new();
+
+                while !packed_reader.is_empty() {
+                    packed_values.push(self.decode_scalar(&field.kind, &mut packed_reader)?);
+                }
+                if let JsonValue::Array(values) = json_obj
+                    .entry(field.name.clone())
+                    .or_insert_with(|| JsonValue::Array(Vec::new()))
+                {
+                    values.extend(packed_values);
+                }
+                continue;
+            }
+            let value = self.decode_field(field, wire_type, &mut reader, depth)?;
+
+            if !field.is_repeated {
+                json_obj.insert(field.name.clone(), value);
+                continue;
+            }
+            if self.is_map_entry(&field.kind)
+                && let JsonValue::Object(entry) = value
+            {
+                let (key, value) = map_entry_into_key_value(entry);
+
+                if let JsonValue::Object(map) = json_obj
+                    .entry(field.name.clone())
+                    .or_insert_with(|| JsonValue::Object(JsonMap::new()))
+                {
+                    map.insert(key, value);
+                }
+                continue;
+            }
+            if let JsonValue::Array(values) = json_obj
+                .entry(field.name.clone())
+                .or_insert_with(|| JsonValue::Array(Vec::new()))
+            {
+                values.push(value);
+            }
+        }
+        Ok(json_obj)
+    }
+
+    fn is_map_entry(&self, field_kind: &FieldKind) -> bool {
+        let FieldKind::Message(message_type) = field_kind else {
+            return false;
+        };
+        self.messages
+            .get(message_type)
+            .is_some_and(|message| message.is_map_entry)
+    }
+
+    fn decode_field(
+        &self,
+        field: &FieldDescriptor,
+        wire_type: u64,
+        reader: &mut WireReader,
+        depth: usize,
+    ) -> anyhow::Result<JsonValue> {
+        let expected_wire_type = match &field.kind {
+            FieldKind::Double | FieldKind::Fixed64 | FieldKind::Sfixed64 => WIRE_TYPE_FIXED64,
+            FieldKind::Float | FieldKind::Fixed32 | FieldKind::Sfixed32 => WIRE_TYPE_FIXED32,
+            FieldKind::String | FieldKind::Bytes | FieldKind::Message(_) => WIRE_TYPE_LEN,
+            _ => WIRE_TYPE_VARINT,
+        };
+        if wire_type != expected_wire_type {
+            bail!(
+                "protobuf field `{}` has wire type {wire_type}, expected {expected_wire_type}",
+                field.name
+            );
+        }
+        if let FieldKind::Message(message_type) = &field.kind {
+            let len = reader.read_len()?;
+            let message_payload = reader.read_bytes(len)?;
+
+            if message_type == TIMESTAMP_MESSAGE_TYPE {
+                return decode_timestamp(message_payload);
+            }
+            let json_obj = self.decode_message(message_type, message_payload, depth + 1)?;
+            return Ok(JsonValue::Object(json_obj));
+        }
+        self.decode_scalar(&field.kind, reader)
+    }
+
+    fn decode_scalar(
+        &self,
+        field_kind: &FieldKind,
+        reader: &mut WireReader,
+    ) -> anyhow::Result<JsonValue> {
+        let json_value = match field_kind {
+            FieldKind::Double => float_to_json(reader.read_f64_le()?),
+            FieldKind::Float => float_to_json(reader.read_f32_le()? as f64),
+            FieldKind::Int32 => JsonValue::from(reader.read_varint()? as i32),
+            FieldKind::Int64 => JsonValue::from(reader.read_varint()? as i64),
+            FieldKind::Uint32 => JsonValue::from(reader.read_varint()? as u32),
+            FieldKind::Uint64 => JsonValue::from(reader.read_varint()?),
+            FieldKind::Sint32 | FieldKind::Sint64 => JsonValue::from(reader.read_zigzag_varint()?),
+            FieldKind::Fixed32 => JsonValue::from(reader.read_u32_le()?),
+            FieldKind::Fixed64 => JsonValue::from(reader.read_u64_le()?),
+            FieldKind::Sfixed32 => JsonValue::from(reader.read_u32_le()? as i32),
+            FieldKind::Sfixed64 => JsonValue::from(reader.read_u64_le()? as i64),
+            FieldKind::Bool => JsonValue::Bool(reader.read_varint()? != 0),
+            FieldKind::String => {
+                let len = reader.read_len()?;
+                let value = std::str::from_utf8(reader.read_bytes(len)?)
+                    .context("invalid UTF-8 in protobuf string")?;
+                JsonValue::String(value.to_string())
+            }
+            FieldKind::Bytes => {
+                let len = reader.read_len()?;
+                JsonValue::String(BASE64_STANDARD.encode(reader.read_bytes(len)?))
+            }
+            FieldKind::Enum(enum_type) => {
+                let number = reader.read_varint()? as i32;
+                self.enums
+                    .get(enum_type)
+                    .and_then(|values| values.get(&number))
+                    .map(|name| JsonValue::String(name.clone()))
+                    .unwrap_or_else(|| JsonValue::from(number))
+            }
+            FieldKind::Message(_) => unreachable!("messages are not scalars"),
+        };
+        Ok(json_value)
+    }
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{scope}.{name}")
+    }
+}
+
+fn float_to_json(value: f64) -> JsonValue {
+    JsonNumber::from_f64(value)
+        .map(JsonValue::Number)
+        .unwrap_or(JsonValue::Null)
+}
+
+fn map_entry_into_key_value(mut entry: JsonObject) -> (String, JsonValue) {
+    let key = match entry.remove("key") {
+        Some(JsonValue::String(key)) => key,
+        Some(key) => key.to_string(),
+        None => String::new(),
+    };
+    let value = entry.remove("value").unwrap_or(JsonValue::Null);
+    (key, value)
+}
+
+fn decode_timestamp(payload: &[u8]) -> anyhow::Result<JsonValue> {
+    let mut reader = WireReader::new(payload);
+    let mut seconds: i64 = 0;
+    let mut nanos: i64 = 0;
+
+    while !reader.is_empty() {
+        let tag = reader.read_varint()?;
+        match (tag >> 3, tag & 0x7) {
+            (1, WIRE_TYPE_VARINT) => seconds = reader.read_varint()? as i64,
+            (2, WIRE_TYPE_VARINT) => nanos = reader.read_varint()? as i32 as i64,
+            (_, wire_type) => skip_field(&mut reader, wire_type)?,
+        }
+    }
+    let timestamp_nanos = seconds as i128 * 1_000_000_000 + nanos as i128;
+    let datetime = OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos)
+        .context("protobuf timestamp is out of range")?;
+    let formatted = datetime.format(&Rfc3339)?;
+    Ok(JsonValue::String(formatted))
+}
+
+fn skip_field(reader: &mut WireReader, wire_type: u64) -> anyhow::Result<()> {
+    match wire_type {
+        WIRE_TYPE_VARINT => {
+            reader.read_varint()?;
+        }
+        WIRE_TYPE_FIXED64 => {
+            reader.read_bytes(8)?;
+        }
+        WIRE_TYPE_LEN => {
+            let len = reader.read_len()?;
+            reader.read_bytes(len)?;
+        }
+        WIRE_TYPE_FIXED32 => {
+            reader.read_bytes(4)?;
+        }
+        _ => bail!("unsupported protobuf wire type {wire_type}"),
+    }
+    Ok(())
+}
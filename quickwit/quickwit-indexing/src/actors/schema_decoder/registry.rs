@@ -0,0 +1,150 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use anyhow::Context;
+use futures::future::BoxFuture;
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+const REGISTRY_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RegisteredSchema {
+    pub schema: String,
+    // Absent for Avro schemas.
+    #[serde(default)]
+    pub schema_type: Option<String>,
+    #[serde(default)]
+    pub references: Vec<SchemaReference>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct SchemaReference {
+    subject: String,
+    version: i32,
+}
+
+/// A minimal client for the Confluent schema registry REST API.
+pub(super) struct SchemaRegistryClient {
+    base_url: String,
+    http_client: reqwest::Client,
+}
+
+impl SchemaRegistryClient {
+    pub fn new(registry_url: &str) -> anyhow::Result<Self> {
+        let http_client = reqwest::Client::builder()
+            .timeout(REGISTRY_REQUEST_TIMEOUT)
+            .build()
+            .context("failed to build schema registry HTTP client")?;
+        Ok(Self {
+            base_url: registry_url.trim_end_matches('/').to_string(),
+            http_client,
+        })
+    }
+
+    /// Fetches a schema by ID. Returns `None` if the schema does not exist.
+    ///
+    /// With `serialized`, protobuf schemas are returned as base64-encoded `FileDescriptorProto`s
+    /// instead of `.proto` files.
+    pub async fn get_schema(
+        &self,
+        schema_id: u32,
+        serialized: bool,
+    ) -> anyhow::Result<Option<RegisteredSchema>> {
+        let url = format!("{}/schemas/ids/{schema_id}", self.base_url);
+        self.get(&url, serialized).await
+    }
+
+    /// Fetches the schemas referenced, directly or transitively, by a schema. Referenced schemas
+    /// are returned before the schemas referencing them. Returns `None` if a referenced schema
+    /// does not exist.
+    pub async fn get_referenced_schemas(
+        &self,
+        references: Vec<SchemaReference>,
+        serialized: bool,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        let mut visited = HashSet::new();
+        let mut schemas = Vec::new();
+        let found = self
+            .collect_referenced_schemas(references, serialized, &mut visited, &mut schemas)
+            .await?;
+        Ok(found.then_some(schemas))
+    }
+
+    fn collect_referenced_schemas<'a>(
+        &'a self,
+        references: Vec<SchemaReference>,
+        serialized: bool,
+        visited: &'a mut HashSet<(String, i32)>,
+        schemas: &'a mut Vec<String>,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            for reference in references {
+                if !visited.insert((reference.subject.clone(), reference.version)) {
+                    continue;
+                }
+                let url = format!(
+                    "{}/subjects/{}/versions/{}",
+                    self.base_url,
+                    utf8_percent_encode(&reference.subject, NON_ALPHANUMERIC),
+                    reference.version
+                );
+                let Some(referenced_schema) = self.get(&url, serialized).await? else {
+                    return Ok(false);
+                };
+                let found = self
+                    .collect_referenced_schemas(
+                        referenced_schema.references,
+                        serialized,
+                        &mut *visited,
+                        &mut *schemas,
+                    )
+                    .await?;
+                if !found {
+                    return Ok(false);
+                }
+                schemas.push(referenced_schema.schema);
+            }
+            Ok(true)
+        })
+    }
+
+    async fn get(&self, url: &str, serialized: bool) -> anyhow::Result<Option<RegisteredSchema>> {
+        let mut request = self.http_client.get(url);
+
+        if serialized {
+            request = request.query(&[("format", "serialized")]);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("failed to reach schema registry at `{url}`"))?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let registered_schema = response
+            .error_for_status()
+            .with_context(|| format!("schema registry request `{url}` failed"))?
+            .json::<RegisteredSchema>()
+            .await
+            .with_context(|| format!("failed to parse schema registry response from `{url}`"))?;
+        Ok(Some(registered_schema))
+    }
+}
@@ -20,6 +20,7 @@ use quickwit_common::pubsub::EventBroker;
 use quickwit_config::NodeConfig;
 use quickwit_ingest::{IngestApiService, IngesterPool};
 use quickwit_proto::indexing::PipelineMetrics;
+use quickwit_proto::ingest::router::IngestRouterServiceClient;
 use quickwit_proto::metastore::MetastoreServiceClient;
 use quickwit_storage::StorageResolver;
 use tracing::info;
@@ -67,6 +68,7 @@ pub async fn start_indexing_service(
     cluster: Cluster,
     metastore: MetastoreServiceClient,
     ingester_pool: IngesterPool,
+    ingest_router: IngestRouterServiceClient,
     storage_resolver: StorageResolver,
     event_broker: EventBroker,
 ) -> anyhow::Result<Mailbox<IndexingService>> {
@@ -86,6 +88,7 @@ pub async fn start_indexing_service(
         ingest_api_service_mailbox,
         merge_scheduler_mailbox,
         ingester_pool,
+        Some(ingest_router),
         storage_resolver,
         event_broker,
     )
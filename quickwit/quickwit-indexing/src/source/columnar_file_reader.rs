@@ -0,0 +1,507 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reads the rows of Parquet and Arrow IPC files as JSON documents.
+//!
+//! Timestamps and dates are converted to RFC 3339 strings in UTC, binary values are encoded in
+//! base64, lists become arrays, and structs and maps become objects. Decimals, times, durations,
+//! and intervals are converted to their string representation, which preserves the precision of
+//! decimals.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufReader;
+
+use anyhow::Context;
+use arrow::array::{Array, AsArray, RecordBatch};
+use arrow::datatypes::{
+    DataType, Date32Type, Date64Type, Float16Type, Float32Type, Float64Type, Int8Type, Int16Type,
+    Int32Type, Int64Type, TimeUnit, TimestampMicrosecondType, TimestampMillisecondType,
+    TimestampNanosecondType, TimestampSecondType, UInt8Type, UInt16Type, UInt32Type, UInt64Type,
+};
+use arrow::error::ArrowError;
+use arrow::ipc::reader::FileReader as IpcFileReader;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use base64::prelude::{BASE64_STANDARD, Engine};
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use quickwit_common::uri::Uri;
+use quickwit_doc_mapper::JsonObject;
+use quickwit_storage::StorageResolver;
+use serde_json::{Number as JsonNumber, Value as JsonValue};
+use tempfile::NamedTempFile;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use super::doc_file_reader::{ColumnarFileFormat, FileRecord, dir_and_filename};
+
+/// Number of rows decoded at once.
+const RECORD_BATCH_NUM_ROWS: usize = 1_024;
+
+const NANOS_PER_DAY: i128 = 86_400_000_000_000;
+
+type RecordBatchIterator = Box<dyn Iterator<Item = Result<RecordBatch, ArrowError>> + Send>;
+
+/// Reads the rows of a columnar file. The offset of a record is the number of rows read from the
+/// beginning of the file, so reading can resume at any row of any row group.
+pub struct ColumnarFileReader {
+    // `None` once all the record batches have been read.
+    record_batch_iter_opt: Option<RecordBatchIterator>,
+    // Rows of the current record batch, converted to JSON documents.
+    docs: VecDeque<Bytes>,
+    num_rows_to_skip: usize,
+    next_row: u64,
+    // Local copy of a remote file, deleted on drop.
+    _temp_file_opt: Option<NamedTempFile>,
+}
+
+impl ColumnarFileReader {
+    pub async fn from_uri(
+        storage_resolver: &StorageResolver,
+        uri: &Uri,
+        format: ColumnarFileFormat,
+        offset: usize,
+    ) -> anyhow::Result<Self> {
+        // Columnar files are not read sequentially, so remote files are downloaded first.
+        let (file, temp_file_opt) = if let Some(filepath) = uri.filepath() {
+            let file = File::open(filepath)
+                .with_context(|| format!("failed to open file `{}`", filepath.display()))?;
+            (file, None)
+        } else {
+            let (dir_uri, file_name) = dir_and_filename(uri)?;
+            let storage = storage_resolver.resolve(&dir_uri).await?;
+            let temp_file = NamedTempFile::new()?;
+            storage.copy_to_file(file_name, temp_file.path()).await?;
+            (temp_file.reopen()?, Some(temp_file))
+        };
+        let (record_batch_iter, num_rows_to_skip) =
+            tokio::task::spawn_blocking(move || open_record_batch_iter(file, format, offset))
+                .await??;
+        let mut reader = ColumnarFileReader {
+            record_batch_iter_opt: Some(record_batch_iter),
+            docs: VecDeque::new(),
+            num_rows_to_skip,
+            next_row: offset as u64,
+            _temp_file_opt: temp_file_opt,
+        };
+        reader.fill_docs().await?;
+        Ok(reader)
+    }
+
+    /// Reads the next row of the file. Returns `None` when EOF is reached.
+    pub async fn next_record(&mut self) -> anyhow::Result<Option<FileRecord>> {
+        let Some(doc) = self.docs.pop_front() else {
+            return Ok(None);
+        };
+        self.next_row += 1;
+
+        // Decodes the next record batch ahead of time to tell whether this row is the last one.
+        if self.docs.is_empty() {
+            self.fill_docs().await?;
+        }
+        Ok(Some(FileRecord {
+            next_offset: self.next_row,
+            doc,
+            is_last: self.docs.is_empty(),
+        }))
+    }
+
+    async fn fill_docs(&mut self) -> anyhow::Result<()> {
+        while self.docs.is_empty() {
+            let Some(mut record_batch_iter) = self.record_batch_iter_opt.take() else {
+                return Ok(());
+            };
+            let num_rows_to_skip = self.num_rows_to_skip;
+
+            let (record_batch_iter, docs_opt) = tokio::task::spawn_blocking(move || {
+                let docs_opt = record_batch_iter
+                    .next()
+                    .map(|record_batch_res| {
+                        let record_batch = record_batch_res?;
+                        let num_skipped_rows = num_rows_to_skip.min(record_batch.num_rows());
+                        let record_batch = record_batch
+                            .slice(num_skipped_rows, record_batch.num_rows() - num_skipped_rows);
+                        let docs = record_batch_to_json_docs(&record_batch)?;
+                        anyhow::Ok((num_skipped_rows, docs))
+                    })
+                    .transpose();
+                (record_batch_iter, docs_opt)
+            })
+            .await?;
+
+            if let Some((num_skipped_rows, docs)) = docs_opt? {
+                self.num_rows_to_skip -= num_skipped_rows;
+                self.docs.extend(docs);
+                self.record_batch_iter_opt = Some(record_batch_iter);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns an iterator over the record batches of the file, starting at row `offset`, along with
+/// the number of rows that remain to be skipped.
+fn open_record_batch_iter(
+    file: File,
+    format: ColumnarFileFormat,
+    offset: usize,
+) -> anyhow::Result<(RecordBatchIterator, usize)> {
+    match format {
+        ColumnarFileFormat::Parquet => {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+                .context("failed to read Parquet file metadata")?;
+            let row_groups = builder.metadata().row_groups();
+            let mut first_row_group = 0;
+            let mut num_rows_to_skip = offset;
+
+            // Skips the row groups that were entirely read.
+            for row_group in row_groups {
+                let num_rows = row_group.num_rows() as usize;
+                if num_rows_to_skip < num_rows {
+                    break;
+                }
+                num_rows_to_skip -= num_rows;
+                first_row_group += 1;
+            }
+            let num_row_groups = row_groups.len();
+            let reader = builder
+                .with_row_groups((first_row_group..num_row_groups).collect())
+                .with_offset(num_rows_to_skip)
+                .with_batch_size(RECORD_BATCH_NUM_ROWS)
+                .build()?;
+            Ok((Box::new(reader), 0))
+        }
+        ColumnarFileFormat::ArrowIpc => {
+            // Arrow IPC files are cheap to decode, so the rows to skip are simply read.
+            let reader = IpcFileReader::try_new(BufReader::new(file), None)
+                .context("failed to read Arrow IPC file footer")?;
+            Ok((Box::new(reader), offset))
+        }
+    }
+}
+
+fn record_batch_to_json_docs(record_batch: &RecordBatch) -> anyhow::Result<Vec<Bytes>> {
+    let schema = record_batch.schema();
+    let mut docs = Vec::with_capacity(record_batch.num_rows());
+
+    for row in 0..record_batch.num_rows() {
+        let mut json_obj = JsonObject::with_capacity(record_batch.num_columns());
+
+        for (field, column) in schema.fields().iter().zip(record_batch.columns()) {
+            let json_value = array_value_to_json(column.as_ref(), row)
+                .with_context(|| format!("failed to convert column `{}`", field.name()))?;
+            json_obj.insert(field.name().clone(), json_value);
+        }
+        docs.push(Bytes::from(serde_json::to_vec(&json_obj)?));
+    }
+    Ok(docs)
+}
+
+fn array_value_to_json(array: &dyn Array, row: usize) -> anyhow::Result<JsonValue> {
+    if array.is_null(row) {
+        return Ok(JsonValue::Null);
+    }
+    let json_value = match array.data_type() {
+        DataType::Null => JsonValue::Null,
+        DataType::Boolean => JsonValue::Bool(array.as_boolean().value(row)),
+        DataType::Int8 => array.as_primitive::<Int8Type>().value(row).into(),
+        DataType::Int16 => array.as_primitive::<Int16Type>().value(row).into(),
+        DataType::Int32 => array.as_primitive::<Int32Type>().value(row).into(),
+        DataType::Int64 => array.as_primitive::<Int64Type>().value(row).into(),
+        DataType::UInt8 => array.as_primitive::<UInt8Type>().value(row).into(),
+        DataType::UInt16 => array.as_primitive::<UInt16Type>().value(row).into(),
+        DataType::UInt32 => array.as_primitive::<UInt32Type>().value(row).into(),
+        DataType::UInt64 => array.as_primitive::<UInt64Type>().value(row).into(),
+        DataType::Float16 => float_to_json(array.as_primitive::<Float16Type>().value(row).to_f64()),
+        DataType::Float32 => float_to_json(array.as_primitive::<Float32Type>().value(row) as f64),
+        DataType::Float64 => float_to_json(array.as_primitive::<Float64Type>().value(row)),
+        DataType::Utf8 => array.as_string::<i32>().value(row).into(),
+        DataType::LargeUtf8 => array.as_string::<i64>().value(row).into(),
+        DataType::Utf8View => array.as_string_view().value(row).into(),
+        DataType::Binary => bytes_to_json(array.as_binary::<i32>().value(row)),
+        DataType::LargeBinary => bytes_to_json(array.as_binary::<i64>().value(row)),
+        DataType::BinaryView => bytes_to_json(array.as_binary_view().value(row)),
+        DataType::FixedSizeBinary(_) => bytes_to_json(array.as_fixed_size_binary().value(row)),
+        DataType::Date32 => {
+            let num_days = array.as_primitive::<Date32Type>().value(row);
+            timestamp_nanos_to_json(num_days as i128 * NANOS_PER_DAY)?
+        }
+        DataType::Date64 => {
+            let timestamp_millis = array.as_primitive::<Date64Type>().value(row);
+            timestamp_nanos_to_json(timestamp_millis as i128 * 1_000_000)?
+        }
+        // Timestamps are stored relative to the UNIX epoch in UTC, whatever their time zone.
+        DataType::Timestamp(time_unit, _) => {
+            let timestamp_nanos = match time_unit {
+                TimeUnit::Second => {
+                    array.as_primitive::<TimestampSecondType>().value(row) as i128 * 1_000_000_000
+                }
+                TimeUnit::Millisecond => {
+                    array.as_primitive::<TimestampMillisecondType>().value(row) as i128 * 1_000_000
+                }
+                TimeUnit::Microsecond => {
+                    array.as_primitive::<TimestampMicrosecondType>().value(row) as i128 * 1_000
+                }
+                TimeUnit::Nanosecond => {
+                    array.as_primitive::<TimestampNanosecondType>().value(row) as i128
+                }
+            };
+            timestamp_nanos_to_json(timestamp_nanos)?
+        }
+        DataType::List(_) => list_to_json(array.as_list::<i32>().value(row).as_ref())?,
+        DataType::LargeList(_) => list_to_json(array.as_list::<i64>().value(row).as_ref())?,
+        DataType::FixedSizeList(..) => {
+            list_to_json(array.as_fixed_size_list().value(row).as_ref())?
+        }
+        DataType::Struct(fields) => {
+            let struct_array = array.as_struct();
+            let mut json_obj = JsonObject::with_capacity(fields.len());
+
+            for (field, column) in fields.iter().zip(struct_array.columns()) {
+                let json_value = array_value_to_json(column.as_ref(), row)?;
+                json_obj.insert(field.name().clone(), json_value);
+            }
+            JsonValue::Object(json_obj)
+        }
+        DataType::Map(..) => {
+            let entries = array.as_map().value(row);
+            let keys = entries.column(0);
+            let values = entries.column(1);
+            let mut json_obj = JsonObject::with_capacity(entries.len());
+
+            for entry in 0..entries.len() {
+                let key = match array_value_to_json(keys.as_ref(), entry)? {
+                    JsonValue::String(key) => key,
+                    key => key.to_string(),
+                };
+                let json_value = array_value_to_json(values.as_ref(), entry)?;
+                json_obj.insert(key, json_value);
+            }
+            JsonValue::Object(json_obj)
+        }
+        DataType::Dictionary(..) => {
+            let dictionary_array = array.as_any_dictionary();
+            let key = array_value_to_json(dictionary_array.keys(), row)?
+                .as_u64()
+                .context("dictionary key should be a non-negative integer")?;
+            array_value_to_json(dictionary_array.values().as_ref(), key as usize)?
+        }
+        _ => {
+            let formatter = ArrayFormatter::try_new(array, &FormatOptions::default())?;
+            JsonValue::String(formatter.value(row).to_string())
+        }
+    };
+    Ok(json_value)
+}
+
+fn list_to_json(values: &dyn Array) -> anyhow::Result<JsonValue> {
+    let json_values = (0..values.len())
+        .map(|index| array_value_to_json(values, index))
+        .collect::<anyhow::Result<Vec<JsonValue>>>()?;
+    Ok(JsonValue::Array(json_values))
+}
+
+fn bytes_to_json(bytes: &[u8]) -> JsonValue {
+    JsonValue::String(BASE64_STANDARD.encode(bytes))
+}
+
+// JSON does not support NaN and infinite values.
+fn float_to_json(value: f64) -> JsonValue {
+    JsonNumber::from_f64(value).map_or(JsonValue::Null, JsonValue::Number)
+}
+
+fn timestamp_nanos_to_json(timestamp_nanos: i128) -> anyhow::Result<JsonValue> {
+    let rfc3339 = OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos)?.format(&Rfc3339)?;
+    Ok(JsonValue::String(rfc3339))
+}
+
+#[cfg(test)]
+pub mod columnar_file_test_helpers {
+    use std::sync::Arc;
+
+    use arrow::array::{
+        ArrayRef, Int64Array, ListBuilder, StringArray, StringBuilder, StructArray,
+        TimestampMillisecondArray,
+    };
+    use arrow::datatypes::{Field, Schema};
+    use arrow::ipc::writer::FileWriter as IpcFileWriter;
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::properties::WriterProperties;
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    /// Builds a record batch of `num_rows` rows, where the `id` column holds the row index.
+    pub fn generate_record_batch(num_rows: usize) -> RecordBatch {
+        let ids: ArrayRef = Arc::new(Int64Array::from_iter_values(0..num_rows as i64));
+        let timestamps: ArrayRef = Arc::new(
+            TimestampMillisecondArray::from_iter_values(
+                (0..num_rows as i64).map(|row| 1_700_000_000_000 + row),
+            )
+            .with_timezone("+02:00"),
+        );
+        let mut tags_builder = ListBuilder::new(StringBuilder::new());
+        for row in 0..num_rows {
+            tags_builder.values().append_value(format!("tag-{row}"));
+            tags_builder.append(true);
+        }
+        let tags: ArrayRef = Arc::new(tags_builder.finish());
+        let hosts: ArrayRef = Arc::new(StringArray::from_iter(
+            (0..num_rows).map(|row| (row % 2 == 0).then(|| format!("host-{row}"))),
+        ));
+        let resource: ArrayRef = Arc::new(StructArray::from(vec![(
+            Arc::new(Field::new("host", DataType::Utf8, true)),
+            hosts,
+        )]));
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("timestamp", timestamps.data_type().clone(), false),
+            Field::new("tags", tags.data_type().clone(), false),
+            Field::new("resource", resource.data_type().clone(), false),
+        ]));
+        RecordBatch::try_new(schema, vec![ids, timestamps, tags, resource]).unwrap()
+    }
+
+    /// Writes a Parquet file of `num_rows` rows, in row groups of `row_group_num_rows` rows.
+    pub fn generate_parquet_file(num_rows: usize, row_group_num_rows: usize) -> NamedTempFile {
+        let record_batch = generate_record_batch(num_rows);
+        let temp_file = tempfile::Builder::new()
+            .suffix(".parquet")
+            .tempfile()
+            .unwrap();
+        let writer_properties = WriterProperties::builder()
+            .set_max_row_group_size(row_group_num_rows)
+            .build();
+        let mut writer = ArrowWriter::try_new(
+            temp_file.reopen().unwrap(),
+            record_batch.schema(),
+            Some(writer_properties),
+        )
+        .unwrap();
+        writer.write(&record_batch).unwrap();
+        writer.close().unwrap();
+        temp_file
+    }
+
+    /// Writes an Arrow IPC file of `num_rows` rows, in record batches of `batch_num_rows` rows.
+    pub fn generate_arrow_ipc_file(num_rows: usize, batch_num_rows: usize) -> NamedTempFile {
+        let record_batch = generate_record_batch(num_rows);
+        let temp_file = tempfile::Builder::new()
+            .suffix(".arrow")
+            .tempfile()
+            .unwrap();
+        let mut writer =
+            IpcFileWriter::try_new(temp_file.reopen().unwrap(), &record_batch.schema()).unwrap();
+
+        for offset in (0..num_rows).step_by(batch_num_rows) {
+            let length = batch_num_rows.min(num_rows - offset);
+            writer.write(&record_batch.slice(offset, length)).unwrap();
+        }
+        writer.finish().unwrap();
+        temp_file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use serde_json::json;
+
+    use super::columnar_file_test_helpers::{
+        generate_arrow_ipc_file, generate_parquet_file, generate_record_batch,
+    };
+    use super::*;
+
+    async fn read_ids(uri: &Uri, format: ColumnarFileFormat, offset: usize) -> Vec<i64> {
+        let storage_resolver = StorageResolver::for_test();
+        let mut reader = ColumnarFileReader::from_uri(&storage_resolver, uri, format, offset)
+            .await
+            .unwrap();
+        let mut ids = Vec::new();
+        let mut next_offset = offset as u64;
+
+        while let Some(record) = reader.next_record().await.unwrap() {
+            let doc: JsonValue = serde_json::from_slice(&record.doc).unwrap();
+            ids.push(doc["id"].as_i64().unwrap());
+            next_offset += 1;
+            assert_eq!(record.next_offset, next_offset);
+            assert_eq!(record.is_last, ids.last() == Some(&9));
+        }
+        ids
+    }
+
+    #[test]
+    fn test_record_batch_to_json_docs() {
+        let record_batch = generate_record_batch(2);
+        let docs = record_batch_to_json_docs(&record_batch).unwrap();
+        assert_eq!(docs.len(), 2);
+
+        let doc: JsonValue = serde_json::from_slice(&docs[0]).unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "id": 0,
+                "timestamp": "2023-11-14T22:13:20Z",
+                "tags": ["tag-0"],
+                "resource": {"host": "host-0"},
+            })
+        );
+        let doc: JsonValue = serde_json::from_slice(&docs[1]).unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "id": 1,
+                "timestamp": "2023-11-14T22:13:20.001Z",
+                "tags": ["tag-1"],
+                "resource": {"host": null},
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_columnar_file_reader_parquet() {
+        let parquet_file = generate_parquet_file(10, 3);
+        let uri = Uri::from_str(parquet_file.path().to_str().unwrap()).unwrap();
+
+        let ids = read_ids(&uri, ColumnarFileFormat::Parquet, 0).await;
+        assert_eq!(ids, (0..10).collect::<Vec<i64>>());
+
+        // Resumes at the beginning of a row group.
+        let ids = read_ids(&uri, ColumnarFileFormat::Parquet, 6).await;
+        assert_eq!(ids, (6..10).collect::<Vec<i64>>());
+
+        // Resumes in the middle of a row group.
+        let ids = read_ids(&uri, ColumnarFileFormat::Parquet, 7).await;
+        assert_eq!(ids, (7..10).collect::<Vec<i64>>());
+
+        let ids = read_ids(&uri, ColumnarFileFormat::Parquet, 10).await;
+        assert!(ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_columnar_file_reader_arrow_ipc() {
+        let arrow_ipc_file = generate_arrow_ipc_file(10, 4);
+        let uri = Uri::from_str(arrow_ipc_file.path().to_str().unwrap()).unwrap();
+
+        let ids = read_ids(&uri, ColumnarFileFormat::ArrowIpc, 0).await;
+        assert_eq!(ids, (0..10).collect::<Vec<i64>>());
+
+        let ids = read_ids(&uri, ColumnarFileFormat::ArrowIpc, 5).await;
+        assert_eq!(ids, (5..10).collect::<Vec<i64>>());
+
+        let ids = read_ids(&uri, ColumnarFileFormat::ArrowIpc, 10).await;
+        assert!(ids.is_empty());
+    }
+}
@@ -0,0 +1,946 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The directory source tails the local files matching a glob, like a log shipper.
+//!
+//! Files are identified by their device, inode, and creation time rather than by their path, so
+//! that a file renamed by a log rotation remains the same partition. Files that are truncated in
+//! place (copytruncate) are read again from the beginning. Files that no longer match the glob,
+//! because they were renamed or deleted, are tailed until they stop growing and then closed.
+//!
+//! The files are local to each indexer, so the source runs one pipeline per indexer and its
+//! partition IDs are prefixed with the ID of the node. The partitions of the closed files are
+//! removed from the checkpoint once the files no longer match the glob.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::Metadata;
+use std::io::SeekFrom;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, UNIX_EPOCH};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use bytes::Bytes;
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use quickwit_actors::{ActorExitStatus, Mailbox};
+use quickwit_config::DirectorySourceParams;
+use quickwit_metastore::checkpoint::{PartitionId, SourceCheckpoint};
+use quickwit_proto::metastore::SourceType;
+use quickwit_proto::types::{NodeId, NodeIdRef, Position, SourceId};
+use serde_json::json;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::actors::DocProcessor;
+use crate::source::{
+    BATCH_NUM_BYTES_LIMIT, BatchBuilder, EMIT_BATCHES_TIMEOUT, Source, SourceContext,
+    SourceRuntime, TypedSourceFactory,
+};
+
+/// Interval at which the glob is evaluated again, in case file system notifications are missed
+/// or not supported (e.g. on network file systems).
+const RESCAN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Duration after which a file that no longer matches the glob is closed if it has not grown.
+const INACTIVE_FILE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Identifies a file independently of its path.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct FileId {
+    device: u64,
+    inode: u64,
+    // Distinguishes files that reuse the inode of a deleted file. Zero if the file system does
+    // not record creation times.
+    creation_time_nanos: u64,
+}
+
+impl FileId {
+    fn from_metadata(metadata: &Metadata) -> Self {
+        let creation_time_nanos = metadata
+            .created()
+            .ok()
+            .and_then(|creation_time| creation_time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+        Self {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+            creation_time_nanos,
+        }
+    }
+
+    fn partition_id(&self, node_id: &NodeIdRef) -> PartitionId {
+        PartitionId::from(format!(
+            "{node_id}:{}:{}:{}",
+            self.device, self.inode, self.creation_time_nanos
+        ))
+    }
+
+    /// Parses the ID of a partition created by the node `node_id`. Returns `None` for the
+    /// partitions of other nodes.
+    fn from_partition_id(node_id: &NodeIdRef, partition_id: &PartitionId) -> Option<Self> {
+        let file_id_str = partition_id
+            .as_str()
+            .strip_prefix(node_id.as_str())?
+            .strip_prefix(':')?;
+        let mut parts = file_id_str.split(':');
+        let file_id = Self {
+            device: parts.next()?.parse().ok()?,
+            inode: parts.next()?.parse().ok()?,
+            creation_time_nanos: parts.next()?.parse().ok()?,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(file_id)
+    }
+}
+
+/// Position in a tailed file. Positions must increase within a partition, so the number of
+/// times the file was truncated precedes the byte offset.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct TailPosition {
+    num_truncations: u64,
+    offset: u64,
+}
+
+impl TailPosition {
+    fn to_offset_str(self) -> String {
+        format!("{:0>20}:{:0>20}", self.num_truncations, self.offset)
+    }
+
+    fn to_position(self) -> Position {
+        Position::offset(self.to_offset_str().as_str())
+    }
+
+    fn to_eof_position(self) -> Position {
+        Position::eof(self.to_offset_str().as_str())
+    }
+
+    /// Parses a checkpointed position. Returns `None` if the file was read entirely.
+    fn from_position(position: &Position) -> anyhow::Result<Option<Self>> {
+        let offset = match position {
+            Position::Beginning => return Ok(Some(Self::default())),
+            Position::Offset(offset) => offset,
+            Position::Eof(_) => return Ok(None),
+        };
+        let (num_truncations_str, offset_str) = offset
+            .as_str()
+            .split_once(':')
+            .with_context(|| format!("invalid directory source position `{offset}`"))?;
+        let tail_position = Self {
+            num_truncations: num_truncations_str
+                .parse()
+                .with_context(|| format!("invalid directory source position `{offset}`"))?,
+            offset: offset_str
+                .parse()
+                .with_context(|| format!("invalid directory source position `{offset}`"))?,
+        };
+        Ok(Some(tail_position))
+    }
+}
+
+struct TailedFile {
+    // Last path at which the file was found.
+    path: PathBuf,
+    // The file remains open after it is renamed or deleted.
+    file: File,
+    // Position after the last complete line.
+    tail_position: TailPosition,
+    // Last position recorded in a checkpoint delta.
+    checkpoint_position: Position,
+    // Bytes read after the last complete line.
+    partial_line: Vec<u8>,
+    // Whether the file matched the glob the last time it was evaluated.
+    is_matched: bool,
+    last_growth_at: Instant,
+}
+
+impl TailedFile {
+    /// Reads the lines appended to the file, up to `max_num_bytes` bytes, and adds them to the
+    /// batch. Returns the number of lines read.
+    async fn read_lines(
+        &mut self,
+        partition_id: &PartitionId,
+        max_num_bytes: u64,
+        batch_builder: &mut BatchBuilder,
+    ) -> anyhow::Result<u64> {
+        let file_len = self.file.metadata().await?.len();
+        let mut read_offset = self.tail_position.offset + self.partial_line.len() as u64;
+
+        if file_len < read_offset {
+            info!(path=%self.path.display(), "file was truncated, reading it from the beginning");
+            self.tail_position = TailPosition {
+                num_truncations: self.tail_position.num_truncations + 1,
+                offset: 0,
+            };
+            self.partial_line.clear();
+            read_offset = 0;
+        }
+        if file_len == read_offset {
+            return Ok(0);
+        }
+        self.last_growth_at = Instant::now();
+        self.file.seek(SeekFrom::Start(read_offset)).await?;
+        (&mut self.file)
+            .take(max_num_bytes)
+            .read_to_end(&mut self.partial_line)
+            .await?;
+
+        let Some(last_newline_pos) = self.partial_line.iter().rposition(|byte| *byte == b'\n')
+        else {
+            return Ok(0);
+        };
+        let remaining = self.partial_line.split_off(last_newline_pos + 1);
+        let lines = std::mem::replace(&mut self.partial_line, remaining);
+        let num_lines = add_lines(&lines, batch_builder);
+        self.tail_position.offset += lines.len() as u64;
+        self.record_delta(
+            partition_id,
+            self.tail_position.to_position(),
+            batch_builder,
+        )?;
+        Ok(num_lines)
+    }
+
+    /// Emits the partial last line of the file, if any, and marks the file as read entirely.
+    /// Returns the number of lines read and the final position of the file.
+    fn close(
+        mut self,
+        partition_id: &PartitionId,
+        batch_builder: &mut BatchBuilder,
+    ) -> anyhow::Result<(u64, Position)> {
+        let num_lines = add_lines(&self.partial_line, batch_builder);
+        self.tail_position.offset += self.partial_line.len() as u64;
+        self.record_delta(
+            partition_id,
+            self.tail_position.to_eof_position(),
+            batch_builder,
+        )?;
+        Ok((num_lines, self.checkpoint_position))
+    }
+
+    fn record_delta(
+        &mut self,
+        partition_id: &PartitionId,
+        to_position: Position,
+        batch_builder: &mut BatchBuilder,
+    ) -> anyhow::Result<()> {
+        if to_position == self.checkpoint_position {
+            return Ok(());
+        }
+        let from_position = std::mem::replace(&mut self.checkpoint_position, to_position.clone());
+        batch_builder.checkpoint_delta.record_partition_delta(
+            partition_id.clone(),
+            from_position,
+            to_position,
+        )?;
+        Ok(())
+    }
+}
+
+/// Adds the non-blank lines of `lines` to the batch, without their line terminators. Returns
+/// the number of lines added.
+fn add_lines(lines: &[u8], batch_builder: &mut BatchBuilder) -> u64 {
+    let mut num_lines = 0;
+
+    for line in lines.split(|byte| *byte == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        batch_builder.add_doc(Bytes::copy_from_slice(line));
+        num_lines += 1;
+    }
+    num_lines
+}
+
+/// Returns the deepest directory of the glob that contains no pattern, and whether the pattern
+/// spans several directory levels.
+fn watched_dir(glob: &str) -> (PathBuf, RecursiveMode) {
+    let mut watched_dir = PathBuf::new();
+    let mut num_pattern_components = 0;
+
+    for component in Path::new(glob).components() {
+        let is_pattern = matches!(component, Component::Normal(name)
+            if name.to_string_lossy().contains(['*', '?', '[']));
+        if num_pattern_components == 0 && !is_pattern {
+            watched_dir.push(component);
+        } else {
+            num_pattern_components += 1;
+        }
+    }
+    // The glob is a plain file path.
+    if num_pattern_components == 0 {
+        watched_dir.pop();
+        num_pattern_components = 1;
+    }
+    let recursive_mode = if num_pattern_components > 1 {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    (watched_dir, recursive_mode)
+}
+
+#[derive(Default)]
+struct FileEvents {
+    notify: Notify,
+    rescan_requested: AtomicBool,
+}
+
+fn watch_files(glob: &str, file_events: Arc<FileEvents>) -> notify::Result<RecommendedWatcher> {
+    let (watched_dir, recursive_mode) = watched_dir(glob);
+    let mut watcher = notify::recommended_watcher(move |event_res: notify::Result<Event>| {
+        let Ok(event) = event_res else {
+            return;
+        };
+        if matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+        ) {
+            file_events.rescan_requested.store(true, Ordering::Relaxed);
+        }
+        file_events.notify.notify_one();
+    })?;
+    watcher.watch(&watched_dir, recursive_mode)?;
+    Ok(watcher)
+}
+
+/// Returns the paths and IDs of the regular files matching the glob.
+fn list_matching_files(glob: &str) -> anyhow::Result<Vec<(PathBuf, FileId)>> {
+    let mut matching_files = Vec::new();
+
+    for path_res in glob::glob(glob)? {
+        let Ok(path) = path_res else {
+            continue;
+        };
+        // Symbolic links are followed.
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.is_file() {
+            matching_files.push((path, FileId::from_metadata(&metadata)));
+        }
+    }
+    Ok(matching_files)
+}
+
+pub struct DirectorySource {
+    source_id: SourceId,
+    node_id: NodeId,
+    glob: String,
+    checkpoint: SourceCheckpoint,
+    tailed_files: BTreeMap<FileId, TailedFile>,
+    // Files read entirely, which are ignored if they match the glob again, along with their final
+    // position.
+    closed_files: HashMap<FileId, Position>,
+    // Partitions of the closed files that no longer match the glob, to remove from the checkpoint.
+    pruned_partitions: Vec<(PartitionId, Position)>,
+    file_events: Arc<FileEvents>,
+    _watcher_opt: Option<RecommendedWatcher>,
+    last_rescan_at: Option<Instant>,
+    inactive_file_timeout: Duration,
+    num_bytes_processed: u64,
+    num_lines_processed: u64,
+}
+
+impl fmt::Debug for DirectorySource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DirectorySource {{ source_id: {} }}", self.source_id)
+    }
+}
+
+impl DirectorySource {
+    async fn rescan(&mut self) -> anyhow::Result<()> {
+        self.last_rescan_at = Some(Instant::now());
+        self.file_events
+            .rescan_requested
+            .store(false, Ordering::Relaxed);
+
+        let glob = self.glob.clone();
+        let matching_files = tokio::task::spawn_blocking(move || list_matching_files(&glob))
+            .await
+            .context("failed to list the files matching the glob")??;
+
+        for tailed_file in self.tailed_files.values_mut() {
+            tailed_file.is_matched = false;
+        }
+        // Forgets the closed files that no longer match the glob, which were usually deleted by
+        // the log rotation, so that the checkpoint does not grow forever.
+        let matching_file_ids: HashSet<FileId> =
+            matching_files.iter().map(|(_, file_id)| *file_id).collect();
+        self.closed_files.retain(|file_id, position| {
+            if matching_file_ids.contains(file_id) {
+                return true;
+            }
+            let partition_id = file_id.partition_id(&self.node_id);
+            self.pruned_partitions
+                .push((partition_id, position.clone()));
+            false
+        });
+        for (path, file_id) in matching_files {
+            if let Some(tailed_file) = self.tailed_files.get_mut(&file_id) {
+                tailed_file.path = path;
+                tailed_file.is_matched = true;
+                continue;
+            }
+            if self.closed_files.contains_key(&file_id) {
+                continue;
+            }
+            let Ok(file) = File::open(&path).await else {
+                continue;
+            };
+            // The path may have been replaced by another file in the meantime.
+            if FileId::from_metadata(&file.metadata().await?) != file_id {
+                continue;
+            }
+            let checkpoint_position = self
+                .checkpoint
+                .position_for_partition(&file_id.partition_id(&self.node_id))
+                .cloned()
+                .unwrap_or_default();
+            let Some(tail_position) = TailPosition::from_position(&checkpoint_position)? else {
+                self.closed_files.insert(file_id, checkpoint_position);
+                continue;
+            };
+            info!(path=%path.display(), offset=tail_position.offset, "tailing file");
+            let tailed_file = TailedFile {
+                path,
+                file,
+                tail_position,
+                checkpoint_position,
+                partial_line: Vec::new(),
+                is_matched: true,
+                last_growth_at: Instant::now(),
+            };
+            self.tailed_files.insert(file_id, tailed_file);
+        }
+        Ok(())
+    }
+
+    fn is_rescan_due(&self) -> bool {
+        self.file_events.rescan_requested.load(Ordering::Relaxed)
+            || self
+                .last_rescan_at
+                .is_none_or(|last_rescan_at| last_rescan_at.elapsed() >= RESCAN_INTERVAL)
+    }
+
+    async fn read_batch(&mut self, batch_builder: &mut BatchBuilder) -> anyhow::Result<()> {
+        for (partition_id, position) in self.pruned_partitions.drain(..) {
+            batch_builder
+                .checkpoint_delta
+                .record_partition_removal(partition_id, position)?;
+        }
+        let mut closable_file_ids = Vec::new();
+
+        for (file_id, tailed_file) in self.tailed_files.iter_mut() {
+            if batch_builder.num_bytes >= BATCH_NUM_BYTES_LIMIT {
+                return Ok(());
+            }
+            let max_num_bytes = BATCH_NUM_BYTES_LIMIT - batch_builder.num_bytes;
+            let num_bytes_before = batch_builder.num_bytes;
+            let num_lines = tailed_file
+                .read_lines(
+                    &file_id.partition_id(&self.node_id),
+                    max_num_bytes,
+                    batch_builder,
+                )
+                .await?;
+            self.num_bytes_processed += batch_builder.num_bytes - num_bytes_before;
+            self.num_lines_processed += num_lines;
+
+            if !tailed_file.is_matched
+                && tailed_file.last_growth_at.elapsed() >= self.inactive_file_timeout
+            {
+                closable_file_ids.push(*file_id);
+            }
+        }
+        for file_id in closable_file_ids {
+            let tailed_file = self
+                .tailed_files
+                .remove(&file_id)
+                .expect("file should be tailed");
+            info!(path=%tailed_file.path.display(), "closing file that no longer matches the glob");
+            let num_bytes_before = batch_builder.num_bytes;
+            let (num_lines, eof_position) =
+                tailed_file.close(&file_id.partition_id(&self.node_id), batch_builder)?;
+            self.num_bytes_processed += batch_builder.num_bytes - num_bytes_before;
+            self.num_lines_processed += num_lines;
+            self.closed_files.insert(file_id, eof_position);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for DirectorySource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        if self.is_rescan_due() {
+            ctx.protect_future(self.rescan()).await?;
+        }
+        let mut batch_builder = BatchBuilder::new(SourceType::Directory);
+        ctx.protect_future(self.read_batch(&mut batch_builder))
+            .await?;
+
+        if batch_builder.checkpoint_delta.is_empty() {
+            // Wait for the files to change, or for the next rescan.
+            let _ = tokio::time::timeout(*EMIT_BATCHES_TIMEOUT, self.file_events.notify.notified())
+                .await;
+            return Ok(Duration::ZERO);
+        }
+        doc_processor_mailbox
+            .send_message(batch_builder.build())
+            .await?;
+        Ok(Duration::ZERO)
+    }
+
+    fn name(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        json!({
+            "num_bytes_processed": self.num_bytes_processed,
+            "num_lines_processed": self.num_lines_processed,
+            "num_tailed_files": self.tailed_files.len(),
+        })
+    }
+}
+
+pub struct DirectorySourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for DirectorySourceFactory {
+    type Source = DirectorySource;
+    type Params = DirectorySourceParams;
+
+    async fn typed_create_source(
+        source_runtime: SourceRuntime,
+        params: DirectorySourceParams,
+    ) -> anyhow::Result<DirectorySource> {
+        let checkpoint = source_runtime.fetch_checkpoint().await?;
+        let node_id = source_runtime.node_id().to_owned();
+
+        // The closed files that no longer match the glob are pruned on the first rescan.
+        let closed_files = checkpoint
+            .iter()
+            .filter(|(_, position)| position.is_eof())
+            .filter_map(|(partition_id, position)| {
+                let file_id = FileId::from_partition_id(&node_id, &partition_id)?;
+                Some((file_id, position))
+            })
+            .collect();
+        let file_events = Arc::new(FileEvents::default());
+        let watcher_opt = match watch_files(&params.glob, file_events.clone()) {
+            Ok(watcher) => Some(watcher),
+            Err(error) => {
+                warn!(
+                    glob=%params.glob,
+                    %error,
+                    "failed to watch the files matching the glob, falling back to polling"
+                );
+                None
+            }
+        };
+        Ok(DirectorySource {
+            source_id: source_runtime.source_id().to_string(),
+            node_id,
+            glob: params.glob,
+            checkpoint,
+            tailed_files: BTreeMap::new(),
+            closed_files,
+            pruned_partitions: Vec::new(),
+            file_events,
+            _watcher_opt: watcher_opt,
+            last_rescan_at: None,
+            inactive_file_timeout: INACTIVE_FILE_TIMEOUT,
+            num_bytes_processed: 0,
+            num_lines_processed: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::num::NonZeroUsize;
+
+    use quickwit_actors::{ActorContext, Universe};
+    use quickwit_config::{SourceConfig, SourceInputFormat, SourceParams};
+    use quickwit_metastore::checkpoint::SourceCheckpointDelta;
+    use quickwit_proto::types::IndexUid;
+    use tokio::sync::watch;
+
+    use super::*;
+    use crate::models::RawDocBatch;
+    use crate::source::SourceActor;
+    use crate::source::tests::SourceRuntimeBuilder;
+
+    fn append_to_file(path: &Path, content: &str) {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    fn file_partition_id(path: &Path) -> PartitionId {
+        let metadata = std::fs::metadata(path).unwrap();
+        FileId::from_metadata(&metadata).partition_id(NodeIdRef::from_str("test-node"))
+    }
+
+    async fn create_directory_source(
+        dir_path: &Path,
+        checkpoint_delta_opt: Option<SourceCheckpointDelta>,
+    ) -> DirectorySource {
+        let params = DirectorySourceParams {
+            glob: format!("{}/*.log", dir_path.display()),
+        };
+        let source_config = SourceConfig {
+            source_id: "test-directory-source".to_string(),
+            num_pipelines: NonZeroUsize::MIN,
+            enabled: true,
+            source_params: SourceParams::Directory(params.clone()),
+            transform_config: None,
+            input_format: SourceInputFormat::PlainText,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
+        };
+        let index_uid = IndexUid::new_with_random_ulid("test-index");
+        let source_runtime = SourceRuntimeBuilder::new(index_uid, source_config)
+            .with_mock_metastore(checkpoint_delta_opt)
+            .build();
+        DirectorySourceFactory::typed_create_source(source_runtime, params)
+            .await
+            .unwrap()
+    }
+
+    fn batch_docs(batch: &RawDocBatch) -> Vec<&str> {
+        batch
+            .docs
+            .iter()
+            .map(|doc| std::str::from_utf8(doc).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_tail_position_round_trip() {
+        let tail_position = TailPosition {
+            num_truncations: 2,
+            offset: 42,
+        };
+        assert_eq!(
+            TailPosition::from_position(&tail_position.to_position()).unwrap(),
+            Some(tail_position)
+        );
+        assert_eq!(
+            TailPosition::from_position(&Position::Beginning).unwrap(),
+            Some(TailPosition::default())
+        );
+        assert_eq!(
+            TailPosition::from_position(&tail_position.to_eof_position()).unwrap(),
+            None
+        );
+        // Truncations sort after the offsets preceding them.
+        let truncated_position = TailPosition {
+            num_truncations: 3,
+            offset: 0,
+        };
+        assert!(tail_position.to_position() < truncated_position.to_position());
+        TailPosition::from_position(&Position::offset(42u64)).unwrap_err();
+    }
+
+    #[test]
+    fn test_file_id_partition_id_round_trip() {
+        let node_id = NodeIdRef::from_str("my-node");
+        let file_id = FileId {
+            device: 1,
+            inode: 2,
+            creation_time_nanos: 3,
+        };
+        let partition_id = file_id.partition_id(node_id);
+        assert_eq!(partition_id.as_str(), "my-node:1:2:3");
+        assert_eq!(
+            FileId::from_partition_id(node_id, &partition_id),
+            Some(file_id)
+        );
+        assert_eq!(
+            FileId::from_partition_id(NodeIdRef::from_str("my-node-2"), &partition_id),
+            None
+        );
+        assert_eq!(
+            FileId::from_partition_id(NodeIdRef::from_str("my"), &partition_id),
+            None
+        );
+        assert_eq!(
+            FileId::from_partition_id(node_id, &PartitionId::from("1:2:3")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_watched_dir() {
+        let (dir, mode) = watched_dir("/var/log/app/*.log");
+        assert_eq!(dir, Path::new("/var/log/app"));
+        assert_eq!(mode, RecursiveMode::NonRecursive);
+
+        let (dir, mode) = watched_dir("/var/log/*/app.log");
+        assert_eq!(dir, Path::new("/var/log"));
+        assert_eq!(mode, RecursiveMode::Recursive);
+
+        let (dir, mode) = watched_dir("/var/log/app.log");
+        assert_eq!(dir, Path::new("/var/log"));
+        assert_eq!(mode, RecursiveMode::NonRecursive);
+    }
+
+    #[tokio::test]
+    async fn test_directory_source_tails_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_path = temp_dir.path().join("app.log");
+        append_to_file(&log_path, "line 1\nline 2\r\n\npartial");
+        // Files that do not match the glob are ignored.
+        append_to_file(&temp_dir.path().join("app.txt"), "ignored\n");
+
+        let mut directory_source = create_directory_source(temp_dir.path(), None).await;
+        directory_source.inactive_file_timeout = Duration::ZERO;
+
+        let universe = Universe::with_accelerated_time();
+        let (source_mailbox, _source_inbox) = universe.create_test_mailbox();
+        let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(json!({}));
+        let ctx: ActorContext<SourceActor> =
+            ActorContext::for_test(&universe, source_mailbox, observable_state_tx);
+
+        directory_source
+            .emit_batches(&doc_processor_mailbox, &ctx)
+            .await
+            .unwrap();
+        let batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batch_docs(&batches[0]), ["line 1", "line 2"]);
+
+        let partition_id = file_partition_id(&log_path);
+        let position_after_lines = TailPosition {
+            num_truncations: 0,
+            offset: 16,
+        };
+        let expected_checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+            partition_id.clone(),
+            Position::Beginning,
+            position_after_lines.to_position(),
+        )
+        .unwrap();
+        assert_eq!(batches[0].checkpoint_delta, expected_checkpoint_delta);
+
+        // The partial line is emitted once it is complete.
+        append_to_file(&log_path, " line\n");
+        directory_source
+            .emit_batches(&doc_processor_mailbox, &ctx)
+            .await
+            .unwrap();
+        let batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batch_docs(&batches[0]), ["partial line"]);
+
+        // Copytruncate: the file is read again from the beginning.
+        std::fs::File::create(&log_path).unwrap();
+        append_to_file(&log_path, "after truncation\n");
+        directory_source
+            .emit_batches(&doc_processor_mailbox, &ctx)
+            .await
+            .unwrap();
+        let batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batch_docs(&batches[0]), ["after truncation"]);
+
+        let position_after_truncation = TailPosition {
+            num_truncations: 1,
+            offset: 17,
+        };
+        let expected_checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+            partition_id.clone(),
+            TailPosition {
+                num_truncations: 0,
+                offset: 29,
+            }
+            .to_position(),
+            position_after_truncation.to_position(),
+        )
+        .unwrap();
+        assert_eq!(batches[0].checkpoint_delta, expected_checkpoint_delta);
+
+        // Rename and create: the rotated file is drained and closed, and the new file is tailed.
+        let rotated_log_path = temp_dir.path().join("app.log.1");
+        append_to_file(&log_path, "before rotation");
+        std::fs::rename(&log_path, &rotated_log_path).unwrap();
+        append_to_file(&log_path, "after rotation\n");
+
+        directory_source.rescan().await.unwrap();
+        directory_source
+            .emit_batches(&doc_processor_mailbox, &ctx)
+            .await
+            .unwrap();
+        let batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        assert_eq!(batches.len(), 1);
+
+        let mut docs = batch_docs(&batches[0]);
+        docs.sort();
+        assert_eq!(docs, ["after rotation", "before rotation"]);
+
+        let checkpoint_delta = &batches[0].checkpoint_delta;
+        let rotated_partition_delta = checkpoint_delta
+            .iter()
+            .find(|(delta_partition_id, _)| *delta_partition_id == partition_id)
+            .unwrap()
+            .1;
+        assert_eq!(
+            rotated_partition_delta.to,
+            TailPosition {
+                num_truncations: 1,
+                offset: 32,
+            }
+            .to_eof_position()
+        );
+        assert_eq!(directory_source.tailed_files.len(), 1);
+        assert_eq!(directory_source.closed_files.len(), 1);
+
+        // The partition of the rotated file, which no longer matches the glob, is removed from the
+        // checkpoint on the next rescan.
+        directory_source.rescan().await.unwrap();
+        directory_source
+            .emit_batches(&doc_processor_mailbox, &ctx)
+            .await
+            .unwrap();
+        let batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        assert_eq!(batches.len(), 1);
+        assert!(batches[0].docs.is_empty());
+
+        let mut expected_checkpoint_delta = SourceCheckpointDelta::default();
+        expected_checkpoint_delta
+            .record_partition_removal(
+                partition_id.clone(),
+                TailPosition {
+                    num_truncations: 1,
+                    offset: 32,
+                }
+                .to_eof_position(),
+            )
+            .unwrap();
+        assert_eq!(batches[0].checkpoint_delta, expected_checkpoint_delta);
+        assert!(directory_source.closed_files.is_empty());
+
+        assert_eq!(
+            directory_source.observable_state(),
+            json!({
+                "num_bytes_processed": 69,
+                "num_lines_processed": 6,
+                "num_tailed_files": 1,
+            })
+        );
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_directory_source_resumes_from_checkpoint() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_path = temp_dir.path().join("app.log");
+        append_to_file(&log_path, "line 1\nline 2\n");
+        let closed_log_path = temp_dir.path().join("closed.log");
+        append_to_file(&closed_log_path, "already indexed\n");
+
+        let mut checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+            file_partition_id(&log_path),
+            Position::Beginning,
+            TailPosition {
+                num_truncations: 0,
+                offset: 7,
+            }
+            .to_position(),
+        )
+        .unwrap();
+        checkpoint_delta
+            .record_partition_delta(
+                file_partition_id(&closed_log_path),
+                Position::Beginning,
+                TailPosition {
+                    num_truncations: 0,
+                    offset: 16,
+                }
+                .to_eof_position(),
+            )
+            .unwrap();
+        // Partitions of deleted files and of other nodes.
+        let deleted_partition_id = PartitionId::from("test-node:1:2:3");
+        checkpoint_delta
+            .record_partition_delta(
+                deleted_partition_id.clone(),
+                Position::Beginning,
+                TailPosition::default().to_eof_position(),
+            )
+            .unwrap();
+        checkpoint_delta
+            .record_partition_delta(
+                PartitionId::from("other-node:1:2:3"),
+                Position::Beginning,
+                TailPosition::default().to_eof_position(),
+            )
+            .unwrap();
+        let mut directory_source =
+            create_directory_source(temp_dir.path(), Some(checkpoint_delta)).await;
+        assert_eq!(directory_source.closed_files.len(), 2);
+
+        let universe = Universe::with_accelerated_time();
+        let (source_mailbox, _source_inbox) = universe.create_test_mailbox();
+        let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(json!({}));
+        let ctx: ActorContext<SourceActor> =
+            ActorContext::for_test(&universe, source_mailbox, observable_state_tx);
+
+        directory_source
+            .emit_batches(&doc_processor_mailbox, &ctx)
+            .await
+            .unwrap();
+        let batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batch_docs(&batches[0]), ["line 2"]);
+        assert_eq!(directory_source.tailed_files.len(), 1);
+
+        let removed_partition_ids: Vec<PartitionId> = batches[0]
+            .checkpoint_delta
+            .iter()
+            .filter(|(_, partition_delta)| partition_delta.remove)
+            .map(|(partition_id, _)| partition_id)
+            .collect();
+        assert_eq!(removed_partition_ids, [deleted_partition_id]);
+        assert_eq!(directory_source.closed_files.len(), 1);
+        universe.assert_quit().await;
+    }
+}
@@ -16,9 +16,9 @@ use std::io;
 use std::path::Path;
 
 use anyhow::Context;
-use async_compression::tokio::bufread::GzipDecoder;
 use bytes::Bytes;
 use quickwit_common::Progress;
+use quickwit_common::compression::{CompressionCodec, MAGIC_BYTES_LEN};
 use quickwit_common::uri::Uri;
 use quickwit_metastore::checkpoint::PartitionId;
 use quickwit_proto::metastore::SourceType;
@@ -26,6 +26,8 @@ use quickwit_proto::types::Position;
 use quickwit_storage::StorageResolver;
 use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
 
+#[cfg(feature = "columnar-files")]
+use super::columnar_file_reader::ColumnarFileReader;
 use super::{BATCH_NUM_BYTES_LIMIT, BatchBuilder};
 
 pub struct FileRecord {
@@ -35,7 +37,7 @@ pub struct FileRecord {
 }
 
 /// A helper wrapper that lets you skip bytes in compressed files where you
-/// cannot seek (e.g. gzip files).
+/// cannot seek.
 struct SkipReader {
     reader: BufReader<Box<dyn AsyncRead + Send + Unpin>>,
     num_bytes_to_skip: usize,
@@ -102,12 +104,24 @@ impl DocFileReader {
         if file_size == 0 {
             return Ok(DocFileReader::empty());
         }
-        // If it's a gzip file, we can't seek to a specific offset. `SkipReader`
-        // starts from the beginning of the file, decompresses and skips the
-        // first `offset` bytes.
-        let reader = if uri.extension() == Some("gz") {
+        // The codec is detected from the file extension, or from the magic bytes
+        // of files without a known compression extension.
+        let codec_opt = match uri.extension().and_then(CompressionCodec::from_extension) {
+            Some(codec) => Some(codec),
+            None => {
+                let magic_bytes = storage
+                    .get_slice(file_name, 0..MAGIC_BYTES_LEN.min(file_size))
+                    .await?;
+                CompressionCodec::from_magic_bytes(&magic_bytes)
+            }
+        };
+        // If the file is compressed, we can't seek to a specific offset.
+        // `SkipReader` starts from the beginning of the file, decompresses and
+        // skips the first `offset` bytes, so offsets are always offsets in the
+        // decompressed data.
+        let reader = if let Some(codec) = codec_opt {
             let stream = storage.get_slice_stream(file_name, 0..file_size).await?;
-            let decompressed_stream = Box::new(GzipDecoder::new(BufReader::new(stream)));
+            let decompressed_stream = codec.async_decoder(BufReader::new(stream));
             DocFileReader {
                 reader: SkipReader::new(decompressed_stream, offset),
                 next_offset: offset as u64,
@@ -143,19 +157,85 @@ impl DocFileReader {
     }
 }
 
+/// Columnar file formats, whose rows are read as JSON documents.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColumnarFileFormat {
+    ArrowIpc,
+    Parquet,
+}
+
+impl ColumnarFileFormat {
+    fn from_uri(uri: &Uri) -> Option<Self> {
+        match uri.extension() {
+            Some("arrow") => Some(Self::ArrowIpc),
+            Some("parquet") => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the records of a file: the lines of a text file, or the rows of a columnar file. The
+/// offsets of the records are respectively byte and row offsets.
+enum RecordReader {
+    Lines(DocFileReader),
+    #[cfg(feature = "columnar-files")]
+    Rows(ColumnarFileReader),
+}
+
+impl RecordReader {
+    async fn from_uri(
+        storage_resolver: &StorageResolver,
+        uri: &Uri,
+        offset: usize,
+    ) -> anyhow::Result<Self> {
+        let Some(columnar_file_format) = ColumnarFileFormat::from_uri(uri) else {
+            let reader = DocFileReader::from_uri(storage_resolver, uri, offset).await?;
+            return Ok(RecordReader::Lines(reader));
+        };
+        #[cfg(feature = "columnar-files")]
+        {
+            let reader =
+                ColumnarFileReader::from_uri(storage_resolver, uri, columnar_file_format, offset)
+                    .await?;
+            Ok(RecordReader::Rows(reader))
+        }
+        #[cfg(not(feature = "columnar-files"))]
+        {
+            anyhow::bail!(
+                "failed to read `{uri}`: {columnar_file_format:?} files require Quickwit to be \
+                 compiled with the `columnar-files` feature"
+            )
+        }
+    }
+
+    async fn next_record(&mut self) -> anyhow::Result<Option<FileRecord>> {
+        match self {
+            RecordReader::Lines(reader) => reader.next_record().await,
+            #[cfg(feature = "columnar-files")]
+            RecordReader::Rows(reader) => reader.next_record().await,
+        }
+    }
+}
+
 pub struct ObjectUriBatchReader {
     partition_id: PartitionId,
-    reader: DocFileReader,
+    reader: RecordReader,
     current_offset: usize,
     is_eof: bool,
+    // Header row emitted before the first batch when the file is resumed past its first line.
+    header_opt: Option<Bytes>,
 }
 
 impl ObjectUriBatchReader {
+    /// Creates a reader starting at `position`. With `has_header`, the first line of the file is
+    /// a header row: it is re-emitted at the beginning of the first batch when resuming past it,
+    /// so that the doc processor always sees the header row first.
     pub async fn try_new(
         storage_resolver: &StorageResolver,
         partition_id: PartitionId,
         uri: &Uri,
         position: Position,
+        has_header: bool,
     ) -> anyhow::Result<Self> {
         let current_offset = match position {
             Position::Beginning => 0,
@@ -165,18 +245,26 @@ impl ObjectUriBatchReader {
             Position::Eof(_) => {
                 return Ok(ObjectUriBatchReader {
                     partition_id,
-                    reader: DocFileReader::empty(),
+                    reader: RecordReader::Lines(DocFileReader::empty()),
                     current_offset: 0,
                     is_eof: true,
+                    header_opt: None,
                 });
             }
         };
-        let reader = DocFileReader::from_uri(storage_resolver, uri, current_offset).await?;
+        let header_opt = if has_header && current_offset > 0 {
+            let mut header_reader = DocFileReader::from_uri(storage_resolver, uri, 0).await?;
+            header_reader.next_record().await?.map(|record| record.doc)
+        } else {
+            None
+        };
+        let reader = RecordReader::from_uri(storage_resolver, uri, current_offset).await?;
         Ok(ObjectUriBatchReader {
             partition_id,
             reader,
             current_offset,
             is_eof: false,
+            header_opt,
         })
     }
 
@@ -189,9 +277,22 @@ impl ObjectUriBatchReader {
         if self.is_eof {
             return Ok(batch_builder);
         }
+        if let Some(header) = self.header_opt.take() {
+            batch_builder.add_doc(header);
+        }
         let limit_num_bytes = self.current_offset + BATCH_NUM_BYTES_LIMIT as usize;
         let mut new_offset = self.current_offset;
-        while new_offset < limit_num_bytes {
+        loop {
+            let is_batch_full = match &self.reader {
+                RecordReader::Lines(_) => new_offset >= limit_num_bytes,
+                // Row offsets are row numbers, so batches of rows are bounded by the size of
+                // their documents instead.
+                #[cfg(feature = "columnar-files")]
+                RecordReader::Rows(_) => batch_builder.num_bytes >= BATCH_NUM_BYTES_LIMIT,
+            };
+            if is_batch_full {
+                break;
+            }
             if let Some(record) = source_progress
                 .protect_future(self.reader.next_record())
                 .await?
@@ -304,9 +405,10 @@ pub mod file_test_helpers {
 
 #[cfg(test)]
 mod tests {
-    use std::io::Cursor;
+    use std::io::{Cursor, Write};
     use std::str::FromStr;
 
+    use async_compression::tokio::write::ZstdEncoder;
     use file_test_helpers::generate_index_doc_file;
     use quickwit_metastore::checkpoint::SourceCheckpointDelta;
 
@@ -441,6 +543,32 @@ mod tests {
         aux_test_resumed_read_record(dummy_doc_file_uri, 1000, 1000).await;
     }
 
+    #[tokio::test]
+    async fn test_resumed_read_record_zstd_without_extension() {
+        let mut documents_bytes = Vec::new();
+        for i in 0..1000 {
+            documents_bytes
+                .write_all(format!("{i:0>7}\n").as_bytes())
+                .unwrap();
+        }
+        let mut zstd_documents = Vec::new();
+        let mut encoder = ZstdEncoder::new(&mut zstd_documents);
+        tokio::io::AsyncWriteExt::write_all(&mut encoder, &documents_bytes)
+            .await
+            .unwrap();
+        tokio::io::AsyncWriteExt::shutdown(&mut encoder)
+            .await
+            .unwrap();
+
+        let mut dummy_doc_file = tempfile::NamedTempFile::new().unwrap();
+        dummy_doc_file.write_all(&zstd_documents).unwrap();
+        dummy_doc_file.flush().unwrap();
+        let dummy_doc_file_uri = dummy_doc_file.path().to_str().unwrap();
+        aux_test_resumed_read_record(dummy_doc_file_uri, 1000, 1).await;
+        aux_test_resumed_read_record(dummy_doc_file_uri, 1000, 40).await;
+        aux_test_resumed_read_record(dummy_doc_file_uri, 1000, 1000).await;
+    }
+
     #[tokio::test]
     async fn test_resumed_read_record_gz() {
         let dummy_doc_file = generate_index_doc_file(true, 1000).await;
@@ -463,7 +591,7 @@ mod tests {
         let uri = Uri::from_str(file.as_ref()).unwrap();
         let partition = PartitionId::from("test");
         let mut batch_reader =
-            ObjectUriBatchReader::try_new(&storage_resolver, partition.clone(), &uri, from)
+            ObjectUriBatchReader::try_new(&storage_resolver, partition.clone(), &uri, from, false)
                 .await
                 .unwrap();
 
@@ -556,4 +684,75 @@ mod tests {
         )
         .await;
     }
+
+    #[tokio::test]
+    async fn test_resume_read_batch_with_header() {
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        temp_file.write_all(b"a,b\n1,2\n3,4\n").unwrap();
+        temp_file.flush().unwrap();
+        let uri = Uri::from_str(temp_file.path().to_str().unwrap()).unwrap();
+        let storage_resolver = StorageResolver::for_test();
+        let progress = Progress::default();
+        let partition = PartitionId::from("test");
+
+        for (from, expected_docs) in [
+            (Position::Beginning, vec!["a,b\n", "1,2\n", "3,4\n"]),
+            (Position::offset(8u64), vec!["a,b\n", "3,4\n"]),
+        ] {
+            let mut batch_reader = ObjectUriBatchReader::try_new(
+                &storage_resolver,
+                partition.clone(),
+                &uri,
+                from,
+                true,
+            )
+            .await
+            .unwrap();
+            let batch = batch_reader
+                .read_batch(&progress, SourceType::Unspecified)
+                .await
+                .unwrap();
+            assert_eq!(batch.docs, expected_docs);
+            assert!(batch_reader.is_eof());
+        }
+    }
+
+    #[cfg(feature = "columnar-files")]
+    #[tokio::test]
+    async fn test_resume_read_batch_parquet() {
+        use crate::source::columnar_file_reader::columnar_file_test_helpers::generate_parquet_file;
+
+        let parquet_file = generate_parquet_file(10, 4);
+        let uri = Uri::from_str(parquet_file.path().to_str().unwrap()).unwrap();
+        let storage_resolver = StorageResolver::for_test();
+        let progress = Progress::default();
+        let partition = PartitionId::from("test");
+
+        let mut batch_reader = ObjectUriBatchReader::try_new(
+            &storage_resolver,
+            partition.clone(),
+            &uri,
+            Position::offset(5u64),
+            false,
+        )
+        .await
+        .unwrap();
+        let batch = batch_reader
+            .read_batch(&progress, SourceType::Unspecified)
+            .await
+            .unwrap();
+        assert_eq!(batch.docs.len(), 5);
+        assert!(batch_reader.is_eof());
+
+        let first_doc: serde_json::Value = serde_json::from_slice(&batch.docs[0]).unwrap();
+        assert_eq!(first_doc["id"], 5);
+
+        let expected_checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+            partition,
+            Position::offset(5u64),
+            Position::eof(10u64),
+        )
+        .unwrap();
+        assert_eq!(batch.checkpoint_delta, expected_checkpoint_delta);
+    }
 }
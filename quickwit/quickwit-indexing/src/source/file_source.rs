@@ -166,6 +166,7 @@ impl TypedSourceFactory for FileSourceFactory {
                     partition_id,
                     &file_uri,
                     position,
+                    source_runtime.source_config.has_csv_header(),
                 )
                 .await?;
                 FileSourceState::Filepath {
@@ -237,6 +238,10 @@ mod tests {
             source_params: SourceParams::File(params.clone()),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let index_uid = IndexUid::new_with_random_ulid("test-index");
         let source_runtime = SourceRuntimeBuilder::new(index_uid, source_config).build();
@@ -289,6 +294,10 @@ mod tests {
             source_params: SourceParams::File(params.clone()),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let index_uid = IndexUid::new_with_random_ulid("test-index");
         let source_runtime = SourceRuntimeBuilder::new(index_uid, source_config).build();
@@ -353,6 +362,10 @@ mod tests {
             source_params: SourceParams::File(params.clone()),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let partition_id = PartitionId::from(uri.as_str());
         let source_checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
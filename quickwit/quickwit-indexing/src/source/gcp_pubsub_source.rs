@@ -317,6 +317,10 @@ mod gcp_pubsub_emulator_tests {
             }),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         }
     }
 
@@ -12,17 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, anyhow, bail};
 use async_trait::async_trait;
+use base64::prelude::{BASE64_STANDARD, Engine};
 use bytes::Bytes;
 use itertools::Itertools;
 use oneshot;
 use quickwit_actors::{ActorExitStatus, Mailbox};
-use quickwit_config::KafkaSourceParams;
+use quickwit_config::{KafkaSourceParams, KafkaStartFrom};
 use quickwit_metastore::checkpoint::{PartitionId, SourceCheckpoint};
 use quickwit_proto::metastore::SourceType;
 use quickwit_proto::types::{IndexUid, Position};
@@ -31,10 +32,10 @@ use rdkafka::consumer::{
     BaseConsumer, CommitMode, Consumer, ConsumerContext, DefaultConsumerContext, Rebalance,
 };
 use rdkafka::error::KafkaError;
-use rdkafka::message::BorrowedMessage;
+use rdkafka::message::{BorrowedMessage, Headers};
 use rdkafka::util::Timeout;
 use rdkafka::{ClientContext, Message, Offset, TopicPartitionList};
-use serde_json::{Value as JsonValue, json};
+use serde_json::{Map as JsonMap, Value as JsonValue, json};
 use tokio::sync::{mpsc, watch};
 use tokio::task::{JoinHandle, spawn_blocking};
 use tokio::time;
@@ -87,10 +88,17 @@ struct KafkaMessage {
     offset: i64,
 }
 
-impl From<BorrowedMessage<'_>> for KafkaMessage {
-    fn from(message: BorrowedMessage<'_>) -> Self {
+impl KafkaMessage {
+    fn new(message: BorrowedMessage<'_>, metadata_field_prefix_opt: Option<&str>) -> Self {
+        let mut doc_opt = message_payload_to_doc(&message);
+
+        if let Some(metadata_field_prefix) = metadata_field_prefix_opt
+            && let Some(doc) = doc_opt.take()
+        {
+            doc_opt = Some(add_metadata_fields(doc, &message, metadata_field_prefix));
+        }
         Self {
-            doc_opt: message_payload_to_doc(&message),
+            doc_opt,
             payload_len: message.payload_len() as u64,
             partition: message.partition(),
             offset: message.offset(),
@@ -100,6 +108,7 @@ impl From<BorrowedMessage<'_>> for KafkaMessage {
 
 struct RdKafkaContext {
     topic: String,
+    start_from_opt: Option<KafkaStartFrom>,
     events_tx: mpsc::Sender<KafkaEvent>,
 }
 
@@ -127,7 +136,7 @@ macro_rules! return_if_err {
 /// The API of the rebalance callback is better explained in the docs of `librdkafka`:
 /// <https://docs.confluent.io/2.0.0/clients/librdkafka/classRdKafka_1_1RebalanceCb.html>
 impl ConsumerContext for RdKafkaContext {
-    fn pre_rebalance(&self, _consumer: &BaseConsumer<Self>, rebalance: &Rebalance) {
+    fn pre_rebalance(&self, consumer: &BaseConsumer<Self>, rebalance: &Rebalance) {
         crate::metrics::INDEXER_METRICS.kafka_rebalance_total.inc();
         quickwit_common::rate_limited_info!(limit_per_min = 3, topic = self.topic, "rebalance");
         if let Rebalance::Revoke(tpl) = rebalance {
@@ -149,15 +158,34 @@ impl ConsumerContext for RdKafkaContext {
             let (assignment_tx, assignment_rx) = oneshot::channel();
             return_if_err!(
                 self.events_tx.blocking_send(KafkaEvent::AssignPartitions {
-                    partitions,
+                    partitions: partitions.clone(),
                     assignment_tx,
                 }),
                 "failed to send assign message to source"
             );
-            let assignment = return_if_err!(
+            let mut assignment = return_if_err!(
                 assignment_rx.recv(),
                 "failed to receive assignment from source"
             );
+            if let Some(start_from) = self.start_from_opt {
+                let checkpointed_partitions: HashSet<i32> = assignment
+                    .iter()
+                    .map(|(partition_id, _)| *partition_id)
+                    .collect();
+                let new_partitions: Vec<i32> = partitions
+                    .into_iter()
+                    .filter(|partition_id| !checkpointed_partitions.contains(partition_id))
+                    .collect();
+                match start_offsets(consumer, &self.topic, &new_partitions, start_from) {
+                    Ok(start_offsets) => assignment.extend(start_offsets),
+                    Err(error) => {
+                        return_if_err!(
+                            self.events_tx.blocking_send(KafkaEvent::Error(error)),
+                            "failed to send error message to source"
+                        );
+                    }
+                }
+            }
             for (partition_id, offset) in assignment {
                 let Some(mut partition) = tpl.find_partition(&self.topic, partition_id) else {
                     warn!("partition `{partition_id}` not found in assignment");
@@ -174,6 +202,46 @@ impl ConsumerContext for RdKafkaContext {
     }
 }
 
+/// Returns the offsets from which the partitions without checkpoint are consumed.
+fn start_offsets(
+    consumer: &BaseConsumer<RdKafkaContext>,
+    topic: &str,
+    partitions: &[i32],
+    start_from: KafkaStartFrom,
+) -> anyhow::Result<Vec<(i32, Offset)>> {
+    let offset = match start_from {
+        KafkaStartFrom::Earliest => Offset::Beginning,
+        KafkaStartFrom::Latest => Offset::End,
+        KafkaStartFrom::Timestamp(timestamp_millis) => {
+            if partitions.is_empty() {
+                return Ok(Vec::new());
+            }
+            // `offsets_for_times` expects the timestamps in place of the offsets and returns the
+            // earliest offset whose timestamp is greater than or equal to the timestamp, or `End`
+            // if there is no such message.
+            let mut tpl = TopicPartitionList::new();
+            for &partition_id in partitions {
+                tpl.add_partition_offset(topic, partition_id, Offset::Offset(timestamp_millis))?;
+            }
+            let start_offsets = consumer
+                .offsets_for_times(tpl, Duration::from_secs(10))
+                .with_context(|| {
+                    format!("failed to look up offsets for timestamp `{timestamp_millis}`")
+                })?
+                .elements()
+                .iter()
+                .map(|tple| (tple.partition(), tple.offset()))
+                .collect();
+            return Ok(start_offsets);
+        }
+    };
+    let start_offsets = partitions
+        .iter()
+        .map(|&partition_id| (partition_id, offset))
+        .collect();
+    Ok(start_offsets)
+}
+
 fn collect_partitions(tpl: &TopicPartitionList, topic: &str) -> Vec<i32> {
     tpl.elements()
         .iter()
@@ -236,6 +304,9 @@ impl KafkaSource {
     ) -> anyhow::Result<Self> {
         let topic = source_params.topic.clone();
         let backfill_mode_enabled = source_params.enable_backfill_mode;
+        let metadata_field_prefix_opt = source_params
+            .include_metadata
+            .then(|| source_params.metadata_field_prefix.clone());
 
         let (events_tx, events_rx) = mpsc::channel(100);
         let (truncate_tx, truncate_rx) = watch::channel(SourceCheckpoint::default());
@@ -252,9 +323,15 @@ impl KafkaSource {
         let max_poll_interval_ms = native_client_config
             .get("max.poll.interval.ms")?
             .parse::<u64>()?;
+        let isolation_level = native_client_config.get("isolation.level")?;
 
-        let poll_loop_jh =
-            spawn_consumer_poll_loop(consumer, topic.clone(), events_tx, truncate_rx);
+        let poll_loop_jh = spawn_consumer_poll_loop(
+            consumer,
+            topic.clone(),
+            metadata_field_prefix_opt,
+            events_tx,
+            truncate_rx,
+        );
         let publish_lock = PublishLock::default();
 
         info!(
@@ -264,6 +341,7 @@ impl KafkaSource {
             group_id,
             max_poll_interval_ms,
             session_timeout_ms,
+            isolation_level,
             "starting Kafka source"
         );
         if max_poll_interval_ms <= 60_000 {
@@ -552,6 +630,7 @@ impl Source for KafkaSource {
 fn spawn_consumer_poll_loop(
     consumer: RdKafkaConsumer,
     topic: String,
+    metadata_field_prefix_opt: Option<String>,
     events_tx: mpsc::Sender<KafkaEvent>,
     mut truncate_rx: watch::Receiver<SourceCheckpoint>,
 ) -> JoinHandle<()> {
@@ -570,7 +649,10 @@ fn spawn_consumer_poll_loop(
         while !events_tx.is_closed() {
             if let Some(message_res) = consumer.poll(Some(Duration::from_secs(1))) {
                 let event = match message_res {
-                    Ok(message) => KafkaEvent::Message(message.into()),
+                    Ok(message) => KafkaEvent::Message(KafkaMessage::new(
+                        message,
+                        metadata_field_prefix_opt.as_deref(),
+                    )),
                     Err(KafkaError::PartitionEOF(partition)) => KafkaEvent::PartitionEOF(partition),
                     Err(error) => KafkaEvent::Error(anyhow!(error)),
                 };
@@ -668,6 +750,14 @@ fn create_consumer(
 
     let mut client_config = parse_client_params(params.client_params)?;
 
+    // With `read_committed`, the consumer skips the messages of aborted transactions and does not
+    // deliver the messages of ongoing transactions, so the source never indexes uncommitted data.
+    // The offsets of the skipped messages and of the transaction markers leave gaps in the
+    // positions of the partitions, which the checkpoint tolerates. This is the default of
+    // librdkafka, but we set it explicitly so as not to depend on it.
+    if client_config.get("isolation.level").is_none() {
+        client_config.set("isolation.level", "read_committed");
+    }
     let log_level = parse_client_log_level(params.client_log_level)?;
     let consumer: RdKafkaConsumer = client_config
         .set("enable.auto.commit", "false") // We manage offsets ourselves: we always want to set this value to `false`.
@@ -679,6 +769,7 @@ fn create_consumer(
         .set_log_level(log_level)
         .create_with_context(RdKafkaContext {
             topic: params.topic,
+            start_from_opt: params.start_from,
             events_tx,
         })
         .context("failed to create Kafka consumer")?;
@@ -755,6 +846,118 @@ fn message_payload_to_doc(message: &BorrowedMessage) -> Option<Bytes> {
     None
 }
 
+/// Adds the key, headers, timestamp, partition, and offset of the message to the document.
+/// Documents that are not JSON objects are returned unchanged and are reported as invalid by the
+/// doc processor.
+fn add_metadata_fields<M: Message>(doc: Bytes, message: &M, field_prefix: &str) -> Bytes {
+    let Ok(mut doc_object) = serde_json::from_slice::<JsonMap<String, JsonValue>>(&doc) else {
+        return doc;
+    };
+    if let Some(key) = message.key() {
+        doc_object.insert(format!("{field_prefix}key"), bytes_to_json(key));
+    }
+    if let Some(headers) = message.headers() {
+        let headers_object: JsonMap<String, JsonValue> = headers
+            .iter()
+            .map(|header| {
+                let value = header.value.map(bytes_to_json).unwrap_or(JsonValue::Null);
+                (header.key.to_string(), value)
+            })
+            .collect();
+        doc_object.insert(
+            format!("{field_prefix}headers"),
+            JsonValue::Object(headers_object),
+        );
+    }
+    if let Some(timestamp_millis) = message.timestamp().to_millis() {
+        doc_object.insert(format!("{field_prefix}timestamp"), timestamp_millis.into());
+    }
+    doc_object.insert(
+        format!("{field_prefix}partition"),
+        message.partition().into(),
+    );
+    doc_object.insert(format!("{field_prefix}offset"), message.offset().into());
+
+    let doc = serde_json::to_vec(&doc_object).expect("JSON object should be serializable");
+    Bytes::from(doc)
+}
+
+/// Converts the key or a header value of a message to a JSON string. Values that are not valid
+/// UTF-8 are base64-encoded.
+fn bytes_to_json(bytes: &[u8]) -> JsonValue {
+    match std::str::from_utf8(bytes) {
+        Ok(value) => JsonValue::String(value.to_string()),
+        Err(_) => JsonValue::String(BASE64_STANDARD.encode(bytes)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rdkafka::message::{Header, OwnedHeaders, OwnedMessage, Timestamp};
+
+    use super::*;
+
+    #[test]
+    fn test_add_metadata_fields() {
+        let headers = OwnedHeaders::new()
+            .insert(Header {
+                key: "trace-id",
+                value: Some("abc"),
+            })
+            .insert(Header {
+                key: "binary",
+                value: Some(&[0xff, 0xfe][..]),
+            })
+            .insert::<[u8]>(Header {
+                key: "empty",
+                value: None,
+            });
+        let message = OwnedMessage::new(
+            None,
+            Some(b"my-key".to_vec()),
+            "my-topic".to_string(),
+            Timestamp::CreateTime(1_700_000_000_000),
+            3,
+            42,
+            Some(headers),
+        );
+        let doc = Bytes::from_static(br#"{"message": "hello"}"#);
+        let doc_with_metadata = add_metadata_fields(doc, &message, "kafka_");
+        let doc_json: JsonValue = serde_json::from_slice(&doc_with_metadata).unwrap();
+        assert_eq!(
+            doc_json,
+            json!({
+                "message": "hello",
+                "kafka_key": "my-key",
+                "kafka_headers": {"trace-id": "abc", "binary": "//4=", "empty": null},
+                "kafka_timestamp": 1_700_000_000_000i64,
+                "kafka_partition": 3,
+                "kafka_offset": 42,
+            })
+        );
+
+        let message = OwnedMessage::new(
+            None,
+            None,
+            "my-topic".to_string(),
+            Timestamp::NotAvailable,
+            0,
+            0,
+            None,
+        );
+        let doc = Bytes::from_static(br#"{"message": "hello"}"#);
+        let doc_with_metadata = add_metadata_fields(doc, &message, "_");
+        let doc_json: JsonValue = serde_json::from_slice(&doc_with_metadata).unwrap();
+        assert_eq!(
+            doc_json,
+            json!({"message": "hello", "_partition": 0, "_offset": 0})
+        );
+
+        let doc = Bytes::from_static(b"hello");
+        assert_eq!(add_metadata_fields(doc.clone(), &message, "kafka_"), doc);
+    }
+}
+
 #[cfg(all(test, feature = "kafka-broker-tests"))]
 mod kafka_broker_tests {
     use std::num::NonZeroUsize;
@@ -887,9 +1090,16 @@ mod kafka_broker_tests {
                     "broker.address.family": "v4",
                 }),
                 enable_backfill_mode: true,
+                include_metadata: false,
+                metadata_field_prefix: "kafka_".to_string(),
+                start_from: None,
             }),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         (source_id, source_config)
     }
@@ -1494,6 +1704,9 @@ mod kafka_broker_tests {
             client_log_level: None,
             client_params: json!({ "bootstrap.servers": bootstrap_servers }),
             enable_backfill_mode: true,
+            include_metadata: false,
+            metadata_field_prefix: "kafka_".to_string(),
+            start_from: None,
         })
         .await
         .unwrap();
@@ -1505,6 +1718,9 @@ mod kafka_broker_tests {
             client_log_level: None,
             client_params: json!({ "bootstrap.servers": bootstrap_servers }),
             enable_backfill_mode: true,
+            include_metadata: false,
+            metadata_field_prefix: "kafka_".to_string(),
+            start_from: None,
         })
         .await
         .unwrap_err();
@@ -1517,6 +1733,9 @@ mod kafka_broker_tests {
                 "bootstrap.servers": "192.0.2.10:9092"
             }),
             enable_backfill_mode: true,
+            include_metadata: false,
+            metadata_field_prefix: "kafka_".to_string(),
+            start_from: None,
         })
         .await
         .unwrap_err();
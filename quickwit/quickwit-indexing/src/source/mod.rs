@@ -52,6 +52,9 @@
 //!   that file.
 //! - the kafka source: the partition id is a kafka topic partition id, and the position is a kafka
 //!   offset.
+#[cfg(feature = "columnar-files")]
+mod columnar_file_reader;
+mod directory_source;
 mod doc_file_reader;
 mod file_source;
 #[cfg(feature = "gcp-pubsub")]
@@ -78,6 +81,7 @@ use std::time::Duration;
 use async_trait::async_trait;
 use bytes::Bytes;
 use bytesize::ByteSize;
+pub use directory_source::{DirectorySource, DirectorySourceFactory};
 pub use file_source::{FileSource, FileSourceFactory};
 #[cfg(feature = "gcp-pubsub")]
 pub use gcp_pubsub_source::{GcpPubSubSource, GcpPubSubSourceFactory};
@@ -405,6 +409,7 @@ pub fn quickwit_supported_sources() -> &'static SourceLoader {
     static SOURCE_LOADER: OnceCell<SourceLoader> = OnceCell::new();
     SOURCE_LOADER.get_or_init(|| {
         let mut source_factory = SourceLoader::default();
+        source_factory.add_source(SourceType::Directory, DirectorySourceFactory);
         source_factory.add_source(SourceType::File, FileSourceFactory);
         #[cfg(feature = "gcp-pubsub")]
         source_factory.add_source(SourceType::PubSub, GcpPubSubSourceFactory);
@@ -690,6 +695,10 @@ mod tests {
                 source_params: SourceParams::void(),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_config: None,
+                schema_config: None,
+                line_format_config: None,
+                index_routing_config: None,
             };
             check_source_connectivity(&StorageResolver::for_test(), &source_config).await?;
         }
@@ -701,6 +710,10 @@ mod tests {
                 source_params: SourceParams::Vec(VecSourceParams::default()),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_config: None,
+                schema_config: None,
+                line_format_config: None,
+                index_routing_config: None,
             };
             check_source_connectivity(&StorageResolver::for_test(), &source_config).await?;
         }
@@ -712,6 +725,10 @@ mod tests {
                 source_params: SourceParams::file_from_str("file-does-not-exist.json").unwrap(),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_config: None,
+                schema_config: None,
+                line_format_config: None,
+                index_routing_config: None,
             };
             assert!(
                 check_source_connectivity(&StorageResolver::for_test(), &source_config)
@@ -727,6 +744,10 @@ mod tests {
                 source_params: SourceParams::file_from_str("data/test_corpus.json").unwrap(),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_config: None,
+                schema_config: None,
+                line_format_config: None,
+                index_routing_config: None,
             };
             assert!(
                 check_source_connectivity(&StorageResolver::for_test(), &source_config)
@@ -75,6 +75,7 @@ pub struct QueueCoordinator {
     local_state: QueueLocalState,
     publish_token: String,
     visibility_settings: VisibilitySettings,
+    has_csv_header: bool,
 }
 
 impl fmt::Debug for QueueCoordinator {
@@ -111,6 +112,7 @@ impl QueueCoordinator {
             local_state: QueueLocalState::default(),
             pipeline_id: source_runtime.pipeline_id,
             source_type: source_runtime.source_config.source_type(),
+            has_csv_header: source_runtime.source_config.has_csv_header(),
             storage_resolver: source_runtime.storage_resolver,
             queue_receiver: QueueReceiver::new(queue.clone(), RECEIVE_POLL_TIMEOUT),
             queue,
@@ -270,7 +272,10 @@ impl QueueCoordinator {
                 self.observable_state.num_messages_processed += 1;
             }
         } else if let Some(ready_message) = self.local_state.get_ready_for_read() {
-            match ready_message.start_processing(&self.storage_resolver).await {
+            match ready_message
+                .start_processing(&self.storage_resolver, self.has_csv_header)
+                .await
+            {
                 Ok(new_in_progress) => {
                     self.local_state.set_currently_read(new_in_progress)?;
                 }
@@ -359,6 +364,7 @@ mod tests {
             storage_resolver: StorageResolver::for_test(),
             publish_token: Ulid::new().to_string(),
             visibility_settings: VisibilitySettings::from_commit_timeout(5),
+            has_csv_header: false,
         }
     }
 
@@ -175,6 +175,7 @@ impl ReadyMessage {
     pub async fn start_processing(
         self,
         storage_resolver: &StorageResolver,
+        has_csv_header: bool,
     ) -> anyhow::Result<Option<InProgressMessage>> {
         let partition_id = self.partition_id();
         match self.content.payload {
@@ -184,6 +185,7 @@ impl ReadyMessage {
                     partition_id.clone(),
                     &uri,
                     self.position,
+                    has_csv_header,
                 )
                 .await?;
                 if batch_reader.is_eof() {
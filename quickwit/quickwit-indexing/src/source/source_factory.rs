@@ -128,6 +128,10 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let source_runtime = SourceRuntimeBuilder::new(index_uid, source_config).build();
         source_loader.load_source(source_runtime).await?;
@@ -125,6 +125,7 @@ impl TestSandbox {
             Some(ingest_api_service),
             merge_scheduler_mailbox,
             IngesterPool::default(),
+            None,
             storage_resolver.clone(),
             EventBroker::default(),
         )
@@ -171,6 +172,10 @@ impl TestSandbox {
             }),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         let pipeline_id = self
             .indexing_service
@@ -2723,6 +2723,8 @@ mod tests {
                     scroll_id: None,
                     failed_splits: Vec::new(),
                     num_successful_splits: 1,
+                    profile: None,
+                    warnings: Vec::new(),
                 })
             });
 
@@ -320,6 +320,7 @@ impl DeleteTaskPlanner {
                 IndexMetasForLeafSearch {
                     doc_mapper_str: doc_mapper_str.to_string(),
                     index_uri,
+                    dedup_settings_opt: None,
                 },
             );
             let leaf_search_request = jobs_to_leaf_request(
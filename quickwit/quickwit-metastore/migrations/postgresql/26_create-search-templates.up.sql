@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS search_templates (
+    template_id VARCHAR(255) NOT NULL,
+    search_template_json TEXT NOT NULL,
+    PRIMARY KEY (template_id)
+);
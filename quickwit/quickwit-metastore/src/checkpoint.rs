@@ -20,6 +20,7 @@ use std::iter::FromIterator;
 use std::ops::Range;
 use std::sync::Arc;
 
+use quickwit_common::is_false;
 use quickwit_proto::types::{Position, SourceId};
 use serde::ser::SerializeMap;
 use serde::{Deserialize, Serialize};
@@ -84,6 +85,10 @@ impl From<i64> for PartitionId {
 pub struct PartitionDelta {
     pub from: Position,
     pub to: Position,
+    /// Whether the partition is removed from the checkpoint once it reaches `to`. See
+    /// [`SourceCheckpointDelta::record_partition_removal`].
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub remove: bool,
 }
 
 #[derive(Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -330,8 +335,12 @@ impl SourceCheckpoint {
         self.check_compatibility(&delta)?;
         debug!(delta=?delta, checkpoint=?self, "applying delta to checkpoint");
         for (partition_id, partition_position) in delta.per_partition {
-            self.per_partition
-                .insert(partition_id, partition_position.to);
+            if partition_position.remove {
+                self.per_partition.remove(&partition_id);
+            } else {
+                self.per_partition
+                    .insert(partition_id, partition_position.to);
+            }
         }
         Ok(())
     }
@@ -405,6 +414,9 @@ impl fmt::Debug for SourceCheckpointDelta {
                 "{}:({}..{}]",
                 partition_id.0, partition_delta.from, partition_delta.to,
             )?;
+            if partition_delta.remove {
+                f.write_str(":removed")?;
+            }
             if i != self.per_partition.len() - 1 {
                 f.write_str(" ")?;
             }
@@ -489,7 +501,8 @@ impl SourceCheckpointDelta {
         let entry = self.per_partition.entry(partition_id);
         match entry {
             Entry::Occupied(mut occupied_entry) => {
-                if occupied_entry.get().to == from_position {
+                // A removed partition cannot be extended within the same delta.
+                if occupied_entry.get().to == from_position && !occupied_entry.get().remove {
                     occupied_entry.get_mut().to = to_position;
                 } else {
                     return Err(PartitionDeltaError::from(IncompatibleCheckpointDelta {
@@ -503,6 +516,41 @@ impl SourceCheckpointDelta {
                 let partition_delta = PartitionDelta {
                     from: from_position,
                     to: to_position,
+                    remove: false,
+                };
+                vacant_entry.insert(partition_delta);
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the removal of a partition from the checkpoint, for sources whose partitions are
+    /// short-lived and would otherwise accumulate in the checkpoint forever (e.g. the files of a
+    /// directory source). The partition must be at `from_position`, and is absent from the
+    /// checkpoint once the delta is applied.
+    pub fn record_partition_removal(
+        &mut self,
+        partition_id: PartitionId,
+        from_position: Position,
+    ) -> Result<(), PartitionDeltaError> {
+        let entry = self.per_partition.entry(partition_id);
+        match entry {
+            Entry::Occupied(mut occupied_entry) => {
+                if occupied_entry.get().to == from_position {
+                    occupied_entry.get_mut().remove = true;
+                } else {
+                    return Err(PartitionDeltaError::from(IncompatibleCheckpointDelta {
+                        partition_id: occupied_entry.key().clone(),
+                        partition_position: occupied_entry.get().to.clone(),
+                        delta_from_position: from_position,
+                    }));
+                }
+            }
+            Entry::Vacant(vacant_entry) => {
+                let partition_delta = PartitionDelta {
+                    from: from_position.clone(),
+                    to: from_position,
+                    remove: true,
                 };
                 vacant_entry.insert(partition_delta);
             }
@@ -515,7 +563,16 @@ impl SourceCheckpointDelta {
     /// Contrary to checkpoint update, the two deltas here need to chain perfectly.
     pub fn extend(&mut self, delta: SourceCheckpointDelta) -> Result<(), PartitionDeltaError> {
         for (partition_id, partition_delta) in delta.per_partition {
-            self.record_partition_delta(partition_id, partition_delta.from, partition_delta.to)?;
+            if partition_delta.from != partition_delta.to {
+                self.record_partition_delta(
+                    partition_id.clone(),
+                    partition_delta.from,
+                    partition_delta.to.clone(),
+                )?;
+            }
+            if partition_delta.remove {
+                self.record_partition_removal(partition_id, partition_delta.to)?;
+            }
         }
         Ok(())
     }
@@ -773,6 +830,86 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_partition_removal() {
+        let mut checkpoint = SourceCheckpoint::default();
+        let mut delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from("a"),
+            Position::Beginning,
+            Position::offset(10u64),
+        )
+        .unwrap();
+        delta
+            .record_partition_delta(
+                PartitionId::from("b"),
+                Position::Beginning,
+                Position::eof(20u64),
+            )
+            .unwrap();
+        checkpoint.try_apply_delta(delta).unwrap();
+        assert_eq!(checkpoint.num_partitions(), 2);
+
+        let mut delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from("a"),
+            Position::offset(10u64),
+            Position::eof(15u64),
+        )
+        .unwrap();
+        let mut removal_delta = SourceCheckpointDelta::default();
+        removal_delta
+            .record_partition_removal(PartitionId::from("a"), Position::eof(15u64))
+            .unwrap();
+        removal_delta
+            .record_partition_removal(PartitionId::from("b"), Position::eof(20u64))
+            .unwrap();
+        delta.extend(removal_delta).unwrap();
+
+        // The removal must chain with the previous deltas.
+        delta
+            .clone()
+            .record_partition_removal(PartitionId::from("a"), Position::eof(10u64))
+            .unwrap_err();
+
+        checkpoint.try_apply_delta(delta).unwrap();
+        assert!(checkpoint.is_empty());
+
+        // A removed partition cannot be extended within the same delta.
+        let mut delta = SourceCheckpointDelta::default();
+        delta
+            .record_partition_removal(PartitionId::from("a"), Position::eof(15u64))
+            .unwrap();
+        delta
+            .record_partition_delta(
+                PartitionId::from("a"),
+                Position::eof(15u64),
+                Position::eof(20u64),
+            )
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_partition_delta_to_beginning_keeps_partition() {
+        // Deltas of other sources never carry the removal marker, even when serialized.
+        let delta = SourceCheckpointDelta::from_range(0..10);
+        let delta_json = serde_json::to_string(&delta).unwrap();
+        assert!(!delta_json.contains("remove"));
+
+        let mut checkpoint: SourceCheckpoint = [(PartitionId::from("a"), Position::Beginning)]
+            .into_iter()
+            .collect();
+        let delta: SourceCheckpointDelta = serde_json::from_value(serde_json::json!({
+            "per_partition": {
+                "a": {"from": "", "to": ""}
+            }
+        }))
+        .unwrap();
+        checkpoint.try_apply_delta(delta).unwrap();
+        assert_eq!(
+            checkpoint.position_for_partition(&PartitionId::from("a")),
+            Some(&Position::Beginning)
+        );
+    }
+
     #[test]
     fn test_index_checkpoint() {
         let mut index_checkpoint = IndexCheckpoint::default();
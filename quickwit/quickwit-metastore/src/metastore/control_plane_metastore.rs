@@ -19,21 +19,23 @@ use quickwit_common::uri::Uri;
 use quickwit_proto::control_plane::{ControlPlaneService, ControlPlaneServiceClient};
 use quickwit_proto::metastore::{
     AcquireShardsRequest, AcquireShardsResponse, AddSourceRequest, CreateIndexRequest,
-    CreateIndexResponse, CreateIndexTemplateRequest, DeleteIndexRequest,
-    DeleteIndexTemplatesRequest, DeleteQuery, DeleteShardsRequest, DeleteShardsResponse,
-    DeleteSourceRequest, DeleteSplitsRequest, DeleteTask, EmptyResponse,
-    FindIndexTemplateMatchesRequest, FindIndexTemplateMatchesResponse, GetClusterIdentityRequest,
-    GetClusterIdentityResponse, GetIndexTemplateRequest, GetIndexTemplateResponse,
+    CreateIndexResponse, CreateIndexTemplateRequest, CreateSearchTemplateRequest,
+    DeleteIndexRequest, DeleteIndexTemplatesRequest, DeleteQuery, DeleteSearchTemplatesRequest,
+    DeleteShardsRequest, DeleteShardsResponse, DeleteSourceRequest, DeleteSplitsRequest,
+    DeleteTask, EmptyResponse, FindIndexTemplateMatchesRequest, FindIndexTemplateMatchesResponse,
+    GetClusterIdentityRequest, GetClusterIdentityResponse, GetIndexTemplateRequest,
+    GetIndexTemplateResponse, GetSearchTemplateRequest, GetSearchTemplateResponse,
     IndexMetadataRequest, IndexMetadataResponse, IndexesMetadataRequest, IndexesMetadataResponse,
     LastDeleteOpstampRequest, LastDeleteOpstampResponse, ListDeleteTasksRequest,
     ListDeleteTasksResponse, ListIndexStatsRequest, ListIndexStatsResponse,
     ListIndexTemplatesRequest, ListIndexTemplatesResponse, ListIndexesMetadataRequest,
-    ListIndexesMetadataResponse, ListShardsRequest, ListShardsResponse, ListSplitsRequest,
-    ListSplitsResponse, ListStaleSplitsRequest, MarkSplitsForDeletionRequest, MetastoreResult,
-    MetastoreService, MetastoreServiceClient, MetastoreServiceStream, OpenShardsRequest,
-    OpenShardsResponse, PruneShardsRequest, PublishSplitsRequest, ResetSourceCheckpointRequest,
-    StageSplitsRequest, ToggleSourceRequest, UpdateIndexRequest, UpdateSourceRequest,
-    UpdateSplitsDeleteOpstampRequest, UpdateSplitsDeleteOpstampResponse,
+    ListIndexesMetadataResponse, ListSearchTemplatesRequest, ListSearchTemplatesResponse,
+    ListShardsRequest, ListShardsResponse, ListSplitsRequest, ListSplitsResponse,
+    ListStaleSplitsRequest, MarkSplitsForDeletionRequest, MetastoreResult, MetastoreService,
+    MetastoreServiceClient, MetastoreServiceStream, OpenShardsRequest, OpenShardsResponse,
+    PruneShardsRequest, PublishSplitsRequest, ResetSourceCheckpointRequest, StageSplitsRequest,
+    ToggleSourceRequest, UpdateIndexRequest, UpdateSourceRequest, UpdateSplitsDeleteOpstampRequest,
+    UpdateSplitsDeleteOpstampResponse,
 };
 
 /// A [`MetastoreService`] implementation that proxies some requests to the control plane so it can
@@ -283,6 +285,36 @@ impl MetastoreService for ControlPlaneMetastore {
         self.metastore.delete_index_templates(request).await
     }
 
+    // Search Template API
+
+    async fn create_search_template(
+        &self,
+        request: CreateSearchTemplateRequest,
+    ) -> MetastoreResult<EmptyResponse> {
+        self.metastore.create_search_template(request).await
+    }
+
+    async fn get_search_template(
+        &self,
+        request: GetSearchTemplateRequest,
+    ) -> MetastoreResult<GetSearchTemplateResponse> {
+        self.metastore.get_search_template(request).await
+    }
+
+    async fn list_search_templates(
+        &self,
+        request: ListSearchTemplatesRequest,
+    ) -> MetastoreResult<ListSearchTemplatesResponse> {
+        self.metastore.list_search_templates(request).await
+    }
+
+    async fn delete_search_templates(
+        &self,
+        request: DeleteSearchTemplatesRequest,
+    ) -> MetastoreResult<EmptyResponse> {
+        self.metastore.delete_search_templates(request).await
+    }
+
     async fn get_cluster_identity(
         &self,
         request: GetClusterIdentityRequest,
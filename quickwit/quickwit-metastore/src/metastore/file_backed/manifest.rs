@@ -17,7 +17,7 @@ use std::path::Path;
 
 use itertools::Itertools;
 use quickwit_common::uri::Uri;
-use quickwit_config::{IndexTemplate, IndexTemplateId};
+use quickwit_config::{IndexTemplate, IndexTemplateId, SearchTemplate, SearchTemplateId};
 use quickwit_proto::metastore::{MetastoreError, MetastoreResult, serde_utils};
 use quickwit_proto::types::{DocMappingUid, IndexId};
 use quickwit_storage::{OwnedBytes, Storage, StorageError, StorageErrorKind, StorageResult};
@@ -41,6 +41,7 @@ impl LegacyManifest {
         Manifest {
             indexes: self.indexes,
             templates: HashMap::new(),
+            search_templates: HashMap::new(),
             identity: Uuid::nil(),
         }
     }
@@ -66,6 +67,7 @@ pub(crate) struct Manifest {
     // The templates are serialized as a sorted `Vec<IndexTemplate>` so the btree map is
     // unnecessary here and we can pass the hash map as is to the `MetastoreState`
     pub templates: HashMap<IndexTemplateId, IndexTemplate>,
+    pub search_templates: HashMap<SearchTemplateId, SearchTemplate>,
     pub identity: Uuid,
 }
 
@@ -106,6 +108,8 @@ impl From<VersionedManifest> for Manifest {
 struct ManifestV0_8 {
     indexes: BTreeMap<IndexId, IndexStatus>,
     templates: Vec<IndexTemplate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    search_templates: Vec<SearchTemplate>,
     #[serde(default, skip_serializing_if = "Uuid::is_nil")]
     identity: Uuid,
 }
@@ -117,9 +121,15 @@ impl From<Manifest> for ManifestV0_8 {
             .into_values()
             .sorted_unstable_by(|left, right| left.template_id.cmp(&right.template_id))
             .collect();
+        let search_templates = manifest
+            .search_templates
+            .into_values()
+            .sorted_unstable_by(|left, right| left.template_id.cmp(&right.template_id))
+            .collect();
         ManifestV0_8 {
             indexes: manifest.indexes,
             templates,
+            search_templates,
             identity: manifest.identity,
         }
     }
@@ -133,9 +143,15 @@ impl From<ManifestV0_8> for Manifest {
             .into_iter()
             .map(|template| (template.template_id.clone(), template))
             .collect();
+        let search_templates = manifest
+            .search_templates
+            .into_iter()
+            .map(|search_template| (search_template.template_id.clone(), search_template))
+            .collect();
         Manifest {
             indexes,
             templates,
+            search_templates,
             identity: manifest.identity,
         }
     }
@@ -157,6 +173,7 @@ impl quickwit_config::TestableForRegression for Manifest {
         Manifest {
             indexes,
             templates,
+            search_templates: HashMap::new(),
             identity: Uuid::nil(),
         }
     }
@@ -334,9 +351,14 @@ mod tests {
                 IndexTemplate::for_test("test-template-2", &["test-index-bar*"], 200),
             ),
         ]);
+        let search_templates = HashMap::from_iter([(
+            "test-search-template".to_string(),
+            SearchTemplate::for_test("test-search-template"),
+        )]);
         let manifest = Manifest {
             indexes,
             templates,
+            search_templates,
             identity: Uuid::nil(),
         };
         let manifest_json = serde_json::to_string_pretty(&manifest).unwrap();
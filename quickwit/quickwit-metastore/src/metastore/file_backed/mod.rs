@@ -38,19 +38,21 @@ use futures::future::try_join_all;
 use futures::stream::FuturesUnordered;
 use itertools::Itertools;
 use quickwit_common::ServiceStream;
-use quickwit_config::IndexTemplate;
+use quickwit_config::{IndexTemplate, SearchTemplate};
 use quickwit_proto::metastore::{
     AcquireShardsRequest, AcquireShardsResponse, AddSourceRequest, CreateIndexRequest,
-    CreateIndexResponse, CreateIndexTemplateRequest, DeleteIndexRequest,
-    DeleteIndexTemplatesRequest, DeleteQuery, DeleteShardsRequest, DeleteShardsResponse,
-    DeleteSourceRequest, DeleteSplitsRequest, DeleteTask, EmptyResponse, EntityKind,
-    FindIndexTemplateMatchesRequest, FindIndexTemplateMatchesResponse, GetClusterIdentityRequest,
-    GetClusterIdentityResponse, GetIndexTemplateRequest, GetIndexTemplateResponse,
-    IndexMetadataFailure, IndexMetadataFailureReason, IndexMetadataRequest, IndexMetadataResponse,
-    IndexTemplateMatch, IndexesMetadataRequest, IndexesMetadataResponse, LastDeleteOpstampRequest,
-    LastDeleteOpstampResponse, ListDeleteTasksRequest, ListDeleteTasksResponse,
-    ListIndexStatsRequest, ListIndexStatsResponse, ListIndexTemplatesRequest,
-    ListIndexTemplatesResponse, ListIndexesMetadataRequest, ListIndexesMetadataResponse,
+    CreateIndexResponse, CreateIndexTemplateRequest, CreateSearchTemplateRequest,
+    DeleteIndexRequest, DeleteIndexTemplatesRequest, DeleteQuery, DeleteSearchTemplatesRequest,
+    DeleteShardsRequest, DeleteShardsResponse, DeleteSourceRequest, DeleteSplitsRequest,
+    DeleteTask, EmptyResponse, EntityKind, FindIndexTemplateMatchesRequest,
+    FindIndexTemplateMatchesResponse, GetClusterIdentityRequest, GetClusterIdentityResponse,
+    GetIndexTemplateRequest, GetIndexTemplateResponse, GetSearchTemplateRequest,
+    GetSearchTemplateResponse, IndexMetadataFailure, IndexMetadataFailureReason,
+    IndexMetadataRequest, IndexMetadataResponse, IndexTemplateMatch, IndexesMetadataRequest,
+    IndexesMetadataResponse, LastDeleteOpstampRequest, LastDeleteOpstampResponse,
+    ListDeleteTasksRequest, ListDeleteTasksResponse, ListIndexStatsRequest, ListIndexStatsResponse,
+    ListIndexTemplatesRequest, ListIndexTemplatesResponse, ListIndexesMetadataRequest,
+    ListIndexesMetadataResponse, ListSearchTemplatesRequest, ListSearchTemplatesResponse,
     ListShardsRequest, ListShardsResponse, ListSplitsRequest, ListSplitsResponse,
     ListStaleSplitsRequest, MarkSplitsForDeletionRequest, MetastoreError, MetastoreResult,
     MetastoreService, MetastoreServiceStream, OpenShardSubrequest, OpenShardsRequest,
@@ -1250,6 +1252,127 @@ impl MetastoreService for FileBackedMetastore {
         Ok(EmptyResponse {})
     }
 
+    // Search Template API
+
+    async fn create_search_template(
+        &self,
+        request: CreateSearchTemplateRequest,
+    ) -> MetastoreResult<EmptyResponse> {
+        let search_template: SearchTemplate =
+            serde_utils::from_json_str(&request.search_template_json)?;
+        let template_id = search_template.template_id.clone();
+
+        search_template
+            .validate()
+            .map_err(|error| MetastoreError::InvalidArgument {
+                message: format!("invalid search template `{template_id}`: `{error:#}`"),
+            })?;
+
+        let mut state_wlock_guard = self.state.write().await;
+
+        let evicted_template_opt = match state_wlock_guard
+            .search_templates
+            .entry(template_id.clone())
+        {
+            Entry::Vacant(entry) => {
+                entry.insert(search_template);
+                None
+            }
+            Entry::Occupied(mut entry) if request.overwrite => {
+                let evicted_template = entry.insert(search_template);
+                Some(evicted_template)
+            }
+            Entry::Occupied(_) => {
+                return Err(MetastoreError::AlreadyExists(EntityKind::SearchTemplate {
+                    template_id,
+                }));
+            }
+        };
+        let manifest = state_wlock_guard.as_manifest();
+        let save_result = save_manifest(&*self.storage, &manifest).await;
+
+        // Rollback on error.
+        if let Err(error) = save_result {
+            if let Some(evicted_template) = evicted_template_opt {
+                state_wlock_guard
+                    .search_templates
+                    .insert(template_id, evicted_template);
+            } else {
+                state_wlock_guard.search_templates.remove(&template_id);
+            }
+            return Err(error);
+        }
+        Ok(EmptyResponse {})
+    }
+
+    async fn get_search_template(
+        &self,
+        request: GetSearchTemplateRequest,
+    ) -> MetastoreResult<GetSearchTemplateResponse> {
+        let inner_rlock_guard = self.state.read().await;
+        let search_template = inner_rlock_guard
+            .search_templates
+            .get(&request.template_id)
+            .ok_or({
+                MetastoreError::NotFound(EntityKind::SearchTemplate {
+                    template_id: request.template_id,
+                })
+            })?;
+        let search_template_json = serde_utils::to_json_str(search_template)?;
+        let response = GetSearchTemplateResponse {
+            search_template_json,
+        };
+        Ok(response)
+    }
+
+    async fn list_search_templates(
+        &self,
+        _request: ListSearchTemplatesRequest,
+    ) -> MetastoreResult<ListSearchTemplatesResponse> {
+        let inner_rlock_guard = self.state.read().await;
+
+        let search_templates_json: Vec<String> = inner_rlock_guard
+            .search_templates
+            .values()
+            .sorted_unstable_by(|left, right| left.template_id.cmp(&right.template_id))
+            .map(serde_utils::to_json_str)
+            .collect::<MetastoreResult<_>>()?;
+        let response = ListSearchTemplatesResponse {
+            search_templates_json,
+        };
+        Ok(response)
+    }
+
+    async fn delete_search_templates(
+        &self,
+        request: DeleteSearchTemplatesRequest,
+    ) -> MetastoreResult<EmptyResponse> {
+        let mut evicted_templates = Vec::with_capacity(request.template_ids.len());
+        let mut state_wlock_guard = self.state.write().await;
+
+        for template_id in &request.template_ids {
+            if let Some(evicted_template) = state_wlock_guard.search_templates.remove(template_id) {
+                evicted_templates.push(evicted_template);
+            }
+        }
+        if evicted_templates.is_empty() {
+            return Ok(EmptyResponse {});
+        }
+        let manifest = state_wlock_guard.as_manifest();
+        let save_result = save_manifest(&*self.storage, &manifest).await;
+
+        // Rollback on error.
+        if let Err(error) = save_result {
+            for evicted_template in evicted_templates {
+                state_wlock_guard
+                    .search_templates
+                    .insert(evicted_template.template_id.clone(), evicted_template);
+            }
+            return Err(error);
+        }
+        Ok(EmptyResponse {})
+    }
+
     // Get cluster identity api
 
     // this returns a constant uuid. on first call, it generate said uuid if it doesn't already
@@ -2409,4 +2532,59 @@ mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    async fn test_create_search_template_rollback() {
+        let mut mock_storage = MockStorage::default();
+
+        mock_storage
+            .expect_uri()
+            .return_const(Uri::for_test("ram:///indexes"));
+
+        mock_storage
+            .expect_put()
+            .once()
+            .returning(|path, _payload| {
+                assert_eq!(path, Path::new(MANIFEST_FILE_NAME));
+                Ok(())
+            });
+
+        mock_storage
+            .expect_put()
+            .once()
+            .returning(|path, _payload| {
+                assert_eq!(path, Path::new(MANIFEST_FILE_NAME));
+                let io_error = StorageErrorKind::Io.with_error(anyhow::anyhow!("IO error"));
+                Err(io_error)
+            });
+
+        let metastore = FileBackedMetastore::for_test(Arc::new(mock_storage));
+
+        let search_template = SearchTemplate::for_test("test-template");
+        let search_template_json = serde_json::to_string(&search_template).unwrap();
+        let create_search_template_request = CreateSearchTemplateRequest {
+            search_template_json,
+            overwrite: false,
+        };
+        metastore
+            .create_search_template(create_search_template_request)
+            .await
+            .unwrap();
+
+        let mut updated_search_template = search_template.clone();
+        updated_search_template.source = r#"{"size": {{size}}}"#.to_string();
+        let search_template_json = serde_json::to_string(&updated_search_template).unwrap();
+        let create_search_template_request = CreateSearchTemplateRequest {
+            search_template_json,
+            overwrite: true,
+        };
+        metastore
+            .create_search_template(create_search_template_request)
+            .await
+            .unwrap_err();
+
+        let state = metastore.state.read().await;
+        assert_eq!(state.search_templates.len(), 1);
+        assert_eq!(state.search_templates["test-template"], search_template);
+    }
 }
@@ -16,7 +16,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
-use quickwit_config::{IndexTemplate, IndexTemplateId};
+use quickwit_config::{IndexTemplate, IndexTemplateId, SearchTemplate, SearchTemplateId};
 use quickwit_proto::metastore::MetastoreResult;
 use quickwit_proto::types::IndexId;
 use quickwit_storage::Storage;
@@ -32,6 +32,7 @@ pub(super) struct MetastoreState {
     pub indexes: HashMap<IndexId, LazyIndexStatus>,
     pub templates: HashMap<IndexTemplateId, IndexTemplate>,
     pub template_matcher: IndexTemplateMatcher,
+    pub search_templates: HashMap<SearchTemplateId, SearchTemplate>,
     pub identity: Uuid,
 }
 
@@ -66,6 +67,7 @@ impl MetastoreState {
             indexes,
             templates: manifest.templates,
             template_matcher,
+            search_templates: manifest.search_templates,
             identity: manifest.identity,
         };
         Ok(state)
@@ -85,9 +87,11 @@ impl MetastoreState {
             })
             .collect();
         let templates = self.templates.clone();
+        let search_templates = self.search_templates.clone();
         Manifest {
             indexes,
             templates,
+            search_templates,
             identity: self.identity,
         }
     }
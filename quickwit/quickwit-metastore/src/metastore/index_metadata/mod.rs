@@ -263,6 +263,7 @@ mod tests {
 
         let new_search_settings = SearchSettings {
             default_search_fields: vec!["message".to_string(), "status".to_string()],
+            ..Default::default()
         };
         let mutation_occurred = current_index_metadata
             .update_index_config(
@@ -1040,6 +1040,7 @@ impl<T> Default for FilterRange<T> {
 /// (false) or the shard table (true)
 fn use_shard_api(params: &SourceParams) -> bool {
     match params {
+        SourceParams::Directory(_) => false,
         SourceParams::File(FileSourceParams::Filepath(_)) => false,
         SourceParams::File(FileSourceParams::Notifications(_)) => true,
         SourceParams::Ingest => true,
@@ -1052,6 +1053,7 @@ fn use_shard_api(params: &SourceParams) -> bool {
         SourceParams::Stdin => panic!("stdin cannot be checkpointed"),
         SourceParams::Vec(_) => false,
         SourceParams::Void(_) => false,
+        SourceParams::Webhook(_) => false,
     }
 }
 
@@ -24,28 +24,31 @@ use quickwit_common::pretty::PrettySample;
 use quickwit_common::uri::Uri;
 use quickwit_common::{ServiceStream, get_bool_from_env, rate_limited_error};
 use quickwit_config::{
-    IndexTemplate, IndexTemplateId, PostgresMetastoreConfig, validate_index_id_pattern,
+    IndexTemplate, IndexTemplateId, PostgresMetastoreConfig, SearchTemplate,
+    validate_index_id_pattern,
 };
 use quickwit_proto::ingest::{Shard, ShardState};
 use quickwit_proto::metastore::{
     AcquireShardsRequest, AcquireShardsResponse, AddSourceRequest, CreateIndexRequest,
-    CreateIndexResponse, CreateIndexTemplateRequest, DeleteIndexRequest,
-    DeleteIndexTemplatesRequest, DeleteQuery, DeleteShardsRequest, DeleteShardsResponse,
-    DeleteSourceRequest, DeleteSplitsRequest, DeleteTask, EmptyResponse, EntityKind,
-    FindIndexTemplateMatchesRequest, FindIndexTemplateMatchesResponse, GetClusterIdentityRequest,
-    GetClusterIdentityResponse, GetIndexTemplateRequest, GetIndexTemplateResponse,
-    IndexMetadataFailure, IndexMetadataFailureReason, IndexMetadataRequest, IndexMetadataResponse,
-    IndexStats, IndexTemplateMatch, IndexesMetadataRequest, IndexesMetadataResponse,
-    LastDeleteOpstampRequest, LastDeleteOpstampResponse, ListDeleteTasksRequest,
-    ListDeleteTasksResponse, ListIndexStatsRequest, ListIndexStatsResponse,
-    ListIndexTemplatesRequest, ListIndexTemplatesResponse, ListIndexesMetadataRequest,
-    ListIndexesMetadataResponse, ListShardsRequest, ListShardsResponse, ListShardsSubresponse,
-    ListSplitsRequest, ListSplitsResponse, ListStaleSplitsRequest, MarkSplitsForDeletionRequest,
-    MetastoreError, MetastoreResult, MetastoreService, MetastoreServiceStream, OpenShardSubrequest,
-    OpenShardSubresponse, OpenShardsRequest, OpenShardsResponse, PruneShardsRequest,
-    PublishSplitsRequest, ResetSourceCheckpointRequest, SplitStats, StageSplitsRequest,
-    ToggleSourceRequest, UpdateIndexRequest, UpdateSourceRequest, UpdateSplitsDeleteOpstampRequest,
-    UpdateSplitsDeleteOpstampResponse, serde_utils,
+    CreateIndexResponse, CreateIndexTemplateRequest, CreateSearchTemplateRequest,
+    DeleteIndexRequest, DeleteIndexTemplatesRequest, DeleteQuery, DeleteSearchTemplatesRequest,
+    DeleteShardsRequest, DeleteShardsResponse, DeleteSourceRequest, DeleteSplitsRequest,
+    DeleteTask, EmptyResponse, EntityKind, FindIndexTemplateMatchesRequest,
+    FindIndexTemplateMatchesResponse, GetClusterIdentityRequest, GetClusterIdentityResponse,
+    GetIndexTemplateRequest, GetIndexTemplateResponse, GetSearchTemplateRequest,
+    GetSearchTemplateResponse, IndexMetadataFailure, IndexMetadataFailureReason,
+    IndexMetadataRequest, IndexMetadataResponse, IndexStats, IndexTemplateMatch,
+    IndexesMetadataRequest, IndexesMetadataResponse, LastDeleteOpstampRequest,
+    LastDeleteOpstampResponse, ListDeleteTasksRequest, ListDeleteTasksResponse,
+    ListIndexStatsRequest, ListIndexStatsResponse, ListIndexTemplatesRequest,
+    ListIndexTemplatesResponse, ListIndexesMetadataRequest, ListIndexesMetadataResponse,
+    ListSearchTemplatesRequest, ListSearchTemplatesResponse, ListShardsRequest, ListShardsResponse,
+    ListShardsSubresponse, ListSplitsRequest, ListSplitsResponse, ListStaleSplitsRequest,
+    MarkSplitsForDeletionRequest, MetastoreError, MetastoreResult, MetastoreService,
+    MetastoreServiceStream, OpenShardSubrequest, OpenShardSubresponse, OpenShardsRequest,
+    OpenShardsResponse, PruneShardsRequest, PublishSplitsRequest, ResetSourceCheckpointRequest,
+    SplitStats, StageSplitsRequest, ToggleSourceRequest, UpdateIndexRequest, UpdateSourceRequest,
+    UpdateSplitsDeleteOpstampRequest, UpdateSplitsDeleteOpstampResponse, serde_utils,
 };
 use quickwit_proto::types::{IndexId, IndexUid, Position, PublishToken, ShardId, SourceId};
 use sea_query::{Alias, Asterisk, Expr, Func, PostgresQueryBuilder, Query, UnionType};
@@ -1744,6 +1747,99 @@ impl MetastoreService for PostgresqlMetastore {
         Ok(EmptyResponse {})
     }
 
+    // Search Template API
+
+    async fn create_search_template(
+        &self,
+        request: CreateSearchTemplateRequest,
+    ) -> MetastoreResult<EmptyResponse> {
+        const INSERT_SEARCH_TEMPLATE_QUERY: &str =
+            include_str!("queries/search_templates/insert.sql");
+        const UPSERT_SEARCH_TEMPLATE_QUERY: &str =
+            include_str!("queries/search_templates/upsert.sql");
+
+        let search_template: SearchTemplate =
+            serde_utils::from_json_str(&request.search_template_json)?;
+
+        search_template
+            .validate()
+            .map_err(|error| MetastoreError::InvalidArgument {
+                message: format!(
+                    "invalid search template `{}`: `{error:#}`",
+                    search_template.template_id
+                ),
+            })?;
+
+        let query = if request.overwrite {
+            UPSERT_SEARCH_TEMPLATE_QUERY
+        } else {
+            INSERT_SEARCH_TEMPLATE_QUERY
+        };
+        let pg_query_result = sqlx::query(query)
+            .bind(&search_template.template_id)
+            .bind(&request.search_template_json)
+            .execute(&self.connection_pool)
+            .await?;
+
+        if pg_query_result.rows_affected() == 0 {
+            return Err(MetastoreError::AlreadyExists(EntityKind::SearchTemplate {
+                template_id: search_template.template_id,
+            }));
+        }
+        Ok(EmptyResponse {})
+    }
+
+    async fn get_search_template(
+        &self,
+        request: GetSearchTemplateRequest,
+    ) -> MetastoreResult<GetSearchTemplateResponse> {
+        let (search_template_json,): (String,) = sqlx::query_as(
+            "SELECT search_template_json FROM search_templates WHERE template_id = $1",
+        )
+        .bind(&request.template_id)
+        .fetch_optional(&self.connection_pool)
+        .await?
+        .ok_or({
+            MetastoreError::NotFound(EntityKind::SearchTemplate {
+                template_id: request.template_id,
+            })
+        })?;
+        let response = GetSearchTemplateResponse {
+            search_template_json,
+        };
+        Ok(response)
+    }
+
+    async fn list_search_templates(
+        &self,
+        _request: ListSearchTemplatesRequest,
+    ) -> MetastoreResult<ListSearchTemplatesResponse> {
+        let pg_search_templates_json: Vec<(String,)> = sqlx::query_as(
+            "SELECT search_template_json FROM search_templates ORDER BY template_id ASC",
+        )
+        .fetch_all(&self.connection_pool)
+        .await?;
+        let search_templates_json: Vec<String> = pg_search_templates_json
+            .into_iter()
+            .map(|(search_template_json,)| search_template_json)
+            .collect();
+        let response = ListSearchTemplatesResponse {
+            search_templates_json,
+        };
+        Ok(response)
+    }
+
+    async fn delete_search_templates(
+        &self,
+        request: DeleteSearchTemplatesRequest,
+    ) -> MetastoreResult<EmptyResponse> {
+        sqlx::query("DELETE FROM search_templates WHERE template_id = ANY($1)")
+            .bind(&request.template_ids)
+            .execute(&self.connection_pool)
+            .await?;
+        Ok(EmptyResponse {})
+    }
+
     async fn get_cluster_identity(
         &self,
         _: GetClusterIdentityRequest,
@@ -0,0 +1,4 @@
+INSERT INTO search_templates(template_id, search_template_json)
+    VALUES ($1, $2)
+ON CONFLICT (template_id)
+    DO NOTHING
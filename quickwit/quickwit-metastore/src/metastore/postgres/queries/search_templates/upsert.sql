@@ -0,0 +1,5 @@
+INSERT INTO search_templates(template_id, search_template_json)
+    VALUES ($1, $2)
+ON CONFLICT (template_id)
+    DO UPDATE SET
+        search_template_json = $2
@@ -216,6 +216,7 @@ pub async fn test_metastore_update_search_settings<
     ] {
         let search_settings = SearchSettings {
             default_search_fields: default_search_fields.clone(),
+            ..Default::default()
         };
         let index_update = UpdateIndexRequest::try_from_updates(
             index_uid.clone(),
@@ -323,6 +324,7 @@ pub async fn test_metastore_update_doc_mapping<
         indexing_options: None,
         expand_dots: false,
         fast: Default::default(),
+        fields: Vec::new(),
     };
 
     let initial = index_config.doc_mapping.clone();
@@ -29,6 +29,7 @@ pub(crate) mod delete_task;
 pub(crate) mod get_identity;
 pub(crate) mod index;
 pub(crate) mod list_splits;
+pub(crate) mod search_template;
 pub(crate) mod shard;
 pub(crate) mod source;
 pub(crate) mod split;
@@ -569,6 +570,32 @@ macro_rules! metastore_test_suite {
                 $crate::tests::template::test_metastore_delete_index_templates::<$metastore_type>().await;
             }
 
+            /// Search Template API tests
+
+            #[tokio::test]
+            #[serial_test::file_serial]
+            async fn test_metastore_create_search_template() {
+                $crate::tests::search_template::test_metastore_create_search_template::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            #[serial_test::file_serial]
+            async fn test_metastore_get_search_template() {
+                $crate::tests::search_template::test_metastore_get_search_template::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            #[serial_test::file_serial]
+            async fn test_metastore_list_search_templates() {
+                $crate::tests::search_template::test_metastore_list_search_templates::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            #[serial_test::file_serial]
+            async fn test_metastore_delete_search_templates() {
+                $crate::tests::search_template::test_metastore_delete_search_templates::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             #[serial_test::file_serial]
             async fn test_metastore_get_identity() {
@@ -0,0 +1,200 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use quickwit_common::rand::append_random_suffix;
+use quickwit_config::SearchTemplate;
+use quickwit_proto::metastore::{
+    CreateSearchTemplateRequest, DeleteSearchTemplatesRequest, EntityKind,
+    GetSearchTemplateRequest, ListSearchTemplatesRequest, MetastoreError, MetastoreResult,
+    MetastoreService, serde_utils,
+};
+
+use super::DefaultForTest;
+use crate::MetastoreServiceExt;
+
+async fn list_all_search_templates(
+    metastore: &mut dyn MetastoreService,
+) -> MetastoreResult<Vec<SearchTemplate>> {
+    let list_search_templates_request = ListSearchTemplatesRequest {};
+    let list_search_templates_response = metastore
+        .list_search_templates(list_search_templates_request)
+        .await?;
+    list_search_templates_response
+        .search_templates_json
+        .into_iter()
+        .map(|search_template_json| serde_utils::from_json_str(&search_template_json))
+        .collect()
+}
+
+async fn cleanup_search_templates(metastore: &mut dyn MetastoreService) {
+    let template_ids = list_all_search_templates(metastore)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|search_template| search_template.template_id)
+        .collect::<Vec<_>>();
+
+    let delete_search_templates_request = DeleteSearchTemplatesRequest { template_ids };
+    metastore
+        .delete_search_templates(delete_search_templates_request)
+        .await
+        .unwrap();
+}
+
+async fn create_search_template(
+    metastore: &mut dyn MetastoreService,
+    search_template: &SearchTemplate,
+    overwrite: bool,
+) -> MetastoreResult<()> {
+    let search_template_json = serde_json::to_string(search_template).unwrap();
+    let create_search_template_request = CreateSearchTemplateRequest {
+        search_template_json,
+        overwrite,
+    };
+    metastore
+        .create_search_template(create_search_template_request)
+        .await?;
+    Ok(())
+}
+
+pub async fn test_metastore_create_search_template<
+    MetastoreUnderTest: MetastoreService + MetastoreServiceExt + DefaultForTest,
+>() {
+    let mut metastore = MetastoreUnderTest::default_for_test().await;
+    cleanup_search_templates(&mut metastore).await;
+
+    let template_id = append_random_suffix("test-create-search-template");
+    let mut search_template = SearchTemplate::for_test(&template_id);
+
+    create_search_template(&mut metastore, &search_template, false)
+        .await
+        .unwrap();
+
+    let search_templates = list_all_search_templates(&mut metastore).await.unwrap();
+    assert_eq!(search_templates, [search_template.clone()]);
+
+    let error = create_search_template(&mut metastore, &search_template, false)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, MetastoreError::AlreadyExists(EntityKind::SearchTemplate { template_id }) if template_id.starts_with("test-create-search-template"))
+    );
+
+    search_template.source = r#"{"size": {{size}}}"#.to_string();
+    create_search_template(&mut metastore, &search_template, true)
+        .await
+        .unwrap();
+
+    let search_templates = list_all_search_templates(&mut metastore).await.unwrap();
+    assert_eq!(search_templates, [search_template.clone()]);
+
+    search_template.source = r#"{"size": {{#size}}}"#.to_string();
+    let error = create_search_template(&mut metastore, &search_template, true)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, MetastoreError::InvalidArgument { .. }));
+}
+
+pub async fn test_metastore_get_search_template<
+    MetastoreUnderTest: MetastoreService + MetastoreServiceExt + DefaultForTest,
+>() {
+    let mut metastore = MetastoreUnderTest::default_for_test().await;
+    cleanup_search_templates(&mut metastore).await;
+
+    let template_id = append_random_suffix("test-get-search-template");
+    let search_template = SearchTemplate::for_test(&template_id);
+
+    let get_search_template_request = GetSearchTemplateRequest {
+        template_id: template_id.clone(),
+    };
+    let error = metastore
+        .get_search_template(get_search_template_request.clone())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, MetastoreError::NotFound(EntityKind::SearchTemplate { template_id }) if template_id.starts_with("test-get-search-template"))
+    );
+
+    create_search_template(&mut metastore, &search_template, false)
+        .await
+        .unwrap();
+
+    let get_search_template_response = metastore
+        .get_search_template(get_search_template_request)
+        .await
+        .unwrap();
+    let fetched_search_template: SearchTemplate =
+        serde_utils::from_json_str(&get_search_template_response.search_template_json).unwrap();
+    assert_eq!(fetched_search_template, search_template);
+}
+
+pub async fn test_metastore_list_search_templates<
+    MetastoreUnderTest: MetastoreService + MetastoreServiceExt + DefaultForTest,
+>() {
+    let mut metastore = MetastoreUnderTest::default_for_test().await;
+    cleanup_search_templates(&mut metastore).await;
+
+    let search_templates = list_all_search_templates(&mut metastore).await.unwrap();
+    assert!(search_templates.is_empty());
+
+    let bar_search_template =
+        SearchTemplate::for_test(&append_random_suffix("test-search-template-bar"));
+    create_search_template(&mut metastore, &bar_search_template, false)
+        .await
+        .unwrap();
+
+    let foo_search_template =
+        SearchTemplate::for_test(&append_random_suffix("test-search-template-foo"));
+    create_search_template(&mut metastore, &foo_search_template, false)
+        .await
+        .unwrap();
+
+    let search_templates = list_all_search_templates(&mut metastore).await.unwrap();
+    assert_eq!(search_templates, [bar_search_template, foo_search_template]);
+}
+
+pub async fn test_metastore_delete_search_templates<
+    MetastoreUnderTest: MetastoreService + MetastoreServiceExt + DefaultForTest,
+>() {
+    let mut metastore = MetastoreUnderTest::default_for_test().await;
+    cleanup_search_templates(&mut metastore).await;
+
+    let foo_template_id = append_random_suffix("test-search-template-foo");
+    let bar_template_id = append_random_suffix("test-search-template-bar");
+    let qux_template_id = append_random_suffix("test-search-template-qux");
+
+    for template_id in [&foo_template_id, &bar_template_id, &qux_template_id] {
+        let search_template = SearchTemplate::for_test(template_id);
+        create_search_template(&mut metastore, &search_template, false)
+            .await
+            .unwrap();
+    }
+    let delete_search_templates_request = DeleteSearchTemplatesRequest {
+        template_ids: vec![foo_template_id, bar_template_id],
+    };
+    metastore
+        .delete_search_templates(delete_search_templates_request.clone())
+        .await
+        .unwrap();
+
+    // Test idempotency.
+    metastore
+        .delete_search_templates(delete_search_templates_request)
+        .await
+        .unwrap();
+
+    let search_templates = list_all_search_templates(&mut metastore).await.unwrap();
+    assert_eq!(search_templates.len(), 1);
+    assert_eq!(search_templates[0].template_id, qux_template_id);
+}
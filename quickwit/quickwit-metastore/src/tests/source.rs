@@ -58,6 +58,10 @@ pub async fn test_metastore_add_source<MetastoreToTest: MetastoreServiceExt + De
         source_params: SourceParams::void(),
         transform_config: None,
         input_format: SourceInputFormat::Json,
+        dead_letter_config: None,
+        schema_config: None,
+        line_format_config: None,
+        index_routing_config: None,
     };
 
     assert_eq!(
@@ -158,6 +162,10 @@ pub async fn test_metastore_update_source<MetastoreToTest: MetastoreServiceExt +
         source_params: SourceParams::void(),
         transform_config: None,
         input_format: SourceInputFormat::Json,
+        dead_letter_config: None,
+        schema_config: None,
+        line_format_config: None,
+        index_routing_config: None,
     };
 
     assert_eq!(
@@ -263,6 +271,10 @@ pub async fn test_metastore_toggle_source<MetastoreToTest: MetastoreServiceExt +
         source_params: SourceParams::void(),
         transform_config: None,
         input_format: SourceInputFormat::Json,
+        dead_letter_config: None,
+        schema_config: None,
+        line_format_config: None,
+        index_routing_config: None,
     };
     let add_source_request =
         AddSourceRequest::try_from_source_config(index_uid.clone(), &source).unwrap();
@@ -329,6 +341,10 @@ pub async fn test_metastore_delete_source<MetastoreToTest: MetastoreServiceExt +
         source_params: SourceParams::void(),
         transform_config: None,
         input_format: SourceInputFormat::Json,
+        dead_letter_config: None,
+        schema_config: None,
+        line_format_config: None,
+        index_routing_config: None,
     };
 
     let index_config = IndexConfig::for_test(&index_id, index_uri.as_str());
@@ -450,6 +466,10 @@ pub async fn test_metastore_reset_checkpoint<
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_config: None,
+            schema_config: None,
+            line_format_config: None,
+            index_routing_config: None,
         };
         metastore
             .add_source(
@@ -524,6 +524,41 @@ pub struct DeleteIndexTemplatesRequest {
     pub template_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct CreateSearchTemplateRequest {
+    #[prost(string, tag = "1")]
+    pub search_template_json: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub overwrite: bool,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct GetSearchTemplateRequest {
+    #[prost(string, tag = "1")]
+    pub template_id: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct GetSearchTemplateResponse {
+    #[prost(string, tag = "1")]
+    pub search_template_json: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ListSearchTemplatesRequest {}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct ListSearchTemplatesResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub search_templates_json: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct DeleteSearchTemplatesRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub template_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[derive(Clone, Copy, PartialEq, Eq, Hash, ::prost::Message)]
 pub struct GetClusterIdentityRequest {}
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
@@ -554,6 +589,10 @@ pub enum SourceType {
     Vec = 10,
     Void = 11,
     Stdin = 13,
+    /// Local directory tailed like a log shipper
+    Directory = 14,
+    /// HTTP endpoint receiving documents pushed by webhooks
+    Webhook = 15,
 }
 impl SourceType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -575,6 +614,8 @@ impl SourceType {
             Self::Vec => "SOURCE_TYPE_VEC",
             Self::Void => "SOURCE_TYPE_VOID",
             Self::Stdin => "SOURCE_TYPE_STDIN",
+            Self::Directory => "SOURCE_TYPE_DIRECTORY",
+            Self::Webhook => "SOURCE_TYPE_WEBHOOK",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -593,6 +634,8 @@ impl SourceType {
             "SOURCE_TYPE_VEC" => Some(Self::Vec),
             "SOURCE_TYPE_VOID" => Some(Self::Void),
             "SOURCE_TYPE_STDIN" => Some(Self::Stdin),
+            "SOURCE_TYPE_DIRECTORY" => Some(Self::Directory),
+            "SOURCE_TYPE_WEBHOOK" => Some(Self::Webhook),
             _ => None,
         }
     }
@@ -793,6 +836,26 @@ impl RpcName for DeleteIndexTemplatesRequest {
         "delete_index_templates"
     }
 }
+impl RpcName for CreateSearchTemplateRequest {
+    fn rpc_name() -> &'static str {
+        "create_search_template"
+    }
+}
+impl RpcName for GetSearchTemplateRequest {
+    fn rpc_name() -> &'static str {
+        "get_search_template"
+    }
+}
+impl RpcName for ListSearchTemplatesRequest {
+    fn rpc_name() -> &'static str {
+        "list_search_templates"
+    }
+}
+impl RpcName for DeleteSearchTemplatesRequest {
+    fn rpc_name() -> &'static str {
+        "delete_search_templates"
+    }
+}
 impl RpcName for GetClusterIdentityRequest {
     fn rpc_name() -> &'static str {
         "get_cluster_identity"
@@ -980,6 +1043,26 @@ pub trait MetastoreService: std::fmt::Debug + Send + Sync + 'static {
         &self,
         request: DeleteIndexTemplatesRequest,
     ) -> crate::metastore::MetastoreResult<EmptyResponse>;
+    ///Creates a search template.
+    async fn create_search_template(
+        &self,
+        request: CreateSearchTemplateRequest,
+    ) -> crate::metastore::MetastoreResult<EmptyResponse>;
+    ///Fetches a search template.
+    async fn get_search_template(
+        &self,
+        request: GetSearchTemplateRequest,
+    ) -> crate::metastore::MetastoreResult<GetSearchTemplateResponse>;
+    ///Returns all the search templates.
+    async fn list_search_templates(
+        &self,
+        request: ListSearchTemplatesRequest,
+    ) -> crate::metastore::MetastoreResult<ListSearchTemplatesResponse>;
+    ///Deletes search templates.
+    async fn delete_search_templates(
+        &self,
+        request: DeleteSearchTemplatesRequest,
+    ) -> crate::metastore::MetastoreResult<EmptyResponse>;
     ///Get cluster identity
     async fn get_cluster_identity(
         &self,
@@ -1287,6 +1370,30 @@ impl MetastoreService for MetastoreServiceClient {
     ) -> crate::metastore::MetastoreResult<EmptyResponse> {
         self.inner.0.delete_index_templates(request).await
     }
+    async fn create_search_template(
+        &self,
+        request: CreateSearchTemplateRequest,
+    ) -> crate::metastore::MetastoreResult<EmptyResponse> {
+        self.inner.0.create_search_template(request).await
+    }
+    async fn get_search_template(
+        &self,
+        request: GetSearchTemplateRequest,
+    ) -> crate::metastore::MetastoreResult<GetSearchTemplateResponse> {
+        self.inner.0.get_search_template(request).await
+    }
+    async fn list_search_templates(
+        &self,
+        request: ListSearchTemplatesRequest,
+    ) -> crate::metastore::MetastoreResult<ListSearchTemplatesResponse> {
+        self.inner.0.list_search_templates(request).await
+    }
+    async fn delete_search_templates(
+        &self,
+        request: DeleteSearchTemplatesRequest,
+    ) -> crate::metastore::MetastoreResult<EmptyResponse> {
+        self.inner.0.delete_search_templates(request).await
+    }
     async fn get_cluster_identity(
         &self,
         request: GetClusterIdentityRequest,
@@ -1505,6 +1612,30 @@ pub mod mock_metastore_service {
         ) -> crate::metastore::MetastoreResult<super::EmptyResponse> {
             self.inner.lock().await.delete_index_templates(request).await
         }
+        async fn create_search_template(
+            &self,
+            request: super::CreateSearchTemplateRequest,
+        ) -> crate::metastore::MetastoreResult<super::EmptyResponse> {
+            self.inner.lock().await.create_search_template(request).await
+        }
+        async fn get_search_template(
+            &self,
+            request: super::GetSearchTemplateRequest,
+        ) -> crate::metastore::MetastoreResult<super::GetSearchTemplateResponse> {
+            self.inner.lock().await.get_search_template(request).await
+        }
+        async fn list_search_templates(
+            &self,
+            request: super::ListSearchTemplatesRequest,
+        ) -> crate::metastore::MetastoreResult<super::ListSearchTemplatesResponse> {
+            self.inner.lock().await.list_search_templates(request).await
+        }
+        async fn delete_search_templates(
+            &self,
+            request: super::DeleteSearchTemplatesRequest,
+        ) -> crate::metastore::MetastoreResult<super::EmptyResponse> {
+            self.inner.lock().await.delete_search_templates(request).await
+        }
         async fn get_cluster_identity(
             &self,
             request: super::GetClusterIdentityRequest,
@@ -2034,6 +2165,70 @@ impl tower::Service<DeleteIndexTemplatesRequest> for InnerMetastoreServiceClient
         Box::pin(fut)
     }
 }
+impl tower::Service<CreateSearchTemplateRequest> for InnerMetastoreServiceClient {
+    type Response = EmptyResponse;
+    type Error = crate::metastore::MetastoreError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: CreateSearchTemplateRequest) -> Self::Future {
+        let svc = self.clone();
+        let fut = async move { svc.0.create_search_template(request).await };
+        Box::pin(fut)
+    }
+}
+impl tower::Service<GetSearchTemplateRequest> for InnerMetastoreServiceClient {
+    type Response = GetSearchTemplateResponse;
+    type Error = crate::metastore::MetastoreError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: GetSearchTemplateRequest) -> Self::Future {
+        let svc = self.clone();
+        let fut = async move { svc.0.get_search_template(request).await };
+        Box::pin(fut)
+    }
+}
+impl tower::Service<ListSearchTemplatesRequest> for InnerMetastoreServiceClient {
+    type Response = ListSearchTemplatesResponse;
+    type Error = crate::metastore::MetastoreError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: ListSearchTemplatesRequest) -> Self::Future {
+        let svc = self.clone();
+        let fut = async move { svc.0.list_search_templates(request).await };
+        Box::pin(fut)
+    }
+}
+impl tower::Service<DeleteSearchTemplatesRequest> for InnerMetastoreServiceClient {
+    type Response = EmptyResponse;
+    type Error = crate::metastore::MetastoreError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: DeleteSearchTemplatesRequest) -> Self::Future {
+        let svc = self.clone();
+        let fut = async move { svc.0.delete_search_templates(request).await };
+        Box::pin(fut)
+    }
+}
 impl tower::Service<GetClusterIdentityRequest> for InnerMetastoreServiceClient {
     type Response = GetClusterIdentityResponse;
     type Error = crate::metastore::MetastoreError;
@@ -2215,6 +2410,26 @@ struct MetastoreServiceTowerServiceStack {
         EmptyResponse,
         crate::metastore::MetastoreError,
     >,
+    create_search_template_svc: quickwit_common::tower::BoxService<
+        CreateSearchTemplateRequest,
+        EmptyResponse,
+        crate::metastore::MetastoreError,
+    >,
+    get_search_template_svc: quickwit_common::tower::BoxService<
+        GetSearchTemplateRequest,
+        GetSearchTemplateResponse,
+        crate::metastore::MetastoreError,
+    >,
+    list_search_templates_svc: quickwit_common::tower::BoxService<
+        ListSearchTemplatesRequest,
+        ListSearchTemplatesResponse,
+        crate::metastore::MetastoreError,
+    >,
+    delete_search_templates_svc: quickwit_common::tower::BoxService<
+        DeleteSearchTemplatesRequest,
+        EmptyResponse,
+        crate::metastore::MetastoreError,
+    >,
     get_cluster_identity_svc: quickwit_common::tower::BoxService<
         GetClusterIdentityRequest,
         GetClusterIdentityResponse,
@@ -2415,6 +2630,30 @@ impl MetastoreService for MetastoreServiceTowerServiceStack {
     ) -> crate::metastore::MetastoreResult<EmptyResponse> {
         self.delete_index_templates_svc.clone().ready().await?.call(request).await
     }
+    async fn create_search_template(
+        &self,
+        request: CreateSearchTemplateRequest,
+    ) -> crate::metastore::MetastoreResult<EmptyResponse> {
+        self.create_search_template_svc.clone().ready().await?.call(request).await
+    }
+    async fn get_search_template(
+        &self,
+        request: GetSearchTemplateRequest,
+    ) -> crate::metastore::MetastoreResult<GetSearchTemplateResponse> {
+        self.get_search_template_svc.clone().ready().await?.call(request).await
+    }
+    async fn list_search_templates(
+        &self,
+        request: ListSearchTemplatesRequest,
+    ) -> crate::metastore::MetastoreResult<ListSearchTemplatesResponse> {
+        self.list_search_templates_svc.clone().ready().await?.call(request).await
+    }
+    async fn delete_search_templates(
+        &self,
+        request: DeleteSearchTemplatesRequest,
+    ) -> crate::metastore::MetastoreResult<EmptyResponse> {
+        self.delete_search_templates_svc.clone().ready().await?.call(request).await
+    }
     async fn get_cluster_identity(
         &self,
         request: GetClusterIdentityRequest,
@@ -2748,6 +2987,46 @@ type DeleteIndexTemplatesLayer = quickwit_common::tower::BoxLayer<
     EmptyResponse,
     crate::metastore::MetastoreError,
 >;
+type CreateSearchTemplateLayer = quickwit_common::tower::BoxLayer<
+    quickwit_common::tower::BoxService<
+        CreateSearchTemplateRequest,
+        EmptyResponse,
+        crate::metastore::MetastoreError,
+    >,
+    CreateSearchTemplateRequest,
+    EmptyResponse,
+    crate::metastore::MetastoreError,
+>;
+type GetSearchTemplateLayer = quickwit_common::tower::BoxLayer<
+    quickwit_common::tower::BoxService<
+        GetSearchTemplateRequest,
+        GetSearchTemplateResponse,
+        crate::metastore::MetastoreError,
+    >,
+    GetSearchTemplateRequest,
+    GetSearchTemplateResponse,
+    crate::metastore::MetastoreError,
+>;
+type ListSearchTemplatesLayer = quickwit_common::tower::BoxLayer<
+    quickwit_common::tower::BoxService<
+        ListSearchTemplatesRequest,
+        ListSearchTemplatesResponse,
+        crate::metastore::MetastoreError,
+    >,
+    ListSearchTemplatesRequest,
+    ListSearchTemplatesResponse,
+    crate::metastore::MetastoreError,
+>;
+type DeleteSearchTemplatesLayer = quickwit_common::tower::BoxLayer<
+    quickwit_common::tower::BoxService<
+        DeleteSearchTemplatesRequest,
+        EmptyResponse,
+        crate::metastore::MetastoreError,
+    >,
+    DeleteSearchTemplatesRequest,
+    EmptyResponse,
+    crate::metastore::MetastoreError,
+>;
 type GetClusterIdentityLayer = quickwit_common::tower::BoxLayer<
     quickwit_common::tower::BoxService<
         GetClusterIdentityRequest,
@@ -2792,6 +3071,10 @@ pub struct MetastoreServiceTowerLayerStack {
     find_index_template_matches_layers: Vec<FindIndexTemplateMatchesLayer>,
     list_index_templates_layers: Vec<ListIndexTemplatesLayer>,
     delete_index_templates_layers: Vec<DeleteIndexTemplatesLayer>,
+    create_search_template_layers: Vec<CreateSearchTemplateLayer>,
+    get_search_template_layers: Vec<GetSearchTemplateLayer>,
+    list_search_templates_layers: Vec<ListSearchTemplatesLayer>,
+    delete_search_templates_layers: Vec<DeleteSearchTemplatesLayer>,
     get_cluster_identity_layers: Vec<GetClusterIdentityLayer>,
 }
 impl MetastoreServiceTowerLayerStack {
@@ -3613,6 +3896,112 @@ impl MetastoreServiceTowerLayerStack {
         >>::Service as tower::Service<
             DeleteIndexTemplatesRequest,
         >>::Future: Send + 'static,
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    CreateSearchTemplateRequest,
+                    EmptyResponse,
+                    crate::metastore::MetastoreError,
+                >,
+            > + Clone + Send + Sync + 'static,
+        <L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                CreateSearchTemplateRequest,
+                EmptyResponse,
+                crate::metastore::MetastoreError,
+            >,
+        >>::Service: tower::Service<
+                CreateSearchTemplateRequest,
+                Response = EmptyResponse,
+                Error = crate::metastore::MetastoreError,
+            > + Clone + Send + Sync + 'static,
+        <<L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                CreateSearchTemplateRequest,
+                EmptyResponse,
+                crate::metastore::MetastoreError,
+            >,
+        >>::Service as tower::Service<
+            CreateSearchTemplateRequest,
+        >>::Future: Send + 'static,
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    GetSearchTemplateRequest,
+                    GetSearchTemplateResponse,
+                    crate::metastore::MetastoreError,
+                >,
+            > + Clone + Send + Sync + 'static,
+        <L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                GetSearchTemplateRequest,
+                GetSearchTemplateResponse,
+                crate::metastore::MetastoreError,
+            >,
+        >>::Service: tower::Service<
+                GetSearchTemplateRequest,
+                Response = GetSearchTemplateResponse,
+                Error = crate::metastore::MetastoreError,
+            > + Clone + Send + Sync + 'static,
+        <<L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                GetSearchTemplateRequest,
+                GetSearchTemplateResponse,
+                crate::metastore::MetastoreError,
+            >,
+        >>::Service as tower::Service<GetSearchTemplateRequest>>::Future: Send + 'static,
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    ListSearchTemplatesRequest,
+                    ListSearchTemplatesResponse,
+                    crate::metastore::MetastoreError,
+                >,
+            > + Clone + Send + Sync + 'static,
+        <L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                ListSearchTemplatesRequest,
+                ListSearchTemplatesResponse,
+                crate::metastore::MetastoreError,
+            >,
+        >>::Service: tower::Service<
+                ListSearchTemplatesRequest,
+                Response = ListSearchTemplatesResponse,
+                Error = crate::metastore::MetastoreError,
+            > + Clone + Send + Sync + 'static,
+        <<L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                ListSearchTemplatesRequest,
+                ListSearchTemplatesResponse,
+                crate::metastore::MetastoreError,
+            >,
+        >>::Service as tower::Service<
+            ListSearchTemplatesRequest,
+        >>::Future: Send + 'static,
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    DeleteSearchTemplatesRequest,
+                    EmptyResponse,
+                    crate::metastore::MetastoreError,
+                >,
+            > + Clone + Send + Sync + 'static,
+        <L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                DeleteSearchTemplatesRequest,
+                EmptyResponse,
+                crate::metastore::MetastoreError,
+            >,
+        >>::Service: tower::Service<
+                DeleteSearchTemplatesRequest,
+                Response = EmptyResponse,
+                Error = crate::metastore::MetastoreError,
+            > + Clone + Send + Sync + 'static,
+        <<L as tower::Layer<
+            quickwit_common::tower::BoxService<
+                DeleteSearchTemplatesRequest,
+                EmptyResponse,
+                crate::metastore::MetastoreError,
+            >,
+        >>::Service as tower::Service<
+            DeleteSearchTemplatesRequest,
+        >>::Future: Send + 'static,
         L: tower::Layer<
                 quickwit_common::tower::BoxService<
                     GetClusterIdentityRequest,
@@ -3705,6 +4094,14 @@ impl MetastoreServiceTowerLayerStack {
             .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self.delete_index_templates_layers
             .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
+        self.create_search_template_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
+        self.get_search_template_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
+        self.list_search_templates_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
+        self.delete_search_templates_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self.get_cluster_identity_layers
             .push(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self
@@ -4344,36 +4741,122 @@ impl MetastoreServiceTowerLayerStack {
             .push(quickwit_common::tower::BoxLayer::new(layer));
         self
     }
-    pub fn stack_get_cluster_identity_layer<L>(mut self, layer: L) -> Self
+    pub fn stack_create_search_template_layer<L>(mut self, layer: L) -> Self
     where
         L: tower::Layer<
                 quickwit_common::tower::BoxService<
-                    GetClusterIdentityRequest,
-                    GetClusterIdentityResponse,
+                    CreateSearchTemplateRequest,
+                    EmptyResponse,
                     crate::metastore::MetastoreError,
                 >,
             > + Send + Sync + 'static,
         L::Service: tower::Service<
-                GetClusterIdentityRequest,
-                Response = GetClusterIdentityResponse,
+                CreateSearchTemplateRequest,
+                Response = EmptyResponse,
                 Error = crate::metastore::MetastoreError,
             > + Clone + Send + Sync + 'static,
         <L::Service as tower::Service<
-            GetClusterIdentityRequest,
+            CreateSearchTemplateRequest,
         >>::Future: Send + 'static,
     {
-        self.get_cluster_identity_layers
+        self.create_search_template_layers
             .push(quickwit_common::tower::BoxLayer::new(layer));
         self
     }
-    pub fn build<T>(self, instance: T) -> MetastoreServiceClient
+    pub fn stack_get_search_template_layer<L>(mut self, layer: L) -> Self
     where
-        T: MetastoreService,
-    {
-        let inner_client = InnerMetastoreServiceClient(std::sync::Arc::new(instance));
-        self.build_from_inner_client(inner_client)
-    }
-    pub fn build_from_channel(
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    GetSearchTemplateRequest,
+                    GetSearchTemplateResponse,
+                    crate::metastore::MetastoreError,
+                >,
+            > + Send + Sync + 'static,
+        L::Service: tower::Service<
+                GetSearchTemplateRequest,
+                Response = GetSearchTemplateResponse,
+                Error = crate::metastore::MetastoreError,
+            > + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<GetSearchTemplateRequest>>::Future: Send + 'static,
+    {
+        self.get_search_template_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer));
+        self
+    }
+    pub fn stack_list_search_templates_layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    ListSearchTemplatesRequest,
+                    ListSearchTemplatesResponse,
+                    crate::metastore::MetastoreError,
+                >,
+            > + Send + Sync + 'static,
+        L::Service: tower::Service<
+                ListSearchTemplatesRequest,
+                Response = ListSearchTemplatesResponse,
+                Error = crate::metastore::MetastoreError,
+            > + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<
+            ListSearchTemplatesRequest,
+        >>::Future: Send + 'static,
+    {
+        self.list_search_templates_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer));
+        self
+    }
+    pub fn stack_delete_search_templates_layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    DeleteSearchTemplatesRequest,
+                    EmptyResponse,
+                    crate::metastore::MetastoreError,
+                >,
+            > + Send + Sync + 'static,
+        L::Service: tower::Service<
+                DeleteSearchTemplatesRequest,
+                Response = EmptyResponse,
+                Error = crate::metastore::MetastoreError,
+            > + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<
+            DeleteSearchTemplatesRequest,
+        >>::Future: Send + 'static,
+    {
+        self.delete_search_templates_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer));
+        self
+    }
+    pub fn stack_get_cluster_identity_layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<
+                quickwit_common::tower::BoxService<
+                    GetClusterIdentityRequest,
+                    GetClusterIdentityResponse,
+                    crate::metastore::MetastoreError,
+                >,
+            > + Send + Sync + 'static,
+        L::Service: tower::Service<
+                GetClusterIdentityRequest,
+                Response = GetClusterIdentityResponse,
+                Error = crate::metastore::MetastoreError,
+            > + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<
+            GetClusterIdentityRequest,
+        >>::Future: Send + 'static,
+    {
+        self.get_cluster_identity_layers
+            .push(quickwit_common::tower::BoxLayer::new(layer));
+        self
+    }
+    pub fn build<T>(self, instance: T) -> MetastoreServiceClient
+    where
+        T: MetastoreService,
+    {
+        let inner_client = InnerMetastoreServiceClient(std::sync::Arc::new(instance));
+        self.build_from_inner_client(inner_client)
+    }
+    pub fn build_from_channel(
         self,
         addr: std::net::SocketAddr,
         channel: tonic::transport::Channel,
@@ -4682,6 +5165,38 @@ impl MetastoreServiceTowerLayerStack {
                 quickwit_common::tower::BoxService::new(inner_client.clone()),
                 |svc, layer| layer.layer(svc),
             );
+        let create_search_template_svc = self
+            .create_search_template_layers
+            .into_iter()
+            .rev()
+            .fold(
+                quickwit_common::tower::BoxService::new(inner_client.clone()),
+                |svc, layer| layer.layer(svc),
+            );
+        let get_search_template_svc = self
+            .get_search_template_layers
+            .into_iter()
+            .rev()
+            .fold(
+                quickwit_common::tower::BoxService::new(inner_client.clone()),
+                |svc, layer| layer.layer(svc),
+            );
+        let list_search_templates_svc = self
+            .list_search_templates_layers
+            .into_iter()
+            .rev()
+            .fold(
+                quickwit_common::tower::BoxService::new(inner_client.clone()),
+                |svc, layer| layer.layer(svc),
+            );
+        let delete_search_templates_svc = self
+            .delete_search_templates_layers
+            .into_iter()
+            .rev()
+            .fold(
+                quickwit_common::tower::BoxService::new(inner_client.clone()),
+                |svc, layer| layer.layer(svc),
+            );
         let get_cluster_identity_svc = self
             .get_cluster_identity_layers
             .into_iter()
@@ -4724,6 +5239,10 @@ impl MetastoreServiceTowerLayerStack {
             find_index_template_matches_svc,
             list_index_templates_svc,
             delete_index_templates_svc,
+            create_search_template_svc,
+            get_search_template_svc,
+            list_search_templates_svc,
+            delete_search_templates_svc,
             get_cluster_identity_svc,
         };
         MetastoreServiceClient::new(tower_svc_stack)
@@ -5014,6 +5533,36 @@ where
             Error = crate::metastore::MetastoreError,
             Future = BoxFuture<EmptyResponse, crate::metastore::MetastoreError>,
         >
+        + tower::Service<
+            CreateSearchTemplateRequest,
+            Response = EmptyResponse,
+            Error = crate::metastore::MetastoreError,
+            Future = BoxFuture<EmptyResponse, crate::metastore::MetastoreError>,
+        >
+        + tower::Service<
+            GetSearchTemplateRequest,
+            Response = GetSearchTemplateResponse,
+            Error = crate::metastore::MetastoreError,
+            Future = BoxFuture<
+                GetSearchTemplateResponse,
+                crate::metastore::MetastoreError,
+            >,
+        >
+        + tower::Service<
+            ListSearchTemplatesRequest,
+            Response = ListSearchTemplatesResponse,
+            Error = crate::metastore::MetastoreError,
+            Future = BoxFuture<
+                ListSearchTemplatesResponse,
+                crate::metastore::MetastoreError,
+            >,
+        >
+        + tower::Service<
+            DeleteSearchTemplatesRequest,
+            Response = EmptyResponse,
+            Error = crate::metastore::MetastoreError,
+            Future = BoxFuture<EmptyResponse, crate::metastore::MetastoreError>,
+        >
         + tower::Service<
             GetClusterIdentityRequest,
             Response = GetClusterIdentityResponse,
@@ -5216,6 +5765,30 @@ where
     ) -> crate::metastore::MetastoreResult<EmptyResponse> {
         self.clone().call(request).await
     }
+    async fn create_search_template(
+        &self,
+        request: CreateSearchTemplateRequest,
+    ) -> crate::metastore::MetastoreResult<EmptyResponse> {
+        self.clone().call(request).await
+    }
+    async fn get_search_template(
+        &self,
+        request: GetSearchTemplateRequest,
+    ) -> crate::metastore::MetastoreResult<GetSearchTemplateResponse> {
+        self.clone().call(request).await
+    }
+    async fn list_search_templates(
+        &self,
+        request: ListSearchTemplatesRequest,
+    ) -> crate::metastore::MetastoreResult<ListSearchTemplatesResponse> {
+        self.clone().call(request).await
+    }
+    async fn delete_search_templates(
+        &self,
+        request: DeleteSearchTemplatesRequest,
+    ) -> crate::metastore::MetastoreResult<EmptyResponse> {
+        self.clone().call(request).await
+    }
     async fn get_cluster_identity(
         &self,
         request: GetClusterIdentityRequest,
@@ -5725,6 +6298,62 @@ where
                 DeleteIndexTemplatesRequest::rpc_name(),
             ))
     }
+    async fn create_search_template(
+        &self,
+        request: CreateSearchTemplateRequest,
+    ) -> crate::metastore::MetastoreResult<EmptyResponse> {
+        self.inner
+            .clone()
+            .create_search_template(request)
+            .await
+            .map(|response| response.into_inner())
+            .map_err(|status| crate::error::grpc_status_to_service_error(
+                status,
+                CreateSearchTemplateRequest::rpc_name(),
+            ))
+    }
+    async fn get_search_template(
+        &self,
+        request: GetSearchTemplateRequest,
+    ) -> crate::metastore::MetastoreResult<GetSearchTemplateResponse> {
+        self.inner
+            .clone()
+            .get_search_template(request)
+            .await
+            .map(|response| response.into_inner())
+            .map_err(|status| crate::error::grpc_status_to_service_error(
+                status,
+                GetSearchTemplateRequest::rpc_name(),
+            ))
+    }
+    async fn list_search_templates(
+        &self,
+        request: ListSearchTemplatesRequest,
+    ) -> crate::metastore::MetastoreResult<ListSearchTemplatesResponse> {
+        self.inner
+            .clone()
+            .list_search_templates(request)
+            .await
+            .map(|response| response.into_inner())
+            .map_err(|status| crate::error::grpc_status_to_service_error(
+                status,
+                ListSearchTemplatesRequest::rpc_name(),
+            ))
+    }
+    async fn delete_search_templates(
+        &self,
+        request: DeleteSearchTemplatesRequest,
+    ) -> crate::metastore::MetastoreResult<EmptyResponse> {
+        self.inner
+            .clone()
+            .delete_search_templates(request)
+            .await
+            .map(|response| response.into_inner())
+            .map_err(|status| crate::error::grpc_status_to_service_error(
+                status,
+                DeleteSearchTemplatesRequest::rpc_name(),
+            ))
+    }
     async fn get_cluster_identity(
         &self,
         request: GetClusterIdentityRequest,
@@ -6129,6 +6758,50 @@ for MetastoreServiceGrpcServerAdapter {
             .map(tonic::Response::new)
             .map_err(crate::error::grpc_error_to_grpc_status)
     }
+    async fn create_search_template(
+        &self,
+        request: tonic::Request<CreateSearchTemplateRequest>,
+    ) -> Result<tonic::Response<EmptyResponse>, tonic::Status> {
+        self.inner
+            .0
+            .create_search_template(request.into_inner())
+            .await
+            .map(tonic::Response::new)
+            .map_err(crate::error::grpc_error_to_grpc_status)
+    }
+    async fn get_search_template(
+        &self,
+        request: tonic::Request<GetSearchTemplateRequest>,
+    ) -> Result<tonic::Response<GetSearchTemplateResponse>, tonic::Status> {
+        self.inner
+            .0
+            .get_search_template(request.into_inner())
+            .await
+            .map(tonic::Response::new)
+            .map_err(crate::error::grpc_error_to_grpc_status)
+    }
+    async fn list_search_templates(
+        &self,
+        request: tonic::Request<ListSearchTemplatesRequest>,
+    ) -> Result<tonic::Response<ListSearchTemplatesResponse>, tonic::Status> {
+        self.inner
+            .0
+            .list_search_templates(request.into_inner())
+            .await
+            .map(tonic::Response::new)
+            .map_err(crate::error::grpc_error_to_grpc_status)
+    }
+    async fn delete_search_templates(
+        &self,
+        request: tonic::Request<DeleteSearchTemplatesRequest>,
+    ) -> Result<tonic::Response<EmptyResponse>, tonic::Status> {
+        self.inner
+            .0
+            .delete_search_templates(request.into_inner())
+            .await
+            .map(tonic::Response::new)
+            .map_err(crate::error::grpc_error_to_grpc_status)
+    }
     async fn get_cluster_identity(
         &self,
         request: tonic::Request<GetClusterIdentityRequest>,
@@ -7193,6 +7866,120 @@ pub mod metastore_service_grpc_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        /// Creates a search template.
+        pub async fn create_search_template(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateSearchTemplateRequest>,
+        ) -> std::result::Result<tonic::Response<super::EmptyResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.metastore.MetastoreService/CreateSearchTemplate",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "quickwit.metastore.MetastoreService",
+                        "CreateSearchTemplate",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        /// Fetches a search template.
+        pub async fn get_search_template(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetSearchTemplateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetSearchTemplateResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.metastore.MetastoreService/GetSearchTemplate",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "quickwit.metastore.MetastoreService",
+                        "GetSearchTemplate",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        /// Returns all the search templates.
+        pub async fn list_search_templates(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListSearchTemplatesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListSearchTemplatesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.metastore.MetastoreService/ListSearchTemplates",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "quickwit.metastore.MetastoreService",
+                        "ListSearchTemplates",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        /// Deletes search templates.
+        pub async fn delete_search_templates(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteSearchTemplatesRequest>,
+        ) -> std::result::Result<tonic::Response<super::EmptyResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.metastore.MetastoreService/DeleteSearchTemplates",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "quickwit.metastore.MetastoreService",
+                        "DeleteSearchTemplates",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
         /// Get cluster identity
         pub async fn get_cluster_identity(
             &mut self,
@@ -7471,6 +8258,32 @@ pub mod metastore_service_grpc_server {
             &self,
             request: tonic::Request<super::DeleteIndexTemplatesRequest>,
         ) -> std::result::Result<tonic::Response<super::EmptyResponse>, tonic::Status>;
+        /// Creates a search template.
+        async fn create_search_template(
+            &self,
+            request: tonic::Request<super::CreateSearchTemplateRequest>,
+        ) -> std::result::Result<tonic::Response<super::EmptyResponse>, tonic::Status>;
+        /// Fetches a search template.
+        async fn get_search_template(
+            &self,
+            request: tonic::Request<super::GetSearchTemplateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetSearchTemplateResponse>,
+            tonic::Status,
+        >;
+        /// Returns all the search templates.
+        async fn list_search_templates(
+            &self,
+            request: tonic::Request<super::ListSearchTemplatesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListSearchTemplatesResponse>,
+            tonic::Status,
+        >;
+        /// Deletes search templates.
+        async fn delete_search_templates(
+            &self,
+            request: tonic::Request<super::DeleteSearchTemplatesRequest>,
+        ) -> std::result::Result<tonic::Response<super::EmptyResponse>, tonic::Status>;
         /// Get cluster identity
         async fn get_cluster_identity(
             &self,
@@ -9138,6 +9951,202 @@ pub mod metastore_service_grpc_server {
                     };
                     Box::pin(fut)
                 }
+                "/quickwit.metastore.MetastoreService/CreateSearchTemplate" => {
+                    #[allow(non_camel_case_types)]
+                    struct CreateSearchTemplateSvc<T: MetastoreServiceGrpc>(pub Arc<T>);
+                    impl<
+                        T: MetastoreServiceGrpc,
+                    > tonic::server::UnaryService<super::CreateSearchTemplateRequest>
+                    for CreateSearchTemplateSvc<T> {
+                        type Response = super::EmptyResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CreateSearchTemplateRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MetastoreServiceGrpc>::create_search_template(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CreateSearchTemplateSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit.metastore.MetastoreService/GetSearchTemplate" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetSearchTemplateSvc<T: MetastoreServiceGrpc>(pub Arc<T>);
+                    impl<
+                        T: MetastoreServiceGrpc,
+                    > tonic::server::UnaryService<super::GetSearchTemplateRequest>
+                    for GetSearchTemplateSvc<T> {
+                        type Response = super::GetSearchTemplateResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetSearchTemplateRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MetastoreServiceGrpc>::get_search_template(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetSearchTemplateSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit.metastore.MetastoreService/ListSearchTemplates" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListSearchTemplatesSvc<T: MetastoreServiceGrpc>(pub Arc<T>);
+                    impl<
+                        T: MetastoreServiceGrpc,
+                    > tonic::server::UnaryService<super::ListSearchTemplatesRequest>
+                    for ListSearchTemplatesSvc<T> {
+                        type Response = super::ListSearchTemplatesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListSearchTemplatesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MetastoreServiceGrpc>::list_search_templates(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListSearchTemplatesSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit.metastore.MetastoreService/DeleteSearchTemplates" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteSearchTemplatesSvc<T: MetastoreServiceGrpc>(pub Arc<T>);
+                    impl<
+                        T: MetastoreServiceGrpc,
+                    > tonic::server::UnaryService<super::DeleteSearchTemplatesRequest>
+                    for DeleteSearchTemplatesSvc<T> {
+                        type Response = super::EmptyResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteSearchTemplatesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MetastoreServiceGrpc>::delete_search_templates(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DeleteSearchTemplatesSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/quickwit.metastore.MetastoreService/GetClusterIdentity" => {
                     #[allow(non_camel_case_types)]
                     struct GetClusterIdentitySvc<T: MetastoreServiceGrpc>(pub Arc<T>);
@@ -185,6 +185,21 @@ pub struct SearchRequest {
     /// that index is not found and this parameter is set to `false`.
     #[prost(bool, tag = "18")]
     pub ignore_missing_indexes: bool,
+    /// If set to true, the response contains a profile of the search execution,
+    /// detailing where the time was spent on every split.
+    #[prost(bool, tag = "19")]
+    pub profile: bool,
+    /// JSON-encoded map of the runtime fields defined by the request, by field name. Runtime
+    /// fields are computed at query time by evaluating a VRL expression on the matching documents.
+    #[prost(string, optional, tag = "20")]
+    pub runtime_fields: ::core::option::Option<::prost::alloc::string::String>,
+    /// Runtime fields whose values are returned along with the hits.
+    #[prost(string, repeated, tag = "21")]
+    pub docvalue_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// JSON-encoded approximate k-nearest neighbor query on a dense vector field. When the query is
+    /// not a match all query, kNN hits and full-text hits are combined with reciprocal rank fusion.
+    #[prost(string, optional, tag = "22")]
+    pub knn: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -232,6 +247,39 @@ pub struct SearchResponse {
     /// Total number of successful splits searched.
     #[prost(uint64, tag = "8")]
     pub num_successful_splits: u64,
+    /// Execution profile of the search (only set if profile was set in the request).
+    #[prost(message, optional, tag = "10")]
+    pub profile: ::core::option::Option<SearchProfile>,
+    /// Non-fatal warnings about the execution of the search, such as the cost of runtime fields.
+    #[prost(string, repeated, tag = "11")]
+    pub warnings: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// Execution profile of a search, as a tree: root phases, leaf nodes, and splits.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SearchProfile {
+    /// Time spent by the root planning the search (resolving indexes, listing splits).
+    #[prost(uint64, tag = "1")]
+    pub plan_microsecs: u64,
+    /// Time spent in the leaf search phase, as observed by the root.
+    #[prost(uint64, tag = "2")]
+    pub leaf_search_microsecs: u64,
+    /// Time spent in the fetch docs phase, as observed by the root.
+    #[prost(uint64, tag = "3")]
+    pub fetch_docs_microsecs: u64,
+    /// Profiles of the leaf nodes involved in the search, sorted by leaf node ID.
+    #[prost(message, repeated, tag = "4")]
+    pub leaf_nodes: ::prost::alloc::vec::Vec<LeafNodeSearchProfile>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct LeafNodeSearchProfile {
+    /// gRPC address of the searcher.
+    #[prost(string, tag = "1")]
+    pub leaf_node_id: ::prost::alloc::string::String,
+    /// Profiles of the splits searched by this node, sorted by decreasing elapsed time.
+    #[prost(message, repeated, tag = "2")]
+    pub splits: ::prost::alloc::vec::Vec<SplitSearchProfile>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -368,6 +416,9 @@ pub struct Hit {
     /// The index id of the hit
     #[prost(string, tag = "4")]
     pub index_id: ::prost::alloc::string::String,
+    /// JSON-encoded values of the requested runtime fields, by field name
+    #[prost(string, optional, tag = "5")]
+    pub fields_json: ::core::option::Option<::prost::alloc::string::String>,
 }
 /// A partial hit, is a hit for which we have not fetch the content yet.
 /// Instead, it holds a document_uri which is enough information to
@@ -455,6 +506,93 @@ pub struct LeafSearchResponse {
     >,
     #[prost(message, optional, tag = "8")]
     pub resource_stats: ::core::option::Option<ResourceStats>,
+    /// Timing breakdown of the slowest splits searched, sorted by decreasing elapsed time.
+    /// Only a bounded number of splits is kept when merging responses.
+    #[prost(message, repeated, tag = "9")]
+    pub slowest_splits: ::prost::alloc::vec::Vec<SplitSearchStats>,
+    /// Execution profile of every split searched.
+    /// Only populated if the search request has `profile` set.
+    #[prost(message, repeated, tag = "10")]
+    pub split_profiles: ::prost::alloc::vec::Vec<SplitSearchProfile>,
+}
+/// Timing breakdown of a search on a single split.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SplitSearchStats {
+    #[prost(string, tag = "1")]
+    pub split_id: ::prost::alloc::string::String,
+    /// gRPC address of the searcher that searched the split.
+    /// It is filled by the root searcher, as leaves do not know how they are addressed.
+    #[prost(string, tag = "2")]
+    pub leaf_node_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub num_docs: u64,
+    #[prost(uint64, tag = "4")]
+    pub warmup_num_bytes: u64,
+    #[prost(uint64, tag = "5")]
+    pub warmup_microsecs: u64,
+    #[prost(uint64, tag = "6")]
+    pub cpu_thread_pool_wait_microsecs: u64,
+    #[prost(uint64, tag = "7")]
+    pub cpu_microsecs: u64,
+    /// Total time spent searching the split, from opening the split to the end of the collection.
+    #[prost(uint64, tag = "8")]
+    pub elapsed_microsecs: u64,
+}
+/// Execution profile of a search on a single split.
+///
+/// The warmup phases run concurrently: their durations do not add up to `warmup_microsecs`.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SplitSearchProfile {
+    #[prost(string, tag = "1")]
+    pub split_id: ::prost::alloc::string::String,
+    /// gRPC address of the searcher that searched the split.
+    /// It is filled by the root searcher, as leaves do not know how they are addressed.
+    #[prost(string, tag = "2")]
+    pub leaf_node_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub num_docs: u64,
+    /// True if the result was served by the leaf search cache. In that case,
+    /// only `elapsed_microsecs` is set.
+    #[prost(bool, tag = "4")]
+    pub cache_hit: bool,
+    /// Time spent opening the split, hotcache fetch included.
+    #[prost(uint64, tag = "5")]
+    pub open_split_microsecs: u64,
+    /// Time spent fetching the split footer and hotcache, from the footer cache or the storage.
+    #[prost(uint64, tag = "6")]
+    pub hotcache_fetch_microsecs: u64,
+    #[prost(uint64, tag = "7")]
+    pub hotcache_num_bytes: u64,
+    /// Time spent warming up term dictionaries, for terms, term ranges and automatons.
+    #[prost(uint64, tag = "8")]
+    pub warmup_term_dict_microsecs: u64,
+    #[prost(uint64, tag = "9")]
+    pub warmup_postings_microsecs: u64,
+    #[prost(uint64, tag = "10")]
+    pub warmup_fast_fields_microsecs: u64,
+    #[prost(uint64, tag = "11")]
+    pub warmup_fieldnorms_microsecs: u64,
+    #[prost(uint64, tag = "12")]
+    pub warmup_microsecs: u64,
+    /// Number of bytes read from the storage layer during the warmup.
+    #[prost(uint64, tag = "13")]
+    pub warmup_num_bytes: u64,
+    #[prost(uint64, tag = "14")]
+    pub cpu_thread_pool_wait_microsecs: u64,
+    /// Time spent executing the query and collecting the results.
+    #[prost(uint64, tag = "15")]
+    pub collection_microsecs: u64,
+    /// Time spent fetching the documents of the split, filled by the root
+    /// from the fetch docs phase.
+    #[prost(uint64, tag = "16")]
+    pub fetch_docs_microsecs: u64,
+    #[prost(uint64, tag = "17")]
+    pub num_fetched_docs: u64,
+    /// Total time spent searching the split, from opening the split to the end of the collection.
+    #[prost(uint64, tag = "18")]
+    pub elapsed_microsecs: u64,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
@@ -484,6 +622,9 @@ pub struct FetchDocsRequest {
     /// `DocMapper` as json serialized trait.
     #[prost(string, tag = "6")]
     pub doc_mapper: ::prost::alloc::string::String,
+    /// If set to true, the response contains the time spent fetching docs in each split.
+    #[prost(bool, tag = "8")]
+    pub profile: bool,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -491,6 +632,20 @@ pub struct FetchDocsResponse {
     /// List of complete hits.
     #[prost(message, repeated, tag = "1")]
     pub hits: ::prost::alloc::vec::Vec<LeafHit>,
+    /// Only populated if the fetch docs request has `profile` set.
+    #[prost(message, repeated, tag = "2")]
+    pub split_profiles: ::prost::alloc::vec::Vec<SplitFetchDocsProfile>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SplitFetchDocsProfile {
+    #[prost(string, tag = "1")]
+    pub split_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub num_docs: u64,
+    /// Time spent fetching the documents, split opening included.
+    #[prost(uint64, tag = "3")]
+    pub elapsed_microsecs: u64,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
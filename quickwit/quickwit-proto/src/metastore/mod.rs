@@ -79,6 +79,11 @@ pub enum EntityKind {
         /// Index template ID.
         template_id: String,
     },
+    /// A search template.
+    SearchTemplate {
+        /// Search template ID.
+        template_id: String,
+    },
 }
 
 impl fmt::Display for EntityKind {
@@ -100,6 +105,9 @@ impl fmt::Display for EntityKind {
             EntityKind::IndexTemplate { template_id } => {
                 write!(f, "index template `{template_id}`")
             }
+            EntityKind::SearchTemplate { template_id } => {
+                write!(f, "search template `{template_id}`")
+            }
         }
     }
 }
@@ -302,6 +310,7 @@ impl SourceType {
     pub fn as_str(&self) -> &'static str {
         match self {
             SourceType::Cli => "ingest-cli",
+            SourceType::Directory => "directory",
             SourceType::File => "file",
             SourceType::IngestV1 => "ingest-api",
             SourceType::IngestV2 => "ingest",
@@ -314,6 +323,7 @@ impl SourceType {
             SourceType::Unspecified => "unspecified",
             SourceType::Vec => "vec",
             SourceType::Void => "void",
+            SourceType::Webhook => "webhook",
         }
     }
 }
@@ -322,6 +332,7 @@ impl fmt::Display for SourceType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let source_type_str = match self {
             SourceType::Cli => "CLI ingest",
+            SourceType::Directory => "directory",
             SourceType::File => "file",
             SourceType::IngestV1 => "ingest API v1",
             SourceType::IngestV2 => "ingest API v2",
@@ -334,6 +345,7 @@ impl fmt::Display for SourceType {
             SourceType::Unspecified => "unspecified",
             SourceType::Vec => "vec",
             SourceType::Void => "void",
+            SourceType::Webhook => "webhook",
         };
         write!(f, "{source_type_str}")
     }
@@ -29,6 +29,15 @@ use tantivy::aggregation::metric::{
 
 // hopefully all From in this module are no-ops, otherwise, this is a very sad situation
 
+/// Error returned when converting an aggregation result computed by Quickwit, which has no tantivy
+/// equivalent, into a tantivy aggregation result.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, thiserror::Error)]
+#[error("{aggregation_type} aggregation results have no tantivy equivalent")]
+pub struct NoTantivyEquivalentError {
+    /// The type of the aggregation.
+    pub aggregation_type: &'static str,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 /// The final aggregation result.
 pub struct AggregationResults(pub Vec<(String, AggregationResult)>);
@@ -39,9 +48,16 @@ impl From<TantivyAggregationResults> for AggregationResults {
     }
 }
 
-impl From<AggregationResults> for TantivyAggregationResults {
-    fn from(value: AggregationResults) -> TantivyAggregationResults {
-        TantivyAggregationResults(value.0.into_iter().map(|(k, v)| (k, v.into())).collect())
+impl TryFrom<AggregationResults> for TantivyAggregationResults {
+    type Error = NoTantivyEquivalentError;
+
+    fn try_from(value: AggregationResults) -> Result<TantivyAggregationResults, Self::Error> {
+        let aggregation_results = value
+            .0
+            .into_iter()
+            .map(|(k, v)| Ok((k, v.try_into()?)))
+            .collect::<Result<_, Self::Error>>()?;
+        Ok(TantivyAggregationResults(aggregation_results))
     }
 }
 
@@ -67,16 +83,19 @@ impl From<TantivyAggregationResult> for AggregationResult {
     }
 }
 
-impl From<AggregationResult> for TantivyAggregationResult {
-    fn from(value: AggregationResult) -> TantivyAggregationResult {
-        match value {
+impl TryFrom<AggregationResult> for TantivyAggregationResult {
+    type Error = NoTantivyEquivalentError;
+
+    fn try_from(value: AggregationResult) -> Result<TantivyAggregationResult, Self::Error> {
+        let aggregation_result = match value {
             AggregationResult::BucketResult(bucket) => {
-                TantivyAggregationResult::BucketResult(bucket.into())
+                TantivyAggregationResult::BucketResult(bucket.try_into()?)
             }
             AggregationResult::MetricResult(metric) => {
-                TantivyAggregationResult::MetricResult(metric.into())
+                TantivyAggregationResult::MetricResult(metric.try_into()?)
             }
-        }
+        };
+        Ok(aggregation_result)
     }
 }
 
@@ -103,6 +122,9 @@ pub enum MetricResult {
     TopHits(TopHitsMetricResult),
     /// Cardinality metric result
     Cardinality(SingleMetricResult),
+    /// Pipeline aggregation result. Tantivy has no pipeline aggregations, they are computed by
+    /// Quickwit on the final aggregation results.
+    Pipeline(PipelineMetricResult),
 }
 
 impl From<TantivyMetricResult> for MetricResult {
@@ -122,9 +144,11 @@ impl From<TantivyMetricResult> for MetricResult {
     }
 }
 
-impl From<MetricResult> for TantivyMetricResult {
-    fn from(value: MetricResult) -> TantivyMetricResult {
-        match value {
+impl TryFrom<MetricResult> for TantivyMetricResult {
+    type Error = NoTantivyEquivalentError;
+
+    fn try_from(value: MetricResult) -> Result<TantivyMetricResult, Self::Error> {
+        let metric_result = match value {
             MetricResult::Average(val) => TantivyMetricResult::Average(val),
             MetricResult::Count(val) => TantivyMetricResult::Count(val),
             MetricResult::Max(val) => TantivyMetricResult::Max(val),
@@ -135,7 +159,13 @@ impl From<MetricResult> for TantivyMetricResult {
             MetricResult::Percentiles(val) => TantivyMetricResult::Percentiles(val.into()),
             MetricResult::TopHits(val) => TantivyMetricResult::TopHits(val),
             MetricResult::Cardinality(val) => TantivyMetricResult::Cardinality(val),
-        }
+            MetricResult::Pipeline(_) => {
+                return Err(NoTantivyEquivalentError {
+                    aggregation_type: "pipeline",
+                });
+            }
+        };
+        Ok(metric_result)
     }
 }
 
@@ -169,6 +199,30 @@ pub enum BucketResult {
         /// The upper bound error for the doc count of each term.
         doc_count_error_upper_bound: Option<u64>,
     },
+    /// This is the composite result. Tantivy has no composite aggregation, it is collected by
+    /// Quickwit and can only appear at the top level of the aggregation results.
+    Composite {
+        /// The buckets, sorted by key.
+        buckets: Vec<CompositeBucketEntry>,
+        /// The key of the last bucket, to pass as `after` to fetch the next page.
+        after_key: Option<CompositeKey>,
+    },
+    /// This is the significant terms result. It is computed by the root searcher from a terms
+    /// aggregation and can only appear at the top level of the aggregation results.
+    SignificantTerms {
+        /// Number of documents in the foreground set.
+        doc_count: u64,
+        /// Number of documents in the background set.
+        bg_count: u64,
+        /// The buckets, sorted by decreasing score.
+        buckets: Vec<SignificantTermsBucketEntry>,
+    },
+    /// This is the rare terms result. It is computed by the root searcher from a terms
+    /// aggregation and can only appear at the top level of the aggregation results.
+    RareTerms {
+        /// The buckets, sorted by increasing doc count.
+        buckets: Vec<BucketEntry>,
+    },
 }
 
 impl From<TantivyBucketResult> for BucketResult {
@@ -196,25 +250,46 @@ impl From<TantivyBucketResult> for BucketResult {
     }
 }
 
-impl From<BucketResult> for TantivyBucketResult {
-    fn from(value: BucketResult) -> TantivyBucketResult {
-        match value {
+impl TryFrom<BucketResult> for TantivyBucketResult {
+    type Error = NoTantivyEquivalentError;
+
+    fn try_from(value: BucketResult) -> Result<TantivyBucketResult, Self::Error> {
+        let bucket_result = match value {
             BucketResult::Range { buckets } => TantivyBucketResult::Range {
-                buckets: buckets.into(),
+                buckets: buckets.try_into()?,
             },
             BucketResult::Histogram { buckets } => TantivyBucketResult::Histogram {
-                buckets: buckets.into(),
+                buckets: buckets.try_into()?,
             },
             BucketResult::Terms {
                 buckets,
                 sum_other_doc_count,
                 doc_count_error_upper_bound,
             } => TantivyBucketResult::Terms {
-                buckets: buckets.into_iter().map(Into::into).collect(),
+                buckets: buckets
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<_, _>>()?,
                 sum_other_doc_count,
                 doc_count_error_upper_bound,
             },
-        }
+            BucketResult::Composite { .. } => {
+                return Err(NoTantivyEquivalentError {
+                    aggregation_type: "composite",
+                });
+            }
+            BucketResult::SignificantTerms { .. } => {
+                return Err(NoTantivyEquivalentError {
+                    aggregation_type: "significant terms",
+                });
+            }
+            BucketResult::RareTerms { .. } => {
+                return Err(NoTantivyEquivalentError {
+                    aggregation_type: "rare terms",
+                });
+            }
+        };
+        Ok(bucket_result)
     }
 }
 
@@ -243,18 +318,25 @@ where U: From<T>
     }
 }
 
-impl<T, U> From<BucketEntries<T>> for TantivyBucketEntries<U>
-where U: From<T>
+impl<T, U> TryFrom<BucketEntries<T>> for TantivyBucketEntries<U>
+where U: TryFrom<T, Error = NoTantivyEquivalentError>
 {
-    fn from(value: BucketEntries<T>) -> TantivyBucketEntries<U> {
-        match value {
-            BucketEntries::Vec(vec) => {
-                TantivyBucketEntries::Vec(vec.into_iter().map(Into::into).collect())
-            }
-            BucketEntries::HashMap(map) => {
-                TantivyBucketEntries::HashMap(map.into_iter().map(|(k, v)| (k, v.into())).collect())
-            }
-        }
+    type Error = NoTantivyEquivalentError;
+
+    fn try_from(value: BucketEntries<T>) -> Result<TantivyBucketEntries<U>, Self::Error> {
+        let bucket_entries = match value {
+            BucketEntries::Vec(vec) => TantivyBucketEntries::Vec(
+                vec.into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<_, _>>()?,
+            ),
+            BucketEntries::HashMap(map) => TantivyBucketEntries::HashMap(
+                map.into_iter()
+                    .map(|(k, v)| Ok((k, v.try_into()?)))
+                    .collect::<Result<_, Self::Error>>()?,
+            ),
+        };
+        Ok(bucket_entries)
     }
 }
 
@@ -291,17 +373,19 @@ impl From<TantivyRangeBucketEntry> for RangeBucketEntry {
     }
 }
 
-impl From<RangeBucketEntry> for TantivyRangeBucketEntry {
-    fn from(value: RangeBucketEntry) -> TantivyRangeBucketEntry {
-        TantivyRangeBucketEntry {
+impl TryFrom<RangeBucketEntry> for TantivyRangeBucketEntry {
+    type Error = NoTantivyEquivalentError;
+
+    fn try_from(value: RangeBucketEntry) -> Result<TantivyRangeBucketEntry, Self::Error> {
+        Ok(TantivyRangeBucketEntry {
             key: value.key.into(),
             doc_count: value.doc_count,
             from: value.from,
             to: value.to,
             from_as_string: value.from_as_string,
             to_as_string: value.to_as_string,
-            sub_aggregation: value.sub_aggregation.into(),
-        }
+            sub_aggregation: value.sub_aggregation.try_into()?,
+        })
     }
 }
 
@@ -328,14 +412,16 @@ impl From<TantivyBucketEntry> for BucketEntry {
     }
 }
 
-impl From<BucketEntry> for TantivyBucketEntry {
-    fn from(value: BucketEntry) -> TantivyBucketEntry {
-        TantivyBucketEntry {
+impl TryFrom<BucketEntry> for TantivyBucketEntry {
+    type Error = NoTantivyEquivalentError;
+
+    fn try_from(value: BucketEntry) -> Result<TantivyBucketEntry, Self::Error> {
+        Ok(TantivyBucketEntry {
             key_as_string: value.key_as_string,
             key: value.key.into(),
             doc_count: value.doc_count,
-            sub_aggregation: value.sub_aggregation.into(),
-        }
+            sub_aggregation: value.sub_aggregation.try_into()?,
+        })
     }
 }
 
@@ -373,6 +459,60 @@ impl From<Key> for TantivyKey {
     }
 }
 
+/// The key of a composite bucket: one value per source, in the order of the sources.
+pub type CompositeKey = Vec<(String, CompositeKeyValue)>;
+
+/// A value of a composite bucket key.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CompositeKeyValue {
+    /// The document has no value for the source, see `missing_bucket`.
+    Null,
+    /// `bool` value
+    Bool(bool),
+    /// `i64` value, also used for dates as milliseconds since the epoch
+    I64(i64),
+    /// `u64` value
+    U64(u64),
+    /// `f64` value
+    F64(f64),
+    /// String value
+    Str(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompositeBucketEntry {
+    /// The identifier of the bucket.
+    pub key: CompositeKey,
+    /// Number of documents in the bucket.
+    pub doc_count: u64,
+    /// Sub-aggregations in this bucket.
+    pub sub_aggregation: AggregationResults,
+}
+
+/// This is the bucket entry of a significant terms aggregation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignificantTermsBucketEntry {
+    /// The term.
+    pub key: Key,
+    /// Number of documents of the foreground set containing the term.
+    pub doc_count: u64,
+    /// Number of documents of the background set containing the term.
+    pub bg_count: u64,
+    /// The significance score of the term.
+    pub score: f64,
+    /// Sub-aggregations in this bucket.
+    pub sub_aggregation: AggregationResults,
+}
+
+/// The result of a pipeline aggregation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PipelineMetricResult {
+    /// The computed value, `None` when there was nothing to compute it from.
+    pub value: Option<f64>,
+    /// The keys of the buckets holding the value, for `max_bucket`.
+    pub keys: Option<Vec<String>>,
+}
+
 /// Single-metric aggregations use this common result structure.
 ///
 /// Main reason to wrap it in value is to match elasticsearch output structure.
@@ -413,3 +553,103 @@ impl From<PercentilesMetricResult> for TantivyPercentilesMetricResult {
         TantivyPercentilesMetricResult { values }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms_result(sub_aggregation: AggregationResults) -> AggregationResult {
+        AggregationResult::BucketResult(BucketResult::Terms {
+            buckets: vec![BucketEntry {
+                key_as_string: None,
+                key: Key::Str("foo".to_string()),
+                doc_count: 1,
+                sub_aggregation,
+            }],
+            sum_other_doc_count: 0,
+            doc_count_error_upper_bound: None,
+        })
+    }
+
+    #[test]
+    fn test_aggregation_results_to_tantivy() {
+        let aggregation_results = AggregationResults(vec![(
+            "terms".to_string(),
+            terms_result(AggregationResults(Vec::new())),
+        )]);
+        let tantivy_aggregation_results =
+            TantivyAggregationResults::try_from(aggregation_results).unwrap();
+        assert_eq!(tantivy_aggregation_results.0.len(), 1);
+    }
+
+    #[test]
+    fn test_composite_aggregation_results_to_tantivy() {
+        let composite_result = AggregationResult::BucketResult(BucketResult::Composite {
+            buckets: Vec::new(),
+            after_key: None,
+        });
+        let aggregation_results = AggregationResults(vec![(
+            "terms".to_string(),
+            terms_result(AggregationResults(vec![(
+                "composite".to_string(),
+                composite_result,
+            )])),
+        )]);
+        let error = TantivyAggregationResults::try_from(aggregation_results).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "composite aggregation results have no tantivy equivalent"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_aggregation_results_to_tantivy() {
+        let pipeline_result =
+            AggregationResult::MetricResult(MetricResult::Pipeline(PipelineMetricResult {
+                value: Some(1.0),
+                keys: None,
+            }));
+        let aggregation_results = AggregationResults(vec![(
+            "terms".to_string(),
+            terms_result(AggregationResults(vec![(
+                "pipeline".to_string(),
+                pipeline_result,
+            )])),
+        )]);
+        let error = TantivyAggregationResults::try_from(aggregation_results).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "pipeline aggregation results have no tantivy equivalent"
+        );
+    }
+
+    #[test]
+    fn test_significant_and_rare_terms_aggregation_results_to_tantivy() {
+        let significant_terms_result =
+            AggregationResult::BucketResult(BucketResult::SignificantTerms {
+                doc_count: 1,
+                bg_count: 2,
+                buckets: Vec::new(),
+            });
+        let aggregation_results = AggregationResults(vec![(
+            "significant_terms".to_string(),
+            significant_terms_result,
+        )]);
+        let error = TantivyAggregationResults::try_from(aggregation_results).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "significant terms aggregation results have no tantivy equivalent"
+        );
+
+        let rare_terms_result = AggregationResult::BucketResult(BucketResult::RareTerms {
+            buckets: Vec::new(),
+        });
+        let aggregation_results =
+            AggregationResults(vec![("rare_terms".to_string(), rare_terms_result)]);
+        let error = TantivyAggregationResults::try_from(aggregation_results).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "rare terms aggregation results have no tantivy equivalent"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Deserializer};
+
+use crate::NotNaNf32;
+use crate::elastic_query_dsl::one_field_map::OneFieldMap;
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, StringOrStructForSerialization};
+use crate::query_ast::{Fuzziness, FuzzyQuery as AstFuzzyQuery, MAX_FUZZY_DISTANCE, QueryAst};
+
+/// The `fuzziness` parameter of Elasticsearch queries: `AUTO`, `AUTO:[low],[high]` or an edit
+/// distance.
+///
+/// See <https://www.elastic.co/guide/en/elasticsearch/reference/current/common-options.html#fuzziness>
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub(crate) struct ElasticFuzziness(pub(crate) Fuzziness);
+
+fn parse_fuzziness(fuzziness_str: &str) -> Result<Fuzziness, String> {
+    let fuzziness_str = fuzziness_str.trim();
+    let invalid_fuzziness_error = || {
+        format!(
+            "invalid fuzziness `{fuzziness_str}`, expected `AUTO`, `AUTO:[low],[high]` or an edit \
+             distance between 0 and {MAX_FUZZY_DISTANCE}"
+        )
+    };
+    let Some(auto_suffix) = fuzziness_str
+        .get(..4)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("auto"))
+        .map(|_| &fuzziness_str[4..])
+    else {
+        let distance: u8 = fuzziness_str
+            .parse()
+            .map_err(|_| invalid_fuzziness_error())?;
+        return Ok(Fuzziness::Distance(distance));
+    };
+    if auto_suffix.is_empty() {
+        return Ok(Fuzziness::default());
+    }
+    let (low_str, high_str) = auto_suffix
+        .strip_prefix(':')
+        .and_then(|low_high| low_high.split_once(','))
+        .ok_or_else(invalid_fuzziness_error)?;
+    let low: u32 = low_str.parse().map_err(|_| invalid_fuzziness_error())?;
+    let high: u32 = high_str.parse().map_err(|_| invalid_fuzziness_error())?;
+    if low > high {
+        return Err(invalid_fuzziness_error());
+    }
+    Ok(Fuzziness::Auto { low, high })
+}
+
+impl<'de> Deserialize<'de> for ElasticFuzziness {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum DistanceOrString {
+            Distance(u8),
+            String(String),
+        }
+        let fuzziness = match DistanceOrString::deserialize(deserializer)? {
+            DistanceOrString::Distance(distance) => Fuzziness::Distance(distance),
+            DistanceOrString::String(fuzziness_str) => {
+                parse_fuzziness(&fuzziness_str).map_err(serde::de::Error::custom)?
+            }
+        };
+        if let Fuzziness::Distance(distance) = fuzziness
+            && distance > MAX_FUZZY_DISTANCE
+        {
+            return Err(serde::de::Error::custom(format!(
+                "fuzziness must be at most {MAX_FUZZY_DISTANCE}, got {distance}"
+            )));
+        }
+        Ok(ElasticFuzziness(fuzziness))
+    }
+}
+
+pub(crate) fn default_transpositions() -> bool {
+    true
+}
+
+/// `FuzzyQuery` as defined in
+/// <https://www.elastic.co/guide/en/elasticsearch/reference/current/query-dsl-fuzzy-query.html>
+#[derive(Deserialize, Clone, Eq, PartialEq, Debug)]
+#[serde(from = "OneFieldMap<StringOrStructForSerialization<FuzzyQueryParams>>")]
+pub(crate) struct FuzzyQuery {
+    pub(crate) field: String,
+    pub(crate) params: FuzzyQueryParams,
+}
+
+#[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FuzzyQueryParams {
+    value: String,
+    #[serde(default)]
+    fuzziness: ElasticFuzziness,
+    #[serde(default)]
+    prefix_length: u32,
+    #[serde(default = "default_transpositions")]
+    transpositions: bool,
+    #[serde(default)]
+    pub boost: Option<NotNaNf32>,
+}
+
+impl ConvertibleToQueryAst for FuzzyQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let distance = self
+            .params
+            .fuzziness
+            .0
+            .distance_for_term(&self.params.value);
+        let fuzzy_ast: QueryAst = AstFuzzyQuery {
+            field: self.field,
+            value: self.params.value,
+            distance,
+            transpositions: self.params.transpositions,
+            prefix_length: self.params.prefix_length,
+            lenient: true,
+        }
+        .into();
+        Ok(fuzzy_ast.boost(self.params.boost))
+    }
+}
+
+impl From<OneFieldMap<StringOrStructForSerialization<FuzzyQueryParams>>> for FuzzyQuery {
+    fn from(
+        fuzzy_query_params: OneFieldMap<StringOrStructForSerialization<FuzzyQueryParams>>,
+    ) -> Self {
+        let OneFieldMap { field, value } = fuzzy_query_params;
+        FuzzyQuery {
+            field,
+            params: value.inner,
+        }
+    }
+}
+
+impl From<String> for FuzzyQueryParams {
+    fn from(value: String) -> FuzzyQueryParams {
+        FuzzyQueryParams {
+            value,
+            fuzziness: ElasticFuzziness::default(),
+            prefix_length: 0,
+            transpositions: true,
+            boost: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fuzziness() {
+        assert_eq!(parse_fuzziness("AUTO").unwrap(), Fuzziness::default());
+        assert_eq!(parse_fuzziness("auto").unwrap(), Fuzziness::default());
+        assert_eq!(
+            parse_fuzziness("AUTO:2,5").unwrap(),
+            Fuzziness::Auto { low: 2, high: 5 }
+        );
+        assert_eq!(parse_fuzziness("1").unwrap(), Fuzziness::Distance(1));
+        parse_fuzziness("AUTO:5,2").unwrap_err();
+        parse_fuzziness("AUTO:2").unwrap_err();
+        parse_fuzziness("fuzzy").unwrap_err();
+    }
+
+    #[test]
+    fn test_fuzzy_query_convert_to_query_ast() {
+        let fuzzy_query_json = r#"{
+            "fuzzy": {
+                "product_name": {
+                    "value": "laptpo",
+                    "fuzziness": "AUTO",
+                    "prefix_length": 1,
+                    "transpositions": false
+                }
+            }
+        }"#;
+        let fuzzy_query: crate::elastic_query_dsl::ElasticQueryDsl =
+            serde_json::from_str(fuzzy_query_json).unwrap();
+        let query_ast = QueryAst::try_from(fuzzy_query).unwrap();
+        let QueryAst::Fuzzy(fuzzy_query) = query_ast else {
+            panic!("expected a fuzzy query, got {query_ast:?}");
+        };
+        assert_eq!(
+            fuzzy_query,
+            AstFuzzyQuery {
+                field: "product_name".to_string(),
+                value: "laptpo".to_string(),
+                distance: 2,
+                transpositions: false,
+                prefix_length: 1,
+                lenient: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_query_string_and_numeric_fuzziness() {
+        let fuzzy_query: FuzzyQuery = serde_json::from_str(r#"{"host": "web-01"}"#).unwrap();
+        assert_eq!(fuzzy_query.params.fuzziness, ElasticFuzziness::default());
+
+        let fuzzy_query: FuzzyQuery =
+            serde_json::from_str(r#"{"host": {"value": "web-01", "fuzziness": 1}}"#).unwrap();
+        assert_eq!(
+            fuzzy_query.params.fuzziness,
+            ElasticFuzziness(Fuzziness::Distance(1))
+        );
+
+        let error =
+            serde_json::from_str::<FuzzyQuery>(r#"{"host": {"value": "web-01", "fuzziness": 3}}"#)
+                .unwrap_err();
+        assert!(error.to_string().contains("fuzziness must be at most 2"));
+    }
+}
@@ -38,6 +38,7 @@ impl ConvertibleToQueryAst for MatchBoolPrefixQuery {
                 max_expansions: default_max_expansions(),
             },
             zero_terms_query: self.params.zero_terms_query,
+            fuzzy: self.params.fuzzy_params(),
         };
         Ok(QueryAst::FullText(FullTextQuery {
             field: self.field,
@@ -49,6 +49,7 @@ impl ConvertibleToQueryAst for MatchPhraseQuery {
                 slop: self.params.slop,
             },
             zero_terms_query: self.params.zero_terms_query,
+            fuzzy: None,
         };
         Ok(QueryAst::FullText(FullTextQuery {
             field: self.field,
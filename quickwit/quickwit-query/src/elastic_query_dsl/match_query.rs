@@ -15,10 +15,11 @@
 use serde::Deserialize;
 
 use super::LeniencyBool;
+use crate::elastic_query_dsl::fuzzy_query::{ElasticFuzziness, default_transpositions};
 use crate::elastic_query_dsl::{
     ConvertibleToQueryAst, ElasticQueryDslInner, StringOrStructForSerialization,
 };
-use crate::query_ast::{FullTextParams, FullTextQuery, QueryAst};
+use crate::query_ast::{FullTextParams, FullTextQuery, FuzzyParams, QueryAst};
 use crate::{BooleanOperand, MatchAllOrNone, OneFieldMap};
 
 /// `MatchQuery` as defined in
@@ -40,6 +41,23 @@ pub(crate) struct MatchQueryParams {
     pub(crate) zero_terms_query: MatchAllOrNone,
     #[serde(default)]
     pub(crate) lenient: LeniencyBool,
+    #[serde(default)]
+    pub(crate) fuzziness: Option<ElasticFuzziness>,
+    #[serde(default)]
+    pub(crate) prefix_length: u32,
+    #[serde(default = "default_transpositions")]
+    pub(crate) fuzzy_transpositions: bool,
+}
+
+impl MatchQueryParams {
+    pub(crate) fn fuzzy_params(&self) -> Option<FuzzyParams> {
+        let ElasticFuzziness(fuzziness) = self.fuzziness?;
+        Some(FuzzyParams {
+            fuzziness,
+            transpositions: self.fuzzy_transpositions,
+            prefix_length: self.prefix_length,
+        })
+    }
 }
 
 impl ConvertibleToQueryAst for MatchQuery {
@@ -48,6 +66,7 @@ impl ConvertibleToQueryAst for MatchQuery {
             tokenizer: None,
             mode: self.params.operator.into(),
             zero_terms_query: self.params.zero_terms_query,
+            fuzzy: self.params.fuzzy_params(),
         };
         Ok(QueryAst::FullText(FullTextQuery {
             field: self.field,
@@ -83,6 +102,9 @@ impl From<String> for MatchQueryParams {
             zero_terms_query: Default::default(),
             operator: Default::default(),
             lenient: false,
+            fuzziness: None,
+            prefix_length: 0,
+            fuzzy_transpositions: true,
         }
     }
 }
@@ -134,6 +156,9 @@ mod tests {
                 operator: BooleanOperand::And,
                 zero_terms_query: crate::MatchAllOrNone::MatchAll,
                 lenient: false,
+                fuzziness: None,
+                prefix_length: 0,
+                fuzzy_transpositions: true,
             },
         };
         let ast = match_query.convert_to_query_ast().unwrap();
@@ -156,4 +181,24 @@ mod tests {
         );
         assert_eq!(params.zero_terms_query, MatchAllOrNone::MatchAll);
     }
+
+    #[test]
+    fn test_match_query_fuzziness() {
+        let match_query: MatchQuery = serde_json::from_str(
+            r#"{"product_name": {"query": "laptpo bag", "fuzziness": "AUTO", "prefix_length": 2}}"#,
+        )
+        .unwrap();
+        let ast = match_query.convert_to_query_ast().unwrap();
+        let QueryAst::FullText(full_text_query) = ast else {
+            panic!()
+        };
+        assert_eq!(
+            full_text_query.params.fuzzy,
+            Some(FuzzyParams {
+                fuzziness: crate::query_ast::Fuzziness::Auto { low: 3, high: 6 },
+                transpositions: true,
+                prefix_length: 2,
+            })
+        );
+    }
 }
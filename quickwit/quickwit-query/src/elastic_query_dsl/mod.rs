@@ -16,6 +16,7 @@ use serde::{Deserialize, Serialize};
 
 mod bool_query;
 mod exists_query;
+mod fuzzy_query;
 mod match_bool_prefix;
 mod match_phrase_query;
 mod match_query;
@@ -41,6 +42,7 @@ pub(crate) use string_or_struct::StringOrStructForSerialization;
 use term_query::TermQuery;
 
 use crate::elastic_query_dsl::exists_query::ExistsQuery;
+use crate::elastic_query_dsl::fuzzy_query::FuzzyQuery;
 use crate::elastic_query_dsl::match_bool_prefix::MatchBoolPrefixQuery;
 use crate::elastic_query_dsl::match_phrase_query::MatchPhraseQuery;
 use crate::elastic_query_dsl::match_query::MatchQuery;
@@ -91,6 +93,7 @@ pub(crate) enum ElasticQueryDslInner {
     Regexp(RegexQuery),
     Wildcard(WildcardQuery),
     Prefix(PrefixQuery),
+    Fuzzy(FuzzyQuery),
 }
 
 #[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
@@ -141,6 +144,7 @@ impl ConvertibleToQueryAst for ElasticQueryDslInner {
             Self::Regexp(regex_query) => regex_query.convert_to_query_ast(),
             Self::Wildcard(wildcard_query) => wildcard_query.convert_to_query_ast(),
             Self::Prefix(prefix_query) => prefix_query.convert_to_query_ast(),
+            Self::Fuzzy(fuzzy_query) => fuzzy_query.convert_to_query_ast(),
         }
     }
 }
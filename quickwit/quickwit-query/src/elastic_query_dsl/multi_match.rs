@@ -192,6 +192,9 @@ mod tests {
                         operator: crate::BooleanOperand::Or,
                         zero_terms_query: Default::default(),
                         lenient: false,
+                        fuzziness: None,
+                        prefix_length: 0,
+                        fuzzy_transpositions: true,
                     },
                 }
                 .into(),
@@ -202,6 +205,9 @@ mod tests {
                         operator: crate::BooleanOperand::Or,
                         zero_terms_query: Default::default(),
                         lenient: false,
+                        fuzziness: None,
+                        prefix_length: 0,
+                        fuzzy_transpositions: true,
                     },
                 }
                 .into(),
@@ -222,6 +228,9 @@ mod tests {
                         operator: crate::BooleanOperand::Or,
                         zero_terms_query: Default::default(),
                         lenient: false,
+                        fuzziness: None,
+                        prefix_length: 0,
+                        fuzzy_transpositions: true,
                     },
                 }
                 .into(),
@@ -232,6 +241,9 @@ mod tests {
                         operator: crate::BooleanOperand::Or,
                         zero_terms_query: Default::default(),
                         lenient: false,
+                        fuzziness: None,
+                        prefix_length: 0,
+                        fuzzy_transpositions: true,
                     },
                 }
                 .into(),
@@ -252,6 +264,9 @@ mod tests {
                         operator: crate::BooleanOperand::Or,
                         zero_terms_query: Default::default(),
                         lenient: false,
+                        fuzziness: None,
+                        prefix_length: 0,
+                        fuzzy_transpositions: true,
                     },
                 }
                 .into(),
@@ -262,6 +277,9 @@ mod tests {
                         operator: crate::BooleanOperand::Or,
                         zero_terms_query: Default::default(),
                         lenient: false,
+                        fuzziness: None,
+                        prefix_length: 0,
+                        fuzzy_transpositions: true,
                     },
                 }
                 .into(),
@@ -344,6 +362,9 @@ mod tests {
                         operator: crate::BooleanOperand::Or,
                         zero_terms_query: Default::default(),
                         lenient: false,
+                        fuzziness: None,
+                        prefix_length: 0,
+                        fuzzy_transpositions: true,
                     },
                 }
                 .into(),
@@ -354,6 +375,9 @@ mod tests {
                         operator: crate::BooleanOperand::Or,
                         zero_terms_query: Default::default(),
                         lenient: false,
+                        fuzziness: None,
+                        prefix_length: 0,
+                        fuzzy_transpositions: true,
                     },
                 }
                 .into(),
@@ -379,5 +403,14 @@ mod tests {
             }"#,
             "Quickwit does not support field boosting",
         );
+        test_multimatch_query_err_aux(
+            r#"{
+                "query": "quick brown fox",
+                "type": "phrase",
+                "fuzziness": "AUTO",
+                "fields": ["body", "title"]
+            }"#,
+            "unknown field `fuzziness`",
+        );
     }
 }
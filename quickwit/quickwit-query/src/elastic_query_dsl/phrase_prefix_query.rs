@@ -56,6 +56,7 @@ impl ConvertibleToQueryAst for MatchPhrasePrefixQuery {
             tokenizer: analyzer,
             mode: FullTextMode::Phrase { slop },
             zero_terms_query,
+            fuzzy: None,
         };
         let phrase_prefix_query_ast = query_ast::PhrasePrefixQuery {
             field: self.field,
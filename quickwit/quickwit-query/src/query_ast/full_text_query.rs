@@ -27,7 +27,9 @@ use tantivy::tokenizer::{TextAnalyzer, TokenStream};
 
 use crate::query_ast::tantivy_query_ast::{TantivyBoolQuery, TantivyQueryAst};
 use crate::query_ast::utils::full_text_query;
-use crate::query_ast::{BuildTantivyAst, BuildTantivyAstContext, QueryAst};
+use crate::query_ast::{
+    BuildTantivyAst, BuildTantivyAstContext, FuzzyParams, FuzzyTermAutomaton, QueryAst,
+};
 use crate::tokenizers::TokenizerManager;
 use crate::{BooleanOperand, InvalidQuery, MatchAllOrNone, find_field_or_hit_dynamic};
 
@@ -41,6 +43,31 @@ pub struct FullTextParams {
     // By default we match no documents.
     #[serde(default, skip_serializing_if = "MatchAllOrNone::is_none")]
     pub zero_terms_query: MatchAllOrNone,
+    // If set, the terms of a single term query and of the `Bool` and `BoolPrefix` modes (except
+    // the prefix) match the terms within an edit distance rather than the exact terms.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzzy: Option<FuzzyParams>,
+}
+
+/// The terms emitted by a text analyzer at a given position.
+#[derive(Debug)]
+pub(crate) struct PositionTerms {
+    pub position: usize,
+    /// The term emitted by the tokenizer.
+    pub term: Term,
+    /// The terms emitted by a synonym filter as alternatives to `term`. They share its position
+    /// and offsets.
+    pub synonyms: Vec<Term>,
+}
+
+impl PositionTerms {
+    pub(crate) fn into_position_term(self) -> (usize, Term) {
+        (self.position, self.term)
+    }
+
+    fn terms(&self) -> impl Iterator<Item = &Term> {
+        std::iter::once(&self.term).chain(&self.synonyms)
+    }
 }
 
 impl FullTextParams {
@@ -54,10 +81,39 @@ impl FullTextParams {
             .as_deref()
             .unwrap_or(text_field_indexing.tokenizer());
         tokenizer_manager
-            .get_tokenizer(tokenizer_name)
+            .get_search_tokenizer(tokenizer_name)
             .with_context(|| format!("no tokenizer named `{tokenizer_name}` is registered"))
     }
 
+    fn tokenize(
+        mut text_analyzer: TextAnalyzer,
+        text: &str,
+        mut make_term: impl FnMut(&str) -> Term,
+    ) -> Vec<PositionTerms> {
+        let mut token_stream = text_analyzer.token_stream(text);
+        let mut tokens: Vec<PositionTerms> = Vec::new();
+        let mut last_token_span: Option<(usize, usize, usize)> = None;
+        token_stream.process(&mut |token| {
+            let term = make_term(&token.text);
+            let token_span = (token.position, token.offset_from, token.offset_to);
+            // Tokens sharing the position of the previous token but not its offsets, such as
+            // the ones emitted by the ngram tokenizer, are not alternatives to it.
+            if last_token_span == Some(token_span)
+                && let Some(last_position_terms) = tokens.last_mut()
+            {
+                last_position_terms.synonyms.push(term);
+                return;
+            }
+            last_token_span = Some(token_span);
+            tokens.push(PositionTerms {
+                position: token.position,
+                term,
+                synonyms: Vec::new(),
+            });
+        });
+        tokens
+    }
+
     pub(crate) fn tokenize_text_into_terms_json(
         &self,
         field: Field,
@@ -65,19 +121,17 @@ impl FullTextParams {
         text: &str,
         json_options: &JsonObjectOptions,
         tokenizer_manager: &TokenizerManager,
-    ) -> anyhow::Result<Vec<(usize, Term)>> {
+    ) -> anyhow::Result<Vec<PositionTerms>> {
         let text_indexing_options = json_options
             .get_text_indexing_options()
             .with_context(|| format!("Json field text `{json_path}` is not indexed"))?;
-        let mut text_analyzer: TextAnalyzer =
+        let text_analyzer: TextAnalyzer =
             self.text_analyzer(text_indexing_options, tokenizer_manager)?;
-        let mut token_stream = text_analyzer.token_stream(text);
-        let mut tokens = Vec::new();
-        token_stream.process(&mut |token| {
+        let tokens = Self::tokenize(text_analyzer, text, |token_text| {
             let mut term =
                 Term::from_field_json_path(field, json_path, json_options.is_expand_dots_enabled());
-            term.append_type_and_str(&token.text);
-            tokens.push((token.position, term));
+            term.append_type_and_str(token_text);
+            term
         });
         Ok(tokens)
     }
@@ -88,47 +142,110 @@ impl FullTextParams {
         text: &str,
         text_field_indexing: &TextFieldIndexing,
         tokenizer_manager: &TokenizerManager,
-    ) -> anyhow::Result<Vec<(usize, Term)>> {
-        let mut text_analyzer: TextAnalyzer =
+    ) -> anyhow::Result<Vec<PositionTerms>> {
+        let text_analyzer: TextAnalyzer =
             self.text_analyzer(text_field_indexing, tokenizer_manager)?;
-        let mut token_stream = text_analyzer.token_stream(text);
-        let mut tokens = Vec::new();
-        token_stream.process(&mut |token| {
-            let term: Term = Term::from_field_text(field, &token.text);
-            tokens.push((token.position, term));
+        let tokens = Self::tokenize(text_analyzer, text, |token_text| {
+            Term::from_field_text(field, token_text)
         });
         Ok(tokens)
     }
 
+    /// Returns the number of leading positions out of `num_positions` whose terms are looked up
+    /// within an edit distance when `fuzzy` is set.
+    fn num_fuzzy_positions(&self, num_positions: usize) -> usize {
+        if self.fuzzy.is_none() {
+            return 0;
+        }
+        match self.mode {
+            _ if num_positions == 1 => 1,
+            FullTextMode::Bool { .. } => num_positions,
+            FullTextMode::BoolPrefix { .. } => num_positions.saturating_sub(1),
+            FullTextMode::Phrase { .. } | FullTextMode::PhraseFallbackToIntersection => 0,
+        }
+    }
+
+    fn fuzzy_term_automaton(
+        &self,
+        term: &Term,
+    ) -> Result<Option<FuzzyTermAutomaton>, InvalidQuery> {
+        let Some(fuzzy_params) = &self.fuzzy else {
+            return Ok(None);
+        };
+        let fuzzy_term_automaton = FuzzyTermAutomaton::from_term(
+            term,
+            fuzzy_params.fuzziness,
+            fuzzy_params.transpositions,
+            fuzzy_params.prefix_length,
+        )?;
+        if fuzzy_term_automaton.distance == 0 {
+            return Ok(None);
+        }
+        Ok(Some(fuzzy_term_automaton))
+    }
+
+    fn make_term_query(
+        &self,
+        term: Term,
+        index_record_option: IndexRecordOption,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        if let Some(fuzzy_term_automaton) = self.fuzzy_term_automaton(&term)? {
+            return Ok(fuzzy_term_automaton.into_tantivy_ast(term.field()));
+        }
+        Ok(TantivyTermQuery::new(term, index_record_option).into())
+    }
+
+    /// Builds a query matching any of the terms of a position.
+    fn make_position_query(
+        &self,
+        position_terms: PositionTerms,
+        index_record_option: IndexRecordOption,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        if position_terms.synonyms.is_empty() {
+            return self.make_term_query(position_terms.term, index_record_option);
+        }
+        let leaf_queries: Vec<TantivyQueryAst> = std::iter::once(position_terms.term)
+            .chain(position_terms.synonyms)
+            .map(|term| self.make_term_query(term, index_record_option))
+            .collect::<Result<_, _>>()?;
+        Ok(TantivyBoolQuery::build_clause(BooleanOperand::Or, leaf_queries).into())
+    }
+
     pub(crate) fn make_query(
         &self,
-        mut terms: Vec<(usize, Term)>,
+        mut terms: Vec<PositionTerms>,
         index_record_option: IndexRecordOption,
     ) -> Result<TantivyQueryAst, InvalidQuery> {
         if terms.is_empty() {
             return Ok(self.zero_terms_query.into());
         }
         if terms.len() == 1 {
-            let term = terms.pop().unwrap().1;
-            return Ok(TantivyTermQuery::new(term, IndexRecordOption::WithFreqs).into());
+            let position_terms = terms.pop().unwrap();
+            return self.make_position_query(position_terms, IndexRecordOption::WithFreqs);
         }
         match self.mode {
             FullTextMode::Bool { operator } => {
                 let leaf_queries: Vec<TantivyQueryAst> = terms
                     .into_iter()
-                    .map(|(_, term)| TantivyTermQuery::new(term, index_record_option).into())
-                    .collect();
+                    .map(|position_terms| {
+                        self.make_position_query(position_terms, index_record_option)
+                    })
+                    .collect::<Result<_, _>>()?;
                 Ok(TantivyBoolQuery::build_clause(operator, leaf_queries).into())
             }
             FullTextMode::BoolPrefix {
                 operator,
                 max_expansions,
             } => {
-                let term_with_prefix = terms.pop();
+                // The synonyms of the prefix are ignored: they are synonyms of a word the user
+                // is still typing.
+                let term_with_prefix = terms.pop().map(PositionTerms::into_position_term);
                 let mut leaf_queries: Vec<TantivyQueryAst> = terms
                     .into_iter()
-                    .map(|(_, term)| TantivyTermQuery::new(term, index_record_option).into())
-                    .collect();
+                    .map(|position_terms| {
+                        self.make_position_query(position_terms, index_record_option)
+                    })
+                    .collect::<Result<_, _>>()?;
                 if let Some(term_with_prefix) = term_with_prefix {
                     let mut phrase_prefix_query =
                         TantivyPhrasePrefixQuery::new_with_offset(vec![term_with_prefix]);
@@ -137,6 +254,7 @@ impl FullTextParams {
                 }
                 Ok(TantivyBoolQuery::build_clause(operator, leaf_queries).into())
             }
+            // Tantivy phrase queries do not support alternatives, so synonyms are ignored.
             FullTextMode::Phrase { slop } => {
                 if !index_record_option.has_positions() {
                     return Err(InvalidQuery::SchemaError(
@@ -144,17 +262,33 @@ impl FullTextParams {
                             .to_string(),
                     ));
                 }
+                let terms: Vec<(usize, Term)> = terms
+                    .into_iter()
+                    .map(PositionTerms::into_position_term)
+                    .collect();
                 let mut phrase_query = TantivyPhraseQuery::new_with_offset(terms);
                 phrase_query.set_slop(slop);
                 Ok(phrase_query.into())
             }
             FullTextMode::PhraseFallbackToIntersection => {
                 if index_record_option.has_positions() {
+                    let terms: Vec<(usize, Term)> = terms
+                        .into_iter()
+                        .map(PositionTerms::into_position_term)
+                        .collect();
                     Ok(TantivyPhraseQuery::new_with_offset(terms).into())
                 } else {
                     let term_query: Vec<TantivyQueryAst> = terms
                         .into_iter()
-                        .map(|(_, term)| TantivyTermQuery::new(term, index_record_option).into())
+                        .map(|position_terms| {
+                            let alternatives: Vec<TantivyQueryAst> = position_terms
+                                .terms()
+                                .map(|term| {
+                                    TantivyTermQuery::new(term.clone(), index_record_option).into()
+                                })
+                                .collect();
+                            TantivyBoolQuery::build_clause(BooleanOperand::Or, alternatives).into()
+                        })
                         .collect();
                     Ok(TantivyBoolQuery::build_clause(BooleanOperand::And, term_query).into())
                 }
@@ -277,8 +411,7 @@ impl FullTextQuery {
                         tokenizer_manager,
                     )
                     .ok()?;
-                let (_pos, term) = terms.pop()?;
-                Some(term)
+                Some(terms.pop()?.term)
             }
             FieldType::JsonObject(json_options) => {
                 let mut terms = self
@@ -291,12 +424,62 @@ impl FullTextQuery {
                         tokenizer_manager,
                     )
                     .ok()?;
-                let (_pos, term) = terms.pop()?;
-                Some(term)
+                Some(terms.pop()?.term)
             }
             _ => None,
         }
     }
+
+    /// Returns the fuzzy term lookups of the query assuming the query is targeting a string or a
+    /// Json field.
+    ///
+    /// This is used to identify which automatons should be run on the term dictionary during
+    /// warmup.
+    pub fn get_fuzzy_term_automatons(
+        &self,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+    ) -> Vec<(Field, FuzzyTermAutomaton)> {
+        if self.params.fuzzy.is_none() {
+            return Vec::new();
+        }
+        let Some((field, field_entry, json_path)) = find_field_or_hit_dynamic(&self.field, schema)
+        else {
+            return Vec::new();
+        };
+        let terms_res = match field_entry.field_type() {
+            FieldType::Str(text_options) => {
+                let Some(text_field_indexing) = text_options.get_indexing_options() else {
+                    return Vec::new();
+                };
+                self.params.tokenize_text_into_terms(
+                    field,
+                    &self.text,
+                    text_field_indexing,
+                    tokenizer_manager,
+                )
+            }
+            FieldType::JsonObject(json_options) => self.params.tokenize_text_into_terms_json(
+                field,
+                json_path,
+                &self.text,
+                json_options,
+                tokenizer_manager,
+            ),
+            _ => return Vec::new(),
+        };
+        let Ok(terms) = terms_res else {
+            return Vec::new();
+        };
+        let num_fuzzy_positions = self.params.num_fuzzy_positions(terms.len());
+        terms
+            .iter()
+            .take(num_fuzzy_positions)
+            .flat_map(PositionTerms::terms)
+            .filter_map(|term| self.params.fuzzy_term_automaton(term).ok().flatten())
+            .map(|fuzzy_term_automaton| (field, fuzzy_term_automaton))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -316,6 +499,7 @@ mod tests {
                 tokenizer: None,
                 mode: BooleanOperand::And.into(),
                 zero_terms_query: crate::MatchAllOrNone::MatchAll,
+                fuzzy: None,
             },
             lenient: false,
         };
@@ -337,6 +521,7 @@ mod tests {
                 tokenizer: None,
                 mode: FullTextMode::Phrase { slop: 1 },
                 zero_terms_query: crate::MatchAllOrNone::MatchAll,
+                fuzzy: None,
             },
             lenient: false,
         };
@@ -363,6 +548,7 @@ mod tests {
                 tokenizer: Some("raw".to_string()),
                 mode: FullTextMode::Phrase { slop: 1 },
                 zero_terms_query: crate::MatchAllOrNone::MatchAll,
+                fuzzy: None,
             },
             lenient: false,
         };
@@ -388,6 +574,7 @@ mod tests {
                 tokenizer: Some("raw".to_string()),
                 mode: FullTextMode::Phrase { slop: 1 },
                 zero_terms_query: crate::MatchAllOrNone::MatchAll,
+                fuzzy: None,
             },
             lenient: false,
         };
@@ -441,6 +628,7 @@ mod tests {
                 tokenizer: None,
                 mode: BooleanOperand::And.into(),
                 zero_terms_query: crate::MatchAllOrNone::MatchAll,
+                fuzzy: None,
             },
             lenient: false,
         };
@@ -453,4 +641,43 @@ mod tests {
         let bool_query = ast.as_bool_query().unwrap();
         assert_eq!(bool_query.must.len(), 2);
     }
+
+    #[test]
+    fn test_full_text_bool_mode_with_synonyms() {
+        use tantivy::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer};
+
+        use crate::tokenizers::SynonymFilter;
+
+        let tokenizer_manager = crate::create_default_quickwit_tokenizer_manager();
+        tokenizer_manager.register_search_analyzer(
+            "default",
+            TextAnalyzer::builder(SimpleTokenizer::default())
+                .filter(LowerCaser)
+                .filter(SynonymFilter::from_groups([["laptop", "notebook"]]))
+                .build(),
+        );
+        let full_text_query = FullTextQuery {
+            field: "body".to_string(),
+            text: "cheap laptop".to_string(),
+            params: super::FullTextParams {
+                tokenizer: None,
+                mode: BooleanOperand::And.into(),
+                zero_terms_query: crate::MatchAllOrNone::MatchAll,
+                fuzzy: None,
+            },
+            lenient: false,
+        };
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("body", TEXT);
+        let schema = schema_builder.build();
+        let context = BuildTantivyAstContext {
+            tokenizer_manager: &tokenizer_manager,
+            ..BuildTantivyAstContext::for_test(&schema)
+        };
+        let ast: TantivyQueryAst = full_text_query.build_tantivy_ast_call(&context).unwrap();
+        let bool_query = ast.as_bool_query().unwrap();
+        assert_eq!(bool_query.must.len(), 2);
+        let synonyms_query = bool_query.must[1].as_bool_query().unwrap();
+        assert_eq!(synonyms_query.should.len(), 2);
+    }
 }
@@ -0,0 +1,462 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use levenshtein_automata::{DFA, Distance, LevenshteinAutomatonBuilder, SINK_STATE};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tantivy::Term;
+use tantivy::schema::{Field, FieldType, Schema as TantivySchema, Type};
+
+use super::{BuildTantivyAst, QueryAst};
+use crate::query_ast::{AutomatonQuery, BuildTantivyAstContext, JsonPathPrefix, TantivyQueryAst};
+use crate::tokenizers::TokenizerManager;
+use crate::{InvalidQuery, find_field_or_hit_dynamic};
+
+/// Maximum edit distance of a fuzzy query. The size of the Levenshtein automata grows very fast
+/// with the distance, and larger distances match too many terms to be useful anyway.
+pub const MAX_FUZZY_DISTANCE: u8 = 2;
+
+// Building a Levenshtein automaton builder is expensive, so we build one for each distance and
+// transposition setting once and for all.
+static LEVENSHTEIN_AUTOMATON_BUILDERS: Lazy<Vec<[LevenshteinAutomatonBuilder; 2]>> =
+    Lazy::new(|| {
+        (0..=MAX_FUZZY_DISTANCE)
+            .map(|distance| {
+                [
+                    LevenshteinAutomatonBuilder::new(distance, false),
+                    LevenshteinAutomatonBuilder::new(distance, true),
+                ]
+            })
+            .collect()
+    });
+
+/// A Fuzzy query matches the terms within a given edit distance of `value`, for instance 'bond'
+/// with a query like 'bnod'.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct FuzzyQuery {
+    pub field: String,
+    pub value: String,
+    /// Maximum number of edits (insertions, deletions, substitutions) between `value` and a
+    /// matching term. Must be between 0 and 2.
+    pub distance: u8,
+    /// Whether swapping two adjacent characters counts as a single edit.
+    #[serde(default = "default_transpositions")]
+    pub transpositions: bool,
+    /// Number of leading characters of `value` that must match exactly.
+    #[serde(default)]
+    pub prefix_length: u32,
+    /// Support missing fields
+    #[serde(default)]
+    pub lenient: bool,
+}
+
+fn default_transpositions() -> bool {
+    true
+}
+
+impl From<FuzzyQuery> for QueryAst {
+    fn from(fuzzy_query: FuzzyQuery) -> Self {
+        Self::Fuzzy(fuzzy_query)
+    }
+}
+
+/// Edit-distance tolerance of a query, as expressed in the Elasticsearch `fuzziness` parameter.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Fuzziness {
+    /// The distance depends on the length of the term: 0 below `low` characters, 1 below `high`
+    /// characters, 2 otherwise.
+    Auto { low: u32, high: u32 },
+    /// A fixed distance.
+    Distance(u8),
+}
+
+impl Default for Fuzziness {
+    fn default() -> Self {
+        Fuzziness::Auto { low: 3, high: 6 }
+    }
+}
+
+impl Fuzziness {
+    /// Returns the edit distance to use for `term`.
+    pub fn distance_for_term(&self, term: &str) -> u8 {
+        match *self {
+            Fuzziness::Auto { low, high } => {
+                let num_chars = term.chars().count() as u32;
+                if num_chars < low {
+                    0
+                } else if num_chars < high {
+                    1
+                } else {
+                    2
+                }
+            }
+            Fuzziness::Distance(distance) => distance,
+        }
+    }
+}
+
+/// Parameters turning the terms of a full-text query into fuzzy term lookups.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FuzzyParams {
+    pub fuzziness: Fuzziness,
+    #[serde(default = "default_transpositions")]
+    pub transpositions: bool,
+    #[serde(default)]
+    pub prefix_length: u32,
+}
+
+/// A lookup of the terms of the term dictionary of a field within a given edit distance of a
+/// text.
+///
+/// This is also used to describe the automatons to run on the term dictionary during warmup.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FuzzyTermAutomaton {
+    /// Bytes every matching term starts with: the path of the term for json fields, followed by
+    /// the characters of the text that must match exactly.
+    pub prefix: Vec<u8>,
+    /// Text matched within `distance` edits.
+    pub text: String,
+    pub distance: u8,
+    pub transpositions: bool,
+}
+
+impl FuzzyTermAutomaton {
+    /// Builds the automaton matching the terms within the edit distance given by `fuzziness` of
+    /// the text of a str or json term.
+    pub fn from_term(
+        term: &Term,
+        fuzziness: Fuzziness,
+        transpositions: bool,
+        prefix_length: u32,
+    ) -> Result<Self, InvalidQuery> {
+        // We skip the 1st byte which is a marker to tell the type of the term. This isn't
+        // present in the dictionary.
+        let term_bytes = &term.value().as_serialized()[1..];
+        let text_start = if term.typ() == Type::Json {
+            // The path of a json term ends with a 0 byte followed by the type of the value.
+            let end_of_path = term_bytes
+                .iter()
+                .position(|byte| *byte == 0u8)
+                .context("json term has no end of path")?;
+            end_of_path + 2
+        } else {
+            0
+        };
+        let text = std::str::from_utf8(term_bytes.get(text_start..).unwrap_or_default())
+            .context("fuzzy queries only apply to text terms")?;
+        let distance = fuzziness.distance_for_term(text);
+        if distance > MAX_FUZZY_DISTANCE {
+            return Err(InvalidQuery::Other(anyhow::anyhow!(
+                "fuzzy distance must be at most {MAX_FUZZY_DISTANCE}, got {distance}"
+            )));
+        }
+        let exact_prefix_len: usize = text
+            .chars()
+            .take(prefix_length as usize)
+            .map(char::len_utf8)
+            .sum();
+        Ok(FuzzyTermAutomaton {
+            prefix: term_bytes[..text_start + exact_prefix_len].to_vec(),
+            text: text[exact_prefix_len..].to_string(),
+            distance,
+            transpositions,
+        })
+    }
+
+    pub fn to_automaton(&self) -> JsonPathPrefix<LevenshteinAutomaton> {
+        JsonPathPrefix {
+            prefix: self.prefix.clone(),
+            automaton: Arc::new(LevenshteinAutomaton::new(
+                &self.text,
+                self.distance,
+                self.transpositions,
+            )),
+        }
+    }
+
+    pub(crate) fn into_tantivy_ast(self, field: Field) -> TantivyQueryAst {
+        AutomatonQuery {
+            field,
+            automaton: Arc::new(self.to_automaton()),
+        }
+        .into()
+    }
+}
+
+/// A Levenshtein DFA matching the byte sequences within a given edit distance of a text.
+pub struct LevenshteinAutomaton(DFA);
+
+impl LevenshteinAutomaton {
+    /// Builds the automaton. `distance` must not exceed [`MAX_FUZZY_DISTANCE`].
+    pub fn new(text: &str, distance: u8, transpositions: bool) -> Self {
+        let builder = &LEVENSHTEIN_AUTOMATON_BUILDERS[distance as usize][transpositions as usize];
+        LevenshteinAutomaton(builder.build_dfa(text))
+    }
+}
+
+impl tantivy_fst::Automaton for LevenshteinAutomaton {
+    type State = u32;
+
+    fn start(&self) -> u32 {
+        self.0.initial_state()
+    }
+
+    fn is_match(&self, state: &u32) -> bool {
+        matches!(self.0.distance(*state), Distance::Exact(_))
+    }
+
+    fn can_match(&self, state: &u32) -> bool {
+        *state != SINK_STATE
+    }
+
+    fn accept(&self, state: &u32, byte: u8) -> u32 {
+        self.0.transition(*state, byte)
+    }
+}
+
+fn normalize_value(
+    value: &str,
+    tokenizer_name: &str,
+    tokenizer_manager: &TokenizerManager,
+) -> anyhow::Result<String> {
+    let mut normalizer = tokenizer_manager
+        .get_normalizer(tokenizer_name)
+        .with_context(|| format!("no tokenizer named `{tokenizer_name}` is registered"))?;
+    let mut token_stream = normalizer.token_stream(value);
+    let normalized_value = token_stream
+        .next()
+        .context("normalizer generated no content")?
+        .text
+        .clone();
+    if token_stream.next().is_some() {
+        anyhow::bail!("normalizer generated multiple tokens")
+    }
+    Ok(normalized_value)
+}
+
+impl FuzzyQuery {
+    pub fn to_fuzzy_term_automaton(
+        &self,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+    ) -> Result<(Field, FuzzyTermAutomaton), InvalidQuery> {
+        let Some((field, field_entry, json_path)) = find_field_or_hit_dynamic(&self.field, schema)
+        else {
+            return Err(InvalidQuery::FieldDoesNotExist {
+                full_path: self.field.clone(),
+            });
+        };
+        let term = match field_entry.field_type() {
+            FieldType::Str(text_options) => {
+                let text_field_indexing = text_options.get_indexing_options().ok_or_else(|| {
+                    InvalidQuery::SchemaError(format!(
+                        "field {} is not full-text searchable",
+                        field_entry.name()
+                    ))
+                })?;
+                let value = normalize_value(
+                    &self.value,
+                    text_field_indexing.tokenizer(),
+                    tokenizer_manager,
+                )?;
+                Term::from_field_text(field, &value)
+            }
+            FieldType::JsonObject(json_options) => {
+                let text_field_indexing =
+                    json_options.get_text_indexing_options().ok_or_else(|| {
+                        InvalidQuery::SchemaError(format!(
+                            "field {} is not full-text searchable",
+                            field_entry.name()
+                        ))
+                    })?;
+                let value = normalize_value(
+                    &self.value,
+                    text_field_indexing.tokenizer(),
+                    tokenizer_manager,
+                )?;
+                let mut term = Term::from_field_json_path(
+                    field,
+                    json_path,
+                    json_options.is_expand_dots_enabled(),
+                );
+                term.append_type_and_str(&value);
+                term
+            }
+            _ => {
+                return Err(InvalidQuery::SchemaError(
+                    "trying to run a Fuzzy query on a non-text field".to_string(),
+                ));
+            }
+        };
+        let fuzzy_term_automaton = FuzzyTermAutomaton::from_term(
+            &term,
+            Fuzziness::Distance(self.distance),
+            self.transpositions,
+            self.prefix_length,
+        )?;
+        Ok((field, fuzzy_term_automaton))
+    }
+}
+
+impl BuildTantivyAst for FuzzyQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        context: &BuildTantivyAstContext,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        let (field, fuzzy_term_automaton) =
+            match self.to_fuzzy_term_automaton(context.schema, context.tokenizer_manager) {
+                Ok(res) => res,
+                Err(InvalidQuery::FieldDoesNotExist { .. }) if self.lenient => {
+                    return Ok(TantivyQueryAst::match_none());
+                }
+                Err(e) => return Err(e),
+            };
+        Ok(fuzzy_term_automaton.into_tantivy_ast(field))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{STRING, TEXT};
+    use tantivy_fst::Automaton;
+
+    use super::*;
+    use crate::create_default_quickwit_tokenizer_manager;
+
+    fn automaton_matches<A: Automaton>(automaton: &A, input: &[u8]) -> bool {
+        let mut state = automaton.start();
+        for byte in input {
+            state = automaton.accept(&state, *byte);
+        }
+        automaton.is_match(&state)
+    }
+
+    #[test]
+    fn test_fuzziness_distance_for_term() {
+        let auto = Fuzziness::default();
+        assert_eq!(auto.distance_for_term("ab"), 0);
+        assert_eq!(auto.distance_for_term("abc"), 1);
+        assert_eq!(auto.distance_for_term("abcde"), 1);
+        assert_eq!(auto.distance_for_term("abcdef"), 2);
+        assert_eq!(Fuzziness::Distance(1).distance_for_term("abcdef"), 1);
+    }
+
+    #[test]
+    fn test_levenshtein_automaton() {
+        let automaton = LevenshteinAutomaton::new("bond", 1, true);
+        assert!(automaton_matches(&automaton, b"bond"));
+        assert!(automaton_matches(&automaton, b"bnod"));
+        assert!(automaton_matches(&automaton, b"bonds"));
+        assert!(!automaton_matches(&automaton, b"bands"));
+
+        let automaton = LevenshteinAutomaton::new("bond", 1, false);
+        assert!(!automaton_matches(&automaton, b"bnod"));
+    }
+
+    #[test]
+    fn test_fuzzy_query_text_field() {
+        let mut schema_builder = TantivySchema::builder();
+        schema_builder.add_text_field("text_field", TEXT);
+        let schema = schema_builder.build();
+        let tokenizer_manager = create_default_quickwit_tokenizer_manager();
+
+        let query = FuzzyQuery {
+            field: "text_field".to_string(),
+            value: "Hostname".to_string(),
+            distance: 1,
+            transpositions: true,
+            prefix_length: 2,
+            lenient: false,
+        };
+        let (_field, fuzzy_term_automaton) = query
+            .to_fuzzy_term_automaton(&schema, &tokenizer_manager)
+            .unwrap();
+        assert_eq!(fuzzy_term_automaton.prefix, b"ho");
+        assert_eq!(fuzzy_term_automaton.text, "stname");
+
+        let automaton = fuzzy_term_automaton.to_automaton();
+        assert!(automaton_matches(&automaton, b"hostnme"));
+        assert!(!automaton_matches(&automaton, b"hsotname"));
+        assert!(automaton_matches(&automaton, b"hotsname"));
+    }
+
+    #[test]
+    fn test_fuzzy_query_json_field() {
+        let mut schema_builder = TantivySchema::builder();
+        schema_builder.add_json_field("json_field", TEXT);
+        let schema = schema_builder.build();
+        let tokenizer_manager = create_default_quickwit_tokenizer_manager();
+
+        let query = FuzzyQuery {
+            field: "json_field.product".to_string(),
+            value: "laptop".to_string(),
+            distance: 1,
+            transpositions: true,
+            prefix_length: 0,
+            lenient: false,
+        };
+        let (_field, fuzzy_term_automaton) = query
+            .to_fuzzy_term_automaton(&schema, &tokenizer_manager)
+            .unwrap();
+        assert_eq!(fuzzy_term_automaton.prefix, b"product\0s");
+        assert_eq!(fuzzy_term_automaton.text, "laptop");
+
+        let automaton = fuzzy_term_automaton.to_automaton();
+        assert!(automaton_matches(&automaton, b"product\0slaptp"));
+        assert!(!automaton_matches(&automaton, b"other\0slaptop"));
+    }
+
+    #[test]
+    fn test_fuzzy_query_invalid() {
+        let mut schema_builder = TantivySchema::builder();
+        schema_builder.add_text_field("text_field", STRING);
+        schema_builder.add_u64_field("u64_field", tantivy::schema::INDEXED);
+        let schema = schema_builder.build();
+        let tokenizer_manager = create_default_quickwit_tokenizer_manager();
+
+        let mut query = FuzzyQuery {
+            field: "text_field".to_string(),
+            value: "laptop".to_string(),
+            distance: 3,
+            transpositions: true,
+            prefix_length: 0,
+            lenient: false,
+        };
+        query
+            .to_fuzzy_term_automaton(&schema, &tokenizer_manager)
+            .unwrap_err();
+
+        query.distance = 1;
+        query.field = "u64_field".to_string();
+        let error = query
+            .to_fuzzy_term_automaton(&schema, &tokenizer_manager)
+            .unwrap_err();
+        assert!(matches!(error, InvalidQuery::SchemaError(_)));
+
+        query.field = "missing_field".to_string();
+        query.lenient = true;
+        let ast = query
+            .build_tantivy_ast_call(&BuildTantivyAstContext::for_test(&schema))
+            .unwrap();
+        assert_eq!(
+            ast.const_predicate(),
+            Some(crate::MatchAllOrNone::MatchNone)
+        );
+    }
+}
@@ -22,6 +22,7 @@ mod bool_query;
 mod cache_node;
 mod field_presence;
 mod full_text_query;
+mod fuzzy_query;
 mod phrase_prefix_query;
 mod range_query;
 mod regex_query;
@@ -37,6 +38,10 @@ pub use bool_query::BoolQuery;
 pub use cache_node::{CacheNode, HitSet, PredicateCache, PredicateCacheInjector};
 pub use field_presence::FieldPresenceQuery;
 pub use full_text_query::{FullTextMode, FullTextParams, FullTextQuery};
+pub use fuzzy_query::{
+    Fuzziness, FuzzyParams, FuzzyQuery, FuzzyTermAutomaton, LevenshteinAutomaton,
+    MAX_FUZZY_DISTANCE,
+};
 pub use phrase_prefix_query::PhrasePrefixQuery;
 pub use range_query::RangeQuery;
 pub use regex_query::{AutomatonQuery, JsonPathPrefix, RegexQuery};
@@ -63,6 +68,7 @@ pub enum QueryAst {
     UserInput(UserInputQuery),
     Wildcard(WildcardQuery),
     Regex(RegexQuery),
+    Fuzzy(FuzzyQuery),
     MatchAll,
     MatchNone,
     Boost {
@@ -107,7 +113,8 @@ impl QueryAst {
             | ast @ QueryAst::FieldPresence(_)
             | ast @ QueryAst::Range(_)
             | ast @ QueryAst::Wildcard(_)
-            | ast @ QueryAst::Regex(_) => Ok(ast),
+            | ast @ QueryAst::Regex(_)
+            | ast @ QueryAst::Fuzzy(_) => Ok(ast),
             QueryAst::UserInput(user_text_query) => {
                 user_text_query.parse_user_query(default_search_fields)
             }
@@ -254,6 +261,7 @@ impl BuildTantivyAst for QueryAst {
             }
             QueryAst::Wildcard(wildcard) => wildcard.build_tantivy_ast_call(context),
             QueryAst::Regex(regex) => regex.build_tantivy_ast_call(context),
+            QueryAst::Fuzzy(fuzzy) => fuzzy.build_tantivy_ast_call(context),
             QueryAst::Cache(cache_node) => cache_node.build_tantivy_ast_call(context),
         }
     }
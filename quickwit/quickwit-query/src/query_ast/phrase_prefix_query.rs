@@ -17,6 +17,7 @@ use tantivy::Term;
 use tantivy::query::PhrasePrefixQuery as TantivyPhrasePrefixQuery;
 use tantivy::schema::{Field, FieldType, Schema as TantivySchema};
 
+use crate::query_ast::full_text_query::PositionTerms;
 use crate::query_ast::tantivy_query_ast::TantivyQueryAst;
 use crate::query_ast::{BuildTantivyAst, BuildTantivyAstContext, FullTextParams, QueryAst};
 use crate::tokenizers::TokenizerManager;
@@ -63,6 +64,10 @@ impl PhrasePrefixQuery {
                     text_field_indexing,
                     tokenizer_manager,
                 )?;
+                let terms: Vec<(usize, Term)> = terms
+                    .into_iter()
+                    .map(PositionTerms::into_position_term)
+                    .collect();
                 if !text_field_indexing.index_option().has_positions() && terms.len() > 1 {
                     return Err(InvalidQuery::SchemaError(
                         "trying to run a phrase prefix query on a field which does not have \
@@ -87,6 +92,10 @@ impl PhrasePrefixQuery {
                     json_options,
                     tokenizer_manager,
                 )?;
+                let terms: Vec<(usize, Term)> = terms
+                    .into_iter()
+                    .map(PositionTerms::into_position_term)
+                    .collect();
                 if !text_field_indexing.index_option().has_positions() && terms.len() > 1 {
                     return Err(InvalidQuery::SchemaError(
                         "trying to run a PhrasePrefix query on a field which does not have \
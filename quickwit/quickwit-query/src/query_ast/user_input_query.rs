@@ -269,8 +269,13 @@ fn convert_user_input_literal(
         tokenizer: None,
         mode,
         zero_terms_query: crate::MatchAllOrNone::MatchNone,
+        fuzzy: None,
     };
     let wildcard = delimiter == Delimiter::None && is_wildcard(&phrase);
+    // On a term that is not quoted, `~N` is an edit distance rather than a slop. Like
+    // Elasticsearch, we cap it to the maximum distance we support.
+    let fuzzy_distance_opt = (delimiter == Delimiter::None && slop > 0)
+        .then(|| slop.min(query_ast::MAX_FUZZY_DISTANCE as u32) as u8);
     let mut phrase_queries: Vec<QueryAst> = field_names
         .into_iter()
         .map(|field_name| {
@@ -291,6 +296,16 @@ fn convert_user_input_literal(
                     case_insensitive: false,
                 }
                 .into()
+            } else if let Some(distance) = fuzzy_distance_opt {
+                query_ast::FuzzyQuery {
+                    field: field_name,
+                    value: phrase.clone(),
+                    distance,
+                    transpositions: true,
+                    prefix_length: 0,
+                    lenient,
+                }
+                .into()
             } else {
                 query_ast::FullTextQuery {
                     field: field_name,
@@ -555,4 +570,31 @@ mod tests {
         assert_eq!(&regex_query.field, "field");
         assert_eq!(&regex_query.regex, ".*");
     }
+
+    #[test]
+    fn test_user_input_query_fuzzy() {
+        let parse_user_query_fuzzy_util = |query: &str| {
+            let ast = UserInputQuery {
+                user_text: query.to_string(),
+                default_fields: None,
+                default_operator: BooleanOperand::Or,
+                lenient: false,
+            }
+            .parse_user_query(&["host".to_string()])
+            .unwrap();
+            let QueryAst::Fuzzy(fuzzy_query) = ast else {
+                panic!("expected a fuzzy query, got {ast:?}")
+            };
+            fuzzy_query
+        };
+        let fuzzy_query = parse_user_query_fuzzy_util("product:laptpo~1");
+        assert_eq!(&fuzzy_query.field, "product");
+        assert_eq!(&fuzzy_query.value, "laptpo");
+        assert_eq!(fuzzy_query.distance, 1);
+        assert!(fuzzy_query.transpositions);
+
+        let fuzzy_query = parse_user_query_fuzzy_util("webserver~5");
+        assert_eq!(&fuzzy_query.field, "host");
+        assert_eq!(fuzzy_query.distance, 2);
+    }
 }
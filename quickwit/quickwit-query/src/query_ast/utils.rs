@@ -23,7 +23,7 @@ use tantivy::schema::{
 use crate::InvalidQuery;
 use crate::MatchAllOrNone::MatchNone as TantivyEmptyQuery;
 use crate::json_literal::InterpretUserInput;
-use crate::query_ast::full_text_query::FullTextParams;
+use crate::query_ast::full_text_query::{FullTextParams, PositionTerms};
 use crate::query_ast::tantivy_query_ast::{TantivyBoolQuery, TantivyQueryAst};
 use crate::tokenizers::{RAW_TOKENIZER_NAME, TokenizerManager};
 
@@ -210,7 +210,7 @@ fn compute_tantivy_ast_query_for_json(
             .should
             .push(TantivyTermQuery::new(term, IndexRecordOption::Basic).into());
     }
-    let position_terms: Vec<(usize, Term)> = full_text_params.tokenize_text_into_terms_json(
+    let position_terms: Vec<PositionTerms> = full_text_params.tokenize_text_into_terms_json(
         field,
         json_path,
         text,
@@ -17,8 +17,8 @@ use crate::query_ast::cache_node::CacheState;
 use crate::query_ast::field_presence::FieldPresenceQuery;
 use crate::query_ast::user_input_query::UserInputQuery;
 use crate::query_ast::{
-    BoolQuery, CacheNode, FullTextQuery, PhrasePrefixQuery, QueryAst, RangeQuery, RegexQuery,
-    TermQuery, TermSetQuery, WildcardQuery,
+    BoolQuery, CacheNode, FullTextQuery, FuzzyQuery, PhrasePrefixQuery, QueryAst, RangeQuery,
+    RegexQuery, TermQuery, TermSetQuery, WildcardQuery,
 };
 
 /// Simple trait to implement a Visitor over the QueryAst.
@@ -42,6 +42,7 @@ pub trait QueryAstVisitor<'a> {
             QueryAst::FieldPresence(exists) => self.visit_exists(exists),
             QueryAst::Wildcard(wildcard) => self.visit_wildcard(wildcard),
             QueryAst::Regex(regex) => self.visit_regex(regex),
+            QueryAst::Fuzzy(fuzzy) => self.visit_fuzzy(fuzzy),
             QueryAst::Cache(cache_node) => self.visit_cache_node(cache_node),
         }
     }
@@ -114,6 +115,10 @@ pub trait QueryAstVisitor<'a> {
         Ok(())
     }
 
+    fn visit_fuzzy(&mut self, _fuzzy_query: &'a FuzzyQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
     fn visit_cache_node(&mut self, cache_node: &'a CacheNode) -> Result<(), Self::Err> {
         // this goes a bit again how the rest of the default Visitor behave. The rational is that in
         // practice, on a cache hit, we don't want to do anything with that node.
@@ -149,6 +154,7 @@ pub trait QueryAstTransformer {
             QueryAst::FieldPresence(exists) => self.transform_exists(exists),
             QueryAst::Wildcard(wildcard) => self.transform_wildcard(wildcard),
             QueryAst::Regex(regex) => self.transform_regex(regex),
+            QueryAst::Fuzzy(fuzzy) => self.transform_fuzzy(fuzzy),
             QueryAst::Cache(cache_node) => self.transform_cache_node(cache_node),
         }
     }
@@ -253,6 +259,10 @@ pub trait QueryAstTransformer {
         Ok(Some(QueryAst::Regex(regex_query)))
     }
 
+    fn transform_fuzzy(&mut self, fuzzy_query: FuzzyQuery) -> Result<Option<QueryAst>, Self::Err> {
+        Ok(Some(QueryAst::Fuzzy(fuzzy_query)))
+    }
+
     fn transform_cache_node(
         &mut self,
         cache_node: CacheNode,
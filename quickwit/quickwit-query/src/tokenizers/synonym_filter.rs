@@ -0,0 +1,160 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tantivy::tokenizer::{Token, TokenFilter, TokenStream, Tokenizer};
+
+/// A token filter emitting, after each token, its synonyms at the same position.
+///
+/// For instance, with the synonym group `[laptop, notebook]`, `cheap laptop` is analyzed as
+/// `[cheap, laptop, notebook]`, where `laptop` and `notebook` share the same position.
+///
+/// Synonyms are single tokens and are matched against the output of the previous filters, so
+/// they usually need to be lowercase.
+#[derive(Clone, Default)]
+pub struct SynonymFilter {
+    synonyms: Arc<HashMap<String, Vec<String>>>,
+}
+
+impl SynonymFilter {
+    /// Creates a synonym filter from groups of equivalent words.
+    pub fn from_groups<G, W>(groups: impl IntoIterator<Item = G>) -> Self
+    where
+        G: IntoIterator<Item = W>,
+        W: AsRef<str>,
+    {
+        let mut synonyms: HashMap<String, Vec<String>> = HashMap::new();
+        for group in groups {
+            let words: Vec<String> = group
+                .into_iter()
+                .map(|word| word.as_ref().to_string())
+                .collect();
+            for word in &words {
+                let word_synonyms = synonyms.entry(word.clone()).or_default();
+                for synonym in &words {
+                    if synonym != word && !word_synonyms.contains(synonym) {
+                        word_synonyms.push(synonym.clone());
+                    }
+                }
+            }
+        }
+        SynonymFilter {
+            synonyms: Arc::new(synonyms),
+        }
+    }
+}
+
+impl TokenFilter for SynonymFilter {
+    type Tokenizer<T: Tokenizer> = SynonymFilterWrapper<T>;
+
+    fn transform<T: Tokenizer>(self, tokenizer: T) -> SynonymFilterWrapper<T> {
+        SynonymFilterWrapper {
+            synonyms: self.synonyms,
+            inner: tokenizer,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SynonymFilterWrapper<T> {
+    synonyms: Arc<HashMap<String, Vec<String>>>,
+    inner: T,
+}
+
+impl<T: Tokenizer> Tokenizer for SynonymFilterWrapper<T> {
+    type TokenStream<'a> = SynonymTokenStream<'a, T::TokenStream<'a>>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        SynonymTokenStream {
+            synonyms: &self.synonyms,
+            tail: self.inner.token_stream(text),
+            pending_synonyms: &[],
+            token: Token::default(),
+        }
+    }
+}
+
+pub struct SynonymTokenStream<'a, T> {
+    synonyms: &'a HashMap<String, Vec<String>>,
+    tail: T,
+    // Synonyms of the last token of `tail` that remain to be emitted.
+    pending_synonyms: &'a [String],
+    token: Token,
+}
+
+impl<T: TokenStream> TokenStream for SynonymTokenStream<'_, T> {
+    fn advance(&mut self) -> bool {
+        if let Some((synonym, pending_synonyms)) = self.pending_synonyms.split_first() {
+            self.token.text.clone_from(synonym);
+            self.pending_synonyms = pending_synonyms;
+            return true;
+        }
+        if !self.tail.advance() {
+            return false;
+        }
+        self.token.clone_from(self.tail.token());
+        self.pending_synonyms = self
+            .synonyms
+            .get(&self.token.text)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        true
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer};
+
+    use super::*;
+
+    #[test]
+    fn test_synonym_filter() {
+        let synonym_filter = SynonymFilter::from_groups([
+            vec!["laptop", "notebook"],
+            vec!["tv", "television", "telly"],
+        ]);
+        let mut analyzer = TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(LowerCaser)
+            .filter(synonym_filter)
+            .build();
+        let mut token_stream = analyzer.token_stream("Cheap Laptop and TV");
+        let mut tokens = Vec::new();
+        token_stream.process(&mut |token| {
+            tokens.push((token.text.clone(), token.position));
+        });
+        assert_eq!(
+            tokens,
+            [
+                ("cheap".to_string(), 0),
+                ("laptop".to_string(), 1),
+                ("notebook".to_string(), 1),
+                ("and".to_string(), 2),
+                ("tv".to_string(), 3),
+                ("television".to_string(), 3),
+                ("telly".to_string(), 3),
+            ]
+        );
+    }
+}
@@ -30,6 +30,8 @@ const RAW_LOWERCASE_TOKENIZER_NAME: &str = "raw_lowercase";
 pub struct TokenizerManager {
     inner: TantivyTokenizerManager,
     is_lowercaser: Arc<RwLock<HashMap<String, bool>>>,
+    // Analyzers used in place of the tokenizer of the same name when analyzing queries.
+    search_analyzers: Arc<RwLock<HashMap<String, TextAnalyzer>>>,
 }
 
 impl TokenizerManager {
@@ -38,6 +40,7 @@ impl TokenizerManager {
         let this = Self {
             inner: TantivyTokenizerManager::new(),
             is_lowercaser: Arc::new(RwLock::new(HashMap::new())),
+            search_analyzers: Arc::new(RwLock::new(HashMap::new())),
         };
 
         // in practice these will almost always be overridden in
@@ -70,11 +73,29 @@ impl TokenizerManager {
             .insert(tokenizer_name.to_string(), does_lowercasing);
     }
 
+    /// Registers the analyzer used in place of the tokenizer `tokenizer_name` when analyzing
+    /// queries, for instance to expand synonyms at search time only.
+    pub fn register_search_analyzer(&self, tokenizer_name: &str, search_analyzer: TextAnalyzer) {
+        self.search_analyzers
+            .write()
+            .unwrap()
+            .insert(tokenizer_name.to_string(), search_analyzer);
+    }
+
     /// Accessing a tokenizer given its name.
     pub fn get_tokenizer(&self, tokenizer_name: &str) -> Option<TextAnalyzer> {
         self.inner.get(tokenizer_name)
     }
 
+    /// Accessing the tokenizer used to analyze queries given its name. This is the tokenizer
+    /// itself unless a search analyzer was registered for it.
+    pub fn get_search_tokenizer(&self, tokenizer_name: &str) -> Option<TextAnalyzer> {
+        if let Some(search_analyzer) = self.search_analyzers.read().unwrap().get(tokenizer_name) {
+            return Some(search_analyzer.clone());
+        }
+        self.get_tokenizer(tokenizer_name)
+    }
+
     /// Query whether a given tokenizer does lowercasing
     pub fn get_normalizer(&self, tokenizer_name: &str) -> Option<TextAnalyzer> {
         let use_lowercaser = self
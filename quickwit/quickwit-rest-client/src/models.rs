@@ -15,6 +15,7 @@
 use std::path::PathBuf;
 use std::time::Duration;
 
+use bytes::Bytes;
 use reqwest::StatusCode;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
@@ -44,6 +45,20 @@ impl ApiResponse {
         Ok(())
     }
 
+    /// Checks status and returns the response if it is not an error.
+    pub async fn check_status(self) -> Result<Self, Error> {
+        if self.inner.status().is_client_error() || self.inner.status().is_server_error() {
+            return Err(self.api_error().await);
+        }
+        Ok(self)
+    }
+
+    /// Reads the next chunk of the response body, or `None` once the body is exhausted.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, Error> {
+        let chunk_opt = self.inner.chunk().await?;
+        Ok(chunk_opt)
+    }
+
     async fn extract_error_message(self) -> Option<String> {
         let error_body_bytes = self.inner.bytes().await.ok()?;
         let error_body_text = std::str::from_utf8(&error_body_bytes).ok()?;
@@ -82,9 +97,13 @@ pub struct SearchResponseRestClient {
     pub num_hits: u64,
     pub hits: Vec<JsonValue>,
     pub snippets: Option<Vec<JsonValue>>,
+    pub fields: Option<Vec<JsonValue>>,
     pub elapsed_time_micros: u64,
     pub errors: Vec<String>,
     pub aggregations: Option<JsonValue>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 #[derive(Clone)]
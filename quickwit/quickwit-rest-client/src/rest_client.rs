@@ -23,7 +23,8 @@ pub use quickwit_ingest::CommitType;
 use quickwit_metastore::{IndexMetadata, Split, SplitInfo};
 use quickwit_proto::ingest::Shard;
 use quickwit_serve::{
-    ListSplitsQueryParams, ListSplitsResponse, RestIngestResponse, SearchRequestQueryString,
+    ExportRequestQueryString, ListSplitsQueryParams, ListSplitsResponse, RestIngestResponse,
+    SearchRequestQueryString,
 };
 use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue};
 use reqwest::tls::Certificate;
@@ -276,6 +277,28 @@ impl QuickwitClient {
         Ok(search_response)
     }
 
+    /// Exports all the documents matching a query. The response body is streamed and should be
+    /// read with [`ApiResponse::chunk`]. No timeout applies to exports.
+    pub async fn export(
+        &self,
+        index_id: &str,
+        export_query: &ExportRequestQueryString,
+    ) -> Result<ApiResponse, Error> {
+        let path = format!("{index_id}/export");
+        let response = self
+            .transport
+            .send(
+                Method::GET,
+                &path,
+                None,
+                Some(export_query),
+                None,
+                Timeout::none(),
+            )
+            .await?;
+        response.check_status().await
+    }
+
     pub fn indexes(&self) -> IndexClient<'_> {
         IndexClient::new(&self.transport, self.timeout)
     }
@@ -802,7 +825,8 @@ mod test {
     use quickwit_ingest::CommitType;
     use quickwit_metastore::IndexMetadata;
     use quickwit_serve::{
-        ListSplitsQueryParams, ListSplitsResponse, RestIngestResponse, SearchRequestQueryString,
+        ExportFormat, ExportRequestQueryString, ListSplitsQueryParams, ListSplitsResponse,
+        RestIngestResponse, SearchRequestQueryString,
     };
     use reqwest::Url;
     use reqwest::header::CONTENT_TYPE;
@@ -840,9 +864,11 @@ mod test {
             num_hits: 0,
             hits: Vec::new(),
             snippets: None,
+            fields: None,
             aggregations: None,
             elapsed_time_micros: 100,
             errors: Vec::new(),
+            warnings: Vec::new(),
         };
         Mock::given(method("POST"))
             .and(path("/api/v1/my-index/search"))
@@ -861,6 +887,51 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_export_endpoint() {
+        let mock_server = MockServer::start().await;
+        let server_url = Url::parse(&mock_server.uri()).unwrap();
+        let qw_client = QuickwitClientBuilder::new(server_url).build();
+        let export_query = ExportRequestQueryString {
+            query: "severity:error".to_string(),
+            fields: Some(vec!["timestamp".to_string(), "message".to_string()]),
+            format: ExportFormat::Csv,
+            ..Default::default()
+        };
+        Mock::given(method("GET"))
+            .and(path("/api/v1/my-index/export"))
+            .and(query_param("query", "severity:error"))
+            .and(query_param("format", "csv"))
+            .and(query_param("fields", "timestamp,message"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK).set_body_string("timestamp,message\n1,a\n"),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        let mut response = qw_client.export("my-index", &export_query).await.unwrap();
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.unwrap() {
+            body.extend_from_slice(&chunk);
+        }
+        assert_eq!(body, b"timestamp,message\n1,a\n");
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/my-index/export"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::BAD_REQUEST)
+                    .set_body_json(json!({"message": "invalid query"})),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        let error = qw_client
+            .export("my-index", &export_query)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Api(_)));
+    }
+
     fn get_ndjson_filepath(ndjson_dataset_filename: &str) -> String {
         format!(
             "{}/resources/tests/{}",
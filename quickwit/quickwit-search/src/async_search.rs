@@ -0,0 +1,775 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Asynchronous search.
+//!
+//! An async search runs a root search in a background task of the searcher that received it. The
+//! splits are searched in small leaf requests, so that partial results can be published as they
+//! complete. The state of the search and its latest results are stored in the search KV store
+//! under the ID of the search, so that they can be fetched from any searcher until they expire.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use futures::StreamExt;
+use futures::stream::{self, BoxStream, FuturesUnordered};
+use quickwit_metastore::SplitMetadata;
+use quickwit_proto::metastore::MetastoreServiceClient;
+use quickwit_proto::search::{LeafSearchResponse, SearchRequest, SearchResponse};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{info, warn};
+use ulid::Ulid;
+
+use crate::collector::{IncrementalCollector, make_merge_collector};
+use crate::pipeline_aggregations::PipelineAggregations;
+use crate::root::{
+    IndexesMetasForLeafSearch, SearchJob, check_num_targeted_splits, fetch_docs_phase,
+    finalize_aggregation_if_any, get_count_from_metadata, is_metadata_count_request,
+    jobs_to_leaf_request, plan_splits_for_root_search,
+};
+use crate::service::SearcherContext;
+use crate::two_phase_terms::TwoPhaseTermsAggregations;
+use crate::{ClusterClient, SearchError};
+
+const ASYNC_SEARCH_KEY_PREFIX: &[u8] = b"async_search:";
+
+/// Maximum number of splits searched by a single leaf request. Partial results are only updated
+/// when a leaf request completes, so smaller leaf requests make for more frequent updates.
+const MAX_SPLITS_PER_LEAF_REQUEST: usize = 100;
+
+/// Minimum interval between two publications of the partial results of a running search.
+const PARTIAL_RESULTS_PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval at which the state of a running search is polled while waiting for its completion.
+const WAIT_FOR_COMPLETION_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Maximum size of the serialized state of an async search. The search KV store is shared with
+/// scroll contexts, so a search whose results exceed this size fails instead of being stored.
+const MAX_ASYNC_SEARCH_ENTRY_NUM_BYTES: usize = 10 * 1024 * 1024;
+
+/// Request submitting an async search.
+#[derive(Debug, Clone)]
+pub struct AsyncSearchRequest {
+    /// The search to run.
+    pub search_request: SearchRequest,
+    /// How long to wait for the search to complete before returning its partial results.
+    pub wait_for_completion_timeout: Duration,
+    /// How long the search and its results are kept, from its submission. A search still running
+    /// after that is stopped.
+    pub keep_alive: Duration,
+    /// Opaque context returned along with the state of the search, for instance to record how
+    /// the results should be rendered.
+    pub context: String,
+}
+
+/// Request fetching the state of an async search.
+#[derive(Debug, Clone)]
+pub struct GetAsyncSearchRequest {
+    /// ID of the async search.
+    pub id: String,
+    /// How long to wait for the search to complete before returning its partial results.
+    pub wait_for_completion_timeout: Duration,
+    /// If set, the results of a completed search are kept for this long from now.
+    pub keep_alive_opt: Option<Duration>,
+}
+
+/// State and latest results of an async search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsyncSearchResponse {
+    /// ID of the async search.
+    pub id: String,
+    /// Whether the search is still running.
+    pub is_running: bool,
+    /// Whether the results are partial: the search is still running, failed, or could not
+    /// search some splits.
+    pub is_partial: bool,
+    /// Submission time of the search, in milliseconds since the Unix epoch.
+    pub start_time_millis: i64,
+    /// Time after which the search and its results are deleted, in milliseconds since the Unix
+    /// epoch.
+    pub expiration_time_millis: i64,
+    /// Completion time of the search, in milliseconds since the Unix epoch.
+    pub completion_time_millis: Option<i64>,
+    /// The latest results of the search, if any.
+    pub search_response: Option<SearchResponse>,
+    /// The error that made the search fail, if any.
+    pub error: Option<SearchError>,
+    /// Opaque context provided on submission.
+    pub context: String,
+}
+
+/// Entry of the search KV store holding an async search. Deleted searches are replaced by a
+/// tombstone, so that their background task stops publishing results.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AsyncSearchEntry {
+    Search(AsyncSearchResponse),
+    Deleted,
+}
+
+/// Submits an async search, and waits up to `wait_for_completion_timeout` for its completion.
+pub async fn submit_async_search(
+    async_search_request: AsyncSearchRequest,
+    searcher_context: Arc<SearcherContext>,
+    metastore: MetastoreServiceClient,
+    cluster_client: ClusterClient,
+) -> crate::Result<AsyncSearchResponse> {
+    let AsyncSearchRequest {
+        mut search_request,
+        wait_for_completion_timeout,
+        keep_alive,
+        context,
+    } = async_search_request;
+
+    if search_request.scroll_ttl_secs.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "scroll is not supported by async search".to_string(),
+        ));
+    }
+    if search_request.knn.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "kNN search is not supported by async search".to_string(),
+        ));
+    }
+    if keep_alive.is_zero() {
+        return Err(SearchError::InvalidArgument(
+            "the keep alive of an async search must be positive".to_string(),
+        ));
+    }
+    search_request.profile = false;
+
+    let id = Ulid::new().to_string();
+    let key = async_search_key(&id)?;
+    let start_time_millis = now_millis();
+    let async_search_response = AsyncSearchResponse {
+        id,
+        is_running: true,
+        is_partial: true,
+        start_time_millis,
+        expiration_time_millis: start_time_millis + keep_alive.as_millis() as i64,
+        completion_time_millis: None,
+        search_response: None,
+        error: None,
+        context,
+    };
+    store_async_search_entry(
+        &cluster_client,
+        &key,
+        &AsyncSearchEntry::Search(async_search_response.clone()),
+        async_search_response.expiration_time_millis,
+    )
+    .await?;
+
+    let (state_tx, mut state_rx) = watch::channel(async_search_response);
+    let async_search_task = AsyncSearchTask {
+        key,
+        cluster_client,
+        searcher_context,
+        state_tx,
+    };
+    tokio::spawn(async_search_task.run(search_request, metastore));
+
+    let _ = tokio::time::timeout(
+        wait_for_completion_timeout,
+        state_rx.wait_for(|async_search_response| !async_search_response.is_running),
+    )
+    .await;
+    let async_search_response = state_rx.borrow().clone();
+    Ok(async_search_response)
+}
+
+/// Returns the state of an async search, waiting up to `wait_for_completion_timeout` for its
+/// completion.
+pub async fn get_async_search(
+    get_async_search_request: GetAsyncSearchRequest,
+    cluster_client: &ClusterClient,
+) -> crate::Result<AsyncSearchResponse> {
+    let GetAsyncSearchRequest {
+        id,
+        wait_for_completion_timeout,
+        keep_alive_opt,
+    } = get_async_search_request;
+
+    let key = async_search_key(&id)?;
+    let deadline = Instant::now() + wait_for_completion_timeout;
+    let mut async_search_response = load_async_search(cluster_client, &key, &id).await?;
+
+    while async_search_response.is_running {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        tokio::time::sleep((deadline - now).min(WAIT_FOR_COMPLETION_POLL_INTERVAL)).await;
+        async_search_response = load_async_search(cluster_client, &key, &id).await?;
+    }
+    // The background task of a running search owns its state, so the keep alive of a search can
+    // only be extended once it has completed.
+    if let Some(keep_alive) = keep_alive_opt
+        && !async_search_response.is_running
+    {
+        async_search_response.expiration_time_millis = now_millis() + keep_alive.as_millis() as i64;
+        store_async_search_entry(
+            cluster_client,
+            &key,
+            &AsyncSearchEntry::Search(async_search_response.clone()),
+            async_search_response.expiration_time_millis,
+        )
+        .await?;
+    }
+    Ok(async_search_response)
+}
+
+/// Deletes an async search and its results. A running search is stopped.
+pub async fn delete_async_search(id: String, cluster_client: &ClusterClient) -> crate::Result<()> {
+    let key = async_search_key(&id)?;
+    let async_search_response = load_async_search(cluster_client, &key, &id).await?;
+    // The background task of a running search stops at the latest on expiration, so the tombstone
+    // does not need to outlive the search.
+    store_async_search_entry(
+        cluster_client,
+        &key,
+        &AsyncSearchEntry::Deleted,
+        async_search_response.expiration_time_millis,
+    )
+    .await?;
+    Ok(())
+}
+
+fn async_search_key(id: &str) -> crate::Result<Vec<u8>> {
+    let ulid = Ulid::from_string(id)
+        .map_err(|_| SearchError::InvalidArgument(format!("invalid async search ID `{id}`")))?;
+    let mut key = ASYNC_SEARCH_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&u128::from(ulid).to_le_bytes());
+    Ok(key)
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn serialize_async_search_entry(async_search_entry: &AsyncSearchEntry) -> crate::Result<Vec<u8>> {
+    let payload = serde_json::to_vec(async_search_entry)
+        .expect("serializing an async search should never fail");
+    if payload.len() > MAX_ASYNC_SEARCH_ENTRY_NUM_BYTES {
+        return Err(SearchError::InvalidArgument(format!(
+            "the results of the async search are too large to be stored ({} bytes, the maximum is \
+             {MAX_ASYNC_SEARCH_ENTRY_NUM_BYTES} bytes), reduce the number of hits or of \
+             aggregation buckets",
+            payload.len()
+        )));
+    }
+    Ok(payload)
+}
+
+async fn store_async_search_entry(
+    cluster_client: &ClusterClient,
+    key: &[u8],
+    async_search_entry: &AsyncSearchEntry,
+    expiration_time_millis: i64,
+) -> crate::Result<()> {
+    let payload = serialize_async_search_entry(async_search_entry)?;
+    // The TTL of the KV store has a granularity of one second.
+    let ttl_millis = (expiration_time_millis - now_millis()).max(1_000) as u64;
+    cluster_client
+        .put_kv(key, &payload, Duration::from_millis(ttl_millis))
+        .await;
+    Ok(())
+}
+
+async fn load_async_search_entry(
+    cluster_client: &ClusterClient,
+    key: &[u8],
+) -> crate::Result<Option<AsyncSearchEntry>> {
+    let Some(payload) = cluster_client.get_kv(key).await else {
+        return Ok(None);
+    };
+    let async_search_entry: AsyncSearchEntry = serde_json::from_slice(&payload)?;
+    Ok(Some(async_search_entry))
+}
+
+async fn load_async_search(
+    cluster_client: &ClusterClient,
+    key: &[u8],
+    id: &str,
+) -> crate::Result<AsyncSearchResponse> {
+    match load_async_search_entry(cluster_client, key).await? {
+        Some(AsyncSearchEntry::Search(async_search_response)) => Ok(async_search_response),
+        Some(AsyncSearchEntry::Deleted) | None => Err(SearchError::NotFound(format!(
+            "async search `{id}` does not exist or has expired"
+        ))),
+    }
+}
+
+/// Runs an async search in the background and publishes its state.
+struct AsyncSearchTask {
+    key: Vec<u8>,
+    cluster_client: ClusterClient,
+    searcher_context: Arc<SearcherContext>,
+    // Holds the latest published state, and notifies the submitter of the search waiting for
+    // its completion.
+    state_tx: watch::Sender<AsyncSearchResponse>,
+}
+
+impl AsyncSearchTask {
+    async fn run(self, search_request: SearchRequest, metastore: MetastoreServiceClient) {
+        let start_instant = Instant::now();
+        let search_result = self.search(search_request, metastore, start_instant).await;
+
+        let mut async_search_response = self.state_tx.borrow().clone();
+        async_search_response.is_running = false;
+        async_search_response.completion_time_millis = Some(now_millis());
+
+        match search_result {
+            Ok(Some(search_response)) => {
+                async_search_response.is_partial = !search_response.failed_splits.is_empty();
+                async_search_response.search_response = Some(search_response);
+            }
+            // The search was deleted or has expired.
+            Ok(None) => return,
+            Err(search_error) => {
+                warn!(id=%async_search_response.id, error=%search_error, "async search failed");
+                // The last partial results, if any, are kept.
+                async_search_response.error = Some(search_error);
+            }
+        }
+        if let Err(search_error) = self.publish(async_search_response.clone()).await {
+            warn!(id=%async_search_response.id, error=%search_error, "async search failed");
+            // The final results are too large to be stored, unlike the last partial results.
+            async_search_response.is_partial = true;
+            async_search_response.search_response = self.state_tx.borrow().search_response.clone();
+            async_search_response.error = Some(search_error);
+
+            if let Err(search_error) = self.publish(async_search_response).await {
+                warn!(error=%search_error, "failed to publish async search error");
+            }
+        }
+    }
+
+    /// Runs the search, publishing partial results along the way. Returns `None` if the search
+    /// was deleted or has expired in the meantime.
+    async fn search(
+        &self,
+        mut search_request: SearchRequest,
+        mut metastore: MetastoreServiceClient,
+        start_instant: Instant,
+    ) -> crate::Result<Option<SearchResponse>> {
+        let pipeline_aggregations_opt =
+            PipelineAggregations::extract_from_request(&mut search_request)?;
+        if TwoPhaseTermsAggregations::extract_from_request(&mut search_request)?.is_some() {
+            return Err(SearchError::InvalidArgument(
+                "significant_terms and rare_terms aggregations are not supported by async search"
+                    .to_string(),
+            ));
+        }
+        let (split_metadatas, indexes_metas_for_leaf_search, runtime_fields_plan_opt) =
+            plan_splits_for_root_search(&mut search_request, &mut metastore).await?;
+        if runtime_fields_plan_opt.is_some() {
+            return Err(SearchError::InvalidArgument(
+                "runtime fields are not supported by async search".to_string(),
+            ));
+        }
+        let num_splits = split_metadatas.len();
+        check_num_targeted_splits(&self.searcher_context, &search_request, num_splits)?;
+
+        info!(
+            query_ast = search_request.query_ast.as_str(),
+            agg = search_request.aggregation_request(),
+            num_splits,
+            "async_search"
+        );
+        let merge_collector = make_merge_collector(
+            &search_request,
+            self.searcher_context.get_aggregation_limits(),
+        )?;
+        let mut incremental_collector = IncrementalCollector::new(merge_collector);
+        let mut leaf_search_responses = self
+            .leaf_search_stream(
+                &search_request,
+                &indexes_metas_for_leaf_search,
+                &split_metadatas,
+            )
+            .await?;
+        let mut last_publish_instant = Instant::now();
+
+        while let Some(leaf_search_response_res) = leaf_search_responses.next().await {
+            let leaf_search_response = leaf_search_response_res?;
+            incremental_collector = run_merge(move || {
+                incremental_collector.add_result(leaf_search_response)?;
+                Ok(incremental_collector)
+            })
+            .await?;
+
+            if last_publish_instant.elapsed() < PARTIAL_RESULTS_PUBLISH_INTERVAL {
+                continue;
+            }
+            let partial_incremental_collector = incremental_collector.clone();
+            let partial_leaf_search_response =
+                run_merge(move || partial_incremental_collector.finalize()).await?;
+            let mut partial_search_response = self
+                .build_search_response(
+                    partial_leaf_search_response,
+                    &search_request,
+                    &indexes_metas_for_leaf_search,
+                    &split_metadatas,
+                    start_instant,
+                )
+                .await?;
+            if let Some(pipeline_aggregations) = &pipeline_aggregations_opt {
+                pipeline_aggregations.apply(&mut partial_search_response)?;
+            }
+            let mut async_search_response = self.state_tx.borrow().clone();
+            async_search_response.search_response = Some(partial_search_response);
+
+            if !self.publish(async_search_response).await? {
+                return Ok(None);
+            }
+            last_publish_instant = Instant::now();
+        }
+        let leaf_search_response = run_merge(move || incremental_collector.finalize()).await?;
+        let mut search_response = self
+            .build_search_response(
+                leaf_search_response,
+                &search_request,
+                &indexes_metas_for_leaf_search,
+                &split_metadatas,
+                start_instant,
+            )
+            .await?;
+        if let Some(pipeline_aggregations) = &pipeline_aggregations_opt {
+            pipeline_aggregations.apply(&mut search_response)?;
+        }
+        Ok(Some(search_response))
+    }
+
+    /// Returns the stream of the responses of the leaf requests, in completion order.
+    async fn leaf_search_stream<'a>(
+        &'a self,
+        search_request: &SearchRequest,
+        indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
+        split_metadatas: &[SplitMetadata],
+    ) -> crate::Result<BoxStream<'a, crate::Result<LeafSearchResponse>>> {
+        if is_metadata_count_request(search_request) {
+            let leaf_search_responses = get_count_from_metadata(split_metadatas);
+            return Ok(stream::iter(leaf_search_responses.into_iter().map(Ok)).boxed());
+        }
+        let jobs: Vec<SearchJob> = split_metadatas.iter().map(SearchJob::from).collect();
+        let assigned_leaf_search_jobs = self
+            .cluster_client
+            .search_job_placer
+            .assign_jobs(jobs, &HashSet::default())
+            .await?;
+        let leaf_search_futures = FuturesUnordered::new();
+
+        for (client, client_jobs) in assigned_leaf_search_jobs {
+            for jobs_chunk in client_jobs.chunks(MAX_SPLITS_PER_LEAF_REQUEST) {
+                let leaf_search_request = jobs_to_leaf_request(
+                    search_request,
+                    indexes_metas_for_leaf_search,
+                    jobs_chunk.to_vec(),
+                )?;
+                leaf_search_futures.push(
+                    self.cluster_client
+                        .leaf_search(leaf_search_request, client.clone()),
+                );
+            }
+        }
+        Ok(leaf_search_futures.boxed())
+    }
+
+    async fn build_search_response(
+        &self,
+        leaf_search_response: LeafSearchResponse,
+        search_request: &SearchRequest,
+        indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
+        split_metadatas: &[SplitMetadata],
+        start_instant: Instant,
+    ) -> crate::Result<SearchResponse> {
+        let (hits, _) = fetch_docs_phase(
+            indexes_metas_for_leaf_search,
+            &leaf_search_response.partial_hits,
+            split_metadatas,
+            search_request,
+            &self.cluster_client,
+        )
+        .await?;
+        // In case there is no index, we don't want the response to contain any aggregation
+        // structure.
+        let aggregation_postcard = if indexes_metas_for_leaf_search.is_empty() {
+            None
+        } else {
+            finalize_aggregation_if_any(
+                search_request,
+                leaf_search_response.intermediate_aggregation_result,
+                &self.searcher_context,
+            )?
+        };
+        Ok(SearchResponse {
+            aggregation_postcard,
+            num_hits: leaf_search_response.num_hits,
+            hits,
+            elapsed_time_micros: start_instant.elapsed().as_micros() as u64,
+            errors: Vec::new(),
+            scroll_id: None,
+            failed_splits: leaf_search_response.failed_splits,
+            num_successful_splits: leaf_search_response.num_successful_splits,
+            profile: None,
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Publishes a new state of the search, unless it was deleted or has expired. Returns whether
+    /// the state was published, or an error if the state is too large to be stored.
+    async fn publish(&self, async_search_response: AsyncSearchResponse) -> crate::Result<bool> {
+        if now_millis() >= async_search_response.expiration_time_millis {
+            return Ok(false);
+        }
+        if let Ok(Some(AsyncSearchEntry::Deleted)) =
+            load_async_search_entry(&self.cluster_client, &self.key).await
+        {
+            return Ok(false);
+        }
+        store_async_search_entry(
+            &self.cluster_client,
+            &self.key,
+            &AsyncSearchEntry::Search(async_search_response.clone()),
+            async_search_response.expiration_time_millis,
+        )
+        .await?;
+        self.state_tx.send_replace(async_search_response);
+        Ok(true)
+    }
+}
+
+/// Runs a merge step on the search thread pool, as merging is cpu-bound.
+async fn run_merge<T: Send + 'static>(
+    merge_fn: impl FnOnce() -> tantivy::Result<T> + Send + 'static,
+) -> crate::Result<T> {
+    let merge_output = crate::search_thread_pool()
+        .run_cpu_intensive(merge_fn)
+        .await
+        .context("failed to merge leaf search responses")??;
+    Ok(merge_output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::RwLock;
+
+    use quickwit_common::ServiceStream;
+    use quickwit_indexing::MockSplitBuilder;
+    use quickwit_metastore::{
+        IndexMetadata, ListIndexesMetadataResponseExt, ListSplitsResponseExt,
+    };
+    use quickwit_proto::metastore::{
+        ListIndexesMetadataResponse, ListSplitsResponse, MockMetastoreService,
+    };
+    use quickwit_proto::search::{FetchDocsResponse, LeafHit, PartialHit};
+    use quickwit_query::query_ast::qast_json_helper;
+
+    use super::*;
+    use crate::{MockSearchService, SearchJobPlacer, searcher_pool_for_test};
+
+    fn mock_metastore() -> MetastoreServiceClient {
+        let mut mock_metastore = MockMetastoreService::new();
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///test-index");
+        let index_uid = index_metadata.index_uid.clone();
+        mock_metastore.expect_list_indexes_metadata().returning(
+            move |_list_indexes_metadata_request| {
+                Ok(ListIndexesMetadataResponse::for_test(vec![
+                    index_metadata.clone(),
+                ]))
+            },
+        );
+        mock_metastore
+            .expect_list_splits()
+            .returning(move |_list_splits_request| {
+                let splits = vec![
+                    MockSplitBuilder::new("split1")
+                        .with_index_uid(&index_uid)
+                        .build(),
+                ];
+                let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+                Ok(ServiceStream::from(vec![Ok(splits_response)]))
+            });
+        MetastoreServiceClient::from_mock(mock_metastore)
+    }
+
+    fn mock_cluster_client() -> ClusterClient {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_leaf_search()
+            .returning(|_leaf_search_request| {
+                Ok(LeafSearchResponse {
+                    num_hits: 2,
+                    partial_hits: (0..2)
+                        .map(|doc_id| PartialHit {
+                            split_id: "split1".to_string(),
+                            doc_id,
+                            ..Default::default()
+                        })
+                        .collect(),
+                    num_attempted_splits: 1,
+                    num_successful_splits: 1,
+                    ..Default::default()
+                })
+            });
+        mock_search_service
+            .expect_fetch_docs()
+            .returning(|fetch_docs_request| {
+                let hits = fetch_docs_request
+                    .partial_hits
+                    .into_iter()
+                    .map(|partial_hit| LeafHit {
+                        leaf_json: format!(r#"{{"doc_id": {}}}"#, partial_hit.doc_id),
+                        partial_hit: Some(partial_hit),
+                        leaf_snippet_json: None,
+                    })
+                    .collect();
+                Ok(FetchDocsResponse {
+                    hits,
+                    split_profiles: Vec::new(),
+                })
+            });
+        let kv: Arc<RwLock<HashMap<Vec<u8>, Vec<u8>>>> = Default::default();
+        let kv_clone = kv.clone();
+        mock_search_service
+            .expect_put_kv()
+            .returning(move |put_kv_request| {
+                kv_clone
+                    .write()
+                    .unwrap()
+                    .insert(put_kv_request.key, put_kv_request.payload);
+            });
+        mock_search_service
+            .expect_get_kv()
+            .returning(move |get_kv_request| kv.read().unwrap().get(&get_kv_request.key).cloned());
+
+        let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service)]);
+        ClusterClient::new(SearchJobPlacer::new(searcher_pool))
+    }
+
+    #[tokio::test]
+    async fn test_async_search_submit_get_delete() {
+        let cluster_client = mock_cluster_client();
+        let async_search_request = AsyncSearchRequest {
+            search_request: SearchRequest {
+                index_id_patterns: vec!["test-index".to_string()],
+                query_ast: qast_json_helper("test", &["body"]),
+                max_hits: 10,
+                ..Default::default()
+            },
+            wait_for_completion_timeout: Duration::from_secs(10),
+            keep_alive: Duration::from_secs(60),
+            context: "test-context".to_string(),
+        };
+        let async_search_response = submit_async_search(
+            async_search_request,
+            Arc::new(SearcherContext::for_test()),
+            mock_metastore(),
+            cluster_client.clone(),
+        )
+        .await
+        .unwrap();
+        assert!(!async_search_response.is_running);
+        assert!(!async_search_response.is_partial);
+        assert!(async_search_response.completion_time_millis.is_some());
+        assert_eq!(async_search_response.context, "test-context");
+
+        let search_response = async_search_response.search_response.unwrap();
+        assert_eq!(search_response.num_hits, 2);
+        assert_eq!(search_response.hits.len(), 2);
+
+        let get_async_search_request = GetAsyncSearchRequest {
+            id: async_search_response.id.clone(),
+            wait_for_completion_timeout: Duration::ZERO,
+            keep_alive_opt: None,
+        };
+        let fetched_async_search_response =
+            get_async_search(get_async_search_request.clone(), &cluster_client)
+                .await
+                .unwrap();
+        assert!(!fetched_async_search_response.is_running);
+        assert_eq!(
+            fetched_async_search_response
+                .search_response
+                .unwrap()
+                .hits
+                .len(),
+            2
+        );
+
+        delete_async_search(async_search_response.id.clone(), &cluster_client)
+            .await
+            .unwrap();
+        let search_error = get_async_search(get_async_search_request, &cluster_client)
+            .await
+            .unwrap_err();
+        assert!(matches!(search_error, SearchError::NotFound(_)));
+
+        let search_error = delete_async_search(async_search_response.id, &cluster_client)
+            .await
+            .unwrap_err();
+        assert!(matches!(search_error, SearchError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_serialize_async_search_entry_too_large() {
+        let mut async_search_response = AsyncSearchResponse {
+            id: Ulid::new().to_string(),
+            is_running: true,
+            is_partial: true,
+            start_time_millis: 0,
+            expiration_time_millis: 0,
+            completion_time_millis: None,
+            search_response: Some(SearchResponse::default()),
+            error: None,
+            context: String::new(),
+        };
+        serialize_async_search_entry(&AsyncSearchEntry::Search(async_search_response.clone()))
+            .unwrap();
+
+        async_search_response.search_response = Some(SearchResponse {
+            aggregation_postcard: Some(vec![0; MAX_ASYNC_SEARCH_ENTRY_NUM_BYTES]),
+            ..Default::default()
+        });
+        let search_error =
+            serialize_async_search_entry(&AsyncSearchEntry::Search(async_search_response))
+                .unwrap_err();
+        let SearchError::InvalidArgument(error_msg) = search_error else {
+            panic!("expected an invalid argument error, got {search_error:?}");
+        };
+        assert!(error_msg.contains("too large to be stored"));
+    }
+
+    #[tokio::test]
+    async fn test_async_search_invalid_id() {
+        let cluster_client = mock_cluster_client();
+        let get_async_search_request = GetAsyncSearchRequest {
+            id: "not-an-id".to_string(),
+            wait_for_completion_timeout: Duration::ZERO,
+            keep_alive_opt: None,
+        };
+        let search_error = get_async_search(get_async_search_request, &cluster_client)
+            .await
+            .unwrap_err();
+        assert!(matches!(search_error, SearchError::InvalidArgument(_)));
+    }
+}
@@ -26,7 +26,7 @@ use tracing::{debug, error, info, warn};
 
 use crate::retry::search::LeafSearchRetryPolicy;
 use crate::retry::{DefaultRetryPolicy, RetryPolicy, retry_client};
-use crate::{SearchJobPlacer, SearchServiceClient, merge_resource_stats_it};
+use crate::{SearchJobPlacer, SearchServiceClient, merge_resource_stats_it, merge_slowest_splits};
 
 /// Maximum number of put requests emitted to perform a replicated given PUT KV.
 const MAX_PUT_KV_ATTEMPTS: usize = 6;
@@ -82,7 +82,10 @@ impl ClusterClient {
         request: LeafSearchRequest,
         mut client: SearchServiceClient,
     ) -> crate::Result<LeafSearchResponse> {
-        let mut response_res = client.leaf_search(request.clone()).await;
+        let mut response_res = client
+            .leaf_search(request.clone())
+            .await
+            .map(|response| with_leaf_node_id(response, &client));
         let retry_policy = LeafSearchRetryPolicy {};
         // We retry only once.
         let Some(retry_request) = retry_policy.retry_request(request, &response_res) else {
@@ -112,7 +115,10 @@ impl ClusterClient {
             "Leaf search response error: `{:?}`. Retry once to execute {:?} with {:?}",
             response_res, retry_request, client
         );
-        let retry_result = client.leaf_search(retry_request).await;
+        let retry_result = client
+            .leaf_search(retry_request)
+            .await
+            .map(|response| with_leaf_node_id(response, &client));
         response_res = merge_original_with_retry_leaf_search_results(response_res, retry_result);
         response_res
     }
@@ -264,6 +270,10 @@ fn merge_original_with_retry_leaf_search_response(
         &original_response.resource_stats,
         &retry_response.resource_stats,
     ]);
+    let mut slowest_splits = original_response.slowest_splits;
+    merge_slowest_splits(retry_response.slowest_splits, &mut slowest_splits);
+    let mut split_profiles = original_response.split_profiles;
+    split_profiles.extend(retry_response.split_profiles);
     Ok(LeafSearchResponse {
         intermediate_aggregation_result,
         num_hits: original_response.num_hits + retry_response.num_hits,
@@ -274,9 +284,31 @@ fn merge_original_with_retry_leaf_search_response(
         num_successful_splits: original_response.num_successful_splits
             + retry_response.num_successful_splits,
         resource_stats,
+        slowest_splits,
+        split_profiles,
     })
 }
 
+/// Records the address of the searcher that returned the response in its split timing
+/// breakdowns and profiles.
+fn with_leaf_node_id(
+    mut leaf_search_response: LeafSearchResponse,
+    client: &SearchServiceClient,
+) -> LeafSearchResponse {
+    let leaf_node_id = client.grpc_addr().to_string();
+    for split_stats in &mut leaf_search_response.slowest_splits {
+        if split_stats.leaf_node_id.is_empty() {
+            split_stats.leaf_node_id.clone_from(&leaf_node_id);
+        }
+    }
+    for split_profile in &mut leaf_search_response.split_profiles {
+        if split_profile.leaf_node_id.is_empty() {
+            split_profile.leaf_node_id.clone_from(&leaf_node_id);
+        }
+    }
+    leaf_search_response
+}
+
 // Merge initial leaf search results with results obtained from a retry.
 fn merge_original_with_retry_leaf_search_results(
     left_search_response_result: crate::Result<LeafSearchResponse>,
@@ -375,7 +407,10 @@ mod tests {
         let mut mock_search_service = MockSearchService::new();
         mock_search_service.expect_fetch_docs().return_once(
             |_: quickwit_proto::search::FetchDocsRequest| {
-                Ok(quickwit_proto::search::FetchDocsResponse { hits: Vec::new() })
+                Ok(quickwit_proto::search::FetchDocsResponse {
+                    hits: Vec::new(),
+                    split_profiles: Vec::new(),
+                })
             },
         );
         let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service)]);
@@ -404,7 +439,10 @@ mod tests {
         let mut mock_search_service_2 = MockSearchService::new();
         mock_search_service_2.expect_fetch_docs().return_once(
             |_: quickwit_proto::search::FetchDocsRequest| {
-                Ok(quickwit_proto::search::FetchDocsResponse { hits: Vec::new() })
+                Ok(quickwit_proto::search::FetchDocsResponse {
+                    hits: Vec::new(),
+                    split_profiles: Vec::new(),
+                })
             },
         );
         let searcher_pool = searcher_pool_for_test([
@@ -21,7 +21,7 @@ use quickwit_common::binary_heap::{SortKeyMapper, TopK};
 use quickwit_doc_mapper::{FastFieldWarmupInfo, WarmupInfo};
 use quickwit_proto::search::{
     LeafSearchResponse, PartialHit, ResourceStats, SearchRequest, SortByValue, SortOrder,
-    SortValue, SplitSearchError,
+    SortValue, SplitSearchError, SplitSearchProfile, SplitSearchStats,
 };
 use quickwit_proto::types::SplitId;
 use serde::Deserialize;
@@ -34,9 +34,12 @@ use tantivy::fastfield::Column;
 use tantivy::tokenizer::TokenizerManager;
 use tantivy::{DateTime, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
 
+use crate::composite_collector::{CompositeBucket, CompositeCollector, CompositeSegmentCollector};
 use crate::find_trace_ids_collector::{FindTraceIdsCollector, FindTraceIdsSegmentCollector, Span};
 use crate::top_k_collector::{QuickwitSegmentTopKCollector, specialized_top_k_segment_collector};
-use crate::{GlobalDocAddress, merge_resource_stats, merge_resource_stats_it};
+use crate::{
+    GlobalDocAddress, merge_resource_stats, merge_resource_stats_it, merge_slowest_splits,
+};
 
 #[derive(Clone, Debug)]
 pub(crate) enum SortByComponent {
@@ -469,6 +472,7 @@ fn get_score_extractor(
 #[allow(clippy::large_enum_variant)]
 enum AggregationSegmentCollectors {
     FindTraceIdsSegmentCollector(Box<FindTraceIdsSegmentCollector>),
+    CompositeSegmentCollector(Box<CompositeSegmentCollector>),
     TantivyAggregationSegmentCollector(AggregationSegmentCollector),
 }
 
@@ -537,6 +541,9 @@ impl SegmentCollector for QuickwitSegmentCollector {
             Some(AggregationSegmentCollectors::FindTraceIdsSegmentCollector(collector)) => {
                 collector.collect_block(filtered_docs)
             }
+            Some(AggregationSegmentCollectors::CompositeSegmentCollector(collector)) => {
+                collector.collect_block(filtered_docs)
+            }
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 collector.collect_block(filtered_docs)
             }
@@ -555,6 +562,9 @@ impl SegmentCollector for QuickwitSegmentCollector {
             Some(AggregationSegmentCollectors::FindTraceIdsSegmentCollector(collector)) => {
                 collector.collect(doc_id, score)
             }
+            Some(AggregationSegmentCollectors::CompositeSegmentCollector(collector)) => {
+                collector.collect(doc_id, score)
+            }
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 collector.collect(doc_id, score)
             }
@@ -575,6 +585,12 @@ impl SegmentCollector for QuickwitSegmentCollector {
                     postcard::to_allocvec(&fruit).expect("Collector fruit should be serializable.");
                 Some(serialized)
             }
+            Some(AggregationSegmentCollectors::CompositeSegmentCollector(collector)) => {
+                let fruit: Vec<CompositeBucket> = collector.harvest()?;
+                let serialized =
+                    postcard::to_allocvec(&fruit).expect("Collector fruit should be serializable.");
+                Some(serialized)
+            }
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 let serialized = postcard::to_allocvec(&collector.harvest()?)
                     .expect("Collector fruit should be serializable.");
@@ -591,6 +607,8 @@ impl SegmentCollector for QuickwitSegmentCollector {
             num_attempted_splits: 1,
             num_successful_splits: 1,
             resource_stats: None,
+            slowest_splits: Vec::new(),
+            split_profiles: Vec::new(),
         })
     }
 }
@@ -602,6 +620,9 @@ pub enum QuickwitAggregations {
     /// Aggregation used by the Jaeger service to find trace IDs that match a
     /// [`quickwit_proto::jaeger::storage::v1::FindTraceIDsRequest`].
     FindTraceIdsAggregation(FindTraceIdsCollector),
+    /// Elasticsearch `composite` aggregation, paging through all the buckets of a multi-source
+    /// aggregation with an `after` key. Tantivy does not support it.
+    CompositeAggregation(CompositeCollector),
     /// Your classic Tantivy aggregation.
     TantivyAggregations(Aggregations),
 }
@@ -613,6 +634,7 @@ impl QuickwitAggregations {
             QuickwitAggregations::FindTraceIdsAggregation(collector) => {
                 collector.fast_field_names()
             }
+            QuickwitAggregations::CompositeAggregation(collector) => collector.fast_field_names(),
             QuickwitAggregations::TantivyAggregations(aggregations) => {
                 get_fast_field_names(aggregations)
             }
@@ -624,6 +646,9 @@ impl QuickwitAggregations {
             QuickwitAggregations::FindTraceIdsAggregation(aggreg) => {
                 QuickwitIncrementalAggregations::FindTraceIdsAggregation(aggreg.clone(), Vec::new())
             }
+            QuickwitAggregations::CompositeAggregation(aggreg) => {
+                QuickwitIncrementalAggregations::CompositeAggregation(aggreg.clone(), Vec::new())
+            }
             QuickwitAggregations::TantivyAggregations(aggreg) => {
                 QuickwitIncrementalAggregations::TantivyAggregations(aggreg.clone(), Vec::new())
             }
@@ -634,6 +659,7 @@ impl QuickwitAggregations {
 #[derive(Clone)]
 enum QuickwitIncrementalAggregations {
     FindTraceIdsAggregation(FindTraceIdsCollector, Vec<Vec<Span>>),
+    CompositeAggregation(CompositeCollector, Vec<Vec<CompositeBucket>>),
     TantivyAggregations(Aggregations, Vec<Vec<u8>>),
     NoAggregation,
 }
@@ -650,6 +676,16 @@ impl QuickwitIncrementalAggregations {
                     state.push(new_state);
                 }
             }
+            QuickwitIncrementalAggregations::CompositeAggregation(collector, state) => {
+                let buckets: Vec<CompositeBucket> =
+                    postcard::from_bytes(&intermediate_result).map_err(map_error)?;
+                state.push(buckets);
+                if state.iter().map(Vec::len).sum::<usize>() >= 2 * collector.size() {
+                    let new_state = collector
+                        .merge_fruits(std::mem::take(state).into_iter().map(Ok).collect())?;
+                    state.push(new_state);
+                }
+            }
             QuickwitIncrementalAggregations::TantivyAggregations(_, state) => {
                 state.push(intermediate_result);
             }
@@ -678,6 +714,7 @@ impl QuickwitIncrementalAggregations {
                 }
                 None
             }
+            QuickwitIncrementalAggregations::CompositeAggregation(_, _) => None,
             QuickwitIncrementalAggregations::TantivyAggregations(_, _) => None,
             QuickwitIncrementalAggregations::NoAggregation => None,
         }
@@ -694,6 +731,11 @@ impl QuickwitIncrementalAggregations {
                 let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
                 Ok(Some(serialized))
             }
+            QuickwitIncrementalAggregations::CompositeAggregation(collector, state) => {
+                let merged_fruit = collector.merge_fruits(state.into_iter().map(Ok).collect())?;
+                let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
+                Ok(Some(serialized))
+            }
             QuickwitIncrementalAggregations::TantivyAggregations(aggregation, state) => {
                 merge_intermediate_aggregation_result(
                     &Some(QuickwitAggregations::TantivyAggregations(aggregation)),
@@ -780,6 +822,11 @@ impl Collector for QuickwitCollector {
                     Box::new(collector.for_segment(0, segment_reader)?),
                 ))
             }
+            Some(QuickwitAggregations::CompositeAggregation(collector)) => {
+                Some(AggregationSegmentCollectors::CompositeSegmentCollector(
+                    Box::new(collector.for_segment(0, segment_reader)?),
+                ))
+            }
             Some(QuickwitAggregations::TantivyAggregations(aggs)) => Some(
                 AggregationSegmentCollectors::TantivyAggregationSegmentCollector(
                     AggregationSegmentCollector::from_agg_req_and_reader(
@@ -885,6 +932,17 @@ fn merge_intermediate_aggregation_result<'a>(
             let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
             Some(serialized)
         }
+        Some(QuickwitAggregations::CompositeAggregation(collector)) => {
+            let fruits: Vec<tantivy::Result<Vec<CompositeBucket>>> =
+                intermediate_aggregation_results
+                    .map(|intermediate_aggregation_result| {
+                        postcard::from_bytes(intermediate_aggregation_result).map_err(map_error)
+                    })
+                    .collect();
+            let merged_fruit: Vec<CompositeBucket> = collector.merge_fruits(fruits)?;
+            let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
+            Some(serialized)
+        }
         Some(QuickwitAggregations::TantivyAggregations(_)) => {
             let merged_opt = intermediate_aggregation_results
                 .map(|bytes| postcard::from_bytes(bytes).map_err(map_error))
@@ -928,6 +986,15 @@ fn merge_leaf_responses(
         .iter()
         .map(|leaf_response| &leaf_response.resource_stats);
     let merged_resource_stats = merge_resource_stats_it(resource_stats_it);
+    let mut slowest_splits = Vec::new();
+    let mut split_profiles = Vec::new();
+    for leaf_response in leaf_responses.iter_mut() {
+        merge_slowest_splits(
+            std::mem::take(&mut leaf_response.slowest_splits),
+            &mut slowest_splits,
+        );
+        split_profiles.append(&mut leaf_response.split_profiles);
+    }
 
     let merged_intermediate_aggregation_result: Option<Vec<u8>> =
         merge_intermediate_aggregation_result(
@@ -971,6 +1038,8 @@ fn merge_leaf_responses(
         num_attempted_splits,
         num_successful_splits,
         resource_stats: merged_resource_stats,
+        slowest_splits,
+        split_profiles,
     })
 }
 
@@ -1202,6 +1271,8 @@ pub(crate) struct IncrementalCollector {
     num_successful_splits: u64,
     start_offset: usize,
     resource_stats: Option<ResourceStats>,
+    slowest_splits: Vec<SplitSearchStats>,
+    split_profiles: Vec<SplitSearchProfile>,
 }
 
 impl IncrementalCollector {
@@ -1223,6 +1294,8 @@ impl IncrementalCollector {
             num_attempted_splits: 0,
             num_successful_splits: 0,
             resource_stats: None,
+            slowest_splits: Vec::new(),
+            split_profiles: Vec::new(),
         }
     }
 
@@ -1236,9 +1309,13 @@ impl IncrementalCollector {
             intermediate_aggregation_result,
             num_successful_splits,
             resource_stats,
+            slowest_splits,
+            split_profiles,
         } = leaf_response;
 
         merge_resource_stats(&resource_stats, &mut self.resource_stats);
+        merge_slowest_splits(slowest_splits, &mut self.slowest_splits);
+        self.split_profiles.extend(split_profiles);
 
         self.num_hits += num_hits;
         self.top_k_hits.add_entries(partial_hits.into_iter());
@@ -1290,6 +1367,8 @@ impl IncrementalCollector {
             num_successful_splits: self.num_successful_splits,
             intermediate_aggregation_result,
             resource_stats: self.resource_stats,
+            slowest_splits: self.slowest_splits,
+            split_profiles: self.split_profiles,
         })
     }
 }
@@ -1800,6 +1879,8 @@ mod tests {
                 num_successful_splits: 3,
                 intermediate_aggregation_result: None,
                 resource_stats: None,
+                slowest_splits: Vec::new(),
+                split_profiles: Vec::new(),
             }],
         );
 
@@ -1819,6 +1900,8 @@ mod tests {
                 num_successful_splits: 3,
                 intermediate_aggregation_result: None,
                 resource_stats: None,
+                slowest_splits: Vec::new(),
+                split_profiles: Vec::new(),
             }
         );
 
@@ -1858,6 +1941,8 @@ mod tests {
                     num_successful_splits: 3,
                     intermediate_aggregation_result: None,
                     resource_stats: None,
+                    slowest_splits: Vec::new(),
+                    split_profiles: Vec::new(),
                 },
                 LeafSearchResponse {
                     num_hits: 10,
@@ -1877,6 +1962,8 @@ mod tests {
                     num_successful_splits: 1,
                     intermediate_aggregation_result: None,
                     resource_stats: None,
+                    slowest_splits: Vec::new(),
+                    split_profiles: Vec::new(),
                 },
             ],
         );
@@ -1910,6 +1997,8 @@ mod tests {
                 num_successful_splits: 4,
                 intermediate_aggregation_result: None,
                 resource_stats: None,
+                slowest_splits: Vec::new(),
+                split_profiles: Vec::new(),
             }
         );
 
@@ -0,0 +1,1208 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+
+use fnv::FnvHashMap;
+use itertools::Itertools;
+use quickwit_query::aggregations::{
+    AggregationResult, AggregationResults, BucketResult, CompositeBucketEntry, CompositeKeyValue,
+    MetricResult,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tantivy::aggregation::metric::SingleMetricResult;
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::columnar::{ColumnType, MonotonicallyMappableToU64, StrColumn};
+use tantivy::fastfield::Column;
+use tantivy::{DateTime, DocId, Score, SegmentReader, TantivyError};
+
+/// Maximum number of buckets of a composite aggregation page. This is the default bucket limit of
+/// tantivy aggregations.
+const MAX_COMPOSITE_SIZE: usize = 65_000;
+
+const DEFAULT_COMPOSITE_SIZE: usize = 10;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompositeAggregationEntry {
+    composite: CompositeAggregationRequest,
+    #[serde(default, alias = "aggregations")]
+    aggs: BTreeMap<String, CompositeMetricAggregation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompositeAggregationRequest {
+    #[serde(default = "default_composite_size")]
+    size: usize,
+    sources: Vec<BTreeMap<String, CompositeSourceRequest>>,
+    #[serde(default)]
+    after: Option<BTreeMap<String, JsonValue>>,
+}
+
+fn default_composite_size() -> usize {
+    DEFAULT_COMPOSITE_SIZE
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CompositeSourceRequest {
+    Terms(TermsSourceRequest),
+    DateHistogram(DateHistogramSourceRequest),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TermsSourceRequest {
+    field: String,
+    #[serde(default)]
+    order: SourceOrder,
+    #[serde(default)]
+    missing_bucket: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DateHistogramSourceRequest {
+    field: String,
+    #[serde(default)]
+    fixed_interval: Option<String>,
+    #[serde(default)]
+    calendar_interval: Option<String>,
+    #[serde(default)]
+    time_zone: Option<String>,
+    #[serde(default)]
+    order: SourceOrder,
+    #[serde(default)]
+    missing_bucket: bool,
+}
+
+/// Order of the values of a composite source.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceOrder {
+    /// Ascending order.
+    #[default]
+    Asc,
+    /// Descending order.
+    Desc,
+}
+
+impl SourceOrder {
+    fn apply(self, ordering: Ordering) -> Ordering {
+        match self {
+            SourceOrder::Asc => ordering,
+            SourceOrder::Desc => ordering.reverse(),
+        }
+    }
+}
+
+/// Metric sub-aggregation computed for each bucket of a composite aggregation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompositeMetricAggregation {
+    /// Average of the values.
+    Avg(MetricFieldRequest),
+    /// Sum of the values.
+    Sum(MetricFieldRequest),
+    /// Minimum value.
+    Min(MetricFieldRequest),
+    /// Maximum value.
+    Max(MetricFieldRequest),
+    /// Number of values.
+    ValueCount(MetricFieldRequest),
+}
+
+impl CompositeMetricAggregation {
+    fn field_name(&self) -> &str {
+        match self {
+            CompositeMetricAggregation::Avg(metric)
+            | CompositeMetricAggregation::Sum(metric)
+            | CompositeMetricAggregation::Min(metric)
+            | CompositeMetricAggregation::Max(metric)
+            | CompositeMetricAggregation::ValueCount(metric) => &metric.field,
+        }
+    }
+
+    fn metric_result(&self, metric_state: &MetricState) -> MetricResult {
+        let value_opt = |value: f64| {
+            let value = if metric_state.count > 0 {
+                Some(value)
+            } else {
+                None
+            };
+            SingleMetricResult { value }
+        };
+        match self {
+            CompositeMetricAggregation::Avg(_) => {
+                MetricResult::Average(value_opt(metric_state.sum / metric_state.count as f64))
+            }
+            CompositeMetricAggregation::Sum(_) => MetricResult::Sum(SingleMetricResult {
+                value: Some(metric_state.sum),
+            }),
+            CompositeMetricAggregation::Min(_) => MetricResult::Min(value_opt(metric_state.min)),
+            CompositeMetricAggregation::Max(_) => MetricResult::Max(value_opt(metric_state.max)),
+            CompositeMetricAggregation::ValueCount(_) => MetricResult::Count(SingleMetricResult {
+                value: Some(metric_state.count as f64),
+            }),
+        }
+    }
+}
+
+/// The field of a metric sub-aggregation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricFieldRequest {
+    field: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CompositeSource {
+    Terms { field: String },
+    DateHistogram { field: String, interval_millis: i64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct CompositeSourceEntry {
+    name: String,
+    source: CompositeSource,
+    order: SourceOrder,
+    missing_bucket: bool,
+}
+
+impl CompositeSourceEntry {
+    fn field_name(&self) -> &str {
+        match &self.source {
+            CompositeSource::Terms { field } | CompositeSource::DateHistogram { field, .. } => {
+                field
+            }
+        }
+    }
+}
+
+/// Collects the buckets of an Elasticsearch `composite` aggregation.
+///
+/// The buckets are the combinations of the values of the sources found in the matching
+/// documents. They are returned sorted by key, one page of `size` buckets at a time: the key of
+/// the last bucket is passed as `after` to fetch the next page. Every leaf keeps its first `size`
+/// buckets after the `after` key, which is enough to compute the exact first `size` buckets
+/// overall.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "BTreeMap<String, CompositeAggregationEntry>")]
+pub struct CompositeCollector {
+    name: String,
+    size: usize,
+    sources: Vec<CompositeSourceEntry>,
+    after_key_opt: Option<Vec<CompositeKeyValue>>,
+    metrics: Vec<(String, CompositeMetricAggregation)>,
+}
+
+impl TryFrom<BTreeMap<String, CompositeAggregationEntry>> for CompositeCollector {
+    type Error = String;
+
+    fn try_from(
+        aggregations: BTreeMap<String, CompositeAggregationEntry>,
+    ) -> Result<Self, Self::Error> {
+        if aggregations.len() != 1 {
+            return Err(
+                "a composite aggregation must be the only top-level aggregation of the request"
+                    .to_string(),
+            );
+        }
+        let (name, entry) = aggregations
+            .into_iter()
+            .next()
+            .expect("there should be exactly one aggregation");
+        let request = entry.composite;
+
+        if request.size == 0 || request.size > MAX_COMPOSITE_SIZE {
+            return Err(format!(
+                "the size of the composite aggregation `{name}` must be between 1 and \
+                 {MAX_COMPOSITE_SIZE}, got {}",
+                request.size
+            ));
+        }
+        if request.sources.is_empty() {
+            return Err(format!(
+                "the composite aggregation `{name}` requires at least one source"
+            ));
+        }
+        let mut sources = Vec::with_capacity(request.sources.len());
+
+        for source_map in request.sources {
+            if source_map.len() != 1 {
+                return Err(format!(
+                    "each source of the composite aggregation `{name}` must define exactly one \
+                     named source"
+                ));
+            }
+            let (source_name, source_request) = source_map
+                .into_iter()
+                .next()
+                .expect("there should be exactly one source");
+
+            if sources
+                .iter()
+                .any(|source: &CompositeSourceEntry| source.name == source_name)
+            {
+                return Err(format!(
+                    "duplicate source `{source_name}` in composite aggregation `{name}`"
+                ));
+            }
+            let source_entry = match source_request {
+                CompositeSourceRequest::Terms(terms) => CompositeSourceEntry {
+                    name: source_name,
+                    source: CompositeSource::Terms { field: terms.field },
+                    order: terms.order,
+                    missing_bucket: terms.missing_bucket,
+                },
+                CompositeSourceRequest::DateHistogram(date_histogram) => {
+                    let interval_millis = parse_date_histogram_interval(&date_histogram)
+                        .map_err(|error| format!("source `{source_name}`: {error}"))?;
+                    CompositeSourceEntry {
+                        name: source_name,
+                        source: CompositeSource::DateHistogram {
+                            field: date_histogram.field,
+                            interval_millis,
+                        },
+                        order: date_histogram.order,
+                        missing_bucket: date_histogram.missing_bucket,
+                    }
+                }
+            };
+            sources.push(source_entry);
+        }
+        let after_key_opt = request
+            .after
+            .map(|after| parse_after_key(&name, &sources, after))
+            .transpose()?;
+
+        Ok(CompositeCollector {
+            name,
+            size: request.size,
+            sources,
+            after_key_opt,
+            metrics: entry.aggs.into_iter().collect(),
+        })
+    }
+}
+
+fn parse_date_histogram_interval(
+    date_histogram: &DateHistogramSourceRequest,
+) -> Result<i64, String> {
+    if let Some(time_zone) = &date_histogram.time_zone
+        && !matches!(time_zone.as_str(), "UTC" | "Etc/UTC" | "Z" | "+00:00")
+    {
+        return Err(format!(
+            "time zone `{time_zone}` is not supported, date histograms are computed in UTC"
+        ));
+    }
+    match (
+        &date_histogram.fixed_interval,
+        &date_histogram.calendar_interval,
+    ) {
+        (Some(fixed_interval), None) => parse_fixed_interval(fixed_interval),
+        (None, Some(calendar_interval)) => match calendar_interval.as_str() {
+            "minute" | "1m" => Ok(60_000),
+            "hour" | "1h" => Ok(3_600_000),
+            "day" | "1d" => Ok(MILLIS_PER_DAY),
+            _ => Err(format!(
+                "calendar interval `{calendar_interval}` is not supported, only `minute`, `hour` \
+                 and `day` are, use `fixed_interval` instead"
+            )),
+        },
+        _ => Err("exactly one of `fixed_interval` or `calendar_interval` must be set".to_string()),
+    }
+}
+
+fn parse_fixed_interval(fixed_interval: &str) -> Result<i64, String> {
+    let unit_start = fixed_interval
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(fixed_interval.len());
+    let (value_str, unit) = fixed_interval.split_at(unit_start);
+    let unit_millis: i64 = match unit {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => MILLIS_PER_DAY,
+        _ => {
+            return Err(format!(
+                "invalid fixed interval `{fixed_interval}`, expected a number followed by `ms`, \
+                 `s`, `m`, `h` or `d`"
+            ));
+        }
+    };
+    let value: i64 = value_str
+        .parse()
+        .map_err(|_| format!("invalid fixed interval `{fixed_interval}`"))?;
+    match value.checked_mul(unit_millis) {
+        Some(interval_millis) if interval_millis > 0 => Ok(interval_millis),
+        _ => Err(format!("invalid fixed interval `{fixed_interval}`")),
+    }
+}
+
+fn parse_after_key(
+    name: &str,
+    sources: &[CompositeSourceEntry],
+    mut after: BTreeMap<String, JsonValue>,
+) -> Result<Vec<CompositeKeyValue>, String> {
+    let mut after_key = Vec::with_capacity(sources.len());
+
+    for source in sources {
+        let Some(after_value_json) = after.remove(&source.name) else {
+            return Err(format!(
+                "the `after` key of the composite aggregation `{name}` is missing the source `{}`",
+                source.name
+            ));
+        };
+        let after_value = match after_value_json {
+            JsonValue::Null => CompositeKeyValue::Null,
+            JsonValue::Bool(value) => CompositeKeyValue::Bool(value),
+            JsonValue::Number(number) => {
+                if let Some(value) = number.as_i64() {
+                    CompositeKeyValue::I64(value)
+                } else if let Some(value) = number.as_u64() {
+                    CompositeKeyValue::U64(value)
+                } else {
+                    CompositeKeyValue::F64(number.as_f64().unwrap_or_default())
+                }
+            }
+            JsonValue::String(value) if matches!(source.source, CompositeSource::Terms { .. }) => {
+                CompositeKeyValue::Str(value)
+            }
+            _ => {
+                return Err(format!(
+                    "invalid value `{after_value_json}` for the source `{}` in the `after` key of \
+                     the composite aggregation `{name}`",
+                    source.name
+                ));
+            }
+        };
+        after_key.push(after_value);
+    }
+    if let Some(unknown_source_name) = after.keys().next() {
+        return Err(format!(
+            "the `after` key of the composite aggregation `{name}` contains the unknown source \
+             `{unknown_source_name}`"
+        ));
+    }
+    Ok(after_key)
+}
+
+impl CompositeCollector {
+    /// The names of the fast fields accessed by this collector.
+    pub fn fast_field_names(&self) -> HashSet<String> {
+        let source_field_names = self
+            .sources
+            .iter()
+            .map(|source| source.field_name().to_string());
+        let metric_field_names = self
+            .metrics
+            .iter()
+            .map(|(_, metric)| metric.field_name().to_string());
+        source_field_names.chain(metric_field_names).collect()
+    }
+
+    /// The maximum number of buckets returned by the aggregation.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn source_orders(&self) -> Vec<SourceOrder> {
+        self.sources.iter().map(|source| source.order).collect()
+    }
+
+    /// Turns the merged buckets into the final aggregation results.
+    pub fn into_aggregation_results(self, buckets: Vec<CompositeBucket>) -> AggregationResults {
+        let buckets: Vec<CompositeBucketEntry> = buckets
+            .into_iter()
+            .map(|bucket| {
+                let key = self
+                    .sources
+                    .iter()
+                    .map(|source| source.name.clone())
+                    .zip(bucket.key)
+                    .collect();
+                let sub_aggregation = self
+                    .metrics
+                    .iter()
+                    .zip(&bucket.metrics)
+                    .map(|((metric_name, metric), metric_state)| {
+                        let metric_result = metric.metric_result(metric_state);
+                        (
+                            metric_name.clone(),
+                            AggregationResult::MetricResult(metric_result),
+                        )
+                    })
+                    .collect();
+                CompositeBucketEntry {
+                    key,
+                    doc_count: bucket.doc_count,
+                    sub_aggregation: AggregationResults(sub_aggregation),
+                }
+            })
+            .collect();
+        let after_key = buckets.last().map(|bucket| bucket.key.clone());
+        let composite_result = BucketResult::Composite { buckets, after_key };
+        AggregationResults(vec![(
+            self.name,
+            AggregationResult::BucketResult(composite_result),
+        )])
+    }
+}
+
+/// A bucket of a composite aggregation, as exchanged between the leaves and the root.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompositeBucket {
+    /// One value per source.
+    pub key: Vec<CompositeKeyValue>,
+    /// Number of documents in the bucket.
+    pub doc_count: u64,
+    /// One state per metric sub-aggregation.
+    pub metrics: Vec<MetricState>,
+}
+
+/// Running state of a metric sub-aggregation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MetricState {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for MetricState {
+    fn default() -> Self {
+        MetricState {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl MetricState {
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn merge(&mut self, other: &MetricState) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+fn key_value_rank(key_value: &CompositeKeyValue) -> u8 {
+    match key_value {
+        CompositeKeyValue::Null => 0,
+        CompositeKeyValue::Bool(_) => 1,
+        CompositeKeyValue::I64(_) | CompositeKeyValue::U64(_) | CompositeKeyValue::F64(_) => 2,
+        CompositeKeyValue::Str(_) => 3,
+    }
+}
+
+fn key_value_as_f64(key_value: &CompositeKeyValue) -> f64 {
+    match key_value {
+        CompositeKeyValue::I64(value) => *value as f64,
+        CompositeKeyValue::U64(value) => *value as f64,
+        CompositeKeyValue::F64(value) => *value,
+        _ => 0.0,
+    }
+}
+
+/// Total order on key values: missing values first, then booleans, numbers and strings.
+/// Numbers of different types are compared by value.
+fn compare_key_values(left: &CompositeKeyValue, right: &CompositeKeyValue) -> Ordering {
+    let rank_ordering = key_value_rank(left).cmp(&key_value_rank(right));
+    if rank_ordering.is_ne() {
+        return rank_ordering;
+    }
+    match (left, right) {
+        (CompositeKeyValue::Bool(left), CompositeKeyValue::Bool(right)) => left.cmp(right),
+        (CompositeKeyValue::Str(left), CompositeKeyValue::Str(right)) => left.cmp(right),
+        (CompositeKeyValue::I64(left), CompositeKeyValue::I64(right)) => left.cmp(right),
+        (CompositeKeyValue::U64(left), CompositeKeyValue::U64(right)) => left.cmp(right),
+        (CompositeKeyValue::I64(left), CompositeKeyValue::U64(right)) => {
+            i128::from(*left).cmp(&i128::from(*right))
+        }
+        (CompositeKeyValue::U64(left), CompositeKeyValue::I64(right)) => {
+            i128::from(*left).cmp(&i128::from(*right))
+        }
+        _ => key_value_as_f64(left).total_cmp(&key_value_as_f64(right)),
+    }
+}
+
+/// Compares two keys in the order of the composite aggregation.
+fn compare_keys<T>(
+    orders: &[SourceOrder],
+    left: &[T],
+    right: &[T],
+    compare_values: impl Fn(&T, &T) -> Ordering,
+) -> Ordering {
+    for ((order, left_value), right_value) in orders.iter().zip(left).zip(right) {
+        let ordering = order.apply(compare_values(left_value, right_value));
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Merges the buckets of several segments or splits and keeps the first `size` ones.
+fn merge_buckets(
+    bucket_lists: Vec<Vec<CompositeBucket>>,
+    orders: &[SourceOrder],
+    size: usize,
+) -> Vec<CompositeBucket> {
+    let mut buckets: Vec<CompositeBucket> = bucket_lists.into_iter().flatten().collect();
+    buckets.sort_by(|left, right| compare_keys(orders, &left.key, &right.key, compare_key_values));
+    buckets
+        .into_iter()
+        .coalesce(|mut left, right| {
+            if compare_keys(orders, &left.key, &right.key, compare_key_values).is_eq() {
+                left.doc_count += right.doc_count;
+                for (left_metric, right_metric) in left.metrics.iter_mut().zip(&right.metrics) {
+                    left_metric.merge(right_metric);
+                }
+                Ok(left)
+            } else {
+                Err((left, right))
+            }
+        })
+        .take(size)
+        .collect()
+}
+
+impl Collector for CompositeCollector {
+    type Fruit = Vec<CompositeBucket>;
+    type Child = CompositeSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: u32,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let mut sources = Vec::with_capacity(self.sources.len());
+
+        for (source_ord, source) in self.sources.iter().enumerate() {
+            let after_value_opt = self
+                .after_key_opt
+                .as_ref()
+                .map(|after_key| &after_key[source_ord]);
+            let segment_source = SegmentSource::open(source, after_value_opt, segment_reader)?;
+            sources.push(segment_source);
+        }
+        let mut metric_columns = Vec::with_capacity(self.metrics.len());
+
+        for (_, metric) in &self.metrics {
+            let metric_column_opt = segment_reader
+                .fast_fields()
+                .u64_lenient(metric.field_name())?;
+            metric_columns.push(metric_column_opt);
+        }
+        Ok(CompositeSegmentCollector {
+            size: self.size,
+            orders: self.source_orders(),
+            source_values: vec![Vec::new(); sources.len()],
+            sources,
+            metric_values: vec![Vec::new(); metric_columns.len()],
+            metric_columns,
+            buckets: FnvHashMap::default(),
+            sentinel_key_opt: None,
+        })
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let bucket_lists: Vec<Vec<CompositeBucket>> =
+            segment_fruits.into_iter().collect::<tantivy::Result<_>>()?;
+        Ok(merge_buckets(
+            bucket_lists,
+            &self.source_orders(),
+            self.size,
+        ))
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+/// Value of a source within a segment. The derived order matches the order of the corresponding
+/// [`CompositeKeyValue`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum SegmentKeyValue {
+    Null,
+    /// Value of the numerical column, mapped to `u64` so that the order is preserved. For date
+    /// histograms, the start of the bucket in milliseconds.
+    Numerical(u64),
+    /// Ordinal of the term in the dictionary of the string column.
+    TermOrd(u64),
+}
+
+/// Position of the `after` value of a source relative to the values of a segment.
+#[derive(Debug)]
+enum SegmentAfterValue {
+    /// The `after` value is this segment value.
+    Exact(SegmentKeyValue),
+    /// The `after` value falls right before this segment value.
+    Before(SegmentKeyValue),
+    /// The `after` value is greater than all the values of the segment.
+    AfterAll,
+    /// The `after` value is a boolean or a number, compared to the decoded numerical values.
+    Numerical(CompositeKeyValue),
+}
+
+struct SegmentSource {
+    str_column_opt: Option<StrColumn>,
+    numerical_column_opt: Option<(Column<u64>, ColumnType)>,
+    date_interval_millis_opt: Option<i64>,
+    missing_bucket: bool,
+    after_value_opt: Option<SegmentAfterValue>,
+}
+
+impl SegmentSource {
+    fn open(
+        source: &CompositeSourceEntry,
+        after_value_opt: Option<&CompositeKeyValue>,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self> {
+        let fast_fields = segment_reader.fast_fields();
+        let numerical_column_opt = fast_fields.u64_lenient(source.field_name())?;
+
+        let (str_column_opt, date_interval_millis_opt) = match &source.source {
+            CompositeSource::Terms { field } => (fast_fields.str(field)?, None),
+            CompositeSource::DateHistogram {
+                field,
+                interval_millis,
+            } => {
+                if let Some((_, column_type)) = &numerical_column_opt
+                    && !matches!(
+                        column_type,
+                        ColumnType::DateTime | ColumnType::I64 | ColumnType::U64 | ColumnType::F64
+                    )
+                {
+                    return Err(TantivyError::InvalidArgument(format!(
+                        "the date histogram source `{}` requires a date or numerical field, but \
+                         `{field}` is of type `{column_type:?}`",
+                        source.name
+                    )));
+                }
+                (None, Some(*interval_millis))
+            }
+        };
+        let mut segment_source = SegmentSource {
+            str_column_opt,
+            numerical_column_opt,
+            date_interval_millis_opt,
+            missing_bucket: source.missing_bucket,
+            after_value_opt: None,
+        };
+        if let Some(after_value) = after_value_opt {
+            segment_source.after_value_opt = Some(segment_source.segment_after_value(after_value)?);
+        }
+        Ok(segment_source)
+    }
+
+    fn segment_after_value(
+        &self,
+        after_value: &CompositeKeyValue,
+    ) -> io::Result<SegmentAfterValue> {
+        let after_str = match after_value {
+            CompositeKeyValue::Null => return Ok(SegmentAfterValue::Exact(SegmentKeyValue::Null)),
+            CompositeKeyValue::Str(after_str) => after_str,
+            _ => return Ok(SegmentAfterValue::Numerical(after_value.clone())),
+        };
+        let Some(str_column) = &self.str_column_opt else {
+            return Ok(SegmentAfterValue::AfterAll);
+        };
+        // Term ordinals follow the order of the terms: we look for the first term greater than
+        // or equal to the `after` value.
+        let num_terms = str_column.num_terms() as u64;
+        let mut buffer = String::new();
+        let mut low = 0;
+        let mut high = num_terms;
+
+        while low < high {
+            let middle = low + (high - low) / 2;
+            buffer.clear();
+            str_column.ord_to_str(middle, &mut buffer)?;
+
+            if buffer.as_str() < after_str.as_str() {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        if low == num_terms {
+            return Ok(SegmentAfterValue::AfterAll);
+        }
+        buffer.clear();
+        str_column.ord_to_str(low, &mut buffer)?;
+
+        if buffer == *after_str {
+            Ok(SegmentAfterValue::Exact(SegmentKeyValue::TermOrd(low)))
+        } else {
+            Ok(SegmentAfterValue::Before(SegmentKeyValue::TermOrd(low)))
+        }
+    }
+
+    fn fill_values(&self, doc: DocId, values: &mut Vec<SegmentKeyValue>) {
+        values.clear();
+
+        if let Some((numerical_column, column_type)) = &self.numerical_column_opt {
+            for value in numerical_column.values_for_doc(doc) {
+                let value = if let Some(interval_millis) = self.date_interval_millis_opt {
+                    let millis = numerical_value_to_millis(value, *column_type);
+                    (millis - millis.rem_euclid(interval_millis)).to_u64()
+                } else {
+                    value
+                };
+                values.push(SegmentKeyValue::Numerical(value));
+            }
+        }
+        if let Some(str_column) = &self.str_column_opt {
+            values.extend(str_column.term_ords(doc).map(SegmentKeyValue::TermOrd));
+        }
+        values.sort_unstable();
+        values.dedup();
+
+        if values.is_empty() && self.missing_bucket {
+            values.push(SegmentKeyValue::Null);
+        }
+    }
+
+    fn numerical_key_value(&self, value: u64) -> CompositeKeyValue {
+        if self.date_interval_millis_opt.is_some() {
+            return CompositeKeyValue::I64(i64::from_u64(value));
+        }
+        let column_type = self
+            .numerical_column_opt
+            .as_ref()
+            .map(|(_, column_type)| *column_type)
+            .unwrap_or(ColumnType::U64);
+        match column_type {
+            ColumnType::I64 => CompositeKeyValue::I64(i64::from_u64(value)),
+            ColumnType::F64 => CompositeKeyValue::F64(f64::from_u64(value)),
+            ColumnType::Bool => CompositeKeyValue::Bool(bool::from_u64(value)),
+            ColumnType::DateTime => {
+                CompositeKeyValue::I64(DateTime::from_u64(value).into_timestamp_millis())
+            }
+            _ => CompositeKeyValue::U64(value),
+        }
+    }
+
+    fn key_value(
+        &self,
+        segment_value: SegmentKeyValue,
+        buffer: &mut String,
+    ) -> io::Result<CompositeKeyValue> {
+        match segment_value {
+            SegmentKeyValue::Null => Ok(CompositeKeyValue::Null),
+            SegmentKeyValue::Numerical(value) => Ok(self.numerical_key_value(value)),
+            SegmentKeyValue::TermOrd(term_ord) => {
+                let str_column = self
+                    .str_column_opt
+                    .as_ref()
+                    .expect("term ordinals should come from the string column");
+                buffer.clear();
+                str_column.ord_to_str(term_ord, buffer)?;
+                Ok(CompositeKeyValue::Str(buffer.clone()))
+            }
+        }
+    }
+
+    /// Compares a segment value to the `after` value of the source, in ascending order.
+    fn compare_to_after(&self, segment_value: SegmentKeyValue) -> Ordering {
+        let Some(after_value) = &self.after_value_opt else {
+            return Ordering::Greater;
+        };
+        match after_value {
+            SegmentAfterValue::Exact(after_value) => segment_value.cmp(after_value),
+            SegmentAfterValue::Before(after_value) => {
+                if segment_value < *after_value {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            }
+            SegmentAfterValue::AfterAll => Ordering::Less,
+            SegmentAfterValue::Numerical(after_value) => match segment_value {
+                SegmentKeyValue::Null => Ordering::Less,
+                SegmentKeyValue::Numerical(value) => {
+                    compare_key_values(&self.numerical_key_value(value), after_value)
+                }
+                SegmentKeyValue::TermOrd(_) => Ordering::Greater,
+            },
+        }
+    }
+}
+
+fn numerical_value_to_millis(value: u64, column_type: ColumnType) -> i64 {
+    match column_type {
+        ColumnType::DateTime => DateTime::from_u64(value).into_timestamp_millis(),
+        ColumnType::I64 => i64::from_u64(value),
+        ColumnType::F64 => f64::from_u64(value) as i64,
+        _ => i64::try_from(value).unwrap_or(i64::MAX),
+    }
+}
+
+fn numerical_value_to_f64(value: u64, column_type: ColumnType) -> f64 {
+    match column_type {
+        ColumnType::I64 => i64::from_u64(value) as f64,
+        ColumnType::F64 => f64::from_u64(value),
+        ColumnType::Bool => value as f64,
+        ColumnType::DateTime => DateTime::from_u64(value).into_timestamp_millis() as f64,
+        _ => value as f64,
+    }
+}
+
+struct SegmentBucket {
+    doc_count: u64,
+    metrics: Vec<MetricState>,
+}
+
+/// Composite aggregation collector working at the scale of the segment.
+pub struct CompositeSegmentCollector {
+    size: usize,
+    orders: Vec<SourceOrder>,
+    sources: Vec<SegmentSource>,
+    metric_columns: Vec<Option<(Column<u64>, ColumnType)>>,
+    // Workbenches holding the values of the current document.
+    source_values: Vec<Vec<SegmentKeyValue>>,
+    metric_values: Vec<Vec<f64>>,
+    buckets: FnvHashMap<Vec<SegmentKeyValue>, SegmentBucket>,
+    // Once the buckets have been truncated, the last key that can make it into the first `size`
+    // buckets.
+    sentinel_key_opt: Option<Vec<SegmentKeyValue>>,
+}
+
+impl CompositeSegmentCollector {
+    fn is_after(&self, key: &[SegmentKeyValue]) -> bool {
+        for ((source, order), value) in self.sources.iter().zip(&self.orders).zip(key) {
+            match order.apply(source.compare_to_after(*value)) {
+                Ordering::Less => return false,
+                Ordering::Greater => return true,
+                Ordering::Equal => {}
+            }
+        }
+        // The key is the `after` key.
+        false
+    }
+
+    fn collect_key(&mut self, key: Vec<SegmentKeyValue>) {
+        if !self.is_after(&key) {
+            return;
+        }
+        if let Some(sentinel_key) = &self.sentinel_key_opt
+            && compare_keys(&self.orders, &key, sentinel_key, Ord::cmp).is_gt()
+        {
+            return;
+        }
+        let num_metrics = self.metric_columns.len();
+        let bucket = self.buckets.entry(key).or_insert_with(|| SegmentBucket {
+            doc_count: 0,
+            metrics: vec![MetricState::default(); num_metrics],
+        });
+        bucket.doc_count += 1;
+
+        for (metric_state, metric_values) in bucket.metrics.iter_mut().zip(&self.metric_values) {
+            for metric_value in metric_values {
+                metric_state.add(*metric_value);
+            }
+        }
+        if self.buckets.len() >= 2 * self.size {
+            self.truncate();
+        }
+    }
+
+    /// Keeps the first `size` buckets: the other ones can no longer make it.
+    fn truncate(&mut self) {
+        let mut buckets: Vec<(Vec<SegmentKeyValue>, SegmentBucket)> =
+            self.buckets.drain().collect();
+        let orders = &self.orders;
+        buckets.select_nth_unstable_by(self.size - 1, |(left, _), (right, _)| {
+            compare_keys(orders, left, right, Ord::cmp)
+        });
+        buckets.truncate(self.size);
+        self.sentinel_key_opt = Some(buckets[self.size - 1].0.clone());
+        self.buckets.extend(buckets);
+    }
+}
+
+impl SegmentCollector for CompositeSegmentCollector {
+    type Fruit = tantivy::Result<Vec<CompositeBucket>>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let mut source_values = std::mem::take(&mut self.source_values);
+
+        for (source, values) in self.sources.iter().zip(source_values.iter_mut()) {
+            source.fill_values(doc, values);
+        }
+        if source_values.iter().any(Vec::is_empty) {
+            self.source_values = source_values;
+            return;
+        }
+        for (metric_column_opt, values) in self.metric_columns.iter().zip(&mut self.metric_values) {
+            values.clear();
+
+            if let Some((metric_column, column_type)) = metric_column_opt {
+                values.extend(
+                    metric_column
+                        .values_for_doc(doc)
+                        .map(|value| numerical_value_to_f64(value, *column_type)),
+                );
+            }
+        }
+        // Multi-valued sources yield one key per combination of values.
+        for key in source_values
+            .iter()
+            .map(|values| values.iter().copied())
+            .multi_cartesian_product()
+        {
+            self.collect_key(key);
+        }
+        self.source_values = source_values;
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let mut buckets: Vec<(Vec<SegmentKeyValue>, SegmentBucket)> =
+            self.buckets.into_iter().collect();
+        buckets.sort_by(|(left, _), (right, _)| compare_keys(&self.orders, left, right, Ord::cmp));
+        buckets.truncate(self.size);
+
+        let mut buffer = String::new();
+        let mut composite_buckets = Vec::with_capacity(buckets.len());
+
+        for (segment_key, segment_bucket) in buckets {
+            let key = self
+                .sources
+                .iter()
+                .zip(segment_key)
+                .map(|(source, segment_value)| source.key_value(segment_value, &mut buffer))
+                .collect::<io::Result<_>>()?;
+            composite_buckets.push(CompositeBucket {
+                key,
+                doc_count: segment_bucket.doc_count,
+                metrics: segment_bucket.metrics,
+            });
+        }
+        Ok(composite_buckets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::QuickwitAggregations;
+
+    fn composite_collector_from_json(aggregations_json: JsonValue) -> CompositeCollector {
+        let aggregations: QuickwitAggregations = serde_json::from_value(aggregations_json).unwrap();
+        let QuickwitAggregations::CompositeAggregation(collector) = aggregations else {
+            panic!("expected a composite aggregation");
+        };
+        collector
+    }
+
+    fn composite_bucket(key: Vec<CompositeKeyValue>, doc_count: u64) -> CompositeBucket {
+        CompositeBucket {
+            key,
+            doc_count,
+            metrics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_composite_collector_deserialize() {
+        let collector = composite_collector_from_json(serde_json::json!({
+            "billing": {
+                "composite": {
+                    "size": 100,
+                    "sources": [
+                        {"tenant": {"terms": {"field": "tenant_id"}}},
+                        {"day": {"date_histogram": {
+                            "field": "timestamp",
+                            "calendar_interval": "1d",
+                            "order": "desc"
+                        }}}
+                    ],
+                    "after": {"tenant": "acme", "day": 1_700_000_000_000i64}
+                },
+                "aggs": {
+                    "bytes": {"sum": {"field": "num_bytes"}}
+                }
+            }
+        }));
+        assert_eq!(collector.name, "billing");
+        assert_eq!(collector.size, 100);
+        assert_eq!(collector.sources.len(), 2);
+        assert_eq!(
+            collector.sources[1].source,
+            CompositeSource::DateHistogram {
+                field: "timestamp".to_string(),
+                interval_millis: MILLIS_PER_DAY,
+            }
+        );
+        assert_eq!(collector.sources[1].order, SourceOrder::Desc);
+        assert_eq!(
+            collector.after_key_opt.unwrap(),
+            vec![
+                CompositeKeyValue::Str("acme".to_string()),
+                CompositeKeyValue::I64(1_700_000_000_000)
+            ]
+        );
+        assert_eq!(collector.metrics.len(), 1);
+        assert_eq!(
+            collector.fast_field_names(),
+            HashSet::from_iter([
+                "tenant_id".to_string(),
+                "timestamp".to_string(),
+                "num_bytes".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_composite_collector_deserialize_invalid() {
+        let invalid_aggregations = [
+            serde_json::json!({
+                "billing": {"composite": {"sources": [{"tenant": {"terms": {"field": "t"}}}]}},
+                "other": {"composite": {"sources": [{"tenant": {"terms": {"field": "t"}}}]}}
+            }),
+            serde_json::json!({"billing": {"composite": {"sources": []}}}),
+            serde_json::json!({
+                "billing": {"composite": {
+                    "size": 0,
+                    "sources": [{"tenant": {"terms": {"field": "t"}}}]
+                }}
+            }),
+            serde_json::json!({
+                "billing": {"composite": {
+                    "sources": [{"day": {"date_histogram": {
+                        "field": "ts",
+                        "calendar_interval": "1M"
+                    }}}]
+                }}
+            }),
+            serde_json::json!({
+                "billing": {"composite": {
+                    "sources": [{"tenant": {"terms": {"field": "t"}}}],
+                    "after": {"unknown": "acme"}
+                }}
+            }),
+        ];
+        for invalid_aggregation in invalid_aggregations {
+            serde_json::from_value::<CompositeCollector>(invalid_aggregation).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_parse_fixed_interval() {
+        assert_eq!(parse_fixed_interval("30m").unwrap(), 1_800_000);
+        assert_eq!(parse_fixed_interval("1d").unwrap(), MILLIS_PER_DAY);
+        assert_eq!(parse_fixed_interval("500ms").unwrap(), 500);
+        parse_fixed_interval("0s").unwrap_err();
+        parse_fixed_interval("1w").unwrap_err();
+        parse_fixed_interval("d").unwrap_err();
+    }
+
+    #[test]
+    fn test_compare_key_values() {
+        let ordered_values = [
+            CompositeKeyValue::Null,
+            CompositeKeyValue::Bool(false),
+            CompositeKeyValue::Bool(true),
+            CompositeKeyValue::I64(-1),
+            CompositeKeyValue::F64(0.5),
+            CompositeKeyValue::U64(1),
+            CompositeKeyValue::Str("a".to_string()),
+            CompositeKeyValue::Str("b".to_string()),
+        ];
+        for (left, right) in ordered_values.iter().tuple_windows() {
+            assert_eq!(compare_key_values(left, right), Ordering::Less);
+            assert_eq!(compare_key_values(right, left), Ordering::Greater);
+        }
+        assert_eq!(
+            compare_key_values(&CompositeKeyValue::I64(1), &CompositeKeyValue::U64(1)),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_merge_buckets() {
+        let orders = [SourceOrder::Asc, SourceOrder::Desc];
+        let key = |tenant: &str, day: i64| {
+            vec![
+                CompositeKeyValue::Str(tenant.to_string()),
+                CompositeKeyValue::I64(day),
+            ]
+        };
+        let merged_buckets = merge_buckets(
+            vec![
+                vec![
+                    composite_bucket(key("a", 1), 1),
+                    composite_bucket(key("b", 2), 2),
+                ],
+                vec![
+                    composite_bucket(key("a", 2), 3),
+                    composite_bucket(key("b", 2), 4),
+                    composite_bucket(key("c", 0), 5),
+                ],
+            ],
+            &orders,
+            3,
+        );
+        assert_eq!(
+            merged_buckets,
+            vec![
+                composite_bucket(key("a", 2), 3),
+                composite_bucket(key("a", 1), 1),
+                composite_bucket(key("b", 2), 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_metric_result() {
+        let mut metric_state = MetricState::default();
+        let avg = CompositeMetricAggregation::Avg(MetricFieldRequest {
+            field: "num_bytes".to_string(),
+        });
+        let MetricResult::Average(average) = avg.metric_result(&metric_state) else {
+            panic!("expected an average");
+        };
+        assert_eq!(average.value, None);
+
+        metric_state.add(1.0);
+        metric_state.add(5.0);
+        let MetricResult::Average(average) = avg.metric_result(&metric_state) else {
+            panic!("expected an average");
+        };
+        assert_eq!(average.value, Some(3.0));
+    }
+}
@@ -40,6 +40,8 @@ pub enum SearchError {
     InvalidArgument(String),
     #[error("{0}")]
     InvalidQuery(String),
+    #[error("not found: {0}")]
+    NotFound(String),
     #[error("storage not found: `{0}`)")]
     StorageResolver(#[from] StorageResolverError),
     #[error("request timed out: {0}")]
@@ -78,6 +80,7 @@ impl ServiceError for SearchError {
             Self::InvalidAggregationRequest(_) => ServiceErrorCode::BadRequest,
             Self::InvalidArgument(_) => ServiceErrorCode::BadRequest,
             Self::InvalidQuery(_) => ServiceErrorCode::BadRequest,
+            Self::NotFound(_) => ServiceErrorCode::NotFound,
             Self::StorageResolver(storage_err) => {
                 rate_limited_error!(
                     limit_per_min = 6,
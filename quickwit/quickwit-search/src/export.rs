@@ -0,0 +1,433 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of all the documents matching a query.
+//!
+//! Contrary to the scroll API, the export does not materialize the list of matching documents:
+//! every split is read page by page, using `search_after` to resume where the previous page
+//! stopped, so that the memory used by an export does not depend on the number of matching
+//! documents.
+
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+use std::sync::Arc;
+
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt, TryStreamExt};
+use quickwit_common::binary_heap::SortKeyMapper;
+use quickwit_metastore::SplitMetadata;
+use quickwit_proto::metastore::MetastoreServiceClient;
+use quickwit_proto::search::{CountHits, Hit, PartialHit, SearchRequest, SortOrder};
+use tracing::info;
+
+use crate::collector::{HitSortingMapper, PartialHitSortingKey};
+use crate::root::{
+    IndexesMetasForLeafSearch, SearchJob, fetch_docs_phase, jobs_to_leaf_request,
+    plan_splits_for_root_search,
+};
+use crate::{ClusterClient, SearchError};
+
+/// Number of documents read per page when documents are exported unsorted. Splits are then
+/// exported one after the other.
+const EXPORT_PAGE_SIZE: u64 = 1_000;
+
+/// Number of documents read per page and per split when documents are exported sorted. All the
+/// splits are then read concurrently, so the memory used by an export is bounded by the number
+/// of splits times this page size.
+const SORTED_EXPORT_PAGE_SIZE: u64 = 100;
+
+/// A stream of exported hits.
+pub type HitStream = BoxStream<'static, crate::Result<Hit>>;
+
+type PageStream = BoxStream<'static, crate::Result<Vec<Hit>>>;
+
+/// Exports all the documents matching a search request.
+///
+/// If the request has sort fields, the documents are returned in that order by merging the sorted
+/// streams of every split. Otherwise, the splits are exported one after the other.
+pub async fn root_export(
+    mut search_request: SearchRequest,
+    mut metastore: MetastoreServiceClient,
+    cluster_client: ClusterClient,
+) -> crate::Result<HitStream> {
+    if search_request.aggregation_request.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "aggregations are not supported by the export API".to_string(),
+        ));
+    }
+    if search_request.search_after.is_some() || search_request.scroll_ttl_secs.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "`search_after` and scroll are not supported by the export API".to_string(),
+        ));
+    }
+    if search_request.knn.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "kNN search is not supported by the export API".to_string(),
+        ));
+    }
+    let (split_metadatas, indexes_metas_for_leaf_search, runtime_fields_plan_opt) =
+        plan_splits_for_root_search(&mut search_request, &mut metastore).await?;
+
+    if runtime_fields_plan_opt.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "runtime fields are not supported by the export API".to_string(),
+        ));
+    }
+    info!(
+        query_ast = search_request.query_ast.as_str(),
+        num_splits = split_metadatas.len(),
+        is_sorted = !search_request.sort_fields.is_empty(),
+        "root_export"
+    );
+    Ok(export_planned_splits(
+        search_request,
+        split_metadatas,
+        indexes_metas_for_leaf_search,
+        cluster_client,
+    ))
+}
+
+/// Exports all the documents matching a search request from the splits returned by
+/// [`plan_splits_for_root_search`].
+///
+/// The max hits, start offset, `search_after`, snippet fields and aggregations of the search
+/// request are ignored.
+pub(crate) fn export_planned_splits(
+    mut search_request: SearchRequest,
+    split_metadatas: Vec<SplitMetadata>,
+    indexes_metas_for_leaf_search: IndexesMetasForLeafSearch,
+    cluster_client: ClusterClient,
+) -> HitStream {
+    let is_sorted = !search_request.sort_fields.is_empty();
+
+    search_request.max_hits = if is_sorted {
+        SORTED_EXPORT_PAGE_SIZE
+    } else {
+        EXPORT_PAGE_SIZE
+    };
+    search_request.start_offset = 0;
+    search_request.count_hits = CountHits::Underestimate as i32;
+    search_request.aggregation_request = None;
+    search_request.search_after = None;
+    search_request.snippet_fields.clear();
+    search_request.profile = false;
+
+    let export_context = Arc::new(ExportContext {
+        search_request,
+        indexes_metas_for_leaf_search,
+        cluster_client,
+    });
+    let mut page_streams: Vec<PageStream> = split_metadatas
+        .into_iter()
+        .map(|split_metadata| split_page_stream(export_context.clone(), split_metadata))
+        .collect();
+
+    if !is_sorted {
+        let hit_stream = stream::iter(page_streams)
+            .flatten()
+            .map_ok(|hits| stream::iter(hits.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed();
+        return hit_stream;
+    }
+    let sort_fields = &export_context.search_request.sort_fields;
+    let hit_sorting_mapper = HitSortingMapper {
+        order1: sort_fields[0].sort_order(),
+        order2: sort_fields
+            .get(1)
+            .map(|sort_field| sort_field.sort_order())
+            .unwrap_or(SortOrder::Desc),
+    };
+    // Splits are pulled from the last one, so we reverse them to pull them in the order returned
+    // by the metastore.
+    page_streams.reverse();
+    merge_sorted_page_streams(page_streams, hit_sorting_mapper)
+}
+
+struct ExportContext {
+    search_request: SearchRequest,
+    indexes_metas_for_leaf_search: IndexesMetasForLeafSearch,
+    cluster_client: ClusterClient,
+}
+
+/// Returns the stream of pages of hits of a split.
+fn split_page_stream(
+    export_context: Arc<ExportContext>,
+    split_metadata: SplitMetadata,
+) -> PageStream {
+    let split_metadata = Arc::new(split_metadata);
+
+    // The state is the `search_after` cursor of the next page, or `None` once the split is
+    // exhausted.
+    stream::try_unfold(Some(None), move |search_after_opt_opt| {
+        next_split_page(
+            export_context.clone(),
+            split_metadata.clone(),
+            search_after_opt_opt,
+        )
+    })
+    .boxed()
+}
+
+async fn next_split_page(
+    export_context: Arc<ExportContext>,
+    split_metadata: Arc<SplitMetadata>,
+    search_after_opt_opt: Option<Option<PartialHit>>,
+) -> crate::Result<Option<(Vec<Hit>, Option<Option<PartialHit>>)>> {
+    let Some(search_after_opt) = search_after_opt_opt else {
+        return Ok(None);
+    };
+    let (hits, next_search_after_opt) =
+        fetch_split_page(&export_context, &split_metadata, search_after_opt).await?;
+    Ok(Some((hits, next_search_after_opt.map(Some))))
+}
+
+/// Fetches the page of hits of a split following `search_after_opt`, and returns the cursor of the
+/// next page, if any.
+async fn fetch_split_page(
+    export_context: &ExportContext,
+    split_metadata: &SplitMetadata,
+    search_after_opt: Option<PartialHit>,
+) -> crate::Result<(Vec<Hit>, Option<PartialHit>)> {
+    let mut search_request = export_context.search_request.clone();
+    search_request.search_after = search_after_opt;
+
+    let search_job = SearchJob::from(split_metadata);
+    let client = export_context
+        .cluster_client
+        .search_job_placer
+        .assign_job(search_job.clone(), &HashSet::new())
+        .await?;
+    let leaf_search_request = jobs_to_leaf_request(
+        &search_request,
+        &export_context.indexes_metas_for_leaf_search,
+        vec![search_job],
+    )?;
+    let leaf_search_response = export_context
+        .cluster_client
+        .leaf_search(leaf_search_request, client)
+        .await?;
+
+    if let Some(search_error) = SearchError::from_split_errors(&leaf_search_response.failed_splits)
+    {
+        return Err(search_error);
+    }
+    let partial_hits = leaf_search_response.partial_hits;
+
+    let next_search_after_opt = if (partial_hits.len() as u64) < search_request.max_hits {
+        None
+    } else {
+        partial_hits.last().cloned()
+    };
+    if partial_hits.is_empty() {
+        return Ok((Vec::new(), None));
+    }
+    let (hits, _) = fetch_docs_phase(
+        &export_context.indexes_metas_for_leaf_search,
+        &partial_hits,
+        std::slice::from_ref(split_metadata),
+        &search_request,
+        &export_context.cluster_client,
+    )
+    .await?;
+    Ok((hits, next_search_after_opt))
+}
+
+struct SplitCursor {
+    page_stream: PageStream,
+    buffered_hits: VecDeque<Hit>,
+}
+
+struct MergeState {
+    cursors: Vec<SplitCursor>,
+    /// Sort key of the first buffered hit of every non-exhausted cursor.
+    heads: BinaryHeap<(PartialHitSortingKey, usize)>,
+    /// Cursors whose head must be refilled before the next hit can be emitted.
+    cursors_to_refill: Vec<usize>,
+    hit_sorting_mapper: HitSortingMapper,
+}
+
+fn hit_sort_key(
+    hit_sorting_mapper: &HitSortingMapper,
+    hit: &Hit,
+) -> crate::Result<PartialHitSortingKey> {
+    let partial_hit = hit
+        .partial_hit
+        .as_ref()
+        .ok_or_else(|| SearchError::Internal("exported hit has no partial hit".to_string()))?;
+    Ok(hit_sorting_mapper.get_sort_key(partial_hit))
+}
+
+/// Merges the sorted pages of several splits into a single sorted stream of hits.
+///
+/// At most one page per split is buffered at any time.
+fn merge_sorted_page_streams(
+    page_streams: Vec<PageStream>,
+    hit_sorting_mapper: HitSortingMapper,
+) -> HitStream {
+    let num_splits = page_streams.len();
+    let cursors = page_streams
+        .into_iter()
+        .map(|page_stream| SplitCursor {
+            page_stream,
+            buffered_hits: VecDeque::new(),
+        })
+        .collect();
+    let merge_state = MergeState {
+        cursors,
+        heads: BinaryHeap::with_capacity(num_splits),
+        cursors_to_refill: (0..num_splits).collect(),
+        hit_sorting_mapper,
+    };
+    stream::try_unfold(merge_state, next_merged_hit).boxed()
+}
+
+async fn next_merged_hit(mut merge_state: MergeState) -> crate::Result<Option<(Hit, MergeState)>> {
+    while let Some(cursor_idx) = merge_state.cursors_to_refill.pop() {
+        let cursor = &mut merge_state.cursors[cursor_idx];
+
+        while cursor.buffered_hits.is_empty() {
+            let Some(hits) = cursor.page_stream.try_next().await? else {
+                break;
+            };
+            cursor.buffered_hits.extend(hits);
+        }
+        if let Some(head_hit) = cursor.buffered_hits.front() {
+            let sort_key = hit_sort_key(&merge_state.hit_sorting_mapper, head_hit)?;
+            merge_state.heads.push((sort_key, cursor_idx));
+        }
+    }
+    // The greatest sort key comes first.
+    let Some((_, cursor_idx)) = merge_state.heads.pop() else {
+        return Ok(None);
+    };
+    let hit = merge_state.cursors[cursor_idx]
+        .buffered_hits
+        .pop_front()
+        .expect("cursor in the heap should have a buffered hit");
+    merge_state.cursors_to_refill.push(cursor_idx);
+    Ok(Some((hit, merge_state)))
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::search::{SortByValue, SortValue};
+
+    use super::*;
+
+    fn make_hit(split_id: &str, doc_id: u32, timestamp: i64) -> Hit {
+        Hit {
+            json: format!(r#"{{"timestamp": {timestamp}}}"#),
+            partial_hit: Some(PartialHit {
+                sort_value: Some(SortByValue {
+                    sort_value: Some(SortValue::I64(timestamp)),
+                }),
+                sort_value2: None,
+                split_id: split_id.to_string(),
+                segment_ord: 0,
+                doc_id,
+            }),
+            snippet: None,
+            index_id: "test-index".to_string(),
+            fields_json: None,
+        }
+    }
+
+    fn make_page_stream(pages: Vec<Vec<Hit>>) -> PageStream {
+        stream::iter(pages.into_iter().map(Ok)).boxed()
+    }
+
+    fn timestamps(hits: &[Hit]) -> Vec<i64> {
+        hits.iter()
+            .map(|hit| {
+                let Some(SortValue::I64(timestamp)) = hit
+                    .partial_hit
+                    .as_ref()
+                    .unwrap()
+                    .sort_value
+                    .unwrap()
+                    .sort_value
+                else {
+                    panic!("expected an i64 sort value");
+                };
+                timestamp
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_merge_sorted_page_streams_desc() {
+        let page_streams = vec![
+            make_page_stream(vec![
+                vec![make_hit("split-1", 0, 10), make_hit("split-1", 1, 7)],
+                vec![make_hit("split-1", 2, 3)],
+            ]),
+            make_page_stream(Vec::new()),
+            make_page_stream(vec![
+                Vec::new(),
+                vec![make_hit("split-2", 0, 9), make_hit("split-2", 1, 8)],
+                vec![make_hit("split-2", 2, 1)],
+            ]),
+        ];
+        let hit_sorting_mapper = HitSortingMapper {
+            order1: SortOrder::Desc,
+            order2: SortOrder::Desc,
+        };
+        let hits: Vec<Hit> = merge_sorted_page_streams(page_streams, hit_sorting_mapper)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(timestamps(&hits), vec![10, 9, 8, 7, 3, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_sorted_page_streams_asc() {
+        let page_streams = vec![
+            make_page_stream(vec![vec![
+                make_hit("split-1", 0, 1),
+                make_hit("split-1", 1, 4),
+            ]]),
+            make_page_stream(vec![
+                vec![make_hit("split-2", 0, 2)],
+                vec![make_hit("split-2", 1, 3)],
+            ]),
+        ];
+        let hit_sorting_mapper = HitSortingMapper {
+            order1: SortOrder::Asc,
+            order2: SortOrder::Desc,
+        };
+        let hits: Vec<Hit> = merge_sorted_page_streams(page_streams, hit_sorting_mapper)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(timestamps(&hits), vec![1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_sorted_page_streams_propagates_errors() {
+        let failing_page_stream: PageStream = stream::iter(vec![
+            Ok(vec![make_hit("split-1", 0, 10)]),
+            Err(SearchError::Internal("leaf search failed".to_string())),
+        ])
+        .boxed();
+        let hit_sorting_mapper = HitSortingMapper {
+            order1: SortOrder::Desc,
+            order2: SortOrder::Desc,
+        };
+        let error = merge_sorted_page_streams(vec![failing_page_stream], hit_sorting_mapper)
+            .try_collect::<Vec<Hit>>()
+            .await
+            .unwrap_err();
+        assert!(matches!(error, SearchError::Internal(_)));
+    }
+}
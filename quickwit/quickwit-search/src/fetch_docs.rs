@@ -14,13 +14,14 @@
 
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{Context, Ok};
 use futures::{StreamExt, TryStreamExt};
 use itertools::Itertools;
 use quickwit_doc_mapper::DocMapper;
 use quickwit_proto::search::{
-    FetchDocsResponse, PartialHit, SnippetRequest, SplitIdAndFooterOffsets,
+    FetchDocsResponse, PartialHit, SnippetRequest, SplitFetchDocsProfile, SplitIdAndFooterOffsets,
 };
 use quickwit_storage::Storage;
 use tantivy::query::Query;
@@ -37,7 +38,7 @@ use crate::{GlobalDocAddress, convert_document_to_json_string};
 const SNIPPET_MAX_NUM_CHARS: usize = 150;
 
 /// Given a list of global doc address, fetches all the documents and
-/// returns them as a hashmap.
+/// returns them as a hashmap, along with the time spent fetching docs in each split.
 async fn fetch_docs_to_map(
     searcher_context: Arc<SearcherContext>,
     mut global_doc_addrs: Vec<GlobalDocAddress>,
@@ -45,7 +46,10 @@ async fn fetch_docs_to_map(
     splits: &[SplitIdAndFooterOffsets],
     doc_mapper: Arc<DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
-) -> anyhow::Result<HashMap<GlobalDocAddress, Document>> {
+) -> anyhow::Result<(
+    HashMap<GlobalDocAddress, Document>,
+    Vec<SplitFetchDocsProfile>,
+)> {
     let mut split_fetch_docs_futures = Vec::new();
 
     let split_offsets_map: HashMap<&str, &SplitIdAndFooterOffsets> = splits
@@ -65,39 +69,51 @@ async fn fetch_docs_to_map(
         let split_and_offset = split_offsets_map
             .get(split_id)
             .ok_or_else(|| anyhow::anyhow!("failed to find offset for split {}", split_id))?;
-        split_fetch_docs_futures.push(fetch_docs_in_split(
+        let num_docs = global_doc_addrs.len() as u64;
+        let fetch_docs_in_split_future = fetch_docs_in_split(
             searcher_context.clone(),
             global_doc_addrs,
             index_storage.clone(),
             split_and_offset,
             doc_mapper.clone(),
             snippet_request_opt,
-        ));
+        );
+        split_fetch_docs_futures.push(async move {
+            let start = Instant::now();
+            let docs = fetch_docs_in_split_future.await?;
+            let split_profile = SplitFetchDocsProfile {
+                split_id: split_id.to_string(),
+                num_docs,
+                elapsed_microsecs: start.elapsed().as_micros() as u64,
+            };
+            Ok((docs, split_profile))
+        });
     }
 
-    let split_fetch_docs: Vec<Vec<(GlobalDocAddress, Document)>> = futures::future::try_join_all(
-        split_fetch_docs_futures,
-    )
-    .await
-    .map_err(|error| {
-        let split_ids = splits
-            .iter()
-            .map(|split| split.split_id.clone())
-            .collect_vec();
-        error!(split_ids = ?split_ids, error = ?error, "error when fetching docs in splits");
-        anyhow::anyhow!(
-            "error when fetching docs for splits {:?}: {:?}",
-            split_ids,
-            error
-        )
-    })?;
+    let split_fetch_docs: Vec<(Vec<(GlobalDocAddress, Document)>, SplitFetchDocsProfile)> =
+        futures::future::try_join_all(split_fetch_docs_futures)
+            .await
+            .map_err(|error| {
+                let split_ids = splits
+                    .iter()
+                    .map(|split| split.split_id.clone())
+                    .collect_vec();
+                error!(split_ids = ?split_ids, error = ?error, "error when fetching docs in splits");
+                anyhow::anyhow!(
+                    "error when fetching docs for splits {:?}: {:?}",
+                    split_ids,
+                    error
+                )
+            })?;
 
-    let global_doc_addr_to_doc_json: HashMap<GlobalDocAddress, Document> = split_fetch_docs
-        .into_iter()
-        .flat_map(|docs| docs.into_iter())
-        .collect();
+    let mut global_doc_addr_to_doc_json: HashMap<GlobalDocAddress, Document> = HashMap::new();
+    let mut split_profiles = Vec::with_capacity(split_fetch_docs.len());
 
-    Ok(global_doc_addr_to_doc_json)
+    for (docs, split_profile) in split_fetch_docs {
+        global_doc_addr_to_doc_json.extend(docs);
+        split_profiles.push(split_profile);
+    }
+    Ok((global_doc_addr_to_doc_json, split_profiles))
 }
 
 /// `fetch_docs` step of search.
@@ -105,6 +121,8 @@ async fn fetch_docs_to_map(
 /// This function takes a list of partial hits (possibly from different splits)
 /// and the storage associated to an index, fetches the document from
 /// the split document stores, and returns the full hits.
+///
+/// If `profile` is set, the response also contains the time spent fetching docs in each split.
 pub async fn fetch_docs(
     searcher_context: Arc<SearcherContext>,
     partial_hits: Vec<PartialHit>,
@@ -112,13 +130,14 @@ pub async fn fetch_docs(
     splits: &[SplitIdAndFooterOffsets],
     doc_mapper: Arc<DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
+    profile: bool,
 ) -> anyhow::Result<FetchDocsResponse> {
     let global_doc_addrs: Vec<GlobalDocAddress> = partial_hits
         .iter()
         .map(GlobalDocAddress::from_partial_hit)
         .collect();
 
-    let mut global_doc_addr_to_doc_json = fetch_docs_to_map(
+    let (mut global_doc_addr_to_doc_json, split_profiles) = fetch_docs_to_map(
         searcher_context,
         global_doc_addrs,
         index_storage,
@@ -144,7 +163,11 @@ pub async fn fetch_docs(
             }
         })
         .collect();
-    Ok(FetchDocsResponse { hits })
+    let split_profiles = if profile { split_profiles } else { Vec::new() };
+    Ok(FetchDocsResponse {
+        hits,
+        split_profiles,
+    })
 }
 
 // number of concurrent fetch allowed for a single split.
@@ -169,7 +192,7 @@ async fn fetch_docs_in_split(
     global_doc_addrs.sort_by_key(|doc| doc.doc_addr);
     // Opens the index without the ephemeral unbounded cache, this cache is indeed not useful
     // when fetching docs as we will fetch them only once.
-    let (mut index, _) = open_index_with_caches(
+    let (mut index, _, _) = open_index_with_caches(
         &searcher_context,
         index_storage,
         split,
@@ -0,0 +1,728 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Approximate k-nearest neighbor search on `dense_vector` fields.
+//!
+//! Each split holds a vector index per dense vector field (see
+//! [`quickwit_doc_mapper::vector_index`]). The kNN search runs as a regular search request whose
+//! query is the kNN filter, sorted by `_score`: leaves return the top `k` vectors of each split
+//! and the root merges them. When the search request also has a query, the query is run as a
+//! second search request and both lists of hits are combined with reciprocal rank fusion.
+
+use std::collections::{HashMap, HashSet};
+
+use futures::future::try_join;
+use quickwit_directories::HotDirectory;
+use quickwit_doc_mapper::DocMapper;
+use quickwit_doc_mapper::vector_index::{VectorIndex, vector_index_file_path};
+use quickwit_metastore::SplitMetadata;
+use quickwit_proto::search::{
+    CountHits, Hit, LeafSearchResponse, PartialHit, SearchRequest, SearchResponse, SortByValue,
+    SortField, SortOrder, SortValue,
+};
+use quickwit_query::query_ast::{BoolQuery, QueryAst};
+use quickwit_storage::OwnedBytes;
+use serde::{Deserialize, Serialize};
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::directory::error::OpenReadError;
+use tantivy::query::Query;
+use tantivy::{Directory, DocId, Index, Score, Searcher, SegmentOrdinal, SegmentReader};
+use tokio::time::Instant;
+
+use crate::root::{IndexesMetasForLeafSearch, root_search_aux};
+use crate::{ClusterClient, GlobalDocAddress, SearchError, SearcherContext};
+
+/// Maximum number of nearest neighbors returned by a kNN search.
+const MAX_K: u32 = 10_000;
+
+/// Maximum number of candidates considered per split.
+const MAX_NUM_CANDIDATES: u32 = 10_000;
+
+const DEFAULT_RANK_CONSTANT: u32 = 60;
+
+/// Approximate k-nearest neighbor query on a dense vector field.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KnnQuery {
+    /// Name of the `dense_vector` field.
+    pub field: String,
+    /// Vector to find the nearest neighbors of.
+    pub query_vector: Vec<f32>,
+    /// Number of nearest neighbors to return.
+    pub k: u32,
+    /// Number of candidates considered per split. Defaults to `1.5 * k`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_candidates: Option<u32>,
+    /// Query the nearest neighbors must match.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<QueryAst>,
+    /// Constant of the reciprocal rank fusion with the hits of the query, if any. Defaults to 60.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank_constant: Option<u32>,
+    /// Number of hits of the query considered by the reciprocal rank fusion. Defaults to the
+    /// number of hits requested.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank_window_size: Option<u32>,
+}
+
+impl KnnQuery {
+    pub(crate) fn parse(knn_json: &str) -> crate::Result<KnnQuery> {
+        serde_json::from_str(knn_json)
+            .map_err(|error| SearchError::InvalidArgument(format!("invalid kNN query: {error}")))
+    }
+
+    fn num_candidates(&self) -> usize {
+        let default_num_candidates = (self.k as usize * 3).div_ceil(2);
+        self.num_candidates
+            .map(|num_candidates| num_candidates as usize)
+            .unwrap_or(default_num_candidates.min(MAX_NUM_CANDIDATES as usize))
+    }
+
+    fn rank_constant(&self) -> u32 {
+        self.rank_constant.unwrap_or(DEFAULT_RANK_CONSTANT)
+    }
+
+    fn rank_window_size(&self, search_request: &SearchRequest) -> u64 {
+        self.rank_window_size
+            .map(u64::from)
+            .unwrap_or(search_request.start_offset + search_request.max_hits)
+    }
+
+    /// Validates the parts of the kNN query that do not depend on the targeted indexes.
+    pub(crate) fn validate(&self, search_request: &SearchRequest) -> crate::Result<()> {
+        if self.k == 0 || self.k > MAX_K {
+            return Err(SearchError::InvalidArgument(format!(
+                "kNN `k` must be between 1 and {MAX_K}"
+            )));
+        }
+        if let Some(num_candidates) = self.num_candidates
+            && (num_candidates < self.k || num_candidates > MAX_NUM_CANDIDATES)
+        {
+            return Err(SearchError::InvalidArgument(format!(
+                "kNN `num_candidates` must be between `k` and {MAX_NUM_CANDIDATES}"
+            )));
+        }
+        if let Some(rank_window_size) = self.rank_window_size
+            && (rank_window_size as u64) < search_request.start_offset + search_request.max_hits
+        {
+            return Err(SearchError::InvalidArgument(
+                "kNN `rank_window_size` must be greater than or equal to the number of hits \
+                 requested"
+                    .to_string(),
+            ));
+        }
+        if search_request.scroll_ttl_secs.is_some() || search_request.search_after.is_some() {
+            return Err(SearchError::InvalidArgument(
+                "scroll and `search_after` are not supported with kNN search".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validates the kNN query against the doc mapper of a targeted index and returns its filter
+    /// resolved against the default search fields of the index.
+    pub(crate) fn validate_for_index(
+        &self,
+        doc_mapper: &DocMapper,
+    ) -> crate::Result<Option<QueryAst>> {
+        let Some(dense_vector_options) = doc_mapper.dense_vector_field(&self.field) else {
+            return Err(SearchError::InvalidArgument(format!(
+                "kNN field `{}` is not a `dense_vector` field",
+                self.field
+            )));
+        };
+        let components = self
+            .query_vector
+            .iter()
+            .map(|component| Some(*component as f64));
+        dense_vector_options
+            .parse_vector(components)
+            .map_err(|error| {
+                SearchError::InvalidArgument(format!("invalid kNN query vector: {error}"))
+            })?;
+
+        let Some(filter) = &self.filter else {
+            return Ok(None);
+        };
+        let filter_resolved = filter
+            .clone()
+            .parse_user_query(doc_mapper.default_search_fields())
+            .map_err(|error| SearchError::InvalidQuery(error.to_string()))?;
+        doc_mapper.query(doc_mapper.schema(), filter_resolved.clone(), true, None)?;
+        Ok(Some(filter_resolved))
+    }
+
+    /// Returns the query used to prune splits: splits can contribute hits through the query or
+    /// through the kNN search, and aggregations are computed on the documents matching the query.
+    pub(crate) fn query_ast_for_split_pruning(
+        &self,
+        query_ast: &QueryAst,
+        has_aggregations: bool,
+    ) -> QueryAst {
+        let filter = self.filter.clone().unwrap_or(QueryAst::MatchAll);
+
+        if let QueryAst::MatchAll = query_ast {
+            return if has_aggregations {
+                QueryAst::MatchAll
+            } else {
+                filter
+            };
+        }
+        BoolQuery {
+            should: vec![query_ast.clone(), filter],
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+fn score_sort_field() -> SortField {
+    SortField {
+        field_name: "_score".to_string(),
+        sort_order: SortOrder::Desc as i32,
+        sort_datetime_format: None,
+    }
+}
+
+/// The header of the vector index of a split and the lists to search.
+pub(crate) struct KnnCandidates {
+    vector_index: VectorIndex,
+    lists: Vec<OwnedBytes>,
+}
+
+/// Fetches the vector index header of the kNN field and the lists closest to the query vector.
+///
+/// Returns `None` if the split has no vector index for the field, for instance because it was
+/// created before the field was added to the doc mapping.
+pub(crate) async fn load_knn_candidates(
+    index: &Index,
+    hot_directory: &HotDirectory,
+    knn_query: &KnnQuery,
+) -> anyhow::Result<Option<KnnCandidates>> {
+    let Ok(field) = index.schema().get_field(&knn_query.field) else {
+        return Ok(None);
+    };
+    let file_slice = match hot_directory.open_read(&vector_index_file_path(field)) {
+        Ok(file_slice) => file_slice,
+        Err(OpenReadError::FileDoesNotExist(_)) => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    let len_prefix = file_slice.read_bytes_slice_async(0..8).await?;
+    let hot_num_bytes = VectorIndex::hot_num_bytes(len_prefix.as_slice())?;
+    let hot_bytes = file_slice.read_bytes_slice_async(0..hot_num_bytes).await?;
+    let vector_index = VectorIndex::open(hot_bytes.as_slice())?;
+
+    if vector_index.dims() != knn_query.query_vector.len() {
+        return Ok(None);
+    }
+    let list_futures = vector_index
+        .probe(&knn_query.query_vector, knn_query.num_candidates())
+        .into_iter()
+        .map(|list| file_slice.read_bytes_slice_async(vector_index.list_byte_range(list)));
+    let lists = futures::future::try_join_all(list_futures).await?;
+
+    Ok(Some(KnnCandidates {
+        vector_index,
+        lists,
+    }))
+}
+
+/// Scores the kNN candidates of a split and returns the `k` nearest neighbors matching the
+/// filter query, with their similarity score as sort value.
+pub(crate) fn leaf_knn_search(
+    searcher: &Searcher,
+    filter_query: &dyn Query,
+    knn_query: &KnnQuery,
+    knn_candidates_opt: Option<&KnnCandidates>,
+    split_id: &str,
+) -> tantivy::Result<LeafSearchResponse> {
+    let mut leaf_search_response = LeafSearchResponse {
+        num_attempted_splits: 1,
+        num_successful_splits: 1,
+        ..Default::default()
+    };
+    let Some(knn_candidates) = knn_candidates_opt else {
+        return Ok(leaf_search_response);
+    };
+    // Splits have a single segment.
+    let Some(filter) = searcher
+        .search(filter_query, &KnnFilterCollector)?
+        .into_iter()
+        .next()
+    else {
+        return Ok(leaf_search_response);
+    };
+    let scored_vectors = knn_candidates.vector_index.search(
+        &knn_query.query_vector,
+        knn_candidates.lists.iter().map(OwnedBytes::as_slice),
+        |doc_id| filter[doc_id as usize],
+        knn_query.k as usize,
+    );
+    leaf_search_response.num_hits = scored_vectors.len() as u64;
+    leaf_search_response.partial_hits = scored_vectors
+        .into_iter()
+        .map(|scored_vector| PartialHit {
+            sort_value: Some(SortByValue {
+                sort_value: Some(SortValue::F64(scored_vector.score as f64)),
+            }),
+            sort_value2: None,
+            split_id: split_id.to_string(),
+            segment_ord: 0,
+            doc_id: scored_vector.doc_id,
+        })
+        .collect();
+    Ok(leaf_search_response)
+}
+
+/// Collects the documents matching the kNN filter, segment by segment.
+struct KnnFilterCollector;
+
+impl Collector for KnnFilterCollector {
+    type Fruit = Vec<Vec<bool>>;
+    type Child = KnnFilterSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(KnnFilterSegmentCollector {
+            matching_docs: vec![false; segment_reader.max_doc() as usize],
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Vec<bool>>) -> tantivy::Result<Vec<Vec<bool>>> {
+        Ok(segment_fruits)
+    }
+}
+
+struct KnnFilterSegmentCollector {
+    matching_docs: Vec<bool>,
+}
+
+impl SegmentCollector for KnnFilterSegmentCollector {
+    type Fruit = Vec<bool>;
+
+    fn collect(&mut self, doc_id: DocId, _score: Score) {
+        self.matching_docs[doc_id as usize] = true;
+    }
+
+    fn harvest(self) -> Vec<bool> {
+        self.matching_docs
+    }
+}
+
+/// Runs a search request with a kNN query.
+///
+/// The nearest neighbors are searched with a first request. If the search request has a query,
+/// it is run as a second request and both lists of hits are combined with reciprocal rank fusion.
+/// The number of hits and the aggregations are computed on the documents matching the query.
+pub(crate) async fn root_knn_search(
+    searcher_context: &SearcherContext,
+    indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
+    search_request: SearchRequest,
+    knn_query: KnnQuery,
+    split_metadatas: Vec<SplitMetadata>,
+    cluster_client: &ClusterClient,
+    start_instant: Instant,
+) -> crate::Result<SearchResponse> {
+    let query_ast: QueryAst = serde_json::from_str(&search_request.query_ast)
+        .map_err(|error| SearchError::InvalidQuery(error.to_string()))?;
+    let is_hybrid = !matches!(query_ast, QueryAst::MatchAll);
+
+    let knn_filter = knn_query.filter.clone().unwrap_or(QueryAst::MatchAll);
+    let knn_search_request = SearchRequest {
+        query_ast: serde_json::to_string(&knn_filter)?,
+        max_hits: knn_query.k as u64,
+        start_offset: 0,
+        sort_fields: vec![score_sort_field()],
+        aggregation_request: None,
+        snippet_fields: Vec::new(),
+        count_hits: CountHits::Underestimate as i32,
+        knn: Some(serde_json::to_string(&knn_query)?),
+        ..search_request.clone()
+    };
+    let knn_search_future = root_search_aux(
+        searcher_context,
+        indexes_metas_for_leaf_search,
+        knn_search_request,
+        split_metadatas.clone(),
+        cluster_client,
+        start_instant,
+    );
+    if !is_hybrid && search_request.aggregation_request.is_none() {
+        let mut knn_search_response = knn_search_future.await?;
+        knn_search_response.num_hits = knn_search_response.hits.len() as u64;
+        knn_search_response.hits = paginate_hits(knn_search_response.hits, &search_request);
+        return Ok(knn_search_response);
+    }
+    let mut query_search_request = SearchRequest {
+        knn: None,
+        start_offset: 0,
+        max_hits: if is_hybrid {
+            knn_query.rank_window_size(&search_request)
+        } else {
+            0
+        },
+        ..search_request.clone()
+    };
+    if query_search_request.sort_fields.is_empty() {
+        query_search_request.sort_fields = vec![score_sort_field()];
+    }
+    let query_search_future = root_search_aux(
+        searcher_context,
+        indexes_metas_for_leaf_search,
+        query_search_request,
+        split_metadatas,
+        cluster_client,
+        start_instant,
+    );
+    let (knn_search_response, query_search_response) =
+        try_join(knn_search_future, query_search_future).await?;
+
+    Ok(merge_knn_and_query_responses(
+        knn_search_response,
+        query_search_response,
+        is_hybrid,
+        knn_query.rank_constant(),
+        &search_request,
+    ))
+}
+
+fn merge_knn_and_query_responses(
+    knn_search_response: SearchResponse,
+    query_search_response: SearchResponse,
+    is_hybrid: bool,
+    rank_constant: u32,
+    search_request: &SearchRequest,
+) -> SearchResponse {
+    let (num_hits, hits) = if is_hybrid {
+        let query_doc_addresses: HashSet<GlobalDocAddress> = query_search_response
+            .hits
+            .iter()
+            .flat_map(|hit| hit.partial_hit.as_ref())
+            .map(GlobalDocAddress::from_partial_hit)
+            .collect();
+        let num_knn_only_hits = knn_search_response
+            .hits
+            .iter()
+            .flat_map(|hit| hit.partial_hit.as_ref())
+            .filter(|partial_hit| {
+                !query_doc_addresses.contains(&GlobalDocAddress::from_partial_hit(partial_hit))
+            })
+            .count() as u64;
+        // Query hits come first so that their snippets are kept.
+        let hits = fuse_hits_with_rrf(
+            [query_search_response.hits, knn_search_response.hits],
+            rank_constant,
+        );
+        (query_search_response.num_hits + num_knn_only_hits, hits)
+    } else {
+        (
+            knn_search_response.hits.len() as u64,
+            knn_search_response.hits,
+        )
+    };
+    let mut failed_splits = query_search_response.failed_splits;
+
+    for failed_split in knn_search_response.failed_splits {
+        if !failed_splits
+            .iter()
+            .any(|other_failed_split| other_failed_split.split_id == failed_split.split_id)
+        {
+            failed_splits.push(failed_split);
+        }
+    }
+    let mut errors = query_search_response.errors;
+    errors.extend(knn_search_response.errors);
+
+    let mut warnings = query_search_response.warnings;
+    warnings.extend(knn_search_response.warnings);
+
+    SearchResponse {
+        num_hits,
+        hits: paginate_hits(hits, search_request),
+        elapsed_time_micros: 0,
+        errors,
+        aggregation_postcard: query_search_response.aggregation_postcard,
+        scroll_id: None,
+        failed_splits,
+        num_successful_splits: query_search_response.num_successful_splits,
+        profile: query_search_response.profile,
+        warnings,
+    }
+}
+
+fn paginate_hits(hits: Vec<Hit>, search_request: &SearchRequest) -> Vec<Hit> {
+    hits.into_iter()
+        .skip(search_request.start_offset as usize)
+        .take(search_request.max_hits as usize)
+        .collect()
+}
+
+/// Combines ranked lists of hits with reciprocal rank fusion: the score of a hit is the sum of
+/// `1 / (rank_constant + rank)` over the lists it appears in, ranks starting at 1. The fused
+/// score replaces the sort values of the hits.
+fn fuse_hits_with_rrf<const N: usize>(ranked_hits: [Vec<Hit>; N], rank_constant: u32) -> Vec<Hit> {
+    let mut fused_hits: HashMap<GlobalDocAddress, (f64, Hit)> = HashMap::new();
+
+    for hits in ranked_hits {
+        for (rank, hit) in hits.into_iter().enumerate() {
+            let Some(partial_hit) = &hit.partial_hit else {
+                continue;
+            };
+            let doc_address = GlobalDocAddress::from_partial_hit(partial_hit);
+            let rrf_score = 1.0 / (rank_constant as f64 + rank as f64 + 1.0);
+            fused_hits.entry(doc_address).or_insert((0.0, hit)).0 += rrf_score;
+        }
+    }
+    let mut fused_hits: Vec<(GlobalDocAddress, f64, Hit)> = fused_hits
+        .into_iter()
+        .map(|(doc_address, (score, hit))| (doc_address, score, hit))
+        .collect();
+    fused_hits.sort_unstable_by(
+        |(left_address, left_score, _), (right_address, right_score, _)| {
+            right_score
+                .total_cmp(left_score)
+                .then_with(|| left_address.cmp(right_address))
+        },
+    );
+    fused_hits
+        .into_iter()
+        .map(|(_, score, mut hit)| {
+            if let Some(partial_hit) = &mut hit.partial_hit {
+                partial_hit.sort_value = Some(SortByValue {
+                    sort_value: Some(SortValue::F64(score)),
+                });
+                partial_hit.sort_value2 = None;
+            }
+            hit
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_query::query_ast::query_ast_from_user_text;
+    use serde_json::json;
+
+    use super::*;
+
+    fn hit(split_id: &str, doc_id: DocId) -> Hit {
+        Hit {
+            partial_hit: Some(PartialHit {
+                split_id: split_id.to_string(),
+                doc_id,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn hit_doc_id(hit: &Hit) -> DocId {
+        hit.partial_hit.as_ref().unwrap().doc_id
+    }
+
+    #[test]
+    fn test_knn_query_serde() {
+        let knn_query: KnnQuery = serde_json::from_value(json!({
+            "field": "embedding",
+            "query_vector": [0.5, 1.0],
+            "k": 10
+        }))
+        .unwrap();
+        assert_eq!(knn_query.num_candidates(), 15);
+        assert_eq!(knn_query.rank_constant(), 60);
+        assert_eq!(
+            serde_json::to_value(&knn_query).unwrap(),
+            json!({"field": "embedding", "query_vector": [0.5, 1.0], "k": 10})
+        );
+        KnnQuery::parse(r#"{"field": "embedding", "query_vector": [], "k": 1, "foo": 1}"#)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_knn_query_validate() {
+        let mut knn_query = KnnQuery {
+            field: "embedding".to_string(),
+            query_vector: vec![0.5, 1.0],
+            k: 10,
+            num_candidates: None,
+            filter: None,
+            rank_constant: None,
+            rank_window_size: None,
+        };
+        let search_request = SearchRequest {
+            max_hits: 20,
+            ..Default::default()
+        };
+        knn_query.validate(&search_request).unwrap();
+
+        knn_query.num_candidates = Some(5);
+        knn_query.validate(&search_request).unwrap_err();
+        knn_query.num_candidates = None;
+
+        knn_query.rank_window_size = Some(10);
+        knn_query.validate(&search_request).unwrap_err();
+        knn_query.rank_window_size = None;
+
+        knn_query.k = 0;
+        knn_query.validate(&search_request).unwrap_err();
+        knn_query.k = 10;
+
+        let scroll_request = SearchRequest {
+            scroll_ttl_secs: Some(60),
+            ..search_request
+        };
+        knn_query.validate(&scroll_request).unwrap_err();
+    }
+
+    #[test]
+    fn test_knn_query_validate_for_index() {
+        let doc_mapper: DocMapper = serde_json::from_value(json!({
+            "field_mappings": [
+                {"name": "body", "type": "text"},
+                {"name": "embedding", "type": "dense_vector", "dims": 2}
+            ],
+            "default_search_fields": ["body"]
+        }))
+        .unwrap();
+
+        let mut knn_query = KnnQuery {
+            field: "embedding".to_string(),
+            query_vector: vec![0.5, 1.0],
+            k: 10,
+            num_candidates: None,
+            filter: Some(query_ast_from_user_text("hello", None)),
+            rank_constant: None,
+            rank_window_size: None,
+        };
+        let filter_resolved = knn_query.validate_for_index(&doc_mapper).unwrap();
+        assert!(filter_resolved.is_some());
+
+        knn_query.query_vector = vec![0.5, 1.0, 2.0];
+        let error = knn_query.validate_for_index(&doc_mapper).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("expected vector with 2 dimensions")
+        );
+
+        knn_query.query_vector = vec![0.0, 0.0];
+        knn_query.validate_for_index(&doc_mapper).unwrap_err();
+
+        knn_query.query_vector = vec![0.5, 1.0];
+        knn_query.field = "body".to_string();
+        let error = knn_query.validate_for_index(&doc_mapper).unwrap_err();
+        assert!(error.to_string().contains("is not a `dense_vector` field"));
+    }
+
+    #[test]
+    fn test_query_ast_for_split_pruning() {
+        let mut knn_query =
+            KnnQuery::parse(r#"{"field": "embedding", "query_vector": [0.5, 1.0], "k": 10}"#)
+                .unwrap();
+        assert_eq!(
+            knn_query.query_ast_for_split_pruning(&QueryAst::MatchAll, false),
+            QueryAst::MatchAll
+        );
+        knn_query.filter = Some(QueryAst::MatchNone);
+        assert_eq!(
+            knn_query.query_ast_for_split_pruning(&QueryAst::MatchAll, false),
+            QueryAst::MatchNone
+        );
+        assert_eq!(
+            knn_query.query_ast_for_split_pruning(&QueryAst::MatchAll, true),
+            QueryAst::MatchAll
+        );
+        let QueryAst::Bool(bool_query) =
+            knn_query.query_ast_for_split_pruning(&QueryAst::MatchNone, false)
+        else {
+            panic!("expected bool query");
+        };
+        assert_eq!(bool_query.should.len(), 2);
+    }
+
+    #[test]
+    fn test_fuse_hits_with_rrf() {
+        let query_hits = vec![hit("split1", 1), hit("split1", 2), hit("split2", 3)];
+        let knn_hits = vec![hit("split2", 3), hit("split1", 4), hit("split1", 1)];
+        let fused_hits = fuse_hits_with_rrf([query_hits, knn_hits], 60);
+
+        let doc_ids: Vec<DocId> = fused_hits.iter().map(hit_doc_id).collect();
+        // 3: 1/63 + 1/61, 1: 1/61 + 1/63, 2: 1/62, 4: 1/62.
+        assert_eq!(doc_ids.len(), 4);
+        assert_eq!(&doc_ids[2..], &[2, 4]);
+
+        let Some(SortValue::F64(score)) = fused_hits[0]
+            .partial_hit
+            .as_ref()
+            .unwrap()
+            .sort_value
+            .unwrap()
+            .sort_value
+        else {
+            panic!("expected f64 sort value");
+        };
+        assert!((score - (1.0 / 61.0 + 1.0 / 63.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_merge_knn_and_query_responses() {
+        let knn_search_response = SearchResponse {
+            num_hits: 3,
+            hits: vec![hit("split1", 4), hit("split1", 1), hit("split1", 5)],
+            ..Default::default()
+        };
+        let query_search_response = SearchResponse {
+            num_hits: 100,
+            hits: vec![hit("split1", 1), hit("split1", 2)],
+            ..Default::default()
+        };
+        let search_request = SearchRequest {
+            max_hits: 2,
+            start_offset: 1,
+            ..Default::default()
+        };
+        let search_response = merge_knn_and_query_responses(
+            knn_search_response.clone(),
+            query_search_response.clone(),
+            true,
+            60,
+            &search_request,
+        );
+        assert_eq!(search_response.num_hits, 102);
+        let doc_ids: Vec<DocId> = search_response.hits.iter().map(hit_doc_id).collect();
+        assert_eq!(doc_ids, [4, 2]);
+
+        let search_response = merge_knn_and_query_responses(
+            knn_search_response,
+            query_search_response,
+            false,
+            60,
+            &search_request,
+        );
+        assert_eq!(search_response.num_hits, 3);
+        let doc_ids: Vec<DocId> = search_response.hits.iter().map(hit_doc_id).collect();
+        assert_eq!(doc_ids, [1, 5]);
+    }
+}
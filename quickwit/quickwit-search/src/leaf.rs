@@ -27,7 +27,8 @@ use quickwit_directories::{CachingDirectory, HotDirectory, StorageDirectory};
 use quickwit_doc_mapper::{Automaton, DocMapper, FastFieldWarmupInfo, TermRange, WarmupInfo};
 use quickwit_proto::search::{
     CountHits, LeafSearchRequest, LeafSearchResponse, PartialHit, ResourceStats, SearchRequest,
-    SortOrder, SortValue, SplitIdAndFooterOffsets, SplitSearchError,
+    SortOrder, SortValue, SplitIdAndFooterOffsets, SplitSearchError, SplitSearchProfile,
+    SplitSearchStats,
 };
 use quickwit_query::query_ast::{
     BoolQuery, CacheNode, QueryAst, QueryAstTransformer, RangeQuery, TermQuery,
@@ -48,6 +49,7 @@ use tokio::task::{JoinError, JoinSet};
 use tracing::*;
 
 use crate::collector::{IncrementalCollector, make_collector_for_split, make_merge_collector};
+use crate::knn::{KnnQuery, leaf_knn_search, load_knn_candidates};
 use crate::metrics::SplitSearchOutcomeCounters;
 use crate::root::is_metadata_count_request_with_ast;
 use crate::search_permit_provider::{SearchPermit, compute_initial_memory_allocation};
@@ -140,6 +142,14 @@ fn configure_storage_retries(
     }
 }
 
+/// Time spent and bytes read fetching the hotcache of a split.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct OpenSplitStats {
+    /// Time spent fetching the split footer and hotcache, from the footer cache or the storage.
+    pub hotcache_fetch_duration: Duration,
+    pub hotcache_num_bytes: u64,
+}
+
 /// Opens a `tantivy::Index` for the given split with several cache layers:
 /// - A split footer cache given by `SearcherContext.split_footer_cache`.
 /// - A fast fields cache given by `SearcherContext.storage_long_term_cache`.
@@ -151,16 +161,21 @@ pub(crate) async fn open_index_with_caches(
     split_and_footer_offsets: &SplitIdAndFooterOffsets,
     tokenizer_manager: Option<&TokenizerManager>,
     ephemeral_unbounded_cache: Option<ByteRangeCache>,
-) -> anyhow::Result<(Index, HotDirectory)> {
+) -> anyhow::Result<(Index, HotDirectory, OpenSplitStats)> {
     let index_storage_with_retry_on_timeout =
         configure_storage_retries(searcher_context, index_storage);
 
+    let hotcache_fetch_start = Instant::now();
     let (hotcache_bytes, bundle_storage) = open_split_bundle(
         searcher_context,
         index_storage_with_retry_on_timeout,
         split_and_footer_offsets,
     )
     .await?;
+    let open_split_stats = OpenSplitStats {
+        hotcache_fetch_duration: hotcache_fetch_start.elapsed(),
+        hotcache_num_bytes: hotcache_bytes.len() as u64,
+    };
 
     let bundle_storage_with_cache = wrap_storage_with_cache(
         searcher_context.fast_fields_cache.clone(),
@@ -185,7 +200,27 @@ pub(crate) async fn open_index_with_caches(
             .tantivy_manager()
             .clone(),
     );
-    Ok((index, hot_directory))
+    Ok((index, hot_directory, open_split_stats))
+}
+
+/// Time spent in the different phases of the warmup of a split.
+///
+/// The phases run concurrently, so their durations do not add up.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct WarmupStats {
+    /// Time spent warming up term dictionaries, for terms, term ranges and automatons.
+    pub term_dict_duration: Duration,
+    pub postings_duration: Duration,
+    pub fast_fields_duration: Duration,
+    pub fieldnorms_duration: Duration,
+}
+
+async fn measure_elapsed(
+    warm_up_future: impl Future<Output = anyhow::Result<()>>,
+) -> anyhow::Result<Duration> {
+    let start = Instant::now();
+    warm_up_future.await?;
+    Ok(start.elapsed())
 }
 
 /// Tantivy search does not make it possible to fetch data asynchronously during
@@ -204,7 +239,10 @@ pub(crate) async fn open_index_with_caches(
 /// This is e.g. required for term aggregation, since we don't know in advance which terms are going
 /// to be hit.
 #[instrument(skip_all)]
-pub(crate) async fn warmup(searcher: &Searcher, warmup_info: &WarmupInfo) -> anyhow::Result<()> {
+pub(crate) async fn warmup(
+    searcher: &Searcher,
+    warmup_info: &WarmupInfo,
+) -> anyhow::Result<WarmupStats> {
     debug!(warmup_info=?warmup_info);
     let warm_up_terms_future = warm_up_terms(searcher, &warmup_info.terms_grouped_by_field)
         .instrument(debug_span!("warm_up_terms"));
@@ -225,17 +263,34 @@ pub(crate) async fn warmup(searcher: &Searcher, warmup_info: &WarmupInfo) -> any
         warm_up_automatons(searcher, &warmup_info.automatons_grouped_by_field)
             .instrument(debug_span!("warm_up_automatons"));
 
-    tokio::try_join!(
-        warm_up_terms_future,
-        warm_up_term_ranges_future,
-        warm_up_fastfields_future,
-        warm_up_term_dict_future,
-        warm_up_fieldnorms_future,
-        warm_up_postings_future,
-        warm_up_automatons_future,
+    let (
+        warm_up_terms_duration,
+        warm_up_term_ranges_duration,
+        fast_fields_duration,
+        warm_up_term_dict_duration,
+        fieldnorms_duration,
+        postings_duration,
+        warm_up_automatons_duration,
+    ) = tokio::try_join!(
+        measure_elapsed(warm_up_terms_future),
+        measure_elapsed(warm_up_term_ranges_future),
+        measure_elapsed(warm_up_fastfields_future),
+        measure_elapsed(warm_up_term_dict_future),
+        measure_elapsed(warm_up_fieldnorms_future),
+        measure_elapsed(warm_up_postings_future),
+        measure_elapsed(warm_up_automatons_future),
     )?;
-
-    Ok(())
+    let term_dict_duration = warm_up_terms_duration
+        .max(warm_up_term_ranges_duration)
+        .max(warm_up_term_dict_duration)
+        .max(warm_up_automatons_duration);
+
+    Ok(WarmupStats {
+        term_dict_duration,
+        postings_duration,
+        fast_fields_duration,
+        fieldnorms_duration,
+    })
 }
 
 async fn warm_up_term_dict_fields(
@@ -382,6 +437,13 @@ async fn warm_up_automatons(
                                 .await
                                 .context("failed to load automaton")
                         }
+                        Automaton::Fuzzy(fuzzy_term_automaton) => inv_idx_clone
+                            .warm_postings_automaton(
+                                fuzzy_term_automaton.to_automaton(),
+                                cpu_intensive_executor,
+                            )
+                            .await
+                            .context("failed to load automaton"),
                     }
                 });
             }
@@ -418,6 +480,8 @@ fn get_leaf_resp_from_count(count: u64) -> LeafSearchResponse {
         num_successful_splits: 1,
         intermediate_aggregation_result: None,
         resource_stats: None,
+        slowest_splits: Vec::new(),
+        split_profiles: Vec::new(),
     }
 }
 
@@ -442,6 +506,7 @@ async fn leaf_search_single_split(
     aggregations_limits: AggregationLimitsGuard,
     search_permit: &mut SearchPermit,
 ) -> crate::Result<Option<LeafSearchResponse>> {
+    let split_search_start = Instant::now();
     let mut leaf_search_state_guard =
         SplitSearchStateGuard::new(ctx.split_outcome_counters.clone());
 
@@ -463,19 +528,32 @@ async fn leaf_search_single_split(
         return Ok(Some(get_leaf_resp_from_count(split.num_docs)));
     }
 
-    if let Some(cached_answer) = ctx
+    if let Some(mut cached_answer) = ctx
         .searcher_context
         .leaf_search_cache
         .get(split.clone(), search_request.clone())
     {
         leaf_search_state_guard.set_state(SplitSearchState::CacheHit);
+        // The timing breakdown of the search that populated the cache is irrelevant here.
+        cached_answer.slowest_splits.clear();
+        cached_answer.split_profiles.clear();
+        if search_request.profile {
+            cached_answer.split_profiles.push(SplitSearchProfile {
+                split_id: split.split_id.clone(),
+                num_docs: split.num_docs,
+                cache_hit: true,
+                elapsed_microsecs: split_search_start.elapsed().as_micros() as u64,
+                ..Default::default()
+            });
+        }
         return Ok(Some(cached_answer));
     }
 
     let split_id = split.split_id.to_string();
     let byte_range_cache =
         ByteRangeCache::with_infinite_capacity(&quickwit_storage::STORAGE_METRICS.shortlived_cache);
-    let (index, hot_directory) = open_index_with_caches(
+    let open_split_start = Instant::now();
+    let (index, hot_directory, open_split_stats) = open_index_with_caches(
         &ctx.searcher_context,
         storage,
         &split,
@@ -483,6 +561,7 @@ async fn leaf_search_single_split(
         Some(byte_range_cache.clone()),
     )
     .await?;
+    let open_split_duration = open_split_start.elapsed();
 
     let index_size = compute_index_size(&hot_directory);
     if index_size < search_permit.memory_allocation() {
@@ -525,7 +604,17 @@ async fn leaf_search_single_split(
 
     let warmup_start = Instant::now();
     leaf_search_state_guard.set_state(SplitSearchState::WarmUp);
-    warmup(&searcher, &warmup_info).await?;
+    let warmup_stats = warmup(&searcher, &warmup_info).await?;
+    let knn_query_opt = search_request
+        .knn
+        .as_deref()
+        .map(KnnQuery::parse)
+        .transpose()?;
+    let knn_candidates_opt = if let Some(knn_query) = &knn_query_opt {
+        load_knn_candidates(&index, &hot_directory, knn_query).await?
+    } else {
+        None
+    };
     let warmup_end = Instant::now();
     let warmup_duration: Duration = warmup_end.duration_since(warmup_start);
     let warmup_size = ByteSize(byte_range_cache.get_num_bytes());
@@ -543,6 +632,7 @@ async fn leaf_search_single_split(
     search_permit.free_warmup_slot();
 
     let split_num_docs = split.num_docs;
+    let profile = search_request.profile;
 
     let span = info_span!("tantivy_search");
 
@@ -566,23 +656,73 @@ async fn leaf_search_single_split(
                     return Ok(None);
                 };
                 collector.update_search_param(&simplified_search_request);
-                let mut leaf_search_response: LeafSearchResponse =
-                    if is_metadata_count_request_with_ast(&query_ast, &simplified_search_request) {
-                        get_leaf_resp_from_count(searcher.num_docs())
-                    } else if collector.is_count_only() {
-                        let count = query.count(&searcher)? as u64;
-                        get_leaf_resp_from_count(count)
-                    } else {
-                        searcher.search(&query, &collector)?
-                    };
-                leaf_search_response.resource_stats = Some(ResourceStats {
+                let mut leaf_search_response: LeafSearchResponse = if let Some(knn_query) =
+                    &knn_query_opt
+                {
+                    leaf_knn_search(
+                        &searcher,
+                        query.as_ref(),
+                        knn_query,
+                        knn_candidates_opt.as_ref(),
+                        &split_clone.split_id,
+                    )?
+                } else if is_metadata_count_request_with_ast(&query_ast, &simplified_search_request)
+                {
+                    get_leaf_resp_from_count(searcher.num_docs())
+                } else if collector.is_count_only() {
+                    let count = query.count(&searcher)? as u64;
+                    get_leaf_resp_from_count(count)
+                } else {
+                    searcher.search(&query, &collector)?
+                };
+                let resource_stats = ResourceStats {
                     cpu_microsecs: cpu_start.elapsed().as_micros() as u64,
                     short_lived_cache_num_bytes: warmup_size.as_u64(),
                     split_num_docs,
                     warmup_microsecs: warmup_duration.as_micros() as u64,
                     cpu_thread_pool_wait_microsecs: cpu_thread_pool_wait_microsecs.as_micros()
                         as u64,
-                });
+                };
+                leaf_search_response.slowest_splits = vec![SplitSearchStats {
+                    split_id: split_clone.split_id.clone(),
+                    leaf_node_id: String::new(),
+                    num_docs: split_num_docs,
+                    warmup_num_bytes: resource_stats.short_lived_cache_num_bytes,
+                    warmup_microsecs: resource_stats.warmup_microsecs,
+                    cpu_thread_pool_wait_microsecs: resource_stats.cpu_thread_pool_wait_microsecs,
+                    cpu_microsecs: resource_stats.cpu_microsecs,
+                    elapsed_microsecs: split_search_start.elapsed().as_micros() as u64,
+                }];
+                if profile {
+                    leaf_search_response.split_profiles = vec![SplitSearchProfile {
+                        split_id: split_clone.split_id.clone(),
+                        leaf_node_id: String::new(),
+                        num_docs: split_num_docs,
+                        cache_hit: false,
+                        open_split_microsecs: open_split_duration.as_micros() as u64,
+                        hotcache_fetch_microsecs: open_split_stats
+                            .hotcache_fetch_duration
+                            .as_micros() as u64,
+                        hotcache_num_bytes: open_split_stats.hotcache_num_bytes,
+                        warmup_term_dict_microsecs: warmup_stats.term_dict_duration.as_micros()
+                            as u64,
+                        warmup_postings_microsecs: warmup_stats.postings_duration.as_micros()
+                            as u64,
+                        warmup_fast_fields_microsecs: warmup_stats.fast_fields_duration.as_micros()
+                            as u64,
+                        warmup_fieldnorms_microsecs: warmup_stats.fieldnorms_duration.as_micros()
+                            as u64,
+                        warmup_microsecs: resource_stats.warmup_microsecs,
+                        warmup_num_bytes: resource_stats.short_lived_cache_num_bytes,
+                        cpu_thread_pool_wait_microsecs: resource_stats
+                            .cpu_thread_pool_wait_microsecs,
+                        collection_microsecs: resource_stats.cpu_microsecs,
+                        fetch_docs_microsecs: 0,
+                        num_fetched_docs: 0,
+                        elapsed_microsecs: split_search_start.elapsed().as_micros() as u64,
+                    }];
+                }
+                leaf_search_response.resource_stats = Some(resource_stats);
                 leaf_search_state_guard.set_state(SplitSearchState::Success);
                 Result::<_, TantivyError>::Ok(Some((
                     simplified_search_request,
@@ -300,6 +300,8 @@ mod tests {
                 split_id: "split_1".to_string(),
             }],
             resource_stats: None,
+            slowest_splits: Vec::new(),
+            split_profiles: Vec::new(),
         };
 
         assert!(cache.get(split_1.clone(), query_1.clone()).is_none());
@@ -391,6 +393,8 @@ mod tests {
                 split_id: "split_1".to_string(),
             }],
             resource_stats: Some(ResourceStats::default()),
+            slowest_splits: Vec::new(),
+            split_profiles: Vec::new(),
         };
 
         // for split_1, 1 and 1bis cover different timestamp ranges
@@ -17,25 +17,33 @@
 #![allow(clippy::bool_assert_comparison)]
 #![deny(clippy::disallowed_methods)]
 
+mod async_search;
 mod client;
 mod cluster_client;
 mod collector;
+mod composite_collector;
 mod error;
+mod export;
 mod fetch_docs;
 mod find_trace_ids_collector;
+mod knn;
 mod leaf;
 mod leaf_cache;
 mod list_fields;
 mod list_fields_cache;
 mod list_terms;
 mod metrics_trackers;
+mod pipeline_aggregations;
 mod retry;
 mod root;
+mod runtime_fields;
 mod scroll_context;
 mod search_job_placer;
 mod search_response_rest;
 mod service;
+mod slow_query_log;
 pub(crate) mod top_k_collector;
+mod two_phase_terms;
 
 mod metrics;
 mod search_permit_provider;
@@ -59,8 +67,9 @@ pub type Result<T> = std::result::Result<T, SearchError>;
 use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::{Arc, OnceLock};
 
+pub use composite_collector::CompositeCollector;
 pub use find_trace_ids_collector::{FindTraceIdsCollector, Span};
-use quickwit_config::SearcherConfig;
+use quickwit_config::{SearcherConfig, SlowQueryLogConfig};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_metastore::{
     IndexMetadata, ListIndexesMetadataResponseExt, ListSplitsQuery, ListSplitsRequestExt,
@@ -68,18 +77,25 @@ use quickwit_metastore::{
 };
 use quickwit_proto::search::{
     PartialHit, ResourceStats, SearchRequest, SearchResponse, SplitIdAndFooterOffsets,
+    SplitSearchStats,
 };
 use quickwit_proto::types::IndexUid;
 use quickwit_storage::StorageResolver;
 pub use service::SearcherContext;
 use tantivy::DocAddress;
 
+pub use crate::async_search::{
+    AsyncSearchRequest, AsyncSearchResponse, GetAsyncSearchRequest, delete_async_search,
+    get_async_search, submit_async_search,
+};
 pub use crate::client::{
     SearchServiceClient, create_search_client_from_channel, create_search_client_from_grpc_addr,
 };
 pub use crate::cluster_client::ClusterClient;
 pub use crate::error::{SearchError, parse_grpc_error};
+pub use crate::export::{HitStream, root_export};
 use crate::fetch_docs::fetch_docs;
+pub use crate::knn::KnnQuery;
 pub use crate::root::{
     IndexMetasForLeafSearch, SearchJob, check_all_index_metadata_found, jobs_to_leaf_request,
     root_search, search_plan,
@@ -89,6 +105,10 @@ pub use crate::search_response_rest::{
     AggregationResults, SearchPlanResponseRest, SearchResponseRest,
 };
 pub use crate::service::{MockSearchService, SearchService, SearchServiceImpl};
+pub use crate::slow_query_log::{
+    SLOW_QUERY_LOG_INDEX_ID, SLOW_QUERY_LOG_TARGET, SlowQueryLogRecord, SlowQueryLogger,
+    slow_query_log_index_config,
+};
 
 /// A pool of searcher clients identified by their gRPC socket address.
 pub type SearcherPool = Pool<SocketAddr, SearchServiceClient>;
@@ -367,6 +387,25 @@ fn merge_resource_stats(
         }
     }
 }
+
+/// Maximum number of per-split timing breakdowns kept in a `LeafSearchResponse`.
+pub(crate) const MAX_NUM_SLOWEST_SPLITS: usize = SlowQueryLogConfig::MAX_NUM_SLOWEST_SPLITS;
+
+/// Merges `new_slowest_splits` into `slowest_splits_acc`, keeping only the
+/// [`MAX_NUM_SLOWEST_SPLITS`] slowest splits, sorted by decreasing elapsed time.
+pub(crate) fn merge_slowest_splits(
+    new_slowest_splits: Vec<SplitSearchStats>,
+    slowest_splits_acc: &mut Vec<SplitSearchStats>,
+) {
+    if new_slowest_splits.is_empty() {
+        return;
+    }
+    slowest_splits_acc.extend(new_slowest_splits);
+    slowest_splits_acc
+        .sort_unstable_by_key(|split_stats| std::cmp::Reverse(split_stats.elapsed_microsecs));
+    slowest_splits_acc.truncate(MAX_NUM_SLOWEST_SPLITS);
+}
+
 #[cfg(test)]
 mod stats_merge_tests {
     use super::*;
@@ -462,4 +501,34 @@ mod stats_merge_tests {
             })
         );
     }
+
+    #[test]
+    fn test_merge_slowest_splits() {
+        let split_stats = |split_id: &str, elapsed_microsecs: u64| SplitSearchStats {
+            split_id: split_id.to_string(),
+            elapsed_microsecs,
+            ..Default::default()
+        };
+        let mut slowest_splits = Vec::new();
+        merge_slowest_splits(Vec::new(), &mut slowest_splits);
+        assert!(slowest_splits.is_empty());
+
+        merge_slowest_splits(
+            vec![split_stats("split-1", 10), split_stats("split-2", 30)],
+            &mut slowest_splits,
+        );
+        merge_slowest_splits(vec![split_stats("split-3", 20)], &mut slowest_splits);
+        let split_ids: Vec<&str> = slowest_splits
+            .iter()
+            .map(|split_stats| split_stats.split_id.as_str())
+            .collect();
+        assert_eq!(split_ids, ["split-2", "split-3", "split-1"]);
+
+        let many_splits = (0..2 * MAX_NUM_SLOWEST_SPLITS as u64)
+            .map(|elapsed_microsecs| split_stats("split", elapsed_microsecs))
+            .collect();
+        merge_slowest_splits(many_splits, &mut slowest_splits);
+        assert_eq!(slowest_splits.len(), MAX_NUM_SLOWEST_SPLITS);
+        assert_eq!(slowest_splits[0].split_id, "split-2");
+    }
 }
@@ -0,0 +1,1830 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pipeline aggregations compute new values from the output of other aggregations. They are
+//! removed from the aggregation request before it is sent to the leaves, and computed by the root
+//! searcher on the final aggregation results.
+//!
+//! Sibling pipeline aggregations (`max_bucket`, `avg_bucket`) add a value next to the
+//! multi-bucket aggregation they point to. Parent pipeline aggregations (`derivative`,
+//! `cumulative_sum`, `moving_fn`, `moving_avg`, `bucket_script`, `bucket_selector`,
+//! `bucket_sort`) are declared in the sub-aggregations of a multi-bucket aggregation and add a
+//! value to, filter, or reorder its buckets.
+
+mod script;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+
+use quickwit_proto::search::{SearchRequest, SearchResponse};
+use quickwit_query::aggregations::{
+    AggregationResult, AggregationResults, BucketEntries, BucketEntry, BucketResult,
+    CompositeBucketEntry, CompositeKeyValue, Key, MetricResult, PipelineMetricResult,
+    RangeBucketEntry, SignificantTermsBucketEntry,
+};
+use serde::Deserialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tantivy::aggregation::agg_result::MetricResult as TantivyMetricResult;
+
+use self::script::{Expr, MovingFunction, Script};
+use crate::error::SearchError;
+
+const PIPELINE_AGGREGATION_TYPES: [&str; 9] = [
+    "derivative",
+    "cumulative_sum",
+    "moving_fn",
+    "moving_avg",
+    "bucket_script",
+    "bucket_selector",
+    "bucket_sort",
+    "max_bucket",
+    "avg_bucket",
+];
+
+const DEFAULT_MOVING_AVG_WINDOW: usize = 5;
+
+fn default_moving_avg_window() -> usize {
+    DEFAULT_MOVING_AVG_WINDOW
+}
+
+/// How buckets with no value for a `buckets_path` are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum GapPolicy {
+    /// The bucket is ignored. A bucket with no documents counts as a gap.
+    #[default]
+    Skip,
+    /// The missing value is replaced with 0.
+    InsertZeros,
+    /// Like `skip`, but the value of a bucket with no documents is used if it exists.
+    KeepValues,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PipelineAggregationRequest {
+    Derivative(DerivativeRequest),
+    CumulativeSum(CumulativeSumRequest),
+    MovingFn(MovingFnRequest),
+    MovingAvg(MovingAvgRequest),
+    BucketScript(BucketScriptRequest),
+    BucketSelector(BucketScriptRequest),
+    BucketSort(BucketSortRequest),
+    MaxBucket(BucketMetricRequest),
+    AvgBucket(BucketMetricRequest),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DerivativeRequest {
+    buckets_path: String,
+    #[serde(default)]
+    gap_policy: GapPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CumulativeSumRequest {
+    buckets_path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MovingFnRequest {
+    buckets_path: String,
+    window: usize,
+    script: Script,
+    #[serde(default)]
+    shift: i64,
+    #[serde(default)]
+    gap_policy: GapPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MovingAvgRequest {
+    buckets_path: String,
+    #[serde(default = "default_moving_avg_window")]
+    window: usize,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    gap_policy: GapPolicy,
+}
+
+/// Request of the `bucket_script` and `bucket_selector` aggregations.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BucketScriptRequest {
+    buckets_path: BTreeMap<String, String>,
+    script: Script,
+    #[serde(default)]
+    gap_policy: GapPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BucketSortRequest {
+    #[serde(default)]
+    sort: Vec<SortFieldRequest>,
+    #[serde(default)]
+    from: usize,
+    #[serde(default)]
+    size: Option<usize>,
+    #[serde(default)]
+    gap_policy: GapPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum SortFieldRequest {
+    Field(String),
+    FieldWithOrder(BTreeMap<String, SortOrderRequest>),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum SortOrderRequest {
+    Order(SortOrder),
+    Object(SortOrderObject),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SortOrderObject {
+    #[serde(default)]
+    order: SortOrder,
+}
+
+/// Request of the `max_bucket` and `avg_bucket` aggregations.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BucketMetricRequest {
+    buckets_path: String,
+    #[serde(default)]
+    gap_policy: GapPolicy,
+}
+
+/// Path to a value within a bucket.
+#[derive(Debug, Clone, PartialEq)]
+enum BucketsPath {
+    /// `_count`, the number of documents in the bucket.
+    Count,
+    /// `_key`, the key of the bucket, if numeric.
+    Key,
+    /// `<agg_name>`, `<agg_name>.<metric_key>` or `<agg_name>[<metric_key>]`, a metric
+    /// sub-aggregation of the bucket.
+    Metric {
+        agg_name: String,
+        metric_key: Option<String>,
+    },
+}
+
+impl BucketsPath {
+    fn parse(path: &str) -> Result<BucketsPath, String> {
+        let buckets_path = match path {
+            "_count" => BucketsPath::Count,
+            "_key" => BucketsPath::Key,
+            _ if path.contains('>') => {
+                return Err(format!(
+                    "buckets_path `{path}` must point to a metric of the bucket"
+                ));
+            }
+            _ => {
+                let (agg_name, metric_key) =
+                    if let Some((agg_name, metric_key)) = path.split_once('[') {
+                        let metric_key = metric_key
+                            .strip_suffix(']')
+                            .ok_or_else(|| format!("invalid buckets_path `{path}`"))?;
+                        (agg_name, Some(metric_key))
+                    } else if let Some((agg_name, metric_key)) = path.split_once('.') {
+                        (agg_name, Some(metric_key))
+                    } else {
+                        (path, None)
+                    };
+                if agg_name.is_empty() {
+                    return Err(format!("invalid buckets_path `{path}`"));
+                }
+                BucketsPath::Metric {
+                    agg_name: agg_name.to_string(),
+                    metric_key: metric_key.map(ToString::to_string),
+                }
+            }
+        };
+        Ok(buckets_path)
+    }
+
+    fn agg_name(&self) -> Option<&str> {
+        match self {
+            BucketsPath::Count | BucketsPath::Key => None,
+            BucketsPath::Metric { agg_name, .. } => Some(agg_name.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BucketMetric {
+    Max,
+    Avg,
+}
+
+/// A sibling pipeline aggregation, computing a value from the buckets of the multi-bucket
+/// aggregation `agg_name`.
+#[derive(Debug, Clone, PartialEq)]
+struct SiblingPipeline {
+    bucket_metric: BucketMetric,
+    agg_name: String,
+    buckets_path: BucketsPath,
+    gap_policy: GapPolicy,
+}
+
+/// A parent pipeline aggregation, computed over the buckets of its parent aggregation.
+#[derive(Debug, Clone, PartialEq)]
+enum ParentPipeline {
+    Derivative {
+        buckets_path: BucketsPath,
+        gap_policy: GapPolicy,
+    },
+    CumulativeSum {
+        buckets_path: BucketsPath,
+    },
+    MovingFn {
+        buckets_path: BucketsPath,
+        window: usize,
+        shift: i64,
+        moving_function: MovingFunction,
+        gap_policy: GapPolicy,
+    },
+    MovingAvg {
+        buckets_path: BucketsPath,
+        window: usize,
+        gap_policy: GapPolicy,
+    },
+    BucketScript {
+        buckets_paths: Vec<(String, BucketsPath)>,
+        expr: Expr,
+        gap_policy: GapPolicy,
+    },
+    BucketSelector {
+        buckets_paths: Vec<(String, BucketsPath)>,
+        expr: Expr,
+        gap_policy: GapPolicy,
+    },
+    BucketSort {
+        sort: Vec<(BucketsPath, SortOrder)>,
+        from: usize,
+        size: Option<usize>,
+        gap_policy: GapPolicy,
+    },
+}
+
+impl ParentPipeline {
+    fn buckets_paths(&self) -> Vec<&BucketsPath> {
+        match self {
+            ParentPipeline::Derivative { buckets_path, .. }
+            | ParentPipeline::CumulativeSum { buckets_path }
+            | ParentPipeline::MovingFn { buckets_path, .. }
+            | ParentPipeline::MovingAvg { buckets_path, .. } => vec![buckets_path],
+            ParentPipeline::BucketScript { buckets_paths, .. }
+            | ParentPipeline::BucketSelector { buckets_paths, .. } => buckets_paths
+                .iter()
+                .map(|(_, buckets_path)| buckets_path)
+                .collect(),
+            ParentPipeline::BucketSort { sort, .. } => {
+                sort.iter().map(|(buckets_path, _)| buckets_path).collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Pipeline {
+    Sibling(SiblingPipeline),
+    Parent(ParentPipeline),
+}
+
+impl Pipeline {
+    /// Returns the names of the aggregations, declared at the same level as the pipeline
+    /// aggregation, that it reads values from.
+    fn referenced_aggregations(&self) -> Vec<&str> {
+        match self {
+            Pipeline::Sibling(sibling_pipeline) => vec![sibling_pipeline.agg_name.as_str()],
+            Pipeline::Parent(parent_pipeline) => parent_pipeline
+                .buckets_paths()
+                .into_iter()
+                .filter_map(BucketsPath::agg_name)
+                .collect(),
+        }
+    }
+}
+
+fn parse_script_expr(script: &Script) -> Result<Expr, String> {
+    Expr::parse(script.source()?)
+}
+
+fn parse_script_buckets_paths(
+    buckets_path: BTreeMap<String, String>,
+    expr: &Expr,
+) -> Result<Vec<(String, BucketsPath)>, String> {
+    for param in expr.params() {
+        if !buckets_path.contains_key(param) {
+            return Err(format!(
+                "script param `{param}` is not defined in the buckets_path"
+            ));
+        }
+    }
+    buckets_path
+        .into_iter()
+        .map(|(param, path)| Ok((param, BucketsPath::parse(&path)?)))
+        .collect()
+}
+
+impl PipelineAggregationRequest {
+    fn compile(self) -> Result<Pipeline, String> {
+        let parent_pipeline = match self {
+            PipelineAggregationRequest::Derivative(request) => ParentPipeline::Derivative {
+                buckets_path: BucketsPath::parse(&request.buckets_path)?,
+                gap_policy: request.gap_policy,
+            },
+            PipelineAggregationRequest::CumulativeSum(request) => ParentPipeline::CumulativeSum {
+                buckets_path: BucketsPath::parse(&request.buckets_path)?,
+            },
+            PipelineAggregationRequest::MovingFn(request) => {
+                if request.window == 0 {
+                    return Err("window must be greater than 0".to_string());
+                }
+                ParentPipeline::MovingFn {
+                    buckets_path: BucketsPath::parse(&request.buckets_path)?,
+                    window: request.window,
+                    shift: request.shift,
+                    moving_function: MovingFunction::parse(request.script.source()?)?,
+                    gap_policy: request.gap_policy,
+                }
+            }
+            PipelineAggregationRequest::MovingAvg(request) => {
+                if request.window == 0 {
+                    return Err("window must be greater than 0".to_string());
+                }
+                if let Some(model) = &request.model
+                    && model != "simple"
+                {
+                    return Err(format!(
+                        "unsupported moving_avg model `{model}`, only `simple` is supported"
+                    ));
+                }
+                ParentPipeline::MovingAvg {
+                    buckets_path: BucketsPath::parse(&request.buckets_path)?,
+                    window: request.window,
+                    gap_policy: request.gap_policy,
+                }
+            }
+            PipelineAggregationRequest::BucketScript(request) => {
+                let expr = parse_script_expr(&request.script)?;
+                ParentPipeline::BucketScript {
+                    buckets_paths: parse_script_buckets_paths(request.buckets_path, &expr)?,
+                    expr,
+                    gap_policy: request.gap_policy,
+                }
+            }
+            PipelineAggregationRequest::BucketSelector(request) => {
+                let expr = parse_script_expr(&request.script)?;
+                ParentPipeline::BucketSelector {
+                    buckets_paths: parse_script_buckets_paths(request.buckets_path, &expr)?,
+                    expr,
+                    gap_policy: request.gap_policy,
+                }
+            }
+            PipelineAggregationRequest::BucketSort(request) => {
+                let mut sort = Vec::with_capacity(request.sort.len());
+
+                for sort_field in request.sort {
+                    let (path, sort_order) = match sort_field {
+                        SortFieldRequest::Field(path) => (path, SortOrder::default()),
+                        SortFieldRequest::FieldWithOrder(field_with_order) => {
+                            let Some((path, sort_order_request)) =
+                                field_with_order.into_iter().next()
+                            else {
+                                return Err("sort field must not be empty".to_string());
+                            };
+                            let sort_order = match sort_order_request {
+                                SortOrderRequest::Order(sort_order) => sort_order,
+                                SortOrderRequest::Object(SortOrderObject { order }) => order,
+                            };
+                            (path, sort_order)
+                        }
+                    };
+                    sort.push((BucketsPath::parse(&path)?, sort_order));
+                }
+                ParentPipeline::BucketSort {
+                    sort,
+                    from: request.from,
+                    size: request.size,
+                    gap_policy: request.gap_policy,
+                }
+            }
+            PipelineAggregationRequest::MaxBucket(request) => {
+                return parse_sibling_pipeline(BucketMetric::Max, request);
+            }
+            PipelineAggregationRequest::AvgBucket(request) => {
+                return parse_sibling_pipeline(BucketMetric::Avg, request);
+            }
+        };
+        Ok(Pipeline::Parent(parent_pipeline))
+    }
+}
+
+fn parse_sibling_pipeline(
+    bucket_metric: BucketMetric,
+    request: BucketMetricRequest,
+) -> Result<Pipeline, String> {
+    let Some((agg_name, path)) = request.buckets_path.split_once('>') else {
+        return Err(format!(
+            "buckets_path `{}` must be of the form `<multi_bucket_aggregation>><metric>`",
+            request.buckets_path
+        ));
+    };
+    let sibling_pipeline = SiblingPipeline {
+        bucket_metric,
+        agg_name: agg_name.to_string(),
+        buckets_path: BucketsPath::parse(path)?,
+        gap_policy: request.gap_policy,
+    };
+    Ok(Pipeline::Sibling(sibling_pipeline))
+}
+
+/// The pipeline aggregations declared in one aggregation map of the request.
+#[derive(Debug, Default)]
+struct PipelineLevel {
+    /// Pipeline aggregations of this level, in dependency order.
+    pipelines: Vec<(String, Pipeline)>,
+    /// Bucket aggregations of this level whose sub-aggregations declare pipeline aggregations.
+    children: Vec<(String, PipelineLevel)>,
+}
+
+impl PipelineLevel {
+    fn is_empty(&self) -> bool {
+        self.pipelines.is_empty() && self.children.is_empty()
+    }
+}
+
+fn is_pipeline_aggregation(aggregation_json: &JsonValue) -> bool {
+    let Some(aggregation_obj) = aggregation_json.as_object() else {
+        return false;
+    };
+    aggregation_obj
+        .keys()
+        .any(|key| PIPELINE_AGGREGATION_TYPES.contains(&key.as_str()))
+}
+
+/// Removes the pipeline aggregations from an aggregation map and its sub-aggregations.
+fn extract_pipeline_level(
+    aggregations_json: &mut JsonMap<String, JsonValue>,
+    is_root: bool,
+) -> crate::Result<PipelineLevel> {
+    let pipeline_names: Vec<String> = aggregations_json
+        .iter()
+        .filter(|(_, aggregation_json)| is_pipeline_aggregation(aggregation_json))
+        .map(|(name, _)| name.clone())
+        .collect();
+    let mut pipelines = Vec::with_capacity(pipeline_names.len());
+
+    for name in pipeline_names {
+        let pipeline_json = aggregations_json
+            .remove(&name)
+            .expect("the pipeline aggregation should be present");
+        let pipeline = serde_json::from_value::<PipelineAggregationRequest>(pipeline_json)
+            .map_err(|error| error.to_string())
+            .and_then(PipelineAggregationRequest::compile)
+            .map_err(|error| {
+                SearchError::InvalidAggregationRequest(format!(
+                    "invalid pipeline aggregation `{name}`: {error}"
+                ))
+            })?;
+        if is_root && matches!(pipeline, Pipeline::Parent(_)) {
+            return Err(SearchError::InvalidAggregationRequest(format!(
+                "pipeline aggregation `{name}` must be declared in the sub-aggregations of a \
+                 multi-bucket aggregation"
+            )));
+        }
+        pipelines.push((name, pipeline));
+    }
+    let mut children = Vec::new();
+
+    for (name, aggregation_json) in aggregations_json.iter_mut() {
+        let Some(aggregation_obj) = aggregation_json.as_object_mut() else {
+            continue;
+        };
+        for sub_aggregations_key in ["aggs", "aggregations"] {
+            let Some(JsonValue::Object(sub_aggregations_json)) =
+                aggregation_obj.get_mut(sub_aggregations_key)
+            else {
+                continue;
+            };
+            let child_level = extract_pipeline_level(sub_aggregations_json, false)?;
+
+            if child_level.is_empty() {
+                continue;
+            }
+            if sub_aggregations_json.is_empty() {
+                aggregation_obj.remove(sub_aggregations_key);
+            }
+            children.push((name.clone(), child_level));
+        }
+    }
+    let aggregation_names: HashSet<&str> = aggregations_json
+        .keys()
+        .chain(pipelines.iter().map(|(name, _)| name))
+        .map(String::as_str)
+        .collect();
+
+    for (name, pipeline) in &pipelines {
+        for referenced_aggregation in pipeline.referenced_aggregations() {
+            if !aggregation_names.contains(referenced_aggregation) {
+                return Err(SearchError::InvalidAggregationRequest(format!(
+                    "buckets_path of pipeline aggregation `{name}` references unknown aggregation \
+                     `{referenced_aggregation}`"
+                )));
+            }
+        }
+    }
+    Ok(PipelineLevel {
+        pipelines: sort_pipelines(pipelines)?,
+        children,
+    })
+}
+
+/// Sorts the pipeline aggregations of a level so that each one comes after the pipeline
+/// aggregations it reads values from. `bucket_sort` aggregations come last when possible, so
+/// that truncating the buckets does not depend on the order of the request.
+fn sort_pipelines(
+    mut pipelines: Vec<(String, Pipeline)>,
+) -> crate::Result<Vec<(String, Pipeline)>> {
+    let mut sorted_pipelines = Vec::with_capacity(pipelines.len());
+
+    while !pipelines.is_empty() {
+        let pending_names: HashSet<&str> =
+            pipelines.iter().map(|(name, _)| name.as_str()).collect();
+        let is_ready = |pipeline: &Pipeline| {
+            pipeline
+                .referenced_aggregations()
+                .into_iter()
+                .all(|referenced_aggregation| !pending_names.contains(referenced_aggregation))
+        };
+        let is_bucket_sort = |pipeline: &Pipeline| {
+            matches!(
+                pipeline,
+                Pipeline::Parent(ParentPipeline::BucketSort { .. })
+            )
+        };
+        let pos_opt = pipelines
+            .iter()
+            .position(|(_, pipeline)| is_ready(pipeline) && !is_bucket_sort(pipeline))
+            .or_else(|| {
+                pipelines
+                    .iter()
+                    .position(|(_, pipeline)| is_ready(pipeline))
+            });
+        let Some(pos) = pos_opt else {
+            let names = pipelines
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>();
+            return Err(SearchError::InvalidAggregationRequest(format!(
+                "pipeline aggregations `{}` reference each other",
+                names.join("`, `")
+            )));
+        };
+        sorted_pipelines.push(pipelines.remove(pos));
+    }
+    Ok(sorted_pipelines)
+}
+
+/// The pipeline aggregations of a search request.
+#[derive(Debug)]
+pub(crate) struct PipelineAggregations {
+    root_level: PipelineLevel,
+}
+
+impl PipelineAggregations {
+    /// Removes the pipeline aggregations from the aggregation request of a search request, so
+    /// that the leaves only see the aggregations they can execute. Returns `None` if the request
+    /// has no pipeline aggregations, in which case it is left untouched.
+    pub(crate) fn extract_from_request(
+        search_request: &mut SearchRequest,
+    ) -> crate::Result<Option<PipelineAggregations>> {
+        let Some(aggregation_request) = &search_request.aggregation_request else {
+            return Ok(None);
+        };
+        // Invalid aggregation requests are reported by the request validation.
+        let Ok(JsonValue::Object(mut aggregations_json)) =
+            serde_json::from_str::<JsonValue>(aggregation_request)
+        else {
+            return Ok(None);
+        };
+        let root_level = extract_pipeline_level(&mut aggregations_json, true)?;
+
+        if root_level.is_empty() {
+            return Ok(None);
+        }
+        search_request.aggregation_request = Some(serde_json::to_string(&aggregations_json)?);
+        Ok(Some(PipelineAggregations { root_level }))
+    }
+
+    /// Computes the pipeline aggregations on the final aggregation results of a search response.
+    pub(crate) fn apply(&self, search_response: &mut SearchResponse) -> crate::Result<()> {
+        let Some(aggregation_postcard) = &mut search_response.aggregation_postcard else {
+            return Ok(());
+        };
+        let mut aggregation_results: AggregationResults =
+            postcard::from_bytes(aggregation_postcard)?;
+        self.apply_to_aggregation_results(&mut aggregation_results)?;
+        *aggregation_postcard = postcard::to_stdvec(&aggregation_results)?;
+        Ok(())
+    }
+
+    fn apply_to_aggregation_results(
+        &self,
+        aggregation_results: &mut AggregationResults,
+    ) -> crate::Result<()> {
+        apply_to_children(&self.root_level, aggregation_results)?;
+
+        for (name, pipeline) in &self.root_level.pipelines {
+            if let Pipeline::Sibling(sibling_pipeline) = pipeline {
+                apply_sibling_pipeline(name, sibling_pipeline, aggregation_results)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The key of a bucket, used by `_key` buckets paths and by `max_bucket`.
+#[derive(Debug, Clone, PartialEq)]
+enum BucketKey {
+    Number(f64),
+    Str(String),
+}
+
+impl BucketKey {
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            BucketKey::Number(number) => Some(*number),
+            BucketKey::Str(_) => None,
+        }
+    }
+
+    fn compare(&self, other: &BucketKey) -> Ordering {
+        match (self, other) {
+            (BucketKey::Number(left), BucketKey::Number(right)) => left.total_cmp(right),
+            (BucketKey::Number(_), BucketKey::Str(_)) => Ordering::Less,
+            (BucketKey::Str(_), BucketKey::Number(_)) => Ordering::Greater,
+            (BucketKey::Str(left), BucketKey::Str(right)) => left.cmp(right),
+        }
+    }
+}
+
+impl From<&Key> for BucketKey {
+    fn from(key: &Key) -> BucketKey {
+        match key {
+            Key::Str(key) => BucketKey::Str(key.clone()),
+            Key::I64(key) => BucketKey::Number(*key as f64),
+            Key::U64(key) => BucketKey::Number(*key as f64),
+            Key::F64(key) => BucketKey::Number(*key),
+        }
+    }
+}
+
+impl fmt::Display for BucketKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BucketKey::Number(number) => write!(f, "{number}"),
+            BucketKey::Str(key) => write!(f, "{key}"),
+        }
+    }
+}
+
+/// Common interface of the bucket entries of the different multi-bucket aggregations.
+trait PipelineBucket {
+    fn doc_count(&self) -> u64;
+
+    fn key(&self) -> BucketKey;
+
+    /// The key as reported by `max_bucket`.
+    fn key_as_string(&self) -> String {
+        self.key().to_string()
+    }
+
+    fn sub_aggregation(&self) -> &AggregationResults;
+
+    fn sub_aggregation_mut(&mut self) -> &mut AggregationResults;
+}
+
+impl PipelineBucket for BucketEntry {
+    fn doc_count(&self) -> u64 {
+        self.doc_count
+    }
+
+    fn key(&self) -> BucketKey {
+        BucketKey::from(&self.key)
+    }
+
+    fn key_as_string(&self) -> String {
+        self.key_as_string
+            .clone()
+            .unwrap_or_else(|| self.key().to_string())
+    }
+
+    fn sub_aggregation(&self) -> &AggregationResults {
+        &self.sub_aggregation
+    }
+
+    fn sub_aggregation_mut(&mut self) -> &mut AggregationResults {
+        &mut self.sub_aggregation
+    }
+}
+
+impl PipelineBucket for RangeBucketEntry {
+    fn doc_count(&self) -> u64 {
+        self.doc_count
+    }
+
+    fn key(&self) -> BucketKey {
+        BucketKey::from(&self.key)
+    }
+
+    fn sub_aggregation(&self) -> &AggregationResults {
+        &self.sub_aggregation
+    }
+
+    fn sub_aggregation_mut(&mut self) -> &mut AggregationResults {
+        &mut self.sub_aggregation
+    }
+}
+
+impl PipelineBucket for CompositeBucketEntry {
+    fn doc_count(&self) -> u64 {
+        self.doc_count
+    }
+
+    /// Formats the key like Elasticsearch does, e.g. `{host=web-1, day=1700000000000}`.
+    fn key(&self) -> BucketKey {
+        let values = self
+            .key
+            .iter()
+            .map(|(source_name, value)| {
+                let value_str = match value {
+                    CompositeKeyValue::Null => "null".to_string(),
+                    CompositeKeyValue::Bool(value) => value.to_string(),
+                    CompositeKeyValue::I64(value) => value.to_string(),
+                    CompositeKeyValue::U64(value) => value.to_string(),
+                    CompositeKeyValue::F64(value) => value.to_string(),
+                    CompositeKeyValue::Str(value) => value.clone(),
+                };
+                format!("{source_name}={value_str}")
+            })
+            .collect::<Vec<_>>();
+        BucketKey::Str(format!("{{{}}}", values.join(", ")))
+    }
+
+    fn sub_aggregation(&self) -> &AggregationResults {
+        &self.sub_aggregation
+    }
+
+    fn sub_aggregation_mut(&mut self) -> &mut AggregationResults {
+        &mut self.sub_aggregation
+    }
+}
+
+impl PipelineBucket for SignificantTermsBucketEntry {
+    fn doc_count(&self) -> u64 {
+        self.doc_count
+    }
+
+    fn key(&self) -> BucketKey {
+        BucketKey::from(&self.key)
+    }
+
+    fn sub_aggregation(&self) -> &AggregationResults {
+        &self.sub_aggregation
+    }
+
+    fn sub_aggregation_mut(&mut self) -> &mut AggregationResults {
+        &mut self.sub_aggregation
+    }
+}
+
+// Keyed buckets.
+impl<B: PipelineBucket> PipelineBucket for (String, B) {
+    fn doc_count(&self) -> u64 {
+        self.1.doc_count()
+    }
+
+    fn key(&self) -> BucketKey {
+        self.1.key()
+    }
+
+    fn key_as_string(&self) -> String {
+        self.1.key_as_string()
+    }
+
+    fn sub_aggregation(&self) -> &AggregationResults {
+        self.1.sub_aggregation()
+    }
+
+    fn sub_aggregation_mut(&mut self) -> &mut AggregationResults {
+        self.1.sub_aggregation_mut()
+    }
+}
+
+fn find_aggregation<'a>(
+    aggregation_results: &'a AggregationResults,
+    name: &str,
+) -> Option<&'a AggregationResult> {
+    aggregation_results
+        .0
+        .iter()
+        .find(|(aggregation_name, _)| aggregation_name == name)
+        .map(|(_, aggregation_result)| aggregation_result)
+}
+
+fn insert_pipeline_result<B: PipelineBucket + ?Sized>(bucket: &mut B, name: &str, value: f64) {
+    let pipeline_result = PipelineMetricResult {
+        value: Some(value).filter(|value| value.is_finite()),
+        keys: None,
+    };
+    bucket.sub_aggregation_mut().0.push((
+        name.to_string(),
+        AggregationResult::MetricResult(MetricResult::Pipeline(pipeline_result)),
+    ));
+}
+
+fn apply_to_children(
+    level: &PipelineLevel,
+    aggregation_results: &mut AggregationResults,
+) -> crate::Result<()> {
+    for (child_name, child_level) in &level.children {
+        let child_bucket_result_opt =
+            aggregation_results
+                .0
+                .iter_mut()
+                .find_map(|(name, aggregation_result)| match aggregation_result {
+                    AggregationResult::BucketResult(bucket_result) if name == child_name => {
+                        Some(bucket_result)
+                    }
+                    _ => None,
+                });
+        if let Some(child_bucket_result) = child_bucket_result_opt {
+            apply_to_bucket_result(child_level, child_bucket_result)?;
+        }
+    }
+    Ok(())
+}
+
+fn apply_to_bucket_result(
+    level: &PipelineLevel,
+    bucket_result: &mut BucketResult,
+) -> crate::Result<()> {
+    match bucket_result {
+        BucketResult::Range { buckets } => apply_to_bucket_entries(level, buckets),
+        BucketResult::Histogram { buckets } => apply_to_bucket_entries(level, buckets),
+        BucketResult::Terms { buckets, .. } => apply_to_buckets(level, buckets),
+        BucketResult::Composite { buckets, .. } => apply_to_buckets(level, buckets),
+        BucketResult::SignificantTerms { buckets, .. } => apply_to_buckets(level, buckets),
+        BucketResult::RareTerms { buckets } => apply_to_buckets(level, buckets),
+    }
+}
+
+fn apply_to_bucket_entries<B: PipelineBucket>(
+    level: &PipelineLevel,
+    bucket_entries: &mut BucketEntries<B>,
+) -> crate::Result<()> {
+    match bucket_entries {
+        BucketEntries::Vec(buckets) => apply_to_buckets(level, buckets),
+        BucketEntries::HashMap(buckets) => apply_to_buckets(level, buckets),
+    }
+}
+
+/// Computes the pipeline aggregations of `level`, declared in the sub-aggregations of the
+/// multi-bucket aggregation holding `buckets`.
+fn apply_to_buckets<B: PipelineBucket>(
+    level: &PipelineLevel,
+    buckets: &mut Vec<B>,
+) -> crate::Result<()> {
+    for bucket in buckets.iter_mut() {
+        apply_to_children(level, bucket.sub_aggregation_mut())?;
+    }
+    for (name, pipeline) in &level.pipelines {
+        match pipeline {
+            Pipeline::Sibling(sibling_pipeline) => {
+                for bucket in buckets.iter_mut() {
+                    apply_sibling_pipeline(name, sibling_pipeline, bucket.sub_aggregation_mut())?;
+                }
+            }
+            Pipeline::Parent(parent_pipeline) => {
+                apply_parent_pipeline(name, parent_pipeline, buckets)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn bucket_refs(bucket_result: &BucketResult) -> Vec<&dyn PipelineBucket> {
+    fn bucket_entries_refs<B: PipelineBucket>(
+        bucket_entries: &BucketEntries<B>,
+    ) -> Vec<&dyn PipelineBucket> {
+        match bucket_entries {
+            BucketEntries::Vec(buckets) => buckets
+                .iter()
+                .map(|bucket| bucket as &dyn PipelineBucket)
+                .collect(),
+            BucketEntries::HashMap(buckets) => buckets
+                .iter()
+                .map(|bucket| bucket as &dyn PipelineBucket)
+                .collect(),
+        }
+    }
+    match bucket_result {
+        BucketResult::Range { buckets } => bucket_entries_refs(buckets),
+        BucketResult::Histogram { buckets } => bucket_entries_refs(buckets),
+        BucketResult::Terms { buckets, .. } => buckets
+            .iter()
+            .map(|bucket| bucket as &dyn PipelineBucket)
+            .collect(),
+        BucketResult::Composite { buckets, .. } => buckets
+            .iter()
+            .map(|bucket| bucket as &dyn PipelineBucket)
+            .collect(),
+        BucketResult::SignificantTerms { buckets, .. } => buckets
+            .iter()
+            .map(|bucket| bucket as &dyn PipelineBucket)
+            .collect(),
+        BucketResult::RareTerms { buckets } => buckets
+            .iter()
+            .map(|bucket| bucket as &dyn PipelineBucket)
+            .collect(),
+    }
+}
+
+/// Resolves the value of a metric sub-aggregation. Multi-value metrics are looked up in their
+/// Elasticsearch representation, e.g. `stats.avg` or `percentiles[99.0]`.
+fn resolve_metric_value(
+    aggregation_results: &AggregationResults,
+    agg_name: &str,
+    metric_key: Option<&str>,
+) -> crate::Result<Option<f64>> {
+    let Some(aggregation_result) = find_aggregation(aggregation_results, agg_name) else {
+        return Ok(None);
+    };
+    let AggregationResult::MetricResult(metric_result) = aggregation_result else {
+        return Err(SearchError::InvalidAggregationRequest(format!(
+            "buckets_path must point to a metric, `{agg_name}` is a bucket aggregation"
+        )));
+    };
+    match (metric_result, metric_key) {
+        (MetricResult::Pipeline(pipeline_result), None | Some("value")) => {
+            Ok(pipeline_result.value)
+        }
+        (MetricResult::Pipeline(_), Some(metric_key)) => {
+            Err(SearchError::InvalidAggregationRequest(format!(
+                "pipeline aggregation `{agg_name}` has no value `{metric_key}`"
+            )))
+        }
+        (
+            MetricResult::Average(single_metric_result)
+            | MetricResult::Count(single_metric_result)
+            | MetricResult::Max(single_metric_result)
+            | MetricResult::Min(single_metric_result)
+            | MetricResult::Sum(single_metric_result)
+            | MetricResult::Cardinality(single_metric_result),
+            None | Some("value"),
+        ) => Ok(single_metric_result.value),
+        (_, None) => Err(SearchError::InvalidAggregationRequest(format!(
+            "buckets_path must select a value of the multi-value metric `{agg_name}`"
+        ))),
+        (_, Some(metric_key)) => {
+            let tantivy_metric_result = TantivyMetricResult::try_from(metric_result.clone())
+                .map_err(|error| SearchError::Internal(error.to_string()))?;
+            let metric_json = serde_json::to_value(tantivy_metric_result)?;
+            Ok(lookup_metric_value(&metric_json, metric_key))
+        }
+    }
+}
+
+fn lookup_metric_value(metric_json: &JsonValue, metric_key: &str) -> Option<f64> {
+    if let Some(value) = metric_json.get(metric_key) {
+        return value.as_f64();
+    }
+    let percent: f64 = metric_key.parse().ok()?;
+
+    match metric_json.get("values")? {
+        JsonValue::Object(values) => values
+            .iter()
+            .find(|(key, _)| key.parse::<f64>().ok() == Some(percent))
+            .and_then(|(_, value)| value.as_f64()),
+        JsonValue::Array(entries) => entries
+            .iter()
+            .find(|entry| entry.get("key").and_then(JsonValue::as_f64) == Some(percent))
+            .and_then(|entry| entry.get("value")?.as_f64()),
+        _ => None,
+    }
+}
+
+/// Resolves the value of a buckets path in a bucket. Returns `None` for gaps, unless the gap
+/// policy is `insert_zeros`.
+fn resolve_bucket_value<B: PipelineBucket + ?Sized>(
+    bucket: &B,
+    buckets_path: &BucketsPath,
+    gap_policy: GapPolicy,
+) -> crate::Result<Option<f64>> {
+    let value_opt = match buckets_path {
+        BucketsPath::Count => Some(bucket.doc_count() as f64),
+        BucketsPath::Key => bucket.key().as_f64(),
+        BucketsPath::Metric {
+            agg_name,
+            metric_key,
+        } => resolve_metric_value(bucket.sub_aggregation(), agg_name, metric_key.as_deref())?,
+    }
+    .filter(|value| value.is_finite());
+
+    let is_empty_bucket = bucket.doc_count() == 0 && *buckets_path != BucketsPath::Count;
+    let is_gap = value_opt.is_none() || (is_empty_bucket && gap_policy == GapPolicy::Skip);
+
+    if !is_gap {
+        return Ok(value_opt);
+    }
+    if gap_policy == GapPolicy::InsertZeros {
+        return Ok(Some(0.0));
+    }
+    Ok(None)
+}
+
+fn resolve_bucket_values<B: PipelineBucket>(
+    buckets: &[B],
+    buckets_path: &BucketsPath,
+    gap_policy: GapPolicy,
+) -> crate::Result<Vec<Option<f64>>> {
+    buckets
+        .iter()
+        .map(|bucket| resolve_bucket_value(bucket, buckets_path, gap_policy))
+        .collect()
+}
+
+/// Resolves the params of a script. Returns `None` if one of them is a gap.
+fn resolve_script_params<B: PipelineBucket>(
+    bucket: &B,
+    buckets_paths: &[(String, BucketsPath)],
+    gap_policy: GapPolicy,
+) -> crate::Result<Option<BTreeMap<String, f64>>> {
+    let mut params = BTreeMap::new();
+
+    for (param, buckets_path) in buckets_paths {
+        let Some(value) = resolve_bucket_value(bucket, buckets_path, gap_policy)? else {
+            return Ok(None);
+        };
+        params.insert(param.clone(), value);
+    }
+    Ok(Some(params))
+}
+
+fn apply_sibling_pipeline(
+    name: &str,
+    sibling_pipeline: &SiblingPipeline,
+    aggregation_results: &mut AggregationResults,
+) -> crate::Result<()> {
+    let Some(AggregationResult::BucketResult(bucket_result)) =
+        find_aggregation(aggregation_results, &sibling_pipeline.agg_name)
+    else {
+        return Err(SearchError::InvalidAggregationRequest(format!(
+            "buckets_path of pipeline aggregation `{name}` must point to a multi-bucket \
+             aggregation"
+        )));
+    };
+    let mut values = Vec::new();
+
+    for bucket in bucket_refs(bucket_result) {
+        if let Some(value) = resolve_bucket_value(
+            bucket,
+            &sibling_pipeline.buckets_path,
+            sibling_pipeline.gap_policy,
+        )? {
+            values.push((value, bucket));
+        }
+    }
+    let pipeline_result = match sibling_pipeline.bucket_metric {
+        BucketMetric::Max => {
+            let max_value_opt = values.iter().map(|(value, _)| *value).reduce(f64::max);
+            let keys = values
+                .iter()
+                .filter(|(value, _)| Some(*value) == max_value_opt)
+                .map(|(_, bucket)| bucket.key_as_string())
+                .collect();
+            PipelineMetricResult {
+                value: max_value_opt,
+                keys: Some(keys),
+            }
+        }
+        BucketMetric::Avg => {
+            let avg_value_opt = if values.is_empty() {
+                None
+            } else {
+                let sum: f64 = values.iter().map(|(value, _)| value).sum();
+                Some(sum / values.len() as f64)
+            };
+            PipelineMetricResult {
+                value: avg_value_opt,
+                keys: None,
+            }
+        }
+    };
+    aggregation_results.0.push((
+        name.to_string(),
+        AggregationResult::MetricResult(MetricResult::Pipeline(pipeline_result)),
+    ));
+    Ok(())
+}
+
+fn script_error(name: &str, error: String) -> SearchError {
+    SearchError::InvalidAggregationRequest(format!(
+        "failed to evaluate the script of pipeline aggregation `{name}`: {error}"
+    ))
+}
+
+fn apply_parent_pipeline<B: PipelineBucket>(
+    name: &str,
+    parent_pipeline: &ParentPipeline,
+    buckets: &mut Vec<B>,
+) -> crate::Result<()> {
+    match parent_pipeline {
+        ParentPipeline::Derivative {
+            buckets_path,
+            gap_policy,
+        } => {
+            let values = resolve_bucket_values(buckets, buckets_path, *gap_policy)?;
+            let mut previous_value_opt: Option<f64> = None;
+
+            for (bucket, value_opt) in buckets.iter_mut().zip(values) {
+                let Some(value) = value_opt else {
+                    continue;
+                };
+                if let Some(previous_value) = previous_value_opt {
+                    insert_pipeline_result(bucket, name, value - previous_value);
+                }
+                previous_value_opt = Some(value);
+            }
+        }
+        ParentPipeline::CumulativeSum { buckets_path } => {
+            let values = resolve_bucket_values(buckets, buckets_path, GapPolicy::InsertZeros)?;
+            let mut sum = 0.0;
+
+            for (bucket, value_opt) in buckets.iter_mut().zip(values) {
+                sum += value_opt.unwrap_or(0.0);
+                insert_pipeline_result(bucket, name, sum);
+            }
+        }
+        ParentPipeline::MovingFn {
+            buckets_path,
+            window,
+            shift,
+            moving_function,
+            gap_policy,
+        } => {
+            // Gaps are represented as `NaN`, which the moving functions ignore.
+            let values: Vec<f64> = resolve_bucket_values(buckets, buckets_path, *gap_policy)?
+                .into_iter()
+                .map(|value_opt| value_opt.unwrap_or(f64::NAN))
+                .collect();
+            let num_values = values.len() as i64;
+
+            for (index, bucket) in buckets.iter_mut().enumerate() {
+                // By default, the window ends just before the current bucket.
+                let end = (index as i64 + shift).clamp(0, num_values);
+                let start = (index as i64 + shift - *window as i64).clamp(0, end);
+                let value = moving_function.apply(&values[start as usize..end as usize]);
+                insert_pipeline_result(bucket, name, value);
+            }
+        }
+        ParentPipeline::MovingAvg {
+            buckets_path,
+            window,
+            gap_policy,
+        } => {
+            let values = resolve_bucket_values(buckets, buckets_path, *gap_policy)?;
+            let mut window_values: VecDeque<f64> = VecDeque::with_capacity(*window);
+
+            for (bucket, value_opt) in buckets.iter_mut().zip(values) {
+                let Some(value) = value_opt else {
+                    continue;
+                };
+                if !window_values.is_empty() {
+                    let avg = window_values.iter().sum::<f64>() / window_values.len() as f64;
+                    insert_pipeline_result(bucket, name, avg);
+                }
+                if window_values.len() == *window {
+                    window_values.pop_front();
+                }
+                window_values.push_back(value);
+            }
+        }
+        ParentPipeline::BucketScript {
+            buckets_paths,
+            expr,
+            gap_policy,
+        } => {
+            for bucket in buckets.iter_mut() {
+                let Some(params) = resolve_script_params(bucket, buckets_paths, *gap_policy)?
+                else {
+                    continue;
+                };
+                let value = expr
+                    .eval_number(&params)
+                    .map_err(|error| script_error(name, error))?;
+                insert_pipeline_result(bucket, name, value);
+            }
+        }
+        ParentPipeline::BucketSelector {
+            buckets_paths,
+            expr,
+            gap_policy,
+        } => {
+            let mut is_selected = Vec::with_capacity(buckets.len());
+
+            for bucket in buckets.iter() {
+                // Like Elasticsearch, buckets with gaps are kept.
+                let is_bucket_selected =
+                    match resolve_script_params(bucket, buckets_paths, *gap_policy)? {
+                        Some(params) => expr
+                            .eval_bool(&params)
+                            .map_err(|error| script_error(name, error))?,
+                        None => true,
+                    };
+                is_selected.push(is_bucket_selected);
+            }
+            let mut is_selected_iter = is_selected.into_iter();
+            buckets.retain(|_| is_selected_iter.next().unwrap_or(true));
+        }
+        ParentPipeline::BucketSort {
+            sort,
+            from,
+            size,
+            gap_policy,
+        } => {
+            if !sort.is_empty() {
+                let mut sort_values = Vec::with_capacity(buckets.len());
+
+                for bucket in buckets.iter() {
+                    let mut bucket_sort_values = Vec::with_capacity(sort.len());
+
+                    for (buckets_path, _) in sort {
+                        let sort_value_opt = if *buckets_path == BucketsPath::Key {
+                            Some(bucket.key())
+                        } else {
+                            resolve_bucket_value(bucket, buckets_path, *gap_policy)?
+                                .map(BucketKey::Number)
+                        };
+                        bucket_sort_values.push(sort_value_opt);
+                    }
+                    sort_values.push(bucket_sort_values);
+                }
+                let mut sorted_buckets: Vec<(Vec<Option<BucketKey>>, B)> =
+                    sort_values.into_iter().zip(buckets.drain(..)).collect();
+                sorted_buckets.sort_by(|(left_values, _), (right_values, _)| {
+                    compare_sort_values(left_values, right_values, sort)
+                });
+                buckets.extend(sorted_buckets.into_iter().map(|(_, bucket)| bucket));
+            }
+            buckets.drain(..(*from).min(buckets.len()));
+
+            if let Some(size) = size {
+                buckets.truncate(*size);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compares the sort values of two buckets. Buckets without a value sort last, whatever the
+/// order.
+fn compare_sort_values(
+    left_values: &[Option<BucketKey>],
+    right_values: &[Option<BucketKey>],
+    sort: &[(BucketsPath, SortOrder)],
+) -> Ordering {
+    for ((left_value_opt, right_value_opt), (_, sort_order)) in
+        left_values.iter().zip(right_values).zip(sort)
+    {
+        let ordering = match (left_value_opt, right_value_opt) {
+            (Some(left_value), Some(right_value)) => {
+                let ordering = left_value.compare(right_value);
+                match sort_order {
+                    SortOrder::Asc => ordering,
+                    SortOrder::Desc => ordering.reverse(),
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tantivy::aggregation::metric::SingleMetricResult;
+
+    use super::*;
+
+    fn extract_pipeline_aggregations(
+        aggregations_json: JsonValue,
+    ) -> crate::Result<Option<(PipelineAggregations, JsonValue)>> {
+        let mut search_request = SearchRequest {
+            aggregation_request: Some(aggregations_json.to_string()),
+            ..Default::default()
+        };
+        let Some(pipeline_aggregations) =
+            PipelineAggregations::extract_from_request(&mut search_request)?
+        else {
+            return Ok(None);
+        };
+        let stripped_aggregations_json =
+            serde_json::from_str(&search_request.aggregation_request.unwrap()).unwrap();
+        Ok(Some((pipeline_aggregations, stripped_aggregations_json)))
+    }
+
+    fn apply_pipeline_aggregations(
+        aggregations_json: JsonValue,
+        aggregation_results: &mut AggregationResults,
+    ) -> crate::Result<()> {
+        let (pipeline_aggregations, _) = extract_pipeline_aggregations(aggregations_json)?.unwrap();
+        pipeline_aggregations.apply_to_aggregation_results(aggregation_results)
+    }
+
+    fn sum_result(value: Option<f64>) -> AggregationResult {
+        AggregationResult::MetricResult(MetricResult::Sum(SingleMetricResult { value }))
+    }
+
+    /// Builds the results of a `histo` histogram aggregation with a `sales` sum sub-aggregation.
+    fn histogram_results(buckets: &[(u64, Option<f64>)]) -> AggregationResults {
+        let buckets = buckets
+            .iter()
+            .enumerate()
+            .map(|(index, (doc_count, sales))| BucketEntry {
+                key_as_string: None,
+                key: Key::F64(index as f64 * 10.0),
+                doc_count: *doc_count,
+                sub_aggregation: AggregationResults(vec![(
+                    "sales".to_string(),
+                    sum_result(*sales),
+                )]),
+            })
+            .collect();
+        AggregationResults(vec![(
+            "histo".to_string(),
+            AggregationResult::BucketResult(BucketResult::Histogram {
+                buckets: BucketEntries::Vec(buckets),
+            }),
+        )])
+    }
+
+    fn histogram_buckets(aggregation_results: &AggregationResults) -> &[BucketEntry] {
+        match find_aggregation(aggregation_results, "histo") {
+            Some(AggregationResult::BucketResult(BucketResult::Histogram {
+                buckets: BucketEntries::Vec(buckets),
+            })) => buckets,
+            _ => panic!("expected histogram results"),
+        }
+    }
+
+    fn pipeline_result<'a>(
+        aggregation_results: &'a AggregationResults,
+        name: &str,
+    ) -> Option<&'a PipelineMetricResult> {
+        match find_aggregation(aggregation_results, name)? {
+            AggregationResult::MetricResult(MetricResult::Pipeline(pipeline_result)) => {
+                Some(pipeline_result)
+            }
+            _ => panic!("expected a pipeline result"),
+        }
+    }
+
+    /// Returns the value of a pipeline aggregation in each histogram bucket, `None` if the bucket
+    /// has no value for it.
+    fn histogram_pipeline_values(
+        aggregation_results: &AggregationResults,
+        name: &str,
+    ) -> Vec<Option<f64>> {
+        histogram_buckets(aggregation_results)
+            .iter()
+            .map(|bucket| pipeline_result(&bucket.sub_aggregation, name)?.value)
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_pipeline_aggregations() {
+        let (pipeline_aggregations, stripped_aggregations_json) =
+            extract_pipeline_aggregations(json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "sales": {"sum": {"field": "price"}},
+                        "sales_deriv": {"derivative": {"buckets_path": "sales"}},
+                        "sales_deriv_sum": {"cumulative_sum": {"buckets_path": "sales_deriv"}}
+                    }
+                },
+                "histo_count": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "count_sum": {"cumulative_sum": {"buckets_path": "_count"}}
+                    }
+                },
+                "max_sales": {"max_bucket": {"buckets_path": "histo>sales"}}
+            }))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            stripped_aggregations_json,
+            json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "sales": {"sum": {"field": "price"}}
+                    }
+                },
+                "histo_count": {
+                    "histogram": {"field": "timestamp", "interval": 10}
+                }
+            })
+        );
+        let root_level = &pipeline_aggregations.root_level;
+        assert_eq!(root_level.pipelines.len(), 1);
+        assert_eq!(root_level.pipelines[0].0, "max_sales");
+        assert_eq!(root_level.children.len(), 2);
+
+        let (histo_name, histo_level) = &root_level.children[0];
+        assert_eq!(histo_name, "histo");
+        let pipeline_names: Vec<&str> = histo_level
+            .pipelines
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(pipeline_names, ["sales_deriv", "sales_deriv_sum"]);
+    }
+
+    #[test]
+    fn test_extract_pipeline_aggregations_none() {
+        assert!(
+            extract_pipeline_aggregations(json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {"sales": {"sum": {"field": "price"}}}
+                }
+            }))
+            .unwrap()
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_extract_pipeline_aggregations_invalid() {
+        let error_message = |aggregations_json: JsonValue| {
+            extract_pipeline_aggregations(aggregations_json)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(
+            error_message(json!({
+                "sales_deriv": {"derivative": {"buckets_path": "sales"}}
+            }))
+            .contains("must be declared in the sub-aggregations")
+        );
+        assert!(
+            error_message(json!({
+                "max_sales": {"max_bucket": {"buckets_path": "histo>sales"}}
+            }))
+            .contains("references unknown aggregation `histo`")
+        );
+        assert!(
+            error_message(json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "a": {"derivative": {"buckets_path": "b"}},
+                        "b": {"derivative": {"buckets_path": "a"}}
+                    }
+                }
+            }))
+            .contains("reference each other")
+        );
+        assert!(
+            error_message(json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "ratio": {"bucket_script": {
+                            "buckets_path": {"count": "_count"},
+                            "script": "params.count / params.total"
+                        }}
+                    }
+                }
+            }))
+            .contains("script param `total` is not defined")
+        );
+        assert!(
+            error_message(json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "sales_deriv": {"derivative": {"buckets_path": "_count", "unit": "1d"}}
+                    }
+                }
+            }))
+            .contains("invalid pipeline aggregation `sales_deriv`")
+        );
+    }
+
+    #[test]
+    fn test_derivative_and_cumulative_sum() {
+        let mut aggregation_results =
+            histogram_results(&[(2, Some(10.0)), (3, Some(25.0)), (0, None), (1, Some(30.0))]);
+        apply_pipeline_aggregations(
+            json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "sales": {"sum": {"field": "price"}},
+                        "sales_deriv": {"derivative": {"buckets_path": "sales"}},
+                        "sales_deriv_zeros": {"derivative": {
+                            "buckets_path": "sales",
+                            "gap_policy": "insert_zeros"
+                        }},
+                        "cumulative_sales": {"cumulative_sum": {"buckets_path": "sales"}},
+                        "cumulative_count": {"cumulative_sum": {"buckets_path": "_count"}}
+                    }
+                }
+            }),
+            &mut aggregation_results,
+        )
+        .unwrap();
+        assert_eq!(
+            histogram_pipeline_values(&aggregation_results, "sales_deriv"),
+            [None, Some(15.0), None, Some(5.0)]
+        );
+        assert_eq!(
+            histogram_pipeline_values(&aggregation_results, "sales_deriv_zeros"),
+            [None, Some(15.0), Some(-25.0), Some(30.0)]
+        );
+        assert_eq!(
+            histogram_pipeline_values(&aggregation_results, "cumulative_sales"),
+            [Some(10.0), Some(35.0), Some(35.0), Some(65.0)]
+        );
+        assert_eq!(
+            histogram_pipeline_values(&aggregation_results, "cumulative_count"),
+            [Some(2.0), Some(5.0), Some(5.0), Some(6.0)]
+        );
+        // The first bucket has no derivative at all.
+        let first_bucket = &histogram_buckets(&aggregation_results)[0];
+        assert!(pipeline_result(&first_bucket.sub_aggregation, "sales_deriv").is_none());
+    }
+
+    #[test]
+    fn test_moving_fn_and_moving_avg() {
+        let mut aggregation_results = histogram_results(&[
+            (1, Some(1.0)),
+            (1, Some(2.0)),
+            (1, Some(3.0)),
+            (1, Some(4.0)),
+            (1, Some(5.0)),
+        ]);
+        apply_pipeline_aggregations(
+            json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "sales": {"sum": {"field": "price"}},
+                        "sales_max": {"moving_fn": {
+                            "buckets_path": "sales",
+                            "window": 2,
+                            "script": "MovingFunctions.max(values)"
+                        }},
+                        "sales_sum_shifted": {"moving_fn": {
+                            "buckets_path": "sales",
+                            "window": 2,
+                            "shift": 1,
+                            "script": {"source": "MovingFunctions.sum(values)"}
+                        }},
+                        "sales_avg": {"moving_avg": {"buckets_path": "sales", "window": 2}}
+                    }
+                }
+            }),
+            &mut aggregation_results,
+        )
+        .unwrap();
+        assert_eq!(
+            histogram_pipeline_values(&aggregation_results, "sales_max"),
+            [None, Some(1.0), Some(2.0), Some(3.0), Some(4.0)]
+        );
+        assert_eq!(
+            histogram_pipeline_values(&aggregation_results, "sales_sum_shifted"),
+            [Some(1.0), Some(3.0), Some(5.0), Some(7.0), Some(9.0)]
+        );
+        assert_eq!(
+            histogram_pipeline_values(&aggregation_results, "sales_avg"),
+            [None, Some(1.0), Some(1.5), Some(2.5), Some(3.5)]
+        );
+    }
+
+    #[test]
+    fn test_bucket_script_selector_and_sort() {
+        let mut aggregation_results = histogram_results(&[
+            (2, Some(10.0)),
+            (4, Some(60.0)),
+            (5, Some(25.0)),
+            (1, Some(40.0)),
+        ]);
+        apply_pipeline_aggregations(
+            json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "sales": {"sum": {"field": "price"}},
+                        "avg_price": {"bucket_script": {
+                            "buckets_path": {"sales": "sales", "count": "_count"},
+                            "script": "params.sales / params.count"
+                        }},
+                        "cheap_buckets": {"bucket_selector": {
+                            "buckets_path": {"avg_price": "avg_price"},
+                            "script": "params.avg_price > 5"
+                        }},
+                        "sales_sort": {"bucket_sort": {
+                            "sort": [{"sales": {"order": "desc"}}],
+                            "size": 2
+                        }}
+                    }
+                }
+            }),
+            &mut aggregation_results,
+        )
+        .unwrap();
+        let bucket_keys: Vec<BucketKey> = histogram_buckets(&aggregation_results)
+            .iter()
+            .map(|bucket| bucket.key())
+            .collect();
+        assert_eq!(
+            bucket_keys,
+            [BucketKey::Number(10.0), BucketKey::Number(30.0)]
+        );
+        assert_eq!(
+            histogram_pipeline_values(&aggregation_results, "avg_price"),
+            [Some(15.0), Some(40.0)]
+        );
+    }
+
+    #[test]
+    fn test_bucket_sort_by_key_with_from() {
+        let mut aggregation_results =
+            histogram_results(&[(2, Some(10.0)), (4, None), (5, Some(25.0))]);
+        apply_pipeline_aggregations(
+            json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "sales": {"sum": {"field": "price"}},
+                        "key_sort": {"bucket_sort": {"sort": [{"_key": "desc"}], "from": 1}}
+                    }
+                }
+            }),
+            &mut aggregation_results,
+        )
+        .unwrap();
+        let bucket_keys: Vec<BucketKey> = histogram_buckets(&aggregation_results)
+            .iter()
+            .map(|bucket| bucket.key())
+            .collect();
+        assert_eq!(
+            bucket_keys,
+            [BucketKey::Number(10.0), BucketKey::Number(0.0)]
+        );
+    }
+
+    #[test]
+    fn test_max_and_avg_bucket() {
+        let mut aggregation_results =
+            histogram_results(&[(2, Some(10.0)), (4, Some(40.0)), (0, None), (1, Some(40.0))]);
+        apply_pipeline_aggregations(
+            json!({
+                "histo": {
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {"sales": {"sum": {"field": "price"}}}
+                },
+                "max_sales": {"max_bucket": {"buckets_path": "histo>sales"}},
+                "avg_sales": {"avg_bucket": {"buckets_path": "histo>sales"}},
+                "avg_count": {"avg_bucket": {
+                    "buckets_path": "histo>_count",
+                    "gap_policy": "insert_zeros"
+                }}
+            }),
+            &mut aggregation_results,
+        )
+        .unwrap();
+        assert_eq!(
+            pipeline_result(&aggregation_results, "max_sales").unwrap(),
+            &PipelineMetricResult {
+                value: Some(40.0),
+                keys: Some(vec!["10".to_string(), "30".to_string()]),
+            }
+        );
+        assert_eq!(
+            pipeline_result(&aggregation_results, "avg_sales").unwrap(),
+            &PipelineMetricResult {
+                value: Some(30.0),
+                keys: None,
+            }
+        );
+        assert_eq!(
+            pipeline_result(&aggregation_results, "avg_count")
+                .unwrap()
+                .value,
+            Some(7.0 / 4.0)
+        );
+    }
+
+    #[test]
+    fn test_pipeline_on_terms_with_stats() {
+        let stats_json = json!({"count": 2, "sum": 30.0, "min": 10.0, "max": 20.0, "avg": 15.0});
+        let stats = serde_json::from_value(stats_json).unwrap();
+        let mut aggregation_results = AggregationResults(vec![(
+            "hosts".to_string(),
+            AggregationResult::BucketResult(BucketResult::Terms {
+                buckets: vec![BucketEntry {
+                    key_as_string: None,
+                    key: Key::Str("web-1".to_string()),
+                    doc_count: 2,
+                    sub_aggregation: AggregationResults(vec![(
+                        "latency".to_string(),
+                        AggregationResult::MetricResult(MetricResult::Stats(stats)),
+                    )]),
+                }],
+                sum_other_doc_count: 0,
+                doc_count_error_upper_bound: None,
+            }),
+        )]);
+        apply_pipeline_aggregations(
+            json!({
+                "hosts": {
+                    "terms": {"field": "host"},
+                    "aggs": {
+                        "latency": {"stats": {"field": "latency"}},
+                        "latency_spread": {"bucket_script": {
+                            "buckets_path": {"max": "latency.max", "min": "latency[min]"},
+                            "script": "params.max - params.min"
+                        }}
+                    }
+                },
+                "max_avg_latency": {"max_bucket": {"buckets_path": "hosts>latency.avg"}}
+            }),
+            &mut aggregation_results,
+        )
+        .unwrap();
+        assert_eq!(
+            pipeline_result(&aggregation_results, "max_avg_latency").unwrap(),
+            &PipelineMetricResult {
+                value: Some(15.0),
+                keys: Some(vec!["web-1".to_string()]),
+            }
+        );
+        let Some(AggregationResult::BucketResult(BucketResult::Terms { buckets, .. })) =
+            find_aggregation(&aggregation_results, "hosts")
+        else {
+            panic!("expected terms results");
+        };
+        assert_eq!(
+            pipeline_result(&buckets[0].sub_aggregation, "latency_spread")
+                .unwrap()
+                .value,
+            Some(10.0)
+        );
+    }
+}
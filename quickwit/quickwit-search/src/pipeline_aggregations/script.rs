@@ -0,0 +1,589 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The small subset of painless supported by the `bucket_script`, `bucket_selector` and
+//! `moving_fn` pipeline aggregations.
+
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use serde::Deserialize;
+
+/// A script, either as a bare string or as an object with a `source`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub(super) enum Script {
+    Source(String),
+    Object(ScriptObject),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct ScriptObject {
+    #[serde(alias = "inline")]
+    source: String,
+    #[serde(default)]
+    lang: Option<String>,
+}
+
+impl Script {
+    /// Returns the source of the script, stripped of an optional leading `return` and trailing
+    /// semicolon.
+    pub(super) fn source(&self) -> Result<&str, String> {
+        let source = match self {
+            Script::Source(source) => source,
+            Script::Object(ScriptObject { source, lang }) => {
+                if let Some(lang) = lang
+                    && lang != "painless"
+                    && lang != "expression"
+                {
+                    return Err(format!("unsupported script language `{lang}`"));
+                }
+                source
+            }
+        };
+        let source = source.trim();
+        let source = source.strip_suffix(';').unwrap_or(source).trim_end();
+        let source = source
+            .strip_prefix("return ")
+            .map(str::trim_start)
+            .unwrap_or(source);
+        Ok(source)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+/// A parsed `bucket_script` or `bucket_selector` expression.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Expr {
+    Number(f64),
+    Bool(bool),
+    Param(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ExprValue {
+    Number(f64),
+    Bool(bool),
+}
+
+impl Expr {
+    /// Parses an expression made of numbers, `params.<name>` variables, arithmetic, comparison
+    /// and boolean operators, and parentheses.
+    pub(super) fn parse(source: &str) -> Result<Expr, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected token `{token:?}` in script `{source}`"));
+        }
+        Ok(expr)
+    }
+
+    /// Returns the names of the params referenced by the expression.
+    pub(super) fn params(&self) -> Vec<&str> {
+        let mut params = Vec::new();
+        self.collect_params(&mut params);
+        params
+    }
+
+    fn collect_params<'a>(&'a self, params: &mut Vec<&'a str>) {
+        match self {
+            Expr::Number(_) | Expr::Bool(_) => {}
+            Expr::Param(name) => params.push(name),
+            Expr::Neg(expr) | Expr::Not(expr) => expr.collect_params(params),
+            Expr::Binary(_, left, right) => {
+                left.collect_params(params);
+                right.collect_params(params);
+            }
+        }
+    }
+
+    pub(super) fn eval(&self, params: &BTreeMap<String, f64>) -> Result<ExprValue, String> {
+        let value = match self {
+            Expr::Number(number) => ExprValue::Number(*number),
+            Expr::Bool(boolean) => ExprValue::Bool(*boolean),
+            Expr::Param(name) => {
+                let value = params
+                    .get(name)
+                    .ok_or_else(|| format!("unknown script param `{name}`"))?;
+                ExprValue::Number(*value)
+            }
+            Expr::Neg(expr) => ExprValue::Number(-expr.eval_number(params)?),
+            Expr::Not(expr) => ExprValue::Bool(!expr.eval_bool(params)?),
+            Expr::Binary(BinaryOp::And, left, right) => {
+                ExprValue::Bool(left.eval_bool(params)? && right.eval_bool(params)?)
+            }
+            Expr::Binary(BinaryOp::Or, left, right) => {
+                ExprValue::Bool(left.eval_bool(params)? || right.eval_bool(params)?)
+            }
+            Expr::Binary(op, left, right) => {
+                let left = left.eval_number(params)?;
+                let right = right.eval_number(params)?;
+                match op {
+                    BinaryOp::Add => ExprValue::Number(left + right),
+                    BinaryOp::Sub => ExprValue::Number(left - right),
+                    BinaryOp::Mul => ExprValue::Number(left * right),
+                    BinaryOp::Div => ExprValue::Number(left / right),
+                    BinaryOp::Rem => ExprValue::Number(left % right),
+                    BinaryOp::Lt => ExprValue::Bool(left < right),
+                    BinaryOp::Le => ExprValue::Bool(left <= right),
+                    BinaryOp::Gt => ExprValue::Bool(left > right),
+                    BinaryOp::Ge => ExprValue::Bool(left >= right),
+                    BinaryOp::Eq => ExprValue::Bool(left == right),
+                    BinaryOp::Ne => ExprValue::Bool(left != right),
+                    BinaryOp::And | BinaryOp::Or => unreachable!(),
+                }
+            }
+        };
+        Ok(value)
+    }
+
+    pub(super) fn eval_number(&self, params: &BTreeMap<String, f64>) -> Result<f64, String> {
+        match self.eval(params)? {
+            ExprValue::Number(number) => Ok(number),
+            ExprValue::Bool(_) => Err("expected a number, got a boolean".to_string()),
+        }
+    }
+
+    pub(super) fn eval_bool(&self, params: &BTreeMap<String, f64>) -> Result<bool, String> {
+        match self.eval(params)? {
+            ExprValue::Bool(boolean) => Ok(boolean),
+            ExprValue::Number(_) => Err("expected a boolean, got a number".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+    OpenParen,
+    CloseParen,
+}
+
+const OPERATORS: [&str; 16] = [
+    "&&", "||", "<=", ">=", "==", "!=", "+", "-", "*", "/", "%", "<", ">", "!", ".", ",",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<CharIndices> = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::OpenParen);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::CloseParen);
+        } else if c.is_ascii_digit() {
+            let mut end = start;
+            while let Some(&(pos, c)) = chars.peek()
+                && (c.is_ascii_digit() || c == '.')
+            {
+                end = pos + c.len_utf8();
+                chars.next();
+            }
+            let number: f64 = source[start..end]
+                .parse()
+                .map_err(|_| format!("invalid number `{}`", &source[start..end]))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(pos, c)) = chars.peek()
+                && (c.is_alphanumeric() || c == '_')
+            {
+                end = pos + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Ident(source[start..end].to_string()));
+        } else {
+            let Some(operator) = OPERATORS
+                .iter()
+                .find(|operator| source[start..].starts_with(**operator))
+            else {
+                return Err(format!("unexpected character `{c}` in script `{source}`"));
+            };
+            for _ in 0..operator.len() {
+                chars.next();
+            }
+            tokens.push(Token::Op(*operator));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(operator)) => Some(*operator),
+            _ => None,
+        }
+    }
+
+    fn parse_binary(
+        &mut self,
+        operators: &[(&str, BinaryOp)],
+        parse_operand: fn(&mut Self) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut left = parse_operand(self)?;
+
+        while let Some(operator) = self.peek_op()
+            && let Some((_, op)) = operators.iter().find(|(symbol, _)| *symbol == operator)
+        {
+            self.pos += 1;
+            let right = parse_operand(self)?;
+            left = Expr::Binary(*op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&[("||", BinaryOp::Or)], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&[("&&", BinaryOp::And)], Self::parse_equality)
+    }
+
+    fn parse_equality(&mut self) -> Result<Expr, String> {
+        self.parse_binary(
+            &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+            Self::parse_comparison,
+        )
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        self.parse_binary(
+            &[
+                ("<", BinaryOp::Lt),
+                ("<=", BinaryOp::Le),
+                (">", BinaryOp::Gt),
+                (">=", BinaryOp::Ge),
+            ],
+            Self::parse_additive,
+        )
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, String> {
+        self.parse_binary(
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            Self::parse_multiplicative,
+        )
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, String> {
+        self.parse_binary(
+            &[
+                ("*", BinaryOp::Mul),
+                ("/", BinaryOp::Div),
+                ("%", BinaryOp::Rem),
+            ],
+            Self::parse_unary,
+        )
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        match self.peek_op() {
+            Some("-") => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.parse_unary()?)))
+            }
+            Some("!") => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| "unexpected end of script".to_string())?;
+        self.pos += 1;
+
+        match token {
+            Token::Number(number) => Ok(Expr::Number(*number)),
+            Token::Ident(ident) if ident == "true" => Ok(Expr::Bool(true)),
+            Token::Ident(ident) if ident == "false" => Ok(Expr::Bool(false)),
+            Token::Ident(ident) if ident == "params" => {
+                if self.peek_op() != Some(".") {
+                    return Err("expected `.` after `params`".to_string());
+                }
+                self.pos += 1;
+                match self.tokens.get(self.pos) {
+                    Some(Token::Ident(name)) => {
+                        self.pos += 1;
+                        Ok(Expr::Param(name.clone()))
+                    }
+                    _ => Err("expected a param name after `params.`".to_string()),
+                }
+            }
+            Token::OpenParen => {
+                let expr = self.parse_or()?;
+                if self.tokens.get(self.pos) != Some(&Token::CloseParen) {
+                    return Err("expected `)`".to_string());
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            token => Err(format!("unexpected token `{token:?}`")),
+        }
+    }
+}
+
+/// The functions of the painless `MovingFunctions` class supported by `moving_fn`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum MovingFunction {
+    Max,
+    Min,
+    Sum,
+    UnweightedAvg,
+    LinearWeightedAvg,
+    StdDev,
+}
+
+impl MovingFunction {
+    /// Parses scripts such as `MovingFunctions.max(values)` or
+    /// `MovingFunctions.stdDev(values, MovingFunctions.unweightedAvg(values))`.
+    pub(super) fn parse(source: &str) -> Result<MovingFunction, String> {
+        let compact_source: String = source.chars().filter(|c| !c.is_whitespace()).collect();
+        let moving_function = match compact_source.as_str() {
+            "MovingFunctions.max(values)" => MovingFunction::Max,
+            "MovingFunctions.min(values)" => MovingFunction::Min,
+            "MovingFunctions.sum(values)" => MovingFunction::Sum,
+            "MovingFunctions.unweightedAvg(values)" => MovingFunction::UnweightedAvg,
+            "MovingFunctions.linearWeightedAvg(values)" => MovingFunction::LinearWeightedAvg,
+            "MovingFunctions.stdDev(values,MovingFunctions.unweightedAvg(values))" => {
+                MovingFunction::StdDev
+            }
+            _ => {
+                return Err(format!(
+                    "unsupported moving_fn script `{source}`, expected one of \
+                     `MovingFunctions.{{max,min,sum,unweightedAvg,linearWeightedAvg}}(values)` or \
+                     `MovingFunctions.stdDev(values, MovingFunctions.unweightedAvg(values))`"
+                ));
+            }
+        };
+        Ok(moving_function)
+    }
+
+    /// Applies the function to the values of a window. Like Elasticsearch, `NaN` values are
+    /// ignored and the result is `NaN` when there is nothing to compute it from, except for `sum`
+    /// which returns 0.
+    pub(super) fn apply(&self, values: &[f64]) -> f64 {
+        let values = values.iter().copied().filter(|value| !value.is_nan());
+
+        match self {
+            MovingFunction::Max => values.reduce(f64::max).unwrap_or(f64::NAN),
+            MovingFunction::Min => values.reduce(f64::min).unwrap_or(f64::NAN),
+            MovingFunction::Sum => values.sum(),
+            MovingFunction::UnweightedAvg => unweighted_avg(values),
+            MovingFunction::LinearWeightedAvg => {
+                let mut weighted_sum = 0.0;
+                let mut total_weight = 0.0;
+
+                for (weight, value) in (1..).zip(values) {
+                    weighted_sum += value * weight as f64;
+                    total_weight += weight as f64;
+                }
+                if total_weight == 0.0 {
+                    return f64::NAN;
+                }
+                weighted_sum / total_weight
+            }
+            MovingFunction::StdDev => {
+                let values: Vec<f64> = values.collect();
+                if values.is_empty() {
+                    return f64::NAN;
+                }
+                let avg = unweighted_avg(values.iter().copied());
+                let variance = values
+                    .iter()
+                    .map(|value| (value - avg) * (value - avg))
+                    .sum::<f64>()
+                    / values.len() as f64;
+                variance.sqrt()
+            }
+        }
+    }
+}
+
+fn unweighted_avg(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), value| {
+        (sum + value, count + 1)
+    });
+    if count == 0 {
+        return f64::NAN;
+    }
+    sum / count as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, params: &[(&str, f64)]) -> ExprValue {
+        let params: BTreeMap<String, f64> = params
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+        Expr::parse(source).unwrap().eval(&params).unwrap()
+    }
+
+    #[test]
+    fn test_expr_arithmetic() {
+        assert_eq!(
+            eval("params.a / params.b * 100", &[("a", 1.0), ("b", 4.0)]),
+            ExprValue::Number(25.0)
+        );
+        assert_eq!(eval("1 + 2 * 3", &[]), ExprValue::Number(7.0));
+        assert_eq!(eval("(1 + 2) * 3", &[]), ExprValue::Number(9.0));
+        assert_eq!(eval("-2.5 + 1", &[]), ExprValue::Number(-1.5));
+        assert_eq!(eval("7 % 4", &[]), ExprValue::Number(3.0));
+    }
+
+    #[test]
+    fn test_expr_boolean() {
+        assert_eq!(
+            eval(
+                "params.total > 200 && params.count <= 3",
+                &[("total", 250.0), ("count", 3.0)]
+            ),
+            ExprValue::Bool(true)
+        );
+        assert_eq!(
+            eval(
+                "params.total < 200 || !(params.count == 3)",
+                &[("total", 250.0), ("count", 3.0)]
+            ),
+            ExprValue::Bool(false)
+        );
+        assert_eq!(eval("1 != 2", &[]), ExprValue::Bool(true));
+    }
+
+    #[test]
+    fn test_expr_params() {
+        let expr = Expr::parse("params.a + params.b / params.a").unwrap();
+        assert_eq!(expr.params(), vec!["a", "b", "a"]);
+    }
+
+    #[test]
+    fn test_expr_invalid() {
+        Expr::parse("params.a +").unwrap_err();
+        Expr::parse("params.a + (1").unwrap_err();
+        Expr::parse("params").unwrap_err();
+        Expr::parse("params.a $ 2").unwrap_err();
+        Expr::parse("doc['field'].value").unwrap_err();
+
+        let params = BTreeMap::from([("a".to_string(), 1.0)]);
+        Expr::parse("params.a && true")
+            .unwrap()
+            .eval(&params)
+            .unwrap_err();
+        Expr::parse("params.b").unwrap().eval(&params).unwrap_err();
+    }
+
+    #[test]
+    fn test_script_source() {
+        let script: Script = serde_json::from_str(r#""return params.a * 2;""#).unwrap();
+        assert_eq!(script.source().unwrap(), "params.a * 2");
+
+        let script: Script =
+            serde_json::from_str(r#"{"inline": "params.a * 2", "lang": "painless"}"#).unwrap();
+        assert_eq!(script.source().unwrap(), "params.a * 2");
+
+        let script: Script =
+            serde_json::from_str(r#"{"source": "params.a * 2", "lang": "mustache"}"#).unwrap();
+        script.source().unwrap_err();
+    }
+
+    #[test]
+    fn test_moving_function() {
+        let values = [1.0, f64::NAN, 3.0, 5.0];
+        let moving_function = |source: &str| MovingFunction::parse(source).unwrap();
+
+        assert_eq!(
+            moving_function("MovingFunctions.max(values)").apply(&values),
+            5.0
+        );
+        assert_eq!(
+            moving_function("MovingFunctions.min(values)").apply(&values),
+            1.0
+        );
+        assert_eq!(
+            moving_function("MovingFunctions.sum(values)").apply(&values),
+            9.0
+        );
+        assert_eq!(
+            moving_function("MovingFunctions.unweightedAvg(values)").apply(&values),
+            3.0
+        );
+        // (1 * 1 + 3 * 2 + 5 * 3) / (1 + 2 + 3)
+        assert_eq!(
+            moving_function("MovingFunctions.linearWeightedAvg(values)").apply(&values),
+            22.0 / 6.0
+        );
+        let std_dev = moving_function(
+            "MovingFunctions.stdDev(values, MovingFunctions.unweightedAvg(values))",
+        )
+        .apply(&values);
+        assert!((std_dev - (8.0f64 / 3.0).sqrt()).abs() < 1e-9);
+
+        assert!(
+            moving_function("MovingFunctions.max(values)")
+                .apply(&[])
+                .is_nan()
+        );
+        assert_eq!(
+            moving_function("MovingFunctions.sum(values)").apply(&[]),
+            0.0
+        );
+        MovingFunction::parse("MovingFunctions.ewma(values, 0.3)").unwrap_err();
+    }
+}
@@ -96,8 +96,10 @@ mod tests {
     #[test]
     fn test_should_not_retry_if_result_is_ok() {
         let retry_policy = DefaultRetryPolicy {};
-        let response_res =
-            crate::Result::<FetchDocsResponse>::Ok(FetchDocsResponse { hits: Vec::new() });
+        let response_res = crate::Result::<FetchDocsResponse>::Ok(FetchDocsResponse {
+            hits: Vec::new(),
+            split_profiles: Vec::new(),
+        });
         assert!(retry_policy.retry_request((), &response_res).is_none());
     }
 
@@ -12,7 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::OnceLock;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
@@ -23,17 +24,18 @@ use itertools::Itertools;
 use quickwit_common::pretty::PrettySample;
 use quickwit_common::shared_consts;
 use quickwit_common::uri::Uri;
-use quickwit_config::build_doc_mapper;
-use quickwit_doc_mapper::DYNAMIC_FIELD_NAME;
+use quickwit_config::{DedupKey, DedupSettings, build_doc_mapper};
 use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
+use quickwit_doc_mapper::{DYNAMIC_FIELD_NAME, JsonObject};
 use quickwit_metastore::{IndexMetadata, ListIndexesMetadataResponseExt, SplitMetadata};
 use quickwit_proto::metastore::{
     ListIndexesMetadataRequest, MetastoreService, MetastoreServiceClient,
 };
 use quickwit_proto::search::{
-    FetchDocsRequest, FetchDocsResponse, Hit, LeafHit, LeafRequestRef, LeafSearchRequest,
-    LeafSearchResponse, PartialHit, SearchPlanResponse, SearchRequest, SearchResponse,
-    SnippetRequest, SortDatetimeFormat, SortField, SortValue, SplitIdAndFooterOffsets,
+    FetchDocsRequest, FetchDocsResponse, Hit, LeafHit, LeafNodeSearchProfile, LeafRequestRef,
+    LeafSearchRequest, LeafSearchResponse, PartialHit, SearchPlanResponse, SearchProfile,
+    SearchRequest, SearchResponse, SnippetRequest, SortDatetimeFormat, SortField, SortValue,
+    SplitFetchDocsProfile, SplitIdAndFooterOffsets, SplitSearchProfile,
 };
 use quickwit_proto::types::{IndexUid, SplitId};
 use quickwit_query::query_ast::{
@@ -49,11 +51,17 @@ use tracing::{debug, info, info_span, instrument};
 
 use crate::cluster_client::ClusterClient;
 use crate::collector::{QuickwitAggregations, make_merge_collector};
+use crate::composite_collector::{CompositeBucket, CompositeCollector};
+use crate::knn::{KnnQuery, root_knn_search};
 use crate::metrics_trackers::{RootSearchMetricsFuture, RootSearchMetricsStep};
+use crate::pipeline_aggregations::PipelineAggregations;
+use crate::runtime_fields::RuntimeFieldsPlan;
 use crate::scroll_context::{ScrollContext, ScrollKeyAndStartOffset};
 use crate::search_job_placer::{Job, group_by, group_jobs_by_index_id};
 use crate::search_response_rest::StorageRequestCount;
 use crate::service::SearcherContext;
+use crate::slow_query_log::CompletedRootSearch;
+use crate::two_phase_terms::TwoPhaseTermsAggregations;
 use crate::{
     SearchError, SearchJobPlacer, SearchPlanResponseRest, SearchServiceClient,
     extract_split_and_footer_offsets, list_relevant_splits,
@@ -157,6 +165,9 @@ pub struct IndexMetasForLeafSearch {
     pub index_uri: Uri,
     /// Doc mapper json string.
     pub doc_mapper_str: String,
+    /// Dedup settings of the index, used to drop duplicate hits.
+    #[serde(default)]
+    pub dedup_settings_opt: Option<DedupSettings>,
 }
 
 pub(crate) type IndexesMetasForLeafSearch = HashMap<IndexUid, IndexMetasForLeafSearch>;
@@ -167,6 +178,8 @@ struct RequestMetadata {
     query_ast_resolved: QueryAst,
     indexes_meta_for_leaf_search: IndexesMetasForLeafSearch,
     sort_fields_is_datetime: HashMap<String, bool>,
+    runtime_fields_plan_opt: Option<RuntimeFieldsPlan>,
+    knn_query_opt: Option<KnnQuery>,
 }
 
 /// Validates request against each index's doc mapper and ensures that:
@@ -174,9 +187,12 @@ struct RequestMetadata {
 /// - resolved query ASTs are the same across indexes.
 /// - if a sort field is of type datetime, it must be a datetime field on all indexes. This
 ///   constraint come from the need to support datetime formatting on sort values.
+/// - the kNN query (if any) targets a dense vector field and its resolved filter is the same across
+///   indexes.
 ///
-/// Returns the timestamp field, the resolved query AST and the indexes metadatas
-/// needed for leaf search requests.
+/// Returns the timestamp field, the resolved query AST stripped of its filters on runtime fields,
+/// the indexes metadatas needed for leaf search requests, the use of runtime fields and the kNN
+/// query with its filter resolved, if any.
 /// Note: the requirements on timestamp fields and resolved query ASTs can be lifted
 /// but it adds complexity that does not seem needed right now.
 fn validate_request_and_build_metadata(
@@ -189,11 +205,26 @@ fn validate_request_and_build_metadata(
     )?;
     let query_ast: QueryAst = serde_json::from_str(&search_request.query_ast)
         .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+    let mut knn_query_opt = search_request
+        .knn
+        .as_deref()
+        .map(KnnQuery::parse)
+        .transpose()?;
+    if let Some(knn_query) = &knn_query_opt {
+        knn_query.validate(search_request)?;
+    }
+    let mut runtime_fields_plan = RuntimeFieldsPlan::for_request(indexes_metadata, search_request)?;
+    // Sorts and aggregations on runtime fields are not validated against the doc mappers.
+    let search_request = runtime_fields_plan.without_runtime_fields(search_request);
+    let search_request = search_request.as_ref();
+
     let mut indexes_meta_for_leaf_search: HashMap<IndexUid, IndexMetasForLeafSearch> =
         HashMap::new();
     let mut query_ast_resolved_opt: Option<QueryAst> = None;
+    let mut runtime_field_filters = Vec::new();
     let mut timestamp_field_opt: Option<String> = None;
     let mut sort_fields_is_datetime: HashMap<String, bool> = HashMap::new();
+    let mut knn_filter_resolved_opt: Option<Option<QueryAst>> = None;
 
     for index_metadata in indexes_metadata {
         let doc_mapper = build_doc_mapper(
@@ -208,6 +239,8 @@ fn validate_request_and_build_metadata(
             .parse_user_query(doc_mapper.default_search_fields())
             // We convert the error to return a 400 to the user (and not a 500).
             .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+        let (query_ast_resolved_for_index, runtime_field_filters_for_index) =
+            runtime_fields_plan.extract_filters(query_ast_resolved_for_index)?;
 
         // Validate uniqueness of resolved query AST.
         if let Some(query_ast_resolved) = &query_ast_resolved_opt {
@@ -220,6 +253,7 @@ fn validate_request_and_build_metadata(
             }
         } else {
             query_ast_resolved_opt = Some(query_ast_resolved_for_index.clone());
+            runtime_field_filters = runtime_field_filters_for_index;
         }
 
         // Validate uniqueness of timestamp field if any.
@@ -256,11 +290,27 @@ fn validate_request_and_build_metadata(
             None,
         )?;
 
+        // Validate the kNN query and the uniqueness of its resolved filter.
+        if let Some(knn_query) = &knn_query_opt {
+            let knn_filter_resolved_for_index = knn_query.validate_for_index(&doc_mapper)?;
+
+            if let Some(knn_filter_resolved) = &knn_filter_resolved_opt {
+                if knn_filter_resolved != &knn_filter_resolved_for_index {
+                    return Err(SearchError::InvalidQuery(
+                        "resolved kNN filters must be the same across indexes".to_string(),
+                    ));
+                }
+            } else {
+                knn_filter_resolved_opt = Some(knn_filter_resolved_for_index);
+            }
+        }
+
         let index_metadata_for_leaf_search = IndexMetasForLeafSearch {
             index_uri: index_metadata.index_uri().clone(),
             doc_mapper_str: serde_json::to_string(&doc_mapper).map_err(|err| {
                 SearchError::Internal(format!("failed to serialize doc mapper. cause: {err}"))
             })?,
+            dedup_settings_opt: index_metadata.index_config.indexing_settings.dedup.clone(),
         };
         indexes_meta_for_leaf_search.insert(
             index_metadata.index_uid.clone(),
@@ -274,11 +324,29 @@ fn validate_request_and_build_metadata(
         )
     })?;
 
+    runtime_fields_plan.set_filters(runtime_field_filters);
+    let runtime_fields_plan_opt = runtime_fields_plan.into_used()?;
+
+    if let Some(knn_query) = &mut knn_query_opt {
+        if runtime_fields_plan_opt
+            .as_ref()
+            .is_some_and(RuntimeFieldsPlan::requires_scan)
+        {
+            return Err(SearchError::InvalidArgument(
+                "kNN search does not support filters, sorts and aggregations on runtime fields"
+                    .to_string(),
+            ));
+        }
+        knn_query.filter = knn_filter_resolved_opt.flatten();
+    }
+
     Ok(RequestMetadata {
         timestamp_field_opt,
         query_ast_resolved,
         indexes_meta_for_leaf_search,
         sort_fields_is_datetime,
+        runtime_fields_plan_opt,
+        knn_query_opt,
     })
 }
 
@@ -372,6 +440,10 @@ fn simplify_search_request_for_scroll_api(req: &SearchRequest) -> crate::Result<
         // to recompute it afterward.
         count_hits: quickwit_proto::search::CountHits::Underestimate as i32,
         ignore_missing_indexes: req.ignore_missing_indexes,
+        profile: false,
+        runtime_fields: None,
+        docvalue_fields: Vec::new(),
+        knn: None,
     })
 }
 
@@ -516,9 +588,15 @@ fn validate_request(
 
     if let Some(agg) = search_request.aggregation_request.as_ref() {
         let aggs: QuickwitAggregations = serde_json::from_str(agg).map_err(|_err| {
-            let err = serde_json::from_str::<tantivy::aggregation::agg_req::Aggregations>(agg)
-                .unwrap_err();
-            SearchError::InvalidAggregationRequest(err.to_string())
+            // The error of an untagged enum is not helpful: we report the error of the variant
+            // the request was meant for.
+            let err = if is_composite_aggregation_request(agg) {
+                serde_json::from_str::<CompositeCollector>(agg).err()
+            } else {
+                serde_json::from_str::<tantivy::aggregation::agg_req::Aggregations>(agg).err()
+            };
+            let err_msg = err.map(|err| err.to_string()).unwrap_or_default();
+            SearchError::InvalidAggregationRequest(err_msg)
         })?;
 
         // ensure that the required fast fields are indeed configured as fast fields.
@@ -546,6 +624,16 @@ fn validate_request(
     Ok(())
 }
 
+fn is_composite_aggregation_request(aggregation_request: &str) -> bool {
+    let Ok(serde_json::Value::Object(aggregations)) = serde_json::from_str(aggregation_request)
+    else {
+        return false;
+    };
+    aggregations
+        .values()
+        .any(|aggregation| aggregation.get("composite").is_some())
+}
+
 fn get_scroll_ttl_duration(search_request: &SearchRequest) -> crate::Result<Option<Duration>> {
     let Some(scroll_ttl_secs) = search_request.scroll_ttl_secs else {
         return Ok(None);
@@ -687,6 +775,8 @@ pub fn get_count_from_metadata(split_metadatas: &[SplitMetadata]) -> Vec<LeafSea
             num_successful_splits: 1,
             intermediate_aggregation_result: None,
             resource_stats: None,
+            slowest_splits: Vec::new(),
+            split_profiles: Vec::new(),
         })
         .collect()
 }
@@ -822,7 +912,7 @@ pub(crate) async fn fetch_docs_phase(
     split_metadatas: &[SplitMetadata],
     search_request: &SearchRequest,
     cluster_client: &ClusterClient,
-) -> crate::Result<Vec<Hit>> {
+) -> crate::Result<(Vec<Hit>, Vec<SplitFetchDocsProfile>)> {
     let snippet_request: Option<SnippetRequest> = get_snippet_request(search_request);
     let hit_order: HashMap<(String, u32, u32), usize> = partial_hits
         .iter()
@@ -851,16 +941,21 @@ pub(crate) async fn fetch_docs_phase(
             indexes_metas_for_leaf_search,
             client_jobs,
         )?;
-        for fetch_docs_request in fetch_jobs_requests {
+        for mut fetch_docs_request in fetch_jobs_requests {
+            fetch_docs_request.profile = search_request.profile;
             fetch_docs_tasks.push(cluster_client.fetch_docs(fetch_docs_request, client.clone()));
         }
     }
     let fetch_docs_responses: Vec<FetchDocsResponse> = try_join_all(fetch_docs_tasks).await?;
 
     // Merge the fetched docs.
-    let leaf_hits = fetch_docs_responses
-        .into_iter()
-        .flat_map(|response| response.hits.into_iter());
+    let mut split_fetch_docs_profiles = Vec::new();
+    let mut leaf_hits = Vec::new();
+
+    for fetch_docs_response in fetch_docs_responses {
+        leaf_hits.extend(fetch_docs_response.hits);
+        split_fetch_docs_profiles.extend(fetch_docs_response.split_profiles);
+    }
 
     // Build map of Split ID > index ID to add the index ID to the hits.
     // Used for ES compatibility.
@@ -879,6 +974,7 @@ pub(crate) async fn fetch_docs_phase(
     let sort_field_2_datetime_format_opt: Option<SortDatetimeFormat> =
         get_sort_field_datetime_format(sort_field_iter.next())?;
     let mut hits_with_position: Vec<(usize, Hit)> = leaf_hits
+        .into_iter()
         .map(|leaf_hit| {
             build_hit_with_position(
                 leaf_hit,
@@ -896,7 +992,7 @@ pub(crate) async fn fetch_docs_phase(
         .map(|(_position, hit)| hit)
         .collect();
 
-    Ok(hits)
+    Ok((hits, split_fetch_docs_profiles))
 }
 
 fn build_hit_with_position(
@@ -946,10 +1042,49 @@ fn build_hit_with_position(
             partial_hit: leaf_hit.partial_hit,
             snippet: leaf_hit.leaf_snippet_json,
             index_id,
+            fields_json: None,
         },
     ))
 }
 
+/// Drops the hits sharing their dedup key with a better ranked hit of the same index, for the
+/// indexes deduplicating documents by a field.
+///
+/// The indexes deduplicating whole documents are skipped: the stored document of a hit differs
+/// from the document received at indexing time, so their hashes cannot be compared.
+///
+/// This is best-effort: only the hits of the requested page are compared, so the page can come
+/// out shorter than requested, and the number of hits and the aggregations still count duplicates.
+fn dedup_hits(indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch, hits: &mut Vec<Hit>) {
+    let dedup_settings_per_index: HashMap<&str, &DedupSettings> = indexes_metas_for_leaf_search
+        .iter()
+        .filter_map(|(index_uid, index_metas)| {
+            let dedup_settings = index_metas
+                .dedup_settings_opt
+                .as_ref()
+                .filter(|dedup_settings| dedup_settings.field.is_some())?;
+            Some((index_uid.index_id.as_str(), dedup_settings))
+        })
+        .collect();
+    if dedup_settings_per_index.is_empty() {
+        return;
+    }
+    let mut seen_dedup_keys: HashSet<(String, DedupKey)> = HashSet::new();
+
+    hits.retain(|hit| {
+        let Some(dedup_settings) = dedup_settings_per_index.get(hit.index_id.as_str()) else {
+            return true;
+        };
+        let Ok(json_obj) = serde_json::from_str::<JsonObject>(&hit.json) else {
+            return true;
+        };
+        let Some(dedup_key) = dedup_settings.dedup_key(&json_obj) else {
+            return true;
+        };
+        seen_dedup_keys.insert((hit.index_id.clone(), dedup_key))
+    });
+}
+
 fn get_sort_field_datetime_format(
     sort_field: Option<&SortField>,
 ) -> crate::Result<Option<SortDatetimeFormat>> {
@@ -969,15 +1104,17 @@ fn get_sort_field_datetime_format(
 /// 2. Merges the search results.
 /// 3. Sends fetch docs requests to multiple leaf nodes.
 /// 4. Builds the response with docs and returns.
-async fn root_search_aux(
+pub(crate) async fn root_search_aux(
     searcher_context: &SearcherContext,
     indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
     search_request: SearchRequest,
     split_metadatas: Vec<SplitMetadata>,
     cluster_client: &ClusterClient,
+    start_instant: Instant,
 ) -> crate::Result<SearchResponse> {
     debug!(split_metadatas = ?PrettySample::new(&split_metadatas, 5));
-    let (first_phase_result, scroll_key_and_start_offset_opt): (
+    let plan_duration = start_instant.elapsed();
+    let (mut first_phase_result, scroll_key_and_start_offset_opt): (
         LeafSearchResponse,
         Option<ScrollKeyAndStartOffset>,
     ) = search_partial_hits_phase_with_scroll(
@@ -989,7 +1126,10 @@ async fn root_search_aux(
     )
     .await?;
 
-    let hits = fetch_docs_phase(
+    let leaf_search_duration = start_instant.elapsed() - plan_duration;
+
+    let fetch_docs_start = Instant::now();
+    let (mut hits, split_fetch_docs_profiles) = fetch_docs_phase(
         indexes_metas_for_leaf_search,
         &first_phase_result.partial_hits,
         &split_metadatas[..],
@@ -997,6 +1137,32 @@ async fn root_search_aux(
         cluster_client,
     )
     .await?;
+    let fetch_docs_duration = fetch_docs_start.elapsed();
+    dedup_hits(indexes_metas_for_leaf_search, &mut hits);
+
+    searcher_context
+        .slow_query_logger
+        .log_if_slow(CompletedRootSearch {
+            search_request: &search_request,
+            leaf_search_response: &first_phase_result,
+            num_splits: split_metadatas.len(),
+            num_docs: split_metadatas.iter().map(|split| split.num_docs).sum(),
+            elapsed: start_instant.elapsed(),
+            fetch_docs_elapsed: fetch_docs_duration,
+        });
+
+    let profile_opt = if search_request.profile {
+        let search_profile = build_search_profile(
+            plan_duration,
+            leaf_search_duration,
+            fetch_docs_duration,
+            std::mem::take(&mut first_phase_result.split_profiles),
+            split_fetch_docs_profiles,
+        );
+        Some(search_profile)
+    } else {
+        None
+    };
 
     let mut aggregation_result_postcard_opt = finalize_aggregation_if_any(
         &search_request,
@@ -1019,9 +1185,64 @@ async fn root_search_aux(
             .map(ToString::to_string),
         failed_splits: first_phase_result.failed_splits,
         num_successful_splits: first_phase_result.num_successful_splits,
+        profile: profile_opt,
+        warnings: Vec::new(),
     })
 }
 
+/// Builds the profile tree of a search: the root phases, the leaf nodes, and the splits
+/// searched by each of them. Doc fetch timings are attached to the splits they were
+/// fetched from.
+fn build_search_profile(
+    plan_duration: Duration,
+    leaf_search_duration: Duration,
+    fetch_docs_duration: Duration,
+    split_profiles: Vec<SplitSearchProfile>,
+    split_fetch_docs_profiles: Vec<SplitFetchDocsProfile>,
+) -> SearchProfile {
+    let mut split_fetch_docs_profiles: HashMap<String, SplitFetchDocsProfile> =
+        split_fetch_docs_profiles
+            .into_iter()
+            .map(|split_fetch_docs_profile| {
+                (
+                    split_fetch_docs_profile.split_id.clone(),
+                    split_fetch_docs_profile,
+                )
+            })
+            .collect();
+    let mut split_profiles_per_leaf_node: BTreeMap<String, Vec<SplitSearchProfile>> =
+        BTreeMap::new();
+
+    for mut split_profile in split_profiles {
+        if let Some(split_fetch_docs_profile) =
+            split_fetch_docs_profiles.remove(&split_profile.split_id)
+        {
+            split_profile.fetch_docs_microsecs = split_fetch_docs_profile.elapsed_microsecs;
+            split_profile.num_fetched_docs = split_fetch_docs_profile.num_docs;
+        }
+        split_profiles_per_leaf_node
+            .entry(split_profile.leaf_node_id.clone())
+            .or_default()
+            .push(split_profile);
+    }
+    let leaf_nodes = split_profiles_per_leaf_node
+        .into_iter()
+        .map(|(leaf_node_id, mut splits)| {
+            splits.sort_unstable_by_key(|split_profile| Reverse(split_profile.elapsed_microsecs));
+            LeafNodeSearchProfile {
+                leaf_node_id,
+                splits,
+            }
+        })
+        .collect();
+    SearchProfile {
+        plan_microsecs: plan_duration.as_micros() as u64,
+        leaf_search_microsecs: leaf_search_duration.as_micros() as u64,
+        fetch_docs_microsecs: fetch_docs_duration.as_micros() as u64,
+        leaf_nodes,
+    }
+}
+
 fn finalize_aggregation(
     intermediate_aggregation_result_bytes_opt: Option<Vec<u8>>,
     aggregations: QuickwitAggregations,
@@ -1032,6 +1253,17 @@ fn finalize_aggregation(
             // The merge collector has already merged the intermediate results.
             return Ok(intermediate_aggregation_result_bytes_opt);
         }
+        QuickwitAggregations::CompositeAggregation(collector) => {
+            let buckets: Vec<CompositeBucket> = if let Some(intermediate_aggregation_result_bytes) =
+                intermediate_aggregation_result_bytes_opt
+            {
+                postcard::from_bytes(&intermediate_aggregation_result_bytes)?
+            } else {
+                Vec::new()
+            };
+            let final_aggregation_proxy = collector.into_aggregation_results(buckets);
+            postcard::to_stdvec(&final_aggregation_proxy)?
+        }
         QuickwitAggregations::TantivyAggregations(aggregations) => {
             let intermediate_aggregation_results =
                 if let Some(intermediate_aggregation_result_bytes) =
@@ -1054,7 +1286,7 @@ fn finalize_aggregation(
     Ok(Some(merge_aggregation_result))
 }
 
-fn finalize_aggregation_if_any(
+pub(crate) fn finalize_aggregation_if_any(
     search_request: &SearchRequest,
     intermediate_aggregation_result_bytes_opt: Option<Vec<u8>>,
     searcher_context: &SearcherContext,
@@ -1152,11 +1384,16 @@ async fn refine_and_list_matches(
     Ok(split_metadatas)
 }
 
-/// Fetches the list of splits and their metadata from the metastore
-async fn plan_splits_for_root_search(
+/// Fetches the list of splits and their metadata from the metastore, along with the use of runtime
+/// fields by the request, if any.
+pub(crate) async fn plan_splits_for_root_search(
     search_request: &mut SearchRequest,
     metastore: &mut MetastoreServiceClient,
-) -> crate::Result<(Vec<SplitMetadata>, IndexesMetasForLeafSearch)> {
+) -> crate::Result<(
+    Vec<SplitMetadata>,
+    IndexesMetasForLeafSearch,
+    Option<RuntimeFieldsPlan>,
+)> {
     let list_indexes_metadatas_request = ListIndexesMetadataRequest {
         index_id_patterns: search_request.index_id_patterns.clone(),
     };
@@ -1174,22 +1411,36 @@ async fn plan_splits_for_root_search(
     }
 
     if indexes_metadata.is_empty() {
-        return Ok((Vec::new(), HashMap::default()));
+        return Ok((Vec::new(), HashMap::default(), None));
     }
 
     let request_metadata = validate_request_and_build_metadata(&indexes_metadata, search_request)?;
+    // Splits are pruned with a query matching the documents of both the query and the kNN search.
+    let query_ast_for_split_pruning = if let Some(knn_query) = &request_metadata.knn_query_opt {
+        knn_query.query_ast_for_split_pruning(
+            &request_metadata.query_ast_resolved,
+            search_request.aggregation_request.is_some(),
+        )
+    } else {
+        request_metadata.query_ast_resolved.clone()
+    };
     let split_metadatas = refine_and_list_matches(
         metastore,
         search_request,
         indexes_metadata,
-        request_metadata.query_ast_resolved,
+        query_ast_for_split_pruning,
         request_metadata.sort_fields_is_datetime,
         request_metadata.timestamp_field_opt,
     )
     .await?;
+    if let Some(knn_query) = &request_metadata.knn_query_opt {
+        search_request.query_ast = serde_json::to_string(&request_metadata.query_ast_resolved)?;
+        search_request.knn = Some(serde_json::to_string(knn_query)?);
+    }
     Ok((
         split_metadatas,
         request_metadata.indexes_meta_for_leaf_search,
+        request_metadata.runtime_fields_plan_opt,
     ))
 }
 
@@ -1207,13 +1458,18 @@ pub async fn root_search(
 ) -> crate::Result<SearchResponse> {
     let start_instant = Instant::now();
 
-    let (split_metadatas, indexes_meta_for_leaf_search) = RootSearchMetricsFuture {
-        start: start_instant,
-        tracked: plan_splits_for_root_search(&mut search_request, &mut metastore),
-        is_success: None,
-        step: RootSearchMetricsStep::Plan,
-    }
-    .await?;
+    let pipeline_aggregations_opt =
+        PipelineAggregations::extract_from_request(&mut search_request)?;
+    let two_phase_terms_opt = TwoPhaseTermsAggregations::extract_from_request(&mut search_request)?;
+
+    let (split_metadatas, indexes_meta_for_leaf_search, runtime_fields_plan_opt) =
+        RootSearchMetricsFuture {
+            start: start_instant,
+            tracked: plan_splits_for_root_search(&mut search_request, &mut metastore),
+            is_success: None,
+            step: RootSearchMetricsStep::Plan,
+        }
+        .await?;
 
     let num_docs: usize = split_metadatas.iter().map(|split| split.num_docs).sum();
     let num_splits = split_metadatas.len();
@@ -1230,6 +1486,106 @@ pub async fn root_search(
         "root_search"
     );
 
+    check_num_targeted_splits(searcher_context, &search_request, num_splits)?;
+
+    let mut search_response_result = match runtime_fields_plan_opt {
+        Some(runtime_fields_plan) if runtime_fields_plan.requires_scan() => {
+            RootSearchMetricsFuture {
+                start: start_instant,
+                tracked: runtime_fields_plan.search(
+                    search_request,
+                    split_metadatas,
+                    indexes_meta_for_leaf_search,
+                    cluster_client.clone(),
+                ),
+                is_success: None,
+                step: RootSearchMetricsStep::Exec {
+                    num_targeted_splits: num_splits,
+                },
+            }
+            .await
+        }
+        runtime_fields_plan_opt => {
+            let knn_query_opt = search_request
+                .knn
+                .as_deref()
+                .map(KnnQuery::parse)
+                .transpose()?;
+            let search_future = async {
+                if let Some(knn_query) = knn_query_opt {
+                    root_knn_search(
+                        searcher_context,
+                        &indexes_meta_for_leaf_search,
+                        search_request,
+                        knn_query,
+                        split_metadatas,
+                        cluster_client,
+                        start_instant,
+                    )
+                    .await
+                } else {
+                    root_search_aux(
+                        searcher_context,
+                        &indexes_meta_for_leaf_search,
+                        search_request,
+                        split_metadatas,
+                        cluster_client,
+                        start_instant,
+                    )
+                    .await
+                }
+            };
+            let mut search_response_result = RootSearchMetricsFuture {
+                start: start_instant,
+                tracked: search_future,
+                is_success: None,
+                step: RootSearchMetricsStep::Exec {
+                    num_targeted_splits: num_splits,
+                },
+            }
+            .await;
+
+            // The request only returns runtime fields, which are evaluated on its hits.
+            if let Some(runtime_fields_plan) = runtime_fields_plan_opt
+                && let Ok(search_response) = &mut search_response_result
+            {
+                runtime_fields_plan.add_docvalue_fields(&mut search_response.hits)?;
+            }
+            search_response_result
+        }
+    };
+
+    if let Some(two_phase_terms) = &two_phase_terms_opt
+        && let Ok(search_response) = &mut search_response_result
+    {
+        two_phase_terms
+            .compute(
+                searcher_context,
+                &metastore,
+                cluster_client,
+                search_response,
+            )
+            .await?;
+    }
+    if let Some(pipeline_aggregations) = &pipeline_aggregations_opt
+        && let Ok(search_response) = &mut search_response_result
+    {
+        pipeline_aggregations.apply(search_response)?;
+    }
+    if let Ok(search_response) = &mut search_response_result {
+        search_response.elapsed_time_micros = start_instant.elapsed().as_micros() as u64;
+    }
+
+    search_response_result
+}
+
+/// Checks that the number of splits targeted by a search does not exceed the
+/// `max_splits_per_search` limit of the searcher.
+pub(crate) fn check_num_targeted_splits(
+    searcher_context: &SearcherContext,
+    search_request: &SearchRequest,
+    num_splits: usize,
+) -> crate::Result<()> {
     if let Some(max_total_split_searches) = searcher_context.searcher_config.max_splits_per_search
         && max_total_split_searches < num_splits
     {
@@ -1244,28 +1600,7 @@ pub async fn root_search(
             "Number of targeted splits {num_splits} exceeds the limit {max_total_split_searches}"
         )));
     }
-
-    let mut search_response_result = RootSearchMetricsFuture {
-        start: start_instant,
-        tracked: root_search_aux(
-            searcher_context,
-            &indexes_meta_for_leaf_search,
-            search_request,
-            split_metadatas,
-            cluster_client,
-        ),
-        is_success: None,
-        step: RootSearchMetricsStep::Exec {
-            num_targeted_splits: num_splits,
-        },
-    }
-    .await;
-
-    if let Ok(search_response) = &mut search_response_result {
-        search_response.elapsed_time_micros = start_instant.elapsed().as_micros() as u64;
-    }
-
-    search_response_result
+    Ok(())
 }
 
 /// Returns details on how a query would be executed
@@ -1273,6 +1608,11 @@ pub async fn search_plan(
     mut search_request: SearchRequest,
     mut metastore: MetastoreServiceClient,
 ) -> crate::Result<SearchPlanResponse> {
+    // Pipeline aggregations are computed by the root and do not change the plan. Significant and
+    // rare terms aggregations are planned as their first phase.
+    PipelineAggregations::extract_from_request(&mut search_request)?;
+    TwoPhaseTermsAggregations::extract_from_request(&mut search_request)?;
+
     let list_indexes_metadatas_request = ListIndexesMetadataRequest {
         index_id_patterns: search_request.index_id_patterns.clone(),
     };
@@ -1786,6 +2126,7 @@ pub fn jobs_to_fetch_docs_requests(
                 index_uri: index_meta.index_uri.to_string(),
                 snippet_request: snippet_request_opt.clone(),
                 doc_mapper: index_meta.doc_mapper_str.clone(),
+                profile: false,
             };
             fetch_docs_requests.push(fetch_docs_req);
 
@@ -1901,6 +2242,7 @@ mod tests {
         let ingest_settings = IngestSettings::default();
         let search_settings = SearchSettings {
             default_search_fields: vec!["body".to_string()],
+            ..Default::default()
         };
         IndexMetadata::new(IndexConfig {
             index_id: index_id.to_string(),
@@ -2075,6 +2417,7 @@ mod tests {
         let indexing_settings = IndexingSettings::default();
         let search_settings = SearchSettings {
             default_search_fields: vec!["body".to_string()],
+            ..Default::default()
         };
         IndexMetadata::new(IndexConfig {
             index_id: index_id.to_string(),
@@ -2119,6 +2462,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_dedup_hits() {
+        let mut indexes_metas_for_leaf_search = IndexesMetasForLeafSearch::new();
+        indexes_metas_for_leaf_search.insert(
+            IndexUid::for_test("index-dedup", 0),
+            IndexMetasForLeafSearch {
+                index_uri: Uri::for_test("ram:///indexes/index-dedup"),
+                doc_mapper_str: String::new(),
+                dedup_settings_opt: Some(DedupSettings {
+                    field: Some("id".to_string()),
+                    ..Default::default()
+                }),
+            },
+        );
+        indexes_metas_for_leaf_search.insert(
+            IndexUid::for_test("index-no-dedup", 0),
+            IndexMetasForLeafSearch {
+                index_uri: Uri::for_test("ram:///indexes/index-no-dedup"),
+                doc_mapper_str: String::new(),
+                dedup_settings_opt: None,
+            },
+        );
+        indexes_metas_for_leaf_search.insert(
+            IndexUid::for_test("index-dedup-doc", 0),
+            IndexMetasForLeafSearch {
+                index_uri: Uri::for_test("ram:///indexes/index-dedup-doc"),
+                doc_mapper_str: String::new(),
+                dedup_settings_opt: Some(DedupSettings::default()),
+            },
+        );
+        let hit = |index_id: &str, json: &str| Hit {
+            json: json.to_string(),
+            index_id: index_id.to_string(),
+            ..Default::default()
+        };
+        let mut hits = vec![
+            hit("index-dedup", r#"{"id": "a", "body": "first"}"#),
+            hit("index-dedup", r#"{"id": "b"}"#),
+            hit("index-dedup", r#"{"id": "a", "body": "second"}"#),
+            hit("index-dedup", r#"{"body": "no id"}"#),
+            hit("index-dedup", r#"{"body": "no id"}"#),
+            hit("index-no-dedup", r#"{"id": "a"}"#),
+            hit("index-no-dedup", r#"{"id": "a"}"#),
+            hit("index-dedup-doc", r#"{"id": "a"}"#),
+            hit("index-dedup-doc", r#"{"id": "a"}"#),
+        ];
+        dedup_hits(&indexes_metas_for_leaf_search, &mut hits);
+
+        let hits_json: Vec<&str> = hits.iter().map(|hit| hit.json.as_str()).collect();
+        assert_eq!(
+            hits_json,
+            [
+                r#"{"id": "a", "body": "first"}"#,
+                r#"{"id": "b"}"#,
+                r#"{"body": "no id"}"#,
+                r#"{"body": "no id"}"#,
+                r#"{"id": "a"}"#,
+                r#"{"id": "a"}"#,
+                r#"{"id": "a"}"#,
+                r#"{"id": "a"}"#,
+            ]
+        );
+    }
+
     #[test]
     fn test_convert_sort_datetime_value() {
         let mut sort_value = SortValue::U64(1617000000000000000);
@@ -2630,6 +3037,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2652,6 +3060,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2724,6 +3133,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2796,6 +3206,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2815,6 +3226,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2902,6 +3314,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -2990,6 +3403,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3031,6 +3445,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3172,6 +3587,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3213,6 +3629,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3329,6 +3746,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3454,6 +3872,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3481,6 +3900,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3578,6 +3998,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3621,6 +4042,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3705,6 +4127,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3837,6 +4260,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -3929,6 +4353,7 @@ mod tests {
             |fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -4219,6 +4644,7 @@ mod tests {
                         tokenizer: None,
                         mode: FullTextMode::PhraseFallbackToIntersection,
                         zero_terms_query: MatchAllOrNone::MatchNone,
+                        fuzzy: None,
                     },
                     lenient: false,
                 },),
@@ -4637,6 +5063,7 @@ mod tests {
                 assert!(fetch_docs_req.partial_hits.len() <= MAX_HITS_PER_PAGE);
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -4646,6 +5073,7 @@ mod tests {
                 assert!(fetch_docs_req.partial_hits.len() <= MAX_HITS_PER_PAGE);
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -4838,6 +5266,7 @@ mod tests {
                 assert!(fetch_docs_req.partial_hits.len() <= MAX_HITS_PER_PAGE_LARGE);
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -4912,6 +5341,7 @@ mod tests {
                 assert!(fetch_docs_req.partial_hits.len() <= MAX_HITS_PER_PAGE_LARGE);
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             },
         );
@@ -5111,6 +5541,7 @@ mod tests {
             .returning(|fetch_docs_req| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             });
         let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service_1)]);
@@ -5232,6 +5663,7 @@ mod tests {
             .returning(|fetch_docs_req| {
                 Ok(quickwit_proto::search::FetchDocsResponse {
                     hits: get_doc_for_fetch_req(fetch_docs_req),
+                    split_profiles: Vec::new(),
                 })
             });
         let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service_1)]);
@@ -5301,4 +5733,49 @@ mod tests {
         assert!(matches!(search_error, SearchError::InvalidArgument { .. }));
         Ok(())
     }
+
+    #[test]
+    fn test_build_search_profile() {
+        let split_profile =
+            |split_id: &str, leaf_node_id: &str, elapsed_microsecs: u64| SplitSearchProfile {
+                split_id: split_id.to_string(),
+                leaf_node_id: leaf_node_id.to_string(),
+                elapsed_microsecs,
+                ..Default::default()
+            };
+        let split_profiles = vec![
+            split_profile("split1", "127.0.0.1:1002", 1_000),
+            split_profile("split2", "127.0.0.1:1001", 2_000),
+            split_profile("split3", "127.0.0.1:1002", 3_000),
+        ];
+        let split_fetch_docs_profiles = vec![SplitFetchDocsProfile {
+            split_id: "split1".to_string(),
+            num_docs: 2,
+            elapsed_microsecs: 500,
+        }];
+        let search_profile = build_search_profile(
+            Duration::from_micros(10),
+            Duration::from_micros(3_000),
+            Duration::from_micros(600),
+            split_profiles,
+            split_fetch_docs_profiles,
+        );
+        assert_eq!(search_profile.plan_microsecs, 10);
+        assert_eq!(search_profile.leaf_search_microsecs, 3_000);
+        assert_eq!(search_profile.fetch_docs_microsecs, 600);
+        assert_eq!(search_profile.leaf_nodes.len(), 2);
+
+        let leaf_node_1 = &search_profile.leaf_nodes[0];
+        assert_eq!(leaf_node_1.leaf_node_id, "127.0.0.1:1001");
+        assert_eq!(leaf_node_1.splits.len(), 1);
+        assert_eq!(leaf_node_1.splits[0].fetch_docs_microsecs, 0);
+
+        let leaf_node_2 = &search_profile.leaf_nodes[1];
+        assert_eq!(leaf_node_2.leaf_node_id, "127.0.0.1:1002");
+        assert_eq!(leaf_node_2.splits.len(), 2);
+        assert_eq!(leaf_node_2.splits[0].split_id, "split3");
+        assert_eq!(leaf_node_2.splits[1].split_id, "split1");
+        assert_eq!(leaf_node_2.splits[1].fetch_docs_microsecs, 500);
+        assert_eq!(leaf_node_2.splits[1].num_fetched_docs, 2);
+    }
 }
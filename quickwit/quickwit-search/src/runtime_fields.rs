@@ -0,0 +1,1200 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime fields: fields computed at query time by evaluating a VRL expression on the documents
+//! matching a query, instead of being read from the index.
+//!
+//! Runtime fields are not indexed, so the root evaluates them itself:
+//! - filters on runtime fields are removed from the query and applied to its hits afterwards,
+//! - sorts and terms aggregations on runtime fields are computed over the hits,
+//! - `docvalue_fields` are evaluated on the returned hits only.
+//!
+//! The first three require to fetch every document matching the rest of the query, up to
+//! [`MAX_RUNTIME_FIELDS_SCANNED_DOCS`] documents, which is orders of magnitude slower than a
+//! regular search. The response carries a warning whenever that happens.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+
+use futures::TryStreamExt;
+use quickwit_config::RuntimeFieldConfig;
+use quickwit_metastore::{IndexMetadata, SplitMetadata};
+use quickwit_proto::search::{CountHits, Hit, SearchRequest, SearchResponse, SortField, SortOrder};
+use quickwit_query::JsonLiteral;
+use quickwit_query::aggregations::{
+    AggregationResult, AggregationResults, BucketEntry, BucketResult, Key,
+};
+use quickwit_query::query_ast::{
+    FieldPresenceQuery, FullTextQuery, FuzzyQuery, PhrasePrefixQuery, QueryAst, QueryAstVisitor,
+    RangeQuery, RegexQuery, TermQuery, TermSetQuery, WildcardQuery,
+};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use tracing::warn;
+
+use crate::export::export_planned_splits;
+use crate::root::IndexesMetasForLeafSearch;
+use crate::{ClusterClient, SearchError};
+
+/// Maximum number of documents on which a search request evaluates runtime fields. Beyond that,
+/// the search stops and returns partial results.
+const MAX_RUNTIME_FIELDS_SCANNED_DOCS: u64 = 100_000;
+
+fn default_terms_size() -> u32 {
+    10
+}
+
+/// Describes how a search request uses runtime fields.
+#[derive(Debug)]
+pub(crate) struct RuntimeFieldsPlan {
+    /// The runtime fields available to the request, by field name.
+    runtime_fields: BTreeMap<String, RuntimeFieldConfig>,
+    filters: Vec<RuntimeFieldFilter>,
+    /// Sort fields of the request, if it sorts on runtime fields.
+    sort_fields: Vec<SortField>,
+    terms_aggregations: Vec<RuntimeTermsAggregation>,
+    has_indexed_aggregations: bool,
+    docvalue_fields: Vec<String>,
+}
+
+impl RuntimeFieldsPlan {
+    /// Collects the runtime fields available to a search request, defined by the search settings
+    /// of the targeted indexes or by the request itself, and how the request uses them.
+    pub(crate) fn for_request(
+        indexes_metadata: &[IndexMetadata],
+        search_request: &SearchRequest,
+    ) -> crate::Result<Self> {
+        let mut runtime_fields: BTreeMap<String, RuntimeFieldConfig> = BTreeMap::new();
+
+        for index_metadata in indexes_metadata {
+            let index_runtime_fields = &index_metadata.index_config.search_settings.runtime_fields;
+
+            for (field_name, runtime_field) in index_runtime_fields {
+                if let Some(other_runtime_field) = runtime_fields.get(field_name)
+                    && other_runtime_field != runtime_field
+                {
+                    return Err(SearchError::InvalidArgument(format!(
+                        "runtime field `{field_name}` is defined differently by the targeted \
+                         indexes"
+                    )));
+                }
+                runtime_fields.insert(field_name.clone(), runtime_field.clone());
+            }
+        }
+        if let Some(runtime_fields_json) = &search_request.runtime_fields {
+            let request_runtime_fields: BTreeMap<String, RuntimeFieldConfig> =
+                serde_json::from_str(runtime_fields_json).map_err(|error| {
+                    SearchError::InvalidArgument(format!("invalid runtime fields: {error}"))
+                })?;
+            // Runtime fields defined by the request take precedence.
+            runtime_fields.extend(request_runtime_fields);
+        }
+        let mut runtime_fields_plan = RuntimeFieldsPlan {
+            runtime_fields,
+            filters: Vec::new(),
+            sort_fields: Vec::new(),
+            terms_aggregations: Vec::new(),
+            has_indexed_aggregations: false,
+            docvalue_fields: Vec::new(),
+        };
+        if runtime_fields_plan.runtime_fields.is_empty() {
+            return Ok(runtime_fields_plan);
+        }
+        let num_runtime_sort_fields = search_request
+            .sort_fields
+            .iter()
+            .filter(|sort_field| runtime_fields_plan.is_runtime_field(&sort_field.field_name))
+            .count();
+
+        if num_runtime_sort_fields > 0 {
+            if num_runtime_sort_fields < search_request.sort_fields.len() {
+                return Err(SearchError::InvalidArgument(
+                    "sorting on both runtime fields and indexed fields is not supported"
+                        .to_string(),
+                ));
+            }
+            runtime_fields_plan.sort_fields = search_request.sort_fields.clone();
+        }
+        if let Some(aggregation_request) = &search_request.aggregation_request {
+            runtime_fields_plan.parse_aggregations(aggregation_request)?;
+        }
+        runtime_fields_plan.docvalue_fields = search_request
+            .docvalue_fields
+            .iter()
+            .filter(|field_name| runtime_fields_plan.is_runtime_field(field_name))
+            .cloned()
+            .collect();
+        Ok(runtime_fields_plan)
+    }
+
+    fn is_runtime_field(&self, field_name: &str) -> bool {
+        self.runtime_fields.contains_key(field_name)
+    }
+
+    fn parse_aggregations(&mut self, aggregation_request: &str) -> crate::Result<()> {
+        // Aggregations that are not a JSON object, such as the find trace IDs aggregation, cannot
+        // target runtime fields.
+        let Ok(aggregations) =
+            serde_json::from_str::<BTreeMap<String, JsonValue>>(aggregation_request)
+        else {
+            self.has_indexed_aggregations = true;
+            return Ok(());
+        };
+        for (aggregation_name, aggregation) in aggregations {
+            let Some(field_name) = self.runtime_field_of_aggregation(&aggregation)? else {
+                self.has_indexed_aggregations = true;
+                continue;
+            };
+            let RuntimeTermsAggregationRequest { terms } = serde_json::from_value(aggregation)
+                .map_err(|error| {
+                    SearchError::InvalidAggregationRequest(format!(
+                        "terms aggregations on runtime fields only support the `field` and `size` \
+                         parameters: {error}"
+                    ))
+                })?;
+            self.terms_aggregations.push(RuntimeTermsAggregation {
+                name: aggregation_name,
+                field_name,
+                size: terms.size as usize,
+                doc_counts: HashMap::new(),
+            });
+        }
+        if self.has_indexed_aggregations && !self.terms_aggregations.is_empty() {
+            return Err(SearchError::InvalidAggregationRequest(
+                "aggregations on runtime fields cannot be combined with aggregations on indexed \
+                 fields"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the runtime field targeted by an aggregation, if any.
+    fn runtime_field_of_aggregation(
+        &self,
+        aggregation: &JsonValue,
+    ) -> crate::Result<Option<String>> {
+        let Some(aggregation_object) = aggregation.as_object() else {
+            return Ok(None);
+        };
+        for (aggregation_type, aggregation_params) in aggregation_object {
+            let Some(field_name) = aggregation_params
+                .get("field")
+                .and_then(JsonValue::as_str)
+                .filter(|field_name| self.is_runtime_field(field_name))
+            else {
+                continue;
+            };
+            if aggregation_type != "terms" {
+                return Err(SearchError::InvalidAggregationRequest(format!(
+                    "only terms aggregations are supported on runtime fields, got a \
+                     `{aggregation_type}` aggregation on `{field_name}`"
+                )));
+            }
+            return Ok(Some(field_name.to_string()));
+        }
+        Ok(None)
+    }
+
+    /// Returns the search request without its sort fields and aggregations on runtime fields, so
+    /// that it can be validated against the doc mappings of the targeted indexes.
+    pub(crate) fn without_runtime_fields<'a>(
+        &self,
+        search_request: &'a SearchRequest,
+    ) -> Cow<'a, SearchRequest> {
+        if self.sort_fields.is_empty() && self.terms_aggregations.is_empty() {
+            return Cow::Borrowed(search_request);
+        }
+        let mut search_request = search_request.clone();
+
+        if !self.sort_fields.is_empty() {
+            search_request.sort_fields.clear();
+        }
+        if !self.terms_aggregations.is_empty() {
+            search_request.aggregation_request = None;
+        }
+        Cow::Owned(search_request)
+    }
+
+    /// Moves the filters on runtime fields out of a resolved query AST.
+    ///
+    /// Only the query itself or the `must`, `filter` and `must_not` clauses of a top-level boolean
+    /// query without `should` clauses can filter on runtime fields: since runtime fields are not
+    /// indexed, they are applied to the hits of the rest of the query.
+    pub(crate) fn extract_filters(
+        &self,
+        query_ast: QueryAst,
+    ) -> crate::Result<(QueryAst, Vec<RuntimeFieldFilter>)> {
+        if self.runtime_fields.is_empty() {
+            return Ok((query_ast, Vec::new()));
+        }
+        let mut filters = Vec::new();
+
+        let query_ast = match query_ast {
+            QueryAst::Bool(mut bool_query) if bool_query.should.is_empty() => {
+                for (clauses, negated) in [
+                    (&mut bool_query.must, false),
+                    (&mut bool_query.filter, false),
+                    (&mut bool_query.must_not, true),
+                ] {
+                    clauses.retain(|clause| match self.filter_from_query_ast(clause, negated) {
+                        Some(filter) => {
+                            filters.push(filter);
+                            false
+                        }
+                        None => true,
+                    });
+                }
+                QueryAst::Bool(bool_query)
+            }
+            query_ast => match self.filter_from_query_ast(&query_ast, false) {
+                Some(filter) => {
+                    filters.push(filter);
+                    QueryAst::MatchAll
+                }
+                None => query_ast,
+            },
+        };
+        let mut runtime_field_finder = RuntimeFieldFinder {
+            runtime_fields: &self.runtime_fields,
+        };
+        if let Err(field_name) = runtime_field_finder.visit(&query_ast) {
+            return Err(SearchError::InvalidQuery(format!(
+                "runtime field `{field_name}` can only be used in the top-level `AND` and `NOT` \
+                 clauses of a query"
+            )));
+        }
+        Ok((query_ast, filters))
+    }
+
+    fn filter_from_query_ast(
+        &self,
+        query_ast: &QueryAst,
+        negated: bool,
+    ) -> Option<RuntimeFieldFilter> {
+        let (field_name, predicate) = match query_ast {
+            QueryAst::Term(TermQuery { field, value }) => {
+                (field, RuntimeFieldPredicate::Term(value.clone()))
+            }
+            // Runtime fields are not analyzed: a full-text query matches the exact value.
+            QueryAst::FullText(FullTextQuery { field, text, .. }) => {
+                (field, RuntimeFieldPredicate::Term(text.clone()))
+            }
+            QueryAst::TermSet(TermSetQuery { terms_per_field }) if terms_per_field.len() == 1 => {
+                let (field, terms) = terms_per_field.iter().next()?;
+                (field, RuntimeFieldPredicate::TermSet(terms.clone()))
+            }
+            QueryAst::Range(RangeQuery {
+                field,
+                lower_bound,
+                upper_bound,
+            }) => (
+                field,
+                RuntimeFieldPredicate::Range {
+                    lower_bound: lower_bound.clone(),
+                    upper_bound: upper_bound.clone(),
+                },
+            ),
+            QueryAst::FieldPresence(FieldPresenceQuery { field }) => {
+                (field, RuntimeFieldPredicate::Exists)
+            }
+            _ => return None,
+        };
+        if !self.is_runtime_field(field_name) {
+            return None;
+        }
+        Some(RuntimeFieldFilter {
+            field_name: field_name.clone(),
+            predicate,
+            negated,
+        })
+    }
+
+    pub(crate) fn set_filters(&mut self, filters: Vec<RuntimeFieldFilter>) {
+        self.filters = filters;
+    }
+
+    /// Returns the plan if the request uses runtime fields, checking that their uses can be
+    /// combined.
+    pub(crate) fn into_used(self) -> crate::Result<Option<Self>> {
+        if !self.requires_scan() && self.docvalue_fields.is_empty() {
+            return Ok(None);
+        }
+        if self.requires_scan() && self.has_indexed_aggregations {
+            return Err(SearchError::InvalidAggregationRequest(
+                "aggregations on indexed fields cannot be combined with filters, sorts or \
+                 aggregations on runtime fields"
+                    .to_string(),
+            ));
+        }
+        Ok(Some(self))
+    }
+
+    /// Returns whether the request has to evaluate runtime fields on every matching document,
+    /// as opposed to only the returned hits.
+    pub(crate) fn requires_scan(&self) -> bool {
+        !self.filters.is_empty()
+            || !self.sort_fields.is_empty()
+            || !self.terms_aggregations.is_empty()
+    }
+
+    /// Returns the runtime fields the request has to evaluate.
+    fn used_runtime_fields(&self) -> impl Iterator<Item = (&String, &RuntimeFieldConfig)> {
+        let used_field_names: BTreeSet<&str> = self
+            .filters
+            .iter()
+            .map(|filter| filter.field_name.as_str())
+            .chain(
+                self.sort_fields
+                    .iter()
+                    .map(|sort_field| sort_field.field_name.as_str()),
+            )
+            .chain(
+                self.terms_aggregations
+                    .iter()
+                    .map(|terms_aggregation| terms_aggregation.field_name.as_str()),
+            )
+            .chain(self.docvalue_fields.iter().map(String::as_str))
+            .collect();
+        self.runtime_fields
+            .iter()
+            .filter(move |(field_name, _)| used_field_names.contains(field_name.as_str()))
+    }
+
+    /// Evaluates the requested `docvalue_fields` on hits returned by a regular search.
+    pub(crate) fn add_docvalue_fields(&self, hits: &mut [Hit]) -> crate::Result<()> {
+        let mut evaluator = RuntimeFieldsEvaluator::try_new(self.used_runtime_fields())?;
+
+        for hit in hits {
+            let values = evaluator.evaluate(&hit.json);
+            hit.fields_json = self.docvalue_fields_json(&values);
+        }
+        Ok(())
+    }
+
+    fn docvalue_fields_json(&self, values: &BTreeMap<String, JsonValue>) -> Option<String> {
+        if self.docvalue_fields.is_empty() {
+            return None;
+        }
+        let fields: serde_json::Map<String, JsonValue> = self
+            .docvalue_fields
+            .iter()
+            .filter_map(|field_name| {
+                let values = match values.get(field_name)? {
+                    JsonValue::Array(values) => values.clone(),
+                    value => vec![value.clone()],
+                };
+                Some((field_name.clone(), JsonValue::Array(values)))
+            })
+            .collect();
+        Some(JsonValue::Object(fields).to_string())
+    }
+
+    fn matches_filters(&self, values: &BTreeMap<String, JsonValue>) -> bool {
+        self.filters
+            .iter()
+            .all(|filter| filter.matches(values.get(&filter.field_name)))
+    }
+
+    fn compare_hits(
+        &self,
+        left_values: &BTreeMap<String, JsonValue>,
+        right_values: &BTreeMap<String, JsonValue>,
+    ) -> Ordering {
+        for sort_field in &self.sort_fields {
+            let ordering = compare_runtime_values(
+                left_values.get(&sort_field.field_name),
+                right_values.get(&sort_field.field_name),
+                sort_field.sort_order(),
+            );
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Performs a search filtering, sorting or aggregating on runtime fields, by evaluating them
+    /// on every document matching the rest of the query.
+    pub(crate) async fn search(
+        mut self,
+        mut search_request: SearchRequest,
+        split_metadatas: Vec<SplitMetadata>,
+        indexes_metas_for_leaf_search: IndexesMetasForLeafSearch,
+        cluster_client: ClusterClient,
+    ) -> crate::Result<SearchResponse> {
+        if search_request.search_after.is_some() || search_request.scroll_ttl_secs.is_some() {
+            return Err(SearchError::InvalidArgument(
+                "`search_after` and scroll are not supported by searches filtering, sorting or \
+                 aggregating on runtime fields"
+                    .to_string(),
+            ));
+        }
+        let mut evaluator = RuntimeFieldsEvaluator::try_new(self.used_runtime_fields())?;
+
+        let num_hits_to_keep = (search_request.start_offset + search_request.max_hits) as usize;
+        let sort_by_runtime_fields = !self.sort_fields.is_empty();
+        // Without aggregations nor sort on runtime fields, we can stop as soon as we have enough
+        // hits, unless we have to count all of them.
+        let can_stop_early = num_hits_to_keep > 0
+            && !sort_by_runtime_fields
+            && self.terms_aggregations.is_empty()
+            && search_request.count_hits() == CountHits::Underestimate;
+
+        if sort_by_runtime_fields {
+            search_request.sort_fields.clear();
+        }
+        let num_successful_splits = split_metadatas.len() as u64;
+        let mut hit_stream = export_planned_splits(
+            search_request.clone(),
+            split_metadatas,
+            indexes_metas_for_leaf_search,
+            cluster_client,
+        );
+        let mut num_scanned_docs: u64 = 0;
+        let mut num_hits: u64 = 0;
+        let mut is_truncated = false;
+        let mut kept_hits: Vec<(Hit, BTreeMap<String, JsonValue>)> = Vec::new();
+
+        while let Some(hit) = hit_stream.try_next().await? {
+            if num_scanned_docs == MAX_RUNTIME_FIELDS_SCANNED_DOCS {
+                is_truncated = true;
+                break;
+            }
+            num_scanned_docs += 1;
+
+            let values = evaluator.evaluate(&hit.json);
+
+            if !self.matches_filters(&values) {
+                continue;
+            }
+            num_hits += 1;
+
+            for terms_aggregation in &mut self.terms_aggregations {
+                terms_aggregation.collect(values.get(&terms_aggregation.field_name));
+            }
+            if sort_by_runtime_fields {
+                kept_hits.push((hit, values));
+
+                // Only keep the top hits, but avoid sorting them after every new hit.
+                if kept_hits.len() >= 2 * num_hits_to_keep.max(1) {
+                    kept_hits.sort_by(|(_, left), (_, right)| self.compare_hits(left, right));
+                    kept_hits.truncate(num_hits_to_keep);
+                }
+            } else if kept_hits.len() < num_hits_to_keep {
+                kept_hits.push((hit, values));
+            } else if can_stop_early {
+                break;
+            }
+        }
+        if sort_by_runtime_fields {
+            kept_hits.sort_by(|(_, left), (_, right)| self.compare_hits(left, right));
+            kept_hits.truncate(num_hits_to_keep);
+        }
+        let hits: Vec<Hit> = kept_hits
+            .into_iter()
+            .skip(search_request.start_offset as usize)
+            .map(|(mut hit, values)| {
+                hit.fields_json = self.docvalue_fields_json(&values);
+                hit
+            })
+            .collect();
+
+        let aggregation_postcard = if self.terms_aggregations.is_empty() {
+            None
+        } else {
+            let aggregation_results = AggregationResults(
+                self.terms_aggregations
+                    .into_iter()
+                    .map(RuntimeTermsAggregation::into_result)
+                    .collect(),
+            );
+            Some(postcard::to_stdvec(&aggregation_results)?)
+        };
+        let field_names: Vec<&str> = self
+            .filters
+            .iter()
+            .map(|filter| filter.field_name.as_str())
+            .chain(
+                self.sort_fields
+                    .iter()
+                    .map(|sort_field| sort_field.field_name.as_str()),
+            )
+            .collect::<BTreeSet<&str>>()
+            .into_iter()
+            .collect();
+        let mut warnings = vec![format!(
+            "runtime fields were evaluated at query time on {num_scanned_docs} documents: \
+             filtering, sorting or aggregating on runtime fields is much slower than on indexed \
+             fields, consider extracting {} at indexing time",
+            if field_names.is_empty() {
+                "them".to_string()
+            } else {
+                format!("`{}`", field_names.join("`, `"))
+            }
+        )];
+        if is_truncated {
+            warn!(
+                query_ast = search_request.query_ast.as_str(),
+                "runtime fields search truncated after {MAX_RUNTIME_FIELDS_SCANNED_DOCS} documents"
+            );
+            warnings.push(format!(
+                "the search stopped after evaluating runtime fields on \
+                 {MAX_RUNTIME_FIELDS_SCANNED_DOCS} documents: the number of hits, the hits and \
+                 the aggregations are partial, narrow down the query or its time range"
+            ));
+        }
+        Ok(SearchResponse {
+            num_hits,
+            hits,
+            elapsed_time_micros: 0,
+            errors: Vec::new(),
+            aggregation_postcard,
+            scroll_id: None,
+            failed_splits: Vec::new(),
+            num_successful_splits,
+            profile: None,
+            warnings,
+        })
+    }
+}
+
+/// A filter on a runtime field, applied to the hits of the rest of the query.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RuntimeFieldFilter {
+    field_name: String,
+    predicate: RuntimeFieldPredicate,
+    negated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RuntimeFieldPredicate {
+    Term(String),
+    TermSet(BTreeSet<String>),
+    Range {
+        lower_bound: Bound<JsonLiteral>,
+        upper_bound: Bound<JsonLiteral>,
+    },
+    Exists,
+}
+
+impl RuntimeFieldFilter {
+    /// Returns whether the value of a runtime field matches the filter. Multivalued fields match
+    /// if any of their values does.
+    fn matches(&self, value_opt: Option<&JsonValue>) -> bool {
+        let mut values = scalar_values(value_opt);
+
+        let is_match = match &self.predicate {
+            RuntimeFieldPredicate::Term(term) => values.any(|value| scalar_equals(value, term)),
+            RuntimeFieldPredicate::TermSet(terms) => {
+                values.any(|value| terms.iter().any(|term| scalar_equals(value, term)))
+            }
+            RuntimeFieldPredicate::Range {
+                lower_bound,
+                upper_bound,
+            } => values.any(|value| is_in_range(value, lower_bound, upper_bound)),
+            RuntimeFieldPredicate::Exists => values.next().is_some(),
+        };
+        is_match != self.negated
+    }
+}
+
+/// Returns the non-null values of a runtime field.
+fn scalar_values(value_opt: Option<&JsonValue>) -> impl Iterator<Item = &JsonValue> {
+    let values: &[JsonValue] = match value_opt {
+        Some(JsonValue::Array(values)) => values,
+        Some(value) => std::slice::from_ref(value),
+        None => &[],
+    };
+    values.iter().filter(|value| !value.is_null())
+}
+
+fn scalar_equals(value: &JsonValue, term: &str) -> bool {
+    match value {
+        JsonValue::String(text) => text == term,
+        JsonValue::Number(number) => term
+            .parse::<f64>()
+            .ok()
+            .zip(number.as_f64())
+            .is_some_and(|(term, number)| term == number),
+        JsonValue::Bool(bool_value) => term.parse::<bool>() == Ok(*bool_value),
+        _ => false,
+    }
+}
+
+fn compare_to_literal(value: &JsonValue, literal: &JsonLiteral) -> Option<Ordering> {
+    match (value, literal) {
+        (JsonValue::Number(number), JsonLiteral::Number(literal_number)) => {
+            number.as_f64()?.partial_cmp(&literal_number.as_f64()?)
+        }
+        (JsonValue::Number(number), JsonLiteral::String(literal_str)) => number
+            .as_f64()?
+            .partial_cmp(&literal_str.parse::<f64>().ok()?),
+        (JsonValue::String(text), JsonLiteral::String(literal_str)) => {
+            Some(text.as_str().cmp(literal_str.as_str()))
+        }
+        (JsonValue::String(text), JsonLiteral::Number(literal_number)) => text
+            .parse::<f64>()
+            .ok()?
+            .partial_cmp(&literal_number.as_f64()?),
+        (JsonValue::Bool(bool_value), JsonLiteral::Bool(literal_bool)) => {
+            Some(bool_value.cmp(literal_bool))
+        }
+        _ => None,
+    }
+}
+
+fn is_in_range(
+    value: &JsonValue,
+    lower_bound: &Bound<JsonLiteral>,
+    upper_bound: &Bound<JsonLiteral>,
+) -> bool {
+    let is_above_lower_bound = match lower_bound {
+        Bound::Included(literal) => matches!(
+            compare_to_literal(value, literal),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
+        Bound::Excluded(literal) => compare_to_literal(value, literal) == Some(Ordering::Greater),
+        Bound::Unbounded => true,
+    };
+    let is_below_upper_bound = match upper_bound {
+        Bound::Included(literal) => matches!(
+            compare_to_literal(value, literal),
+            Some(Ordering::Less | Ordering::Equal)
+        ),
+        Bound::Excluded(literal) => compare_to_literal(value, literal) == Some(Ordering::Less),
+        Bound::Unbounded => true,
+    };
+    is_above_lower_bound && is_below_upper_bound
+}
+
+/// Compares the values of a runtime field for sorting. Multivalued fields are sorted by their
+/// first value, and documents without value come last, whatever the sort order.
+fn compare_runtime_values(
+    left_opt: Option<&JsonValue>,
+    right_opt: Option<&JsonValue>,
+    sort_order: SortOrder,
+) -> Ordering {
+    match (
+        scalar_values(left_opt).next(),
+        scalar_values(right_opt).next(),
+    ) {
+        (Some(left), Some(right)) => {
+            let ordering = compare_scalars(left, right);
+            match sort_order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn compare_scalars(left: &JsonValue, right: &JsonValue) -> Ordering {
+    fn type_rank(value: &JsonValue) -> u8 {
+        match value {
+            JsonValue::Bool(_) => 0,
+            JsonValue::Number(_) => 1,
+            JsonValue::String(_) => 2,
+            _ => 3,
+        }
+    }
+    match (left, right) {
+        (JsonValue::Bool(left), JsonValue::Bool(right)) => left.cmp(right),
+        (JsonValue::Number(left), JsonValue::Number(right)) => left
+            .as_f64()
+            .unwrap_or(f64::NAN)
+            .total_cmp(&right.as_f64().unwrap_or(f64::NAN)),
+        (JsonValue::String(left), JsonValue::String(right)) => left.cmp(right),
+        _ => type_rank(left).cmp(&type_rank(right)),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuntimeTermsAggregationRequest {
+    terms: RuntimeTermsAggregationParams,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuntimeTermsAggregationParams {
+    #[allow(dead_code)]
+    field: String,
+    #[serde(default = "default_terms_size")]
+    size: u32,
+}
+
+/// A terms aggregation on a runtime field.
+#[derive(Debug)]
+struct RuntimeTermsAggregation {
+    name: String,
+    field_name: String,
+    size: usize,
+    /// Number of documents per term, by string representation of the term.
+    doc_counts: HashMap<String, (Key, u64)>,
+}
+
+impl RuntimeTermsAggregation {
+    fn collect(&mut self, value_opt: Option<&JsonValue>) {
+        let keys: BTreeMap<String, Key> = scalar_values(value_opt)
+            .filter_map(|value| {
+                let key = match value {
+                    JsonValue::String(text) => Key::Str(text.clone()),
+                    JsonValue::Number(number) => {
+                        if let Some(number_i64) = number.as_i64() {
+                            Key::I64(number_i64)
+                        } else if let Some(number_u64) = number.as_u64() {
+                            Key::U64(number_u64)
+                        } else {
+                            Key::F64(number.as_f64()?)
+                        }
+                    }
+                    JsonValue::Bool(bool_value) => Key::Str(bool_value.to_string()),
+                    _ => return None,
+                };
+                Some((value.to_string(), key))
+            })
+            .collect();
+        // A document is counted once per distinct term.
+        for (key_str, key) in keys {
+            self.doc_counts.entry(key_str).or_insert((key, 0)).1 += 1;
+        }
+    }
+
+    fn into_result(self) -> (String, AggregationResult) {
+        let mut doc_counts: Vec<(String, (Key, u64))> = self.doc_counts.into_iter().collect();
+        doc_counts.sort_by(
+            |(left_key, (_, left_count)), (right_key, (_, right_count))| {
+                right_count
+                    .cmp(left_count)
+                    .then_with(|| left_key.cmp(right_key))
+            },
+        );
+        let sum_other_doc_count = doc_counts
+            .iter()
+            .skip(self.size)
+            .map(|(_, (_, doc_count))| doc_count)
+            .sum();
+        let buckets = doc_counts
+            .into_iter()
+            .take(self.size)
+            .map(|(_, (key, doc_count))| BucketEntry {
+                key_as_string: None,
+                key,
+                doc_count,
+                sub_aggregation: AggregationResults(Vec::new()),
+            })
+            .collect();
+        let terms_result = BucketResult::Terms {
+            buckets,
+            sum_other_doc_count,
+            doc_count_error_upper_bound: Some(0),
+        };
+        (self.name, AggregationResult::BucketResult(terms_result))
+    }
+}
+
+/// Looks for a reference to a runtime field in a query AST.
+struct RuntimeFieldFinder<'a> {
+    runtime_fields: &'a BTreeMap<String, RuntimeFieldConfig>,
+}
+
+impl RuntimeFieldFinder<'_> {
+    fn check_field<'b>(&self, field_name: &'b str) -> Result<(), &'b str> {
+        if self.runtime_fields.contains_key(field_name) {
+            return Err(field_name);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> QueryAstVisitor<'a> for RuntimeFieldFinder<'_> {
+    type Err = &'a str;
+
+    fn visit_term(&mut self, term_query: &'a TermQuery) -> Result<(), Self::Err> {
+        self.check_field(&term_query.field)
+    }
+
+    fn visit_term_set(&mut self, term_set_query: &'a TermSetQuery) -> Result<(), Self::Err> {
+        for field_name in term_set_query.terms_per_field.keys() {
+            self.check_field(field_name)?;
+        }
+        Ok(())
+    }
+
+    fn visit_full_text(&mut self, full_text_query: &'a FullTextQuery) -> Result<(), Self::Err> {
+        self.check_field(&full_text_query.field)
+    }
+
+    fn visit_phrase_prefix(
+        &mut self,
+        phrase_prefix_query: &'a PhrasePrefixQuery,
+    ) -> Result<(), Self::Err> {
+        self.check_field(&phrase_prefix_query.field)
+    }
+
+    fn visit_range(&mut self, range_query: &'a RangeQuery) -> Result<(), Self::Err> {
+        self.check_field(&range_query.field)
+    }
+
+    fn visit_exists(&mut self, exists_query: &'a FieldPresenceQuery) -> Result<(), Self::Err> {
+        self.check_field(&exists_query.field)
+    }
+
+    fn visit_wildcard(&mut self, wildcard_query: &'a WildcardQuery) -> Result<(), Self::Err> {
+        self.check_field(&wildcard_query.field)
+    }
+
+    fn visit_regex(&mut self, regex_query: &'a RegexQuery) -> Result<(), Self::Err> {
+        self.check_field(&regex_query.field)
+    }
+
+    fn visit_fuzzy(&mut self, fuzzy_query: &'a FuzzyQuery) -> Result<(), Self::Err> {
+        self.check_field(&fuzzy_query.field)
+    }
+}
+
+/// Evaluates the VRL expressions of runtime fields on documents.
+#[cfg(feature = "vrl")]
+struct RuntimeFieldsEvaluator {
+    programs: Vec<(String, vrl::compiler::Program, vrl::compiler::TimeZone)>,
+    runtime: vrl::compiler::runtime::Runtime,
+}
+
+#[cfg(feature = "vrl")]
+impl RuntimeFieldsEvaluator {
+    fn try_new<'a>(
+        runtime_fields: impl Iterator<Item = (&'a String, &'a RuntimeFieldConfig)>,
+    ) -> crate::Result<Self> {
+        use vrl::compiler::runtime::Runtime;
+        use vrl::compiler::state::RuntimeState;
+
+        let mut programs = Vec::new();
+
+        for (field_name, runtime_field) in runtime_fields {
+            let (program, timezone) = runtime_field.compile_vrl_script().map_err(|error| {
+                SearchError::InvalidArgument(format!(
+                    "invalid runtime field `{field_name}`: {error}"
+                ))
+            })?;
+            programs.push((field_name.clone(), program, timezone));
+        }
+        Ok(RuntimeFieldsEvaluator {
+            programs,
+            runtime: Runtime::new(RuntimeState::default()),
+        })
+    }
+
+    /// Returns the values of the runtime fields for a JSON document. Fields whose expression
+    /// fails or returns `null` have no value.
+    fn evaluate(&mut self, doc_json: &str) -> BTreeMap<String, JsonValue> {
+        use vrl::compiler::TargetValueRef;
+        use vrl::value::{Secrets, Value as VrlValue};
+
+        let mut values = BTreeMap::new();
+
+        let doc: VrlValue = match serde_json::from_str(doc_json) {
+            Ok(doc) => doc,
+            Err(error) => {
+                warn!(%error, "failed to parse document to evaluate runtime fields");
+                return values;
+            }
+        };
+        for (field_name, program, timezone) in &self.programs {
+            let mut value = doc.clone();
+            let mut metadata = VrlValue::Object(BTreeMap::new());
+            let mut secrets = Secrets::default();
+            let mut target = TargetValueRef {
+                value: &mut value,
+                metadata: &mut metadata,
+                secrets: &mut secrets,
+            };
+            let runtime_res = self.runtime.resolve(&mut target, program, timezone);
+            self.runtime.clear();
+
+            match runtime_res {
+                Ok(vrl_value) => {
+                    if let Ok(json_value) = serde_json::to_value(vrl_value)
+                        && !json_value.is_null()
+                    {
+                        values.insert(field_name.clone(), json_value);
+                    }
+                }
+                Err(terminate) => {
+                    tracing::debug!(
+                        %field_name,
+                        error=?terminate,
+                        "failed to evaluate runtime field"
+                    );
+                }
+            }
+        }
+        values
+    }
+}
+
+/// Without the `vrl` feature, runtime fields cannot be evaluated.
+#[cfg(not(feature = "vrl"))]
+struct RuntimeFieldsEvaluator;
+
+#[cfg(not(feature = "vrl"))]
+impl RuntimeFieldsEvaluator {
+    fn try_new<'a>(
+        _runtime_fields: impl Iterator<Item = (&'a String, &'a RuntimeFieldConfig)>,
+    ) -> crate::Result<Self> {
+        Err(SearchError::InvalidArgument(
+            "runtime fields require Quickwit to be built with the `vrl` feature".to_string(),
+        ))
+    }
+
+    fn evaluate(&mut self, _doc_json: &str) -> BTreeMap<String, JsonValue> {
+        BTreeMap::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_query::query_ast::{BoolQuery, UserInputQuery};
+
+    use super::*;
+
+    fn runtime_fields_plan_for_test(search_request: &SearchRequest) -> RuntimeFieldsPlan {
+        let search_request = SearchRequest {
+            runtime_fields: Some(
+                r#"{"status_code": {"script": "parse_int!(.status)"}}"#.to_string(),
+            ),
+            ..search_request.clone()
+        };
+        RuntimeFieldsPlan::for_request(&[], &search_request).unwrap()
+    }
+
+    fn parse_user_query(user_text: &str) -> QueryAst {
+        let user_input_query: QueryAst = UserInputQuery {
+            user_text: user_text.to_string(),
+            default_fields: Some(vec!["body".to_string()]),
+            default_operator: quickwit_query::BooleanOperand::And,
+            lenient: false,
+        }
+        .into();
+        user_input_query.parse_user_query(&[]).unwrap()
+    }
+
+    #[test]
+    fn test_runtime_fields_plan_unused() {
+        let search_request = SearchRequest {
+            docvalue_fields: vec!["body".to_string()],
+            ..Default::default()
+        };
+        let runtime_fields_plan = runtime_fields_plan_for_test(&search_request);
+        let (query_ast, filters) = runtime_fields_plan
+            .extract_filters(parse_user_query("body:error"))
+            .unwrap();
+        assert!(filters.is_empty());
+        assert_eq!(query_ast, parse_user_query("body:error"));
+        assert!(runtime_fields_plan.into_used().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_runtime_fields_plan_extract_filters() {
+        let runtime_fields_plan = runtime_fields_plan_for_test(&SearchRequest::default());
+
+        let (query_ast, filters) = runtime_fields_plan
+            .extract_filters(parse_user_query(
+                "body:error AND status_code:>=500 AND NOT status_code:503",
+            ))
+            .unwrap();
+        let QueryAst::Bool(BoolQuery { must, must_not, .. }) = &query_ast else {
+            panic!("expected a boolean query, got {query_ast:?}");
+        };
+        assert_eq!(must.len(), 1);
+        assert!(must_not.is_empty());
+        assert_eq!(filters.len(), 2);
+        assert!(
+            filters
+                .iter()
+                .all(|filter| filter.field_name == "status_code")
+        );
+        assert!(!filters[0].negated);
+        assert!(filters[1].negated);
+
+        let (_query_ast, filters) = runtime_fields_plan
+            .extract_filters(parse_user_query("status_code:500"))
+            .unwrap();
+        assert_eq!(filters.len(), 1);
+
+        let error = runtime_fields_plan
+            .extract_filters(parse_user_query("body:error OR status_code:500"))
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("runtime field `status_code` can only be used")
+        );
+    }
+
+    #[test]
+    fn test_runtime_fields_plan_sort_and_aggregations() {
+        let search_request = SearchRequest {
+            sort_fields: vec![SortField {
+                field_name: "status_code".to_string(),
+                sort_order: SortOrder::Desc as i32,
+                sort_datetime_format: None,
+            }],
+            aggregation_request: Some(
+                r#"{"codes": {"terms": {"field": "status_code", "size": 3}}}"#.to_string(),
+            ),
+            ..Default::default()
+        };
+        let runtime_fields_plan = runtime_fields_plan_for_test(&search_request);
+        assert!(runtime_fields_plan.requires_scan());
+
+        let search_request_for_validation =
+            runtime_fields_plan.without_runtime_fields(&search_request);
+        assert!(search_request_for_validation.sort_fields.is_empty());
+        assert!(search_request_for_validation.aggregation_request.is_none());
+
+        let search_request = SearchRequest {
+            aggregation_request: Some(
+                r#"{"codes": {"avg": {"field": "status_code"}}}"#.to_string(),
+            ),
+            ..Default::default()
+        };
+        let error = RuntimeFieldsPlan::for_request(
+            &[],
+            &SearchRequest {
+                runtime_fields: Some(r#"{"status_code": {"script": "."}}"#.to_string()),
+                ..search_request
+            },
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("only terms aggregations"));
+    }
+
+    #[test]
+    fn test_runtime_field_filter_matches() {
+        let runtime_fields_plan = runtime_fields_plan_for_test(&SearchRequest::default());
+        let filter = |user_text: &str| {
+            let (_, mut filters) = runtime_fields_plan
+                .extract_filters(parse_user_query(user_text))
+                .unwrap();
+            filters.pop().unwrap()
+        };
+        let status_500 = JsonValue::from(500);
+        let status_404 = JsonValue::from(404);
+
+        assert!(filter("status_code:500").matches(Some(&status_500)));
+        assert!(!filter("status_code:500").matches(Some(&status_404)));
+        assert!(!filter("status_code:500").matches(None));
+        assert!(filter("status_code:>=500").matches(Some(&status_500)));
+        assert!(!filter("status_code:>500").matches(Some(&status_500)));
+        assert!(filter("status_code:[400 TO 499]").matches(Some(&status_404)));
+        assert!(filter("status_code:IN [404 503]").matches(Some(&status_404)));
+        assert!(filter("status_code:*").matches(Some(&status_404)));
+        assert!(!filter("status_code:*").matches(None));
+        assert!(filter("status_code:500").matches(Some(&serde_json::json!([404, 500]))));
+    }
+
+    #[test]
+    fn test_compare_runtime_values() {
+        let one = JsonValue::from(1);
+        let two = JsonValue::from(2.5);
+        assert_eq!(
+            compare_runtime_values(Some(&one), Some(&two), SortOrder::Asc),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_runtime_values(Some(&one), Some(&two), SortOrder::Desc),
+            Ordering::Greater
+        );
+        // Documents without value come last in both orders.
+        assert_eq!(
+            compare_runtime_values(None, Some(&one), SortOrder::Asc),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_runtime_values(None, Some(&one), SortOrder::Desc),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_runtime_terms_aggregation() {
+        let mut terms_aggregation = RuntimeTermsAggregation {
+            name: "codes".to_string(),
+            field_name: "status_code".to_string(),
+            size: 2,
+            doc_counts: HashMap::new(),
+        };
+        for value in [
+            serde_json::json!(500),
+            serde_json::json!(404),
+            serde_json::json!([500, 500]),
+            serde_json::json!(200),
+            serde_json::json!(null),
+            serde_json::json!(404),
+            serde_json::json!(500),
+        ] {
+            terms_aggregation.collect(Some(&value));
+        }
+        let (name, result) = terms_aggregation.into_result();
+        assert_eq!(name, "codes");
+        let AggregationResult::BucketResult(BucketResult::Terms {
+            buckets,
+            sum_other_doc_count,
+            ..
+        }) = result
+        else {
+            panic!("expected a terms result");
+        };
+        let doc_counts: Vec<(String, u64)> = buckets
+            .into_iter()
+            .map(|bucket| (format!("{:?}", bucket.key), bucket.doc_count))
+            .collect();
+        assert_eq!(
+            doc_counts,
+            [("I64(500)".to_string(), 3), ("I64(404)".to_string(), 2)]
+        );
+        assert_eq!(sum_other_doc_count, 1);
+    }
+
+    #[cfg(feature = "vrl")]
+    #[test]
+    fn test_runtime_fields_evaluator() {
+        let runtime_fields = BTreeMap::from([
+            (
+                "status_code".to_string(),
+                RuntimeFieldConfig {
+                    script: r"parse_int!(parse_regex!(.message, r'status=(?P<code>\d+)').code)"
+                        .to_string(),
+                },
+            ),
+            (
+                "host".to_string(),
+                RuntimeFieldConfig {
+                    script: "upcase!(.host)".to_string(),
+                },
+            ),
+        ]);
+        let mut evaluator = RuntimeFieldsEvaluator::try_new(runtime_fields.iter()).unwrap();
+        let values = evaluator.evaluate(r#"{"message": "GET / status=503", "host": 1}"#);
+        assert_eq!(values.get("status_code"), Some(&JsonValue::from(503)));
+        // The expression of `host` fails on a number.
+        assert!(!values.contains_key("host"));
+    }
+}
@@ -131,8 +131,11 @@ struct TrackedValue {
 /// Once the capacity [LOCAL_KV_CACHE_SIZE] is reached, the oldest entries are
 /// removed.
 ///
-/// Currently this store is only used for caching scroll contexts. Using it for
-/// other purposes is risky as use cases would compete for its capacity.
+/// Currently this store is only used for caching scroll contexts and async
+/// searches. Using it for other purposes is risky as use cases would compete for
+/// its capacity. An async search takes a single entry, overwritten as its results
+/// are updated, and searches whose results exceed a size limit fail rather than
+/// being stored.
 #[derive(Clone)]
 pub(crate) struct MiniKV {
     ttl_with_cache: Arc<RwLock<TtlCache<Vec<u8>, TrackedValue>>>,
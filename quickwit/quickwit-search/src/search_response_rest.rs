@@ -15,11 +15,23 @@
 use std::convert::TryFrom;
 
 use quickwit_common::truncate_str;
-use quickwit_proto::search::SearchResponse;
-use quickwit_query::aggregations::AggregationResults as AggregationResultsProxy;
+use quickwit_proto::search::{SearchProfile, SearchResponse};
+use quickwit_query::aggregations::{
+    AggregationResult as AggregationResultProxy, AggregationResults as AggregationResultsProxy,
+    BucketEntries as BucketEntriesProxy, BucketEntry as BucketEntryProxy,
+    BucketResult as BucketResultProxy, CompositeBucketEntry as CompositeBucketEntryProxy,
+    CompositeKey, CompositeKeyValue, MetricResult as MetricResultProxy, NoTantivyEquivalentError,
+    RangeBucketEntry as RangeBucketEntryProxy,
+    SignificantTermsBucketEntry as SignificantTermsBucketEntryProxy,
+};
 use quickwit_query::query_ast::QueryAst;
 use serde::{Deserialize, Serialize};
-use serde_json::Value as JsonValue;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tantivy::aggregation::Key as TantivyKey;
+use tantivy::aggregation::agg_result::{
+    BucketEntry as TantivyBucketEntry, BucketResult as TantivyBucketResult,
+    MetricResult as TantivyMetricResult, RangeBucketEntry as TantivyRangeBucketEntry,
+};
 
 use crate::error::SearchError;
 
@@ -27,16 +39,237 @@ use crate::error::SearchError;
 // TODO previously, we were using zero-copy when possible, which we are no longer doing:
 // is that problematic? How can we return to zero/low-copy without it being painful?
 #[derive(Serialize, PartialEq, Debug)]
-pub struct AggregationResults(tantivy::aggregation::agg_result::AggregationResults);
+pub struct AggregationResults(JsonMap<String, JsonValue>);
 
 impl AggregationResults {
     /// Parse an ES aggregation result ast from our non-ambiguous postcard format
     pub fn from_postcard(postcard_bytes: &[u8]) -> anyhow::Result<Self> {
-        let aggregation_result: AggregationResultsProxy = postcard::from_bytes(postcard_bytes)?;
-        Ok(AggregationResults(aggregation_result.into()))
+        let aggregation_results: AggregationResultsProxy = postcard::from_bytes(postcard_bytes)?;
+        let aggregation_results_json = render_aggregation_results(aggregation_results)?;
+        Ok(AggregationResults(aggregation_results_json))
     }
 }
 
+// Composite, pipeline, significant terms and rare terms aggregations are computed by Quickwit and
+// have no tantivy equivalent, and they can appear within buckets, so the results are rendered
+// level by level: tantivy serializes everything but the buckets and the sub-aggregations.
+fn render_aggregation_results(
+    aggregation_results: AggregationResultsProxy,
+) -> serde_json::Result<JsonMap<String, JsonValue>> {
+    aggregation_results
+        .0
+        .into_iter()
+        .map(|(name, aggregation_result)| {
+            let aggregation_result_json = render_aggregation_result(aggregation_result)?;
+            Ok((name, aggregation_result_json))
+        })
+        .collect()
+}
+
+fn render_aggregation_result(
+    aggregation_result: AggregationResultProxy,
+) -> serde_json::Result<JsonValue> {
+    match aggregation_result {
+        AggregationResultProxy::MetricResult(MetricResultProxy::Pipeline(pipeline_result)) => {
+            let mut pipeline_result_json = JsonMap::new();
+            pipeline_result_json.insert("value".to_string(), pipeline_result.value.into());
+            if let Some(keys) = pipeline_result.keys {
+                pipeline_result_json.insert("keys".to_string(), keys.into());
+            }
+            Ok(JsonValue::Object(pipeline_result_json))
+        }
+        AggregationResultProxy::MetricResult(metric_result) => {
+            let tantivy_metric_result = TantivyMetricResult::try_from(metric_result)
+                .map_err(no_tantivy_equivalent_error)?;
+            serde_json::to_value(tantivy_metric_result)
+        }
+        AggregationResultProxy::BucketResult(bucket_result) => render_bucket_result(bucket_result),
+    }
+}
+
+fn render_bucket_result(bucket_result: BucketResultProxy) -> serde_json::Result<JsonValue> {
+    let (buckets_json, bucket_result_without_buckets) = match bucket_result {
+        BucketResultProxy::Range { buckets } => {
+            let buckets_json = render_bucket_entries(buckets, render_range_bucket_entry)?;
+            let bucket_result = BucketResultProxy::Range {
+                buckets: BucketEntriesProxy::Vec(Vec::new()),
+            };
+            (buckets_json, bucket_result)
+        }
+        BucketResultProxy::Histogram { buckets } => {
+            let buckets_json = render_bucket_entries(buckets, render_bucket_entry)?;
+            let bucket_result = BucketResultProxy::Histogram {
+                buckets: BucketEntriesProxy::Vec(Vec::new()),
+            };
+            (buckets_json, bucket_result)
+        }
+        BucketResultProxy::Terms {
+            buckets,
+            sum_other_doc_count,
+            doc_count_error_upper_bound,
+        } => {
+            let buckets_json =
+                render_bucket_entries(BucketEntriesProxy::Vec(buckets), render_bucket_entry)?;
+            let bucket_result = BucketResultProxy::Terms {
+                buckets: Vec::new(),
+                sum_other_doc_count,
+                doc_count_error_upper_bound,
+            };
+            (buckets_json, bucket_result)
+        }
+        BucketResultProxy::Composite { buckets, after_key } => {
+            let mut composite_result_json = JsonMap::new();
+            if let Some(after_key) = after_key {
+                let after_key_json = JsonValue::Object(composite_key_to_json(after_key));
+                composite_result_json.insert("after_key".to_string(), after_key_json);
+            }
+            let buckets_json = buckets
+                .into_iter()
+                .map(render_composite_bucket_entry)
+                .collect::<serde_json::Result<Vec<JsonValue>>>()?;
+            composite_result_json.insert("buckets".to_string(), JsonValue::Array(buckets_json));
+            return Ok(JsonValue::Object(composite_result_json));
+        }
+        BucketResultProxy::SignificantTerms {
+            doc_count,
+            bg_count,
+            buckets,
+        } => {
+            let mut significant_terms_result_json = JsonMap::new();
+            significant_terms_result_json.insert("doc_count".to_string(), doc_count.into());
+            significant_terms_result_json.insert("bg_count".to_string(), bg_count.into());
+            let buckets_json = buckets
+                .into_iter()
+                .map(render_significant_terms_bucket_entry)
+                .collect::<serde_json::Result<Vec<JsonValue>>>()?;
+            significant_terms_result_json
+                .insert("buckets".to_string(), JsonValue::Array(buckets_json));
+            return Ok(JsonValue::Object(significant_terms_result_json));
+        }
+        BucketResultProxy::RareTerms { buckets } => {
+            let mut rare_terms_result_json = JsonMap::new();
+            let buckets_json =
+                render_bucket_entries(BucketEntriesProxy::Vec(buckets), render_bucket_entry)?;
+            rare_terms_result_json.insert("buckets".to_string(), buckets_json);
+            return Ok(JsonValue::Object(rare_terms_result_json));
+        }
+    };
+    let tantivy_bucket_result = TantivyBucketResult::try_from(bucket_result_without_buckets)
+        .map_err(no_tantivy_equivalent_error)?;
+    let mut bucket_result_json = serde_json::to_value(tantivy_bucket_result)?;
+    if let Some(bucket_result_json_obj) = bucket_result_json.as_object_mut() {
+        bucket_result_json_obj.insert("buckets".to_string(), buckets_json);
+    }
+    Ok(bucket_result_json)
+}
+
+// The results without tantivy equivalent are rendered by Quickwit before reaching tantivy, so this
+// error is not expected.
+fn no_tantivy_equivalent_error(error: NoTantivyEquivalentError) -> serde_json::Error {
+    serde::ser::Error::custom(error)
+}
+
+fn render_bucket_entries<T>(
+    bucket_entries: BucketEntriesProxy<T>,
+    render_bucket_entry_fn: fn(T) -> serde_json::Result<JsonValue>,
+) -> serde_json::Result<JsonValue> {
+    match bucket_entries {
+        BucketEntriesProxy::Vec(bucket_entries) => {
+            let bucket_entries_json = bucket_entries
+                .into_iter()
+                .map(render_bucket_entry_fn)
+                .collect::<serde_json::Result<Vec<JsonValue>>>()?;
+            Ok(JsonValue::Array(bucket_entries_json))
+        }
+        BucketEntriesProxy::HashMap(bucket_entries) => {
+            let bucket_entries_json = bucket_entries
+                .into_iter()
+                .map(|(key, bucket_entry)| Ok((key, render_bucket_entry_fn(bucket_entry)?)))
+                .collect::<serde_json::Result<JsonMap<String, JsonValue>>>()?;
+            Ok(JsonValue::Object(bucket_entries_json))
+        }
+    }
+}
+
+fn render_bucket_entry(mut bucket_entry: BucketEntryProxy) -> serde_json::Result<JsonValue> {
+    let sub_aggregation = std::mem::replace(
+        &mut bucket_entry.sub_aggregation,
+        AggregationResultsProxy(Vec::new()),
+    );
+    let tantivy_bucket_entry =
+        TantivyBucketEntry::try_from(bucket_entry).map_err(no_tantivy_equivalent_error)?;
+    let bucket_entry_json = serde_json::to_value(tantivy_bucket_entry)?;
+    extend_with_sub_aggregation(bucket_entry_json, sub_aggregation)
+}
+
+fn render_range_bucket_entry(
+    mut bucket_entry: RangeBucketEntryProxy,
+) -> serde_json::Result<JsonValue> {
+    let sub_aggregation = std::mem::replace(
+        &mut bucket_entry.sub_aggregation,
+        AggregationResultsProxy(Vec::new()),
+    );
+    let tantivy_bucket_entry =
+        TantivyRangeBucketEntry::try_from(bucket_entry).map_err(no_tantivy_equivalent_error)?;
+    let bucket_entry_json = serde_json::to_value(tantivy_bucket_entry)?;
+    extend_with_sub_aggregation(bucket_entry_json, sub_aggregation)
+}
+
+fn render_composite_bucket_entry(
+    bucket_entry: CompositeBucketEntryProxy,
+) -> serde_json::Result<JsonValue> {
+    let mut bucket_entry_json = JsonMap::new();
+    let key_json = JsonValue::Object(composite_key_to_json(bucket_entry.key));
+    bucket_entry_json.insert("key".to_string(), key_json);
+    bucket_entry_json.insert("doc_count".to_string(), bucket_entry.doc_count.into());
+    extend_with_sub_aggregation(
+        JsonValue::Object(bucket_entry_json),
+        bucket_entry.sub_aggregation,
+    )
+}
+
+fn render_significant_terms_bucket_entry(
+    bucket_entry: SignificantTermsBucketEntryProxy,
+) -> serde_json::Result<JsonValue> {
+    let mut bucket_entry_json = JsonMap::new();
+    let key_json = serde_json::to_value(TantivyKey::from(bucket_entry.key))?;
+    bucket_entry_json.insert("key".to_string(), key_json);
+    bucket_entry_json.insert("doc_count".to_string(), bucket_entry.doc_count.into());
+    bucket_entry_json.insert("score".to_string(), bucket_entry.score.into());
+    bucket_entry_json.insert("bg_count".to_string(), bucket_entry.bg_count.into());
+    extend_with_sub_aggregation(
+        JsonValue::Object(bucket_entry_json),
+        bucket_entry.sub_aggregation,
+    )
+}
+
+fn extend_with_sub_aggregation(
+    mut bucket_entry_json: JsonValue,
+    sub_aggregation: AggregationResultsProxy,
+) -> serde_json::Result<JsonValue> {
+    if let Some(bucket_entry_json_obj) = bucket_entry_json.as_object_mut() {
+        bucket_entry_json_obj.extend(render_aggregation_results(sub_aggregation)?);
+    }
+    Ok(bucket_entry_json)
+}
+
+fn composite_key_to_json(composite_key: CompositeKey) -> JsonMap<String, JsonValue> {
+    composite_key
+        .into_iter()
+        .map(|(source_name, value)| {
+            let value_json = match value {
+                CompositeKeyValue::Null => JsonValue::Null,
+                CompositeKeyValue::Bool(value) => JsonValue::from(value),
+                CompositeKeyValue::I64(value) => JsonValue::from(value),
+                CompositeKeyValue::U64(value) => JsonValue::from(value),
+                CompositeKeyValue::F64(value) => JsonValue::from(value),
+                CompositeKeyValue::Str(value) => JsonValue::from(value),
+            };
+            (source_name, value_json)
+        })
+        .collect()
+}
+
 /// SearchResponseRest represents the response returned by the REST search API
 /// and is meant to be serialized into JSON.
 #[derive(Serialize, PartialEq, Debug, utoipa::ToSchema)]
@@ -50,6 +283,10 @@ pub struct SearchResponseRest {
     #[schema(value_type = Vec<Object>)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub snippets: Option<Vec<JsonValue>>,
+    /// List of requested runtime field values, one object per hit.
+    #[schema(value_type = Vec<Object>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Vec<JsonValue>>,
     /// Elapsed time.
     pub elapsed_time_micros: u64,
     /// Search errors.
@@ -58,6 +295,13 @@ pub struct SearchResponseRest {
     #[schema(value_type = Object)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aggregations: Option<AggregationResults>,
+    /// Execution profile of the search, only set if requested.
+    #[schema(value_type = Object)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<SearchProfile>,
+    /// Warnings about the execution of the search, e.g. the cost of runtime fields.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 impl TryFrom<SearchResponse> for SearchResponseRest {
@@ -66,6 +310,7 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
     fn try_from(search_response: SearchResponse) -> Result<Self, Self::Error> {
         let mut documents = Vec::with_capacity(search_response.hits.len());
         let mut snippets = Vec::new();
+        let mut fields = Vec::new();
         for hit in search_response.hits {
             let document: JsonValue = serde_json::from_str(&hit.json).map_err(|err| {
                 SearchError::Internal(format!(
@@ -85,6 +330,14 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
                     })?;
                 snippets.push(snippet_opt);
             }
+            if let Some(fields_json) = hit.fields_json {
+                let hit_fields: JsonValue = serde_json::from_str(&fields_json).map_err(|err| {
+                    SearchError::Internal(format!(
+                        "failed to serialize fields `{fields_json}` to JSON: `{err}`"
+                    ))
+                })?;
+                fields.push(hit_fields);
+            }
         }
 
         let snippet_opt = if !snippets.is_empty() {
@@ -92,6 +345,11 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
         } else {
             None
         };
+        let fields_opt = if !fields.is_empty() {
+            Some(fields)
+        } else {
+            None
+        };
 
         let aggregations_opt =
             if let Some(aggregation_postcard) = search_response.aggregation_postcard {
@@ -106,9 +364,12 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
             num_hits: search_response.num_hits,
             hits: documents,
             snippets: snippet_opt,
+            fields: fields_opt,
             elapsed_time_micros: search_response.elapsed_time_micros,
             errors: search_response.errors,
             aggregations: aggregations_opt,
+            profile: search_response.profile,
+            warnings: search_response.warnings,
         })
     }
 }
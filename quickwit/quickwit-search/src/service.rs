@@ -42,7 +42,12 @@ use crate::metrics_trackers::LeafSearchMetricsFuture;
 use crate::root::fetch_docs_phase;
 use crate::scroll_context::{MiniKV, ScrollContext, ScrollKeyAndStartOffset};
 use crate::search_permit_provider::SearchPermitProvider;
-use crate::{ClusterClient, SearchError, fetch_docs, root_search, search_plan};
+use crate::slow_query_log::SlowQueryLogger;
+use crate::{
+    AsyncSearchRequest, AsyncSearchResponse, ClusterClient, GetAsyncSearchRequest, HitStream,
+    SearchError, delete_async_search, fetch_docs, get_async_search, root_export, root_search,
+    search_plan, submit_async_search,
+};
 
 #[derive(Clone)]
 /// The search service implementation.
@@ -102,6 +107,30 @@ pub trait SearchService: 'static + Send + Sync {
         request: LeafListTermsRequest,
     ) -> crate::Result<LeafListTermsResponse>;
 
+    /// Exports all the documents matching a search request as a stream of hits.
+    ///
+    /// Splits are read page by page so that the memory used by the export does not depend on
+    /// the number of matching documents.
+    async fn root_export(&self, request: SearchRequest) -> crate::Result<HitStream>;
+
+    /// Submits a search running in the background of this searcher.
+    ///
+    /// The state of the search and its latest, possibly partial, results are stored in the
+    /// search KV store, so that they can be fetched from any searcher until they expire.
+    async fn submit_async_search(
+        &self,
+        request: AsyncSearchRequest,
+    ) -> crate::Result<AsyncSearchResponse>;
+
+    /// Returns the state and the latest results of an async search.
+    async fn get_async_search(
+        &self,
+        request: GetAsyncSearchRequest,
+    ) -> crate::Result<AsyncSearchResponse>;
+
+    /// Deletes an async search and its results, stopping it if it is still running.
+    async fn delete_async_search(&self, id: String) -> crate::Result<()>;
+
     /// Performs a scroll request.
     async fn scroll(&self, scroll_request: ScrollRequest) -> crate::Result<SearchResponse>;
 
@@ -216,6 +245,7 @@ impl SearchService for SearchServiceImpl {
             &fetch_docs_request.split_offsets,
             doc_mapper,
             snippet_request_opt,
+            fetch_docs_request.profile,
         )
         .await?;
 
@@ -258,6 +288,39 @@ impl SearchService for SearchServiceImpl {
         Ok(leaf_search_response)
     }
 
+    async fn root_export(&self, search_request: SearchRequest) -> crate::Result<HitStream> {
+        root_export(
+            search_request,
+            self.metastore.clone(),
+            self.cluster_client.clone(),
+        )
+        .await
+    }
+
+    async fn submit_async_search(
+        &self,
+        async_search_request: AsyncSearchRequest,
+    ) -> crate::Result<AsyncSearchResponse> {
+        submit_async_search(
+            async_search_request,
+            self.searcher_context.clone(),
+            self.metastore.clone(),
+            self.cluster_client.clone(),
+        )
+        .await
+    }
+
+    async fn get_async_search(
+        &self,
+        get_async_search_request: GetAsyncSearchRequest,
+    ) -> crate::Result<AsyncSearchResponse> {
+        get_async_search(get_async_search_request, &self.cluster_client).await
+    }
+
+    async fn delete_async_search(&self, id: String) -> crate::Result<()> {
+        delete_async_search(id, &self.cluster_client).await
+    }
+
     async fn scroll(&self, scroll_request: ScrollRequest) -> crate::Result<SearchResponse> {
         scroll(scroll_request, &self.cluster_client, &self.searcher_context).await
     }
@@ -358,7 +421,7 @@ pub(crate) async fn scroll(
     }
 
     // Fetch the actual documents.
-    let hits: Vec<Hit> = fetch_docs_phase(
+    let (hits, _): (Vec<Hit>, _) = fetch_docs_phase(
         &scroll_context.indexes_metas_for_leaf_search,
         &partial_hits[..],
         &scroll_context.split_metadatas[..],
@@ -392,6 +455,8 @@ pub(crate) async fn scroll(
         aggregation_postcard: None,
         failed_splits: scroll_context.failed_splits,
         num_successful_splits: scroll_context.num_successful_splits,
+        profile: None,
+        warnings: Vec::new(),
     })
 }
 /// [`SearcherContext`] provides a common set of variables
@@ -416,6 +481,8 @@ pub struct SearcherContext {
     pub list_fields_cache: ListFieldsCache,
     /// The aggregation limits are passed to limit the memory usage.
     pub aggregation_limit: AggregationLimitsGuard,
+    /// Logs the root searches exceeding the configured slow query threshold.
+    pub slow_query_logger: SlowQueryLogger,
 }
 
 impl std::fmt::Debug for SearcherContext {
@@ -453,6 +520,7 @@ impl SearcherContext {
             Some(searcher_config.aggregation_memory_limit.as_u64()),
             Some(searcher_config.aggregation_bucket_limit),
         );
+        let slow_query_logger = SlowQueryLogger::new(searcher_config.slow_query_log.clone());
 
         Self {
             searcher_config,
@@ -464,6 +532,7 @@ impl SearcherContext {
             list_fields_cache,
             split_cache_opt,
             aggregation_limit,
+            slow_query_logger,
         }
     }
 
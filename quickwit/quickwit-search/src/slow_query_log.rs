@@ -0,0 +1,374 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use quickwit_common::uri::Uri;
+use quickwit_config::{
+    ConfigFormat, IndexConfig, SlowQueryLogConfig, load_index_config_from_user_config,
+};
+use quickwit_proto::search::{LeafSearchResponse, SearchRequest, SplitSearchStats};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// ID of the index in which slow query records are ingested, if enabled.
+pub const SLOW_QUERY_LOG_INDEX_ID: &str = "quickwit-slowlog";
+
+/// Tracing target of the slow query log records.
+pub const SLOW_QUERY_LOG_TARGET: &str = "quickwit_search::slow_query_log";
+
+/// Capacity of the channel buffering the records waiting to be ingested. Records are dropped
+/// when it is full: we never want the slow query log to slow down searches further.
+const SLOW_QUERY_LOG_CHANNEL_CAPACITY: usize = 1_000;
+
+const SLOW_QUERY_LOG_INDEX_CONFIG: &str = r#"
+version: 0.8
+
+index_id: ${INDEX_ID}
+
+doc_mapping:
+  mode: lenient
+  field_mappings:
+    - name: timestamp_micros
+      type: datetime
+      input_formats: [unix_timestamp]
+      output_format: unix_timestamp_micros
+      fast: true
+      fast_precision: milliseconds
+    - name: index_id_patterns
+      type: array<text>
+      tokenizer: raw
+      fast: true
+    - name: query_ast
+      type: text
+      tokenizer: default
+    - name: aggregation_request
+      type: text
+      tokenizer: default
+    - name: elapsed_micros
+      type: u64
+      fast: true
+    - name: fetch_docs_micros
+      type: u64
+      fast: true
+    - name: num_splits
+      type: u64
+      fast: true
+    - name: num_docs
+      type: u64
+      fast: true
+    - name: num_hits
+      type: u64
+      fast: true
+    - name: num_failed_splits
+      type: u64
+      fast: true
+    - name: warmup_num_bytes
+      type: u64
+      fast: true
+    - name: warmup_micros
+      type: u64
+      fast: true
+    - name: cpu_micros
+      type: u64
+      fast: true
+    - name: slowest_splits
+      type: json
+      tokenizer: raw
+      fast: true
+
+  timestamp_field: timestamp_micros
+
+retention:
+  period: 30 days
+  schedule: daily
+"#;
+
+/// Returns the config of the index in which slow query records are ingested.
+pub fn slow_query_log_index_config(default_index_root_uri: &Uri) -> anyhow::Result<IndexConfig> {
+    let index_config_str =
+        SLOW_QUERY_LOG_INDEX_CONFIG.replace("${INDEX_ID}", SLOW_QUERY_LOG_INDEX_ID);
+    let index_config = load_index_config_from_user_config(
+        ConfigFormat::Yaml,
+        index_config_str.as_bytes(),
+        default_index_root_uri,
+    )?;
+    Ok(index_config)
+}
+
+/// A record of the slow query log.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SlowQueryLogRecord {
+    /// Time at which the query completed.
+    pub timestamp_micros: u64,
+    /// Index ID patterns targeted by the query.
+    pub index_id_patterns: Vec<String>,
+    /// JSON serialized query AST.
+    pub query_ast: String,
+    /// JSON serialized aggregation request, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregation_request: Option<String>,
+    /// Total time spent by the root searcher on the query.
+    pub elapsed_micros: u64,
+    /// Time spent fetching the documents of the hits.
+    pub fetch_docs_micros: u64,
+    /// Number of splits targeted by the query.
+    pub num_splits: u64,
+    /// Number of documents in the targeted splits.
+    pub num_docs: u64,
+    /// Number of hits matching the query.
+    pub num_hits: u64,
+    /// Number of splits the search failed on.
+    pub num_failed_splits: u64,
+    /// Number of bytes downloaded to warm up the splits, summed over all splits.
+    pub warmup_num_bytes: u64,
+    /// Time spent warming up the splits, summed over all splits.
+    pub warmup_micros: u64,
+    /// CPU time spent searching the splits, summed over all splits.
+    pub cpu_micros: u64,
+    /// Timing breakdown of the slowest splits.
+    pub slowest_splits: Vec<SplitSearchStats>,
+}
+
+/// Everything the root searcher knows about a query once it has completed.
+pub(crate) struct CompletedRootSearch<'a> {
+    pub search_request: &'a SearchRequest,
+    pub leaf_search_response: &'a LeafSearchResponse,
+    pub num_splits: usize,
+    pub num_docs: usize,
+    pub elapsed: Duration,
+    pub fetch_docs_elapsed: Duration,
+}
+
+/// Logs the root searches exceeding the configured threshold.
+///
+/// Records are always emitted under the [`SLOW_QUERY_LOG_TARGET`] tracing target, and are also
+/// forwarded to a record sink if one is set, so that they can be ingested into the
+/// [`SLOW_QUERY_LOG_INDEX_ID`] index.
+#[derive(Clone, Default)]
+pub struct SlowQueryLogger {
+    config_opt: Option<SlowQueryLogConfig>,
+    record_tx_opt: Option<mpsc::Sender<SlowQueryLogRecord>>,
+}
+
+impl SlowQueryLogger {
+    /// Creates a new slow query logger. The logger is disabled if `config_opt` is `None`.
+    pub fn new(config_opt: Option<SlowQueryLogConfig>) -> Self {
+        SlowQueryLogger {
+            config_opt,
+            record_tx_opt: None,
+        }
+    }
+
+    /// Returns true if records should be ingested into the slow query log index.
+    pub fn is_index_enabled(&self) -> bool {
+        self.config_opt
+            .as_ref()
+            .map(|config| config.enable_index)
+            .unwrap_or(false)
+    }
+
+    /// Creates the sink to which records are forwarded and returns its receiving end.
+    pub fn create_record_sink(&mut self) -> mpsc::Receiver<SlowQueryLogRecord> {
+        let (record_tx, record_rx) = mpsc::channel(SLOW_QUERY_LOG_CHANNEL_CAPACITY);
+        self.record_tx_opt = Some(record_tx);
+        record_rx
+    }
+
+    pub(crate) fn log_if_slow(&self, completed_search: CompletedRootSearch) {
+        let Some(config) = &self.config_opt else {
+            return;
+        };
+        if completed_search.elapsed < config.threshold() {
+            return;
+        }
+        if config.sampling_rate < 1.0 && rand::random::<f64>() >= config.sampling_rate {
+            return;
+        }
+        let record = build_slow_query_log_record(completed_search, config.num_slowest_splits);
+        let slowest_splits_json = serde_json::to_string(&record.slowest_splits)
+            .expect("split search stats should be JSON serializable");
+        tracing::info!(
+            target: SLOW_QUERY_LOG_TARGET,
+            index_id_patterns = ?record.index_id_patterns,
+            query_ast = %record.query_ast,
+            aggregation_request = record.aggregation_request.as_deref(),
+            elapsed_micros = record.elapsed_micros,
+            fetch_docs_micros = record.fetch_docs_micros,
+            num_splits = record.num_splits,
+            num_docs = record.num_docs,
+            num_hits = record.num_hits,
+            num_failed_splits = record.num_failed_splits,
+            warmup_num_bytes = record.warmup_num_bytes,
+            warmup_micros = record.warmup_micros,
+            cpu_micros = record.cpu_micros,
+            slowest_splits = %slowest_splits_json,
+            "slow query"
+        );
+        if let Some(record_tx) = &self.record_tx_opt
+            && record_tx.try_send(record).is_err()
+        {
+            quickwit_common::rate_limited_warn!(
+                limit_per_min = 1,
+                "slow query log sink is full or closed, dropping record"
+            );
+        }
+    }
+}
+
+fn build_slow_query_log_record(
+    completed_search: CompletedRootSearch,
+    num_slowest_splits: usize,
+) -> SlowQueryLogRecord {
+    let CompletedRootSearch {
+        search_request,
+        leaf_search_response,
+        num_splits,
+        num_docs,
+        elapsed,
+        fetch_docs_elapsed,
+    } = completed_search;
+    let timestamp_micros = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_micros() as u64)
+        .unwrap_or_else(|_| {
+            warn!("system time is before the UNIX epoch");
+            0
+        });
+    let resource_stats = leaf_search_response.resource_stats.unwrap_or_default();
+    let slowest_splits = leaf_search_response
+        .slowest_splits
+        .iter()
+        .take(num_slowest_splits)
+        .cloned()
+        .collect();
+    SlowQueryLogRecord {
+        timestamp_micros,
+        index_id_patterns: search_request.index_id_patterns.clone(),
+        query_ast: search_request.query_ast.clone(),
+        aggregation_request: search_request.aggregation_request.clone(),
+        elapsed_micros: elapsed.as_micros() as u64,
+        fetch_docs_micros: fetch_docs_elapsed.as_micros() as u64,
+        num_splits: num_splits as u64,
+        num_docs: num_docs as u64,
+        num_hits: leaf_search_response.num_hits,
+        num_failed_splits: leaf_search_response.failed_splits.len() as u64,
+        warmup_num_bytes: resource_stats.short_lived_cache_num_bytes,
+        warmup_micros: resource_stats.warmup_microsecs,
+        cpu_micros: resource_stats.cpu_microsecs,
+        slowest_splits,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::search::ResourceStats;
+
+    use super::*;
+
+    fn slow_query_log_config(threshold_millis: u64) -> SlowQueryLogConfig {
+        serde_json::from_value(serde_json::json!({
+            "threshold_millis": threshold_millis,
+            "num_slowest_splits": 1,
+        }))
+        .unwrap()
+    }
+
+    fn split_search_stats(split_id: &str, elapsed_microsecs: u64) -> SplitSearchStats {
+        SplitSearchStats {
+            split_id: split_id.to_string(),
+            leaf_node_id: "127.0.0.1:7281".to_string(),
+            elapsed_microsecs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_slow_query_log_index_config() {
+        let index_config = slow_query_log_index_config(&Uri::for_test("ram:///indexes")).unwrap();
+        assert_eq!(index_config.index_id, SLOW_QUERY_LOG_INDEX_ID);
+        assert_eq!(
+            index_config.doc_mapping.timestamp_field.as_deref(),
+            Some("timestamp_micros")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slow_query_logger() {
+        let search_request = SearchRequest {
+            index_id_patterns: vec!["my-index-*".to_string()],
+            query_ast: r#"{"type":"match_all"}"#.to_string(),
+            ..Default::default()
+        };
+        let leaf_search_response = LeafSearchResponse {
+            num_hits: 42,
+            resource_stats: Some(ResourceStats {
+                short_lived_cache_num_bytes: 1_000,
+                warmup_microsecs: 2_000,
+                ..Default::default()
+            }),
+            slowest_splits: vec![
+                split_search_stats("split-2", 200),
+                split_search_stats("split-1", 100),
+            ],
+            ..Default::default()
+        };
+        let mut slow_query_logger = SlowQueryLogger::new(Some(slow_query_log_config(1_000)));
+        let mut record_rx = slow_query_logger.create_record_sink();
+
+        slow_query_logger.log_if_slow(CompletedRootSearch {
+            search_request: &search_request,
+            leaf_search_response: &leaf_search_response,
+            num_splits: 2,
+            num_docs: 100,
+            elapsed: Duration::from_millis(10),
+            fetch_docs_elapsed: Duration::from_millis(1),
+        });
+        assert!(record_rx.try_recv().is_err());
+
+        slow_query_logger.log_if_slow(CompletedRootSearch {
+            search_request: &search_request,
+            leaf_search_response: &leaf_search_response,
+            num_splits: 2,
+            num_docs: 100,
+            elapsed: Duration::from_secs(2),
+            fetch_docs_elapsed: Duration::from_millis(1),
+        });
+        let record = record_rx.try_recv().unwrap();
+        assert_eq!(record.index_id_patterns, ["my-index-*"]);
+        assert_eq!(record.elapsed_micros, 2_000_000);
+        assert_eq!(record.fetch_docs_micros, 1_000);
+        assert_eq!(record.num_splits, 2);
+        assert_eq!(record.num_hits, 42);
+        assert_eq!(record.warmup_num_bytes, 1_000);
+        assert_eq!(record.warmup_micros, 2_000);
+        assert_eq!(record.slowest_splits, [split_search_stats("split-2", 200)]);
+    }
+
+    #[test]
+    fn test_slow_query_logger_disabled() {
+        let slow_query_logger = SlowQueryLogger::new(None);
+        assert!(!slow_query_logger.is_index_enabled());
+        // Logging without any config or sink is a no-op.
+        slow_query_logger.log_if_slow(CompletedRootSearch {
+            search_request: &SearchRequest::default(),
+            leaf_search_response: &LeafSearchResponse::default(),
+            num_splits: 0,
+            num_docs: 0,
+            elapsed: Duration::from_secs(60),
+            fetch_docs_elapsed: Duration::ZERO,
+        });
+    }
+}
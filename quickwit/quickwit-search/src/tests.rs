@@ -83,6 +83,65 @@ async fn test_single_node_simple() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_search_profile() -> anyhow::Result<()> {
+    let index_id = "single-node-search-profile";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: title
+                type: text
+              - name: body
+                type: text
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    let docs = vec![
+        json!({"title": "snoopy", "body": "Snoopy is an anthropomorphic beagle in the comic strip..."}),
+        json!({"title": "beagle", "body": "The beagle is a breed of small scent hound."}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("beagle", &["body"]),
+        max_hits: 1,
+        profile: true,
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        search_request.clone(),
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    assert_eq!(single_node_result.num_hits, 2);
+
+    let search_profile = single_node_result.profile.unwrap();
+    assert_eq!(search_profile.leaf_nodes.len(), 1);
+
+    let leaf_node_profile = &search_profile.leaf_nodes[0];
+    assert_eq!(leaf_node_profile.leaf_node_id, "127.0.0.1:7280");
+    assert_eq!(leaf_node_profile.splits.len(), 1);
+
+    let split_profile = &leaf_node_profile.splits[0];
+    assert!(!split_profile.cache_hit);
+    assert_eq!(split_profile.num_docs, 2);
+    assert_eq!(split_profile.num_fetched_docs, 1);
+    assert!(split_profile.hotcache_num_bytes > 0);
+    assert!(split_profile.elapsed_microsecs >= split_profile.open_split_microsecs);
+
+    let single_node_result = single_node_search(
+        SearchRequest {
+            profile: false,
+            ..search_request
+        },
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    assert!(single_node_result.profile.is_none());
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_termset() -> anyhow::Result<()> {
     let index_id = "single-node-termset-1";
@@ -1388,6 +1447,255 @@ async fn test_single_node_aggregation() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_composite_aggregation() -> anyhow::Result<()> {
+    let index_id = "single-node-composite-agg";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: tenant
+                type: text
+                tokenizer: raw
+                fast: true
+              - name: timestamp
+                type: datetime
+                fast: true
+              - name: num_bytes
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["tenant"]).await?;
+    // Two batches, to merge the buckets of two splits.
+    test_sandbox
+        .add_documents(vec![
+            json!({"tenant": "acme", "timestamp": 1_700_000_000, "num_bytes": 10}),
+            json!({"tenant": "acme", "timestamp": 1_700_003_600, "num_bytes": 20}),
+            json!({"tenant": "globex", "timestamp": 1_700_100_000, "num_bytes": 5}),
+            json!({"timestamp": 1_700_100_000, "num_bytes": 1}),
+        ])
+        .await?;
+    test_sandbox
+        .add_documents(vec![
+            json!({"tenant": "acme", "timestamp": 1_700_100_000, "num_bytes": 30}),
+            json!({"tenant": "initech", "timestamp": 1_700_000_000, "num_bytes": 40}),
+        ])
+        .await?;
+
+    let composite_page = |after: Option<JsonValue>| {
+        let mut composite = json!({
+            "size": 2,
+            "sources": [
+                {"tenant": {"terms": {"field": "tenant"}}},
+                {"day": {"date_histogram": {"field": "timestamp", "fixed_interval": "1d"}}}
+            ]
+        });
+        if let Some(after) = after {
+            composite["after"] = after;
+        }
+        let agg_req = json!({
+            "by_tenant_day": {
+                "composite": composite,
+                "aggs": {"bytes": {"sum": {"field": "num_bytes"}}}
+            }
+        });
+        let search_request = SearchRequest {
+            index_id_patterns: vec![index_id.to_string()],
+            query_ast: qast_json_helper("*", &[]),
+            aggregation_request: Some(agg_req.to_string()),
+            ..Default::default()
+        };
+        single_node_search(
+            search_request,
+            test_sandbox.metastore(),
+            test_sandbox.storage_resolver(),
+        )
+    };
+    let aggregations_json = |search_response: quickwit_proto::search::SearchResponse| {
+        let aggregations =
+            AggregationResults::from_postcard(&search_response.aggregation_postcard.unwrap())
+                .unwrap();
+        serde_json::to_value(aggregations).unwrap()
+    };
+    let first_page = aggregations_json(composite_page(None).await?);
+    assert_json_eq!(
+        first_page["by_tenant_day"],
+        json!({
+            "after_key": {"tenant": "acme", "day": 1_700_092_800_000i64},
+            "buckets": [
+                {
+                    "key": {"tenant": "acme", "day": 1_699_920_000_000i64},
+                    "doc_count": 2,
+                    "bytes": {"value": 30.0}
+                },
+                {
+                    "key": {"tenant": "acme", "day": 1_700_092_800_000i64},
+                    "doc_count": 1,
+                    "bytes": {"value": 30.0}
+                }
+            ]
+        })
+    );
+    let second_page = aggregations_json(
+        composite_page(Some(first_page["by_tenant_day"]["after_key"].clone())).await?,
+    );
+    let second_page_keys: Vec<&JsonValue> = second_page["by_tenant_day"]["buckets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bucket| &bucket["key"]["tenant"])
+        .collect();
+    assert_eq!(second_page_keys, [&json!("globex"), &json!("initech")]);
+
+    let last_page = aggregations_json(
+        composite_page(Some(second_page["by_tenant_day"]["after_key"].clone())).await?,
+    );
+    assert_json_eq!(last_page["by_tenant_day"], json!({"buckets": []}));
+
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_pipeline_aggregations() -> anyhow::Result<()> {
+    let index_id = "single-node-pipeline-agg";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: hour
+                type: u64
+                fast: true
+              - name: price
+                type: f64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &[]).await?;
+    test_sandbox
+        .add_documents(vec![
+            json!({"hour": 0, "price": 10.0}),
+            json!({"hour": 0, "price": 20.0}),
+            json!({"hour": 1, "price": 5.0}),
+            json!({"hour": 3, "price": 40.0}),
+        ])
+        .await?;
+    let agg_req = json!({
+        "by_hour": {
+            "histogram": {"field": "hour", "interval": 1},
+            "aggs": {
+                "sales": {"sum": {"field": "price"}},
+                "sales_deriv": {"derivative": {"buckets_path": "sales"}},
+                "cumulative_sales": {"cumulative_sum": {"buckets_path": "sales"}}
+            }
+        },
+        "max_sales": {"max_bucket": {"buckets_path": "by_hour>sales"}}
+    });
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("*", &[]),
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let search_response = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    let aggregations =
+        AggregationResults::from_postcard(&search_response.aggregation_postcard.unwrap())?;
+    let aggregations_json = serde_json::to_value(aggregations)?;
+
+    assert_json_eq!(
+        aggregations_json["max_sales"],
+        json!({"value": 40.0, "keys": ["3"]})
+    );
+    let buckets = aggregations_json["by_hour"]["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 4);
+    assert!(buckets[0].get("sales_deriv").is_none());
+    assert_json_eq!(buckets[1]["sales_deriv"], json!({"value": -25.0}));
+    // The empty bucket is a gap.
+    assert!(buckets[2].get("sales_deriv").is_none());
+    assert_json_eq!(buckets[3]["sales_deriv"], json!({"value": 35.0}));
+
+    let cumulative_sales: Vec<&JsonValue> = buckets
+        .iter()
+        .map(|bucket| &bucket["cumulative_sales"]["value"])
+        .collect();
+    assert_eq!(
+        cumulative_sales,
+        [&json!(30.0), &json!(35.0), &json!(35.0), &json!(75.0)]
+    );
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_significant_and_rare_terms_aggregations() -> anyhow::Result<()> {
+    let index_id = "single-node-significant-rare-terms";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: service
+                type: text
+                tokenizer: raw
+              - name: status
+                type: text
+                tokenizer: raw
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &[]).await?;
+    let mut docs = Vec::new();
+    for (service, status, num_docs) in [
+        ("api", "500", 4),
+        ("api", "404", 1),
+        ("api", "200", 5),
+        ("web", "404", 10),
+        ("web", "200", 20),
+    ] {
+        for _ in 0..num_docs {
+            docs.push(json!({"service": service, "status": status}));
+        }
+    }
+    test_sandbox.add_documents(docs).await?;
+
+    let agg_req = json!({
+        "over_represented": {"significant_terms": {"field": "status"}},
+        "rare_statuses": {"rare_terms": {"field": "status"}}
+    });
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("service:api", &[]),
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let search_response = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 10);
+    let aggregations =
+        AggregationResults::from_postcard(&search_response.aggregation_postcard.unwrap())?;
+    let aggregations_json = serde_json::to_value(aggregations)?;
+
+    // `500` is 40% of the foreground set and 10% of the background set. `200` is less frequent
+    // in the foreground set, and `404` is below the minimum doc count.
+    let significant_terms_json = &aggregations_json["over_represented"];
+    assert_eq!(significant_terms_json["doc_count"], 10);
+    assert_eq!(significant_terms_json["bg_count"], 40);
+    let buckets = significant_terms_json["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 1);
+    assert_eq!(buckets[0]["key"], "500");
+    assert_eq!(buckets[0]["doc_count"], 4);
+    assert_eq!(buckets[0]["bg_count"], 4);
+    let score = buckets[0]["score"].as_f64().unwrap();
+    assert!((score - 1.2).abs() < 1e-9);
+
+    assert_json_eq!(
+        aggregations_json["rare_statuses"],
+        json!({"buckets": [{"key": "404", "doc_count": 1}]})
+    );
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_aggregation_missing_fast_field() {
     let index_id = "single-node-agg-2";
@@ -0,0 +1,1028 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `significant_terms` and `rare_terms` aggregations are computed by the root searcher in two
+//! coordinated phases. The aggregation is first replaced with a terms aggregation collecting
+//! candidate terms, then a second search restricted to the candidates computes the counts the
+//! final buckets are made of.
+//!
+//! For `significant_terms`, the candidates are the most frequent terms of the foreground set, i.e.
+//! the documents matching the query. The second phase counts them in the background set, all the
+//! documents of the targeted indexes unless a `background_filter` is specified, and each candidate
+//! is scored by comparing its frequency in both sets.
+//!
+//! For `rare_terms`, the candidates are the least frequent terms of each split. The count of a
+//! term is underestimated when a split drops it for being frequent in that split, so the second
+//! phase counts the candidates exactly on the documents matching the query.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::time::Instant;
+
+use quickwit_proto::metastore::MetastoreServiceClient;
+use quickwit_proto::search::{CountHits, SearchRequest, SearchResponse};
+use quickwit_query::ElasticQueryDsl;
+use quickwit_query::aggregations::{
+    AggregationResult, AggregationResults, BucketEntry, BucketResult, Key,
+    SignificantTermsBucketEntry,
+};
+use quickwit_query::query_ast::{BoolQuery, QueryAst, TermSetQuery};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map as JsonMap, Value as JsonValue, json};
+
+use crate::cluster_client::ClusterClient;
+use crate::error::SearchError;
+use crate::root::{check_num_targeted_splits, plan_splits_for_root_search, root_search_aux};
+use crate::service::SearcherContext;
+
+const DEFAULT_SIGNIFICANT_TERMS_SIZE: usize = 10;
+
+const DEFAULT_SIGNIFICANT_TERMS_SHARD_SIZE: usize = 1_000;
+
+const DEFAULT_SIGNIFICANT_TERMS_MIN_DOC_COUNT: u64 = 3;
+
+const DEFAULT_RARE_TERMS_MAX_DOC_COUNT: u64 = 1;
+
+/// Same limit as Elasticsearch.
+const MAX_RARE_TERMS_MAX_DOC_COUNT: u64 = 100;
+
+/// Maximum number of candidate terms collected by the first phase.
+const MAX_CANDIDATE_TERMS: usize = 10_000;
+
+/// Name of the terms aggregation of the second phase searches.
+const CANDIDATES_AGGREGATION_NAME: &str = "candidates";
+
+fn default_significant_terms_size() -> usize {
+    DEFAULT_SIGNIFICANT_TERMS_SIZE
+}
+
+fn default_significant_terms_min_doc_count() -> u64 {
+    DEFAULT_SIGNIFICANT_TERMS_MIN_DOC_COUNT
+}
+
+fn default_rare_terms_max_doc_count() -> u64 {
+    DEFAULT_RARE_TERMS_MAX_DOC_COUNT
+}
+
+fn default_background_is_superset() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignificantTermsRequest {
+    field: String,
+    #[serde(default = "default_significant_terms_size")]
+    size: usize,
+    /// Number of candidate terms, the most frequent terms of the foreground set.
+    #[serde(default)]
+    shard_size: Option<usize>,
+    #[serde(default = "default_significant_terms_min_doc_count")]
+    min_doc_count: u64,
+    #[serde(default)]
+    background_filter: Option<ElasticQueryDsl>,
+    #[serde(default)]
+    jlh: Option<JlhParams>,
+    #[serde(default)]
+    chi_square: Option<ChiSquareParams>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JlhParams {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChiSquareParams {
+    #[serde(default)]
+    include_negatives: bool,
+    #[serde(default = "default_background_is_superset")]
+    background_is_superset: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RareTermsRequest {
+    field: String,
+    #[serde(default = "default_rare_terms_max_doc_count")]
+    max_doc_count: u64,
+}
+
+/// Scores the frequency of a term in the foreground set (subset) against its frequency in the
+/// background set (superset), with the same formulas as Elasticsearch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SignificanceHeuristic {
+    Jlh,
+    ChiSquare {
+        include_negatives: bool,
+        background_is_superset: bool,
+    },
+}
+
+impl SignificanceHeuristic {
+    /// Returns `None` if the term is not significant.
+    fn score(
+        &self,
+        subset_freq: u64,
+        subset_size: u64,
+        superset_freq: u64,
+        superset_size: u64,
+    ) -> Option<f64> {
+        if subset_size == 0 || superset_size == 0 {
+            return None;
+        }
+        match *self {
+            SignificanceHeuristic::Jlh => {
+                // A term missing from the background set is counted once, to avoid dividing by
+                // zero.
+                let superset_freq = superset_freq.max(1);
+                let subset_probability = subset_freq as f64 / subset_size as f64;
+                let superset_probability = superset_freq as f64 / superset_size as f64;
+                let absolute_probability_change = subset_probability - superset_probability;
+
+                if absolute_probability_change <= 0.0 {
+                    return None;
+                }
+                let relative_probability_change = subset_probability / superset_probability;
+                Some(absolute_probability_change * relative_probability_change)
+            }
+            SignificanceHeuristic::ChiSquare {
+                include_negatives,
+                background_is_superset,
+            } => {
+                let frequencies = Frequencies::new(
+                    subset_freq as f64,
+                    subset_size as f64,
+                    superset_freq as f64,
+                    superset_size as f64,
+                    background_is_superset,
+                );
+                // The term is less frequent in the foreground set than in the rest of the
+                // background set.
+                if !include_negatives
+                    && frequencies.n11 / frequencies.n_1 < frequencies.n10 / frequencies.n_0
+                {
+                    return None;
+                }
+                let denominator =
+                    frequencies.n_1 * frequencies.n1_ * frequencies.n0_ * frequencies.n_0;
+                if denominator <= 0.0 {
+                    return None;
+                }
+                let numerator = frequencies.n
+                    * (frequencies.n11 * frequencies.n00 - frequencies.n01 * frequencies.n10)
+                        .powi(2);
+                Some(numerator / denominator)
+            }
+        }
+    }
+}
+
+/// The contingency table of a term. The first digit tells whether the documents contain the term,
+/// the second whether they belong to the foreground set, and `_` stands for both.
+struct Frequencies {
+    n00: f64,
+    n01: f64,
+    n10: f64,
+    n11: f64,
+    n0_: f64,
+    n1_: f64,
+    n_0: f64,
+    n_1: f64,
+    n: f64,
+}
+
+impl Frequencies {
+    fn new(
+        subset_freq: f64,
+        subset_size: f64,
+        superset_freq: f64,
+        superset_size: f64,
+        background_is_superset: bool,
+    ) -> Frequencies {
+        if background_is_superset {
+            Frequencies {
+                n00: superset_size - superset_freq - (subset_size - subset_freq),
+                n01: subset_size - subset_freq,
+                n10: superset_freq - subset_freq,
+                n11: subset_freq,
+                n0_: superset_size - superset_freq,
+                n1_: superset_freq,
+                n_0: superset_size - subset_size,
+                n_1: subset_size,
+                n: superset_size,
+            }
+        } else {
+            Frequencies {
+                n00: superset_size - superset_freq,
+                n01: subset_size - subset_freq,
+                n10: superset_freq,
+                n11: subset_freq,
+                n0_: superset_size - superset_freq + subset_size - subset_freq,
+                n1_: superset_freq + subset_freq,
+                n_0: superset_size,
+                n_1: subset_size,
+                n: superset_size + subset_size,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SignificantTerms {
+    field: String,
+    size: usize,
+    shard_size: usize,
+    min_doc_count: u64,
+    background_query_ast: QueryAst,
+    heuristic: SignificanceHeuristic,
+}
+
+impl SignificantTermsRequest {
+    fn compile(self) -> Result<SignificantTerms, String> {
+        let heuristic = match (self.jlh, self.chi_square) {
+            (Some(_), Some(_)) => {
+                return Err("only one significance heuristic can be specified".to_string());
+            }
+            (_, Some(chi_square_params)) => SignificanceHeuristic::ChiSquare {
+                include_negatives: chi_square_params.include_negatives,
+                background_is_superset: chi_square_params.background_is_superset,
+            },
+            (_, None) => SignificanceHeuristic::Jlh,
+        };
+        if self.size == 0 {
+            return Err("`size` must be strictly positive".to_string());
+        }
+        let shard_size = self
+            .shard_size
+            .unwrap_or(DEFAULT_SIGNIFICANT_TERMS_SHARD_SIZE)
+            .max(self.size);
+        if shard_size > MAX_CANDIDATE_TERMS {
+            return Err(format!(
+                "`shard_size` and `size` must not exceed {MAX_CANDIDATE_TERMS}"
+            ));
+        }
+        let background_query_ast = match self.background_filter {
+            Some(background_filter) => QueryAst::try_from(background_filter)
+                .map_err(|error| format!("invalid `background_filter`: {error}"))?,
+            None => QueryAst::MatchAll,
+        };
+        Ok(SignificantTerms {
+            field: self.field,
+            size: self.size,
+            shard_size,
+            min_doc_count: self.min_doc_count,
+            background_query_ast,
+            heuristic,
+        })
+    }
+}
+
+impl SignificantTerms {
+    /// The terms aggregation collecting the candidates in the foreground set. It keeps the
+    /// sub-aggregations of the `significant_terms` aggregation.
+    fn candidates_terms_json(&self) -> JsonValue {
+        json!({"field": self.field, "size": self.shard_size})
+    }
+
+    async fn compute(
+        &self,
+        two_phase_terms: &TwoPhaseTermsAggregations,
+        searcher_context: &SearcherContext,
+        metastore: &MetastoreServiceClient,
+        cluster_client: &ClusterClient,
+        foreground_count: u64,
+        candidate_buckets: Vec<BucketEntry>,
+    ) -> crate::Result<BucketResult> {
+        let candidate_buckets: Vec<BucketEntry> = candidate_buckets
+            .into_iter()
+            .filter(|bucket| bucket.doc_count >= self.min_doc_count)
+            .collect();
+        let candidate_terms: BTreeSet<String> = candidate_buckets
+            .iter()
+            .map(|bucket| key_to_term(&bucket.key))
+            .collect();
+
+        let mut background_count_request =
+            two_phase_terms.sub_request(&self.background_query_ast, None)?;
+        background_count_request.start_timestamp = None;
+        background_count_request.end_timestamp = None;
+        let background_count_future = search_sub_request(
+            searcher_context,
+            background_count_request,
+            metastore.clone(),
+            cluster_client,
+        );
+        let background_buckets_future = async {
+            if candidate_terms.is_empty() {
+                return Ok(Vec::new());
+            }
+            let query_ast = candidates_query_ast(
+                self.background_query_ast.clone(),
+                &self.field,
+                candidate_terms.clone(),
+            );
+            let aggregations_json = json!({
+                CANDIDATES_AGGREGATION_NAME: {
+                    "terms": {"field": self.field, "size": candidate_terms.len()}
+                }
+            });
+            let mut background_buckets_request =
+                two_phase_terms.sub_request(&query_ast, Some(aggregations_json))?;
+            background_buckets_request.start_timestamp = None;
+            background_buckets_request.end_timestamp = None;
+            let search_response = search_sub_request(
+                searcher_context,
+                background_buckets_request,
+                metastore.clone(),
+                cluster_client,
+            )
+            .await?;
+            candidates_aggregation_buckets(&search_response)
+        };
+        let (background_count_response, background_buckets) =
+            futures::try_join!(background_count_future, background_buckets_future)?;
+        let background_count = background_count_response.num_hits;
+
+        let background_doc_counts: HashMap<String, u64> = background_buckets
+            .into_iter()
+            .map(|bucket| (key_to_term(&bucket.key), bucket.doc_count))
+            .collect();
+        let mut buckets: Vec<SignificantTermsBucketEntry> = candidate_buckets
+            .into_iter()
+            .filter_map(|bucket| {
+                let bg_count = background_doc_counts
+                    .get(&key_to_term(&bucket.key))
+                    .copied()
+                    .unwrap_or(0);
+                let score = self.heuristic.score(
+                    bucket.doc_count,
+                    foreground_count,
+                    bg_count,
+                    background_count,
+                )?;
+                Some(SignificantTermsBucketEntry {
+                    key: bucket.key,
+                    doc_count: bucket.doc_count,
+                    bg_count,
+                    score,
+                    sub_aggregation: bucket.sub_aggregation,
+                })
+            })
+            .collect();
+        buckets.sort_by(|left, right| right.score.total_cmp(&left.score));
+        buckets.truncate(self.size);
+
+        Ok(BucketResult::SignificantTerms {
+            doc_count: foreground_count,
+            bg_count: background_count,
+            buckets,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RareTerms {
+    field: String,
+    max_doc_count: u64,
+    sub_aggregations_json: Option<JsonValue>,
+}
+
+impl RareTermsRequest {
+    fn compile(self, sub_aggregations_json: Option<JsonValue>) -> Result<RareTerms, String> {
+        if !(1..=MAX_RARE_TERMS_MAX_DOC_COUNT).contains(&self.max_doc_count) {
+            return Err(format!(
+                "`max_doc_count` must be between 1 and {MAX_RARE_TERMS_MAX_DOC_COUNT}"
+            ));
+        }
+        Ok(RareTerms {
+            field: self.field,
+            max_doc_count: self.max_doc_count,
+            sub_aggregations_json,
+        })
+    }
+}
+
+impl RareTerms {
+    /// The terms aggregation collecting the candidates, the least frequent terms of each split.
+    /// The sub-aggregations are only computed by the second phase.
+    fn candidates_terms_json(&self) -> JsonValue {
+        json!({
+            "field": self.field,
+            "size": MAX_CANDIDATE_TERMS,
+            "order": {"_count": "asc"}
+        })
+    }
+
+    async fn compute(
+        &self,
+        two_phase_terms: &TwoPhaseTermsAggregations,
+        searcher_context: &SearcherContext,
+        metastore: &MetastoreServiceClient,
+        cluster_client: &ClusterClient,
+        candidate_buckets: Vec<BucketEntry>,
+    ) -> crate::Result<BucketResult> {
+        // The count of the first phase can only be underestimated.
+        let candidate_terms: BTreeSet<String> = candidate_buckets
+            .iter()
+            .filter(|bucket| bucket.doc_count <= self.max_doc_count)
+            .map(|bucket| key_to_term(&bucket.key))
+            .collect();
+        if candidate_terms.is_empty() {
+            return Ok(BucketResult::RareTerms {
+                buckets: Vec::new(),
+            });
+        }
+        let query_ast: QueryAst = serde_json::from_str(&two_phase_terms.search_request.query_ast)?;
+        let query_ast = candidates_query_ast(query_ast, &self.field, candidate_terms.clone());
+
+        let mut candidates_aggregation_json = JsonMap::new();
+        candidates_aggregation_json.insert(
+            "terms".to_string(),
+            json!({
+                "field": self.field,
+                "size": candidate_terms.len(),
+                "order": {"_count": "asc"}
+            }),
+        );
+        if let Some(sub_aggregations_json) = &self.sub_aggregations_json {
+            candidates_aggregation_json.insert("aggs".to_string(), sub_aggregations_json.clone());
+        }
+        let aggregations_json = json!({
+            CANDIDATES_AGGREGATION_NAME: JsonValue::Object(candidates_aggregation_json)
+        });
+        let candidates_request =
+            two_phase_terms.sub_request(&query_ast, Some(aggregations_json))?;
+        let search_response = search_sub_request(
+            searcher_context,
+            candidates_request,
+            metastore.clone(),
+            cluster_client,
+        )
+        .await?;
+
+        let mut buckets: Vec<BucketEntry> = candidates_aggregation_buckets(&search_response)?
+            .into_iter()
+            .filter(|bucket| {
+                bucket.doc_count <= self.max_doc_count
+                    && candidate_terms.contains(&key_to_term(&bucket.key))
+            })
+            .collect();
+        buckets.sort_by(|left, right| {
+            left.doc_count
+                .cmp(&right.doc_count)
+                .then_with(|| compare_keys(&left.key, &right.key))
+        });
+        Ok(BucketResult::RareTerms { buckets })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TwoPhaseTermsAggregation {
+    SignificantTerms(SignificantTerms),
+    RareTerms(RareTerms),
+}
+
+/// The `significant_terms` and `rare_terms` aggregations of a search request.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TwoPhaseTermsAggregations {
+    /// The rewritten search request, from which the second phase requests are derived.
+    search_request: SearchRequest,
+    aggregations: Vec<(String, TwoPhaseTermsAggregation)>,
+}
+
+impl TwoPhaseTermsAggregations {
+    /// Replaces the `significant_terms` and `rare_terms` aggregations of a search request with the
+    /// terms aggregations collecting their candidates. Returns `None` if the request has no such
+    /// aggregations, in which case it is left untouched.
+    pub(crate) fn extract_from_request(
+        search_request: &mut SearchRequest,
+    ) -> crate::Result<Option<TwoPhaseTermsAggregations>> {
+        let Some(aggregation_request) = &search_request.aggregation_request else {
+            return Ok(None);
+        };
+        // Invalid aggregation requests are reported by the request validation.
+        let Ok(JsonValue::Object(mut aggregations_json)) =
+            serde_json::from_str::<JsonValue>(aggregation_request)
+        else {
+            return Ok(None);
+        };
+        let mut aggregations = Vec::new();
+
+        for (name, aggregation_json) in aggregations_json.iter_mut() {
+            let Some(aggregation_obj) = aggregation_json.as_object_mut() else {
+                continue;
+            };
+            for sub_aggregations_key in ["aggs", "aggregations"] {
+                if let Some(sub_aggregations_json) = aggregation_obj.get(sub_aggregations_key) {
+                    check_no_nested_aggregations(sub_aggregations_json)?;
+                }
+            }
+            let aggregation =
+                if let Some(request_json) = aggregation_obj.remove("significant_terms") {
+                    let significant_terms =
+                        parse_request::<SignificantTermsRequest>(name, request_json).and_then(
+                            |request| request.compile().map_err(|error| invalid(name, error)),
+                        )?;
+                    aggregation_obj.insert(
+                        "terms".to_string(),
+                        significant_terms.candidates_terms_json(),
+                    );
+                    TwoPhaseTermsAggregation::SignificantTerms(significant_terms)
+                } else if let Some(request_json) = aggregation_obj.remove("rare_terms") {
+                    let sub_aggregations_json = aggregation_obj
+                        .remove("aggs")
+                        .or_else(|| aggregation_obj.remove("aggregations"));
+                    let rare_terms = parse_request::<RareTermsRequest>(name, request_json)
+                        .and_then(|request| {
+                            request
+                                .compile(sub_aggregations_json)
+                                .map_err(|error| invalid(name, error))
+                        })?;
+                    aggregation_obj.insert("terms".to_string(), rare_terms.candidates_terms_json());
+                    TwoPhaseTermsAggregation::RareTerms(rare_terms)
+                } else {
+                    continue;
+                };
+            aggregations.push((name.clone(), aggregation));
+        }
+        if aggregations.is_empty() {
+            return Ok(None);
+        }
+        search_request.aggregation_request = Some(serde_json::to_string(&aggregations_json)?);
+
+        // The size of the foreground set is the number of hits.
+        if aggregations.iter().any(|(_, aggregation)| {
+            matches!(aggregation, TwoPhaseTermsAggregation::SignificantTerms(_))
+        }) {
+            search_request.count_hits = CountHits::CountAll as i32;
+        }
+        Ok(Some(TwoPhaseTermsAggregations {
+            search_request: search_request.clone(),
+            aggregations,
+        }))
+    }
+
+    /// Runs the second phase of the aggregations and replaces the results of the terms
+    /// aggregations of the first phase with the final results.
+    pub(crate) async fn compute(
+        &self,
+        searcher_context: &SearcherContext,
+        metastore: &MetastoreServiceClient,
+        cluster_client: &ClusterClient,
+        search_response: &mut SearchResponse,
+    ) -> crate::Result<()> {
+        let Some(aggregation_postcard) = &search_response.aggregation_postcard else {
+            return Ok(());
+        };
+        let mut aggregation_results: AggregationResults =
+            postcard::from_bytes(aggregation_postcard)?;
+
+        for (name, aggregation) in &self.aggregations {
+            let Some((_, aggregation_result)) = aggregation_results
+                .0
+                .iter_mut()
+                .find(|(result_name, _)| result_name == name)
+            else {
+                continue;
+            };
+            let AggregationResult::BucketResult(BucketResult::Terms { buckets, .. }) =
+                aggregation_result
+            else {
+                return Err(SearchError::Internal(format!(
+                    "expected terms aggregation results for aggregation `{name}`"
+                )));
+            };
+            let candidate_buckets = std::mem::take(buckets);
+
+            let bucket_result = match aggregation {
+                TwoPhaseTermsAggregation::SignificantTerms(significant_terms) => {
+                    significant_terms
+                        .compute(
+                            self,
+                            searcher_context,
+                            metastore,
+                            cluster_client,
+                            search_response.num_hits,
+                            candidate_buckets,
+                        )
+                        .await?
+                }
+                TwoPhaseTermsAggregation::RareTerms(rare_terms) => {
+                    rare_terms
+                        .compute(
+                            self,
+                            searcher_context,
+                            metastore,
+                            cluster_client,
+                            candidate_buckets,
+                        )
+                        .await?
+                }
+            };
+            *aggregation_result = AggregationResult::BucketResult(bucket_result);
+        }
+        search_response.aggregation_postcard = Some(postcard::to_stdvec(&aggregation_results)?);
+        Ok(())
+    }
+
+    /// Builds a second phase search request, which only counts hits and computes aggregations.
+    fn sub_request(
+        &self,
+        query_ast: &QueryAst,
+        aggregations_json_opt: Option<JsonValue>,
+    ) -> crate::Result<SearchRequest> {
+        Ok(SearchRequest {
+            query_ast: serde_json::to_string(query_ast)?,
+            max_hits: 0,
+            start_offset: 0,
+            aggregation_request: aggregations_json_opt
+                .map(|aggregations_json| aggregations_json.to_string()),
+            snippet_fields: Vec::new(),
+            sort_fields: Vec::new(),
+            scroll_ttl_secs: None,
+            search_after: None,
+            count_hits: CountHits::CountAll as i32,
+            profile: false,
+            docvalue_fields: Vec::new(),
+            knn: None,
+            ..self.search_request.clone()
+        })
+    }
+}
+
+fn parse_request<T: DeserializeOwned>(name: &str, request_json: JsonValue) -> crate::Result<T> {
+    serde_json::from_value(request_json).map_err(|error| invalid(name, error.to_string()))
+}
+
+fn invalid(name: &str, error: String) -> SearchError {
+    SearchError::InvalidAggregationRequest(format!("invalid aggregation `{name}`: {error}"))
+}
+
+fn check_no_nested_aggregations(aggregations_json: &JsonValue) -> crate::Result<()> {
+    let Some(aggregations_obj) = aggregations_json.as_object() else {
+        return Ok(());
+    };
+    for (name, aggregation_json) in aggregations_obj {
+        let Some(aggregation_obj) = aggregation_json.as_object() else {
+            continue;
+        };
+        if aggregation_obj.contains_key("significant_terms")
+            || aggregation_obj.contains_key("rare_terms")
+        {
+            return Err(SearchError::InvalidAggregationRequest(format!(
+                "aggregation `{name}` must be a top-level aggregation: significant_terms and \
+                 rare_terms aggregations cannot be nested"
+            )));
+        }
+        for sub_aggregations_key in ["aggs", "aggregations"] {
+            if let Some(sub_aggregations_json) = aggregation_obj.get(sub_aggregations_key) {
+                check_no_nested_aggregations(sub_aggregations_json)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Restricts a query to the documents containing one of the candidate terms.
+fn candidates_query_ast(
+    query_ast: QueryAst,
+    field: &str,
+    candidate_terms: BTreeSet<String>,
+) -> QueryAst {
+    let term_set_query = TermSetQuery {
+        terms_per_field: HashMap::from([(field.to_string(), candidate_terms)]),
+    };
+    QueryAst::Bool(BoolQuery {
+        must: vec![query_ast],
+        filter: vec![term_set_query.into()],
+        ..Default::default()
+    })
+}
+
+/// Formats a terms aggregation key as a term of a term set query. Numeric terms are returned as
+/// `f64` keys, integral values are formatted without a fractional part so that they can be parsed
+/// by integer fields.
+fn key_to_term(key: &Key) -> String {
+    match key {
+        Key::Str(term) => term.clone(),
+        Key::I64(value) => value.to_string(),
+        Key::U64(value) => value.to_string(),
+        Key::F64(value) if value.fract() == 0.0 && value.abs() < (1u64 << 53) as f64 => {
+            (*value as i64).to_string()
+        }
+        Key::F64(value) => value.to_string(),
+    }
+}
+
+fn compare_keys(left: &Key, right: &Key) -> Ordering {
+    fn key_as_f64(key: &Key) -> Option<f64> {
+        match key {
+            Key::Str(_) => None,
+            Key::I64(value) => Some(*value as f64),
+            Key::U64(value) => Some(*value as f64),
+            Key::F64(value) => Some(*value),
+        }
+    }
+    match (left, right) {
+        (Key::Str(left), Key::Str(right)) => left.cmp(right),
+        // Numbers are sorted before strings.
+        (Key::Str(_), _) => Ordering::Greater,
+        (_, Key::Str(_)) => Ordering::Less,
+        _ => key_as_f64(left)
+            .unwrap_or_default()
+            .total_cmp(&key_as_f64(right).unwrap_or_default()),
+    }
+}
+
+fn candidates_aggregation_buckets(
+    search_response: &SearchResponse,
+) -> crate::Result<Vec<BucketEntry>> {
+    let Some(aggregation_postcard) = &search_response.aggregation_postcard else {
+        return Ok(Vec::new());
+    };
+    let aggregation_results: AggregationResults = postcard::from_bytes(aggregation_postcard)?;
+
+    for (name, aggregation_result) in aggregation_results.0 {
+        if name == CANDIDATES_AGGREGATION_NAME
+            && let AggregationResult::BucketResult(BucketResult::Terms { buckets, .. }) =
+                aggregation_result
+        {
+            return Ok(buckets);
+        }
+    }
+    Ok(Vec::new())
+}
+
+async fn search_sub_request(
+    searcher_context: &SearcherContext,
+    mut search_request: SearchRequest,
+    mut metastore: MetastoreServiceClient,
+    cluster_client: &ClusterClient,
+) -> crate::Result<SearchResponse> {
+    let (split_metadatas, indexes_metas_for_leaf_search, runtime_fields_plan_opt) =
+        plan_splits_for_root_search(&mut search_request, &mut metastore).await?;
+    check_num_targeted_splits(searcher_context, &search_request, split_metadatas.len())?;
+
+    match runtime_fields_plan_opt {
+        Some(runtime_fields_plan) if runtime_fields_plan.requires_scan() => {
+            runtime_fields_plan
+                .search(
+                    search_request,
+                    split_metadatas,
+                    indexes_metas_for_leaf_search,
+                    cluster_client.clone(),
+                )
+                .await
+        }
+        _ => {
+            root_search_aux(
+                searcher_context,
+                &indexes_metas_for_leaf_search,
+                search_request,
+                split_metadatas,
+                cluster_client,
+                Instant::now(),
+            )
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(aggregations_json: JsonValue) -> crate::Result<Option<TwoPhaseTermsAggregations>> {
+        let mut search_request = SearchRequest {
+            aggregation_request: Some(aggregations_json.to_string()),
+            ..Default::default()
+        };
+        TwoPhaseTermsAggregations::extract_from_request(&mut search_request)
+    }
+
+    #[test]
+    fn test_extract_significant_terms() {
+        let mut search_request = SearchRequest {
+            aggregation_request: Some(
+                json!({
+                    "errors": {
+                        "significant_terms": {
+                            "field": "error_code",
+                            "size": 5,
+                            "background_filter": {"term": {"service": "api"}},
+                            "chi_square": {"include_negatives": true}
+                        },
+                        "aggs": {"hosts": {"terms": {"field": "host"}}}
+                    },
+                    "avg_latency": {"avg": {"field": "latency"}}
+                })
+                .to_string(),
+            ),
+            count_hits: CountHits::Underestimate as i32,
+            ..Default::default()
+        };
+        let two_phase_terms = TwoPhaseTermsAggregations::extract_from_request(&mut search_request)
+            .unwrap()
+            .unwrap();
+        assert_eq!(search_request.count_hits(), CountHits::CountAll);
+
+        let aggregations_json: JsonValue =
+            serde_json::from_str(search_request.aggregation_request()).unwrap();
+        assert_eq!(
+            aggregations_json,
+            json!({
+                "errors": {
+                    "terms": {"field": "error_code", "size": DEFAULT_SIGNIFICANT_TERMS_SHARD_SIZE},
+                    "aggs": {"hosts": {"terms": {"field": "host"}}}
+                },
+                "avg_latency": {"avg": {"field": "latency"}}
+            })
+        );
+        let [(name, TwoPhaseTermsAggregation::SignificantTerms(significant_terms))] =
+            &two_phase_terms.aggregations[..]
+        else {
+            panic!("expected a significant terms aggregation");
+        };
+        assert_eq!(name, "errors");
+        assert_eq!(significant_terms.size, 5);
+        assert_eq!(
+            significant_terms.min_doc_count,
+            DEFAULT_SIGNIFICANT_TERMS_MIN_DOC_COUNT
+        );
+        assert!(matches!(
+            significant_terms.background_query_ast,
+            QueryAst::Term(_)
+        ));
+        assert_eq!(
+            significant_terms.heuristic,
+            SignificanceHeuristic::ChiSquare {
+                include_negatives: true,
+                background_is_superset: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_extract_rare_terms() {
+        let mut search_request = SearchRequest {
+            aggregation_request: Some(
+                json!({
+                    "rare_codes": {
+                        "rare_terms": {"field": "error_code", "max_doc_count": 2},
+                        "aggs": {"hosts": {"terms": {"field": "host"}}}
+                    }
+                })
+                .to_string(),
+            ),
+            count_hits: CountHits::Underestimate as i32,
+            ..Default::default()
+        };
+        let two_phase_terms = TwoPhaseTermsAggregations::extract_from_request(&mut search_request)
+            .unwrap()
+            .unwrap();
+        assert_eq!(search_request.count_hits(), CountHits::Underestimate);
+
+        let aggregations_json: JsonValue =
+            serde_json::from_str(search_request.aggregation_request()).unwrap();
+        assert_eq!(
+            aggregations_json,
+            json!({
+                "rare_codes": {
+                    "terms": {
+                        "field": "error_code",
+                        "size": MAX_CANDIDATE_TERMS,
+                        "order": {"_count": "asc"}
+                    }
+                }
+            })
+        );
+        let [(_, TwoPhaseTermsAggregation::RareTerms(rare_terms))] =
+            &two_phase_terms.aggregations[..]
+        else {
+            panic!("expected a rare terms aggregation");
+        };
+        assert_eq!(rare_terms.max_doc_count, 2);
+        assert_eq!(
+            rare_terms.sub_aggregations_json,
+            Some(json!({"hosts": {"terms": {"field": "host"}}}))
+        );
+    }
+
+    #[test]
+    fn test_extract_without_two_phase_terms_aggregations() {
+        let two_phase_terms_opt =
+            extract(json!({"codes": {"terms": {"field": "error_code"}}})).unwrap();
+        assert!(two_phase_terms_opt.is_none());
+    }
+
+    #[test]
+    fn test_extract_invalid_requests() {
+        let error = extract(json!({
+            "hosts": {
+                "terms": {"field": "host"},
+                "aggs": {"codes": {"rare_terms": {"field": "error_code"}}}
+            }
+        }))
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("must be a top-level aggregation")
+        );
+
+        let error = extract(json!({
+            "codes": {"rare_terms": {"field": "error_code", "max_doc_count": 101}}
+        }))
+        .unwrap_err();
+        assert!(error.to_string().contains("max_doc_count"));
+
+        let error = extract(json!({
+            "codes": {
+                "significant_terms": {"field": "error_code", "jlh": {}, "chi_square": {}}
+            }
+        }))
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("only one significance heuristic")
+        );
+
+        let error = extract(json!({
+            "codes": {"significant_terms": {"field": "error_code", "gnd": {}}}
+        }))
+        .unwrap_err();
+        assert!(matches!(error, SearchError::InvalidAggregationRequest(_)));
+    }
+
+    #[test]
+    fn test_jlh_score() {
+        let jlh = SignificanceHeuristic::Jlh;
+        // 50% of the foreground set and 10% of the background set.
+        let score = jlh.score(50, 100, 100, 1_000).unwrap();
+        assert!((score - 0.4 * 5.0).abs() < 1e-9);
+        // Less frequent in the foreground set.
+        assert!(jlh.score(5, 100, 100, 1_000).is_none());
+        // Missing from the background set.
+        let score = jlh.score(10, 100, 0, 1_000).unwrap();
+        assert!((score - (0.1 - 0.001) * 100.0).abs() < 1e-9);
+        assert!(jlh.score(0, 0, 10, 1_000).is_none());
+    }
+
+    #[test]
+    fn test_chi_square_score() {
+        let chi_square = SignificanceHeuristic::ChiSquare {
+            include_negatives: false,
+            background_is_superset: true,
+        };
+        let score = chi_square.score(50, 100, 100, 1_000).unwrap();
+        // n00 = 850, n01 = 50, n10 = 50, n11 = 50, n0_ = 900, n1_ = 100, n_0 = 900, n_1 = 100
+        let expected_score =
+            1_000.0 * (50.0 * 850.0 - 50.0 * 50.0_f64).powi(2) / (100.0 * 100.0 * 900.0 * 900.0);
+        assert!((score - expected_score).abs() < 1e-9);
+        assert!(chi_square.score(5, 100, 100, 1_000).is_none());
+
+        let chi_square_with_negatives = SignificanceHeuristic::ChiSquare {
+            include_negatives: true,
+            background_is_superset: true,
+        };
+        assert!(chi_square_with_negatives.score(5, 100, 100, 1_000).unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_key_to_term() {
+        assert_eq!(key_to_term(&Key::Str("E42".to_string())), "E42");
+        assert_eq!(key_to_term(&Key::F64(404.0)), "404");
+        assert_eq!(key_to_term(&Key::F64(-1.0)), "-1");
+        assert_eq!(key_to_term(&Key::F64(0.5)), "0.5");
+        assert_eq!(key_to_term(&Key::U64(7)), "7");
+    }
+
+    #[test]
+    fn test_compare_keys() {
+        let mut keys = vec![
+            Key::Str("b".to_string()),
+            Key::F64(10.0),
+            Key::Str("a".to_string()),
+            Key::F64(2.0),
+        ];
+        keys.sort_by(compare_keys);
+        assert_eq!(
+            keys.iter().map(key_to_term).collect::<Vec<_>>(),
+            ["2", "10", "a", "b"]
+        );
+    }
+}
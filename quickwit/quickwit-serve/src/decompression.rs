@@ -16,7 +16,8 @@ use std::io::Read;
 use std::sync::OnceLock;
 
 use bytes::Bytes;
-use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use flate2::read::ZlibDecoder;
+use quickwit_common::compression::CompressionCodec;
 use quickwit_common::metrics::{GaugeGuard, MEMORY_METRICS};
 use quickwit_common::thread_pool::run_cpu_intensive;
 use thiserror::Error;
@@ -38,31 +39,8 @@ fn get_ingest_load_shield() -> &'static LoadShield {
 /// Ingesting data is usually CPU bound and there is considerable latency until the data is
 /// searchable, so the second approach is more suitable for this use case.
 async fn decompress_body(encoding: Option<String>, body: Bytes) -> Result<Bytes, warp::Rejection> {
-    match encoding.as_deref() {
-        Some("identity") => Ok(body),
-        Some("gzip" | "x-gzip") => {
-            let decompressed = run_cpu_intensive(move || {
-                let mut decompressed = Vec::new();
-                let mut decoder = MultiGzDecoder::new(body.as_ref());
-                decoder
-                    .read_to_end(&mut decompressed)
-                    .map_err(|_| warp::reject::custom(CorruptedData))?;
-                Result::<_, warp::Rejection>::Ok(Bytes::from(decompressed))
-            })
-            .await
-            .map_err(|_| warp::reject::custom(CorruptedData))??;
-            Ok(decompressed)
-        }
-        Some("zstd") => {
-            let decompressed = run_cpu_intensive(move || {
-                zstd::decode_all(body.as_ref())
-                    .map(Bytes::from)
-                    .map_err(|_| warp::reject::custom(CorruptedData))
-            })
-            .await
-            .map_err(|_| warp::reject::custom(CorruptedData))??;
-            Ok(decompressed)
-        }
+    let codec = match encoding.as_deref() {
+        None | Some("identity") => return Ok(body),
         Some("deflate" | "x-deflate") => {
             let decompressed = run_cpu_intensive(move || {
                 let mut decompressed = Vec::new();
@@ -73,13 +51,20 @@ async fn decompress_body(encoding: Option<String>, body: Bytes) -> Result<Bytes,
             })
             .await
             .map_err(|_| warp::reject::custom(CorruptedData))??;
-            Ok(decompressed)
+            return Ok(decompressed);
         }
-        Some(encoding) => Err(warp::reject::custom(UnsupportedEncoding(
-            encoding.to_string(),
-        ))),
-        _ => Ok(body),
-    }
+        Some(encoding) => CompressionCodec::from_content_encoding(encoding)
+            .ok_or_else(|| warp::reject::custom(UnsupportedEncoding(encoding.to_string())))?,
+    };
+    let decompressed = run_cpu_intensive(move || {
+        codec
+            .decompress(body.as_ref())
+            .map(Bytes::from)
+            .map_err(|_| warp::reject::custom(CorruptedData))
+    })
+    .await
+    .map_err(|_| warp::reject::custom(CorruptedData))??;
+    Ok(decompressed)
 }
 
 #[derive(Debug, Error)]
@@ -89,7 +74,11 @@ pub(crate) struct CorruptedData;
 impl Reject for CorruptedData {}
 
 #[derive(Debug, Error)]
-#[error("Unsupported Content-Encoding {}. Supported encodings are 'gzip' and 'zstd'", self.0)]
+#[error(
+    "Unsupported Content-Encoding {}. Supported encodings are 'bzip2', 'deflate', 'gzip', 'lz4', \
+     'xz' and 'zstd'",
+    self.0
+)]
 pub(crate) struct UnsupportedEncoding(String);
 
 impl Reject for UnsupportedEncoding {}
@@ -122,4 +111,61 @@ impl Body {
             _permit: load_shield_permit,
         }
     }
+
+    #[cfg(test)]
+    pub async fn for_test(content: Bytes) -> Body {
+        let permit = get_ingest_load_shield().acquire_permit().await.unwrap();
+        Body::new(content, permit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    use super::*;
+
+    fn gzip(bytes: &[u8]) -> Bytes {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        Bytes::from(encoder.finish().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_decompress_body() {
+        let body = Bytes::from_static(br#"{"body": "hello"}"#);
+
+        let decompressed = decompress_body(None, body.clone()).await.unwrap();
+        assert_eq!(decompressed, body);
+
+        let decompressed = decompress_body(Some("identity".to_string()), body.clone())
+            .await
+            .unwrap();
+        assert_eq!(decompressed, body);
+
+        let decompressed = decompress_body(Some("gzip".to_string()), gzip(&body))
+            .await
+            .unwrap();
+        assert_eq!(decompressed, body);
+
+        // Bodies sent without `Content-Encoding` are never decompressed.
+        let compressed_body = gzip(&body);
+        let decompressed = decompress_body(None, compressed_body.clone())
+            .await
+            .unwrap();
+        assert_eq!(decompressed, compressed_body);
+
+        let rejection = decompress_body(Some("zstd".to_string()), body.clone())
+            .await
+            .unwrap_err();
+        assert!(rejection.find::<CorruptedData>().is_some());
+
+        let rejection = decompress_body(Some("brotli".to_string()), body)
+            .await
+            .unwrap_err();
+        assert!(rejection.find::<UnsupportedEncoding>().is_some());
+    }
 }
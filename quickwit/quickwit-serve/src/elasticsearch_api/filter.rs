@@ -19,8 +19,9 @@ use warp::reject::LengthRequired;
 use warp::{Filter, Rejection};
 
 use super::model::{
-    CatIndexQueryParams, DeleteQueryParams, FieldCapabilityQueryParams, FieldCapabilityRequestBody,
-    MultiSearchQueryParams, SearchQueryParamsCount,
+    ASYNC_SEARCH_QUERY_PARAM_NAMES, AsyncSearchQueryParams, CatIndexQueryParams, DeleteQueryParams,
+    FieldCapabilityQueryParams, FieldCapabilityRequestBody, GetAsyncSearchQueryParams,
+    MultiSearchQueryParams, PutMappingRequestBody, SearchQueryParamsCount, SearchTemplateBody,
 };
 use crate::Body;
 use crate::decompression::get_body_bytes;
@@ -164,6 +165,48 @@ pub(crate) fn elastic_resolve_index_filter()
         .and(warp::get())
 }
 
+#[utoipa::path(get, tag = "Metadata", path = "/{index}/_mapping")]
+pub(crate) fn elastic_index_mapping_filter()
+-> impl Filter<Extract = (Vec<String>,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / String / "_mapping")
+        .and_then(extract_index_id_patterns)
+        .and(warp::get())
+}
+
+#[utoipa::path(get, tag = "Metadata", path = "/_mapping")]
+pub(crate) fn elastic_mapping_filter()
+-> impl Filter<Extract = (Vec<String>,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_mapping")
+        .and_then(extract_index_id_patterns_default)
+        .and(warp::get())
+}
+
+#[utoipa::path(put, tag = "Metadata", path = "/{index}/_mapping")]
+pub(crate) fn elastic_index_put_mapping_filter()
+-> impl Filter<Extract = (Vec<String>, PutMappingRequestBody), Error = Rejection> + Clone {
+    warp::path!("_elastic" / String / "_mapping")
+        .and_then(extract_index_id_patterns)
+        .and(warp::put())
+        .and(warp::body::content_length_limit(BODY_LENGTH_LIMIT.as_u64()))
+        .and(warp::body::json())
+}
+
+#[utoipa::path(get, tag = "Metadata", path = "/{index}/_settings")]
+pub(crate) fn elastic_index_settings_filter()
+-> impl Filter<Extract = (Vec<String>,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / String / "_settings")
+        .and_then(extract_index_id_patterns)
+        .and(warp::get())
+}
+
+#[utoipa::path(get, tag = "Metadata", path = "/_settings")]
+pub(crate) fn elastic_settings_filter()
+-> impl Filter<Extract = (Vec<String>,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_settings")
+        .and_then(extract_index_id_patterns_default)
+        .and(warp::get())
+}
+
 #[utoipa::path(get, tag = "Count", path = "/{index}/_count")]
 pub(crate) fn elastic_index_count_filter()
 -> impl Filter<Extract = (Vec<String>, SearchQueryParamsCount, SearchBody), Error = Rejection> + Clone
@@ -231,6 +274,28 @@ pub(crate) fn elastic_index_search_filter()
         .and(json_or_empty())
 }
 
+#[utoipa::path(post, tag = "Search", path = "/{index}/_search/template")]
+pub(crate) fn elastic_index_search_template_filter()
+-> impl Filter<Extract = (Vec<String>, SearchQueryParams, SearchTemplateBody), Error = Rejection> + Clone
+{
+    warp::path!("_elastic" / String / "_search" / "template")
+        .and_then(extract_index_id_patterns)
+        .and(warp::get().or(warp::post()).unify())
+        .and(warp::query())
+        .and(json_or_empty())
+}
+
+#[utoipa::path(post, tag = "Search", path = "/_render/template/{id}")]
+pub(crate) fn elastic_render_search_template_filter()
+-> impl Filter<Extract = (Option<String>, SearchTemplateBody), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_render" / "template")
+        .map(|| None::<String>)
+        .or(warp::path!("_elastic" / "_render" / "template" / String).map(Some))
+        .unify()
+        .and(warp::get().or(warp::post()).unify())
+        .and(json_or_empty())
+}
+
 #[utoipa::path(post, tag = "Search", path = "/_msearch")]
 pub(crate) fn elastic_multi_search_filter()
 -> impl Filter<Extract = (Bytes, MultiSearchQueryParams), Error = Rejection> + Clone {
@@ -265,3 +330,67 @@ pub(crate) fn elastic_scroll_filter()
             },
         )
 }
+
+/// Splits the query string of an async search submission between the async search parameters and
+/// the search parameters, as each of them rejects the parameters of the other.
+fn async_search_query_params()
+-> impl Filter<Extract = (AsyncSearchQueryParams, SearchQueryParams), Error = Rejection> + Clone {
+    warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .and_then(|query_string: String| async move {
+            let (async_search_params, search_params): (Vec<&str>, Vec<&str>) = query_string
+                .split('&')
+                .filter(|param| !param.is_empty())
+                .partition(|param| {
+                    let param_name = param.split_once('=').map_or(*param, |(name, _)| name);
+                    ASYNC_SEARCH_QUERY_PARAM_NAMES.contains(&param_name)
+                });
+            let async_search_query_params = parse_query_params(&async_search_params)?;
+            let search_query_params = parse_query_params(&search_params)?;
+            Ok::<_, Rejection>((async_search_query_params, search_query_params))
+        })
+        .untuple_one()
+}
+
+fn parse_query_params<T: DeserializeOwned>(params: &[&str]) -> Result<T, Rejection> {
+    serde_qs::from_str(&params.join("&"))
+        .map_err(|error| warp::reject::custom(crate::rest::InvalidArgument(error.to_string())))
+}
+
+#[utoipa::path(post, tag = "Search", path = "/{index}/_async_search")]
+pub(crate) fn elastic_index_async_search_filter() -> impl Filter<
+    Extract = (
+        Vec<String>,
+        AsyncSearchQueryParams,
+        SearchQueryParams,
+        SearchBody,
+    ),
+    Error = Rejection,
+> + Clone {
+    warp::path!("_elastic" / String / "_async_search")
+        .and_then(extract_index_id_patterns)
+        .and(warp::post())
+        .and(async_search_query_params())
+        .and(json_or_empty())
+}
+
+#[utoipa::path(get, tag = "Search", path = "/_async_search/{id}")]
+pub(crate) fn elastic_get_async_search_filter()
+-> impl Filter<Extract = (String, GetAsyncSearchQueryParams), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_async_search" / String)
+        .and(warp::get())
+        .and(warp::query())
+}
+
+#[utoipa::path(get, tag = "Search", path = "/_async_search/status/{id}")]
+pub(crate) fn elastic_async_search_status_filter()
+-> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_async_search" / "status" / String).and(warp::get())
+}
+
+#[utoipa::path(delete, tag = "Search", path = "/_async_search/{id}")]
+pub(crate) fn elastic_delete_async_search_filter()
+-> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_async_search" / String).and(warp::delete())
+}
@@ -31,11 +31,17 @@ use quickwit_proto::metastore::MetastoreServiceClient;
 use quickwit_search::SearchService;
 use rest_handler::es_compat_cluster_health_handler;
 pub use rest_handler::{
-    es_compat_cat_indices_handler, es_compat_cluster_info_handler, es_compat_delete_index_handler,
-    es_compat_index_cat_indices_handler, es_compat_index_count_handler,
-    es_compat_index_field_capabilities_handler, es_compat_index_multi_search_handler,
-    es_compat_index_search_handler, es_compat_index_stats_handler, es_compat_resolve_index_handler,
-    es_compat_scroll_handler, es_compat_search_handler, es_compat_stats_handler,
+    es_compat_async_search_status_handler, es_compat_cat_indices_handler,
+    es_compat_cluster_info_handler, es_compat_delete_async_search_handler,
+    es_compat_delete_index_handler, es_compat_get_async_search_handler,
+    es_compat_index_async_search_handler, es_compat_index_cat_indices_handler,
+    es_compat_index_count_handler, es_compat_index_field_capabilities_handler,
+    es_compat_index_mapping_handler, es_compat_index_multi_search_handler,
+    es_compat_index_put_mapping_handler, es_compat_index_search_handler,
+    es_compat_index_search_template_handler, es_compat_index_settings_handler,
+    es_compat_index_stats_handler, es_compat_render_search_template_handler,
+    es_compat_resolve_index_handler, es_compat_scroll_handler, es_compat_search_handler,
+    es_compat_stats_handler,
 };
 use serde::{Deserialize, Serialize};
 use warp::hyper::StatusCode;
@@ -89,12 +95,32 @@ pub fn elastic_api_handlers(
         ))
         .boxed()
         .or(es_compat_index_stats_handler(metastore.clone()))
-        .or(es_compat_delete_index_handler(index_service))
+        .or(es_compat_delete_index_handler(index_service.clone()))
         .or(es_compat_stats_handler(metastore.clone()))
         .or(es_compat_cluster_health_handler(cluster))
         .or(es_compat_index_cat_indices_handler(metastore.clone()))
         .or(es_compat_cat_indices_handler(metastore.clone()))
         .or(es_compat_resolve_index_handler(metastore.clone()))
+        .boxed()
+        .or(es_compat_index_mapping_handler(
+            metastore.clone(),
+            search_service.clone(),
+        ))
+        .or(es_compat_index_put_mapping_handler(index_service))
+        .or(es_compat_index_settings_handler(metastore.clone()))
+        .boxed()
+        .or(es_compat_index_search_template_handler(
+            metastore.clone(),
+            search_service.clone(),
+        ))
+        .or(es_compat_render_search_template_handler(metastore.clone()))
+        .boxed()
+        .or(es_compat_index_async_search_handler(search_service.clone()))
+        .or(es_compat_async_search_status_handler(
+            search_service.clone(),
+        ))
+        .or(es_compat_get_async_search_handler(search_service.clone()))
+        .or(es_compat_delete_async_search_handler(search_service))
         .recover(recover_fn)
         .boxed()
     // Register newly created handlers here.
@@ -143,6 +169,7 @@ fn make_elastic_api_response<T: serde::Serialize>(
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
+    use std::time::Duration;
 
     use assert_json_diff::assert_json_include;
     use mockall::predicate;
@@ -153,7 +180,8 @@ mod tests {
     use quickwit_metastore::metastore_for_test;
     use quickwit_proto::ingest::router::IngestRouterServiceClient;
     use quickwit_proto::metastore::MetastoreServiceClient;
-    use quickwit_search::MockSearchService;
+    use quickwit_proto::search::SearchResponse;
+    use quickwit_search::{AsyncSearchResponse, MockSearchService, SearchError};
     use quickwit_storage::StorageResolver;
     use serde_json::Value as JsonValue;
     use warp::Filter;
@@ -161,7 +189,10 @@ mod tests {
     use super::elastic_api_handlers;
     use super::model::ElasticsearchError;
     use crate::BuildInfo;
-    use crate::elasticsearch_api::rest_handler::es_compat_cluster_info_handler;
+    use crate::elasticsearch_api::rest_handler::{
+        es_compat_async_search_status_handler, es_compat_cluster_info_handler,
+        es_compat_get_async_search_handler, es_compat_index_async_search_handler,
+    };
     use crate::rest::recover_fn;
 
     fn ingest_service_client() -> IngestServiceClient {
@@ -539,4 +570,201 @@ mod tests {
             .await;
         assert_eq!(resp.status(), 200);
     }
+
+    fn mock_async_search_response(
+        context: String,
+        search_response_opt: Option<SearchResponse>,
+    ) -> AsyncSearchResponse {
+        let is_running = search_response_opt.is_none();
+        AsyncSearchResponse {
+            id: "01J0000000000000000000000A".to_string(),
+            is_running,
+            is_partial: is_running,
+            start_time_millis: 1_000,
+            expiration_time_millis: 61_000,
+            completion_time_millis: (!is_running).then_some(2_000),
+            search_response: search_response_opt,
+            error: None,
+            context,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_search_submit_running_search() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_submit_async_search()
+            .withf(|async_search_request| {
+                async_search_request.search_request.index_id_patterns == vec!["index-1".to_string()]
+                    && async_search_request.search_request.max_hits == 5
+                    && async_search_request.wait_for_completion_timeout
+                        == Duration::from_millis(100)
+                    && async_search_request.keep_alive == Duration::from_secs(60)
+            })
+            .returning(|async_search_request| {
+                Ok(mock_async_search_response(
+                    async_search_request.context,
+                    None,
+                ))
+            });
+        let handler =
+            es_compat_index_async_search_handler(Arc::new(mock_search_service)).recover(recover_fn);
+        let resp = warp::test::request()
+            .path(
+                "/_elastic/index-1/_async_search?wait_for_completion_timeout=100ms&size=5&\
+                 keep_alive=1m",
+            )
+            .method("POST")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let expected_response_json = serde_json::json!({
+            "id": "01J0000000000000000000000A",
+            "is_running": true,
+            "is_partial": true,
+            "start_time_in_millis": 1_000,
+            "expiration_time_in_millis": 61_000,
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+        assert!(resp_json.get("response").is_some());
+        assert!(resp_json.get("completion_time_in_millis").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_async_search_submit_completed_search_not_kept() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_submit_async_search()
+            .returning(|async_search_request| {
+                let search_response = SearchResponse {
+                    num_hits: 3,
+                    num_successful_splits: 1,
+                    ..Default::default()
+                };
+                Ok(mock_async_search_response(
+                    async_search_request.context,
+                    Some(search_response),
+                ))
+            });
+        mock_search_service
+            .expect_delete_async_search()
+            .times(1)
+            .returning(|_id| Ok(()));
+        let handler =
+            es_compat_index_async_search_handler(Arc::new(mock_search_service)).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/_elastic/index-1/_async_search")
+            .method("POST")
+            .body(r#"{"query": {"match_all": {}}}"#)
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let expected_response_json = serde_json::json!({
+            "is_running": false,
+            "is_partial": false,
+            "completion_time_in_millis": 2_000,
+            "response": {
+                "hits": {
+                    "total": {
+                        "value": 3,
+                        "relation": "eq"
+                    }
+                }
+            }
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+        assert!(resp_json.get("id").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_async_search_submit_invalid_params() {
+        let mock_search_service = MockSearchService::new();
+        let handler =
+            es_compat_index_async_search_handler(Arc::new(mock_search_service)).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/_elastic/index-1/_async_search?keep_alive=forever")
+            .method("POST")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/_elastic/index-1/_async_search?unknown_param=1")
+            .method("POST")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_async_search_get_and_status() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_get_async_search()
+            .returning(|get_async_search_request| {
+                if get_async_search_request.id != "01J0000000000000000000000A" {
+                    return Err(SearchError::NotFound("async search not found".to_string()));
+                }
+                let search_response = SearchResponse {
+                    num_hits: 3,
+                    num_successful_splits: 2,
+                    ..Default::default()
+                };
+                Ok(mock_async_search_response(
+                    String::new(),
+                    Some(search_response),
+                ))
+            });
+        let mock_search_service = Arc::new(mock_search_service);
+        let handler = es_compat_get_async_search_handler(mock_search_service.clone())
+            .or(es_compat_async_search_status_handler(mock_search_service))
+            .recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/_elastic/_async_search/01J0000000000000000000000B")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+
+        let resp = warp::test::request()
+            .path(
+                "/_elastic/_async_search/01J0000000000000000000000A?wait_for_completion_timeout=1s",
+            )
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let expected_response_json = serde_json::json!({
+            "id": "01J0000000000000000000000A",
+            "is_running": false,
+            "response": {
+                "hits": {
+                    "total": {
+                        "value": 3,
+                    }
+                }
+            }
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+
+        let resp = warp::test::request()
+            .path("/_elastic/_async_search/status/01J0000000000000000000000A")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let expected_response_json = serde_json::json!({
+            "id": "01J0000000000000000000000A",
+            "is_running": false,
+            "_shards": {
+                "total": 2,
+                "successful": 2,
+                "failed": 0,
+            },
+            "completion_status": 200,
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+    }
 }
@@ -0,0 +1,99 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use elasticsearch_dsl::{ErrorCause, ShardStatistics};
+use serde::{Deserialize, Serialize};
+
+use super::search_response::ElasticsearchResponse;
+
+// Async search doc: https://www.elastic.co/guide/en/elasticsearch/reference/current/async-search.html
+
+/// Names of the query parameters of [`AsyncSearchQueryParams`]. The other query parameters of an
+/// async search submission are search parameters.
+pub const ASYNC_SEARCH_QUERY_PARAM_NAMES: &[&str] = &[
+    "keep_alive",
+    "keep_on_completion",
+    "wait_for_completion_timeout",
+];
+
+/// Query parameters of an async search submission that are not search parameters.
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AsyncSearchQueryParams {
+    /// How long the search and its results are kept. Defaults to 5 days.
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Whether the results of a search completing within `wait_for_completion_timeout` are
+    /// kept. Defaults to false.
+    #[serde(default)]
+    pub keep_on_completion: Option<bool>,
+    /// How long to wait for the search to complete. Defaults to 1 second.
+    #[serde(default)]
+    pub wait_for_completion_timeout: Option<String>,
+}
+
+/// Query parameters of the get async search API.
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GetAsyncSearchQueryParams {
+    /// If set, the results of a completed search are kept for this long from now.
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// How long to wait for the search to complete. Defaults to returning immediately.
+    #[serde(default)]
+    pub wait_for_completion_timeout: Option<String>,
+}
+
+/// Rendering options of the results of an async search, stored along with the search as they
+/// are only known on submission.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct AsyncSearchRenderOptions {
+    pub append_shard_doc: bool,
+    pub _source_excludes: Option<Vec<String>>,
+    pub _source_includes: Option<Vec<String>>,
+    pub allow_partial_search_results: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ElasticsearchAsyncSearchResponse {
+    /// Omitted when the search completed on submission and its results were not kept.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub is_partial: bool,
+    pub is_running: bool,
+    pub start_time_in_millis: i64,
+    pub expiration_time_in_millis: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_time_in_millis: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<ElasticsearchResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorCause>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ElasticsearchAsyncSearchStatusResponse {
+    pub id: String,
+    pub is_partial: bool,
+    pub is_running: bool,
+    pub start_time_in_millis: i64,
+    pub expiration_time_in_millis: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_time_in_millis: Option<i64>,
+    #[serde(rename = "_shards")]
+    pub shards: ShardStatistics,
+    /// HTTP status code of the search, once completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_status: Option<u16>,
+}
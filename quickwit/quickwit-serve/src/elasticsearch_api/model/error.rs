@@ -151,6 +151,8 @@ pub enum ElasticException {
     // This is an exception proper to Quickwit.
     #[serde(rename = "rate_limited_exception")]
     RateLimited,
+    #[serde(rename = "resource_not_found_exception")]
+    ResourceNotFound,
     // This is an exception proper to Quickwit.
     #[serde(rename = "source_not_found_exception")]
     SourceNotFound,
@@ -167,6 +169,7 @@ impl ElasticException {
             Self::RateLimited => "rate_limited_exception",
             Self::IllegalArgument => "illegal_argument_exception",
             Self::IndexNotFound => "index_not_found_exception",
+            Self::ResourceNotFound => "resource_not_found_exception",
             Self::SourceNotFound => "source_not_found_exception",
             Self::Timeout => "timeout_exception",
         }
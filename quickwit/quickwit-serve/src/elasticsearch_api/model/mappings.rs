@@ -0,0 +1,598 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use quickwit_doc_mapper::{DocMapping, FieldMappingEntry, Mode};
+use quickwit_proto::search::{ListFieldType, ListFieldsEntryResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value as JsonValue, json};
+use warp::hyper::StatusCode;
+
+use super::{ElasticException, ElasticsearchError};
+
+/// Mapping of a field in the Elasticsearch format.
+///
+/// When deserializing a `PUT _mapping` request, the mapping parameters that have no Quickwit
+/// equivalent (`index`, `doc_values`, `ignore_above`...) are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ElasticFieldMapping {
+    #[serde(rename = "type")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field_type: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, ElasticFieldMapping>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, ElasticFieldMapping>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dims: Option<u32>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<String>,
+}
+
+impl ElasticFieldMapping {
+    fn with_type(field_type: &str) -> Self {
+        ElasticFieldMapping {
+            field_type: Some(field_type.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn is_object(&self) -> bool {
+        match self.field_type.as_deref() {
+            Some("object") => true,
+            Some(_) => false,
+            None => !self.properties.is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ElasticsearchMappings {
+    pub dynamic: &'static str,
+    pub properties: BTreeMap<String, ElasticFieldMapping>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ElasticsearchIndexMappings {
+    pub mappings: ElasticsearchMappings,
+}
+
+/// Response of `GET _mapping`, keyed by index ID.
+pub type ElasticsearchMappingsResponse = BTreeMap<String, ElasticsearchIndexMappings>;
+
+/// Body of a `PUT _mapping` request. Only new fields can be added.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PutMappingRequestBody {
+    #[serde(default)]
+    pub properties: BTreeMap<String, ElasticFieldMapping>,
+    #[serde(default)]
+    pub _meta: serde::de::IgnoredAny,
+}
+
+/// Converts the doc mapping of an index to the Elasticsearch format. The fields captured by a
+/// JSON field or by the dynamic mode are taken from the `list_fields_entries` of the index.
+pub fn convert_to_es_mappings(
+    index_id: &str,
+    doc_mapping: &DocMapping,
+    list_fields_entries: &[ListFieldsEntryResponse],
+) -> ElasticsearchIndexMappings {
+    let mut properties: BTreeMap<String, ElasticFieldMapping> = doc_mapping
+        .field_mappings
+        .iter()
+        .filter_map(|field_mapping_entry| {
+            let field_mapping_json = serde_json::to_value(field_mapping_entry).ok()?;
+            let es_field_mapping = es_field_mapping_from_json(&field_mapping_json)?;
+            Some((field_mapping_entry.name.clone(), es_field_mapping))
+        })
+        .collect();
+
+    for list_fields_entry in list_fields_entries {
+        if !list_fields_entry
+            .index_ids
+            .iter()
+            .any(|list_field_index_id| list_field_index_id == index_id)
+        {
+            continue;
+        }
+        let Some(es_field_mapping) = es_field_mapping_from_list_field(list_fields_entry) else {
+            continue;
+        };
+        let field_path = split_field_path(&list_fields_entry.field_name);
+        insert_field_mapping(&mut properties, &field_path, es_field_mapping);
+    }
+    let dynamic = match &doc_mapping.mode {
+        Mode::Lenient => "false",
+        Mode::Strict => "strict",
+        Mode::Dynamic(_) => "true",
+    };
+    ElasticsearchIndexMappings {
+        mappings: ElasticsearchMappings {
+            dynamic,
+            properties,
+        },
+    }
+}
+
+/// Converts a serialized field mapping entry to the Elasticsearch format. Concatenate fields
+/// have no Elasticsearch equivalent and are omitted.
+fn es_field_mapping_from_json(field_mapping_json: &JsonValue) -> Option<ElasticFieldMapping> {
+    let field_type = field_mapping_json.get("type")?.as_str()?;
+    let field_type = field_type
+        .strip_prefix("array<")
+        .and_then(|field_type| field_type.strip_suffix('>'))
+        .unwrap_or(field_type);
+
+    let es_field_mapping = match field_type {
+        "text" => {
+            let es_field_type = if field_mapping_json
+                .get("tokenizer")
+                .and_then(JsonValue::as_str)
+                == Some("raw")
+            {
+                "keyword"
+            } else {
+                "text"
+            };
+            ElasticFieldMapping {
+                fields: sub_field_mappings_from_json(field_mapping_json, "fields"),
+                ..ElasticFieldMapping::with_type(es_field_type)
+            }
+        }
+        "i64" => ElasticFieldMapping::with_type("long"),
+        "u64" => ElasticFieldMapping::with_type("unsigned_long"),
+        "f64" => ElasticFieldMapping::with_type("double"),
+        "bool" => ElasticFieldMapping::with_type("boolean"),
+        "ip" => ElasticFieldMapping::with_type("ip"),
+        "datetime" => ElasticFieldMapping::with_type("date_nanos"),
+        "bytes" => ElasticFieldMapping::with_type("binary"),
+        "json" => ElasticFieldMapping::with_type("object"),
+        "object" => ElasticFieldMapping {
+            properties: sub_field_mappings_from_json(field_mapping_json, "field_mappings"),
+            ..ElasticFieldMapping::with_type("object")
+        },
+        "dense_vector" => ElasticFieldMapping {
+            dims: field_mapping_json
+                .get("dims")
+                .and_then(JsonValue::as_u64)
+                .map(|dims| dims as u32),
+            similarity: field_mapping_json
+                .get("similarity")
+                .and_then(JsonValue::as_str)
+                .map(str::to_string),
+            ..ElasticFieldMapping::with_type("dense_vector")
+        },
+        _ => return None,
+    };
+    Some(es_field_mapping)
+}
+
+fn sub_field_mappings_from_json(
+    field_mapping_json: &JsonValue,
+    key: &str,
+) -> BTreeMap<String, ElasticFieldMapping> {
+    let Some(sub_field_mappings_json) = field_mapping_json.get(key).and_then(JsonValue::as_array)
+    else {
+        return BTreeMap::new();
+    };
+    sub_field_mappings_json
+        .iter()
+        .filter_map(|sub_field_mapping_json| {
+            let name = sub_field_mapping_json.get("name")?.as_str()?;
+            let es_field_mapping = es_field_mapping_from_json(sub_field_mapping_json)?;
+            Some((name.to_string(), es_field_mapping))
+        })
+        .collect()
+}
+
+fn es_field_mapping_from_list_field(
+    list_fields_entry: &ListFieldsEntryResponse,
+) -> Option<ElasticFieldMapping> {
+    let es_field_type = match ListFieldType::try_from(list_fields_entry.field_type).ok()? {
+        // Dynamic fields use the `raw` tokenizer and are fast by default.
+        ListFieldType::Str if list_fields_entry.aggregatable => "keyword",
+        ListFieldType::Str => "text",
+        ListFieldType::U64 => "unsigned_long",
+        ListFieldType::I64 => "long",
+        ListFieldType::F64 => "double",
+        ListFieldType::Bool => "boolean",
+        ListFieldType::Date => "date_nanos",
+        ListFieldType::Bytes => "binary",
+        ListFieldType::IpAddr => "ip",
+        ListFieldType::Facet | ListFieldType::Json => return None,
+    };
+    Some(ElasticFieldMapping::with_type(es_field_type))
+}
+
+/// Inserts the mapping of a field discovered in the splits, unless the field or one of its
+/// parents is already mapped to a leaf type.
+fn insert_field_mapping(
+    properties: &mut BTreeMap<String, ElasticFieldMapping>,
+    field_path: &[String],
+    es_field_mapping: ElasticFieldMapping,
+) {
+    let Some((field_name, parent_path)) = field_path.split_last() else {
+        return;
+    };
+    let mut properties = properties;
+
+    for parent_name in parent_path {
+        let parent_field_mapping = properties
+            .entry(parent_name.clone())
+            .or_insert_with(|| ElasticFieldMapping::with_type("object"));
+
+        if !parent_field_mapping.is_object() {
+            return;
+        }
+        properties = &mut parent_field_mapping.properties;
+    }
+    properties
+        .entry(field_name.clone())
+        .or_insert(es_field_mapping);
+}
+
+/// Splits a field name on its unescaped dots.
+fn split_field_path(field_name: &str) -> Vec<String> {
+    let mut field_path = Vec::new();
+    let mut current_segment = String::new();
+    let mut chars = field_name.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped_char) = chars.next() {
+                    current_segment.push(escaped_char);
+                }
+            }
+            '.' => field_path.push(std::mem::take(&mut current_segment)),
+            _ => current_segment.push(c),
+        }
+    }
+    field_path.push(current_segment);
+    field_path
+}
+
+/// Adds the fields of a `PUT _mapping` request to a doc mapping. Fields that already exist with
+/// the same type are left untouched, changing the type of an existing field is rejected.
+///
+/// Returns the updated doc mapping, or `None` if the request does not add any field.
+pub fn add_es_properties_to_doc_mapping(
+    doc_mapping: &DocMapping,
+    properties: &BTreeMap<String, ElasticFieldMapping>,
+) -> Result<Option<DocMapping>, ElasticsearchError> {
+    let mut field_mappings_json: Vec<JsonValue> = doc_mapping
+        .field_mappings
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<_, _>>()
+        .map_err(|error| {
+            ElasticsearchError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to serialize doc mapping: {error}"),
+                Some(ElasticException::Internal),
+            )
+        })?;
+    let num_added_fields =
+        add_es_properties(&mut field_mappings_json, properties, "").map_err(|reason| {
+            ElasticsearchError::new(
+                StatusCode::BAD_REQUEST,
+                reason,
+                Some(ElasticException::IllegalArgument),
+            )
+        })?;
+    if num_added_fields == 0 {
+        return Ok(None);
+    }
+    let field_mappings: Vec<FieldMappingEntry> =
+        serde_json::from_value(JsonValue::Array(field_mappings_json)).map_err(|error| {
+            ElasticsearchError::new(
+                StatusCode::BAD_REQUEST,
+                format!("invalid mapping: {error}"),
+                Some(ElasticException::IllegalArgument),
+            )
+        })?;
+    let mut updated_doc_mapping = doc_mapping.clone();
+    updated_doc_mapping.field_mappings = field_mappings;
+    Ok(Some(updated_doc_mapping))
+}
+
+fn add_es_properties(
+    field_mappings_json: &mut Vec<JsonValue>,
+    properties: &BTreeMap<String, ElasticFieldMapping>,
+    path_prefix: &str,
+) -> Result<usize, String> {
+    let mut num_added_fields = 0;
+
+    for (field_name, es_field_mapping) in properties {
+        let field_path = format!("{path_prefix}{field_name}");
+        let Some(field_mapping_json) = field_mappings_json
+            .iter_mut()
+            .find(|field_mapping_json| field_mapping_json["name"] == field_name.as_str())
+        else {
+            let field_mapping_json = field_mapping_json_from_es(field_name, es_field_mapping)?;
+            field_mappings_json.push(field_mapping_json);
+            num_added_fields += 1;
+            continue;
+        };
+        if es_field_mapping.is_object() && field_mapping_json["type"] == "object" {
+            if let Some(sub_field_mappings_json) = field_mapping_json
+                .get_mut("field_mappings")
+                .and_then(JsonValue::as_array_mut)
+            {
+                num_added_fields += add_es_properties(
+                    sub_field_mappings_json,
+                    &es_field_mapping.properties,
+                    &format!("{field_path}."),
+                )?;
+            }
+            continue;
+        }
+        let requested_field_mapping_json =
+            field_mapping_json_from_es(field_name, es_field_mapping)?;
+        let current_es_field_type = es_field_mapping_from_json(field_mapping_json)
+            .and_then(|current_es_field_mapping| current_es_field_mapping.field_type)
+            .unwrap_or_default();
+        let requested_es_field_type = es_field_mapping_from_json(&requested_field_mapping_json)
+            .and_then(|requested_es_field_mapping| requested_es_field_mapping.field_type)
+            .unwrap_or_default();
+
+        if current_es_field_type != requested_es_field_type {
+            return Err(format!(
+                "mapper [{field_path}] cannot be changed from type [{current_es_field_type}] to \
+                 [{requested_es_field_type}]"
+            ));
+        }
+    }
+    Ok(num_added_fields)
+}
+
+/// Converts an Elasticsearch field mapping to a serialized Quickwit field mapping entry.
+fn field_mapping_json_from_es(
+    field_name: &str,
+    es_field_mapping: &ElasticFieldMapping,
+) -> Result<JsonValue, String> {
+    let es_field_type = match es_field_mapping.field_type.as_deref() {
+        Some(es_field_type) => es_field_type,
+        None if es_field_mapping.is_object() => "object",
+        None => return Err(format!("no type specified for field [{field_name}]")),
+    };
+    let field_mapping_json = match es_field_type {
+        "text" | "match_only_text" => {
+            let mut sub_field_mappings_json = Vec::new();
+
+            for (sub_field_name, sub_es_field_mapping) in &es_field_mapping.fields {
+                let sub_field_mapping_json =
+                    field_mapping_json_from_es(sub_field_name, sub_es_field_mapping)?;
+                if sub_field_mapping_json["type"] != "text" {
+                    return Err(format!(
+                        "only `text` and `keyword` multi-fields are supported, field \
+                         [{field_name}.{sub_field_name}]"
+                    ));
+                }
+                sub_field_mappings_json.push(sub_field_mapping_json);
+            }
+            json!({
+                "name": field_name,
+                "type": "text",
+                "record": "position",
+                "fieldnorms": true,
+                "fields": sub_field_mappings_json,
+            })
+        }
+        "keyword" | "constant_keyword" | "wildcard" => json!({
+            "name": field_name,
+            "type": "text",
+            "tokenizer": "raw",
+            "fast": true,
+        }),
+        "long" | "integer" | "short" | "byte" => {
+            json!({"name": field_name, "type": "i64", "fast": true})
+        }
+        "unsigned_long" => json!({"name": field_name, "type": "u64", "fast": true}),
+        "double" | "float" | "half_float" | "scaled_float" => {
+            json!({"name": field_name, "type": "f64", "fast": true})
+        }
+        "boolean" => json!({"name": field_name, "type": "bool", "fast": true}),
+        "date" => json!({"name": field_name, "type": "datetime", "fast": true}),
+        "date_nanos" => json!({
+            "name": field_name,
+            "type": "datetime",
+            "fast": true,
+            "fast_precision": "nanoseconds",
+        }),
+        "ip" => json!({"name": field_name, "type": "ip", "fast": true}),
+        "binary" => json!({"name": field_name, "type": "bytes"}),
+        "flattened" => json!({"name": field_name, "type": "json"}),
+        "object" if es_field_mapping.properties.is_empty() => {
+            json!({"name": field_name, "type": "json"})
+        }
+        "object" => {
+            let mut sub_field_mappings_json = Vec::new();
+            add_es_properties(
+                &mut sub_field_mappings_json,
+                &es_field_mapping.properties,
+                &format!("{field_name}."),
+            )?;
+            json!({
+                "name": field_name,
+                "type": "object",
+                "field_mappings": sub_field_mappings_json,
+            })
+        }
+        "dense_vector" => {
+            let Some(dims) = es_field_mapping.dims else {
+                return Err(format!(
+                    "`dims` must be specified for dense vector field [{field_name}]"
+                ));
+            };
+            let similarity = es_field_mapping.similarity.as_deref().unwrap_or("cosine");
+            json!({
+                "name": field_name,
+                "type": "dense_vector",
+                "dims": dims,
+                "similarity": similarity,
+            })
+        }
+        _ => {
+            return Err(format!(
+                "unsupported type [{es_field_type}] for field [{field_name}]"
+            ));
+        }
+    };
+    Ok(field_mapping_json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc_mapping() -> DocMapping {
+        serde_json::from_value(json!({
+            "mode": "dynamic",
+            "field_mappings": [
+                {
+                    "name": "message",
+                    "type": "text",
+                    "fields": [{"name": "raw", "type": "text", "tokenizer": "raw"}]
+                },
+                {"name": "level", "type": "text", "tokenizer": "raw", "fast": true},
+                {"name": "timestamp", "type": "datetime", "fast": true},
+                {
+                    "name": "resource",
+                    "type": "object",
+                    "field_mappings": [{"name": "service", "type": "text", "tokenizer": "raw"}]
+                },
+                {"name": "attributes", "type": "json"},
+                {"name": "latencies", "type": "array<f64>"},
+                {"name": "all", "type": "concatenate", "concatenate_fields": ["message"]}
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn list_fields_entry(field_name: &str, field_type: ListFieldType) -> ListFieldsEntryResponse {
+        ListFieldsEntryResponse {
+            field_name: field_name.to_string(),
+            field_type: field_type as i32,
+            index_ids: vec!["my-index".to_string()],
+            searchable: true,
+            aggregatable: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_convert_to_es_mappings() {
+        let list_fields_entries = vec![
+            list_fields_entry("message", ListFieldType::Str),
+            list_fields_entry("attributes.http.status", ListFieldType::I64),
+            list_fields_entry("host\\.name", ListFieldType::Str),
+            list_fields_entry("level.sub", ListFieldType::Str),
+        ];
+        let es_mappings = convert_to_es_mappings("my-index", &doc_mapping(), &list_fields_entries);
+        assert_eq!(
+            serde_json::to_value(&es_mappings).unwrap(),
+            json!({
+                "mappings": {
+                    "dynamic": "true",
+                    "properties": {
+                        "message": {"type": "text", "fields": {"raw": {"type": "keyword"}}},
+                        "level": {"type": "keyword"},
+                        "timestamp": {"type": "date_nanos"},
+                        "resource": {
+                            "type": "object",
+                            "properties": {"service": {"type": "keyword"}}
+                        },
+                        "attributes": {
+                            "type": "object",
+                            "properties": {
+                                "http": {
+                                    "type": "object",
+                                    "properties": {"status": {"type": "long"}}
+                                }
+                            }
+                        },
+                        "latencies": {"type": "double"},
+                        "host.name": {"type": "keyword"}
+                    }
+                }
+            })
+        );
+        let es_mappings =
+            convert_to_es_mappings("other-index", &doc_mapping(), &list_fields_entries);
+        assert!(!es_mappings.mappings.properties.contains_key("host.name"));
+    }
+
+    #[test]
+    fn test_add_es_properties_to_doc_mapping() {
+        let doc_mapping = doc_mapping();
+        let properties: BTreeMap<String, ElasticFieldMapping> = serde_json::from_value(json!({
+            "level": {"type": "keyword", "ignore_above": 256},
+            "resource": {"properties": {"region": {"type": "keyword"}}},
+            "duration": {"type": "integer"},
+            "embedding": {"type": "dense_vector", "dims": 3, "similarity": "dot_product"}
+        }))
+        .unwrap();
+        let updated_doc_mapping = add_es_properties_to_doc_mapping(&doc_mapping, &properties)
+            .unwrap()
+            .unwrap();
+        let updated_es_mappings = convert_to_es_mappings("my-index", &updated_doc_mapping, &[]);
+        let updated_properties = updated_es_mappings.mappings.properties;
+        assert_eq!(
+            updated_properties["duration"],
+            ElasticFieldMapping::with_type("long")
+        );
+        assert_eq!(
+            updated_properties["resource"].properties["region"],
+            ElasticFieldMapping::with_type("keyword")
+        );
+        assert_eq!(updated_properties["embedding"].dims, Some(3));
+
+        // Re-applying the same mapping is a no-op.
+        let same_properties: BTreeMap<String, ElasticFieldMapping> =
+            serde_json::from_value(json!({"level": {"type": "keyword"}})).unwrap();
+        assert!(
+            add_es_properties_to_doc_mapping(&doc_mapping, &same_properties)
+                .unwrap()
+                .is_none()
+        );
+        let conflicting_properties: BTreeMap<String, ElasticFieldMapping> = serde_json::from_value(
+            json!({"resource": {"properties": {"service": {"type": "long"}}}}),
+        )
+        .unwrap();
+        let error =
+            add_es_properties_to_doc_mapping(&doc_mapping, &conflicting_properties).unwrap_err();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            error.error.reason.unwrap(),
+            "mapper [resource.service] cannot be changed from type [keyword] to [long]"
+        );
+        let unsupported_properties: BTreeMap<String, ElasticFieldMapping> =
+            serde_json::from_value(json!({"comments": {"type": "nested"}})).unwrap();
+        add_es_properties_to_doc_mapping(&doc_mapping, &unsupported_properties).unwrap_err();
+    }
+
+    #[test]
+    fn test_split_field_path() {
+        assert_eq!(split_field_path("a.b"), ["a", "b"]);
+        assert_eq!(split_field_path("a\\.b.c"), ["a.b", "c"]);
+        assert_eq!(split_field_path("a"), ["a"]);
+    }
+}
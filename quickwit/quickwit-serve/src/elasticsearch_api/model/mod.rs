@@ -12,18 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod async_search;
 mod bulk_body;
 mod bulk_query_params;
 mod cat_indices;
 mod error;
 mod field_capability;
+mod mappings;
 mod multi_search;
 mod scroll;
 mod search_body;
 mod search_query_params;
 mod search_response;
+mod search_template;
+mod settings;
 mod stats;
 
+pub use async_search::{
+    ASYNC_SEARCH_QUERY_PARAM_NAMES, AsyncSearchQueryParams, AsyncSearchRenderOptions,
+    ElasticsearchAsyncSearchResponse, ElasticsearchAsyncSearchStatusResponse,
+    GetAsyncSearchQueryParams,
+};
 pub use bulk_body::BulkAction;
 pub use bulk_query_params::ElasticBulkOptions;
 pub use cat_indices::{
@@ -35,6 +44,10 @@ pub use field_capability::{
     FieldCapabilityQueryParams, FieldCapabilityRequestBody, FieldCapabilityResponse,
     build_list_field_request_for_es_api, convert_to_es_field_capabilities_response,
 };
+pub use mappings::{
+    ElasticsearchMappingsResponse, PutMappingRequestBody, add_es_properties_to_doc_mapping,
+    convert_to_es_mappings,
+};
 pub use multi_search::{
     MultiSearchHeader, MultiSearchQueryParams, MultiSearchResponse, MultiSearchSingleResponse,
 };
@@ -43,7 +56,9 @@ pub use scroll::ScrollQueryParams;
 pub use search_body::SearchBody;
 pub use search_query_params::{DeleteQueryParams, SearchQueryParams, SearchQueryParamsCount};
 pub use search_response::ElasticsearchResponse;
+pub use search_template::{RenderSearchTemplateResponse, SearchTemplateBody};
 use serde::{Deserialize, Serialize};
+pub use settings::{ElasticsearchIndexSettings, ElasticsearchSettingsResponse};
 pub use stats::{ElasticsearchStatsResponse, StatsResponseEntry};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
 use quickwit_proto::search::SortOrder;
@@ -81,13 +81,22 @@ pub struct SearchBody {
     pub stored_fields: Option<BTreeSet<String>>,
     #[serde(default)]
     pub search_after: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub profile: bool,
+    #[serde(default)]
+    pub runtime_mappings: BTreeMap<String, RuntimeMapping>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_docvalue_fields")]
+    pub docvalue_fields: Vec<String>,
+    #[serde(default)]
+    pub knn: Option<KnnSearch>,
+    #[serde(default)]
+    pub rank: Option<Rank>,
 
     // Ignored values, only here for compatibility with OpenSearch Dashboards.
     #[serde(default)]
     pub _source: serde::de::IgnoredAny,
     #[serde(default)]
-    pub docvalue_fields: serde::de::IgnoredAny,
-    #[serde(default)]
     pub script_fields: serde::de::IgnoredAny,
     #[serde(default)]
     pub highlight: serde::de::IgnoredAny,
@@ -95,6 +104,86 @@ pub struct SearchBody {
     pub version: serde::de::IgnoredAny,
 }
 
+/// A runtime field defined in the `runtime_mappings` of a search request.
+///
+/// The script is a VRL expression rather than a Painless script, and the type of the field is
+/// ignored: it is inferred from the values returned by the script.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeMapping {
+    #[serde(default)]
+    #[serde(rename = "type")]
+    pub _field_type: serde::de::IgnoredAny,
+    pub script: RuntimeMappingScript,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum RuntimeMappingScript {
+    Source(String),
+    Object { source: String },
+}
+
+impl RuntimeMappingScript {
+    pub fn into_source(self) -> String {
+        match self {
+            RuntimeMappingScript::Source(source) => source,
+            RuntimeMappingScript::Object { source } => source,
+        }
+    }
+}
+
+/// An approximate k-nearest neighbor search on a `dense_vector` field.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KnnSearch {
+    pub field: String,
+    pub query_vector: Vec<f32>,
+    pub k: u32,
+    #[serde(default)]
+    pub num_candidates: Option<u32>,
+    #[serde(default)]
+    pub filter: Option<ElasticQueryDsl>,
+}
+
+/// Combines the hits of the query and of the kNN search. Only reciprocal rank fusion is
+/// supported.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rank {
+    pub rrf: RrfRank,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RrfRank {
+    #[serde(default)]
+    pub rank_constant: Option<u32>,
+    #[serde(default)]
+    pub rank_window_size: Option<u32>,
+}
+
+/// OpenSearch Dashboards sends `docvalue_fields` as objects carrying a `format`, which is ignored.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DocValueField {
+    FieldName(String),
+    Object { field: String },
+}
+
+fn deserialize_docvalue_fields<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where D: Deserializer<'de> {
+    let docvalue_fields: Vec<DocValueField> = Vec::deserialize(deserializer)?;
+    let field_names = docvalue_fields
+        .into_iter()
+        .map(|docvalue_field| match docvalue_field {
+            DocValueField::FieldName(field_name) => field_name,
+            DocValueField::Object { field, .. } => field,
+        })
+        .collect();
+    Ok(field_names)
+}
+
 struct FieldSortVecVisitor;
 
 #[derive(Deserialize)]
@@ -297,4 +386,31 @@ mod tests {
         assert!(error_msg.contains("unknown field `term`"));
         assert!(error_msg.contains("expected one of "));
     }
+
+    #[test]
+    fn test_runtime_mappings_and_docvalue_fields() {
+        let json = r#"
+            {
+                "runtime_mappings": {
+                    "duration_secs": {
+                        "type": "double",
+                        "script": {"source": ".duration_ms / 1000"}
+                    },
+                    "service": {"script": "downcase!(.service_name)"}
+                },
+                "docvalue_fields": [
+                    "service",
+                    {"field": "duration_secs", "format": "use_field_mapping"}
+                ]
+            }
+        "#;
+        let search_body = serde_json::from_str::<SearchBody>(json).unwrap();
+        let sources: Vec<String> = search_body
+            .runtime_mappings
+            .into_values()
+            .map(|runtime_mapping| runtime_mapping.script.into_source())
+            .collect();
+        assert_eq!(sources, [".duration_ms / 1000", "downcase!(.service_name)"]);
+        assert_eq!(search_body.docvalue_fields, ["service", "duration_secs"]);
+    }
 }
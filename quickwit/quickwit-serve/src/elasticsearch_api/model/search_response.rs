@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use elasticsearch_dsl::{ClusterStatistics, HitsMetadata, ShardStatistics, Suggest};
+use quickwit_proto::search::SearchProfile;
 use quickwit_search::AggregationResults;
 use serde::Serialize;
 
@@ -75,4 +76,11 @@ pub struct ElasticsearchResponse {
     #[serde(skip_serializing_if = "Map::is_empty", default)]
     /// Suggest response
     pub suggest: Map<String, Vec<Suggest>>,
+
+    /// Execution profile of the search, only set if requested.
+    ///
+    /// Quickwit has no concept of shards: the profile is a tree of leaf nodes and splits
+    /// rather than the Elasticsearch per-shard profile.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<SearchProfile>,
 }
@@ -0,0 +1,35 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+/// Body of the `_search/template` and `_render/template` requests. The template is either
+/// referenced by `id` or provided inline as `source`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SearchTemplateBody {
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Inline mustache template, either as a string or as a JSON object.
+    #[serde(default)]
+    pub source: Option<JsonValue>,
+    #[serde(default)]
+    pub params: JsonMap<String, JsonValue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderSearchTemplateResponse {
+    pub template_output: JsonValue,
+}
@@ -0,0 +1,128 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use quickwit_metastore::IndexMetadata;
+use serde::Serialize;
+
+/// Settings of an index in the Elasticsearch format. As in Elasticsearch, values are strings.
+///
+/// The `indexing` and `retention` sections are specific to Quickwit.
+#[derive(Debug, Clone, Serialize)]
+pub struct ElasticsearchIndexSettingsEntry {
+    pub creation_date: String,
+    pub uuid: String,
+    pub provided_name: String,
+    pub number_of_shards: String,
+    pub number_of_replicas: String,
+    pub refresh_interval: String,
+    pub indexing: ElasticsearchIndexingSettings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention: Option<ElasticsearchRetentionSettings>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ElasticsearchIndexingSettings {
+    pub commit_timeout_secs: String,
+    pub split_num_docs_target: String,
+    pub docstore_blocksize: String,
+    pub docstore_compression_level: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ElasticsearchRetentionSettings {
+    pub period: String,
+    pub schedule: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ElasticsearchSettings {
+    pub index: ElasticsearchIndexSettingsEntry,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ElasticsearchIndexSettings {
+    pub settings: ElasticsearchSettings,
+}
+
+/// Response of `GET _settings`, keyed by index ID.
+pub type ElasticsearchSettingsResponse = BTreeMap<String, ElasticsearchIndexSettings>;
+
+impl From<&IndexMetadata> for ElasticsearchIndexSettings {
+    fn from(index_metadata: &IndexMetadata) -> Self {
+        let index_config = &index_metadata.index_config;
+        let indexing_settings = &index_config.indexing_settings;
+
+        let indexing = ElasticsearchIndexingSettings {
+            commit_timeout_secs: indexing_settings.commit_timeout_secs.to_string(),
+            split_num_docs_target: indexing_settings.split_num_docs_target.to_string(),
+            docstore_blocksize: indexing_settings.docstore_blocksize.to_string(),
+            docstore_compression_level: indexing_settings.docstore_compression_level.to_string(),
+        };
+        let retention = index_config
+            .retention_policy_opt
+            .as_ref()
+            .map(|retention_policy| ElasticsearchRetentionSettings {
+                period: retention_policy.retention_period.clone(),
+                schedule: retention_policy.evaluation_schedule.clone(),
+            });
+        let index = ElasticsearchIndexSettingsEntry {
+            creation_date: (index_metadata.create_timestamp * 1_000).to_string(),
+            uuid: index_metadata.index_uid.to_string(),
+            provided_name: index_config.index_id.clone(),
+            number_of_shards: "1".to_string(),
+            number_of_replicas: "1".to_string(),
+            refresh_interval: format!("{}s", indexing_settings.commit_timeout_secs),
+            indexing,
+            retention,
+        };
+        ElasticsearchIndexSettings {
+            settings: ElasticsearchSettings { index },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::RetentionPolicy;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_es_index_settings_from_index_metadata() {
+        let mut index_metadata = IndexMetadata::for_test("my-index", "ram:///indexes/my-index");
+        index_metadata.create_timestamp = 1_700_000_000;
+        index_metadata.index_config.retention_policy_opt = Some(RetentionPolicy {
+            retention_period: "30 days".to_string(),
+            evaluation_schedule: "daily".to_string(),
+        });
+        let es_index_settings = ElasticsearchIndexSettings::from(&index_metadata);
+        let es_index_settings_json = serde_json::to_value(&es_index_settings).unwrap();
+        let index_settings_json = &es_index_settings_json["settings"]["index"];
+
+        assert_eq!(index_settings_json["creation_date"], "1700000000000");
+        assert_eq!(index_settings_json["provided_name"], "my-index");
+        assert_eq!(index_settings_json["refresh_interval"], "60s");
+        assert_eq!(
+            index_settings_json["indexing"]["split_num_docs_target"],
+            "10000000"
+        );
+        assert_eq!(
+            index_settings_json["retention"],
+            json!({"period": "30 days", "schedule": "daily"})
+        );
+    }
+}
@@ -24,42 +24,58 @@ use futures_util::StreamExt;
 use itertools::Itertools;
 use quickwit_cluster::Cluster;
 use quickwit_common::truncate_str;
-use quickwit_config::{NodeConfig, validate_index_id_pattern};
+use quickwit_config::{
+    IndexConfig, MustacheTemplate, NodeConfig, SearchTemplate, prepare_doc_mapping_update,
+    validate_index_id_pattern,
+};
 use quickwit_index_management::IndexService;
 use quickwit_metastore::*;
-use quickwit_proto::metastore::MetastoreServiceClient;
+use quickwit_proto::metastore::{
+    EntityKind, GetSearchTemplateRequest, MetastoreError, MetastoreService, MetastoreServiceClient,
+    serde_utils,
+};
 use quickwit_proto::search::{
-    CountHits, ListFieldsResponse, PartialHit, ScrollRequest, SearchResponse, SortByValue,
-    SortDatetimeFormat,
+    CountHits, ListFieldsRequest, ListFieldsResponse, PartialHit, ScrollRequest, SearchResponse,
+    SortByValue, SortDatetimeFormat,
 };
-use quickwit_proto::types::IndexUid;
+use quickwit_proto::types::{DocMappingUid, IndexUid};
 use quickwit_query::BooleanOperand;
 use quickwit_query::query_ast::{BoolQuery, QueryAst, UserInputQuery};
 use quickwit_search::{
-    AggregationResults, SearchError, SearchService, list_all_splits, resolve_index_patterns,
+    AggregationResults, AsyncSearchRequest, AsyncSearchResponse, GetAsyncSearchRequest, KnnQuery,
+    SearchError, SearchService, list_all_splits, resolve_index_patterns,
 };
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{Value as JsonValue, json};
 use warp::hyper::StatusCode;
 use warp::reply::with_status;
 use warp::{Filter, Rejection};
 
 use super::filter::{
-    elastic_cat_indices_filter, elastic_cluster_health_filter, elastic_cluster_info_filter,
-    elastic_delete_index_filter, elastic_field_capabilities_filter,
-    elastic_index_cat_indices_filter, elastic_index_count_filter,
-    elastic_index_field_capabilities_filter, elastic_index_search_filter,
-    elastic_index_stats_filter, elastic_multi_search_filter, elastic_resolve_index_filter,
-    elastic_scroll_filter, elastic_stats_filter, elasticsearch_filter,
+    elastic_async_search_status_filter, elastic_cat_indices_filter, elastic_cluster_health_filter,
+    elastic_cluster_info_filter, elastic_delete_async_search_filter, elastic_delete_index_filter,
+    elastic_field_capabilities_filter, elastic_get_async_search_filter,
+    elastic_index_async_search_filter, elastic_index_cat_indices_filter,
+    elastic_index_count_filter, elastic_index_field_capabilities_filter,
+    elastic_index_mapping_filter, elastic_index_put_mapping_filter, elastic_index_search_filter,
+    elastic_index_search_template_filter, elastic_index_settings_filter,
+    elastic_index_stats_filter, elastic_mapping_filter, elastic_multi_search_filter,
+    elastic_render_search_template_filter, elastic_resolve_index_filter, elastic_scroll_filter,
+    elastic_settings_filter, elastic_stats_filter, elasticsearch_filter,
 };
 use super::model::{
-    CatIndexQueryParams, DeleteQueryParams, ElasticsearchCatIndexResponse, ElasticsearchError,
-    ElasticsearchResolveIndexEntryResponse, ElasticsearchResolveIndexResponse,
-    ElasticsearchResponse, ElasticsearchStatsResponse, FieldCapabilityQueryParams,
-    FieldCapabilityRequestBody, FieldCapabilityResponse, MultiSearchHeader, MultiSearchQueryParams,
-    MultiSearchResponse, MultiSearchSingleResponse, ScrollQueryParams, SearchBody,
-    SearchQueryParams, SearchQueryParamsCount, StatsResponseEntry,
-    build_list_field_request_for_es_api, convert_to_es_field_capabilities_response,
+    AsyncSearchQueryParams, AsyncSearchRenderOptions, CatIndexQueryParams, DeleteQueryParams,
+    ElasticException, ElasticsearchAsyncSearchResponse, ElasticsearchAsyncSearchStatusResponse,
+    ElasticsearchCatIndexResponse, ElasticsearchError, ElasticsearchIndexSettings,
+    ElasticsearchMappingsResponse, ElasticsearchResolveIndexEntryResponse,
+    ElasticsearchResolveIndexResponse, ElasticsearchResponse, ElasticsearchSettingsResponse,
+    ElasticsearchStatsResponse, FieldCapabilityQueryParams, FieldCapabilityRequestBody,
+    FieldCapabilityResponse, GetAsyncSearchQueryParams, MultiSearchHeader, MultiSearchQueryParams,
+    MultiSearchResponse, MultiSearchSingleResponse, PutMappingRequestBody,
+    RenderSearchTemplateResponse, ScrollQueryParams, SearchBody, SearchQueryParams,
+    SearchQueryParamsCount, SearchTemplateBody, StatsResponseEntry,
+    add_es_properties_to_doc_mapping, build_list_field_request_for_es_api,
+    convert_to_es_field_capabilities_response, convert_to_es_mappings,
 };
 use super::{TrackTotalHits, make_elastic_api_response};
 use crate::format::BodyFormat;
@@ -237,6 +253,48 @@ pub fn es_compat_index_cat_indices_handler(
         .boxed()
 }
 
+/// GET _elastic/{index}/_mapping
+pub fn es_compat_index_mapping_handler(
+    metastore_service: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_index_mapping_filter()
+        .or(elastic_mapping_filter())
+        .unify()
+        .and(with_arg(metastore_service))
+        .and(with_arg(search_service))
+        .then(es_compat_index_mapping)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// PUT _elastic/{index}/_mapping
+pub fn es_compat_index_put_mapping_handler(
+    index_service: IndexService,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_index_put_mapping_filter()
+        .and(with_arg(index_service))
+        .then(es_compat_index_put_mapping)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// GET _elastic/{index}/_settings
+pub fn es_compat_index_settings_handler(
+    metastore_service: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_index_settings_filter()
+        .or(elastic_settings_filter())
+        .unify()
+        .and(with_arg(metastore_service))
+        .then(es_compat_index_settings)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
 /// GET  _elastic/_resolve/index/{index}
 pub fn es_compat_resolve_index_handler(
     metastore_service: MetastoreServiceClient,
@@ -260,6 +318,32 @@ pub fn es_compat_index_search_handler(
         .boxed()
 }
 
+/// GET or POST _elastic/{index}/_search/template
+pub fn es_compat_index_search_template_handler(
+    metastore: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_index_search_template_filter()
+        .and(with_arg(metastore))
+        .and(with_arg(search_service))
+        .then(es_compat_index_search_template)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// GET or POST _elastic/_render/template/{id}
+pub fn es_compat_render_search_template_handler(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_render_search_template_filter()
+        .and(with_arg(metastore))
+        .then(es_compat_render_search_template)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
 /// GET or POST _elastic/{index}/_count
 pub fn es_compat_index_count_handler(
     search_service: Arc<dyn SearchService>,
@@ -302,6 +386,54 @@ pub fn es_compat_scroll_handler(
         .boxed()
 }
 
+/// POST _elastic/{index}/_async_search
+pub fn es_compat_index_async_search_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_index_async_search_filter()
+        .and(with_arg(search_service))
+        .then(es_compat_index_async_search)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// GET _elastic/_async_search/{id}
+pub fn es_compat_get_async_search_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_get_async_search_filter()
+        .and(with_arg(search_service))
+        .then(es_compat_get_async_search)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// GET _elastic/_async_search/status/{id}
+pub fn es_compat_async_search_status_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_async_search_status_filter()
+        .and(with_arg(search_service))
+        .then(es_compat_async_search_status)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// DELETE _elastic/_async_search/{id}
+pub fn es_compat_delete_async_search_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_delete_async_search_filter()
+        .and(with_arg(search_service))
+        .then(es_compat_delete_async_search)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
 #[allow(clippy::result_large_err)]
 fn build_request_for_es_api(
     index_id_patterns: Vec<String>,
@@ -396,6 +528,52 @@ fn build_request_for_es_api(
 
     let has_doc_id_field = sort_fields.iter().any(is_doc_field);
     let search_after = partial_hit_from_search_after_param(search_body.search_after, &sort_fields)?;
+    let profile = search_body.profile;
+
+    let runtime_fields: Option<String> = if search_body.runtime_mappings.is_empty() {
+        None
+    } else {
+        let runtime_fields: serde_json::Map<String, serde_json::Value> = search_body
+            .runtime_mappings
+            .into_iter()
+            .map(|(field_name, runtime_mapping)| {
+                let script = runtime_mapping.script.into_source();
+                (field_name, json!({ "script": script }))
+            })
+            .collect();
+        serde_json::to_string(&runtime_fields).ok()
+    };
+    let mut docvalue_fields = search_body.docvalue_fields;
+    docvalue_fields.extend(search_params.docvalue_fields.unwrap_or_default());
+
+    let knn = match (search_body.knn, search_body.rank) {
+        (Some(knn_search), rank_opt) => {
+            let filter: Option<QueryAst> = knn_search
+                .filter
+                .map(QueryAst::try_from)
+                .transpose()
+                .map_err(|err: anyhow::Error| SearchError::InvalidQuery(err.to_string()))?;
+            let rrf_rank_opt = rank_opt.map(|rank| rank.rrf);
+            let knn_query = KnnQuery {
+                field: knn_search.field,
+                query_vector: knn_search.query_vector,
+                k: knn_search.k,
+                num_candidates: knn_search.num_candidates,
+                filter,
+                rank_constant: rrf_rank_opt
+                    .as_ref()
+                    .and_then(|rrf_rank| rrf_rank.rank_constant),
+                rank_window_size: rrf_rank_opt.and_then(|rrf_rank| rrf_rank.rank_window_size),
+            };
+            Some(serde_json::to_string(&knn_query).expect("Failed to serialize KnnQuery"))
+        }
+        (None, Some(_)) => {
+            return Err(ElasticsearchError::from(SearchError::InvalidArgument(
+                "`rank` is only supported with a `knn` search".to_string(),
+            )));
+        }
+        (None, None) => None,
+    };
 
     Ok((
         quickwit_proto::search::SearchRequest {
@@ -412,6 +590,10 @@ fn build_request_for_es_api(
             search_after,
             count_hits,
             ignore_missing_indexes,
+            profile,
+            runtime_fields,
+            docvalue_fields,
+            knn,
         },
         has_doc_id_field,
     ))
@@ -533,13 +715,111 @@ async fn es_compat_index_search(
     Ok(search_response_rest)
 }
 
+fn illegal_argument_error(reason: String) -> ElasticsearchError {
+    ElasticsearchError::new(
+        StatusCode::BAD_REQUEST,
+        reason,
+        Some(ElasticException::IllegalArgument),
+    )
+}
+
+/// Renders the search template referenced by `id` (stored in the metastore) or provided inline
+/// as `source` with the request parameters.
+async fn render_search_template(
+    template_id_opt: Option<String>,
+    template_body: SearchTemplateBody,
+    metastore: &MetastoreServiceClient,
+) -> Result<String, ElasticsearchError> {
+    let params = JsonValue::Object(template_body.params);
+
+    match (template_id_opt.or(template_body.id), template_body.source) {
+        (Some(template_id), None) => {
+            let get_search_template_request = GetSearchTemplateRequest { template_id };
+            let get_search_template_response = metastore
+                .get_search_template(get_search_template_request)
+                .await
+                .map_err(|metastore_error| match metastore_error {
+                    MetastoreError::NotFound(entity @ EntityKind::SearchTemplate { .. }) => {
+                        ElasticsearchError::new(
+                            StatusCode::NOT_FOUND,
+                            format!("{entity} not found"),
+                            Some(ElasticException::ResourceNotFound),
+                        )
+                    }
+                    _ => ElasticsearchError::from(SearchError::from(metastore_error)),
+                })?;
+            let search_template: SearchTemplate =
+                serde_utils::from_json_str(&get_search_template_response.search_template_json)
+                    .map_err(SearchError::from)?;
+            search_template
+                .render(&params)
+                .map_err(|error| illegal_argument_error(format!("{error:#}")))
+        }
+        (None, Some(source)) => {
+            let source = match source {
+                JsonValue::String(source) => source,
+                JsonValue::Object(_) => source.to_string(),
+                _ => {
+                    let reason = "search template `source` must be a string or a JSON object";
+                    return Err(illegal_argument_error(reason.to_string()));
+                }
+            };
+            let mustache_template = MustacheTemplate::parse(&source).map_err(|error| {
+                illegal_argument_error(format!("failed to parse search template source: {error}"))
+            })?;
+            Ok(mustache_template.render(&params))
+        }
+        (Some(_), Some(_)) => Err(illegal_argument_error(
+            "search template `id` and `source` are mutually exclusive".to_string(),
+        )),
+        (None, None) => Err(illegal_argument_error(
+            "either search template `id` or `source` must be specified".to_string(),
+        )),
+    }
+}
+
+async fn es_compat_index_search_template(
+    index_id_patterns: Vec<String>,
+    search_params: SearchQueryParams,
+    template_body: SearchTemplateBody,
+    metastore: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+) -> Result<ElasticsearchResponse, ElasticsearchError> {
+    let rendered_template = render_search_template(None, template_body, &metastore).await?;
+    let search_body: SearchBody = serde_json::from_str(&rendered_template).map_err(|error| {
+        illegal_argument_error(format!("failed to parse rendered search template: {error}"))
+    })?;
+    es_compat_index_search(
+        index_id_patterns,
+        search_params,
+        search_body,
+        search_service,
+    )
+    .await
+}
+
+async fn es_compat_render_search_template(
+    template_id_opt: Option<String>,
+    template_body: SearchTemplateBody,
+    metastore: MetastoreServiceClient,
+) -> Result<RenderSearchTemplateResponse, ElasticsearchError> {
+    let rendered_template =
+        render_search_template(template_id_opt, template_body, &metastore).await?;
+    let template_output: JsonValue = serde_json::from_str(&rendered_template).map_err(|error| {
+        illegal_argument_error(format!(
+            "rendered search template is not valid JSON: {error}"
+        ))
+    })?;
+    Ok(RenderSearchTemplateResponse { template_output })
+}
+
 /// Returns JSON in the format:
 ///
 /// {
 ///   "acknowledged": true
 /// }
 #[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct ElasticsearchDeleteResponse {
+pub struct ElasticsearchAcknowledgedResponse {
     pub acknowledged: bool,
 }
 
@@ -547,7 +827,7 @@ async fn es_compat_delete_index(
     index_id_patterns: Vec<String>,
     query_params: DeleteQueryParams,
     index_service: IndexService,
-) -> Result<ElasticsearchDeleteResponse, ElasticsearchError> {
+) -> Result<ElasticsearchAcknowledgedResponse, ElasticsearchError> {
     index_service
         .delete_indexes(
             index_id_patterns,
@@ -555,7 +835,7 @@ async fn es_compat_delete_index(
             false,
         )
         .await?;
-    Ok(ElasticsearchDeleteResponse { acknowledged: true })
+    Ok(ElasticsearchAcknowledgedResponse { acknowledged: true })
 }
 
 async fn es_compat_stats(
@@ -661,6 +941,102 @@ async fn es_compat_resolve_index(
     })
 }
 
+async fn es_compat_index_mapping(
+    index_id_patterns: Vec<String>,
+    mut metastore: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+) -> Result<ElasticsearchMappingsResponse, ElasticsearchError> {
+    let indexes_metadata = resolve_index_patterns(&index_id_patterns, &mut metastore).await?;
+
+    if indexes_metadata.is_empty() {
+        return Ok(ElasticsearchMappingsResponse::new());
+    }
+    // The fields captured by JSON fields or by the dynamic mode are only known from the splits.
+    let list_fields_request = ListFieldsRequest {
+        index_id_patterns: indexes_metadata
+            .iter()
+            .map(|index_metadata| index_metadata.index_id().to_string())
+            .collect(),
+        fields: Vec::new(),
+        start_timestamp: None,
+        end_timestamp: None,
+    };
+    let list_fields_response = search_service.root_list_fields(list_fields_request).await?;
+
+    let es_mappings_response = indexes_metadata
+        .iter()
+        .map(|index_metadata| {
+            let index_id = index_metadata.index_id();
+            let es_index_mappings = convert_to_es_mappings(
+                index_id,
+                &index_metadata.index_config.doc_mapping,
+                &list_fields_response.fields,
+            );
+            (index_id.to_string(), es_index_mappings)
+        })
+        .collect();
+    Ok(es_mappings_response)
+}
+
+async fn es_compat_index_put_mapping(
+    index_id_patterns: Vec<String>,
+    put_mapping_request: PutMappingRequestBody,
+    index_service: IndexService,
+) -> Result<ElasticsearchAcknowledgedResponse, ElasticsearchError> {
+    let mut metastore = index_service.metastore();
+    let indexes_metadata = resolve_index_patterns(&index_id_patterns, &mut metastore).await?;
+
+    for index_metadata in indexes_metadata {
+        let current_index_config = index_metadata.index_config;
+        let Some(mut new_doc_mapping) = add_es_properties_to_doc_mapping(
+            &current_index_config.doc_mapping,
+            &put_mapping_request.properties,
+        )?
+        else {
+            continue;
+        };
+        new_doc_mapping.doc_mapping_uid = DocMappingUid::random();
+
+        let (new_doc_mapping, _) = prepare_doc_mapping_update(
+            new_doc_mapping,
+            &current_index_config.doc_mapping,
+            &current_index_config.search_settings,
+        )
+        .map_err(|error| {
+            ElasticsearchError::new(
+                StatusCode::BAD_REQUEST,
+                format!("invalid mapping: {error:#}"),
+                Some(ElasticException::IllegalArgument),
+            )
+        })?;
+        let new_index_config = IndexConfig {
+            doc_mapping: new_doc_mapping,
+            ..current_index_config
+        };
+        index_service
+            .update_index(index_metadata.index_uid, new_index_config)
+            .await?;
+    }
+    Ok(ElasticsearchAcknowledgedResponse { acknowledged: true })
+}
+
+async fn es_compat_index_settings(
+    index_id_patterns: Vec<String>,
+    mut metastore: MetastoreServiceClient,
+) -> Result<ElasticsearchSettingsResponse, ElasticsearchError> {
+    let indexes_metadata = resolve_index_patterns(&index_id_patterns, &mut metastore).await?;
+    let es_settings_response = indexes_metadata
+        .iter()
+        .map(|index_metadata| {
+            (
+                index_metadata.index_id().to_string(),
+                ElasticsearchIndexSettings::from(index_metadata),
+            )
+        })
+        .collect();
+    Ok(es_settings_response)
+}
+
 async fn es_compat_index_field_capabilities(
     index_id_patterns: Vec<String>,
     search_params: FieldCapabilityQueryParams,
@@ -791,8 +1167,13 @@ fn convert_hit(
         }
     }
 
+    let fields = hit
+        .fields_json
+        .and_then(|fields_json| serde_json::from_str(&fields_json).ok())
+        .unwrap_or_default();
+
     ElasticHit {
-        fields: Default::default(),
+        fields,
         explanation: None,
         index: hit.index_id,
         id: "".to_string(),
@@ -942,6 +1323,217 @@ async fn es_scroll(
     Ok(search_response_rest)
 }
 
+const DEFAULT_ASYNC_SEARCH_KEEP_ALIVE: Duration = Duration::from_secs(5 * 24 * 60 * 60);
+
+const DEFAULT_ASYNC_SEARCH_WAIT_FOR_COMPLETION_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[allow(clippy::result_large_err)]
+fn parse_async_search_duration(
+    param_name: &str,
+    duration_str_opt: Option<&str>,
+) -> Result<Option<Duration>, ElasticsearchError> {
+    let Some(duration_str) = duration_str_opt else {
+        return Ok(None);
+    };
+    let duration = humantime::parse_duration(duration_str).map_err(|_| {
+        SearchError::InvalidArgument(format!("invalid `{param_name}`: {duration_str}"))
+    })?;
+    Ok(Some(duration))
+}
+
+async fn es_compat_index_async_search(
+    index_id_patterns: Vec<String>,
+    async_search_params: AsyncSearchQueryParams,
+    search_params: SearchQueryParams,
+    search_body: SearchBody,
+    search_service: Arc<dyn SearchService>,
+) -> Result<ElasticsearchAsyncSearchResponse, ElasticsearchError> {
+    if search_params.scroll.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "scroll is not supported by async search".to_string(),
+        )
+        .into());
+    }
+    let wait_for_completion_timeout = parse_async_search_duration(
+        "wait_for_completion_timeout",
+        async_search_params.wait_for_completion_timeout.as_deref(),
+    )?
+    .unwrap_or(DEFAULT_ASYNC_SEARCH_WAIT_FOR_COMPLETION_TIMEOUT);
+    let keep_alive =
+        parse_async_search_duration("keep_alive", async_search_params.keep_alive.as_deref())?
+            .unwrap_or(DEFAULT_ASYNC_SEARCH_KEEP_ALIVE);
+    let _source_excludes = search_params._source_excludes.clone();
+    let _source_includes = search_params._source_includes.clone();
+    let allow_partial_search_results = search_params.allow_partial_search_results();
+    let (search_request, append_shard_doc) =
+        build_request_for_es_api(index_id_patterns, search_params, search_body)?;
+    let render_options = AsyncSearchRenderOptions {
+        append_shard_doc,
+        _source_excludes,
+        _source_includes,
+        allow_partial_search_results,
+    };
+    let async_search_request = AsyncSearchRequest {
+        search_request,
+        wait_for_completion_timeout,
+        keep_alive,
+        context: serde_json::to_string(&render_options)
+            .expect("serializing render options should never fail"),
+    };
+    let async_search_response = search_service
+        .submit_async_search(async_search_request)
+        .await?;
+
+    let keep_on_completion = async_search_params.keep_on_completion.unwrap_or(false);
+
+    if async_search_response.is_running || keep_on_completion {
+        return convert_to_es_async_search_response(async_search_response);
+    }
+    // The search completed on submission and its results are not kept, so they can't be
+    // fetched later on.
+    search_service
+        .delete_async_search(async_search_response.id.clone())
+        .await?;
+    let mut async_search_response_rest =
+        convert_to_es_async_search_response(async_search_response)?;
+    async_search_response_rest.id = None;
+    Ok(async_search_response_rest)
+}
+
+async fn es_compat_get_async_search(
+    id: String,
+    get_async_search_params: GetAsyncSearchQueryParams,
+    search_service: Arc<dyn SearchService>,
+) -> Result<ElasticsearchAsyncSearchResponse, ElasticsearchError> {
+    let wait_for_completion_timeout = parse_async_search_duration(
+        "wait_for_completion_timeout",
+        get_async_search_params
+            .wait_for_completion_timeout
+            .as_deref(),
+    )?
+    .unwrap_or_default();
+    let keep_alive_opt =
+        parse_async_search_duration("keep_alive", get_async_search_params.keep_alive.as_deref())?;
+    let get_async_search_request = GetAsyncSearchRequest {
+        id,
+        wait_for_completion_timeout,
+        keep_alive_opt,
+    };
+    let async_search_response = search_service
+        .get_async_search(get_async_search_request)
+        .await?;
+    convert_to_es_async_search_response(async_search_response)
+}
+
+async fn es_compat_async_search_status(
+    id: String,
+    search_service: Arc<dyn SearchService>,
+) -> Result<ElasticsearchAsyncSearchStatusResponse, ElasticsearchError> {
+    let get_async_search_request = GetAsyncSearchRequest {
+        id,
+        wait_for_completion_timeout: Duration::ZERO,
+        keep_alive_opt: None,
+    };
+    let async_search_response = search_service
+        .get_async_search(get_async_search_request)
+        .await?;
+    let shards = async_search_response
+        .search_response
+        .as_ref()
+        .map(|search_response| {
+            let num_failed_splits = search_response.failed_splits.len() as u32;
+            let num_successful_splits = search_response.num_successful_splits as u32;
+            ShardStatistics {
+                total: num_successful_splits + num_failed_splits,
+                successful: num_successful_splits,
+                skipped: 0u32,
+                failed: num_failed_splits,
+                failures: Vec::new(),
+            }
+        })
+        .unwrap_or_default();
+    let completion_status = if async_search_response.is_running {
+        None
+    } else if let Some(search_error) = async_search_response.error {
+        Some(ElasticsearchError::from(search_error).status.as_u16())
+    } else {
+        Some(StatusCode::OK.as_u16())
+    };
+    Ok(ElasticsearchAsyncSearchStatusResponse {
+        id: async_search_response.id,
+        is_partial: async_search_response.is_partial,
+        is_running: async_search_response.is_running,
+        start_time_in_millis: async_search_response.start_time_millis,
+        expiration_time_in_millis: async_search_response.expiration_time_millis,
+        completion_time_in_millis: async_search_response.completion_time_millis,
+        shards,
+        completion_status,
+    })
+}
+
+async fn es_compat_delete_async_search(
+    id: String,
+    search_service: Arc<dyn SearchService>,
+) -> Result<ElasticsearchAcknowledgedResponse, ElasticsearchError> {
+    search_service.delete_async_search(id).await?;
+    Ok(ElasticsearchAcknowledgedResponse { acknowledged: true })
+}
+
+#[allow(clippy::result_large_err)]
+fn convert_to_es_async_search_response(
+    async_search_response: AsyncSearchResponse,
+) -> Result<ElasticsearchAsyncSearchResponse, ElasticsearchError> {
+    let render_options: AsyncSearchRenderOptions =
+        serde_json::from_str(&async_search_response.context).map_err(|_| {
+            ElasticsearchError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to parse async search context".to_string(),
+                None,
+            )
+        })?;
+    let mut error_opt = async_search_response
+        .error
+        .map(|search_error| ElasticsearchError::from(search_error).error);
+
+    let response_opt = if let Some(search_response) = async_search_response.search_response {
+        let took_millis = (search_response.elapsed_time_micros / 1_000) as u32;
+        match convert_to_es_search_response(
+            search_response,
+            render_options.append_shard_doc,
+            render_options._source_excludes,
+            render_options._source_includes,
+            render_options.allow_partial_search_results,
+        ) {
+            Ok(mut search_response_rest) => {
+                search_response_rest.took = took_millis;
+                Some(search_response_rest)
+            }
+            // Failed splits make the search fail if partial results are not allowed.
+            Err(elasticsearch_error) => {
+                if error_opt.is_none() {
+                    error_opt = Some(elasticsearch_error.error);
+                }
+                None
+            }
+        }
+    } else if error_opt.is_none() {
+        // The search is running, but no partial results are available yet.
+        Some(ElasticsearchResponse::default())
+    } else {
+        None
+    };
+    Ok(ElasticsearchAsyncSearchResponse {
+        id: Some(async_search_response.id),
+        is_partial: async_search_response.is_partial,
+        is_running: async_search_response.is_running,
+        start_time_in_millis: async_search_response.start_time_millis,
+        expiration_time_in_millis: async_search_response.expiration_time_millis,
+        completion_time_in_millis: async_search_response.completion_time_millis,
+        response: response_opt,
+        error: error_opt,
+    })
+}
+
 fn convert_to_es_cat_indices_response(
     index_id_to_resp: &mut HashMap<IndexUid, ElasticsearchCatIndexResponse>,
     splits: Vec<SplitMetadata>,
@@ -1052,6 +1644,7 @@ fn convert_to_es_search_response(
             failed: num_failed_splits,
             failures: Vec::new(),
         },
+        profile: resp.profile,
         ..Default::default()
     })
 }
@@ -1114,6 +1707,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_build_request_for_es_api_with_knn() {
+        let search_body: SearchBody = serde_json::from_value(json!({
+            "query": {"match": {"title": "foo"}},
+            "knn": {
+                "field": "embedding",
+                "query_vector": [0.5, 1.0],
+                "k": 5,
+                "num_candidates": 50,
+                "filter": {"term": {"lang": "en"}}
+            },
+            "rank": {"rrf": {"rank_constant": 20}}
+        }))
+        .unwrap();
+        let (search_request, _) = build_request_for_es_api(
+            vec!["my-index".to_string()],
+            SearchQueryParams::default(),
+            search_body,
+        )
+        .unwrap();
+        let knn_query: KnnQuery =
+            serde_json::from_str(search_request.knn.as_deref().unwrap()).unwrap();
+        assert_eq!(knn_query.field, "embedding");
+        assert_eq!(knn_query.query_vector, [0.5, 1.0]);
+        assert_eq!(knn_query.k, 5);
+        assert_eq!(knn_query.num_candidates, Some(50));
+        assert!(knn_query.filter.is_some());
+        assert_eq!(knn_query.rank_constant, Some(20));
+        assert_eq!(knn_query.rank_window_size, None);
+
+        let search_body: SearchBody = serde_json::from_value(json!({"rank": {"rrf": {}}})).unwrap();
+        let error = build_request_for_es_api(
+            vec!["my-index".to_string()],
+            SearchQueryParams::default(),
+            search_body,
+        )
+        .unwrap_err();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    }
+
     #[test]
     fn test_single_element() {
         let input = "app";
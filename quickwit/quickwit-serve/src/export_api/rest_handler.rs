@@ -0,0 +1,364 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::{StreamExt, stream};
+use quickwit_proto::search::{CountHits, Hit, SearchRequest};
+use quickwit_query::query_ast::query_ast_from_user_text;
+use quickwit_search::{HitStream, SearchError, SearchService};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tracing::warn;
+use warp::hyper::header::CONTENT_TYPE;
+use warp::{Filter, Rejection, Reply};
+
+use crate::format::{csv_cell, lookup_field, push_csv_line};
+use crate::rest::recover_fn;
+use crate::rest_api_response::into_rest_api_response;
+use crate::search_api::{SortBy, extract_index_id_patterns, sort_by_mini_dsl};
+use crate::simple_list::{from_simple_list, to_simple_list};
+use crate::{BodyFormat, with_arg};
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(export),
+    components(schemas(ExportFormat, ExportRequestQueryString))
+)]
+pub(crate) struct ExportApi;
+
+/// Output format of the export API.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// One JSON document per line.
+    #[default]
+    Ndjson,
+    /// Comma-separated values, with a header line. Requires `fields`.
+    Csv,
+}
+
+impl ExportFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+/// This struct represents the QueryString passed to the export API.
+#[derive(
+    Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::IntoParams, utoipa::ToSchema,
+)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+pub struct ExportRequestQueryString {
+    /// Query text. The query language is that of tantivy.
+    pub query: String,
+    // Fields to search on
+    #[param(rename = "search_field")]
+    #[schema(rename = "search_field")]
+    #[serde(default)]
+    #[serde(rename = "search_field")]
+    #[serde(deserialize_with = "from_simple_list")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "to_simple_list")]
+    pub search_fields: Option<Vec<String>>,
+    /// If set, restrict the export to documents with a `timestamp >= start_timestamp`.
+    /// This timestamp is expressed in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_timestamp: Option<i64>,
+    /// If set, restrict the export to documents with a `timestamp < end_timestamp`.
+    /// This timestamp is expressed in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_timestamp: Option<i64>,
+    /// Specifies how documents are sorted. Unsorted exports are faster.
+    #[serde(deserialize_with = "sort_by_mini_dsl")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "SortBy::is_empty")]
+    #[param(value_type = String)]
+    pub sort_by: SortBy,
+    /// The output format: `ndjson` (default) or `csv`.
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Fields to export. Nested fields are expressed with dots. By default, the whole documents
+    /// are exported.
+    #[serde(default)]
+    #[serde(deserialize_with = "from_simple_list")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "to_simple_list")]
+    pub fields: Option<Vec<String>>,
+}
+
+fn export_request_from_query_string(
+    index_id_patterns: Vec<String>,
+    export_request: &ExportRequestQueryString,
+) -> Result<SearchRequest, SearchError> {
+    let query_ast =
+        query_ast_from_user_text(&export_request.query, export_request.search_fields.clone());
+    let query_ast_json = serde_json::to_string(&query_ast)?;
+    let search_request = SearchRequest {
+        index_id_patterns,
+        query_ast: query_ast_json,
+        start_timestamp: export_request.start_timestamp,
+        end_timestamp: export_request.end_timestamp,
+        sort_fields: export_request.sort_by.sort_fields.clone(),
+        count_hits: CountHits::Underestimate as i32,
+        ..Default::default()
+    };
+    Ok(search_request)
+}
+
+pub(crate) fn export_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "export")
+        .and_then(extract_index_id_patterns)
+        .and(warp::get())
+        .and(warp::query())
+        .and(with_arg(search_service))
+        .then(export)
+        .recover(recover_fn)
+        .boxed()
+}
+
+#[utoipa::path(
+    get,
+    tag = "Search",
+    path = "/{index_id}/export",
+    responses(
+        (status = 200, description = "Successfully started the export.")
+    ),
+    params(
+        ExportRequestQueryString,
+        ("index_id" = String, Path, description = "The index ID to export."),
+    )
+)]
+/// Export Documents
+///
+/// Streams all the documents matching a query as NDJSON or CSV, split by split. If the export
+/// fails midway, the response body is interrupted.
+async fn export(
+    index_id_patterns: Vec<String>,
+    export_request: ExportRequestQueryString,
+    search_service: Arc<dyn SearchService>,
+) -> warp::reply::Response {
+    let format = export_request.format;
+
+    match export_endpoint(index_id_patterns, export_request, &*search_service).await {
+        Ok(body_stream) => {
+            let body = warp::reply::Body::wrap_stream(body_stream);
+            let response = warp::reply::Response::new(body);
+            warp::reply::with_header(response, CONTENT_TYPE, format.content_type()).into_response()
+        }
+        Err(search_error) => {
+            into_rest_api_response::<(), _>(Err(search_error), BodyFormat::default())
+                .into_response()
+        }
+    }
+}
+
+async fn export_endpoint(
+    index_id_patterns: Vec<String>,
+    export_request: ExportRequestQueryString,
+    search_service: &dyn SearchService,
+) -> Result<stream::BoxStream<'static, Result<Bytes, SearchError>>, SearchError> {
+    if export_request.format == ExportFormat::Csv && export_request.fields.is_none() {
+        return Err(SearchError::InvalidArgument(
+            "the `fields` parameter is required by the CSV format".to_string(),
+        ));
+    }
+    let search_request = export_request_from_query_string(index_id_patterns, &export_request)?;
+    let hit_stream = search_service.root_export(search_request).await?;
+    Ok(encode_hits(
+        hit_stream,
+        export_request.format,
+        export_request.fields,
+    ))
+}
+
+/// Encodes the exported hits, one line per hit.
+fn encode_hits(
+    hit_stream: HitStream,
+    format: ExportFormat,
+    fields_opt: Option<Vec<String>>,
+) -> stream::BoxStream<'static, Result<Bytes, SearchError>> {
+    let header_opt = match (format, &fields_opt) {
+        (ExportFormat::Csv, Some(fields)) => {
+            let mut header = String::new();
+            push_csv_line(&mut header, fields.iter().map(|field| field.as_str()));
+            Some(Ok(Bytes::from(header)))
+        }
+        _ => None,
+    };
+    let lines = hit_stream.map(move |hit_result| {
+        let hit = hit_result.inspect_err(|search_error| {
+            warn!(error=%search_error, "export failed midway");
+        })?;
+        encode_hit(&hit, format, fields_opt.as_deref()).map(Bytes::from)
+    });
+    stream::iter(header_opt).chain(lines).boxed()
+}
+
+fn encode_hit(
+    hit: &Hit,
+    format: ExportFormat,
+    fields_opt: Option<&[String]>,
+) -> Result<String, SearchError> {
+    let Some(fields) = fields_opt else {
+        let mut line = hit.json.clone();
+        line.push('\n');
+        return Ok(line);
+    };
+    let document: JsonMap<String, JsonValue> = serde_json::from_str(&hit.json)?;
+    let values = fields
+        .iter()
+        .map(|field| lookup_field(&document, field).unwrap_or(&JsonValue::Null));
+
+    match format {
+        ExportFormat::Ndjson => {
+            let projected_document: JsonMap<String, JsonValue> =
+                fields.iter().cloned().zip(values.cloned()).collect();
+            let mut line = serde_json::to_string(&projected_document)?;
+            line.push('\n');
+            Ok(line)
+        }
+        ExportFormat::Csv => {
+            let cells: Vec<String> = values.map(csv_cell).collect();
+            let mut line = String::new();
+            push_csv_line(&mut line, cells.iter().map(|cell| cell.as_str()));
+            Ok(line)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use quickwit_search::MockSearchService;
+
+    use super::*;
+
+    fn mock_search_service(hits: Vec<&'static str>) -> MockSearchService {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_export()
+            .returning(move |search_request| {
+                assert_eq!(search_request.index_id_patterns, vec!["logs".to_string()]);
+                let hits: Vec<quickwit_search::Result<Hit>> = hits
+                    .iter()
+                    .map(|json| {
+                        Ok(Hit {
+                            json: json.to_string(),
+                            ..Default::default()
+                        })
+                    })
+                    .collect();
+                Ok(stream::iter(hits).boxed())
+            });
+        mock_search_service
+    }
+
+    #[tokio::test]
+    async fn test_export_ndjson() {
+        let hits = vec![
+            r#"{"service":"api","attributes":{"status":500}}"#,
+            r#"{"service":"web"}"#,
+        ];
+        let export_handler = export_handler(Arc::new(mock_search_service(hits)));
+
+        let response = warp::test::request()
+            .path("/logs/export?query=*")
+            .reply(&export_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "application/x-ndjson"
+        );
+        assert_eq!(
+            response.body(),
+            "{\"service\":\"api\",\"attributes\":{\"status\":500}}\n{\"service\":\"web\"}\n"
+        );
+        let response = warp::test::request()
+            .path("/logs/export?query=*&fields=attributes.status")
+            .reply(&export_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.body(),
+            "{\"attributes.status\":500}\n{\"attributes.status\":null}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_csv() {
+        let hits = vec![
+            r#"{"service":"api","message":"hello, world"}"#,
+            r#"{"service":"web"}"#,
+        ];
+        let export_handler = export_handler(Arc::new(mock_search_service(hits)));
+
+        let response = warp::test::request()
+            .path("/logs/export?query=*&format=csv&fields=service,message")
+            .reply(&export_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            response.body(),
+            "service,message\napi,\"hello, world\"\nweb,\n"
+        );
+        let response = warp::test::request()
+            .path("/logs/export?query=*&format=csv")
+            .reply(&export_handler)
+            .await;
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_encode_hits_interrupts_on_error() {
+        let hit_stream: HitStream = stream::iter(vec![
+            Ok(Hit {
+                json: r#"{"service":"api"}"#.to_string(),
+                ..Default::default()
+            }),
+            Err(SearchError::Internal("leaf search failed".to_string())),
+        ])
+        .boxed();
+        let mut lines = encode_hits(hit_stream, ExportFormat::Ndjson, None);
+        assert_eq!(
+            lines.try_next().await.unwrap().unwrap(),
+            "{\"service\":\"api\"}\n"
+        );
+        lines.try_next().await.unwrap_err();
+    }
+
+    #[test]
+    fn test_export_request_from_query_string() {
+        let export_request: ExportRequestQueryString =
+            serde_qs::from_str("query=service:api&sort_by=-timestamp&start_timestamp=10").unwrap();
+        let search_request =
+            export_request_from_query_string(vec!["logs".to_string()], &export_request).unwrap();
+        assert_eq!(search_request.sort_fields.len(), 1);
+        assert_eq!(search_request.sort_fields[0].field_name, "timestamp");
+        assert_eq!(search_request.start_timestamp, Some(10));
+        assert_eq!(search_request.max_hits, 0);
+    }
+}
@@ -16,6 +16,7 @@ use std::fmt;
 
 use quickwit_config::ConfigFormat;
 use serde::{self, Deserialize, Serialize, Serializer};
+use serde_json::{Map as JsonMap, Value as JsonValue};
 use thiserror::Error;
 use warp::hyper::header::CONTENT_TYPE;
 use warp::{Filter, Rejection};
@@ -111,3 +112,44 @@ pub(crate) fn extract_config_format()
         },
     )
 }
+
+/// Renders a JSON value as a CSV cell: strings are not quoted and nulls are empty.
+pub(crate) fn csv_cell(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(string) => string.clone(),
+        _ => value.to_string(),
+    }
+}
+
+/// Appends a line of CSV cells, quoting the cells that contain separators or quotes.
+pub(crate) fn push_csv_line<'a>(csv: &mut String, cells: impl Iterator<Item = &'a str>) {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            csv.push(',');
+        }
+        if cell.contains([',', '"', '\n', '\r']) {
+            csv.push('"');
+            csv.push_str(&cell.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(cell);
+        }
+    }
+    csv.push('\n');
+}
+
+/// Looks up a field in a document, first as a top-level key, then as a dot-separated path.
+pub(crate) fn lookup_field<'a>(
+    document: &'a JsonMap<String, JsonValue>,
+    path: &str,
+) -> Option<&'a JsonValue> {
+    if let Some(value) = document.get(path) {
+        return Some(value);
+    }
+    let (head, tail) = path.split_once('.')?;
+    match document.get(head)? {
+        JsonValue::Object(sub_document) => lookup_field(sub_document, tail),
+        _ => None,
+    }
+}
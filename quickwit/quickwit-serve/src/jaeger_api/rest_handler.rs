@@ -481,6 +481,8 @@ mod tests {
                     scroll_id: None,
                     failed_splits: Vec::new(),
                     num_successful_splits: 1,
+                    profile: None,
+                    warnings: Vec::new(),
                 })
             });
         let mock_search_service = Arc::new(mock_search_service);
@@ -514,6 +516,8 @@ mod tests {
                     scroll_id: None,
                     failed_splits: Vec::new(),
                     num_successful_splits: 1,
+                    profile: None,
+                    warnings: Vec::new(),
                 })
             });
         let mock_search_service = Arc::new(mock_search_service);
@@ -20,6 +20,7 @@ mod decompression;
 mod delete_task_api;
 mod developer_api;
 mod elasticsearch_api;
+mod export_api;
 mod format;
 mod grpc;
 mod health_check_api;
@@ -37,10 +38,14 @@ mod rate_modulator;
 mod rest;
 mod rest_api_response;
 mod search_api;
+mod search_template_api;
 pub(crate) mod simple_list;
+mod slow_query_log;
+mod sql_api;
 pub mod tcp_listener;
 mod template_api;
 mod ui_handler;
+mod webhook_api;
 
 use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
@@ -107,7 +112,7 @@ use quickwit_proto::search::ReportSplitsRequest;
 use quickwit_proto::types::NodeId;
 use quickwit_search::{
     SearchJobPlacer, SearchService, SearchServiceClient, SearcherContext, SearcherPool,
-    create_search_client_from_channel, start_searcher_service,
+    create_search_client_from_channel, slow_query_log_index_config, start_searcher_service,
 };
 use quickwit_storage::{SplitCache, StorageResolver};
 use tcp_listener::TcpListenerResolver;
@@ -120,6 +125,7 @@ use tracing::{debug, error, info, warn};
 use warp::{Filter, Rejection};
 
 pub use crate::build_info::{BuildInfo, RuntimeInfo};
+pub use crate::export_api::{ExportFormat, ExportRequestQueryString};
 pub use crate::index_api::{ListSplitsQueryParams, ListSplitsResponse};
 pub use crate::ingest_api::{RestIngestResponse, RestParseFailure};
 pub use crate::metrics::SERVE_METRICS;
@@ -536,6 +542,17 @@ pub async fn serve_quickwit(
         .await
         .context("failed to start ingest v1 service")?;
 
+    // Setup ingest service v2.
+    let (ingest_router, ingest_router_service, ingester_opt) = setup_ingest_v2(
+        &node_config,
+        &cluster,
+        &event_broker,
+        control_plane_client.clone(),
+        ingester_pool.clone(),
+    )
+    .await
+    .context("failed to start ingest v2 service")?;
+
     let indexing_service_opt = if node_config.is_service_enabled(QuickwitService::Indexer) {
         let indexing_service = start_indexing_service(
             &universe,
@@ -544,6 +561,7 @@ pub async fn serve_quickwit(
             cluster.clone(),
             metastore_through_control_plane.clone(),
             ingester_pool.clone(),
+            ingest_router_service.clone(),
             storage_resolver.clone(),
             event_broker.clone(),
         )
@@ -562,17 +580,6 @@ pub async fn serve_quickwit(
         indexing_service_opt.clone(),
     );
 
-    // Setup ingest service v2.
-    let (ingest_router, ingest_router_service, ingester_opt) = setup_ingest_v2(
-        &node_config,
-        &cluster,
-        &event_broker,
-        control_plane_client.clone(),
-        ingester_pool,
-    )
-    .await
-    .context("failed to start ingest v2 service")?;
-
     if node_config.is_service_enabled(QuickwitService::Indexer)
         || node_config.is_service_enabled(QuickwitService::ControlPlane)
     {
@@ -630,10 +637,29 @@ pub async fn serve_quickwit(
             None
         };
 
-    let searcher_context = Arc::new(SearcherContext::new(
-        node_config.searcher_config.clone(),
-        split_cache_opt,
-    ));
+    let mut searcher_context =
+        SearcherContext::new(node_config.searcher_config.clone(), split_cache_opt);
+
+    if node_config.is_service_enabled(QuickwitService::Searcher)
+        && searcher_context.slow_query_logger.is_index_enabled()
+    {
+        let slow_query_log_index_config =
+            slow_query_log_index_config(&node_config.default_index_root_uri)
+                .context("failed to load slow query log index config")?;
+        match index_manager
+            .create_index(slow_query_log_index_config, false)
+            .await
+        {
+            Ok(_)
+            | Err(IndexServiceError::Metastore(MetastoreError::AlreadyExists(
+                EntityKind::Index { .. },
+            ))) => {}
+            Err(error) => bail!("failed to create slow query log index: {error}"),
+        };
+        let record_rx = searcher_context.slow_query_logger.create_record_sink();
+        slow_query_log::spawn_slow_query_log_ingester(record_rx, ingest_router_service.clone());
+    }
+    let searcher_context = Arc::new(searcher_context);
 
     let (search_job_placer, search_service) = setup_searcher(
         &node_config,
@@ -26,6 +26,7 @@ use crate::cluster_api::ClusterApi;
 use crate::delete_task_api::DeleteTaskApi;
 use crate::developer_api::DeveloperApi;
 use crate::elasticsearch_api::ElasticCompatibleApi;
+use crate::export_api::ExportApi;
 use crate::health_check_api::HealthCheckApi;
 use crate::index_api::IndexApi;
 use crate::indexing_api::IndexingApi;
@@ -35,7 +36,10 @@ use crate::metrics_api::MetricsApi;
 use crate::node_info_handler::NodeInfoApi;
 use crate::otlp_api::OtlpApi;
 use crate::search_api::SearchApi;
+use crate::search_template_api::SearchTemplateApi;
+use crate::sql_api::SqlApi;
 use crate::template_api::IndexTemplateApi;
+use crate::webhook_api::WebhookApi;
 
 /// Builds the OpenApi docs structure using the registered/merged docs.
 pub fn build_docs() -> utoipa::openapi::OpenApi {
@@ -96,6 +100,10 @@ pub fn build_docs() -> utoipa::openapi::OpenApi {
     docs_base.merge_components_and_paths(MetricsApi::openapi().with_path_prefix("/metrics"));
     docs_base.merge_components_and_paths(NodeInfoApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(SearchApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(SearchTemplateApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(ExportApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(SqlApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(WebhookApi::openapi().with_path_prefix("/api/v1"));
 
     // Schemas
     docs_base.merge_components_and_paths(MetastoreApiSchemas::openapi());
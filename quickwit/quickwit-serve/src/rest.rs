@@ -41,6 +41,7 @@ use crate::decompression::{CorruptedData, UnsupportedEncoding};
 use crate::delete_task_api::delete_task_api_handlers;
 use crate::developer_api::developer_api_routes;
 use crate::elasticsearch_api::elastic_api_handlers;
+use crate::export_api::export_handler;
 use crate::health_check_api::health_check_handlers;
 use crate::index_api::index_management_handlers;
 use crate::indexing_api::indexing_get_handler;
@@ -53,8 +54,11 @@ use crate::rest_api_response::{RestApiError, RestApiResponse};
 use crate::search_api::{
     search_get_handler, search_plan_get_handler, search_plan_post_handler, search_post_handler,
 };
+use crate::search_template_api::search_template_api_handlers;
+use crate::sql_api::sql_api_handlers;
 use crate::template_api::index_template_api_handlers;
 use crate::ui_handler::ui_handler;
+use crate::webhook_api::webhook_api_handlers;
 use crate::{BodyFormat, BuildInfo, QuickwitServices, RuntimeInfo};
 
 #[derive(Debug)]
@@ -317,6 +321,10 @@ fn api_v1_routes(
         .boxed()
         .or(search_routes(quickwit_services.search_service.clone()))
         .boxed()
+        .or(export_handler(quickwit_services.search_service.clone()))
+        .boxed()
+        .or(sql_api_handlers(quickwit_services.search_service.clone()))
+        .boxed()
         .or(ingest_api_handlers(
             quickwit_services.ingest_router_service.clone(),
             quickwit_services.ingest_service.clone(),
@@ -325,6 +333,13 @@ fn api_v1_routes(
             enable_ingest_v2(),
         ))
         .boxed()
+        .or(webhook_api_handlers(
+            quickwit_services.metastore_client.clone(),
+            quickwit_services.ingest_router_service.clone(),
+            quickwit_services.node_config.ingest_api_config.clone(),
+            enable_ingest_v2(),
+        ))
+        .boxed()
         .or(otlp_ingest_api_handlers(
             quickwit_services.otlp_logs_service_opt.clone(),
             quickwit_services.otlp_traces_service_opt.clone(),
@@ -346,6 +361,10 @@ fn api_v1_routes(
         .or(index_template_api_handlers(
             quickwit_services.metastore_client.clone(),
         ))
+        .boxed()
+        .or(search_template_api_handlers(
+            quickwit_services.metastore_client.clone(),
+        ))
         .boxed(),
     )
 }
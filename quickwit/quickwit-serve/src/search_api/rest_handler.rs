@@ -16,6 +16,7 @@ use std::convert::TryFrom;
 use std::sync::Arc;
 
 use percent_encoding::percent_decode_str;
+use quickwit_common::is_false;
 use quickwit_config::validate_index_id_pattern;
 use quickwit_proto::search::{CountHits, SortField, SortOrder};
 use quickwit_query::query_ast::query_ast_from_user_text;
@@ -129,8 +130,11 @@ impl Serialize for SortBy {
     where S: Serializer {
         let mut sort_by_mini_dsl = String::new();
 
-        for sort_field in &self.sort_fields {
-            if sort_field.sort_order() == SortOrder::Desc {
+        for (i, sort_field) in self.sort_fields.iter().enumerate() {
+            if i > 0 {
+                sort_by_mini_dsl.push(',');
+            }
+            if sort_field.sort_order() == SortOrder::Asc {
                 sort_by_mini_dsl.push('-');
             }
             sort_by_mini_dsl.push_str(&sort_field.field_name);
@@ -173,6 +177,26 @@ pub struct SearchRequestQueryString {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(serialize_with = "to_simple_list")]
     pub snippet_fields: Option<Vec<String>>,
+    #[param(value_type = Object)]
+    #[schema(value_type = Object)]
+    /// Runtime fields computed at query time, as a JSON object mapping each field name to its
+    /// definition, e.g. `{"duration_secs": {"script": ".duration_ms / 1000"}}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_fields: Option<JsonValue>,
+    /// Runtime fields whose values are returned for each hit.
+    #[serde(default)]
+    #[serde(deserialize_with = "from_simple_list")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "to_simple_list")]
+    pub docvalue_fields: Option<Vec<String>>,
+    #[param(value_type = Object)]
+    #[schema(value_type = Object)]
+    /// Approximate k-nearest neighbor search on a dense vector field, e.g.
+    /// `{"field": "embedding", "query_vector": [0.1, 0.2], "k": 10}`. The optional `filter` is a
+    /// query text. Hits of the kNN search and of the query are combined with reciprocal rank
+    /// fusion.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub knn: Option<JsonValue>,
     /// If set, restrict search to documents with a `timestamp >= start_timestamp`.
     /// This timestamp is expressed in seconds.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -210,6 +234,10 @@ pub struct SearchRequestQueryString {
     #[schema(value_type = bool)]
     #[serde(default)]
     pub allow_failed_splits: bool,
+    /// If set to true, the response contains a profile of the search execution.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub profile: bool,
 }
 
 mod count_hits_from_bool {
@@ -265,10 +293,28 @@ pub fn search_request_from_api_request(
         search_after: None,
         count_hits: search_request.count_all.into(),
         ignore_missing_indexes: false,
+        profile: search_request.profile,
+        runtime_fields: search_request
+            .runtime_fields
+            .map(|runtime_fields| runtime_fields.to_string()),
+        docvalue_fields: search_request.docvalue_fields.unwrap_or_default(),
+        knn: search_request.knn.map(knn_from_api_request).transpose()?,
     };
     Ok(search_request)
 }
 
+/// Converts the kNN query of the REST API, whose filter is a query text, to the kNN query of the
+/// search request.
+fn knn_from_api_request(mut knn: JsonValue) -> Result<String, SearchError> {
+    if let Some(filter) = knn.get_mut("filter")
+        && let Some(filter_text) = filter.as_str()
+    {
+        let filter_ast = query_ast_from_user_text(filter_text, None);
+        *filter = serde_json::to_value(&filter_ast)?;
+    }
+    Ok(knn.to_string())
+}
+
 async fn search_endpoint(
     index_id_patterns: Vec<String>,
     search_request: SearchRequestQueryString,
@@ -498,9 +544,12 @@ mod tests {
             num_hits: 55,
             hits: Vec::new(),
             snippets: None,
+            fields: None,
             elapsed_time_micros: 0u64,
             errors: Vec::new(),
             aggregations: None,
+            profile: None,
+            warnings: Vec::new(),
         };
         let search_response_json: JsonValue = serde_json::to_value(search_response)?;
         let expected_search_response_json: JsonValue = json!({
@@ -833,6 +882,9 @@ mod tests {
                 "Expected sort fields `{:?}` for query param `{sort_by_query_param}`, got: {:?}",
                 expected_sort_fields, req.sort_by.sort_fields
             );
+            let sort_by_mini_dsl: String =
+                serde_json::from_value(serde_json::to_value(&req.sort_by).unwrap()).unwrap();
+            assert_eq!(SortBy::from(sort_by_mini_dsl), req.sort_by);
         }
 
         let rest_search_api_filter = search_get_filter();
@@ -1001,6 +1053,7 @@ mod tests {
                     partial_hit: None,
                     snippet: Some(r#"{"title": [], "body": ["foo <em>bar</em> baz"]}"#.to_string()),
                     index_id: "quickwit-demo-index".to_string(),
+                    fields_json: None,
                 }],
                 num_hits: 1,
                 elapsed_time_micros: 16,
@@ -1030,6 +1083,97 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rest_search_api_route_with_runtime_fields() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::search::SearchRequest| {
+                    let runtime_fields: JsonValue =
+                        serde_json::from_str(search_request.runtime_fields.as_deref().unwrap())
+                            .unwrap();
+                    runtime_fields == json!({"title_len": {"script": "strlen!(.title)"}})
+                        && search_request.docvalue_fields == ["title_len"]
+                },
+            ))
+            .returning(|_| {
+                Ok(quickwit_proto::search::SearchResponse {
+                    hits: vec![quickwit_proto::search::Hit {
+                        json: r#"{"title": "foo"}"#.to_string(),
+                        partial_hit: None,
+                        snippet: None,
+                        index_id: "quickwit-demo-index".to_string(),
+                        fields_json: Some(r#"{"title_len": [3]}"#.to_string()),
+                    }],
+                    num_hits: 1,
+                    elapsed_time_micros: 16,
+                    warnings: vec!["runtime fields are expensive".to_string()],
+                    ..Default::default()
+                })
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&json!({
+                "query": "*",
+                "runtime_fields": {"title_len": {"script": "strlen!(.title)"}},
+                "docvalue_fields": "title_len",
+            }))
+            .reply(&rest_search_api_handler)
+            .await;
+
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "num_hits": 1,
+            "hits": [{"title": "foo"}],
+            "fields": [{"title_len": [3]}],
+            "elapsed_time_micros": 16,
+            "errors": [],
+            "warnings": ["runtime fields are expensive"],
+        });
+        assert_json_eq!(resp_json, expected_response_json);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_route_with_knn() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::search::SearchRequest| {
+                    let knn: JsonValue =
+                        serde_json::from_str(search_request.knn.as_deref().unwrap()).unwrap();
+                    knn["field"] == "embedding"
+                        && knn["query_vector"] == json!([0.5, 1.0])
+                        && knn["k"] == 3
+                        && knn["filter"]["type"] == "user_input"
+                        && knn["filter"]["user_text"] == "title:foo"
+                },
+            ))
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&json!({
+                "query": "*",
+                "knn": {
+                    "field": "embedding",
+                    "query_vector": [0.5, 1.0],
+                    "k": 3,
+                    "filter": "title:foo",
+                },
+            }))
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_rest_search_api_multi_indexes() {
         {
@@ -0,0 +1,453 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::type_name;
+
+use bytes::Bytes;
+use quickwit_config::{ConfigFormat, SearchTemplate, SearchTemplateId, VersionedSearchTemplate};
+use quickwit_proto::metastore::{
+    CreateSearchTemplateRequest, DeleteSearchTemplatesRequest, GetSearchTemplateRequest,
+    ListSearchTemplatesRequest, MetastoreError, MetastoreResult, MetastoreService,
+    MetastoreServiceClient, serde_utils,
+};
+use serde_json::Value as JsonValue;
+use warp::reject::Rejection;
+use warp::{Filter, Reply};
+
+use crate::format::{extract_config_format, extract_format_from_qs};
+use crate::rest::recover_fn;
+use crate::rest_api_response::into_rest_api_response;
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(
+        create_search_template,
+        get_search_template,
+        update_search_template,
+        delete_search_template,
+        list_search_templates,
+    ),
+    components(schemas(VersionedSearchTemplate))
+)]
+pub(crate) struct SearchTemplateApi;
+
+pub(crate) fn search_template_api_handlers(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    create_search_template_handler(metastore.clone())
+        .or(get_search_template_handler(metastore.clone()))
+        .or(update_search_template_handler(metastore.clone()))
+        .or(delete_search_template_handler(metastore.clone()))
+        .or(list_search_templates_handler(metastore.clone()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+async fn store_search_template(
+    search_template: SearchTemplate,
+    overwrite: bool,
+    metastore: MetastoreServiceClient,
+) -> MetastoreResult<SearchTemplate> {
+    search_template.validate().map_err(|error| {
+        let message = format!("invalid search template: {error:#}");
+        MetastoreError::InvalidArgument { message }
+    })?;
+    let search_template_json = serde_utils::to_json_str(&search_template)?;
+    let create_search_template_request = CreateSearchTemplateRequest {
+        search_template_json,
+        overwrite,
+    };
+    metastore
+        .create_search_template(create_search_template_request)
+        .await?;
+    Ok(search_template)
+}
+
+fn create_search_template_handler(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("search-templates")
+        .and(warp::post())
+        .and(warp::filters::body::bytes())
+        .and(extract_config_format())
+        .and(with_arg(metastore))
+        .then(create_search_template)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Templates",
+    path = "/search-templates",
+    request_body = VersionedSearchTemplate,
+    responses(
+        (status = 200, description = "The search template was successfully created.", body = VersionedSearchTemplate)
+    ),
+)]
+/// Creates a new search template.
+async fn create_search_template(
+    body: Bytes,
+    config_format: ConfigFormat,
+    metastore: MetastoreServiceClient,
+) -> MetastoreResult<SearchTemplate> {
+    let search_template: SearchTemplate =
+        config_format
+            .parse(&body)
+            .map_err(|error| MetastoreError::JsonDeserializeError {
+                struct_name: type_name::<SearchTemplate>().to_string(),
+                message: error.to_string(),
+            })?;
+    store_search_template(search_template, false, metastore).await
+}
+
+fn get_search_template_handler(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("search-templates" / String)
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(get_search_template)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Templates",
+    path = "/search-templates/{template_id}",
+    responses(
+        (status = 200, description = "The search template was successfully retrieved.", body = VersionedSearchTemplate),
+        (status = 404, description = "The search template was not found.")
+    ),
+)]
+/// Retrieves the search template identified by `template_id`.
+async fn get_search_template(
+    template_id: SearchTemplateId,
+    metastore: MetastoreServiceClient,
+) -> MetastoreResult<SearchTemplate> {
+    let get_search_template_request = GetSearchTemplateRequest { template_id };
+    let get_search_template_response = metastore
+        .get_search_template(get_search_template_request)
+        .await?;
+    let search_template: SearchTemplate =
+        serde_utils::from_json_str(&get_search_template_response.search_template_json)?;
+    Ok(search_template)
+}
+
+fn update_search_template_handler(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("search-templates" / String)
+        .and(warp::put())
+        .and(warp::filters::body::bytes())
+        .and(extract_config_format())
+        .and(with_arg(metastore))
+        .then(update_search_template)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+}
+
+#[utoipa::path(
+    put,
+    tag = "Templates",
+    path = "/search-templates/{template_id}",
+    request_body = VersionedSearchTemplate,
+    responses(
+        (status = 200, description = "The search template was successfully updated.", body = VersionedSearchTemplate),
+    ),
+)]
+/// Creates or updates the search template identified by `template_id`.
+async fn update_search_template(
+    template_id: SearchTemplateId,
+    body: Bytes,
+    config_format: ConfigFormat,
+    metastore: MetastoreServiceClient,
+) -> MetastoreResult<SearchTemplate> {
+    let mut json_value: JsonValue =
+        config_format
+            .parse(&body)
+            .map_err(|error| MetastoreError::JsonDeserializeError {
+                struct_name: type_name::<SearchTemplate>().to_string(),
+                message: error.to_string(),
+            })?;
+    json_value["template_id"] = JsonValue::String(template_id);
+
+    if let Some(JsonValue::Number(number)) = json_value.get("version") {
+        json_value["version"] = JsonValue::String(number.to_string());
+    }
+    let search_template: SearchTemplate = serde_utils::from_json_value(json_value)?;
+    store_search_template(search_template, true, metastore).await
+}
+
+fn delete_search_template_handler(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("search-templates" / String)
+        .and(warp::delete())
+        .and(with_arg(metastore))
+        .then(delete_search_template)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Templates",
+    path = "/search-templates/{template_id}",
+    responses(
+        (status = 200, description = "The search template was successfully deleted."),
+    ),
+)]
+/// Deletes the search template identified by the provided `template_id`.
+async fn delete_search_template(
+    template_id: SearchTemplateId,
+    metastore: MetastoreServiceClient,
+) -> MetastoreResult<()> {
+    let template_ids = vec![template_id];
+    let delete_search_templates_request = DeleteSearchTemplatesRequest { template_ids };
+    metastore
+        .delete_search_templates(delete_search_templates_request)
+        .await?;
+    Ok(())
+}
+
+fn list_search_templates_handler(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("search-templates")
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(list_search_templates)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Templates",
+    path = "/search-templates",
+    responses(
+        (status = 200, description = "The search templates were successfully retrieved.", body = [VersionedSearchTemplate]),
+    ),
+)]
+/// Retrieves all the search templates stored in the metastore.
+async fn list_search_templates(
+    metastore: MetastoreServiceClient,
+) -> MetastoreResult<Vec<SearchTemplate>> {
+    let list_search_templates_request = ListSearchTemplatesRequest {};
+    let list_search_templates_response = metastore
+        .list_search_templates(list_search_templates_request)
+        .await?;
+    let search_templates: Vec<SearchTemplate> = list_search_templates_response
+        .search_templates_json
+        .into_iter()
+        .map(|search_template_json| {
+            serde_utils::from_json_str::<SearchTemplate>(&search_template_json)
+        })
+        .collect::<MetastoreResult<_>>()?;
+    Ok(search_templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::metastore::{
+        EmptyResponse, EntityKind, GetSearchTemplateResponse, ListSearchTemplatesResponse,
+        MockMetastoreService,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_search_template() {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_create_search_template()
+            .return_once(|request| {
+                assert!(!request.overwrite);
+
+                let search_template: SearchTemplate =
+                    serde_json::from_str(&request.search_template_json).unwrap();
+
+                assert_eq!(search_template.template_id, "test-template-foo");
+                assert_eq!(
+                    search_template.source,
+                    r#"{"query":{"match":{"{{field}}":"{{value}}"}}}"#
+                );
+                Ok(EmptyResponse {})
+            });
+        let metastore = MetastoreServiceClient::from_mock(mock_metastore);
+        let create_search_template_handler = create_search_template_handler(metastore);
+        let response = warp::test::request()
+            .path("/search-templates")
+            .method("POST")
+            .json(&json!({
+                "version": "0.9",
+                "template_id": "test-template-foo",
+                "source": {"query": {"match": {"{{field}}": "{{value}}"}}},
+            }))
+            .reply(&create_search_template_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_create_invalid_search_template() {
+        let mock_metastore = MockMetastoreService::new();
+        let metastore = MetastoreServiceClient::from_mock(mock_metastore);
+        let create_search_template_handler = create_search_template_handler(metastore);
+        let response = warp::test::request()
+            .path("/search-templates")
+            .method("POST")
+            .json(&json!({
+                "version": "0.9",
+                "template_id": "test-template-foo",
+                "source": r#"{"query": {{#query}}}"#,
+            }))
+            .reply(&create_search_template_handler)
+            .await;
+        assert_eq!(response.status(), 400);
+
+        let body = std::str::from_utf8(response.body()).unwrap();
+        assert!(body.contains("unclosed section `{{#query}}`"));
+    }
+
+    #[tokio::test]
+    async fn test_get_search_template() {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_get_search_template()
+            .withf(|request| request.template_id == "test-template-foo")
+            .return_once(|request| {
+                let error = MetastoreError::NotFound(EntityKind::SearchTemplate {
+                    template_id: request.template_id,
+                });
+                Err(error)
+            });
+        mock_metastore
+            .expect_get_search_template()
+            .withf(|request| request.template_id == "test-template-bar")
+            .return_once(|_request| {
+                let search_template = SearchTemplate::for_test("test-template-bar");
+                let search_template_json = serde_utils::to_json_str(&search_template).unwrap();
+                let response = GetSearchTemplateResponse {
+                    search_template_json,
+                };
+                Ok(response)
+            });
+        let metastore = MetastoreServiceClient::from_mock(mock_metastore);
+        let get_search_template_handler = get_search_template_handler(metastore);
+
+        let response = warp::test::request()
+            .path("/search-templates/test-template-foo")
+            .reply(&get_search_template_handler)
+            .await;
+        assert_eq!(response.status(), 404);
+
+        let response = warp::test::request()
+            .path("/search-templates/test-template-bar")
+            .reply(&get_search_template_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+
+        let search_template: SearchTemplate = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            search_template,
+            SearchTemplate::for_test("test-template-bar")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_search_template() {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_create_search_template()
+            .return_once(|request| {
+                assert!(request.overwrite);
+
+                let search_template: SearchTemplate =
+                    serde_json::from_str(&request.search_template_json).unwrap();
+                assert_eq!(search_template.template_id, "test-template-foo");
+                Ok(EmptyResponse {})
+            });
+        let metastore = MetastoreServiceClient::from_mock(mock_metastore);
+        let update_search_template_handler = update_search_template_handler(metastore);
+        let response = warp::test::request()
+            .path("/search-templates/test-template-foo")
+            .method("PUT")
+            .json(&json!({
+                "version": "0.9",
+                "template_id": "test-template-bar", // This `template_id` should be ignored and overridden by the path parameter.
+                "source": "{\"size\": {{size}}}",
+            }))
+            .reply(&update_search_template_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_delete_search_template() {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_delete_search_templates()
+            .return_once(|request| {
+                assert_eq!(request.template_ids, ["test-template-foo"]);
+                Ok(EmptyResponse {})
+            });
+        let metastore = MetastoreServiceClient::from_mock(mock_metastore);
+        let delete_search_template_handler = delete_search_template_handler(metastore);
+        let response = warp::test::request()
+            .path("/search-templates/test-template-foo")
+            .method("DELETE")
+            .reply(&delete_search_template_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_list_search_templates() {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_list_search_templates()
+            .return_once(|_request| {
+                let search_templates_json = ["test-template-bar", "test-template-foo"]
+                    .into_iter()
+                    .map(|template_id| {
+                        let search_template = SearchTemplate::for_test(template_id);
+                        serde_json::to_string(&search_template).unwrap()
+                    })
+                    .collect();
+                let response = ListSearchTemplatesResponse {
+                    search_templates_json,
+                };
+                Ok(response)
+            });
+        let metastore = MetastoreServiceClient::from_mock(mock_metastore);
+        let list_search_templates_handler = list_search_templates_handler(metastore);
+        let response = warp::test::request()
+            .path("/search-templates")
+            .method("GET")
+            .reply(&list_search_templates_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+
+        let search_templates: Vec<SearchTemplate> =
+            serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(search_templates.len(), 2);
+        assert_eq!(search_templates[0].template_id, "test-template-bar");
+        assert_eq!(search_templates[1].template_id, "test-template-foo");
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use quickwit_common::spawn_named_task;
+use quickwit_config::INGEST_V2_SOURCE_ID;
+use quickwit_ingest::{CommitType, JsonDocBatchV2Builder};
+use quickwit_proto::ingest::router::{
+    IngestRequestV2, IngestRouterService, IngestRouterServiceClient, IngestSubrequest,
+};
+use quickwit_proto::types::DocUidGenerator;
+use quickwit_search::{SLOW_QUERY_LOG_INDEX_ID, SlowQueryLogRecord};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Maximum number of records ingested in a single request.
+const MAX_NUM_RECORDS_PER_BATCH: usize = 100;
+
+/// Delay between two ingest requests, so that records get batched together.
+const INGEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Spawns a task ingesting the slow query log records into the `quickwit-slowlog` index.
+pub(crate) fn spawn_slow_query_log_ingester(
+    record_rx: mpsc::Receiver<SlowQueryLogRecord>,
+    ingest_router: IngestRouterServiceClient,
+) {
+    spawn_named_task(
+        ingest_slow_query_log_records(record_rx, ingest_router),
+        "slow_query_log_ingester",
+    );
+}
+
+async fn ingest_slow_query_log_records(
+    mut record_rx: mpsc::Receiver<SlowQueryLogRecord>,
+    ingest_router: IngestRouterServiceClient,
+) {
+    let mut records = Vec::with_capacity(MAX_NUM_RECORDS_PER_BATCH);
+
+    while record_rx
+        .recv_many(&mut records, MAX_NUM_RECORDS_PER_BATCH)
+        .await
+        > 0
+    {
+        let mut doc_batch_builder = JsonDocBatchV2Builder::with_num_docs(records.len());
+        let mut doc_uid_generator = DocUidGenerator::default();
+
+        for record in records.drain(..) {
+            if let Err(error) = doc_batch_builder.add_doc(doc_uid_generator.next_doc_uid(), record)
+            {
+                warn!(%error, "failed to serialize slow query log record");
+            }
+        }
+        let subrequest = IngestSubrequest {
+            subrequest_id: 0,
+            index_id: SLOW_QUERY_LOG_INDEX_ID.to_string(),
+            source_id: INGEST_V2_SOURCE_ID.to_string(),
+            doc_batch: Some(doc_batch_builder.build()),
+        };
+        let ingest_request = IngestRequestV2 {
+            commit_type: CommitType::Auto.into(),
+            subrequests: vec![subrequest],
+        };
+        match ingest_router.ingest(ingest_request).await {
+            Ok(ingest_response) => {
+                if let Some(failure) = ingest_response.failures.first() {
+                    warn!(reason=?failure.reason(), "failed to ingest slow query log records");
+                }
+            }
+            Err(error) => {
+                warn!(%error, "failed to ingest slow query log records");
+            }
+        }
+        tokio::time::sleep(INGEST_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::ingest::router::{
+        IngestResponseV2, IngestSuccess, MockIngestRouterService,
+    };
+
+    use super::*;
+
+    fn slow_query_log_record(elapsed_micros: u64) -> SlowQueryLogRecord {
+        SlowQueryLogRecord {
+            timestamp_micros: 1_700_000_000_000_000,
+            index_id_patterns: vec!["my-index".to_string()],
+            query_ast: r#"{"type":"match_all"}"#.to_string(),
+            aggregation_request: None,
+            elapsed_micros,
+            fetch_docs_micros: 0,
+            num_splits: 1,
+            num_docs: 10,
+            num_hits: 10,
+            num_failed_splits: 0,
+            warmup_num_bytes: 0,
+            warmup_micros: 0,
+            cpu_micros: 0,
+            slowest_splits: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingest_slow_query_log_records() {
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .returning(|ingest_request| {
+                assert_eq!(ingest_request.subrequests.len(), 1);
+                let subrequest = &ingest_request.subrequests[0];
+                assert_eq!(subrequest.index_id, SLOW_QUERY_LOG_INDEX_ID);
+                assert_eq!(subrequest.doc_batch.as_ref().unwrap().num_docs(), 2);
+                Ok(IngestResponseV2 {
+                    successes: vec![IngestSuccess::default()],
+                    failures: Vec::new(),
+                })
+            });
+        let ingest_router = IngestRouterServiceClient::from_mock(mock_ingest_router);
+        let (record_tx, record_rx) = mpsc::channel(10);
+        record_tx.send(slow_query_log_record(1_000)).await.unwrap();
+        record_tx.send(slow_query_log_record(2_000)).await.unwrap();
+        drop(record_tx);
+
+        ingest_slow_query_log_records(record_rx, ingest_router).await;
+    }
+}
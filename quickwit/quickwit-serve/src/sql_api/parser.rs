@@ -0,0 +1,985 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parser for the restricted SQL dialect supported by the `_sql` endpoint.
+//!
+//! Grammar:
+//!
+//! ```text
+//! Query      := SELECT SelectItem [, SelectItem]* FROM Index [, Index]*
+//!               [WHERE Expr] [GROUP BY Expr [, Expr]*]
+//!               [ORDER BY Expr [ASC | DESC] [, Expr [ASC | DESC]]*]
+//!               [LIMIT Number] [OFFSET Number]
+//! SelectItem := * | Expr [[AS] Identifier]
+//! Expr       := Conjunction [OR Conjunction]*
+//! Conjunction:= Negation [AND Negation]*
+//! Negation   := NOT Negation | Predicate
+//! Predicate  := Operand [ComparisonOp Operand
+//!                       | [NOT] IN ( Literal [, Literal]* )
+//!                       | [NOT] BETWEEN Literal AND Literal
+//!                       | [NOT] LIKE String
+//!                       | IS [NOT] NULL]
+//! Operand    := Literal | Identifier | Identifier ( [DISTINCT] Args ) | ( Expr )
+//! ```
+
+use std::fmt;
+
+use anyhow::{Context, bail};
+
+/// Maximum nesting depth of parenthesized expressions.
+const MAX_EXPR_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    QuotedIdentifier(String),
+    String(String),
+    Number(String),
+    Star,
+    Comma,
+    LeftParen,
+    RightParen,
+    Operator(ComparisonOperator),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Identifier(identifier) => write!(formatter, "{identifier}"),
+            Token::QuotedIdentifier(identifier) => write!(formatter, "\"{identifier}\""),
+            Token::String(string) => write!(formatter, "'{string}'"),
+            Token::Number(number) => write!(formatter, "{number}"),
+            Token::Star => write!(formatter, "*"),
+            Token::Comma => write!(formatter, ","),
+            Token::LeftParen => write!(formatter, "("),
+            Token::RightParen => write!(formatter, ")"),
+            Token::Operator(operator) => write!(formatter, "{operator}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ComparisonOperator {
+    Eq,
+    NotEq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+impl ComparisonOperator {
+    /// Returns the operator to use when swapping the operands, e.g. `1 < x` => `x > 1`.
+    pub fn flip(self) -> Self {
+        match self {
+            ComparisonOperator::Eq => ComparisonOperator::Eq,
+            ComparisonOperator::NotEq => ComparisonOperator::NotEq,
+            ComparisonOperator::Lt => ComparisonOperator::Gt,
+            ComparisonOperator::Lte => ComparisonOperator::Gte,
+            ComparisonOperator::Gt => ComparisonOperator::Lt,
+            ComparisonOperator::Gte => ComparisonOperator::Lte,
+        }
+    }
+}
+
+impl fmt::Display for ComparisonOperator {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let operator_str = match self {
+            ComparisonOperator::Eq => "=",
+            ComparisonOperator::NotEq => "!=",
+            ComparisonOperator::Lt => "<",
+            ComparisonOperator::Lte => "<=",
+            ComparisonOperator::Gt => ">",
+            ComparisonOperator::Gte => ">=",
+        };
+        formatter.write_str(operator_str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Literal {
+    String(String),
+    Number(String),
+    Boolean(bool),
+    Null,
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Literal::String(string) => write!(formatter, "'{}'", string.replace('\'', "''")),
+            Literal::Number(number) => write!(formatter, "{number}"),
+            Literal::Boolean(boolean) => {
+                write!(formatter, "{}", boolean.to_string().to_uppercase())
+            }
+            Literal::Null => write!(formatter, "NULL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+    Column(String),
+    Literal(Literal),
+    /// The `*` in `COUNT(*)`.
+    Wildcard,
+    Function {
+        /// Upper-cased function name.
+        name: String,
+        args: Vec<Expr>,
+        distinct: bool,
+    },
+    Comparison {
+        left: Box<Expr>,
+        operator: ComparisonOperator,
+        right: Box<Expr>,
+    },
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    InList {
+        expr: Box<Expr>,
+        list: Vec<Literal>,
+        negated: bool,
+    },
+    Between {
+        expr: Box<Expr>,
+        low: Literal,
+        high: Literal,
+        negated: bool,
+    },
+    Like {
+        expr: Box<Expr>,
+        pattern: String,
+        negated: bool,
+    },
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let not_str = |negated: bool| if negated { "NOT " } else { "" };
+
+        match self {
+            Expr::Column(column) => write!(formatter, "{column}"),
+            Expr::Literal(literal) => write!(formatter, "{literal}"),
+            Expr::Wildcard => write!(formatter, "*"),
+            Expr::Function {
+                name,
+                args,
+                distinct,
+            } => {
+                write!(formatter, "{name}(")?;
+                if *distinct {
+                    write!(formatter, "DISTINCT ")?;
+                }
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(formatter, ", ")?;
+                    }
+                    write!(formatter, "{arg}")?;
+                }
+                write!(formatter, ")")
+            }
+            Expr::Comparison {
+                left,
+                operator,
+                right,
+            } => write!(formatter, "{left} {operator} {right}"),
+            Expr::And(exprs) => write_joined(formatter, exprs, " AND "),
+            Expr::Or(exprs) => write_joined(formatter, exprs, " OR "),
+            Expr::Not(expr) => write!(formatter, "NOT ({expr})"),
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                write!(formatter, "{expr} {}IN (", not_str(*negated))?;
+                for (i, literal) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(formatter, ", ")?;
+                    }
+                    write!(formatter, "{literal}")?;
+                }
+                write!(formatter, ")")
+            }
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => write!(
+                formatter,
+                "{expr} {}BETWEEN {low} AND {high}",
+                not_str(*negated)
+            ),
+            Expr::Like {
+                expr,
+                pattern,
+                negated,
+            } => write!(
+                formatter,
+                "{expr} {}LIKE {}",
+                not_str(*negated),
+                Literal::String(pattern.clone())
+            ),
+            Expr::IsNull { expr, negated } => {
+                write!(formatter, "{expr} IS {}NULL", not_str(*negated))
+            }
+        }
+    }
+}
+
+fn write_joined(formatter: &mut fmt::Formatter, exprs: &[Expr], separator: &str) -> fmt::Result {
+    write!(formatter, "(")?;
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            formatter.write_str(separator)?;
+        }
+        write!(formatter, "{expr}")?;
+    }
+    write!(formatter, ")")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SelectItem {
+    Wildcard,
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OrderByItem {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+/// A parsed SQL `SELECT` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SqlQuery {
+    pub select_items: Vec<SelectItem>,
+    pub index_id_patterns: Vec<String>,
+    pub where_clause: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub order_by: Vec<OrderByItem>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+/// Parses a SQL query.
+pub(crate) fn parse_sql(sql: &str) -> anyhow::Result<SqlQuery> {
+    let tokens = tokenize(sql)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        depth: 0,
+    };
+    let sql_query = parser.parse_query()?;
+
+    if let Some(token) = parser.peek() {
+        bail!("unexpected token `{token}` after the end of the query");
+    }
+    Ok(sql_query)
+}
+
+fn is_identifier_start(ch: char) -> bool {
+    ch.is_ascii_alphabetic() || ch == '_'
+}
+
+fn is_identifier_char(ch: char) -> bool {
+    // `*` is accepted within identifiers to support index ID patterns such as `logs-*`.
+    ch.is_ascii_alphanumeric() || ['_', '-', '.', '@', '*'].contains(&ch)
+}
+
+fn tokenize(sql: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+
+    while let Some((start, ch)) = chars.next() {
+        let token = match ch {
+            ch if ch.is_whitespace() => continue,
+            '*' => Token::Star,
+            ',' => Token::Comma,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ';' => {
+                if chars.any(|(_, ch)| !ch.is_whitespace()) {
+                    bail!("only a single SQL statement is supported");
+                }
+                break;
+            }
+            '=' => Token::Operator(ComparisonOperator::Eq),
+            '!' => {
+                if chars.next_if(|(_, ch)| *ch == '=').is_none() {
+                    bail!("unexpected character `!` at position {start}");
+                }
+                Token::Operator(ComparisonOperator::NotEq)
+            }
+            '<' => {
+                if chars.next_if(|(_, ch)| *ch == '=').is_some() {
+                    Token::Operator(ComparisonOperator::Lte)
+                } else if chars.next_if(|(_, ch)| *ch == '>').is_some() {
+                    Token::Operator(ComparisonOperator::NotEq)
+                } else {
+                    Token::Operator(ComparisonOperator::Lt)
+                }
+            }
+            '>' => {
+                if chars.next_if(|(_, ch)| *ch == '=').is_some() {
+                    Token::Operator(ComparisonOperator::Gte)
+                } else {
+                    Token::Operator(ComparisonOperator::Gt)
+                }
+            }
+            '\'' | '"' | '`' => {
+                let quote = ch;
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, ch)) if ch == quote => {
+                            // A doubled quote is an escaped quote.
+                            if chars.next_if(|(_, ch)| *ch == quote).is_some() {
+                                value.push(quote);
+                            } else {
+                                break;
+                            }
+                        }
+                        Some((_, ch)) => value.push(ch),
+                        None => bail!("unterminated quoted string starting at position {start}"),
+                    }
+                }
+                if quote == '\'' {
+                    Token::String(value)
+                } else {
+                    Token::QuotedIdentifier(value)
+                }
+            }
+            ch if ch.is_ascii_digit() || ch == '-' || ch == '.' => {
+                let mut number = ch.to_string();
+                while let Some((_, ch)) = chars.next_if(|(_, ch)| {
+                    ch.is_ascii_alphanumeric() || *ch == '.' || *ch == '-' || *ch == '+'
+                }) {
+                    number.push(ch);
+                }
+                if number.parse::<f64>().is_err() {
+                    bail!("invalid number `{number}` at position {start}");
+                }
+                Token::Number(number)
+            }
+            ch if is_identifier_start(ch) => {
+                let mut identifier = ch.to_string();
+                while let Some((_, ch)) = chars.next_if(|(_, ch)| is_identifier_char(*ch)) {
+                    identifier.push(ch);
+                }
+                Token::Identifier(identifier)
+            }
+            _ => bail!("unexpected character `{ch}` at position {start}"),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+const RESERVED_KEYWORDS: &[&str] = &[
+    "AND", "AS", "ASC", "BETWEEN", "BY", "DESC", "DISTINCT", "FROM", "GROUP", "IN", "IS", "LIKE",
+    "LIMIT", "NOT", "NULL", "OFFSET", "OR", "ORDER", "SELECT", "WHERE",
+];
+
+fn is_reserved_keyword(identifier: &str) -> bool {
+    RESERVED_KEYWORDS
+        .iter()
+        .any(|keyword| identifier.eq_ignore_ascii_case(keyword))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token_opt = self.tokens.get(self.position).cloned();
+        if token_opt.is_some() {
+            self.position += 1;
+        }
+        token_opt
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Identifier(identifier)) => identifier.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the next token is an identifier that can be used as an implicit alias.
+    fn peek_implicit_alias(&self) -> bool {
+        match self.peek() {
+            Some(Token::QuotedIdentifier(_)) => true,
+            Some(Token::Identifier(identifier)) => !is_reserved_keyword(identifier),
+            _ => false,
+        }
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> anyhow::Result<()> {
+        if self.consume_keyword(keyword) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(token) => bail!("expected `{keyword}`, found `{token}`"),
+            None => bail!("expected `{keyword}`, found end of query"),
+        }
+    }
+
+    fn consume_token(&mut self, expected_token: &Token) -> bool {
+        if self.peek() == Some(expected_token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_token(&mut self, expected_token: &Token) -> anyhow::Result<()> {
+        if self.consume_token(expected_token) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(token) => bail!("expected `{expected_token}`, found `{token}`"),
+            None => bail!("expected `{expected_token}`, found end of query"),
+        }
+    }
+
+    fn parse_identifier(&mut self) -> anyhow::Result<String> {
+        match self.next_token() {
+            Some(Token::Identifier(identifier)) => {
+                if is_reserved_keyword(&identifier) {
+                    bail!("expected an identifier, found keyword `{identifier}`");
+                }
+                Ok(identifier)
+            }
+            Some(Token::QuotedIdentifier(identifier)) => Ok(identifier),
+            Some(token) => bail!("expected an identifier, found `{token}`"),
+            None => bail!("expected an identifier, found end of query"),
+        }
+    }
+
+    fn parse_u64(&mut self, clause: &str) -> anyhow::Result<u64> {
+        match self.next_token() {
+            Some(Token::Number(number)) => number.parse::<u64>().with_context(|| {
+                format!("{clause} must be a non-negative integer, got `{number}`")
+            }),
+            Some(token) => bail!("{clause} must be a non-negative integer, got `{token}`"),
+            None => bail!("{clause} must be a non-negative integer, found end of query"),
+        }
+    }
+
+    fn parse_query(&mut self) -> anyhow::Result<SqlQuery> {
+        self.expect_keyword("SELECT")?;
+
+        let mut select_items = vec![self.parse_select_item()?];
+        while self.consume_token(&Token::Comma) {
+            select_items.push(self.parse_select_item()?);
+        }
+        self.expect_keyword("FROM")?;
+
+        let mut index_id_patterns = vec![self.parse_index_id_pattern()?];
+        while self.consume_token(&Token::Comma) {
+            index_id_patterns.push(self.parse_index_id_pattern()?);
+        }
+        let where_clause = if self.consume_keyword("WHERE") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        let mut group_by = Vec::new();
+
+        if self.consume_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by.push(self.parse_expr()?);
+
+            while self.consume_token(&Token::Comma) {
+                group_by.push(self.parse_expr()?);
+            }
+        }
+        if self.peek_keyword("HAVING") {
+            bail!("`HAVING` is not supported");
+        }
+        let mut order_by = Vec::new();
+
+        if self.consume_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            order_by.push(self.parse_order_by_item()?);
+
+            while self.consume_token(&Token::Comma) {
+                order_by.push(self.parse_order_by_item()?);
+            }
+        }
+        let limit = if self.consume_keyword("LIMIT") {
+            Some(self.parse_u64("LIMIT")?)
+        } else {
+            None
+        };
+        let offset = if self.consume_keyword("OFFSET") {
+            Some(self.parse_u64("OFFSET")?)
+        } else {
+            None
+        };
+        Ok(SqlQuery {
+            select_items,
+            index_id_patterns,
+            where_clause,
+            group_by,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn parse_select_item(&mut self) -> anyhow::Result<SelectItem> {
+        if self.consume_token(&Token::Star) {
+            return Ok(SelectItem::Wildcard);
+        }
+        let expr = self.parse_expr()?;
+        let alias = if self.consume_keyword("AS") {
+            Some(self.parse_identifier()?)
+        } else if self.peek_implicit_alias() {
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+        Ok(SelectItem::Expr { expr, alias })
+    }
+
+    fn parse_index_id_pattern(&mut self) -> anyhow::Result<String> {
+        match self.next_token() {
+            Some(Token::Identifier(index_id_pattern))
+            | Some(Token::QuotedIdentifier(index_id_pattern))
+            | Some(Token::String(index_id_pattern)) => Ok(index_id_pattern),
+            Some(Token::Star) => bail!("index ID patterns cannot start with `*`, quote them"),
+            Some(token) => bail!("expected an index ID, found `{token}`"),
+            None => bail!("expected an index ID, found end of query"),
+        }
+    }
+
+    fn parse_order_by_item(&mut self) -> anyhow::Result<OrderByItem> {
+        let expr = self.parse_expr()?;
+        let descending = if self.consume_keyword("DESC") {
+            true
+        } else {
+            self.consume_keyword("ASC");
+            false
+        };
+        Ok(OrderByItem { expr, descending })
+    }
+
+    fn parse_expr(&mut self) -> anyhow::Result<Expr> {
+        self.depth += 1;
+
+        if self.depth > MAX_EXPR_DEPTH {
+            bail!("expression is too deeply nested");
+        }
+        let mut exprs = vec![self.parse_conjunction()?];
+
+        while self.consume_keyword("OR") {
+            exprs.push(self.parse_conjunction()?);
+        }
+        self.depth -= 1;
+
+        if exprs.len() == 1 {
+            return Ok(exprs.pop().unwrap());
+        }
+        Ok(Expr::Or(exprs))
+    }
+
+    fn parse_conjunction(&mut self) -> anyhow::Result<Expr> {
+        let mut exprs = vec![self.parse_negation()?];
+
+        while self.consume_keyword("AND") {
+            exprs.push(self.parse_negation()?);
+        }
+        if exprs.len() == 1 {
+            return Ok(exprs.pop().unwrap());
+        }
+        Ok(Expr::And(exprs))
+    }
+
+    fn parse_negation(&mut self) -> anyhow::Result<Expr> {
+        if self.consume_keyword("NOT") {
+            let expr = self.parse_negation()?;
+            return Ok(Expr::Not(Box::new(expr)));
+        }
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> anyhow::Result<Expr> {
+        let operand = self.parse_operand()?;
+
+        if let Some(Token::Operator(operator)) = self.peek() {
+            let operator = *operator;
+            self.position += 1;
+            let right = self.parse_operand()?;
+            return Ok(Expr::Comparison {
+                left: Box::new(operand),
+                operator,
+                right: Box::new(right),
+            });
+        }
+        if self.consume_keyword("IS") {
+            let negated = self.consume_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull {
+                expr: Box::new(operand),
+                negated,
+            });
+        }
+        let negated = self.consume_keyword("NOT");
+
+        if self.consume_keyword("IN") {
+            self.expect_token(&Token::LeftParen)?;
+            let mut list = vec![self.parse_literal()?];
+
+            while self.consume_token(&Token::Comma) {
+                list.push(self.parse_literal()?);
+            }
+            self.expect_token(&Token::RightParen)?;
+            return Ok(Expr::InList {
+                expr: Box::new(operand),
+                list,
+                negated,
+            });
+        }
+        if self.consume_keyword("BETWEEN") {
+            let low = self.parse_literal()?;
+            self.expect_keyword("AND")?;
+            let high = self.parse_literal()?;
+            return Ok(Expr::Between {
+                expr: Box::new(operand),
+                low,
+                high,
+                negated,
+            });
+        }
+        if self.consume_keyword("LIKE") {
+            let pattern = match self.next_token() {
+                Some(Token::String(pattern)) => pattern,
+                Some(token) => bail!("expected a string pattern after `LIKE`, found `{token}`"),
+                None => bail!("expected a string pattern after `LIKE`, found end of query"),
+            };
+            return Ok(Expr::Like {
+                expr: Box::new(operand),
+                pattern,
+                negated,
+            });
+        }
+        if negated {
+            match self.peek() {
+                Some(token) => {
+                    bail!("expected `IN`, `BETWEEN` or `LIKE` after `NOT`, found `{token}`")
+                }
+                None => bail!("expected `IN`, `BETWEEN` or `LIKE` after `NOT`, found end of query"),
+            }
+        }
+        Ok(operand)
+    }
+
+    fn parse_literal(&mut self) -> anyhow::Result<Literal> {
+        match self.parse_operand()? {
+            Expr::Literal(literal) => Ok(literal),
+            expr => bail!("expected a literal, found `{expr}`"),
+        }
+    }
+
+    fn parse_operand(&mut self) -> anyhow::Result<Expr> {
+        let Some(token) = self.next_token() else {
+            bail!("expected an expression, found end of query");
+        };
+        match token {
+            Token::String(string) => Ok(Expr::Literal(Literal::String(string))),
+            Token::Number(number) => Ok(Expr::Literal(Literal::Number(number))),
+            Token::QuotedIdentifier(identifier) => Ok(Expr::Column(identifier)),
+            Token::LeftParen => {
+                let expr = self.parse_expr()?;
+                self.expect_token(&Token::RightParen)?;
+                Ok(expr)
+            }
+            Token::Identifier(identifier) => {
+                if identifier.eq_ignore_ascii_case("TRUE") {
+                    return Ok(Expr::Literal(Literal::Boolean(true)));
+                }
+                if identifier.eq_ignore_ascii_case("FALSE") {
+                    return Ok(Expr::Literal(Literal::Boolean(false)));
+                }
+                if identifier.eq_ignore_ascii_case("NULL") {
+                    return Ok(Expr::Literal(Literal::Null));
+                }
+                if is_reserved_keyword(&identifier) {
+                    bail!("expected an expression, found keyword `{identifier}`");
+                }
+                if self.consume_token(&Token::LeftParen) {
+                    return self.parse_function_call(identifier.to_ascii_uppercase());
+                }
+                Ok(Expr::Column(identifier))
+            }
+            token => bail!("expected an expression, found `{token}`"),
+        }
+    }
+
+    fn parse_function_call(&mut self, name: String) -> anyhow::Result<Expr> {
+        let distinct = self.consume_keyword("DISTINCT");
+        let mut args = Vec::new();
+
+        if !self.consume_token(&Token::RightParen) {
+            loop {
+                if self.consume_token(&Token::Star) {
+                    args.push(Expr::Wildcard);
+                } else {
+                    args.push(self.parse_expr()?);
+                }
+                if self.consume_token(&Token::RightParen) {
+                    break;
+                }
+                self.expect_token(&Token::Comma)?;
+            }
+        }
+        Ok(Expr::Function {
+            name,
+            args,
+            distinct,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str) -> Expr {
+        Expr::Column(name.to_string())
+    }
+
+    fn string(value: &str) -> Expr {
+        Expr::Literal(Literal::String(value.to_string()))
+    }
+
+    fn number(value: &str) -> Expr {
+        Expr::Literal(Literal::Number(value.to_string()))
+    }
+
+    #[test]
+    fn test_parse_sql_simple_select() {
+        let sql_query = parse_sql("SELECT * FROM my-index LIMIT 10").unwrap();
+        assert_eq!(sql_query.select_items, vec![SelectItem::Wildcard]);
+        assert_eq!(sql_query.index_id_patterns, vec!["my-index".to_string()]);
+        assert!(sql_query.where_clause.is_none());
+        assert_eq!(sql_query.limit, Some(10));
+        assert_eq!(sql_query.offset, None);
+
+        let sql_query =
+            parse_sql("select title, \"user.name\" as user from logs-*, \"other\" offset 5;")
+                .unwrap();
+        assert_eq!(
+            sql_query.select_items,
+            vec![
+                SelectItem::Expr {
+                    expr: column("title"),
+                    alias: None,
+                },
+                SelectItem::Expr {
+                    expr: column("user.name"),
+                    alias: Some("user".to_string()),
+                },
+            ]
+        );
+        assert_eq!(
+            sql_query.index_id_patterns,
+            vec!["logs-*".to_string(), "other".to_string()]
+        );
+        assert_eq!(sql_query.offset, Some(5));
+    }
+
+    #[test]
+    fn test_parse_sql_where_clause() {
+        let sql_query = parse_sql(
+            "SELECT * FROM logs WHERE status >= 500 AND (service = 'api' OR service = 'web') AND \
+             host NOT IN ('a', 'b') AND latency BETWEEN 1.5 AND 10 AND message LIKE 'err%' AND \
+             NOT user_id IS NULL",
+        )
+        .unwrap();
+        let expected_where_clause = Expr::And(vec![
+            Expr::Comparison {
+                left: Box::new(column("status")),
+                operator: ComparisonOperator::Gte,
+                right: Box::new(number("500")),
+            },
+            Expr::Or(vec![
+                Expr::Comparison {
+                    left: Box::new(column("service")),
+                    operator: ComparisonOperator::Eq,
+                    right: Box::new(string("api")),
+                },
+                Expr::Comparison {
+                    left: Box::new(column("service")),
+                    operator: ComparisonOperator::Eq,
+                    right: Box::new(string("web")),
+                },
+            ]),
+            Expr::InList {
+                expr: Box::new(column("host")),
+                list: vec![
+                    Literal::String("a".to_string()),
+                    Literal::String("b".to_string()),
+                ],
+                negated: true,
+            },
+            Expr::Between {
+                expr: Box::new(column("latency")),
+                low: Literal::Number("1.5".to_string()),
+                high: Literal::Number("10".to_string()),
+                negated: false,
+            },
+            Expr::Like {
+                expr: Box::new(column("message")),
+                pattern: "err%".to_string(),
+                negated: false,
+            },
+            Expr::Not(Box::new(Expr::IsNull {
+                expr: Box::new(column("user_id")),
+                negated: false,
+            })),
+        ]);
+        assert_eq!(sql_query.where_clause, Some(expected_where_clause));
+    }
+
+    #[test]
+    fn test_parse_sql_aggregation() {
+        let sql_query = parse_sql(
+            "SELECT service, COUNT(*) AS num_requests, PERCENTILE(latency, 99), COUNT(DISTINCT \
+             user_id) FROM logs GROUP BY service ORDER BY num_requests DESC, service LIMIT 5",
+        )
+        .unwrap();
+        assert_eq!(sql_query.select_items.len(), 4);
+        assert_eq!(
+            sql_query.select_items[1],
+            SelectItem::Expr {
+                expr: Expr::Function {
+                    name: "COUNT".to_string(),
+                    args: vec![Expr::Wildcard],
+                    distinct: false,
+                },
+                alias: Some("num_requests".to_string()),
+            }
+        );
+        assert_eq!(
+            sql_query.select_items[2],
+            SelectItem::Expr {
+                expr: Expr::Function {
+                    name: "PERCENTILE".to_string(),
+                    args: vec![column("latency"), number("99")],
+                    distinct: false,
+                },
+                alias: None,
+            }
+        );
+        assert_eq!(
+            sql_query.select_items[3],
+            SelectItem::Expr {
+                expr: Expr::Function {
+                    name: "COUNT".to_string(),
+                    args: vec![column("user_id")],
+                    distinct: true,
+                },
+                alias: None,
+            }
+        );
+        assert_eq!(sql_query.group_by, vec![column("service")]);
+        assert_eq!(
+            sql_query.order_by,
+            vec![
+                OrderByItem {
+                    expr: column("num_requests"),
+                    descending: true,
+                },
+                OrderByItem {
+                    expr: column("service"),
+                    descending: false,
+                },
+            ]
+        );
+        assert_eq!(sql_query.limit, Some(5));
+    }
+
+    #[test]
+    fn test_parse_sql_string_escaping() {
+        let sql_query = parse_sql("SELECT * FROM logs WHERE name = 'O''Reilly'").unwrap();
+        assert_eq!(
+            sql_query.where_clause,
+            Some(Expr::Comparison {
+                left: Box::new(column("name")),
+                operator: ComparisonOperator::Eq,
+                right: Box::new(string("O'Reilly")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_sql_errors() {
+        let error_message = |sql: &str| parse_sql(sql).unwrap_err().to_string();
+
+        assert_eq!(
+            error_message("SELECT * WHERE x = 1"),
+            "expected `FROM`, found `WHERE`"
+        );
+        assert_eq!(
+            error_message("SELECT * FROM logs WHERE x = 'abc"),
+            "unterminated quoted string starting at position 29"
+        );
+        assert_eq!(
+            error_message("SELECT * FROM logs LIMIT -1"),
+            "LIMIT must be a non-negative integer, got `-1`"
+        );
+        assert_eq!(
+            error_message("SELECT * FROM logs GROUP BY x HAVING COUNT(*) > 1"),
+            "`HAVING` is not supported"
+        );
+        assert_eq!(
+            error_message("SELECT * FROM logs; DROP TABLE logs"),
+            "only a single SQL statement is supported"
+        );
+        assert_eq!(
+            error_message("SELECT * FROM logs WHERE x NOT = 1"),
+            "expected `IN`, `BETWEEN` or `LIKE` after `NOT`, found `=`"
+        );
+        assert_eq!(
+            error_message("SELECT * FROM logs JOIN other"),
+            "unexpected token `JOIN` after the end of the query"
+        );
+    }
+}
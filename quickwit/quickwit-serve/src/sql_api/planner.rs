@@ -0,0 +1,1135 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Translates a parsed SQL query into a search request.
+//!
+//! The `WHERE` clause is planned onto a [`QueryAst`], `GROUP BY` expressions onto nested
+//! `terms`, `histogram` and `date_histogram` bucket aggregations, aggregate functions onto metric
+//! aggregations, and `ORDER BY` onto sort fields. Anything that cannot be translated is rejected
+//! instead of being evaluated by scanning documents.
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Bound;
+use std::str::FromStr;
+
+use anyhow::{Context, bail};
+use quickwit_proto::search::{CountHits, SearchRequest, SortField, SortOrder};
+use quickwit_query::query_ast::{
+    BoolQuery, FieldPresenceQuery, FullTextMode, FullTextParams, FullTextQuery, QueryAst,
+    RangeQuery, TermQuery, TermSetQuery, UserInputQuery, WildcardQuery,
+};
+use quickwit_query::{BooleanOperand, JsonLiteral, MatchAllOrNone};
+use serde_json::{Value as JsonValue, json};
+
+use super::parser::{ComparisonOperator, Expr, Literal, OrderByItem, SelectItem, SqlQuery};
+
+/// Number of rows returned when the query does not have a `LIMIT` clause.
+const DEFAULT_LIMIT: u64 = 100;
+
+/// Maximum number of rows a query can return.
+const MAX_LIMIT: u64 = 10_000;
+
+const AGGREGATE_FUNCTIONS: &[&str] = &["AVG", "COUNT", "MAX", "MIN", "PERCENTILE", "SUM"];
+
+/// The execution plan of a SQL query.
+#[derive(Debug)]
+pub(crate) struct SqlPlan {
+    pub search_request: SearchRequest,
+    pub output: OutputPlan,
+}
+
+/// Describes how the search response is turned into a table.
+#[derive(Debug, PartialEq)]
+pub(crate) enum OutputPlan {
+    /// Rows are the documents returned by the search.
+    Documents { projections: Vec<Projection> },
+    /// Rows are the buckets of the (possibly nested) group aggregations.
+    Groups(GroupsPlan),
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Projection {
+    /// All the top-level fields of the documents.
+    Wildcard,
+    Field {
+        path: String,
+        column: String,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct GroupsPlan {
+    /// Number of nested group aggregations, named `group_0`, `group_1`, etc.
+    pub num_groups: usize,
+    /// Metric aggregations, named `metric_0`, `metric_1`, etc., attached to the innermost group.
+    pub metrics: Vec<MetricKind>,
+    pub columns: Vec<(String, ColumnSource)>,
+    pub order_by: Vec<(ColumnSource, bool)>,
+    pub offset: usize,
+    pub limit: usize,
+    /// Whether the groups must be returned entirely: truncated terms aggregations or more groups
+    /// than `limit` fail the query instead of returning incomplete results.
+    pub require_all_groups: bool,
+}
+
+/// Where the value of a column comes from in a group aggregation row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColumnSource {
+    GroupKey(usize),
+    DocCount,
+    Metric(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MetricKind {
+    /// A metric reporting a single `value`.
+    SingleValue,
+    /// A `percentiles` metric computed for a single percentile.
+    Percentile,
+}
+
+/// Plans a SQL query onto a search request.
+pub(crate) fn plan_sql(sql_query: SqlQuery) -> anyhow::Result<SqlPlan> {
+    let query_ast = match &sql_query.where_clause {
+        Some(where_clause) => plan_where_clause(where_clause)?,
+        None => QueryAst::MatchAll,
+    };
+    let limit = sql_query.limit.unwrap_or(DEFAULT_LIMIT);
+
+    if limit > MAX_LIMIT {
+        bail!("LIMIT must be less than or equal to {MAX_LIMIT}, got {limit}");
+    }
+    let offset = sql_query.offset.unwrap_or(0);
+    let search_request = SearchRequest {
+        index_id_patterns: sql_query.index_id_patterns.clone(),
+        query_ast: serde_json::to_string(&query_ast)?,
+        count_hits: CountHits::CountAll as i32,
+        ..Default::default()
+    };
+    if is_aggregation_query(&sql_query) {
+        plan_groups(sql_query, search_request, limit, offset)
+    } else {
+        plan_documents(sql_query, search_request, limit, offset)
+    }
+}
+
+fn is_aggregate_function(expr: &Expr) -> bool {
+    matches!(expr, Expr::Function { name, .. } if AGGREGATE_FUNCTIONS.contains(&name.as_str()))
+}
+
+fn contains_aggregate_function(expr: &Expr) -> bool {
+    match expr {
+        Expr::Function { args, .. } => {
+            is_aggregate_function(expr) || args.iter().any(contains_aggregate_function)
+        }
+        _ => false,
+    }
+}
+
+fn is_aggregation_query(sql_query: &SqlQuery) -> bool {
+    !sql_query.group_by.is_empty()
+        || sql_query
+            .select_items
+            .iter()
+            .any(|select_item| match select_item {
+                SelectItem::Expr { expr, .. } => contains_aggregate_function(expr),
+                SelectItem::Wildcard => false,
+            })
+}
+
+fn select_item_column_name(expr: &Expr, alias: &Option<String>) -> String {
+    alias.clone().unwrap_or_else(|| expr.to_string())
+}
+
+/// Resolves references to a select item, by alias or by (1-based) ordinal, used in the `GROUP BY`
+/// and `ORDER BY` clauses.
+fn resolve_select_item_reference(
+    expr: &Expr,
+    select_items: &[SelectItem],
+    clause: &str,
+) -> anyhow::Result<Expr> {
+    match expr {
+        Expr::Literal(Literal::Number(number)) => {
+            let ordinal: usize = number
+                .parse()
+                .ok()
+                .filter(|ordinal| *ordinal > 0)
+                .with_context(|| format!("invalid {clause} position `{number}`"))?;
+            match select_items.get(ordinal - 1) {
+                Some(SelectItem::Expr { expr, .. }) => Ok(expr.clone()),
+                Some(SelectItem::Wildcard) => {
+                    bail!("{clause} position {ordinal} refers to `*`")
+                }
+                None => bail!(
+                    "{clause} position {ordinal} is not in select list (it has {} items)",
+                    select_items.len()
+                ),
+            }
+        }
+        Expr::Column(column) => {
+            for select_item in select_items {
+                if let SelectItem::Expr {
+                    expr,
+                    alias: Some(alias),
+                } = select_item
+                    && alias == column
+                {
+                    return Ok(expr.clone());
+                }
+            }
+            Ok(expr.clone())
+        }
+        _ => Ok(expr.clone()),
+    }
+}
+
+fn plan_documents(
+    sql_query: SqlQuery,
+    mut search_request: SearchRequest,
+    limit: u64,
+    offset: u64,
+) -> anyhow::Result<SqlPlan> {
+    let mut projections = Vec::with_capacity(sql_query.select_items.len());
+
+    for select_item in &sql_query.select_items {
+        let projection = match select_item {
+            SelectItem::Wildcard => Projection::Wildcard,
+            SelectItem::Expr {
+                expr: Expr::Column(path),
+                alias,
+            } => Projection::Field {
+                path: path.clone(),
+                column: alias.clone().unwrap_or_else(|| path.clone()),
+            },
+            SelectItem::Expr { expr, .. } => bail!(
+                "unsupported select expression `{expr}`: only columns can be selected in queries \
+                 without GROUP BY or aggregate functions"
+            ),
+        };
+        projections.push(projection);
+    }
+    for OrderByItem { expr, descending } in &sql_query.order_by {
+        let expr = resolve_select_item_reference(expr, &sql_query.select_items, "ORDER BY")?;
+        let Expr::Column(field_name) = expr else {
+            bail!(
+                "unsupported ORDER BY expression `{expr}`: only columns can be used to sort \
+                 documents"
+            );
+        };
+        let sort_order = if *descending {
+            SortOrder::Desc
+        } else {
+            SortOrder::Asc
+        };
+        search_request.sort_fields.push(SortField {
+            field_name,
+            sort_order: sort_order as i32,
+            sort_datetime_format: None,
+        });
+    }
+    search_request.max_hits = limit;
+    search_request.start_offset = offset;
+
+    let sql_plan = SqlPlan {
+        search_request,
+        output: OutputPlan::Documents { projections },
+    };
+    Ok(sql_plan)
+}
+
+#[derive(Default)]
+struct GroupsPlanner {
+    group_by: Vec<Expr>,
+    metric_exprs: Vec<Expr>,
+    metric_aggs: Vec<(MetricKind, JsonValue)>,
+}
+
+impl GroupsPlanner {
+    fn resolve_column_source(&mut self, expr: &Expr) -> anyhow::Result<ColumnSource> {
+        if let Some(group_idx) = self
+            .group_by
+            .iter()
+            .position(|group_expr| group_expr == expr)
+        {
+            return Ok(ColumnSource::GroupKey(group_idx));
+        }
+        if !is_aggregate_function(expr) {
+            if let Expr::Column(column) = expr {
+                bail!(
+                    "column `{column}` must appear in the GROUP BY clause or be used in an \
+                     aggregate function"
+                );
+            }
+            bail!(
+                "unsupported expression `{expr}`: expressions must appear in the GROUP BY clause \
+                 or be aggregate functions"
+            );
+        }
+        let Expr::Function {
+            name,
+            args,
+            distinct,
+        } = expr
+        else {
+            unreachable!("aggregate functions are function calls");
+        };
+        if name == "COUNT" && !distinct && args.as_slice() == [Expr::Wildcard] {
+            return Ok(ColumnSource::DocCount);
+        }
+        if let Some(metric_idx) = self
+            .metric_exprs
+            .iter()
+            .position(|metric_expr| metric_expr == expr)
+        {
+            return Ok(ColumnSource::Metric(metric_idx));
+        }
+        let metric_agg = plan_metric(expr, name, args, *distinct)?;
+        self.metric_exprs.push(expr.clone());
+        self.metric_aggs.push(metric_agg);
+        Ok(ColumnSource::Metric(self.metric_exprs.len() - 1))
+    }
+}
+
+fn expect_column_arg<'a>(function_name: &str, arg: &'a Expr) -> anyhow::Result<&'a str> {
+    match arg {
+        Expr::Column(column) => Ok(column),
+        _ => bail!("`{function_name}` expects a column argument, got `{arg}`"),
+    }
+}
+
+fn plan_metric(
+    expr: &Expr,
+    name: &str,
+    args: &[Expr],
+    distinct: bool,
+) -> anyhow::Result<(MetricKind, JsonValue)> {
+    if distinct && name != "COUNT" {
+        bail!("unsupported aggregate function `{expr}`: DISTINCT is only supported with COUNT");
+    }
+    match (name, args) {
+        ("COUNT", [arg]) => {
+            let field = expect_column_arg(name, arg)?;
+            let metric_agg = if distinct {
+                json!({"cardinality": {"field": field}})
+            } else {
+                json!({"value_count": {"field": field}})
+            };
+            Ok((MetricKind::SingleValue, metric_agg))
+        }
+        ("SUM" | "AVG" | "MIN" | "MAX", [arg]) => {
+            let field = expect_column_arg(name, arg)?;
+            let metric_agg = json!({name.to_ascii_lowercase(): {"field": field}});
+            Ok((MetricKind::SingleValue, metric_agg))
+        }
+        ("PERCENTILE", [arg, Expr::Literal(Literal::Number(percent))]) => {
+            let field = expect_column_arg(name, arg)?;
+            let percent: f64 = percent
+                .parse()
+                .ok()
+                .filter(|percent| (0.0..=100.0).contains(percent))
+                .with_context(|| {
+                    format!("`PERCENTILE` expects a percentile between 0 and 100, got `{percent}`")
+                })?;
+            let metric_agg = json!({
+                "percentiles": {
+                    "field": field,
+                    "percents": [percent],
+                    "keyed": false,
+                }
+            });
+            Ok((MetricKind::Percentile, metric_agg))
+        }
+        ("PERCENTILE", _) => {
+            bail!("`PERCENTILE` expects a column and a numeric percentile, got `{expr}`")
+        }
+        _ => bail!("unsupported aggregate function call `{expr}`"),
+    }
+}
+
+/// Returns the bucket aggregation implementing a `GROUP BY` expression.
+fn plan_group(expr: &Expr) -> anyhow::Result<JsonValue> {
+    let group_agg = match expr {
+        Expr::Column(field) => json!({"terms": {"field": field}}),
+        Expr::Function {
+            name,
+            args,
+            distinct: false,
+        } => match (name.as_str(), args.as_slice()) {
+            ("DATE_TRUNC", [Expr::Literal(Literal::String(unit)), arg]) => {
+                let field = expect_column_arg(name, arg)?;
+                let fixed_interval = match unit.to_ascii_lowercase().as_str() {
+                    "second" => "1s",
+                    "minute" => "1m",
+                    "hour" => "1h",
+                    "day" => "1d",
+                    _ => bail!(
+                        "unsupported `DATE_TRUNC` unit `{unit}`: supported units are `second`, \
+                         `minute`, `hour` and `day`"
+                    ),
+                };
+                json!({
+                    "date_histogram": {
+                        "field": field,
+                        "fixed_interval": fixed_interval,
+                        "min_doc_count": 1,
+                    }
+                })
+            }
+            ("DATE_HISTOGRAM", [arg, Expr::Literal(Literal::String(fixed_interval))]) => {
+                let field = expect_column_arg(name, arg)?;
+                json!({
+                    "date_histogram": {
+                        "field": field,
+                        "fixed_interval": fixed_interval,
+                        "min_doc_count": 1,
+                    }
+                })
+            }
+            ("HISTOGRAM", [arg, Expr::Literal(Literal::Number(interval))]) => {
+                let field = expect_column_arg(name, arg)?;
+                let interval: f64 = interval
+                    .parse()
+                    .ok()
+                    .filter(|interval| *interval > 0.0)
+                    .with_context(|| {
+                        format!("`HISTOGRAM` expects a positive interval, got `{interval}`")
+                    })?;
+                json!({
+                    "histogram": {
+                        "field": field,
+                        "interval": interval,
+                        "min_doc_count": 1,
+                    }
+                })
+            }
+            ("DATE_TRUNC", _) => {
+                bail!("`DATE_TRUNC` expects a unit and a column, got `{expr}`")
+            }
+            ("DATE_HISTOGRAM", _) => {
+                bail!("`DATE_HISTOGRAM` expects a column and an interval, got `{expr}`")
+            }
+            ("HISTOGRAM", _) => {
+                bail!("`HISTOGRAM` expects a column and an interval, got `{expr}`")
+            }
+            _ => bail!("unsupported GROUP BY expression `{expr}`"),
+        },
+        _ => bail!("unsupported GROUP BY expression `{expr}`"),
+    };
+    Ok(group_agg)
+}
+
+fn plan_groups(
+    sql_query: SqlQuery,
+    mut search_request: SearchRequest,
+    limit: u64,
+    offset: u64,
+) -> anyhow::Result<SqlPlan> {
+    let mut planner = GroupsPlanner::default();
+
+    for group_expr in &sql_query.group_by {
+        let group_expr =
+            resolve_select_item_reference(group_expr, &sql_query.select_items, "GROUP BY")?;
+        if contains_aggregate_function(&group_expr) {
+            bail!("aggregate functions are not allowed in GROUP BY: `{group_expr}`");
+        }
+        if !planner.group_by.contains(&group_expr) {
+            planner.group_by.push(group_expr);
+        }
+    }
+    let mut columns = Vec::with_capacity(sql_query.select_items.len());
+
+    for select_item in &sql_query.select_items {
+        let SelectItem::Expr { expr, alias } = select_item else {
+            bail!("`SELECT *` is not supported with GROUP BY or aggregate functions");
+        };
+        let column_source = planner.resolve_column_source(expr)?;
+        columns.push((select_item_column_name(expr, alias), column_source));
+    }
+    let mut order_by = Vec::with_capacity(sql_query.order_by.len());
+
+    for OrderByItem { expr, descending } in &sql_query.order_by {
+        let expr = resolve_select_item_reference(expr, &sql_query.select_items, "ORDER BY")?;
+        let column_source = planner.resolve_column_source(&expr)?;
+        order_by.push((column_source, *descending));
+    }
+    let num_groups = planner.group_by.len();
+    // Nested terms aggregations select the top buckets of each level independently, so the top
+    // rows of the combined groups cannot be computed.
+    let has_column_group = planner
+        .group_by
+        .iter()
+        .any(|group_expr| matches!(group_expr, Expr::Column(_)));
+    let require_all_groups = num_groups > 1 && has_column_group;
+
+    if require_all_groups
+        && (!order_by.is_empty() || sql_query.limit.is_some() || sql_query.offset.is_some())
+    {
+        bail!(
+            "ORDER BY, LIMIT and OFFSET are only supported with GROUP BY on a single column or on \
+             time buckets and histograms"
+        );
+    }
+    // Without a page to select, all the groups are returned, up to the maximum limit, and the
+    // query fails if some are missing.
+    let limit = if require_all_groups { MAX_LIMIT } else { limit };
+    // Terms aggregations only return their top buckets, so they must return enough of them to
+    // fill the requested page.
+    let terms_size = offset + limit;
+
+    let mut aggs: serde_json::Map<String, JsonValue> = planner
+        .metric_aggs
+        .iter()
+        .enumerate()
+        .map(|(metric_idx, (_, metric_agg))| (format!("metric_{metric_idx}"), metric_agg.clone()))
+        .collect();
+
+    for (group_idx, group_expr) in planner.group_by.iter().enumerate().rev() {
+        let mut group_agg = plan_group(group_expr)?;
+
+        if let Some(terms_agg) = group_agg.get_mut("terms") {
+            terms_agg["size"] = json!(terms_size);
+
+            // With a single level of grouping, the first sort criterion is pushed down to the
+            // terms aggregation so that it returns the right top buckets.
+            if num_groups == 1
+                && let Some((column_source, descending)) = order_by.first()
+            {
+                let order_key = match column_source {
+                    ColumnSource::GroupKey(_) => "_key".to_string(),
+                    ColumnSource::DocCount => "_count".to_string(),
+                    ColumnSource::Metric(metric_idx) => {
+                        if planner.metric_aggs[*metric_idx].0 == MetricKind::Percentile {
+                            bail!(
+                                "ORDER BY `{}` is not supported with GROUP BY on a column",
+                                planner.metric_exprs[*metric_idx]
+                            );
+                        }
+                        format!("metric_{metric_idx}")
+                    }
+                };
+                let order_direction = if *descending { "desc" } else { "asc" };
+                terms_agg["order"] = json!({ order_key: order_direction });
+            }
+        }
+        if !aggs.is_empty() {
+            group_agg["aggs"] = JsonValue::Object(aggs);
+        }
+        aggs = serde_json::Map::from_iter([(format!("group_{group_idx}"), group_agg)]);
+    }
+    if !aggs.is_empty() {
+        search_request.aggregation_request = Some(serde_json::to_string(&aggs)?);
+    }
+    search_request.max_hits = 0;
+
+    let groups_plan = GroupsPlan {
+        num_groups,
+        metrics: planner
+            .metric_aggs
+            .iter()
+            .map(|(metric_kind, _)| *metric_kind)
+            .collect(),
+        columns,
+        order_by,
+        offset: offset as usize,
+        limit: limit as usize,
+        require_all_groups,
+    };
+    let sql_plan = SqlPlan {
+        search_request,
+        output: OutputPlan::Groups(groups_plan),
+    };
+    Ok(sql_plan)
+}
+
+fn literal_to_term(literal: &Literal) -> anyhow::Result<String> {
+    match literal {
+        Literal::String(string) => Ok(string.clone()),
+        Literal::Number(number) => Ok(number.clone()),
+        Literal::Boolean(boolean) => Ok(boolean.to_string()),
+        Literal::Null => bail!("comparisons with NULL are not supported, use `IS [NOT] NULL`"),
+    }
+}
+
+fn literal_to_json_literal(literal: &Literal) -> anyhow::Result<JsonLiteral> {
+    match literal {
+        Literal::String(string) => Ok(JsonLiteral::String(string.clone())),
+        Literal::Number(number) => {
+            let number = serde_json::Number::from_str(number)
+                .with_context(|| format!("invalid number `{number}`"))?;
+            Ok(JsonLiteral::Number(number))
+        }
+        Literal::Boolean(boolean) => Ok(JsonLiteral::Bool(*boolean)),
+        Literal::Null => bail!("comparisons with NULL are not supported, use `IS [NOT] NULL`"),
+    }
+}
+
+fn expect_column<'a>(expr: &'a Expr, context: &str) -> anyhow::Result<&'a str> {
+    match expr {
+        Expr::Column(column) => Ok(column),
+        _ => bail!("unsupported {context}: expected a column, got `{expr}`"),
+    }
+}
+
+fn negate(query_ast: QueryAst) -> QueryAst {
+    BoolQuery {
+        must_not: vec![query_ast],
+        ..Default::default()
+    }
+    .into()
+}
+
+fn negate_if(query_ast: QueryAst, negated: bool) -> QueryAst {
+    if negated {
+        negate(query_ast)
+    } else {
+        query_ast
+    }
+}
+
+/// Translates a `LIKE` pattern into a wildcard query pattern.
+fn like_pattern_to_wildcard(pattern: &str) -> String {
+    let mut wildcard = String::with_capacity(pattern.len());
+
+    for ch in pattern.chars() {
+        match ch {
+            '%' => wildcard.push('*'),
+            '_' => wildcard.push('?'),
+            '*' | '?' | '\\' => {
+                wildcard.push('\\');
+                wildcard.push(ch);
+            }
+            _ => wildcard.push(ch),
+        }
+    }
+    wildcard
+}
+
+fn plan_comparison(
+    left: &Expr,
+    operator: ComparisonOperator,
+    right: &Expr,
+) -> anyhow::Result<QueryAst> {
+    let (field, operator, literal) = match (left, right) {
+        (Expr::Column(field), Expr::Literal(literal)) => (field, operator, literal),
+        (Expr::Literal(literal), Expr::Column(field)) => (field, operator.flip(), literal),
+        _ => bail!(
+            "unsupported comparison `{left} {operator} {right}`: comparisons must be between a \
+             column and a literal"
+        ),
+    };
+    let query_ast = match operator {
+        ComparisonOperator::Eq | ComparisonOperator::NotEq => {
+            let term_query: QueryAst = TermQuery {
+                field: field.clone(),
+                value: literal_to_term(literal)?,
+            }
+            .into();
+            negate_if(term_query, operator == ComparisonOperator::NotEq)
+        }
+        ComparisonOperator::Lt
+        | ComparisonOperator::Lte
+        | ComparisonOperator::Gt
+        | ComparisonOperator::Gte => {
+            let bound_value = literal_to_json_literal(literal)?;
+            let (lower_bound, upper_bound) = match operator {
+                ComparisonOperator::Lt => (Bound::Unbounded, Bound::Excluded(bound_value)),
+                ComparisonOperator::Lte => (Bound::Unbounded, Bound::Included(bound_value)),
+                ComparisonOperator::Gt => (Bound::Excluded(bound_value), Bound::Unbounded),
+                _ => (Bound::Included(bound_value), Bound::Unbounded),
+            };
+            RangeQuery {
+                field: field.clone(),
+                lower_bound,
+                upper_bound,
+            }
+            .into()
+        }
+    };
+    Ok(query_ast)
+}
+
+fn plan_where_function(name: &str, args: &[Expr], expr: &Expr) -> anyhow::Result<QueryAst> {
+    let query_ast = match (name, args) {
+        ("MATCH", [Expr::Column(field), Expr::Literal(Literal::String(text))]) => FullTextQuery {
+            field: field.clone(),
+            text: text.clone(),
+            params: FullTextParams {
+                tokenizer: None,
+                mode: FullTextMode::Bool {
+                    operator: BooleanOperand::Or,
+                },
+                zero_terms_query: MatchAllOrNone::MatchNone,
+                fuzzy: None,
+            },
+            lenient: false,
+        }
+        .into(),
+        ("QUERY", [Expr::Literal(Literal::String(user_text))]) => UserInputQuery {
+            user_text: user_text.clone(),
+            default_fields: None,
+            default_operator: BooleanOperand::And,
+            lenient: false,
+        }
+        .into(),
+        ("MATCH", _) => bail!("`MATCH` expects a column and a string, got `{expr}`"),
+        ("QUERY", _) => bail!("`QUERY` expects a query string, got `{expr}`"),
+        _ => bail!("unsupported function `{expr}` in WHERE clause"),
+    };
+    Ok(query_ast)
+}
+
+/// Translates a `WHERE` clause into a query AST.
+fn plan_where_clause(expr: &Expr) -> anyhow::Result<QueryAst> {
+    let query_ast = match expr {
+        Expr::And(exprs) => BoolQuery {
+            must: exprs
+                .iter()
+                .map(plan_where_clause)
+                .collect::<anyhow::Result<_>>()?,
+            ..Default::default()
+        }
+        .into(),
+        Expr::Or(exprs) => BoolQuery {
+            should: exprs
+                .iter()
+                .map(plan_where_clause)
+                .collect::<anyhow::Result<_>>()?,
+            ..Default::default()
+        }
+        .into(),
+        Expr::Not(expr) => negate(plan_where_clause(expr)?),
+        Expr::Comparison {
+            left,
+            operator,
+            right,
+        } => plan_comparison(left, *operator, right)?,
+        Expr::InList {
+            expr,
+            list,
+            negated,
+        } => {
+            let field = expect_column(expr, "IN expression")?;
+            let terms: BTreeSet<String> = list
+                .iter()
+                .map(literal_to_term)
+                .collect::<anyhow::Result<_>>()?;
+            let term_set_query: QueryAst = TermSetQuery {
+                terms_per_field: HashMap::from_iter([(field.to_string(), terms)]),
+            }
+            .into();
+            negate_if(term_set_query, *negated)
+        }
+        Expr::Between {
+            expr,
+            low,
+            high,
+            negated,
+        } => {
+            let field = expect_column(expr, "BETWEEN expression")?;
+            let range_query: QueryAst = RangeQuery {
+                field: field.to_string(),
+                lower_bound: Bound::Included(literal_to_json_literal(low)?),
+                upper_bound: Bound::Included(literal_to_json_literal(high)?),
+            }
+            .into();
+            negate_if(range_query, *negated)
+        }
+        Expr::Like {
+            expr,
+            pattern,
+            negated,
+        } => {
+            let field = expect_column(expr, "LIKE expression")?;
+            let wildcard_query: QueryAst = WildcardQuery {
+                field: field.to_string(),
+                value: like_pattern_to_wildcard(pattern),
+                lenient: false,
+                case_insensitive: false,
+            }
+            .into();
+            negate_if(wildcard_query, *negated)
+        }
+        Expr::IsNull { expr, negated } => {
+            let field = expect_column(expr, "IS NULL expression")?;
+            let field_presence_query: QueryAst = FieldPresenceQuery {
+                field: field.to_string(),
+            }
+            .into();
+            // `IS NULL` matches documents where the field is absent.
+            negate_if(field_presence_query, !*negated)
+        }
+        Expr::Function { name, args, .. } if !is_aggregate_function(expr) => {
+            plan_where_function(name, args, expr)?
+        }
+        Expr::Literal(Literal::Boolean(true)) => QueryAst::MatchAll,
+        Expr::Literal(Literal::Boolean(false)) => QueryAst::MatchNone,
+        _ => bail!("unsupported WHERE clause expression `{expr}`"),
+    };
+    Ok(query_ast)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_api::parser::parse_sql;
+
+    fn plan(sql: &str) -> anyhow::Result<SqlPlan> {
+        plan_sql(parse_sql(sql)?)
+    }
+
+    fn plan_error(sql: &str) -> String {
+        plan(sql).unwrap_err().to_string()
+    }
+
+    fn query_ast(sql_plan: &SqlPlan) -> JsonValue {
+        serde_json::from_str(&sql_plan.search_request.query_ast).unwrap()
+    }
+
+    fn aggregation_request(sql_plan: &SqlPlan) -> JsonValue {
+        serde_json::from_str(
+            sql_plan
+                .search_request
+                .aggregation_request
+                .as_ref()
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plan_sql_documents() {
+        let sql_plan = plan(
+            "SELECT title, user.name AS user FROM logs-* ORDER BY timestamp DESC, 1 LIMIT 20 \
+             OFFSET 40",
+        )
+        .unwrap();
+        let search_request = &sql_plan.search_request;
+        assert_eq!(search_request.index_id_patterns, vec!["logs-*".to_string()]);
+        assert_eq!(query_ast(&sql_plan), json!({"type": "match_all"}));
+        assert_eq!(search_request.max_hits, 20);
+        assert_eq!(search_request.start_offset, 40);
+        assert!(search_request.aggregation_request.is_none());
+        assert_eq!(
+            search_request.sort_fields,
+            vec![
+                SortField {
+                    field_name: "timestamp".to_string(),
+                    sort_order: SortOrder::Desc as i32,
+                    sort_datetime_format: None,
+                },
+                SortField {
+                    field_name: "title".to_string(),
+                    sort_order: SortOrder::Asc as i32,
+                    sort_datetime_format: None,
+                },
+            ]
+        );
+        assert_eq!(
+            sql_plan.output,
+            OutputPlan::Documents {
+                projections: vec![
+                    Projection::Field {
+                        path: "title".to_string(),
+                        column: "title".to_string(),
+                    },
+                    Projection::Field {
+                        path: "user.name".to_string(),
+                        column: "user".to_string(),
+                    },
+                ],
+            }
+        );
+        let sql_plan = plan("SELECT * FROM logs").unwrap();
+        assert_eq!(sql_plan.search_request.max_hits, DEFAULT_LIMIT);
+    }
+
+    #[test]
+    fn test_plan_sql_where_clause() {
+        let sql_plan = plan(
+            "SELECT * FROM logs WHERE status >= 500 AND service != 'api' AND 10 > latency AND \
+             host IN ('a', 'b') AND size BETWEEN 1 AND 10 AND path LIKE '/api/%_*' AND user IS \
+             NULL AND (MATCH(message, 'error') OR QUERY('level:warn'))",
+        )
+        .unwrap();
+        let expected_query_ast = json!({
+            "type": "bool",
+            "must": [
+                {
+                    "type": "range",
+                    "field": "status",
+                    "lower_bound": {"Included": 500},
+                    "upper_bound": "Unbounded",
+                },
+                {
+                    "type": "bool",
+                    "must_not": [{"type": "term", "field": "service", "value": "api"}],
+                },
+                {
+                    "type": "range",
+                    "field": "latency",
+                    "lower_bound": "Unbounded",
+                    "upper_bound": {"Excluded": 10},
+                },
+                {
+                    "type": "term_set",
+                    "terms_per_field": {"host": ["a", "b"]},
+                },
+                {
+                    "type": "range",
+                    "field": "size",
+                    "lower_bound": {"Included": 1},
+                    "upper_bound": {"Included": 10},
+                },
+                {
+                    "type": "wildcard",
+                    "field": "path",
+                    "value": "/api/*?\\*",
+                    "lenient": false,
+                    "case_insensitive": false,
+                },
+                {
+                    "type": "bool",
+                    "must_not": [{"type": "field_presence", "field": "user"}],
+                },
+                {
+                    "type": "bool",
+                    "should": [
+                        {
+                            "type": "full_text",
+                            "field": "message",
+                            "text": "error",
+                            "params": {"mode": {"type": "bool", "operator": "Or"}},
+                            "lenient": false,
+                        },
+                        {
+                            "type": "user_input",
+                            "user_text": "level:warn",
+                            "default_operator": "And",
+                            "lenient": false,
+                        },
+                    ],
+                },
+            ],
+        });
+        assert_eq!(query_ast(&sql_plan), expected_query_ast);
+    }
+
+    #[test]
+    fn test_plan_sql_group_by_terms() {
+        let sql_plan = plan(
+            "SELECT service, COUNT(*) AS num_requests, AVG(latency), PERCENTILE(latency, 99), \
+             COUNT(DISTINCT user_id) FROM logs WHERE status = 500 GROUP BY 1 ORDER BY \
+             AVG(latency) DESC LIMIT 5",
+        )
+        .unwrap();
+        assert_eq!(sql_plan.search_request.max_hits, 0);
+
+        let expected_aggregation_request = json!({
+            "group_0": {
+                "terms": {
+                    "field": "service",
+                    "size": 5,
+                    "order": {"metric_0": "desc"},
+                },
+                "aggs": {
+                    "metric_0": {"avg": {"field": "latency"}},
+                    "metric_1": {
+                        "percentiles": {"field": "latency", "percents": [99.0], "keyed": false},
+                    },
+                    "metric_2": {"cardinality": {"field": "user_id"}},
+                },
+            },
+        });
+        assert_eq!(aggregation_request(&sql_plan), expected_aggregation_request);
+        let expected_groups_plan = GroupsPlan {
+            num_groups: 1,
+            metrics: vec![
+                MetricKind::SingleValue,
+                MetricKind::Percentile,
+                MetricKind::SingleValue,
+            ],
+            columns: vec![
+                ("service".to_string(), ColumnSource::GroupKey(0)),
+                ("num_requests".to_string(), ColumnSource::DocCount),
+                ("AVG(latency)".to_string(), ColumnSource::Metric(0)),
+                (
+                    "PERCENTILE(latency, 99)".to_string(),
+                    ColumnSource::Metric(1),
+                ),
+                (
+                    "COUNT(DISTINCT user_id)".to_string(),
+                    ColumnSource::Metric(2),
+                ),
+            ],
+            order_by: vec![(ColumnSource::Metric(0), true)],
+            offset: 0,
+            limit: 5,
+            require_all_groups: false,
+        };
+        assert_eq!(sql_plan.output, OutputPlan::Groups(expected_groups_plan));
+    }
+
+    #[test]
+    fn test_plan_sql_group_by_time_buckets() {
+        let sql_plan = plan(
+            "SELECT DATE_TRUNC('hour', timestamp) AS hour, HISTOGRAM(size, 100), MAX(latency) \
+             FROM logs GROUP BY hour, 2 ORDER BY hour",
+        )
+        .unwrap();
+        let expected_aggregation_request = json!({
+            "group_0": {
+                "date_histogram": {
+                    "field": "timestamp",
+                    "fixed_interval": "1h",
+                    "min_doc_count": 1,
+                },
+                "aggs": {
+                    "group_1": {
+                        "histogram": {"field": "size", "interval": 100.0, "min_doc_count": 1},
+                        "aggs": {
+                            "metric_0": {"max": {"field": "latency"}},
+                        },
+                    },
+                },
+            },
+        });
+        assert_eq!(aggregation_request(&sql_plan), expected_aggregation_request);
+        let sql_plan =
+            plan("SELECT DATE_HISTOGRAM(timestamp, '5m'), COUNT(*) FROM logs GROUP BY 1").unwrap();
+        assert_eq!(
+            aggregation_request(&sql_plan),
+            json!({
+                "group_0": {
+                    "date_histogram": {
+                        "field": "timestamp",
+                        "fixed_interval": "5m",
+                        "min_doc_count": 1,
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_plan_sql_aggregates_without_group_by() {
+        let sql_plan = plan("SELECT COUNT(*), SUM(size) FROM logs").unwrap();
+        assert_eq!(sql_plan.search_request.max_hits, 0);
+        assert_eq!(
+            sql_plan.search_request.count_hits,
+            CountHits::CountAll as i32
+        );
+        assert_eq!(
+            aggregation_request(&sql_plan),
+            json!({"metric_0": {"sum": {"field": "size"}}})
+        );
+        let sql_plan = plan("SELECT COUNT(*) FROM logs").unwrap();
+        assert!(sql_plan.search_request.aggregation_request.is_none());
+    }
+
+    #[test]
+    fn test_plan_sql_errors() {
+        assert_eq!(
+            plan_error("SELECT service, COUNT(*) FROM logs"),
+            "column `service` must appear in the GROUP BY clause or be used in an aggregate \
+             function"
+        );
+        assert_eq!(
+            plan_error("SELECT * FROM logs GROUP BY service"),
+            "`SELECT *` is not supported with GROUP BY or aggregate functions"
+        );
+        assert_eq!(
+            plan_error("SELECT UPPER(service) FROM logs"),
+            "unsupported select expression `UPPER(service)`: only columns can be selected in \
+             queries without GROUP BY or aggregate functions"
+        );
+        assert_eq!(
+            plan_error("SELECT * FROM logs WHERE a = b"),
+            "unsupported comparison `a = b`: comparisons must be between a column and a literal"
+        );
+        assert_eq!(
+            plan_error("SELECT * FROM logs WHERE a = NULL"),
+            "comparisons with NULL are not supported, use `IS [NOT] NULL`"
+        );
+        assert_eq!(
+            plan_error("SELECT DATE_TRUNC('month', ts), COUNT(*) FROM logs GROUP BY 1"),
+            "unsupported `DATE_TRUNC` unit `month`: supported units are `second`, `minute`, \
+             `hour` and `day`"
+        );
+        assert_eq!(
+            plan_error("SELECT SUM(DISTINCT size) FROM logs"),
+            "unsupported aggregate function `SUM(DISTINCT size)`: DISTINCT is only supported with \
+             COUNT"
+        );
+        assert_eq!(
+            plan_error("SELECT * FROM logs LIMIT 10001"),
+            "LIMIT must be less than or equal to 10000, got 10001"
+        );
+        assert_eq!(
+            plan_error("SELECT service, COUNT(*) FROM logs GROUP BY 3"),
+            "GROUP BY position 3 is not in select list (it has 2 items)"
+        );
+    }
+
+    #[test]
+    fn test_plan_sql_multi_level_group_by_with_order_by_or_limit() {
+        let expected_error = "ORDER BY, LIMIT and OFFSET are only supported with GROUP BY on a \
+                              single column or on time buckets and histograms";
+        assert_eq!(
+            plan_error(
+                "SELECT service, host, COUNT(*) AS num_requests FROM logs GROUP BY service, host \
+                 ORDER BY num_requests DESC"
+            ),
+            expected_error
+        );
+        assert_eq!(
+            plan_error("SELECT service, host, COUNT(*) FROM logs GROUP BY 1, 2 LIMIT 10"),
+            expected_error
+        );
+        assert_eq!(
+            plan_error(
+                "SELECT DATE_TRUNC('hour', timestamp), service, COUNT(*) FROM logs GROUP BY 1, 2 \
+                 OFFSET 10"
+            ),
+            expected_error
+        );
+        // Without ORDER BY, LIMIT or OFFSET, all the groups are returned in the order of the
+        // aggregations.
+        let sql_plan = plan("SELECT service, host, COUNT(*) FROM logs GROUP BY 1, 2").unwrap();
+        assert_eq!(
+            aggregation_request(&sql_plan),
+            json!({
+                "group_0": {
+                    "terms": {"field": "service", "size": 10_000},
+                    "aggs": {
+                        "group_1": {
+                            "terms": {"field": "host", "size": 10_000},
+                        },
+                    },
+                },
+            })
+        );
+        let OutputPlan::Groups(groups_plan) = &sql_plan.output else {
+            panic!("expected a groups plan");
+        };
+        assert_eq!(groups_plan.limit, 10_000);
+        assert!(groups_plan.require_all_groups);
+
+        // Histograms return all their buckets, so the groups can be sorted and paged.
+        plan(
+            "SELECT DATE_TRUNC('hour', timestamp), HISTOGRAM(size, 100), COUNT(*) FROM logs GROUP \
+             BY 1, 2 ORDER BY 3 DESC LIMIT 10",
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use quickwit_search::{SearchError, SearchService};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use warp::hyper::header::CONTENT_TYPE;
+use warp::{Filter, Rejection, Reply};
+
+use super::parser::parse_sql;
+use super::planner::plan_sql;
+use super::table::{SqlTable, build_table};
+use crate::rest::recover_fn;
+use crate::rest_api_response::into_rest_api_response;
+use crate::{BodyFormat, with_arg};
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(sql_query),
+    components(schemas(SqlRequest, SqlResponse, SqlResponseFormat))
+)]
+pub(crate) struct SqlApi;
+
+/// Output format of the SQL API.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SqlResponseFormat {
+    Json,
+    #[default]
+    PrettyJson,
+    Csv,
+}
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SqlRequest {
+    /// The SQL query.
+    pub query: String,
+    /// The output format: `json`, `pretty_json` (default), or `csv`.
+    #[serde(default)]
+    pub format: SqlResponseFormat,
+}
+
+#[derive(Debug, Serialize, PartialEq, utoipa::ToSchema)]
+pub(crate) struct SqlResponse {
+    /// The column names.
+    pub columns: Vec<String>,
+    /// The rows, each holding one value per column.
+    #[schema(value_type = Vec<Vec<Object>>)]
+    pub rows: Vec<Vec<JsonValue>>,
+    /// Elapsed time.
+    pub elapsed_time_micros: u64,
+}
+
+pub(crate) fn sql_api_handlers(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    sql_query_handler(search_service)
+        .recover(recover_fn)
+        .boxed()
+}
+
+fn sql_query_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("_sql")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::body::json())
+        .and(with_arg(search_service))
+        .then(sql_query)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/_sql",
+    request_body = SqlRequest,
+    responses(
+        (status = 200, description = "Successfully executed the SQL query.", body = SqlResponse)
+    ),
+)]
+/// SQL Query
+///
+/// Executes a SQL query against one or several indexes. The query is translated into a search
+/// request: queries that cannot be translated are rejected.
+async fn sql_query(
+    sql_request: SqlRequest,
+    search_service: Arc<dyn SearchService>,
+) -> warp::reply::Response {
+    let sql_response_result = sql_endpoint(&sql_request.query, &*search_service).await;
+
+    match (sql_request.format, sql_response_result) {
+        (SqlResponseFormat::Csv, Ok((sql_table, _))) => {
+            warp::reply::with_header(sql_table.to_csv(), CONTENT_TYPE, "text/csv; charset=utf-8")
+                .into_response()
+        }
+        (SqlResponseFormat::Json, sql_response_result) => {
+            let sql_response_result = sql_response_result.map(into_sql_response);
+            into_rest_api_response(sql_response_result, BodyFormat::Json).into_response()
+        }
+        (_, sql_response_result) => {
+            let sql_response_result = sql_response_result.map(into_sql_response);
+            into_rest_api_response(sql_response_result, BodyFormat::PrettyJson).into_response()
+        }
+    }
+}
+
+fn into_sql_response((sql_table, elapsed_time_micros): (SqlTable, u64)) -> SqlResponse {
+    SqlResponse {
+        columns: sql_table.columns,
+        rows: sql_table.rows,
+        elapsed_time_micros,
+    }
+}
+
+async fn sql_endpoint(
+    sql: &str,
+    search_service: &dyn SearchService,
+) -> Result<(SqlTable, u64), SearchError> {
+    let sql_plan = parse_sql(sql)
+        .and_then(plan_sql)
+        .map_err(|error| SearchError::InvalidQuery(format!("invalid SQL query: {error}")))?;
+    let search_response = search_service.root_search(sql_plan.search_request).await?;
+
+    if let Some(search_error) = SearchError::from_split_errors(&search_response.failed_splits[..]) {
+        return Err(search_error);
+    }
+    let elapsed_time_micros = search_response.elapsed_time_micros;
+    let sql_table = build_table(&sql_plan.output, search_response)
+        .map_err(|error| SearchError::Internal(error.to_string()))?;
+    Ok((sql_table, elapsed_time_micros))
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::search::{Hit, SearchResponse};
+    use quickwit_search::MockSearchService;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sql_api_documents() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .returning(|search_request| {
+                assert_eq!(search_request.index_id_patterns, vec!["logs".to_string()]);
+                assert_eq!(search_request.max_hits, 2);
+                Ok(SearchResponse {
+                    num_hits: 2,
+                    hits: vec![
+                        Hit {
+                            json: r#"{"service": "api", "status": 500}"#.to_string(),
+                            ..Default::default()
+                        },
+                        Hit {
+                            json: r#"{"service": "web, frontend", "status": 404}"#.to_string(),
+                            ..Default::default()
+                        },
+                    ],
+                    elapsed_time_micros: 10,
+                    ..Default::default()
+                })
+            });
+        let sql_api_handler = sql_api_handlers(Arc::new(mock_search_service));
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/_sql")
+            .json(&json!({
+                "query": "SELECT service, status FROM logs WHERE status >= 400 LIMIT 2",
+                "format": "json",
+            }))
+            .reply(&sql_api_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+        let response_json: JsonValue = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json,
+            json!({
+                "columns": ["service", "status"],
+                "rows": [["api", 500], ["web, frontend", 404]],
+                "elapsed_time_micros": 10,
+            })
+        );
+        let response = warp::test::request()
+            .method("POST")
+            .path("/_sql")
+            .json(&json!({
+                "query": "SELECT service, status FROM logs WHERE status >= 400 LIMIT 2",
+                "format": "csv",
+            }))
+            .reply(&sql_api_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            response.body(),
+            "service,status\napi,500\n\"web, frontend\",404\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sql_api_invalid_query() {
+        let sql_api_handler = sql_api_handlers(Arc::new(MockSearchService::new()));
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/_sql")
+            .json(&json!({
+                "query": "SELECT service, COUNT(*) FROM logs",
+            }))
+            .reply(&sql_api_handler)
+            .await;
+        assert_eq!(response.status(), 400);
+        let response_json: JsonValue = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json["message"],
+            "invalid SQL query: column `service` must appear in the GROUP BY clause or be used in \
+             an aggregate function"
+        );
+        let response = warp::test::request()
+            .method("POST")
+            .path("/_sql")
+            .json(&json!({
+                "query": "SELECT * FROM logs",
+                "bad_param": 10,
+            }))
+            .reply(&sql_api_handler)
+            .await;
+        assert_eq!(response.status(), 400);
+    }
+}
@@ -0,0 +1,488 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use anyhow::{Context, bail};
+use quickwit_proto::search::SearchResponse;
+use quickwit_search::AggregationResults;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use super::planner::{ColumnSource, GroupsPlan, MetricKind, OutputPlan, Projection};
+use crate::format::{csv_cell, lookup_field, push_csv_line};
+
+/// The tabular result of a SQL query.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct SqlTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+impl SqlTable {
+    /// Renders the table as CSV, with a header line.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        push_csv_line(&mut csv, self.columns.iter().map(|column| column.as_str()));
+
+        for row in &self.rows {
+            let cells: Vec<String> = row.iter().map(csv_cell).collect();
+            push_csv_line(&mut csv, cells.iter().map(|cell| cell.as_str()));
+        }
+        csv
+    }
+}
+
+/// Builds the table of a SQL query from its search response.
+pub(crate) fn build_table(
+    output_plan: &OutputPlan,
+    search_response: SearchResponse,
+) -> anyhow::Result<SqlTable> {
+    match output_plan {
+        OutputPlan::Documents { projections } => {
+            build_documents_table(projections, search_response)
+        }
+        OutputPlan::Groups(groups_plan) => build_groups_table(groups_plan, search_response),
+    }
+}
+
+fn build_documents_table(
+    projections: &[Projection],
+    search_response: SearchResponse,
+) -> anyhow::Result<SqlTable> {
+    let documents: Vec<JsonMap<String, JsonValue>> = search_response
+        .hits
+        .iter()
+        .map(|hit| serde_json::from_str(&hit.json).context("failed to parse hit"))
+        .collect::<anyhow::Result<_>>()?;
+
+    // The columns of `*` are the union of the top-level fields of the returned documents, in order
+    // of appearance.
+    let mut wildcard_columns: Vec<String> = Vec::new();
+
+    if projections.contains(&Projection::Wildcard) {
+        for document in &documents {
+            for key in document.keys() {
+                if !wildcard_columns.contains(key) {
+                    wildcard_columns.push(key.clone());
+                }
+            }
+        }
+    }
+    let mut columns = Vec::new();
+    let mut paths = Vec::new();
+
+    for projection in projections {
+        match projection {
+            Projection::Wildcard => {
+                columns.extend(wildcard_columns.iter().cloned());
+                paths.extend(wildcard_columns.iter().map(String::as_str));
+            }
+            Projection::Field { path, column } => {
+                columns.push(column.clone());
+                paths.push(path.as_str());
+            }
+        }
+    }
+    let rows = documents
+        .iter()
+        .map(|document| {
+            paths
+                .iter()
+                .map(|path| {
+                    lookup_field(document, path)
+                        .cloned()
+                        .unwrap_or(JsonValue::Null)
+                })
+                .collect()
+        })
+        .collect();
+    Ok(SqlTable { columns, rows })
+}
+
+/// A row of a group aggregation.
+struct GroupRow {
+    group_keys: Vec<JsonValue>,
+    doc_count: JsonValue,
+    metrics: Vec<JsonValue>,
+}
+
+impl GroupRow {
+    fn value(&self, column_source: ColumnSource) -> &JsonValue {
+        match column_source {
+            ColumnSource::GroupKey(group_idx) => &self.group_keys[group_idx],
+            ColumnSource::DocCount => &self.doc_count,
+            ColumnSource::Metric(metric_idx) => &self.metrics[metric_idx],
+        }
+    }
+}
+
+fn extract_metrics(aggs: &JsonValue, metric_kinds: &[MetricKind]) -> Vec<JsonValue> {
+    metric_kinds
+        .iter()
+        .enumerate()
+        .map(|(metric_idx, metric_kind)| {
+            let metric = &aggs[format!("metric_{metric_idx}")];
+            let value = match metric_kind {
+                MetricKind::SingleValue => &metric["value"],
+                MetricKind::Percentile => match &metric["values"] {
+                    JsonValue::Array(values) => values
+                        .first()
+                        .map(|value| &value["value"])
+                        .unwrap_or(&JsonValue::Null),
+                    JsonValue::Object(values) => values.values().next().unwrap_or(&JsonValue::Null),
+                    _ => &JsonValue::Null,
+                },
+            };
+            value.clone()
+        })
+        .collect()
+}
+
+fn collect_group_rows(
+    aggs: &JsonValue,
+    groups_plan: &GroupsPlan,
+    group_keys: &mut Vec<JsonValue>,
+    doc_count: &JsonValue,
+    group_rows: &mut Vec<GroupRow>,
+) -> anyhow::Result<()> {
+    let group_idx = group_keys.len();
+
+    if group_idx == groups_plan.num_groups {
+        let group_row = GroupRow {
+            group_keys: group_keys.clone(),
+            doc_count: doc_count.clone(),
+            metrics: extract_metrics(aggs, &groups_plan.metrics),
+        };
+        group_rows.push(group_row);
+        return Ok(());
+    }
+    let group_agg = &aggs[format!("group_{group_idx}")];
+
+    // Documents left out of the top buckets of a terms aggregation are counted in
+    // `sum_other_doc_count`.
+    if groups_plan.require_all_groups
+        && group_agg["sum_other_doc_count"]
+            .as_u64()
+            .is_some_and(|sum_other_doc_count| sum_other_doc_count > 0)
+    {
+        bail!(
+            "GROUP BY returned incomplete groups: a column has more than {} distinct values, \
+             narrow down the query with a WHERE clause",
+            groups_plan.limit
+        );
+    }
+    let Some(buckets) = group_agg["buckets"].as_array() else {
+        return Ok(());
+    };
+    for bucket in buckets {
+        let key = match &bucket["key_as_string"] {
+            JsonValue::Null => bucket["key"].clone(),
+            key_as_string => key_as_string.clone(),
+        };
+        group_keys.push(key);
+        collect_group_rows(
+            bucket,
+            groups_plan,
+            group_keys,
+            &bucket["doc_count"],
+            group_rows,
+        )?;
+        group_keys.pop();
+    }
+    Ok(())
+}
+
+/// Compares two cell values, sorting nulls last.
+fn compare_values(left: &JsonValue, right: &JsonValue) -> Ordering {
+    match (left, right) {
+        (JsonValue::Null, JsonValue::Null) => Ordering::Equal,
+        (JsonValue::Null, _) => Ordering::Greater,
+        (_, JsonValue::Null) => Ordering::Less,
+        (JsonValue::Number(left), JsonValue::Number(right)) => {
+            let left = left.as_f64().unwrap_or(f64::NAN);
+            let right = right.as_f64().unwrap_or(f64::NAN);
+            left.total_cmp(&right)
+        }
+        (JsonValue::String(left), JsonValue::String(right)) => left.cmp(right),
+        (JsonValue::Bool(left), JsonValue::Bool(right)) => left.cmp(right),
+        _ => left.to_string().cmp(&right.to_string()),
+    }
+}
+
+fn build_groups_table(
+    groups_plan: &GroupsPlan,
+    search_response: SearchResponse,
+) -> anyhow::Result<SqlTable> {
+    let aggs = if let Some(aggregation_postcard) = &search_response.aggregation_postcard {
+        let aggregation_results = AggregationResults::from_postcard(aggregation_postcard)?;
+        serde_json::to_value(aggregation_results)?
+    } else {
+        JsonValue::Null
+    };
+    let mut group_rows = Vec::new();
+    collect_group_rows(
+        &aggs,
+        groups_plan,
+        &mut Vec::with_capacity(groups_plan.num_groups),
+        &JsonValue::from(search_response.num_hits),
+        &mut group_rows,
+    )?;
+    if groups_plan.require_all_groups && group_rows.len() > groups_plan.limit {
+        bail!(
+            "GROUP BY returned more than {} groups, narrow down the query with a WHERE clause",
+            groups_plan.limit
+        );
+    }
+    if !groups_plan.order_by.is_empty() {
+        group_rows.sort_by(|left, right| {
+            for (column_source, descending) in &groups_plan.order_by {
+                let mut ordering =
+                    compare_values(left.value(*column_source), right.value(*column_source));
+                if *descending {
+                    ordering = ordering.reverse();
+                }
+                if ordering.is_ne() {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+    }
+    let columns = groups_plan
+        .columns
+        .iter()
+        .map(|(column, _)| column.clone())
+        .collect();
+    let rows = group_rows
+        .iter()
+        .skip(groups_plan.offset)
+        .take(groups_plan.limit)
+        .map(|group_row| {
+            groups_plan
+                .columns
+                .iter()
+                .map(|(_, column_source)| group_row.value(*column_source).clone())
+                .collect()
+        })
+        .collect();
+    Ok(SqlTable { columns, rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::search::Hit;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_sql_table_to_csv() {
+        let sql_table = SqlTable {
+            columns: vec!["name".to_string(), "count".to_string()],
+            rows: vec![
+                vec![json!("plain"), json!(3)],
+                vec![json!("with,comma"), json!(null)],
+                vec![json!("with \"quotes\""), json!(1.5)],
+            ],
+        };
+        assert_eq!(
+            sql_table.to_csv(),
+            "name,count\nplain,3\n\"with,comma\",\n\"with \"\"quotes\"\"\",1.5\n"
+        );
+    }
+
+    #[test]
+    fn test_build_documents_table() {
+        let search_response = SearchResponse {
+            num_hits: 2,
+            hits: vec![
+                Hit {
+                    json: r#"{"title": "foo", "user": {"name": "alice"}}"#.to_string(),
+                    ..Default::default()
+                },
+                Hit {
+                    json: r#"{"title": "bar", "size": 12}"#.to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let projections = vec![
+            Projection::Field {
+                path: "user.name".to_string(),
+                column: "user_name".to_string(),
+            },
+            Projection::Wildcard,
+        ];
+        let sql_table =
+            build_table(&OutputPlan::Documents { projections }, search_response).unwrap();
+        assert_eq!(
+            sql_table.columns,
+            vec!["user_name", "title", "user", "size"]
+        );
+        assert_eq!(
+            sql_table.rows,
+            vec![
+                vec![
+                    json!("alice"),
+                    json!("foo"),
+                    json!({"name": "alice"}),
+                    json!(null)
+                ],
+                vec![json!(null), json!("bar"), json!(null), json!(12)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_group_rows() {
+        let aggs = json!({
+            "group_0": {
+                "buckets": [
+                    {
+                        "key": 1.0,
+                        "key_as_string": "2024-01-01T00:00:00Z",
+                        "doc_count": 3,
+                        "group_1": {
+                            "buckets": [
+                                {
+                                    "key": "api",
+                                    "doc_count": 2,
+                                    "metric_0": {"value": 10.0},
+                                    "metric_1": {"values": [{"key": 99.0, "value": 42.0}]},
+                                },
+                                {
+                                    "key": "web",
+                                    "doc_count": 1,
+                                    "metric_0": {"value": 5.0},
+                                    "metric_1": {"values": [{"key": 99.0, "value": 7.0}]},
+                                },
+                            ],
+                        },
+                    },
+                ],
+            },
+        });
+        let groups_plan = GroupsPlan {
+            num_groups: 2,
+            metrics: vec![MetricKind::SingleValue, MetricKind::Percentile],
+            columns: Vec::new(),
+            order_by: Vec::new(),
+            offset: 0,
+            limit: 10,
+            require_all_groups: true,
+        };
+        let mut group_rows = Vec::new();
+        collect_group_rows(
+            &aggs,
+            &groups_plan,
+            &mut Vec::new(),
+            &json!(3),
+            &mut group_rows,
+        )
+        .unwrap();
+        assert_eq!(group_rows.len(), 2);
+        assert_eq!(
+            group_rows[0].group_keys,
+            vec![json!("2024-01-01T00:00:00Z"), json!("api")]
+        );
+        assert_eq!(group_rows[0].doc_count, json!(2));
+        assert_eq!(group_rows[0].metrics, vec![json!(10.0), json!(42.0)]);
+        assert_eq!(
+            group_rows[1].group_keys,
+            vec![json!("2024-01-01T00:00:00Z"), json!("web")]
+        );
+        assert_eq!(group_rows[1].metrics, vec![json!(5.0), json!(7.0)]);
+    }
+
+    #[test]
+    fn test_collect_group_rows_incomplete_groups() {
+        let aggs = json!({
+            "group_0": {
+                "sum_other_doc_count": 0,
+                "buckets": [
+                    {
+                        "key": "api",
+                        "doc_count": 3,
+                        "group_1": {
+                            "sum_other_doc_count": 1,
+                            "buckets": [{"key": "host-1", "doc_count": 2}],
+                        },
+                    },
+                ],
+            },
+        });
+        let mut groups_plan = GroupsPlan {
+            num_groups: 2,
+            metrics: Vec::new(),
+            columns: Vec::new(),
+            order_by: Vec::new(),
+            offset: 0,
+            limit: 10_000,
+            require_all_groups: true,
+        };
+        let error = collect_group_rows(
+            &aggs,
+            &groups_plan,
+            &mut Vec::new(),
+            &json!(3),
+            &mut Vec::new(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "GROUP BY returned incomplete groups: a column has more than 10000 distinct values, \
+             narrow down the query with a WHERE clause"
+        );
+        groups_plan.require_all_groups = false;
+        let mut group_rows = Vec::new();
+        collect_group_rows(
+            &aggs,
+            &groups_plan,
+            &mut Vec::new(),
+            &json!(3),
+            &mut group_rows,
+        )
+        .unwrap();
+        assert_eq!(group_rows.len(), 1);
+    }
+
+    #[test]
+    fn test_build_groups_table_without_group_by() {
+        let groups_plan = GroupsPlan {
+            num_groups: 0,
+            metrics: Vec::new(),
+            columns: vec![("COUNT(*)".to_string(), ColumnSource::DocCount)],
+            order_by: Vec::new(),
+            offset: 0,
+            limit: 10,
+            require_all_groups: false,
+        };
+        let search_response = SearchResponse {
+            num_hits: 42,
+            ..Default::default()
+        };
+        let sql_table = build_table(&OutputPlan::Groups(groups_plan), search_response).unwrap();
+        assert_eq!(sql_table.columns, vec!["COUNT(*)"]);
+        assert_eq!(sql_table.rows, vec![vec![json!(42)]]);
+    }
+
+    #[test]
+    fn test_compare_values() {
+        assert_eq!(compare_values(&json!(1), &json!(2.5)), Ordering::Less);
+        assert_eq!(compare_values(&json!("b"), &json!("a")), Ordering::Greater);
+        assert_eq!(compare_values(&json!(null), &json!(1)), Ordering::Greater);
+        assert_eq!(compare_values(&json!(1), &json!(null)), Ordering::Less);
+    }
+}
@@ -0,0 +1,485 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use quickwit_common::rate_limiter::{RateLimiter, RateLimiterSettings};
+use quickwit_common::tower::ConstantRate;
+use quickwit_config::{
+    INGEST_V2_SOURCE_ID, IngestApiConfig, SourceParams, WebhookSourceParams, validate_identifier,
+};
+use quickwit_ingest::{DocBatchV2Builder, IngestServiceError};
+use quickwit_metastore::IndexMetadataResponseExt;
+use quickwit_proto::ingest::CommitTypeV2;
+use quickwit_proto::ingest::router::{
+    IngestRequestV2, IngestRouterService, IngestRouterServiceClient, IngestSubrequest,
+};
+use quickwit_proto::metastore::{
+    IndexMetadataRequest, MetastoreError, MetastoreService, MetastoreServiceClient,
+};
+use quickwit_proto::types::{DocUidGenerator, IndexId, SourceId};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde_json::Value as JsonValue;
+use warp::http::HeaderMap;
+use warp::{Filter, Rejection};
+
+use super::signature::verify_signature;
+use crate::decompression::get_body_bytes;
+use crate::ingest_api::RestIngestResponse;
+use crate::rest_api_response::into_rest_api_response;
+use crate::{Body, BodyFormat, with_arg};
+
+/// Duration during which the configuration of a webhook source is reused before being fetched
+/// again from the metastore.
+const WEBHOOK_SOURCE_CACHE_TTL: Duration = Duration::from_secs(10);
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(webhook))]
+pub struct WebhookApi;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum WebhookApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error(transparent)]
+    Ingest(#[from] IngestServiceError),
+    #[error(transparent)]
+    Metastore(#[from] MetastoreError),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("source `{source_id}` has exceeded its rate limit")]
+    RateLimited { source_id: SourceId },
+    #[error("unauthenticated request: {0}")]
+    Unauthenticated(String),
+    #[error("{0}")]
+    Unavailable(String),
+}
+
+impl ServiceError for WebhookApiError {
+    fn error_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::BadRequest(_) => ServiceErrorCode::BadRequest,
+            Self::Ingest(ingest_error) => ingest_error.error_code(),
+            Self::Metastore(metastore_error) => metastore_error.error_code(),
+            Self::NotFound(_) => ServiceErrorCode::NotFound,
+            Self::RateLimited { .. } => ServiceErrorCode::TooManyRequests,
+            Self::Unauthenticated(_) => ServiceErrorCode::Unauthenticated,
+            Self::Unavailable(_) => ServiceErrorCode::Unavailable,
+        }
+    }
+}
+
+struct WebhookSourceEntry {
+    params: WebhookSourceParams,
+    rate_limiter_opt: Option<RateLimiter>,
+    fetched_at: Instant,
+}
+
+/// Caches the parameters and the rate limiters of the webhook sources so that the metastore is
+/// not queried on every request. Rate limits are enforced per node.
+#[derive(Clone)]
+struct WebhookSources {
+    metastore: MetastoreServiceClient,
+    entries: Arc<Mutex<HashMap<(IndexId, SourceId), WebhookSourceEntry>>>,
+}
+
+impl WebhookSources {
+    fn new(metastore: MetastoreServiceClient) -> Self {
+        Self {
+            metastore,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Returns the parameters of the webhook source.
+    async fn get_params(
+        &self,
+        index_id: &IndexId,
+        source_id: &SourceId,
+    ) -> Result<WebhookSourceParams, WebhookApiError> {
+        let key = (index_id.clone(), source_id.clone());
+        let is_fresh = self
+            .entries
+            .lock()
+            .unwrap()
+            .get(&key)
+            .is_some_and(|entry| entry.fetched_at.elapsed() < WEBHOOK_SOURCE_CACHE_TTL);
+
+        if !is_fresh {
+            let params = match self.fetch_params(index_id, source_id).await {
+                Ok(params) => params,
+                Err(error) => {
+                    self.entries.lock().unwrap().remove(&key);
+                    return Err(error);
+                }
+            };
+            let mut entries = self.entries.lock().unwrap();
+
+            match entries.get_mut(&key) {
+                Some(entry) if entry.params.max_requests_per_sec == params.max_requests_per_sec => {
+                    entry.params = params;
+                    entry.fetched_at = Instant::now();
+                }
+                _ => {
+                    let rate_limiter_opt = params.max_requests_per_sec.map(|max_requests| {
+                        let max_requests = max_requests.get() as u64;
+                        RateLimiter::from_settings(RateLimiterSettings {
+                            burst_limit: max_requests,
+                            rate_limit: ConstantRate::new(max_requests, Duration::from_secs(1)),
+                            refill_period: Duration::from_secs(1),
+                        })
+                    });
+                    let entry = WebhookSourceEntry {
+                        params,
+                        rate_limiter_opt,
+                        fetched_at: Instant::now(),
+                    };
+                    entries.insert(key.clone(), entry);
+                }
+            }
+        }
+        let entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.get(&key) else {
+            return Err(webhook_source_not_found_error(source_id));
+        };
+        Ok(entry.params.clone())
+    }
+
+    /// Acquires a permit from the rate limiter of the webhook source. Must be called after the
+    /// request is authenticated, so that forged requests do not consume the budget of the source.
+    fn acquire_permit(
+        &self,
+        index_id: &IndexId,
+        source_id: &SourceId,
+    ) -> Result<(), WebhookApiError> {
+        let key = (index_id.clone(), source_id.clone());
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.get_mut(&key) else {
+            return Err(webhook_source_not_found_error(source_id));
+        };
+        if let Some(rate_limiter) = &mut entry.rate_limiter_opt
+            && !rate_limiter.acquire(1)
+        {
+            return Err(WebhookApiError::RateLimited {
+                source_id: source_id.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn fetch_params(
+        &self,
+        index_id: &IndexId,
+        source_id: &SourceId,
+    ) -> Result<WebhookSourceParams, WebhookApiError> {
+        let index_metadata_request = IndexMetadataRequest::for_index_id(index_id.clone());
+        let mut index_metadata = self
+            .metastore
+            .index_metadata(index_metadata_request)
+            .await?
+            .deserialize_index_metadata()?;
+
+        let Some(source_config) = index_metadata.sources.remove(source_id) else {
+            return Err(WebhookApiError::NotFound(format!(
+                "source `{source_id}` not found in index `{index_id}`"
+            )));
+        };
+        let source_type = source_config.source_type();
+
+        let SourceParams::Webhook(params) = source_config.source_params else {
+            return Err(WebhookApiError::BadRequest(format!(
+                "source `{source_id}` is a {source_type} source, not a webhook source"
+            )));
+        };
+        if !source_config.enabled {
+            return Err(WebhookApiError::Unavailable(format!(
+                "webhook source `{source_id}` is disabled"
+            )));
+        }
+        Ok(params)
+    }
+}
+
+fn webhook_source_not_found_error(source_id: &SourceId) -> WebhookApiError {
+    WebhookApiError::NotFound(format!("webhook source `{source_id}` not found"))
+}
+
+pub(crate) fn webhook_api_handlers(
+    metastore: MetastoreServiceClient,
+    ingest_router: IngestRouterServiceClient,
+    config: IngestApiConfig,
+    enable_ingest_v2: bool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    let webhook_sources = WebhookSources::new(metastore);
+
+    warp::path!(String / "sources" / String / "webhook")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(
+            config.content_length_limit.as_u64(),
+        ))
+        .and(warp::header::headers_cloned())
+        .and(get_body_bytes())
+        .and(with_arg(webhook_sources))
+        .and(with_arg(ingest_router))
+        .then(
+            move |index_id, source_id, headers, body, webhook_sources, ingest_router| {
+                webhook(
+                    index_id,
+                    source_id,
+                    headers,
+                    body,
+                    webhook_sources,
+                    ingest_router,
+                    enable_ingest_v2,
+                )
+            },
+        )
+        .map(|result| into_rest_api_response(result, BodyFormat::default()))
+        .boxed()
+}
+
+#[utoipa::path(
+    post,
+    tag = "Ingest",
+    path = "/{index_id}/sources/{source_id}/webhook",
+    request_body(content = String, description = "JSON or NDJSON documents pushed by a webhook", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Successfully ingested documents.", body = RestIngestResponse)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to add docs to."),
+        ("source_id" = String, Path, description = "The ID of the webhook source."),
+    )
+)]
+/// Ingests the documents pushed by a webhook.
+async fn webhook(
+    index_id: IndexId,
+    source_id: SourceId,
+    headers: HeaderMap,
+    body: Body,
+    webhook_sources: WebhookSources,
+    ingest_router: IngestRouterServiceClient,
+    enable_ingest_v2: bool,
+) -> Result<RestIngestResponse, WebhookApiError> {
+    if !enable_ingest_v2 {
+        let message = "webhook sources require ingest v2: environment variable \
+                       `QW_ENABLE_INGEST_V2` is set to false";
+        return Err(WebhookApiError::Unavailable(message.to_string()));
+    }
+    // Validate index ID early because propagating back the right error (400)
+    // from deeper ingest layers is harder
+    if validate_identifier("", &index_id).is_err() {
+        return Err(WebhookApiError::BadRequest("invalid index ID".to_string()));
+    }
+    let params = webhook_sources.get_params(&index_id, &source_id).await?;
+
+    if let Some(hmac_params) = &params.hmac {
+        verify_signature(hmac_params, &headers, &body.content)?;
+    }
+    webhook_sources.acquire_permit(&index_id, &source_id)?;
+
+    let docs = split_docs(&body.content)?;
+    drop(body);
+
+    let mut doc_batch_builder = DocBatchV2Builder::default();
+    let mut doc_uid_generator = DocUidGenerator::default();
+
+    for doc in docs {
+        doc_batch_builder.add_doc(doc_uid_generator.next_doc_uid(), &doc);
+    }
+    let Some(doc_batch) = doc_batch_builder.build() else {
+        return Ok(RestIngestResponse::default());
+    };
+    let num_docs_for_processing = doc_batch.num_docs() as u64;
+
+    let subrequest = IngestSubrequest {
+        subrequest_id: 0,
+        index_id,
+        source_id: INGEST_V2_SOURCE_ID.to_string(),
+        doc_batch: Some(doc_batch),
+    };
+    let request = IngestRequestV2 {
+        commit_type: CommitTypeV2::Auto as i32,
+        subrequests: vec![subrequest],
+    };
+    let response = ingest_router
+        .ingest(request)
+        .await
+        .map_err(IngestServiceError::from)?;
+    let rest_ingest_response =
+        RestIngestResponse::from_ingest_v2(response, None, num_docs_for_processing)?;
+    Ok(rest_ingest_response)
+}
+
+/// Splits the body of a webhook request into documents. The body is a sequence of JSON values
+/// separated by whitespace, which covers single JSON documents (pretty-printed or not) and NDJSON.
+/// The elements of top-level arrays are ingested as separate documents.
+fn split_docs(body: &Bytes) -> Result<Vec<Vec<u8>>, WebhookApiError> {
+    let mut docs = Vec::new();
+
+    for value_res in serde_json::Deserializer::from_slice(body).into_iter::<JsonValue>() {
+        let value = value_res
+            .map_err(|error| WebhookApiError::BadRequest(format!("invalid JSON body: {error}")))?;
+        match value {
+            JsonValue::Array(values) => {
+                for value in values {
+                    docs.push(serialize_doc(value)?);
+                }
+            }
+            value => docs.push(serialize_doc(value)?),
+        }
+    }
+    Ok(docs)
+}
+
+fn serialize_doc(value: JsonValue) -> Result<Vec<u8>, WebhookApiError> {
+    if !value.is_object() {
+        return Err(WebhookApiError::BadRequest(
+            "webhook documents must be JSON objects".to_string(),
+        ));
+    }
+    Ok(serde_json::to_vec(&value).expect("JSON value should be serializable"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use quickwit_config::{SourceConfig, WebhookHmacParams};
+    use quickwit_metastore::IndexMetadata;
+    use quickwit_proto::ingest::router::MockIngestRouterService;
+    use quickwit_proto::metastore::{IndexMetadataResponse, MockMetastoreService};
+    use warp::http::HeaderValue;
+
+    use super::*;
+
+    fn split_docs_str(body: &str) -> Result<Vec<String>, WebhookApiError> {
+        let docs = split_docs(&Bytes::from(body.to_string()))?
+            .into_iter()
+            .map(|doc| String::from_utf8(doc).unwrap())
+            .collect();
+        Ok(docs)
+    }
+
+    #[test]
+    fn test_split_docs() {
+        assert!(split_docs_str("").unwrap().is_empty());
+
+        let docs = split_docs_str("{\n  \"action\": \"opened\",\n  \"number\": 1\n}\n").unwrap();
+        assert_eq!(docs, [r#"{"action":"opened","number":1}"#]);
+
+        let docs = split_docs_str("{\"id\": 1}\n\n{\"id\": 2}\n").unwrap();
+        assert_eq!(docs, [r#"{"id":1}"#, r#"{"id":2}"#]);
+
+        let docs = split_docs_str("[{\"id\": 1}, {\"id\": 2}]").unwrap();
+        assert_eq!(docs, [r#"{"id":1}"#, r#"{"id":2}"#]);
+
+        let error = split_docs_str("{\"id\": 1}\n{\"id\":").unwrap_err();
+        assert!(matches!(error, WebhookApiError::BadRequest(_)));
+
+        let error = split_docs_str("[1, 2]").unwrap_err();
+        assert_eq!(error.to_string(), "webhook documents must be JSON objects");
+    }
+
+    #[tokio::test]
+    async fn test_webhook_bad_signature_does_not_consume_rate_limit() {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore.expect_index_metadata().return_once(|_| {
+            let mut index_metadata =
+                IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+            let webhook_params = WebhookSourceParams {
+                hmac: Some(WebhookHmacParams {
+                    header: "X-Signature".to_string(),
+                    secret: "It's a Secret to Everybody".to_string(),
+                    algorithm: Default::default(),
+                    prefix: "sha256=".to_string(),
+                    encoding: Default::default(),
+                }),
+                max_requests_per_sec: Some(NonZeroU32::new(1).unwrap()),
+            };
+            let source_config =
+                SourceConfig::for_test("test-source", SourceParams::Webhook(webhook_params));
+            index_metadata.add_source(source_config).unwrap();
+            Ok(IndexMetadataResponse::try_from_index_metadata(&index_metadata).unwrap())
+        });
+        let webhook_sources =
+            WebhookSources::new(MetastoreServiceClient::from_mock(mock_metastore));
+
+        // The requests are rejected before reaching the ingest router.
+        let ingest_router = IngestRouterServiceClient::from_mock(MockIngestRouterService::new());
+        let body = || Body::for_test(Bytes::from_static(b"Hello, World!"));
+        let mut forged_headers = HeaderMap::new();
+        forged_headers.insert("x-signature", HeaderValue::from_static("sha256=deadbeef"));
+
+        for _ in 0..3 {
+            let error = webhook(
+                "test-index".to_string(),
+                "test-source".to_string(),
+                forged_headers.clone(),
+                body().await,
+                webhook_sources.clone(),
+                ingest_router.clone(),
+                true,
+            )
+            .await
+            .unwrap_err();
+            assert!(matches!(error, WebhookApiError::Unauthenticated(_)));
+        }
+        let key = ("test-index".to_string(), "test-source".to_string());
+        let available_permits = webhook_sources.entries.lock().unwrap()[&key]
+            .rate_limiter_opt
+            .as_ref()
+            .unwrap()
+            .available_permits();
+        assert_eq!(available_permits, 1);
+
+        // Example from the GitHub documentation.
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-signature",
+            HeaderValue::from_static(
+                "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            ),
+        );
+        let error = webhook(
+            "test-index".to_string(),
+            "test-source".to_string(),
+            headers.clone(),
+            body().await,
+            webhook_sources.clone(),
+            ingest_router.clone(),
+            true,
+        )
+        .await
+        .unwrap_err();
+        // The body is not JSON, but the request was authenticated and consumed a permit.
+        assert!(matches!(error, WebhookApiError::BadRequest(_)));
+
+        let error = webhook(
+            "test-index".to_string(),
+            "test-source".to_string(),
+            headers,
+            body().await,
+            webhook_sources,
+            ingest_router,
+            true,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, WebhookApiError::RateLimited { .. }));
+    }
+}
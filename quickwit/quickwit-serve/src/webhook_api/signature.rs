@@ -0,0 +1,148 @@
+// Copyright 2021-Present Datadog, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use base64::prelude::{BASE64_STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use quickwit_config::{WebhookHmacAlgorithm, WebhookHmacParams, WebhookSignatureEncoding};
+use sha1::Sha1;
+use sha2::Sha256;
+use warp::http::HeaderMap;
+
+use super::rest_handler::WebhookApiError;
+
+/// Verifies the HMAC signature of the body of a webhook request.
+///
+/// The signature is compared in constant time.
+pub(super) fn verify_signature(
+    hmac_params: &WebhookHmacParams,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), WebhookApiError> {
+    let header_value = headers
+        .get(hmac_params.header.as_str())
+        .ok_or_else(|| {
+            WebhookApiError::Unauthenticated(format!(
+                "missing signature header `{}`",
+                hmac_params.header
+            ))
+        })?
+        .to_str()
+        .map_err(|_| invalid_signature_error())?;
+    let encoded_signature = header_value
+        .trim()
+        .strip_prefix(hmac_params.prefix.as_str())
+        .ok_or_else(invalid_signature_error)?;
+    let signature_opt = match hmac_params.encoding {
+        WebhookSignatureEncoding::Base64 => BASE64_STANDARD.decode(encoded_signature).ok(),
+        WebhookSignatureEncoding::Hex => hex::decode(encoded_signature).ok(),
+    };
+    let signature = signature_opt.ok_or_else(invalid_signature_error)?;
+
+    let secret = hmac_params.secret.as_bytes();
+    let verify_result = match hmac_params.algorithm {
+        WebhookHmacAlgorithm::Sha1 => Hmac::<Sha1>::new_from_slice(secret)
+            .expect("HMAC should accept keys of any size")
+            .chain_update(body)
+            .verify_slice(&signature),
+        WebhookHmacAlgorithm::Sha256 => Hmac::<Sha256>::new_from_slice(secret)
+            .expect("HMAC should accept keys of any size")
+            .chain_update(body)
+            .verify_slice(&signature),
+    };
+    verify_result.map_err(|_| invalid_signature_error())
+}
+
+fn invalid_signature_error() -> WebhookApiError {
+    WebhookApiError::Unauthenticated("invalid signature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use warp::http::HeaderValue;
+
+    use super::*;
+
+    fn hmac_params(
+        algorithm: WebhookHmacAlgorithm,
+        prefix: &str,
+        encoding: WebhookSignatureEncoding,
+    ) -> WebhookHmacParams {
+        WebhookHmacParams {
+            header: "X-Signature".to_string(),
+            secret: "It's a Secret to Everybody".to_string(),
+            algorithm,
+            prefix: prefix.to_string(),
+            encoding,
+        }
+    }
+
+    fn headers(signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-signature", HeaderValue::from_str(signature).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        // Example from the GitHub documentation.
+        let body = b"Hello, World!";
+        let hmac_params = hmac_params(
+            WebhookHmacAlgorithm::Sha256,
+            "sha256=",
+            WebhookSignatureEncoding::Hex,
+        );
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        verify_signature(&hmac_params, &headers(signature), body).unwrap();
+
+        let error =
+            verify_signature(&hmac_params, &headers(signature), b"Hello, World?").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unauthenticated request: invalid signature"
+        );
+
+        let error = verify_signature(&hmac_params, &headers(&signature[7..]), body).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unauthenticated request: invalid signature"
+        );
+
+        let error = verify_signature(&hmac_params, &HeaderMap::new(), body).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unauthenticated request: missing signature header `X-Signature`"
+        );
+    }
+
+    #[test]
+    fn test_verify_signature_sha1_base64() {
+        let body = b"Hello, World!";
+        let secret = "It's a Secret to Everybody";
+        let signature = Hmac::<Sha1>::new_from_slice(secret.as_bytes())
+            .unwrap()
+            .chain_update(body)
+            .finalize()
+            .into_bytes();
+        let hmac_params = hmac_params(
+            WebhookHmacAlgorithm::Sha1,
+            "",
+            WebhookSignatureEncoding::Base64,
+        );
+        let encoded_signature = BASE64_STANDARD.encode(signature);
+        verify_signature(&hmac_params, &headers(&encoded_signature), body).unwrap();
+
+        let hex_signature = hex::encode(signature);
+        verify_signature(&hmac_params, &headers(&hex_signature), body).unwrap_err();
+    }
+}